@@ -0,0 +1,61 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+
+use chip_8::{CpuBuilder, ExecutionEngine};
+
+/// Instructions run per benchmark iteration
+const CYCLES: usize = 10_000;
+
+/// Loads a program at the start of program memory
+fn memory_with(program: &[u8]) -> [u8; 0x1000] {
+    let mut memory = [0; 0x1000];
+    memory[..program.len()].copy_from_slice(program);
+    memory
+}
+
+/// Runs the bundled sierpinski ROM, which is mostly drawing
+fn sierpinski(c: &mut Criterion) {
+    let memory = memory_with(include_bytes!("../roms/sierpinski.ch8"));
+    let mut group = c.benchmark_group("sierpinski");
+    group.throughput(Throughput::Elements(CYCLES as u64));
+
+    for engine in [ExecutionEngine::Interpreter, ExecutionEngine::Cached] {
+        group.bench_function(format!("{:?}", engine), |b| {
+            b.iter(|| {
+                let mut cpu = CpuBuilder::new().memory(memory).engine(engine).build();
+                black_box(cpu.run_cycles(CYCLES))
+            })
+        });
+    }
+
+    group.finish();
+}
+
+/// Runs a tight loop of register arithmetic, skips and jumps
+fn alu_loop(c: &mut Criterion) {
+    let memory = memory_with(&[
+        0x70, 0x01, // 0x200: ADD V0, 1
+        0x81, 0x04, // 0x202: ADD V1, V0
+        0x82, 0x13, // 0x204: XOR V2, V1
+        0x83, 0x26, // 0x206: SHR V3
+        0x30, 0xFF, // 0x208: SE V0, 0xFF
+        0x12, 0x00, // 0x20A: JP 0x200
+        0x60, 0x00, // 0x20C: LD V0, 0
+        0x12, 0x00, // 0x20E: JP 0x200
+    ]);
+    let mut group = c.benchmark_group("alu_loop");
+    group.throughput(Throughput::Elements(CYCLES as u64));
+
+    for engine in [ExecutionEngine::Interpreter, ExecutionEngine::Cached] {
+        group.bench_function(format!("{:?}", engine), |b| {
+            b.iter(|| {
+                let mut cpu = CpuBuilder::new().memory(memory).engine(engine).build();
+                black_box(cpu.run_cycles(CYCLES))
+            })
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, sierpinski, alu_loop);
+criterion_main!(benches);
@@ -0,0 +1,37 @@
+//! Lists a ROM's instructions
+//!
+//! `cargo run --example disassemble -- ROM` decodes every two bytes of ROM
+//! as an instruction, from where it's loaded at 0x200. Sprites and other
+//! data come out as instructions too, as nothing here follows the jumps to
+//! tell them apart.
+
+use std::env;
+use std::error::Error;
+use std::process;
+
+use chip_8::instruction::Instruction;
+use chip_8::rom_file;
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let rom = match env::args().nth(1) {
+        Some(rom) => rom_file::load(&rom)?,
+        None => {
+            eprintln!("usage: disassemble ROM");
+            process::exit(1);
+        }
+    };
+
+    for (index, bytes) in rom.chunks(2).enumerate() {
+        let addr = 0x200 + index * 2;
+        match *bytes {
+            [high, low] => {
+                let opcode = u16::from_be_bytes([high, low]);
+                println!("0x{:03X}  {:04X}  {}", addr, opcode, Instruction::decode(opcode));
+            }
+            // an odd byte left over at the end
+            [byte] => println!("0x{:03X}  {:02X}", addr, byte),
+            _ => unreachable!(),
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,39 @@
+//! Adds two numbers on a CPU with no window at all
+//!
+//! `cargo run --example headless_adder -- 200 100` builds a four
+//! instruction program around the numbers, runs it until it halts, and
+//! reads the sum and carry back out of the registers.
+
+use std::env;
+use std::process;
+
+use chip_8::CpuBuilder;
+
+fn main() {
+    let numbers: Vec<u8> = match env::args().skip(1).map(|arg| arg.parse()).collect() {
+        Ok(numbers) => numbers,
+        Err(_) => {
+            eprintln!("usage: headless_adder A B, each from 0 to 255");
+            process::exit(1);
+        }
+    };
+    let (a, b) = match numbers[..] {
+        [] => (2, 3),
+        [a, b] => (a, b),
+        _ => {
+            eprintln!("usage: headless_adder A B, each from 0 to 255");
+            process::exit(1);
+        }
+    };
+
+    let program = [
+        0x60, a, // LD V0, a
+        0x61, b, // LD V1, b
+        0x80, 0x14, // ADD V0, V1, with the carry in VF
+        0x00, 0x00, // halt
+    ];
+    let mut cpu = CpuBuilder::new().memory(program).build();
+    while cpu.run().is_some() {}
+
+    println!("{} + {} = {}, carry {}", a, b, cpu.registers(0x0), cpu.registers(0xF));
+}
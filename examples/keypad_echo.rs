@@ -0,0 +1,35 @@
+//! Types hex digits on the keypad and shows what the program makes of them
+//!
+//! `cargo run --example keypad_echo`, then type hex digits and press enter.
+//! Each digit is pressed and let go on the CHIP-8 keypad, and a program
+//! waiting on FX0A draws it on the screen, which is printed back.
+
+use std::io::{self, BufRead};
+
+use chip_8::Emulator;
+
+// LD V0, K; CLS; LD F, V0; DRW V1, V1, 5; JP 0x200
+const ECHO: [u8; 10] = [0xF0, 0x0A, 0x00, 0xE0, 0xF0, 0x29, 0xD1, 0x15, 0x12, 0x00];
+
+fn main() -> io::Result<()> {
+    let mut emulator = Emulator::default();
+    emulator.load_rom(&ECHO).expect("the program fits in memory");
+    emulator.run_frame();
+
+    for line in io::stdin().lock().lines() {
+        for key in line?.chars().filter_map(|digit| digit.to_digit(16)) {
+            // FX0A takes the key once it's let go, as on the COSMAC VIP
+            emulator.key_down(key as u8);
+            emulator.run_frame();
+            emulator.key_up(key as u8);
+            emulator.run_frame();
+
+            println!("{:X} read as {:X}", key, emulator.cpu().registers(0));
+            for row in emulator.frame().rows().take(5) {
+                let glyph: String = row[..4].iter().map(|&lit| if lit { '#' } else { ' ' }).collect();
+                println!("  {}", glyph);
+            }
+        }
+    }
+    Ok(())
+}
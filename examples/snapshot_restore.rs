@@ -0,0 +1,42 @@
+//! Saves a running machine to bytes and picks it up again from them
+//!
+//! `cargo run --example snapshot_restore -- [ROM]` runs ROM, the bundled
+//! sierpinski by default, for a second, saves its state, and runs another
+//! second. Then it goes back to the saved state, reads from the bytes as a
+//! save file would be, and checks the second run draws the same again.
+
+use std::env;
+use std::error::Error;
+
+use chip_8::{rom_file, Emulator, Snapshot};
+
+/// Frames in a second
+const SECOND: usize = 60;
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let rom = env::args().nth(1).unwrap_or_else(|| String::from("./roms/sierpinski.ch8"));
+    let mut emulator = Emulator::default();
+    emulator.load_rom(&rom_file::load(&rom)?)?;
+
+    for _ in 0..SECOND {
+        emulator.run_frame();
+    }
+    let saved = emulator.cpu().snapshot().to_bytes();
+    println!("saved {} bytes after a second", saved.len());
+
+    for _ in 0..SECOND {
+        emulator.run_frame();
+    }
+    let first = emulator.frame().clone();
+
+    emulator.cpu_mut().restore(&Snapshot::from_bytes(&saved)?);
+    for _ in 0..SECOND {
+        emulator.run_frame();
+    }
+
+    if *emulator.frame() != first {
+        return Err("the second run after restoring drew something different".into());
+    }
+    println!("restored and ran another second, drawing the same screen");
+    Ok(())
+}
@@ -0,0 +1,40 @@
+//! Runs a ROM for a while and prints its screen to the terminal
+//!
+//! `cargo run --example terminal_renderer -- [ROM] [FRAMES]` runs ROM,
+//! the bundled sierpinski by default, for FRAMES 60ths of a second, 120
+//! unless given, then draws the screen two pixel rows to a line of text.
+
+use std::env;
+use std::error::Error;
+
+use chip_8::{rom_file, Emulator, StopReason};
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let mut args = env::args().skip(1);
+    let rom = args.next().unwrap_or_else(|| String::from("./roms/sierpinski.ch8"));
+    let frames: usize = args.next().map(|frames| frames.parse()).transpose()?.unwrap_or(120);
+
+    let mut emulator = Emulator::default();
+    emulator.load_rom(&rom_file::load(&rom)?)?;
+    for _ in 0..frames {
+        if let StopReason::Error(err) = emulator.run_frame() {
+            return Err(err.into());
+        }
+    }
+
+    let screen = emulator.frame();
+    println!("+{}+", "-".repeat(screen.width()));
+    for y in (0..screen.height()).step_by(2) {
+        let line: String = (0..screen.width())
+            .map(|x| match (screen.pixel(x, y), screen.pixel(x, y + 1)) {
+                (true, true) => '█',
+                (true, false) => '▀',
+                (false, true) => '▄',
+                (false, false) => ' ',
+            })
+            .collect();
+        println!("|{}|", line);
+    }
+    println!("+{}+", "-".repeat(screen.width()));
+    Ok(())
+}
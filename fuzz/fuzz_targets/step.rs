@@ -0,0 +1,34 @@
+//! Runs arbitrary bytes as a program, which may fail but must never panic
+//!
+//! Run with `cargo +nightly fuzz run step` from the repository root.
+
+#![no_main]
+
+use chip_8::{CpuBuilder, CpuStatus, ExecutionEngine};
+use libfuzzer_sys::fuzz_target;
+
+/// Enough to get through most loops without making each run slow
+const MAX_STEPS: usize = 10_000;
+
+fuzz_target!(|data: &[u8]| {
+    // the first byte picks the engine, the rest is the program
+    let (engine, program) = match data.split_first() {
+        Some((&flags, program)) if flags & 1 == 1 => (ExecutionEngine::Cached, program),
+        Some((_, program)) => (ExecutionEngine::Interpreter, program),
+        None => return,
+    };
+
+    let mut memory = [0; 0x1000];
+    let len = program.len().min(0xE00);
+    memory[..len].copy_from_slice(&program[..len]);
+    let mut cpu = CpuBuilder::new().memory(memory).engine(engine).build();
+
+    for step in 0..MAX_STEPS {
+        match cpu.step() {
+            Ok(CpuStatus::Running | CpuStatus::WaitingForVBlank) => {}
+            Ok(CpuStatus::WaitingForKey(_)) => cpu.set_key((step % 16) as u8, step % 2 == 0),
+            Ok(CpuStatus::Halted) | Err(_) => break,
+        }
+        cpu.tick_timers();
+    }
+});
@@ -0,0 +1,791 @@
+//! Static analysis of ROMs, without running them
+//!
+//! [`scan`] follows the program's control flow from the start address to
+//! find its code, then reports which instructions it uses, any that need a
+//! SUPER-CHIP, MegaChip or XO-CHIP interpreter, and a guess at the quirks
+//! it expects. [`cfg`] splits the same code into basic blocks and draws how
+//! control flows between them, as Graphviz DOT or JSON.
+//!
+//! # Example
+//!
+//! ```
+//! use chip_8::analyze::{self, Variant};
+//!
+//! // CLS; scroll right (00FB); JP 0x202
+//! let report = analyze::scan(&[0x00, 0xE0, 0x00, 0xFB, 0x12, 0x02]);
+//!
+//! assert_eq!(report.variant, Variant::SuperChip);
+//! assert_eq!(report.unsupported().next().unwrap().opcode, 0x00FB);
+//! ```
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use serde_json::json;
+
+use crate::instruction::Instruction;
+use crate::{OpCode, Quirks, PROGRAM_START};
+
+/// The interpreter family a ROM was written for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Variant {
+    /// The original instruction set
+    Chip8,
+    /// HP's CHIP-48 for the HP48 calculators, with the original instructions
+    /// but its own take on shifts, BNNN and FX55/FX65
+    Chip48,
+    /// SUPER-CHIP 1.1, adding high resolution, scrolling and the RPL flags
+    SuperChip,
+    /// MegaChip8, adding a 256x192 screen of 256 colour sprites, digitised
+    /// sound and 24 bit addressing
+    MegaChip,
+    /// XO-CHIP, adding colour planes, audio patterns and 16 bit addressing
+    XoChip,
+}
+
+impl fmt::Display for Variant {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Variant::Chip8 => write!(f, "CHIP-8"),
+            Variant::Chip48 => write!(f, "CHIP-48"),
+            Variant::SuperChip => write!(f, "SUPER-CHIP"),
+            Variant::MegaChip => write!(f, "MegaChip"),
+            Variant::XoChip => write!(f, "XO-CHIP"),
+        }
+    }
+}
+
+impl Variant {
+    /// The quirks the interpreter had, for running ROMs written for it
+    ///
+    /// # Examples
+    /// ```
+    /// use chip_8::analyze::Variant;
+    /// use chip_8::CpuBuilder;
+    ///
+    /// let quirks = Variant::Chip48.quirks();
+    /// assert!(quirks.jump_uses_vx && !quirks.shift_uses_vy);
+    /// let cpu = CpuBuilder::new().quirks(quirks).build();
+    /// ```
+    pub fn quirks(self) -> Quirks {
+        match self {
+            Variant::Chip8 => Quirks {
+                vf_reset: true,
+                shift_uses_vy: true,
+                memory_increments_i: true,
+                display_wait: true,
+                ..Quirks::default()
+            },
+            // shifts in place, BXNN, and FX55/FX65 moving I one short
+            Variant::Chip48 => Quirks {
+                memory_increments_i: true,
+                memory_increments_i_by_x: true,
+                jump_uses_vx: true,
+                clip_sprites: true,
+                ..Quirks::default()
+            },
+            Variant::SuperChip | Variant::MegaChip => Quirks {
+                jump_uses_vx: true,
+                clip_sprites: true,
+                collision_rows: true,
+                wait_key_on_press: true,
+                ..Quirks::default()
+            },
+            Variant::XoChip => Quirks {
+                shift_uses_vy: true,
+                memory_increments_i: true,
+                wait_key_on_press: true,
+                ..Quirks::default()
+            },
+        }
+    }
+
+    /// Looks a variant up by a short name like `chip48`, ignoring case and
+    /// dashes, for command lines
+    pub fn from_name(name: &str) -> Option<Variant> {
+        match name.to_ascii_lowercase().replace('-', "").as_str() {
+            "chip8" | "vip" => Some(Variant::Chip8),
+            "chip48" => Some(Variant::Chip48),
+            "schip" | "superchip" => Some(Variant::SuperChip),
+            "megachip" => Some(Variant::MegaChip),
+            "xochip" => Some(Variant::XoChip),
+            _ => None,
+        }
+    }
+
+    /// Whether the interpreter has every instruction `other` introduced
+    ///
+    /// Each family has its own, apart from CHIP-48, which has only the
+    /// originals, and MegaChip and XO-CHIP, which both carry SUPER-CHIP's.
+    ///
+    /// # Examples
+    /// ```
+    /// use chip_8::analyze::Variant;
+    ///
+    /// assert!(Variant::XoChip.includes(Variant::SuperChip));
+    /// assert!(!Variant::XoChip.includes(Variant::MegaChip));
+    /// ```
+    pub fn includes(self, other: Variant) -> bool {
+        match (self, other) {
+            _ if self == other => true,
+            (_, Variant::Chip8 | Variant::Chip48) => true,
+            (Variant::MegaChip | Variant::XoChip, Variant::SuperChip) => true,
+            _ => false,
+        }
+    }
+}
+
+/// An instruction from outside the original CHIP-8 set
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Extension {
+    /// Where in memory it was found
+    pub address: usize,
+    pub opcode: OpCode,
+    /// Its opcode pattern, like `"00FF"` or `"DXY0"`
+    pub pattern: &'static str,
+    /// The interpreter that introduced it
+    pub variant: Variant,
+}
+
+impl Extension {
+    /// Whether this emulator can run the instruction
+    pub fn supported(&self) -> bool {
+        matches!(
+            self.pattern,
+            "00FE" | "00FF" | "DXY0" | "FX75" | "FX85" | "F002" | "FX3A" | "0010" | "0011" | "01NN" | "02NN" | "03NN" | "04NN" | "05NN"
+        )
+    }
+}
+
+/// What [`scan`] found in a ROM
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RomReport {
+    /// Every address an instruction can be reached at, lowest first
+    pub code: Vec<usize>,
+    /// How many reachable instructions there are with each opcode pattern
+    pub opcodes: BTreeMap<&'static str, usize>,
+    /// Reachable instructions from SUPER-CHIP, MegaChip or XO-CHIP, by
+    /// address
+    pub extensions: Vec<Extension>,
+    /// Reachable opcodes no interpreter knows, as (address, opcode)
+    pub unknown: Vec<(usize, OpCode)>,
+    /// Where BNNN jumps are, whose targets depend on a register and so
+    /// weren't followed
+    pub indirect_jumps: Vec<usize>,
+    /// The newest interpreter family any reachable instruction needs
+    pub variant: Variant,
+    /// A guess at the quirks the ROM expects
+    ///
+    /// Only quirks that show in the code are set: shifts with two different
+    /// registers suggest `shift_uses_vy`, and SUPER-CHIP instructions
+    /// suggest `jump_uses_vx` and `clip_sprites`. The rest can't be told
+    /// apart without running the ROM.
+    pub quirks: Quirks,
+}
+
+impl RomReport {
+    /// The reachable instructions this emulator can't run
+    pub fn unsupported(&self) -> impl Iterator<Item = &Extension> + '_ {
+        self.extensions.iter().filter(|extension| !extension.supported())
+    }
+}
+
+/// A reachable instruction an interpreter can't run, from [`verify`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompatibilityIssue {
+    /// An instruction from an interpreter the one checked against doesn't
+    /// include, and what it was checked against
+    Missing(Extension, Variant),
+    /// An opcode no interpreter knows
+    Unknown { address: usize, opcode: OpCode },
+}
+
+impl CompatibilityIssue {
+    /// Where the instruction is
+    pub fn address(&self) -> usize {
+        match *self {
+            CompatibilityIssue::Missing(extension, _) => extension.address,
+            CompatibilityIssue::Unknown { address, .. } => address,
+        }
+    }
+}
+
+impl fmt::Display for CompatibilityIssue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CompatibilityIssue::Missing(extension, variant) => write!(
+                f,
+                "{:04X} at 0x{:03X} is a {} instruction, which {} doesn't have",
+                extension.opcode, extension.address, extension.variant, variant
+            ),
+            CompatibilityIssue::Unknown { address, opcode } => {
+                write!(f, "{:04X} at 0x{:03X} isn't an instruction on any interpreter", opcode, address)
+            }
+        }
+    }
+}
+
+/// How control gets from one instruction to another
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum EdgeKind {
+    /// On to the next instruction, including after a call returns
+    Next,
+    /// A 1NNN jump
+    Jump,
+    /// A 2NNN call, or a 0NNN machine code call
+    Call,
+    /// Over the next instruction, when a skip's condition holds
+    Skip,
+}
+
+impl fmt::Display for EdgeKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EdgeKind::Next => write!(f, "next"),
+            EdgeKind::Jump => write!(f, "jump"),
+            EdgeKind::Call => write!(f, "call"),
+            EdgeKind::Skip => write!(f, "skip"),
+        }
+    }
+}
+
+/// Where control can go after a block
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Edge {
+    /// The address it goes to, which can be outside the ROM
+    pub to: usize,
+    pub kind: EdgeKind,
+}
+
+/// An instruction in a [`BasicBlock`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockInstruction {
+    pub address: usize,
+    /// The first two bytes, for the four byte XO-CHIP and MegaChip ones
+    pub opcode: OpCode,
+    /// The instruction as assembly, or the interpreter and pattern of one
+    /// from outside the original set, like `SUPER-CHIP 00FF`
+    pub text: String,
+}
+
+/// A run of instructions that's only ever entered at the top and left at
+/// the bottom
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BasicBlock {
+    pub start: usize,
+    /// Just past the last instruction
+    pub end: usize,
+    pub instructions: Vec<BlockInstruction>,
+    /// Where control can go after the last instruction, nowhere after a
+    /// return, halt or BNNN
+    pub edges: Vec<Edge>,
+}
+
+/// The basic blocks of a ROM and how control flows between them, from [`cfg`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ControlFlowGraph {
+    /// Every block reachable from 0x200, by start address
+    pub blocks: Vec<BasicBlock>,
+    /// Where BNNN jumps are, whose targets depend on a register and so
+    /// weren't followed
+    pub indirect_jumps: Vec<usize>,
+}
+
+impl ControlFlowGraph {
+    /// The block starting at `addr`
+    pub fn block(&self, addr: usize) -> Option<&BasicBlock> {
+        let index = self.blocks.binary_search_by_key(&addr, |block| block.start).ok()?;
+        Some(&self.blocks[index])
+    }
+
+    /// Where the subroutines are, the targets of every call, lowest first
+    pub fn subroutines(&self) -> Vec<usize> {
+        let mut subroutines: Vec<usize> = self
+            .blocks
+            .iter()
+            .flat_map(|block| &block.edges)
+            .filter(|edge| edge.kind == EdgeKind::Call)
+            .map(|edge| edge.to)
+            .collect();
+        subroutines.sort_unstable();
+        subroutines.dedup();
+        subroutines
+    }
+
+    /// The graph in Graphviz's DOT language, a box of disassembly for each
+    /// block, for `dot -Tsvg`
+    ///
+    /// Subroutines are drawn with a double border, calls in bold and skips
+    /// dashed. Calls and jumps out of the ROM go to boxes with just the
+    /// address.
+    pub fn to_dot(&self) -> String {
+        let subroutines = self.subroutines();
+        let mut dot = String::from("digraph cfg {\n    node [shape=box, fontname=monospace];\n");
+        for block in &self.blocks {
+            let lines: String = block
+                .instructions
+                .iter()
+                .map(|line| format!("0x{:03X}  {:04X}  {}\\l", line.address, line.opcode, line.text))
+                .collect();
+            let border = if subroutines.contains(&block.start) { ", peripheries=2" } else { "" };
+            dot += &format!("    \"0x{:03X}\" [label=\"{}\"{}];\n", block.start, lines, border);
+        }
+        for block in &self.blocks {
+            for edge in &block.edges {
+                let style = match edge.kind {
+                    EdgeKind::Next => "",
+                    EdgeKind::Jump => " [label=jump]",
+                    EdgeKind::Call => " [label=call, style=bold]",
+                    EdgeKind::Skip => " [label=skip, style=dashed]",
+                };
+                dot += &format!("    \"0x{:03X}\" -> \"0x{:03X}\"{};\n", block.start, edge.to, style);
+            }
+        }
+        dot + "}\n"
+    }
+
+    /// The graph as JSON, with addresses and opcodes as plain numbers and
+    /// each edge's kind as `next`, `jump`, `call` or `skip`
+    pub fn to_json(&self) -> String {
+        let blocks: Vec<_> = self
+            .blocks
+            .iter()
+            .map(|block| {
+                let instructions: Vec<_> = block
+                    .instructions
+                    .iter()
+                    .map(|line| json!({ "address": line.address, "opcode": line.opcode, "text": line.text }))
+                    .collect();
+                let edges: Vec<_> =
+                    block.edges.iter().map(|edge| json!({ "to": edge.to, "kind": edge.kind.to_string() })).collect();
+                json!({ "start": block.start, "end": block.end, "instructions": instructions, "edges": edges })
+            })
+            .collect();
+        json!({
+            "blocks": blocks,
+            "subroutines": self.subroutines(),
+            "indirect_jumps": self.indirect_jumps,
+        })
+        .to_string()
+    }
+}
+
+/// The pattern and variant of an opcode from outside the original set
+///
+/// MegaChip's instructions other than `0011` sit in the middle of the
+/// `0NNN` machine code calls, so they're only recognised in a ROM that
+/// turns MegaChip on, `mega` being true.
+fn extension(opcode: OpCode, mega: bool) -> Option<(&'static str, Variant)> {
+    let low = opcode & 0x00FF;
+    Some(match opcode >> 12 {
+        0x0 if opcode == 0x0011 => ("0011", Variant::MegaChip),
+        0x0 if mega => match opcode >> 8 {
+            0x0 if opcode == 0x0010 => ("0010", Variant::MegaChip),
+            0x0 if opcode & 0xFFF0 == 0x00B0 => ("00BN", Variant::MegaChip),
+            0x1 => ("01NN", Variant::MegaChip),
+            0x2 => ("02NN", Variant::MegaChip),
+            0x3 => ("03NN", Variant::MegaChip),
+            0x4 => ("04NN", Variant::MegaChip),
+            0x5 => ("05NN", Variant::MegaChip),
+            0x6 if low & 0xF0 == 0 => ("060N", Variant::MegaChip),
+            0x7 if low == 0 => ("0700", Variant::MegaChip),
+            0x8 if low & 0xF0 == 0 => ("080N", Variant::MegaChip),
+            0x9 => ("09NN", Variant::MegaChip),
+            _ => return extension(opcode, false),
+        },
+        0x0 if opcode & 0xFFF0 == 0x00C0 => ("00CN", Variant::SuperChip),
+        0x0 if opcode & 0xFFF0 == 0x00D0 => ("00DN", Variant::XoChip),
+        0x0 => match opcode {
+            0x00FB => ("00FB", Variant::SuperChip),
+            0x00FC => ("00FC", Variant::SuperChip),
+            0x00FD => ("00FD", Variant::SuperChip),
+            0x00FE => ("00FE", Variant::SuperChip),
+            0x00FF => ("00FF", Variant::SuperChip),
+            _ => return None,
+        },
+        0x5 if opcode & 0xF == 0x2 => ("5XY2", Variant::XoChip),
+        0x5 if opcode & 0xF == 0x3 => ("5XY3", Variant::XoChip),
+        0xD if opcode & 0xF == 0x0 => ("DXY0", Variant::SuperChip),
+        0xF if opcode == 0xF000 => ("F000", Variant::XoChip),
+        0xF if opcode == 0xF002 => ("F002", Variant::XoChip),
+        0xF if low == 0x01 => ("FN01", Variant::XoChip),
+        0xF if low == 0x30 => ("FX30", Variant::SuperChip),
+        0xF if low == 0x3A => ("FX3A", Variant::XoChip),
+        0xF if low == 0x75 => ("FX75", Variant::SuperChip),
+        0xF if low == 0x85 => ("FX85", Variant::SuperChip),
+        _ => return None,
+    })
+}
+
+/// Where control can go once the instruction at `addr` has run
+///
+/// Returns, halts, SUPER-CHIP's exit, BNNN and unknown opcodes go nowhere
+/// that can be known without running the ROM.
+fn successors(addr: usize, opcode: OpCode, mega: bool) -> Vec<Edge> {
+    let next = addr + 2;
+    let edge = |to, kind| Edge { to, kind };
+    if let Some((pattern, _)) = extension(opcode, mega) {
+        return match pattern {
+            "00FD" => vec![],
+            _ => vec![edge(addr + width(opcode, mega), EdgeKind::Next)],
+        };
+    }
+
+    match Instruction::decode(opcode) {
+        Instruction::Halt | Instruction::Return | Instruction::JumpReg(_) | Instruction::Unknown(_) => vec![],
+        Instruction::Jump(nnn) => vec![edge(nnn.index(), EdgeKind::Jump)],
+        Instruction::Call(nnn) | Instruction::Sys(nnn) => {
+            vec![edge(next, EdgeKind::Next), edge(nnn.index(), EdgeKind::Call)]
+        }
+        Instruction::SkipEqual(..)
+        | Instruction::SkipNotEqual(..)
+        | Instruction::SkipEqualReg(..)
+        | Instruction::SkipNotEqualReg(..)
+        | Instruction::SkipKeyPressed(_)
+        | Instruction::SkipKeyNotPressed(_) => vec![edge(next, EdgeKind::Next), edge(next + 2, EdgeKind::Skip)],
+        _ => vec![edge(next, EdgeKind::Next)],
+    }
+}
+
+/// How many bytes the instruction takes, four for the ones with the rest of
+/// an address after them
+fn width(opcode: OpCode, mega: bool) -> usize {
+    match extension(opcode, mega) {
+        Some(("F000" | "01NN", _)) => 4,
+        _ => 2,
+    }
+}
+
+/// Whether a ROM turns MegaChip on with `0011` anywhere it could be an
+/// instruction
+fn is_mega(rom: &[u8]) -> bool {
+    rom.chunks_exact(2).any(|opcode| opcode == [0x00, 0x11])
+}
+
+/// The opcode at `addr` in a ROM loaded at 0x200
+fn opcode_at(rom: &[u8], addr: usize) -> OpCode {
+    let offset = addr - PROGRAM_START;
+    (rom[offset] as OpCode) << 8 | rom[offset + 1] as OpCode
+}
+
+/// Follows every path through a ROM loaded at 0x200 and reports on the
+/// code it finds
+///
+/// Jumps, calls and both sides of every skip are followed. Paths stop at
+/// returns, halts, SUPER-CHIP's exit, BNNN and unknown opcodes, and
+/// anywhere outside the ROM.
+///
+/// A ROM with MegaChip's `0011` anywhere it could be an instruction is
+/// taken to be MegaChip throughout, its `0NNN`s read as MegaChip's
+/// instructions rather than machine code calls.
+///
+/// # Examples
+/// ```
+/// use chip_8::analyze;
+///
+/// let report = analyze::scan(include_bytes!("../roms/sierpinski.ch8"));
+/// assert!(report.unsupported().next().is_none());
+/// assert!(report.opcodes.contains_key("DXYN"));
+/// ```
+pub fn scan(rom: &[u8]) -> RomReport {
+    let rom_range = PROGRAM_START..PROGRAM_START + rom.len();
+    let mut report = RomReport {
+        code: Vec::new(),
+        opcodes: BTreeMap::new(),
+        extensions: Vec::new(),
+        unknown: Vec::new(),
+        indirect_jumps: Vec::new(),
+        variant: Variant::Chip8,
+        quirks: Quirks::default(),
+    };
+
+    let mega = is_mega(rom);
+
+    let mut seen = vec![false; rom_range.end];
+    let mut pending = vec![PROGRAM_START];
+    while let Some(addr) = pending.pop() {
+        if !rom_range.contains(&addr) || addr + 1 >= rom_range.end || seen[addr] {
+            continue;
+        }
+        seen[addr] = true;
+
+        let opcode = opcode_at(rom, addr);
+
+        if let Some((pattern, variant)) = extension(opcode, mega) {
+            *report.opcodes.entry(pattern).or_insert(0) += 1;
+            report.extensions.push(Extension { address: addr, opcode, pattern, variant });
+            report.variant = report.variant.max(variant);
+        } else {
+            let instruction = Instruction::decode(opcode);
+            *report.opcodes.entry(instruction.pattern()).or_insert(0) += 1;
+            match instruction {
+                Instruction::JumpReg(_) => report.indirect_jumps.push(addr),
+                Instruction::Unknown(opcode) => report.unknown.push((addr, opcode)),
+                Instruction::ShiftRight(x, y) | Instruction::ShiftLeft(x, y) => report.quirks.shift_uses_vy |= x != y,
+                _ => {}
+            }
+        }
+        pending.extend(successors(addr, opcode, mega).iter().map(|edge| edge.to));
+    }
+
+    if report.variant >= Variant::SuperChip {
+        report.quirks.jump_uses_vx = true;
+        report.quirks.clip_sprites = true;
+    }
+
+    report.code = (0..seen.len()).filter(|&addr| seen[addr]).collect();
+    report.extensions.sort_by_key(|extension| extension.address);
+    report.unknown.sort_unstable();
+    report.indirect_jumps.sort_unstable();
+    report
+}
+
+/// Checks a ROM can run on `variant` before it's run, rather than partway
+/// through a game
+///
+/// Everything [`scan`] can reach is checked, so an instruction behind a
+/// BNNN jump can still turn up later, and data right after a skip is
+/// checked as though it were code.
+///
+/// # Examples
+/// ```
+/// use chip_8::analyze::{self, Variant};
+///
+/// // scroll down 4 (00C4); JP 0x202
+/// let rom = [0x00, 0xC4, 0x12, 0x02];
+/// assert_eq!(analyze::verify(&rom, Variant::SuperChip), []);
+///
+/// let issues = analyze::verify(&rom, Variant::Chip8);
+/// assert_eq!(issues[0].to_string(), "00C4 at 0x200 is a SUPER-CHIP instruction, which CHIP-8 doesn't have");
+/// ```
+pub fn verify(rom: &[u8], variant: Variant) -> Vec<CompatibilityIssue> {
+    let report = scan(rom);
+    let mut issues: Vec<CompatibilityIssue> = report
+        .extensions
+        .iter()
+        .filter(|extension| !variant.includes(extension.variant))
+        .map(|&extension| CompatibilityIssue::Missing(extension, variant))
+        .collect();
+    issues.extend(report.unknown.iter().map(|&(address, opcode)| CompatibilityIssue::Unknown { address, opcode }));
+    issues.sort_by_key(CompatibilityIssue::address);
+    issues
+}
+
+/// Splits the code [`scan`] finds into basic blocks, joined by the jumps,
+/// calls, skips and plain steps between them
+///
+/// A block starts at 0x200, wherever a jump, call or skip goes, after
+/// every call and skip, and anywhere control can arrive from more than
+/// one place. Instructions reached only by running into them belong to
+/// the block before.
+///
+/// # Examples
+/// ```
+/// use chip_8::analyze::{self, EdgeKind};
+///
+/// // LD V0, 0; loop: ADD V0, 1; SE V0, 10; JP loop; terminate
+/// let graph = analyze::cfg(&[0x60, 0x00, 0x70, 0x01, 0x30, 0x0A, 0x12, 0x02, 0x00, 0x00]);
+///
+/// let starts: Vec<usize> = graph.blocks.iter().map(|block| block.start).collect();
+/// assert_eq!(starts, [0x200, 0x202, 0x206, 0x208]);
+/// assert_eq!(graph.block(0x206).unwrap().edges[0].kind, EdgeKind::Jump);
+/// assert!(graph.to_dot().contains("\"0x206\" -> \"0x202\" [label=jump];"));
+/// ```
+pub fn cfg(rom: &[u8]) -> ControlFlowGraph {
+    let report = scan(rom);
+    let mega = is_mega(rom);
+
+    let flow: BTreeMap<usize, (OpCode, Vec<Edge>)> = report
+        .code
+        .iter()
+        .map(|&addr| {
+            let opcode = opcode_at(rom, addr);
+            (addr, (opcode, successors(addr, opcode, mega)))
+        })
+        .collect();
+
+    // an instruction that's only ever run into from the one before carries
+    // on its block; anything else starts one
+    let mut incoming: BTreeMap<usize, usize> = BTreeMap::new();
+    let mut leaders = vec![PROGRAM_START];
+    for (_, edges) in flow.values() {
+        let straight = matches!(edges[..], [Edge { kind: EdgeKind::Next, .. }]);
+        for edge in edges {
+            *incoming.entry(edge.to).or_insert(0) += 1;
+            if !straight {
+                leaders.push(edge.to);
+            }
+        }
+    }
+    leaders.extend(incoming.iter().filter(|&(_, &count)| count > 1).map(|(&addr, _)| addr));
+    leaders.retain(|addr| flow.contains_key(addr));
+    leaders.sort_unstable();
+    leaders.dedup();
+
+    let carries_on = |addr: usize| flow.contains_key(&addr) && leaders.binary_search(&addr).is_err();
+    let blocks = leaders
+        .iter()
+        .map(|&start| {
+            let mut block = BasicBlock { start, end: start, instructions: Vec::new(), edges: Vec::new() };
+            let mut addr = start;
+            loop {
+                let (opcode, edges) = &flow[&addr];
+                let text = match extension(*opcode, mega) {
+                    Some((pattern, variant)) => format!("{} {}", variant, pattern),
+                    None => Instruction::decode(*opcode).to_string(),
+                };
+                block.instructions.push(BlockInstruction { address: addr, opcode: *opcode, text });
+                block.end = addr + width(*opcode, mega);
+
+                match edges[..] {
+                    [Edge { to, kind: EdgeKind::Next }] if carries_on(to) => addr = to,
+                    _ => {
+                        block.edges.clone_from(edges);
+                        return block;
+                    }
+                }
+            }
+        })
+        .collect();
+
+    ControlFlowGraph { blocks, indirect_jumps: report.indirect_jumps }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scan_follows_code_past_4k() {
+        // LD V0, 0 up to 0x1000, then terminate
+        let mut rom = [0x60, 0x00].repeat(0x700);
+        rom.extend_from_slice(&[0x00, 0x00]);
+        let report = scan(&rom);
+
+        assert_eq!(report.code.len(), 0x701);
+        assert_eq!(report.code.last(), Some(&0x1000));
+    }
+
+    #[test]
+    fn scan_follows_jumps_calls_and_skips() {
+        let rom = [
+            0x22, 0x0A, // 0x200 CALL 0x20A
+            0x30, 0x01, // 0x202 SE V0, 1
+            0x12, 0x08, // 0x204 JP 0x208
+            0xFF, 0xFF, // 0x206 data, skipped over but still reachable
+            0x00, 0x00, // 0x208 terminate
+            0x80, 0x14, // 0x20A ADD V0, V1
+            0x00, 0xEE, // 0x20C RET
+            0xAB, 0xCD, // 0x20E data, never reached
+        ];
+        let report = scan(&rom);
+
+        assert_eq!(report.code, [0x200, 0x202, 0x204, 0x206, 0x208, 0x20A, 0x20C]);
+        assert_eq!(report.unknown, [(0x206, 0xFFFF)]);
+        assert_eq!(report.opcodes["8XY4"], 1);
+        assert_eq!(report.opcodes["1NNN"], 1);
+        assert_eq!(report.variant, Variant::Chip8);
+        assert_eq!(report.quirks, Quirks::default());
+    }
+
+    #[test]
+    fn cfg_splits_at_calls_skips_and_joins() {
+        let rom = [
+            0x22, 0x0A, // 0x200 CALL 0x20A
+            0x30, 0x01, // 0x202 SE V0, 1
+            0x12, 0x02, // 0x204 JP 0x202
+            0x00, 0x00, // 0x206 terminate
+            0xFF, 0xFF, // 0x208 data, never reached
+            0x80, 0x14, // 0x20A ADD V0, V1
+            0x00, 0xEE, // 0x20C RET
+        ];
+        let graph = cfg(&rom);
+
+        let starts: Vec<usize> = graph.blocks.iter().map(|block| block.start).collect();
+        assert_eq!(starts, [0x200, 0x202, 0x204, 0x206, 0x20A]);
+        assert_eq!(graph.subroutines(), [0x20A]);
+        let skip = graph.block(0x202).unwrap();
+        assert_eq!(skip.edges, [Edge { to: 0x204, kind: EdgeKind::Next }, Edge { to: 0x206, kind: EdgeKind::Skip }]);
+        let subroutine = graph.block(0x20A).unwrap();
+        assert_eq!((subroutine.end, subroutine.instructions[1].text.as_str()), (0x20E, "RET"));
+        assert!(subroutine.edges.is_empty());
+
+        let dot = graph.to_dot();
+        assert!(dot.contains("\"0x200\" -> \"0x20A\" [label=call, style=bold];"));
+        assert!(dot.contains("\"0x20A\" [label=\"0x20A  8014  ADD V0, V1\\l0x20C  00EE  RET\\l\", peripheries=2];"));
+
+        let json: serde_json::Value = serde_json::from_str(&graph.to_json()).unwrap();
+        assert_eq!(json["blocks"][0]["edges"], json!([{ "to": 0x202, "kind": "next" }, { "to": 0x20A, "kind": "call" }]));
+        assert_eq!(json["subroutines"], json!([0x20A]));
+    }
+
+    #[test]
+    fn verify_checks_against_the_chosen_variant() {
+        let rom = [
+            0x00, 0xFF, // 0x200 high resolution
+            0x30, 0x00, // 0x202 SE V0, 0
+            0xFF, 0xFF, // 0x204 data, reachable past the skip
+            0xF0, 0x00, 0x12, 0x34, // 0x206 LD I, 0x1234
+            0x00, 0x00, // 0x20A terminate
+        ];
+
+        let addresses = |variant| verify(&rom, variant).iter().map(CompatibilityIssue::address).collect::<Vec<_>>();
+        assert_eq!(addresses(Variant::Chip8), [0x200, 0x204, 0x206]);
+        assert_eq!(addresses(Variant::MegaChip), [0x204, 0x206]);
+        assert_eq!(addresses(Variant::XoChip), [0x204]);
+        assert_eq!(verify(&rom, Variant::XoChip), [CompatibilityIssue::Unknown { address: 0x204, opcode: 0xFFFF }]);
+    }
+
+    #[test]
+    fn scan_flags_extensions_and_guesses_quirks() {
+        let rom = [
+            0x00, 0xFB, // 0x200 scroll right
+            0x81, 0x26, // 0x202 SHR V1, V2
+            0xF2, 0x75, // 0x204 LD R, V2
+            0xB3, 0x00, // 0x206 JP V0, 0x300
+        ];
+        let report = scan(&rom);
+
+        assert_eq!(report.variant, Variant::SuperChip);
+        assert_eq!(report.extensions.len(), 2);
+        assert_eq!(report.unsupported().map(|extension| extension.pattern).collect::<Vec<_>>(), ["00FB"]);
+        assert_eq!(report.indirect_jumps, [0x206]);
+        assert!(report.quirks.shift_uses_vy);
+        assert!(report.quirks.jump_uses_vx);
+        assert!(report.quirks.clip_sprites);
+    }
+
+    #[test]
+    fn scan_steps_over_long_xo_chip_addresses() {
+        let rom = [
+            0xF0, 0x00, 0x12, 0x34, // 0x200 LD I, 0x1234
+            0x00, 0x00, // 0x204 terminate
+        ];
+        let report = scan(&rom);
+
+        assert_eq!(report.code, [0x200, 0x204]);
+        assert_eq!(report.variant, Variant::XoChip);
+    }
+
+    #[test]
+    fn scan_reads_megachip_only_once_it_is_turned_on() {
+        let rom = [
+            0x00, 0x11, // 0x200 mega on
+            0x01, 0x01, 0x23, 0x45, // 0x202 LDHI I, 0x012345
+            0x02, 0x10, // 0x206 load 16 palette colours
+            0x07, 0x00, // 0x208 stop the digitised sound
+            0x00, 0x00, // 0x20A terminate
+        ];
+        let report = scan(&rom);
+
+        assert_eq!(report.variant, Variant::MegaChip);
+        assert_eq!(report.code, [0x200, 0x202, 0x206, 0x208, 0x20A]);
+        assert_eq!(report.extensions.len(), 4);
+        assert_eq!(report.unsupported().map(|extension| extension.pattern).collect::<Vec<_>>(), ["0700"]);
+
+        // without it, 0210 is a machine code call
+        let report = scan(&[0x02, 0x10, 0x00, 0x00]);
+        assert_eq!(report.variant, Variant::Chip8);
+        assert_eq!(report.opcodes["0NNN"], 1);
+    }
+}
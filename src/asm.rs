@@ -0,0 +1,619 @@
+//! Assembling Octo source into ROMs
+//!
+//! [`assemble`] builds a ROM, to be loaded at 0x200, from a useful subset of
+//! the [Octo](https://github.com/JohnEarnest/Octo) language that most
+//! community CHIP-8 sources are written in:
+//!
+//! - `: name` defines a label; a bare `name` calls it, `jump name` jumps
+//!   there and `i := name` points I at it
+//! - `:const NAME VALUE` and `:alias NAME vX` name numbers and registers
+//! - `loop` ... `again` loops, leaving early with `while CONDITION`
+//! - `if CONDITION then STATEMENT` and `if CONDITION begin ... else ... end`,
+//!   where a condition is `vX == N`, `vX != vY`, `vX key` or `vX -key`
+//! - numbers on their own emit a byte each, for sprites and other data,
+//!   in decimal, `0x` hex or `0b` binary
+//! - the register statements `:=`, `+=`, `-=`, `=-`, `|=`, `&=`, `^=`,
+//!   `>>=` and `<<=`, and `clear`, `return`, `sprite`, `bcd`, `save`,
+//!   `load`, `saveflags`, `loadflags`, `delay :=`, `buzzer :=`, `jump0`
+//!   and `i += vX`, SUPER-CHIP's `lores` and `hires`, and XO-CHIP's
+//!   `audio` and `pitch :=`
+//!
+//! As in Octo, a program that defines `main` anywhere but at the very
+//! start gets a `jump main` at 0x200. Comments run from `#` to the end of
+//! the line. Macros, `:org` and the rest of the SUPER-CHIP and XO-CHIP
+//! graphics statements aren't supported.
+//!
+//! # Example
+//!
+//! ```
+//! use chip_8::asm;
+//!
+//! let rom = asm::assemble(
+//!     ": main
+//!        v0 := 0
+//!        loop
+//!          v0 += 1
+//!          if v0 == 10 then return
+//!        again",
+//! )
+//! .unwrap();
+//!
+//! assert_eq!(rom, [0x60, 0x00, 0x70, 0x01, 0x40, 0x0A, 0x00, 0xEE, 0x12, 0x02]);
+//! ```
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+
+use crate::instruction::Instruction;
+use crate::symbols::{LineTable, SymbolTable};
+use crate::{Addr, Address, Byte, OpCode, Reg, PROGRAM_START};
+
+/// How many bytes fit between the start of the program and the end of
+/// memory
+const MAX_ROM: usize = 0x1000 - PROGRAM_START;
+
+/// Why a source file couldn't be assembled, and the line it happened on
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AsmError {
+    /// The line, counting from 1
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl Error for AsmError {}
+
+/// An assembled ROM, with what a debugger needs to show it as source
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Program {
+    pub rom: Vec<u8>,
+    /// Where each label ended up
+    pub symbols: SymbolTable,
+    /// Which line each instruction came from
+    pub lines: LineTable,
+}
+
+/// Assembles Octo `source` into a ROM
+pub fn assemble(source: &str) -> Result<Vec<u8>, AsmError> {
+    assemble_program(source).map(|program| program.rom)
+}
+
+/// Assembles Octo `source` into a ROM, along with where each of its labels
+/// ended up
+///
+/// # Examples
+/// ```
+/// use chip_8::asm;
+///
+/// let (rom, symbols) = asm::assemble_with_symbols(": main v0 := 1 : done jump done").unwrap();
+/// assert_eq!(rom.len(), 4);
+/// assert_eq!(symbols.name(0x202), Some("done"));
+/// ```
+pub fn assemble_with_symbols(source: &str) -> Result<(Vec<u8>, SymbolTable), AsmError> {
+    assemble_program(source).map(|program| (program.rom, program.symbols))
+}
+
+/// Assembles Octo `source` into a ROM, its labels and the line each
+/// instruction came from
+pub fn assemble_program(source: &str) -> Result<Program, AsmError> {
+    let tokens = source
+        .lines()
+        .enumerate()
+        .flat_map(|(line, text)| {
+            let code = text.split('#').next().unwrap_or_default();
+            code.split_whitespace().map(move |token| (line + 1, token))
+        })
+        .collect();
+
+    let mut assembler = Assembler {
+        tokens,
+        pos: 0,
+        line: 1,
+        rom: Vec::new(),
+        labels: HashMap::new(),
+        lines: LineTable::new(),
+        consts: HashMap::new(),
+        aliases: HashMap::new(),
+        fixups: Vec::new(),
+        blocks: Vec::new(),
+    };
+    assembler.run()?;
+
+    let mut symbols = SymbolTable::new();
+    for (name, addr) in assembler.labels {
+        symbols.insert(addr.get(), name);
+    }
+    Ok(Program { rom: assembler.rom, symbols, lines: assembler.lines })
+}
+
+/// A control structure that's still waiting for its end
+enum Block {
+    /// Where the loop starts, and the `while` jumps out of it
+    Loop { start: Addr, exits: Vec<usize> },
+    /// The jump past the `if` or `else` body
+    If { jump: usize, has_else: bool },
+}
+
+/// An address operand that named a label before it was defined
+struct Fixup<'a> {
+    /// Where the opcode is in the ROM
+    at: usize,
+    label: &'a str,
+    line: usize,
+}
+
+/// A register operand, or a number
+enum Operand {
+    Register(Reg),
+    Number(i64),
+}
+
+/// Opcodes for skipping the next instruction when a condition is false,
+/// and when it's true
+type Condition = (Instruction, Instruction);
+
+struct Assembler<'a> {
+    tokens: Vec<(usize, &'a str)>,
+    pos: usize,
+    /// The line of the token last taken
+    line: usize,
+    rom: Vec<u8>,
+    labels: HashMap<&'a str, Addr>,
+    lines: LineTable,
+    consts: HashMap<&'a str, i64>,
+    aliases: HashMap<&'a str, Reg>,
+    fixups: Vec<Fixup<'a>>,
+    blocks: Vec<Block>,
+}
+
+impl<'a> Assembler<'a> {
+    fn run(&mut self) -> Result<(), AsmError> {
+        let defines_main = self.tokens.windows(2).any(|pair| pair[0].1 == ":" && pair[1].1 == "main");
+        let starts_with_main = self.tokens.len() >= 2 && self.tokens[0].1 == ":" && self.tokens[1].1 == "main";
+        if defines_main && !starts_with_main {
+            self.fixups.push(Fixup { at: 0, label: "main", line: 1 });
+            self.emit(Instruction::Jump(Addr::nnn(0)))?;
+        }
+
+        while self.pos < self.tokens.len() {
+            self.statement()?;
+        }
+
+        if let Some(block) = self.blocks.last() {
+            let open = match block {
+                Block::Loop { .. } => "a loop without an `again`",
+                Block::If { .. } => "an `if ... begin` without an `end`",
+            };
+            return Err(self.error(format!("the program ends inside {}", open)));
+        }
+
+        for fixup in &self.fixups {
+            let addr = match self.labels.get(fixup.label) {
+                Some(&addr) => addr,
+                None => return Err(AsmError { line: fixup.line, message: format!("`{}` is never defined", fixup.label) }),
+            };
+            self.rom[fixup.at] |= (addr.get() >> 8) as Byte;
+            self.rom[fixup.at + 1] = addr.get() as Byte;
+        }
+        Ok(())
+    }
+
+    fn error(&self, message: String) -> AsmError {
+        AsmError { line: self.line, message }
+    }
+
+    fn next(&mut self) -> Result<&'a str, AsmError> {
+        match self.tokens.get(self.pos) {
+            Some(&(line, token)) => {
+                self.pos += 1;
+                self.line = line;
+                Ok(token)
+            }
+            None => Err(self.error(String::from("the program ends in the middle of a statement"))),
+        }
+    }
+
+    fn expect(&mut self, expected: &str) -> Result<(), AsmError> {
+        let token = self.next()?;
+        if token == expected {
+            Ok(())
+        } else {
+            Err(self.error(format!("expected `{}`, found `{}`", expected, token)))
+        }
+    }
+
+    /// The address the next byte will be at
+    fn here(&self) -> Addr {
+        Addr::nnn((PROGRAM_START + self.rom.len()) as Address)
+    }
+
+    fn emit_byte(&mut self, byte: Byte) -> Result<(), AsmError> {
+        if self.rom.len() == MAX_ROM {
+            return Err(self.error(format!("the program is bigger than the {} bytes that fit in memory", MAX_ROM)));
+        }
+        self.rom.push(byte);
+        Ok(())
+    }
+
+    fn emit(&mut self, instruction: Instruction) -> Result<(), AsmError> {
+        self.lines.insert(self.here().get(), self.line);
+        let opcode: OpCode = instruction.encode();
+        self.emit_byte((opcode >> 8) as Byte)?;
+        self.emit_byte(opcode as Byte)
+    }
+
+    /// Emits a jump to be pointed somewhere later, returning where it is
+    fn emit_jump(&mut self) -> Result<usize, AsmError> {
+        let at = self.rom.len();
+        self.emit(Instruction::Jump(Addr::nnn(0)))?;
+        Ok(at)
+    }
+
+    /// Points the jump at `at` to the next instruction
+    fn patch_jump(&mut self, at: usize) {
+        let here = self.here();
+        self.rom[at] = 0x10 | (here.get() >> 8) as Byte;
+        self.rom[at + 1] = here.get() as Byte;
+    }
+
+    fn number(&self, token: &str) -> Option<i64> {
+        let (negative, digits) = match token.strip_prefix('-') {
+            Some(digits) => (true, digits),
+            None => (false, token),
+        };
+        let value = if let Some(hex) = digits.strip_prefix("0x").or_else(|| digits.strip_prefix("0X")) {
+            i64::from_str_radix(hex, 16).ok()?
+        } else if let Some(binary) = digits.strip_prefix("0b").or_else(|| digits.strip_prefix("0B")) {
+            i64::from_str_radix(binary, 2).ok()?
+        } else if digits.starts_with(|c: char| c.is_ascii_digit()) {
+            digits.parse().ok()?
+        } else {
+            *self.consts.get(digits)?
+        };
+        Some(if negative { -value } else { value })
+    }
+
+    fn register(&self, token: &str) -> Option<Reg> {
+        if let Some(&reg) = self.aliases.get(token) {
+            return Some(reg);
+        }
+        let digit = token.strip_prefix('v').or_else(|| token.strip_prefix('V'))?;
+        match digit.len() {
+            1 => Byte::from_str_radix(digit, 16).ok().and_then(Reg::new),
+            _ => None,
+        }
+    }
+
+    fn expect_register(&mut self) -> Result<Reg, AsmError> {
+        let token = self.next()?;
+        self.register(token).ok_or_else(|| self.error(format!("expected a register, found `{}`", token)))
+    }
+
+    fn expect_byte(&mut self) -> Result<Byte, AsmError> {
+        let token = self.next()?;
+        self.byte(token)
+    }
+
+    /// A number that fits in a byte, negative numbers wrapping around
+    fn byte(&self, token: &str) -> Result<Byte, AsmError> {
+        match self.number(token) {
+            Some(value) if (-128..=255).contains(&value) => Ok(value as Byte),
+            Some(value) => Err(self.error(format!("{} doesn't fit in a byte", value))),
+            None => Err(self.error(format!("expected a number, found `{}`", token))),
+        }
+    }
+
+    fn operand(&mut self) -> Result<Operand, AsmError> {
+        let token = self.next()?;
+        if let Some(reg) = self.register(token) {
+            return Ok(Operand::Register(reg));
+        }
+        self.byte(token).map(|byte| Operand::Number(byte as i64))
+    }
+
+    /// An address for an instruction's NNN: a number, a constant or a
+    /// label, which may not be defined yet
+    fn address(&mut self, instruction: fn(Addr) -> Instruction) -> Result<(), AsmError> {
+        let token = self.next()?;
+        let addr = match self.number(token) {
+            Some(addr @ 0..=0xFFF) => Addr::nnn(addr as Address),
+            Some(addr) => return Err(self.error(format!("0x{:X} isn't an address in memory", addr))),
+            None => match self.labels.get(token) {
+                Some(&addr) => addr,
+                None => {
+                    self.fixups.push(Fixup { at: self.rom.len(), label: token, line: self.line });
+                    Addr::nnn(0)
+                }
+            },
+        };
+        self.emit(instruction(addr))
+    }
+
+    fn condition(&mut self) -> Result<Condition, AsmError> {
+        let x = self.expect_register()?;
+        let comparison = self.next()?;
+        let condition = match comparison {
+            "key" => (Instruction::SkipKeyNotPressed(x), Instruction::SkipKeyPressed(x)),
+            "-key" => (Instruction::SkipKeyPressed(x), Instruction::SkipKeyNotPressed(x)),
+            "==" | "!=" => {
+                let (equal, not_equal) = match self.operand()? {
+                    Operand::Register(y) => (Instruction::SkipEqualReg(x, y), Instruction::SkipNotEqualReg(x, y)),
+                    Operand::Number(nn) => (Instruction::SkipEqual(x, nn as Byte), Instruction::SkipNotEqual(x, nn as Byte)),
+                };
+                if comparison == "==" {
+                    (not_equal, equal)
+                } else {
+                    (equal, not_equal)
+                }
+            }
+            _ => return Err(self.error(format!("expected `==`, `!=`, `key` or `-key`, found `{}`", comparison))),
+        };
+        Ok(condition)
+    }
+
+    fn statement(&mut self) -> Result<(), AsmError> {
+        let token = self.next()?;
+        match token {
+            ":" => {
+                let name = self.next()?;
+                if self.labels.insert(name, self.here()).is_some() {
+                    return Err(self.error(format!("`{}` is already defined", name)));
+                }
+            }
+            ":const" => {
+                let name = self.next()?;
+                let token = self.next()?;
+                let value = self.number(token).ok_or_else(|| self.error(format!("expected a number, found `{}`", token)))?;
+                self.consts.insert(name, value);
+            }
+            ":alias" => {
+                let name = self.next()?;
+                let reg = self.expect_register()?;
+                self.aliases.insert(name, reg);
+            }
+            ":byte" => {
+                let byte = self.expect_byte()?;
+                self.emit_byte(byte)?;
+            }
+            ":call" => self.address(Instruction::Call)?,
+            "clear" => self.emit(Instruction::Clear)?,
+            "lores" => self.emit(Instruction::LowRes)?,
+            "hires" => self.emit(Instruction::HighRes)?,
+            "audio" => self.emit(Instruction::LoadAudio)?,
+            "return" | ";" => self.emit(Instruction::Return)?,
+            "jump" => self.address(Instruction::Jump)?,
+            "jump0" => self.address(Instruction::JumpReg)?,
+            "bcd" => self.register_statement(Instruction::Bcd)?,
+            "save" => self.register_statement(Instruction::RegDump)?,
+            "load" => self.register_statement(Instruction::RegLoad)?,
+            "saveflags" => self.register_statement(Instruction::SaveFlags)?,
+            "loadflags" => self.register_statement(Instruction::LoadFlags)?,
+            "sprite" => {
+                let x = self.expect_register()?;
+                let y = self.expect_register()?;
+                match self.expect_byte()? {
+                    rows @ 0..=15 => self.emit(Instruction::Draw(x, y, rows))?,
+                    rows => return Err(self.error(format!("sprites are at most 15 rows, not {}", rows))),
+                }
+            }
+            "delay" => {
+                self.expect(":=")?;
+                self.register_statement(Instruction::SetDelay)?;
+            }
+            "buzzer" => {
+                self.expect(":=")?;
+                self.register_statement(Instruction::SetSound)?;
+            }
+            "pitch" => {
+                self.expect(":=")?;
+                self.register_statement(Instruction::SetPitch)?;
+            }
+            "i" => match self.next()? {
+                ":=" => {
+                    if self.tokens.get(self.pos).map(|&(_, token)| token) == Some("hex") {
+                        self.next()?;
+                        self.register_statement(Instruction::SetISprite)?;
+                    } else {
+                        self.address(Instruction::SetI)?;
+                    }
+                }
+                "+=" => self.register_statement(Instruction::AddI)?,
+                op => return Err(self.error(format!("expected `:=` or `+=` after `i`, found `{}`", op))),
+            },
+            "loop" => self.blocks.push(Block::Loop { start: self.here(), exits: Vec::new() }),
+            "while" => {
+                let (_, skip_if_true) = self.condition()?;
+                self.emit(skip_if_true)?;
+                let jump = self.emit_jump()?;
+                match self.blocks.iter_mut().rev().find(|block| matches!(block, Block::Loop { .. })) {
+                    Some(Block::Loop { exits, .. }) => exits.push(jump),
+                    _ => return Err(self.error(String::from("`while` outside a loop"))),
+                }
+            }
+            "again" => match self.blocks.pop() {
+                Some(Block::Loop { start, exits }) => {
+                    self.emit(Instruction::Jump(start))?;
+                    for exit in exits {
+                        self.patch_jump(exit);
+                    }
+                }
+                _ => return Err(self.error(String::from("`again` without a `loop`"))),
+            },
+            "if" => {
+                let (skip_if_false, skip_if_true) = self.condition()?;
+                match self.next()? {
+                    "then" => self.emit(skip_if_false)?,
+                    "begin" => {
+                        self.emit(skip_if_true)?;
+                        let jump = self.emit_jump()?;
+                        self.blocks.push(Block::If { jump, has_else: false });
+                    }
+                    token => return Err(self.error(format!("expected `then` or `begin`, found `{}`", token))),
+                }
+            }
+            "else" => match self.blocks.pop() {
+                Some(Block::If { jump, has_else: false }) => {
+                    let end = self.emit_jump()?;
+                    self.patch_jump(jump);
+                    self.blocks.push(Block::If { jump: end, has_else: true });
+                }
+                _ => return Err(self.error(String::from("`else` without an `if ... begin`"))),
+            },
+            "end" => match self.blocks.pop() {
+                Some(Block::If { jump, .. }) => self.patch_jump(jump),
+                _ => return Err(self.error(String::from("`end` without an `if ... begin`"))),
+            },
+            _ => {
+                if let Some(x) = self.register(token) {
+                    self.register_assignment(x)?;
+                } else if self.number(token).is_some() {
+                    let byte = self.byte(token)?;
+                    self.emit_byte(byte)?;
+                } else if token.starts_with(':') {
+                    return Err(self.error(format!("`{}` isn't supported", token)));
+                } else {
+                    // a bare name calls the subroutine
+                    self.pos -= 1;
+                    self.address(Instruction::Call)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn register_statement(&mut self, instruction: fn(Reg) -> Instruction) -> Result<(), AsmError> {
+        let x = self.expect_register()?;
+        self.emit(instruction(x))
+    }
+
+    /// Statements starting with a register, like `v0 += 1`
+    fn register_assignment(&mut self, x: Reg) -> Result<(), AsmError> {
+        let op = self.next()?;
+        let instruction = match op {
+            ":=" => match self.next()? {
+                "random" => Instruction::Rand(x, self.expect_byte()?),
+                "delay" => Instruction::GetDelay(x),
+                "key" => Instruction::WaitKey(x),
+                _ => {
+                    self.pos -= 1;
+                    match self.operand()? {
+                        Operand::Register(y) => Instruction::Assign(x, y),
+                        Operand::Number(nn) => Instruction::SetRegister(x, nn as Byte),
+                    }
+                }
+            },
+            "+=" => match self.operand()? {
+                Operand::Register(y) => Instruction::AddReg(x, y),
+                Operand::Number(nn) => Instruction::Add(x, nn as Byte),
+            },
+            "-=" => match self.operand()? {
+                Operand::Register(y) => Instruction::SubReg(x, y),
+                Operand::Number(nn) => Instruction::Add(x, (nn as Byte).wrapping_neg()),
+            },
+            "=-" | "|=" | "&=" | "^=" | ">>=" | "<<=" => {
+                let y = self.expect_register()?;
+                match op {
+                    "=-" => Instruction::SubN(x, y),
+                    "|=" => Instruction::Or(x, y),
+                    "&=" => Instruction::And(x, y),
+                    "^=" => Instruction::Xor(x, y),
+                    ">>=" => Instruction::ShiftRight(x, y),
+                    _ => Instruction::ShiftLeft(x, y),
+                }
+            }
+            _ => return Err(self.error(format!("expected an assignment like `:=` or `+=`, found `{}`", op))),
+        };
+        self.emit(instruction)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn labels_can_be_used_before_they_are_defined() {
+        let rom = assemble(
+            ": main
+               i := smiley
+               sprite v0 v1 2
+               draw-again
+             : draw-again
+               return
+             : smiley
+               0b00100100 0x18",
+        )
+        .unwrap();
+
+        assert_eq!(rom, [0xA2, 0x08, 0xD0, 0x12, 0x22, 0x06, 0x00, 0xEE, 0x24, 0x18]);
+    }
+
+    #[test]
+    fn main_gets_a_jump_unless_it_comes_first() {
+        let rom = assemble(": helper return : main helper").unwrap();
+        assert_eq!(rom, [0x12, 0x04, 0x00, 0xEE, 0x22, 0x02]);
+
+        assert_eq!(assemble("v0 := 1").unwrap(), [0x60, 0x01]);
+    }
+
+    #[test]
+    fn constants_and_aliases_stand_in_for_their_values() {
+        let rom = assemble(
+            ":const SPEED 3 # pixels a frame
+             :alias x v4
+             x += SPEED
+             x -= 1
+             x := random 0xF0
+             v5 =- x
+             vf >>= x",
+        )
+        .unwrap();
+
+        assert_eq!(rom, [0x74, 0x03, 0x74, 0xFF, 0xC4, 0xF0, 0x85, 0x47, 0x8F, 0x46]);
+    }
+
+    #[test]
+    fn blocks_jump_around_their_bodies() {
+        let rom = assemble(
+            "loop
+               while v0 != 5
+               if v1 key begin
+                 v0 += 1
+               else
+                 v0 := v2
+               end
+             again",
+        )
+        .unwrap();
+
+        assert_eq!(
+            rom,
+            [
+                0x40, 0x05, // 200: skip leaving while v0 != 5
+                0x12, 0x10, // 202: leave the loop
+                0xE1, 0x9E, // 204: skip to the body if v1's key is pressed
+                0x12, 0x0C, // 206: to the else
+                0x70, 0x01, // 208
+                0x12, 0x0E, // 20A: past the else
+                0x80, 0x20, // 20C
+                0x12, 0x00, // 20E: again
+            ]
+        );
+    }
+
+    #[test]
+    fn errors_name_the_line() {
+        let err = assemble("v0 := 1\nv0 += 300").unwrap_err();
+        assert_eq!(err.to_string(), "line 2: 300 doesn't fit in a byte");
+
+        assert_eq!(assemble("\n\nnowhere").unwrap_err(), AsmError { line: 3, message: String::from("`nowhere` is never defined") });
+        assert_eq!(assemble("loop v0 := 1").unwrap_err().message, "the program ends inside a loop without an `again`");
+        assert_eq!(assemble("end").unwrap_err().message, "`end` without an `if ... begin`");
+    }
+}
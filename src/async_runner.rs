@@ -0,0 +1,168 @@
+//! Running an emulator as a task inside tokio, driven over channels
+//!
+//! [`Emulator::run_async`] is [`frontend::run`](crate::frontend::run) for
+//! async code: it takes [`InputEvents`] from an unbounded channel and
+//! publishes a [`FrameOutput`] on a watch channel after every update,
+//! waiting for the next frame in between rather than blocking a thread. A
+//! server can hold the other ends in each connection's task and run as many
+//! emulators as it likes on one runtime.
+//!
+//! # Example
+//!
+//! ```
+//! use chip_8::async_runner::FrameOutput;
+//! use chip_8::frontend::{Exit, InputEvents};
+//! use chip_8::{CpuBuilder, Emulator};
+//! use tokio::sync::{mpsc, watch};
+//!
+//! # tokio::runtime::Builder::new_current_thread().enable_time().build().unwrap().block_on(async {
+//! // a CPU straight from the builder, which halts on 0000
+//! let mut emulator = Emulator::new(CpuBuilder::new().build());
+//! // LD I, 0 (the font's 0); DRW V0, V0, 5; terminate
+//! emulator.load_rom(&[0xA0, 0x00, 0xD0, 0x05, 0x00, 0x00]).unwrap();
+//!
+//! let (_input, input_events) = mpsc::unbounded_channel::<InputEvents>();
+//! let (frame_output, frames) = watch::channel(FrameOutput::default());
+//! assert_eq!(emulator.run_async(input_events, frame_output).await, Exit::Halted);
+//!
+//! let lit = frames.borrow().frame.pixels().iter().filter(|&&lit| lit).count();
+//! assert_eq!(lit, 14);
+//! # });
+//! ```
+
+use std::time::{Duration, Instant};
+
+use tokio::sync::mpsc::error::TryRecvError;
+use tokio::sync::{mpsc, watch};
+use tokio::time::{self, MissedTickBehavior};
+
+use crate::audio::Pattern;
+use crate::frontend::{Exit, Frontend, InputEvents, Runner};
+use crate::timing::TIMER_HZ;
+use crate::{Emulator, FrameBuffer};
+
+/// What [`Emulator::run_async`] publishes after every update
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FrameOutput {
+    pub frame: FrameBuffer,
+    /// Whether the buzzer should be sounding
+    pub audio: bool,
+    /// The XO-CHIP pattern it should play instead of its tone, if any
+    pub pattern: Option<Pattern>,
+    /// How many updates there have been, so a receiver can tell it missed some
+    pub updates: u64,
+}
+
+/// A [`Frontend`] made of the two channels
+struct Channels {
+    input: mpsc::UnboundedReceiver<InputEvents>,
+    output: watch::Sender<FrameOutput>,
+    // set once the input side hangs up or nobody's watching the output
+    closed: bool,
+}
+
+impl Frontend for Channels {
+    fn render(&mut self, fb: &FrameBuffer) {
+        self.output.send_modify(|output| {
+            output.frame.clone_from(fb);
+            output.updates += 1;
+        });
+        self.closed |= self.output.is_closed();
+    }
+
+    /// Everything sent since the last poll, as one lot of events
+    fn poll_input(&mut self) -> InputEvents {
+        let mut events = InputEvents { quit: self.closed, ..InputEvents::default() };
+        loop {
+            match self.input.try_recv() {
+                Ok(sent) => {
+                    events.keys.extend(sent.keys);
+                    events.macros.extend(sent.macros);
+                    events.quit |= sent.quit;
+                }
+                Err(TryRecvError::Empty) => return events,
+                Err(TryRecvError::Disconnected) => {
+                    events.quit = true;
+                    return events;
+                }
+            }
+        }
+    }
+
+    fn audio(&mut self, on: bool) {
+        self.output.send_if_modified(|output| std::mem::replace(&mut output.audio, on) != on);
+    }
+
+    fn audio_pattern(&mut self, pattern: Option<Pattern>) {
+        self.output.send_if_modified(|output| std::mem::replace(&mut output.pattern, pattern) != pattern);
+    }
+
+    fn now(&self) -> Instant {
+        time::Instant::now().into_std()
+    }
+}
+
+impl Emulator {
+    /// Runs in real time until the program stops, [`InputEvents::quit`]
+    /// comes in, the `input` sender is dropped or every `output` receiver
+    /// is, yielding to the executor between frames
+    ///
+    /// Time is tokio's, so a paused runtime's clock paces it too.
+    pub async fn run_async(
+        &mut self,
+        input: mpsc::UnboundedReceiver<InputEvents>,
+        output: watch::Sender<FrameOutput>,
+    ) -> Exit {
+        let mut channels = Channels { input, output, closed: false };
+        let mut runner = Runner::new();
+        let mut frames = time::interval(Duration::from_secs_f64(1.0 / TIMER_HZ));
+        // the runner catches up on missed frames itself
+        frames.set_missed_tick_behavior(MissedTickBehavior::Skip);
+        loop {
+            frames.tick().await;
+            if let Some(exit) = runner.update(&mut channels, self) {
+                return exit;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // LD V0, 5; SKP V0; JP 0x202; terminate
+    const ROM: [u8; 8] = [0x60, 0x05, 0xE0, 0x9E, 0x12, 0x02, 0x00, 0x00];
+
+    fn emulator() -> Emulator {
+        let mut emulator = Emulator::new(crate::CpuBuilder::new().build());
+        emulator.load_rom(&ROM).unwrap();
+        emulator
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn keys_sent_reach_the_program() {
+        let (input, input_events) = mpsc::unbounded_channel();
+        let (frame_output, mut frames) = watch::channel(FrameOutput::default());
+        let running = tokio::spawn(async move { emulator().run_async(input_events, frame_output).await });
+
+        frames.changed().await.unwrap();
+        assert!(!running.is_finished());
+        input.send(InputEvents { keys: vec![(0x5, true)], ..InputEvents::default() }).unwrap();
+        assert_eq!(running.await.unwrap(), Exit::Halted);
+        assert!(frames.borrow().updates > 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn hanging_up_quits() {
+        let (input, input_events) = mpsc::unbounded_channel();
+        let (frame_output, frames) = watch::channel(FrameOutput::default());
+        drop(input);
+        assert_eq!(emulator().run_async(input_events, frame_output).await, Exit::Quit);
+
+        let (_input, input_events) = mpsc::unbounded_channel();
+        let (frame_output, frames_too) = watch::channel(FrameOutput::default());
+        drop((frames, frames_too));
+        assert_eq!(emulator().run_async(input_events, frame_output).await, Exit::Quit);
+    }
+}
@@ -0,0 +1,436 @@
+//! Sound output for the CHIP-8 buzzer
+//!
+//! CHIP-8 has a single tone that plays for as long as the sound timer is
+//! non-zero. The emulator doesn't make any noise itself; frontends implement
+//! [`AudioSink`] for whatever audio library they use, and a [`Tone`] turns
+//! the CPU's sound timer into `start_tone`/`stop_tone` calls.
+//!
+//! # Example
+//!
+//! ```
+//! use chip_8::audio::{AudioSink, Tone};
+//! use chip_8::CpuBuilder;
+//!
+//! struct Speaker;
+//!
+//! impl AudioSink for Speaker {
+//!     fn start_tone(&mut self) { /* start the beep */ }
+//!     fn stop_tone(&mut self) { /* stop the beep */ }
+//! }
+//!
+//! let mut cpu = CpuBuilder::new().build();
+//! let mut tone = Tone::new(Speaker);
+//!
+//! // once per 60Hz frame
+//! cpu.tick_timers();
+//! tone.update(cpu.sound_active());
+//! ```
+//!
+//! XO-CHIP programs can swap the tone for a [`Pattern`] of their own, 128
+//! one bit samples played at a pitch the program sets. Sinks that want to
+//! play them implement [`AudioSink::set_pattern`], and a [`Resampler`]
+//! turns the pattern into samples at the host's rate.
+//!
+//! With the `audio-cpal` feature, [`CpalBeeper`] is a ready-made sink that
+//! plays a square wave, or the program's pattern, through the default
+//! output device.
+
+#[cfg(feature = "audio-cpal")]
+mod beeper;
+
+#[cfg(feature = "audio-cpal")]
+pub use beeper::CpalBeeper;
+
+use crate::Byte;
+
+/// The pitch XO-CHIP programs start with, which plays a pattern at 4000Hz
+pub const DEFAULT_PITCH: Byte = 64;
+
+/// How the player wants the buzzer to sound, whatever the program does
+///
+/// # Examples
+/// ```
+/// use chip_8::audio::AudioSettings;
+///
+/// let settings = AudioSettings { volume: 50, ..AudioSettings::default() };
+/// assert_eq!(settings.gain(), 0.5);
+/// assert_eq!(AudioSettings { muted: true, ..settings }.gain(), 0.0);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct AudioSettings {
+    /// The tone's pitch in Hz, which XO-CHIP patterns ignore
+    pub frequency: u32,
+    /// From 0 to 100
+    pub volume: u8,
+    /// Keeps the buzzer quiet without losing the volume
+    pub muted: bool,
+}
+
+impl AudioSettings {
+    /// An A, 440Hz
+    pub const DEFAULT_FREQUENCY: u32 = 440;
+
+    /// A quarter of full volume
+    pub const DEFAULT_VOLUME: u8 = 25;
+
+    /// How loud to play, from 0 to 1, taking the mute into account
+    pub fn gain(&self) -> f32 {
+        if self.muted {
+            0.0
+        } else {
+            self.volume.min(100) as f32 / 100.0
+        }
+    }
+}
+
+impl Default for AudioSettings {
+    fn default() -> Self {
+        AudioSettings {
+            frequency: AudioSettings::DEFAULT_FREQUENCY,
+            volume: AudioSettings::DEFAULT_VOLUME,
+            muted: false,
+        }
+    }
+}
+
+/// An XO-CHIP audio pattern: 128 one bit samples, loaded by F002 from 16
+/// bytes at I with the first sample in the top bit, looping for as long as
+/// the sound timer runs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Pattern {
+    pub bits: [Byte; 16],
+    /// Set by FX3A, each 48 up doubling the playback rate
+    pub pitch: Byte,
+}
+
+impl Default for Pattern {
+    /// Silence at the default pitch
+    fn default() -> Self {
+        Pattern { bits: [0; 16], pitch: DEFAULT_PITCH }
+    }
+}
+
+impl Pattern {
+    /// How many samples a second the pattern plays at,
+    /// `4000 * 2^((pitch - 64) / 48)`
+    ///
+    /// # Examples
+    /// ```
+    /// use chip_8::audio::Pattern;
+    ///
+    /// let pattern = Pattern { bits: [0xF0; 16], pitch: 112 };
+    /// assert_eq!(pattern.sample_rate(), 8000.0);
+    /// ```
+    pub fn sample_rate(&self) -> f64 {
+        4000.0 * 2f64.powf((self.pitch as f64 - 64.0) / 48.0)
+    }
+
+    /// Whether sample `index` of the 128 is high, wrapping around past the
+    /// end
+    pub fn bit(&self, index: usize) -> bool {
+        let index = index % 128;
+        self.bits[index / 8] & 0x80 >> (index % 8) != 0
+    }
+}
+
+/// Turns a [`Pattern`] into samples at the host's sample rate, picking up
+/// where it left off from one buffer to the next
+///
+/// Each output sample is the average of the pattern over the time it
+/// covers, between -1 and 1, so patterns played faster than the host's rate
+/// are smoothed rather than skipping bits.
+///
+/// # Examples
+/// ```
+/// use chip_8::audio::{Pattern, Resampler};
+///
+/// // eight high samples then all low, at 4000Hz
+/// let mut bits = [0; 16];
+/// bits[0] = 0xFF;
+/// let pattern = Pattern { bits, ..Pattern::default() };
+/// let mut resampler = Resampler::new(16000);
+///
+/// let mut samples = [0.0; 64];
+/// resampler.fill(&pattern, &mut samples);
+/// // every bit lasts four samples at 16kHz, so a byte lasts 32
+/// assert!(samples[..32].iter().all(|&sample| sample == 1.0));
+/// assert!(samples[32..].iter().all(|&sample| sample == -1.0));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Resampler {
+    host_rate: f64,
+    // how far through the pattern playback is, in samples of the pattern
+    position: f64,
+}
+
+impl Resampler {
+    /// Makes a resampler for a host playing `host_rate` samples a second,
+    /// starting from the beginning of the pattern
+    ///
+    /// # Panics
+    ///
+    /// Panics if `host_rate` is 0
+    pub fn new(host_rate: u32) -> Resampler {
+        assert!(host_rate > 0, "the host's sample rate can't be 0");
+        Resampler { host_rate: host_rate as f64, position: 0.0 }
+    }
+
+    /// Goes back to the beginning of the pattern, for when a new one starts
+    pub fn restart(&mut self) {
+        self.position = 0.0;
+    }
+
+    /// The next sample of `pattern` at the host's rate
+    pub fn next_sample(&mut self, pattern: &Pattern) -> f32 {
+        let step = pattern.sample_rate() / self.host_rate;
+        let end = self.position + step;
+
+        let mut sum = 0.0;
+        let mut at = self.position;
+        while at < end {
+            let until = (at.floor() + 1.0).min(end);
+            let level = if pattern.bit(at as usize) { 1.0 } else { -1.0 };
+            sum += (until - at) * level;
+            at = until;
+        }
+
+        self.position = end % 128.0;
+        (sum / step) as f32
+    }
+
+    /// Fills `samples` with the pattern, one host sample each
+    pub fn fill(&mut self, pattern: &Pattern, samples: &mut [f32]) {
+        for sample in samples {
+            *sample = self.next_sample(pattern);
+        }
+    }
+}
+
+/// Something that can play (and stop playing) the CHIP-8 tone
+pub trait AudioSink {
+    /// Starts playing the tone
+    fn start_tone(&mut self);
+
+    /// Stops playing the tone
+    fn stop_tone(&mut self);
+
+    /// Plays `pattern` in place of the tone from now on, or goes back to
+    /// the tone if it's `None`
+    ///
+    /// Does nothing unless the sink can play XO-CHIP patterns, in which
+    /// case the program still gets its usual tone.
+    fn set_pattern(&mut self, pattern: Option<Pattern>) {
+        let _ = pattern;
+    }
+
+    /// Changes the tone's frequency and volume from now on
+    ///
+    /// The mute is left to [`Tone`], which stops the sink instead; sinks
+    /// that can't change how they sound can leave this out.
+    fn configure(&mut self, settings: &AudioSettings) {
+        let _ = settings;
+    }
+}
+
+impl<S: AudioSink + ?Sized> AudioSink for Box<S> {
+    fn start_tone(&mut self) {
+        (**self).start_tone();
+    }
+
+    fn stop_tone(&mut self) {
+        (**self).stop_tone();
+    }
+
+    fn set_pattern(&mut self, pattern: Option<Pattern>) {
+        (**self).set_pattern(pattern);
+    }
+
+    fn configure(&mut self, settings: &AudioSettings) {
+        (**self).configure(settings);
+    }
+}
+
+/// Tracks whether the tone is playing, only telling the sink when that changes
+pub struct Tone<S: AudioSink> {
+    sink: S,
+    playing: bool,
+    pattern: Option<Pattern>,
+    settings: AudioSettings,
+}
+
+impl<S: AudioSink> Tone<S> {
+    /// Makes a new, silent Tone that drives the given sink
+    pub fn new(sink: S) -> Tone<S> {
+        Tone {
+            sink,
+            playing: false,
+            pattern: None,
+            settings: AudioSettings::default(),
+        }
+    }
+
+    /// Starts or stops the tone to match `active`, keeping it stopped
+    /// while muted
+    ///
+    /// Usually called with `cpu.sound_active()` after every timer tick
+    pub fn update(&mut self, active: bool) {
+        let active = active && !self.settings.muted;
+        if active && !self.playing {
+            self.sink.start_tone();
+        } else if !active && self.playing {
+            self.sink.stop_tone();
+        }
+
+        self.playing = active;
+    }
+
+    /// Switches the sink to `pattern`, or back to the tone with `None`
+    ///
+    /// Usually called with `cpu.audio_pattern()` alongside `update`
+    pub fn update_pattern(&mut self, pattern: Option<Pattern>) {
+        if pattern != self.pattern {
+            self.sink.set_pattern(pattern);
+            self.pattern = pattern;
+        }
+    }
+
+    /// Passes new settings on to the sink if they've changed, stopping the
+    /// tone straight away if they mute it
+    pub fn configure(&mut self, settings: AudioSettings) {
+        if settings == self.settings {
+            return;
+        }
+        self.sink.configure(&settings);
+        self.settings = settings;
+        if settings.muted && self.playing {
+            self.sink.stop_tone();
+            self.playing = false;
+        }
+    }
+
+    /// The settings last passed to `configure`
+    pub fn settings(&self) -> AudioSettings {
+        self.settings
+    }
+
+    /// Whether the tone is currently playing
+    pub fn is_playing(&self) -> bool {
+        self.playing
+    }
+
+    /// A convenience method for retrieving the sink, e.g. to change its settings
+    pub fn sink(&mut self) -> &mut S {
+        &mut self.sink
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct RecordingSink {
+        calls: Vec<&'static str>,
+    }
+
+    impl AudioSink for RecordingSink {
+        fn start_tone(&mut self) {
+            self.calls.push("start");
+        }
+
+        fn stop_tone(&mut self) {
+            self.calls.push("stop");
+        }
+
+        fn set_pattern(&mut self, pattern: Option<Pattern>) {
+            self.calls.push(if pattern.is_some() { "pattern" } else { "tone" });
+        }
+
+        fn configure(&mut self, _settings: &AudioSettings) {
+            self.calls.push("configure");
+        }
+    }
+
+    #[test]
+    fn update_only_notifies_sink_on_changes() {
+        let mut tone = Tone::new(RecordingSink::default());
+        tone.update(false);
+        tone.update(true);
+        tone.update(true);
+        assert!(tone.is_playing());
+
+        tone.update(false);
+        tone.update(false);
+        assert!(!tone.is_playing());
+
+        assert_eq!(tone.sink().calls, vec!["start", "stop"]);
+    }
+
+    #[test]
+    fn muting_stops_the_tone_until_unmuted() {
+        let mut tone = Tone::new(RecordingSink::default());
+        let muted = AudioSettings { muted: true, ..AudioSettings::default() };
+        tone.update(true);
+        tone.configure(muted);
+        tone.configure(muted);
+        assert!(!tone.is_playing());
+
+        tone.update(true);
+        assert!(!tone.is_playing());
+
+        tone.configure(AudioSettings::default());
+        tone.update(true);
+        assert!(tone.is_playing());
+        assert_eq!(tone.sink().calls, vec!["start", "configure", "stop", "configure", "start"]);
+    }
+
+    #[test]
+    fn update_pattern_only_notifies_sink_on_changes() {
+        let mut tone = Tone::new(RecordingSink::default());
+        let pattern = Pattern { bits: [0xAA; 16], pitch: 80 };
+        tone.update_pattern(None);
+        tone.update_pattern(Some(pattern));
+        tone.update_pattern(Some(pattern));
+        tone.update_pattern(Some(Pattern { pitch: 81, ..pattern }));
+        tone.update_pattern(None);
+
+        assert_eq!(tone.sink().calls, vec!["pattern", "pattern", "tone"]);
+    }
+
+    #[test]
+    fn pitch_doubles_the_rate_every_48() {
+        let at = |pitch| Pattern { pitch, ..Pattern::default() }.sample_rate();
+        assert_eq!(at(DEFAULT_PITCH), 4000.0);
+        assert_eq!(at(16), 2000.0);
+        assert!((at(255) - 63_082.4).abs() < 0.1);
+    }
+
+    #[test]
+    fn resampler_averages_bits_faster_than_the_host() {
+        // one high sample then seven low, over and over, at 8kHz
+        let pattern = Pattern { bits: [0x80; 16], pitch: 112 };
+        let mut resampler = Resampler::new(4000);
+
+        let mut samples = [0.0; 8];
+        resampler.fill(&pattern, &mut samples);
+        assert_eq!(samples, [0.0, -1.0, -1.0, -1.0, 0.0, -1.0, -1.0, -1.0]);
+    }
+
+    #[test]
+    fn resampler_carries_its_position_between_buffers() {
+        let mut bits = [0; 16];
+        bits[0] = 0x0F;
+        let pattern = Pattern { bits, ..Pattern::default() };
+
+        let mut whole = Resampler::new(44_100);
+        let mut expected = [0.0; 300];
+        whole.fill(&pattern, &mut expected);
+
+        let mut split = Resampler::new(44_100);
+        let mut samples = [0.0; 300];
+        let (first, second) = samples.split_at_mut(123);
+        split.fill(&pattern, first);
+        split.fill(&pattern, second);
+        assert_eq!(samples, expected);
+    }
+}
@@ -0,0 +1,138 @@
+use std::error::Error;
+use std::sync::{Arc, Mutex};
+
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use cpal::{FromSample, SizedSample};
+
+use super::{AudioSettings, AudioSink, Pattern, Resampler};
+
+/// Settings shared between the beeper and the audio thread
+struct Settings {
+    playing: bool,
+    frequency: f32,
+    volume: f32,
+    // an XO-CHIP pattern to play in place of the square wave
+    pattern: Option<Pattern>,
+}
+
+/// Plays the CHIP-8 tone as a square wave on the default output device, or
+/// the program's XO-CHIP audio pattern once it's loaded one
+///
+/// Only available with the `audio-cpal` feature
+pub struct CpalBeeper {
+    settings: Arc<Mutex<Settings>>,
+    // the stream stops playing when dropped, so hold on to it
+    _stream: cpal::Stream,
+}
+
+impl CpalBeeper {
+    /// The frequency used by `new`, in Hz
+    pub const DEFAULT_FREQUENCY: f32 = AudioSettings::DEFAULT_FREQUENCY as f32;
+
+    /// The volume used by `new`, between 0 and 1
+    pub const DEFAULT_VOLUME: f32 = AudioSettings::DEFAULT_VOLUME as f32 / 100.0;
+
+    /// Opens the default output device with a 440Hz tone at a quarter volume
+    ///
+    /// Fails if there is no output device or it can't be opened
+    pub fn new() -> Result<CpalBeeper, Box<dyn Error>> {
+        let host = cpal::default_host();
+        let device = host
+            .default_output_device()
+            .ok_or("no audio output device available")?;
+        let config = device.default_output_config()?;
+
+        let settings = Arc::new(Mutex::new(Settings {
+            playing: false,
+            frequency: CpalBeeper::DEFAULT_FREQUENCY,
+            volume: CpalBeeper::DEFAULT_VOLUME,
+            pattern: None,
+        }));
+
+        let stream = match config.sample_format() {
+            cpal::SampleFormat::F32 => build_stream::<f32>(&device, &config.into(), &settings)?,
+            cpal::SampleFormat::I16 => build_stream::<i16>(&device, &config.into(), &settings)?,
+            cpal::SampleFormat::U16 => build_stream::<u16>(&device, &config.into(), &settings)?,
+            format => return Err(format!("unsupported sample format {}", format).into()),
+        };
+        stream.play()?;
+
+        Ok(CpalBeeper {
+            settings,
+            _stream: stream,
+        })
+    }
+
+    /// Sets the pitch of the tone in Hz
+    pub fn set_frequency(&mut self, frequency: f32) {
+        self.settings.lock().unwrap().frequency = frequency;
+    }
+
+    /// Sets the volume of the tone, clamped between 0 (silent) and 1
+    pub fn set_volume(&mut self, volume: f32) {
+        self.settings.lock().unwrap().volume = volume.clamp(0.0, 1.0);
+    }
+}
+
+impl AudioSink for CpalBeeper {
+    fn start_tone(&mut self) {
+        self.settings.lock().unwrap().playing = true;
+    }
+
+    fn stop_tone(&mut self) {
+        self.settings.lock().unwrap().playing = false;
+    }
+
+    fn set_pattern(&mut self, pattern: Option<Pattern>) {
+        self.settings.lock().unwrap().pattern = pattern;
+    }
+
+    fn configure(&mut self, settings: &AudioSettings) {
+        self.set_frequency(settings.frequency as f32);
+        self.set_volume(settings.gain());
+    }
+}
+
+/// Opens an output stream that writes a square wave, or the pattern, whenever
+/// the tone is playing
+fn build_stream<T>(
+    device: &cpal::Device,
+    config: &cpal::StreamConfig,
+    settings: &Arc<Mutex<Settings>>,
+) -> Result<cpal::Stream, cpal::BuildStreamError>
+where
+    T: SizedSample + FromSample<f32>,
+{
+    let sample_rate = config.sample_rate.0 as f32;
+    let channels = config.channels as usize;
+    let settings = Arc::clone(settings);
+    // position within the current wave period, from 0 up to 1
+    let mut phase = 0.0;
+    let mut resampler = Resampler::new(config.sample_rate.0);
+
+    device.build_output_stream(
+        config,
+        move |data: &mut [T], _: &cpal::OutputCallbackInfo| {
+            let settings = settings.lock().unwrap();
+            for frame in data.chunks_mut(channels) {
+                let value = if !settings.playing {
+                    0.0
+                } else if let Some(pattern) = &settings.pattern {
+                    resampler.next_sample(pattern) * settings.volume
+                } else if phase < 0.5 {
+                    settings.volume
+                } else {
+                    -settings.volume
+                };
+
+                phase = (phase + settings.frequency / sample_rate) % 1.0;
+
+                for sample in frame.iter_mut() {
+                    *sample = T::from_sample(value);
+                }
+            }
+        },
+        |err| eprintln!("audio stream error: {}", err),
+        None,
+    )
+}
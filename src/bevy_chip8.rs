@@ -0,0 +1,229 @@
+//! A Bevy plugin that runs a CHIP-8 machine inside a game
+//!
+//! [`Chip8Plugin`] adds systems that, every update, press the CHIP-8 keys
+//! the [`KeyMap`] binds to the keyboard keys just pressed or let go, run
+//! however many frames the time since the last update calls for, and copy
+//! the screen into an [`Image`], which can go on a sprite or on the
+//! material of an arcade cabinet's screen. They do nothing until there's a
+//! [`Chip8`] resource to run.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use bevy_app::{App, Startup};
+//! use bevy_asset::Assets;
+//! use bevy_ecs::prelude::*;
+//! use bevy_image::Image;
+//! use chip_8::bevy_chip8::{Chip8, Chip8Plugin};
+//! use chip_8::{Emulator, KeyMap};
+//!
+//! fn insert_machine(mut commands: Commands, mut images: ResMut<Assets<Image>>) {
+//!     let mut emulator = Emulator::default();
+//!     emulator.load_rom(&std::fs::read("roms/pong.ch8").unwrap()).unwrap();
+//!     let screen = images.add(Chip8::screen_image());
+//!     commands.insert_resource(Chip8::new(emulator, KeyMap::qwerty(), screen));
+//! }
+//!
+//! // along with Bevy's default plugins, for the window, input and time
+//! App::new().add_plugins(Chip8Plugin).add_systems(Startup, insert_machine).run();
+//! ```
+
+use std::sync::Mutex;
+
+use bevy_app::{App, Plugin, Update};
+use bevy_asset::{Assets, Handle, RenderAssetUsages};
+use bevy_ecs::prelude::*;
+use bevy_image::{Image, ImageSampler};
+use bevy_input::keyboard::KeyCode;
+use bevy_input::ButtonInput;
+use bevy_time::Time;
+use wgpu_types::{Extent3d, TextureDimension, TextureFormat};
+
+use crate::screen::{Palette, HEIGHT, WIDTH};
+use crate::{keymap, Emulator, KeyMap, StopReason};
+
+/// Adds the systems that run a [`Chip8`] resource
+pub struct Chip8Plugin;
+
+impl Plugin for Chip8Plugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, (map_keyboard, step, update_screen).chain().run_if(resource_exists::<Chip8>));
+    }
+}
+
+/// A CHIP-8 machine, the keys that play it and the image it's shown on
+#[derive(Resource)]
+pub struct Chip8 {
+    // resources have to be shareable between threads and observers
+    // aren't, so the emulator is only ever reached through `get_mut`
+    emulator: Mutex<Emulator>,
+    pub keymap: KeyMap,
+    /// The image [`update_screen`] draws into, made by
+    /// [`screen_image`](Chip8::screen_image)
+    pub screen: Handle<Image>,
+    pub palette: Palette,
+    /// Stops frames running, leaving the keys to be passed in
+    pub paused: bool,
+    /// Why the program stopped, after which frames stop running until it's
+    /// cleared
+    pub stopped: Option<StopReason>,
+}
+
+impl Chip8 {
+    pub fn new(emulator: Emulator, keymap: KeyMap, screen: Handle<Image>) -> Chip8 {
+        Chip8 {
+            emulator: Mutex::new(emulator),
+            keymap,
+            screen,
+            palette: Palette::default(),
+            paused: false,
+            stopped: None,
+        }
+    }
+
+    /// A blank image the size of the screen, sampled without smoothing so
+    /// the pixels stay square however far it's scaled up
+    pub fn screen_image() -> Image {
+        let size = Extent3d { width: WIDTH as u32, height: HEIGHT as u32, depth_or_array_layers: 1 };
+        let mut image = Image::new_fill(
+            size,
+            TextureDimension::D2,
+            &[0, 0, 0, 0xFF],
+            TextureFormat::Rgba8UnormSrgb,
+            RenderAssetUsages::default(),
+        );
+        image.sampler = ImageSampler::nearest();
+        image
+    }
+
+    /// The machine itself, for loading ROMs and anything else
+    pub fn emulator(&mut self) -> &mut Emulator {
+        self.emulator.get_mut().unwrap()
+    }
+}
+
+/// Presses and lets go of the CHIP-8 keys bound to the keyboard keys that
+/// changed this update
+///
+/// Bevy's [`KeyCode`]s are where keys are, so keys are always matched as
+/// [`KeyInput::Physical`](crate::keymap::KeyInput::Physical).
+pub fn map_keyboard(keys: Res<ButtonInput<KeyCode>>, mut chip8: ResMut<Chip8>) {
+    let chip8 = &mut *chip8;
+    let emulator = chip8.emulator.get_mut().unwrap();
+    for code in keys.get_just_pressed() {
+        if let Some(key) = chip8.keymap.get(&key_name(*code)) {
+            emulator.key_down(key);
+        }
+    }
+    for code in keys.get_just_released() {
+        if let Some(key) = chip8.keymap.get(&key_name(*code)) {
+            emulator.key_up(key);
+        }
+    }
+}
+
+/// Runs the frames due since the last update
+pub fn step(time: Res<Time>, mut chip8: ResMut<Chip8>) {
+    if chip8.paused || chip8.stopped.is_some() {
+        return;
+    }
+    let emulator = chip8.emulator();
+    for _ in 0..emulator.frames_due(time.delta_secs_f64()) {
+        if let reason @ (StopReason::Halted | StopReason::Error(_)) = emulator.run_frame() {
+            chip8.stopped = Some(reason);
+            return;
+        }
+    }
+}
+
+/// Copies the screen into the [`Chip8::screen`] image, in the palette's
+/// colours or MegaChip's own, resizing the image when the screen switches
+/// resolution
+pub fn update_screen(mut chip8: ResMut<Chip8>, mut images: ResMut<Assets<Image>>) {
+    let Palette { on, off } = chip8.palette;
+    let screen = chip8.screen.clone();
+    let Some(image) = images.get_mut(&screen) else {
+        return;
+    };
+    let frame = chip8.emulator().frame();
+    let size = Extent3d { width: frame.width() as u32, height: frame.height() as u32, depth_or_array_layers: 1 };
+    if image.texture_descriptor.size != size {
+        image.resize(size);
+    }
+    let data = &mut image.data;
+    data.clear();
+    for (index, &lit) in frame.pixels().iter().enumerate() {
+        let [red, green, blue] = match frame.color(index % frame.width(), index / frame.width()) {
+            Some(color) => color,
+            None if lit => on,
+            None => off,
+        };
+        data.extend_from_slice(&[red, green, blue, 0xFF]);
+    }
+}
+
+/// The name the [`KeyMap`] knows a key by, "X" for the X key and "Up" for
+/// the up arrow, as the windowed frontends name them
+fn key_name(code: KeyCode) -> String {
+    keymap::code_name(&format!("{:?}", code))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    // LD V0, 5; SKP V0; JP 0x202; LD I, 0 (the font's 0); DRW V0, V0, 5; terminate
+    const ROM: [u8; 12] = [0x60, 0x05, 0xE0, 0x9E, 0x12, 0x02, 0xA0, 0x00, 0xD0, 0x05, 0x00, 0x00];
+
+    fn app() -> App {
+        let mut app = App::new();
+        app.add_plugins(Chip8Plugin);
+        app.init_resource::<Time>();
+        app.init_resource::<ButtonInput<KeyCode>>();
+        app.init_resource::<Assets<Image>>();
+
+        let mut emulator = Emulator::new(crate::CpuBuilder::new().build());
+        emulator.load_rom(&ROM).unwrap();
+        let screen = app.world_mut().resource_mut::<Assets<Image>>().add(Chip8::screen_image());
+        app.insert_resource(Chip8::new(emulator, KeyMap::qwerty(), screen));
+        app
+    }
+
+    /// Runs an update a frame's time after the last
+    fn update(app: &mut App) {
+        app.world_mut().resource_mut::<Time>().advance_by(Duration::from_secs_f64(1.0 / 60.0));
+        app.update();
+    }
+
+    #[test]
+    fn keys_play_the_machine_and_the_screen_follows() {
+        let mut app = app();
+        update(&mut app);
+        assert_eq!(app.world().resource::<Chip8>().stopped, None);
+
+        // W is 5 on the QWERTY layout
+        app.world_mut().resource_mut::<ButtonInput<KeyCode>>().press(KeyCode::KeyW);
+        update(&mut app);
+        assert_eq!(app.world().resource::<Chip8>().stopped, Some(StopReason::Halted));
+
+        let chip8 = app.world().resource::<Chip8>();
+        let image = app.world().resource::<Assets<Image>>().get(&chip8.screen).unwrap();
+        let data = &image.data;
+        assert_eq!(data.len(), WIDTH * HEIGHT * 4);
+        // the 0 is drawn at V0, 5, in green on black
+        let corner = (5 * WIDTH + 5) * 4;
+        assert_eq!(data[corner..corner + 4], [0x00, 0xFF, 0x00, 0xFF]);
+        assert_eq!(data[..4], [0x00, 0x00, 0x00, 0xFF]);
+    }
+
+    #[test]
+    fn key_names_match_the_windowed_frontends() {
+        assert_eq!(key_name(KeyCode::KeyX), "X");
+        assert_eq!(key_name(KeyCode::Digit1), "1");
+        assert_eq!(key_name(KeyCode::ArrowUp), "Up");
+        assert_eq!(key_name(KeyCode::Space), "Space");
+        assert_eq!(key_name(KeyCode::BracketLeft), "LeftBracket");
+    }
+}
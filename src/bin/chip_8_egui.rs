@@ -0,0 +1,449 @@
+//! A frontend with debugger panels beside the game, for working on ROMs
+//!
+//! `chip_8_egui [ROM]` runs the ROM with windows for the registers, the
+//! disassembly around the program counter, memory, the stack and the
+//! breakpoints, each of which can be moved, collapsed or hidden. Clicking a
+//! line of disassembly toggles a breakpoint on it, and the memory panel can
+//! write bytes anywhere from the program start up.
+//!
+//! `chip_8_egui ROM KEYMAP` takes a keymap preset or file as well, otherwise
+//! the ROM's own preset from the database is used. The keypad panel draws
+//! the keys the keymap's layout shows, for playing on a touchscreen.
+
+use std::env;
+use std::fs;
+use std::process;
+use std::time::Instant;
+
+use chip_8::instruction::Instruction;
+use chip_8::keymap::KeyInput;
+use chip_8::memory::{MemoryView, ROW_BYTES};
+use chip_8::timing::Clock;
+use chip_8::{rom_db, Cpu, CpuBuilder, CpuStatus, KeyMap};
+use eframe::egui;
+
+/// How many instructions the disassembly shows either side of the program counter
+const DISASSEMBLY_CONTEXT: usize = 8;
+
+/// How big the keypad panel's buttons are, enough for a finger
+const KEYPAD_BUTTON: f32 = 56.0;
+
+/// Which panels are open
+#[derive(Clone, Copy)]
+struct Panels {
+    registers: bool,
+    disassembly: bool,
+    memory: bool,
+    stack: bool,
+    breakpoints: bool,
+    keypad: bool,
+}
+
+/// The game and the debugger around it
+struct DebugApp {
+    cpu: Cpu,
+    keymap: KeyMap,
+    clock: Clock,
+    last_frame: Instant,
+    running: bool,
+    // the program terminated or failed, so only a reset gets it going again
+    stopped: bool,
+    // lets a resume step off the breakpoint it paused on
+    resuming: bool,
+    breakpoints: Vec<usize>,
+    // the address being typed into the breakpoints panel
+    new_breakpoint: String,
+    // the address and bytes being typed into the memory panel
+    edit_addr: String,
+    edit_bytes: String,
+    // why the program last paused, shown in the toolbar
+    status: String,
+    panels: Panels,
+    // the keypad panel's buttons being held, so only changes reach the CPU
+    keypad_held: [bool; 16],
+}
+
+impl DebugApp {
+    fn new(cpu: Cpu, keymap: KeyMap) -> DebugApp {
+        DebugApp {
+            cpu,
+            keymap,
+            clock: Clock::default(),
+            last_frame: Instant::now(),
+            running: true,
+            stopped: false,
+            resuming: false,
+            breakpoints: Vec::new(),
+            new_breakpoint: String::new(),
+            edit_addr: String::new(),
+            edit_bytes: String::new(),
+            status: String::from("Running"),
+            panels: Panels {
+                registers: true,
+                disassembly: true,
+                memory: false,
+                stack: true,
+                breakpoints: true,
+                keypad: true,
+            },
+            keypad_held: [false; 16],
+        }
+    }
+
+    /// Runs a frame's worth of instructions then ticks the timers, pausing
+    /// before any instruction with a breakpoint
+    fn run_frame(&mut self) {
+        for _ in 0..self.clock.frame_instructions() {
+            let pc = self.cpu.state().pc as usize;
+            if !self.resuming && self.breakpoints.contains(&pc) {
+                self.pause(format!("Breakpoint at 0x{:03X}", pc));
+                return;
+            }
+            self.resuming = false;
+
+            if !self.step() || self.cpu.waiting_for_key() || self.cpu.waiting_for_vblank() {
+                break;
+            }
+        }
+
+        if !self.stopped {
+            self.cpu.tick_timers();
+        }
+    }
+
+    /// Runs one instruction, returning false if the program has stopped
+    fn step(&mut self) -> bool {
+        match self.cpu.step() {
+            Ok(CpuStatus::Halted) => self.stop(String::from("Program terminated")),
+            Err(err) => self.stop(err.to_string()),
+            Ok(_) => return true,
+        }
+        false
+    }
+
+    /// Runs one frame while paused, stepping off any breakpoint it's on
+    /// and stopping at the next
+    fn step_frame(&mut self) {
+        self.resuming = true;
+        self.status = String::from("Paused");
+        self.run_frame();
+    }
+
+    fn pause(&mut self, status: String) {
+        self.running = false;
+        self.status = status;
+    }
+
+    fn stop(&mut self, status: String) {
+        self.stopped = true;
+        self.pause(status);
+    }
+
+    fn resume(&mut self) {
+        self.running = true;
+        self.resuming = true;
+        self.last_frame = Instant::now();
+        self.status = String::from("Running");
+    }
+
+    fn reset(&mut self) {
+        self.cpu.reset();
+        self.stopped = false;
+        self.resume();
+    }
+
+    /// Passes key presses and releases on to the keypad, unless a text box
+    /// has the keyboard
+    fn handle_keys(&mut self, ctx: &egui::Context) {
+        if ctx.wants_keyboard_input() {
+            return;
+        }
+
+        let events = ctx.input(|input| input.events.clone());
+        for event in events {
+            if let egui::Event::Key { key, physical_key, pressed, repeat: false, .. } = event {
+                let key = match self.keymap.input() {
+                    KeyInput::Physical => physical_key.unwrap_or(key),
+                    KeyInput::Character => key,
+                };
+                if let Some(key) = self.keymap.get(key.name()) {
+                    self.cpu.set_key(key, pressed);
+                }
+            }
+        }
+    }
+
+    fn toolbar(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.add_enabled_ui(!self.stopped, |ui| {
+                if self.running {
+                    if ui.button("Pause").clicked() {
+                        self.pause(String::from("Paused"));
+                    }
+                } else if ui.button("Run").clicked() {
+                    self.resume();
+                }
+                if ui.add_enabled(!self.running, egui::Button::new("Step")).clicked() && self.step() {
+                    self.status = String::from("Paused");
+                }
+                if ui.add_enabled(!self.running, egui::Button::new("Frame")).clicked() {
+                    self.step_frame();
+                }
+            });
+            if ui.button("Reset").clicked() {
+                self.reset();
+            }
+
+            ui.separator();
+            ui.checkbox(&mut self.panels.registers, "Registers");
+            ui.checkbox(&mut self.panels.disassembly, "Disassembly");
+            ui.checkbox(&mut self.panels.memory, "Memory");
+            ui.checkbox(&mut self.panels.stack, "Stack");
+            ui.checkbox(&mut self.panels.breakpoints, "Breakpoints");
+            ui.checkbox(&mut self.panels.keypad, "Keypad");
+
+            ui.separator();
+            ui.label(&self.status);
+        });
+    }
+
+    /// Draws the screen as large as fits, keeping square pixels
+    fn screen(&self, ui: &mut egui::Ui) {
+        let display = self.cpu.display();
+        let (width, height) = (display.width() as f32, display.height() as f32);
+        let available = ui.available_size();
+        let scale = (available.x / width).min(available.y / height).max(1.0);
+        let size = egui::vec2(width * scale, height * scale);
+        let (response, painter) = ui.allocate_painter(size, egui::Sense::hover());
+
+        painter.rect_filled(response.rect, 0.0, egui::Color32::BLACK);
+        for (y, row) in display.rows().enumerate() {
+            for (x, _) in row.iter().enumerate().filter(|(_, on)| **on) {
+                let min = response.rect.min + egui::vec2(x as f32 * scale, y as f32 * scale);
+                // MegaChip's pixels have colours of their own
+                let color = display.color(x, y).map_or(egui::Color32::WHITE, |[r, g, b]| egui::Color32::from_rgb(r, g, b));
+                painter.rect_filled(egui::Rect::from_min_size(min, egui::vec2(scale, scale)), 0.0, color);
+            }
+        }
+    }
+
+    fn registers(&self, ui: &mut egui::Ui) {
+        let state = self.cpu.state();
+        egui::Grid::new("registers").striped(true).show(ui, |ui| {
+            for row in 0..4 {
+                for col in 0..4 {
+                    let reg = row * 4 + col;
+                    ui.monospace(format!("V{:X} {:02X}", reg, state.registers[reg]));
+                }
+                ui.end_row();
+            }
+        });
+        ui.separator();
+        ui.monospace(format!("PC 0x{:03X}  I 0x{:03X}", state.pc, state.i));
+        ui.monospace(format!("DT 0x{:02X}   ST 0x{:02X}", state.delay, state.sound));
+    }
+
+    fn disassembly(&mut self, ui: &mut egui::Ui) {
+        let memory = self.cpu.memory();
+        let pc = self.cpu.state().pc as usize;
+        let start = pc.saturating_sub(DISASSEMBLY_CONTEXT * 2);
+        let end = (pc + DISASSEMBLY_CONTEXT * 2).min(memory.len() - 2);
+
+        for addr in (start..=end).step_by(2) {
+            let opcode = (memory[addr] as u16) << 8 | memory[addr + 1] as u16;
+            let marker = if addr == pc { '>' } else { ' ' };
+            let breakpoint = if self.breakpoints.contains(&addr) { '*' } else { ' ' };
+            let line = format!("{}{} 0x{:03X}  {:04X}  {}", marker, breakpoint, addr, opcode, Instruction::decode(opcode));
+
+            let text = egui::RichText::new(line).monospace();
+            if ui.add(egui::Button::new(text).frame(false).selected(addr == pc)).clicked() {
+                toggle(&mut self.breakpoints, addr);
+            }
+        }
+    }
+
+    fn memory(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.add(egui::TextEdit::singleline(&mut self.edit_addr).desired_width(50.0).hint_text("0x200"));
+            ui.add(egui::TextEdit::singleline(&mut self.edit_bytes).desired_width(120.0).hint_text("A2 1F"));
+            if ui.button("Write").clicked() {
+                self.status = match parse_address(&self.edit_addr) {
+                    Some(addr) => match MemoryView::edit(&mut self.cpu, addr, &self.edit_bytes) {
+                        Ok(count) => format!("Wrote {} bytes at 0x{:03X}", count, addr),
+                        Err(err) => err,
+                    },
+                    None => format!("Not an address: {}", self.edit_addr),
+                };
+            }
+        });
+        ui.separator();
+
+        let memory = self.cpu.memory();
+        let row_height = ui.text_style_height(&egui::TextStyle::Monospace);
+        egui::ScrollArea::vertical().show_rows(ui, row_height, memory.len() / ROW_BYTES, |ui, rows| {
+            let view = MemoryView::new(memory, rows.start * ROW_BYTES, rows.len() * ROW_BYTES);
+            for row in view.rows(memory) {
+                ui.monospace(row.to_string());
+            }
+        });
+    }
+
+    fn stack(&self, ui: &mut egui::Ui) {
+        ui.label(format!("{} of {} in use", self.cpu.stack_pointer(), self.cpu.stack_depth()));
+        // the most recent call on top
+        for (depth, addr) in self.cpu.stack_frames().iter().enumerate().rev() {
+            ui.monospace(format!("{:2}  0x{:03X}", depth, addr));
+        }
+    }
+
+    fn breakpoints(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            let edit = ui.add(egui::TextEdit::singleline(&mut self.new_breakpoint).desired_width(60.0).hint_text("0x200"));
+            let entered = edit.lost_focus() && ui.input(|input| input.key_pressed(egui::Key::Enter));
+            if ui.button("Add").clicked() || entered {
+                match parse_address(&self.new_breakpoint) {
+                    Some(addr) => {
+                        if !self.breakpoints.contains(&addr) {
+                            self.breakpoints.push(addr);
+                        }
+                        self.new_breakpoint.clear();
+                    }
+                    _ => self.status = format!("Not an address: {}", self.new_breakpoint),
+                }
+            }
+        });
+
+        let mut removed = None;
+        for &addr in &self.breakpoints {
+            ui.horizontal(|ui| {
+                ui.monospace(format!("0x{:03X}", addr));
+                if ui.small_button("Remove").clicked() {
+                    removed = Some(addr);
+                }
+            });
+        }
+        if let Some(addr) = removed {
+            toggle(&mut self.breakpoints, addr);
+        }
+    }
+
+    /// Draws the keys in the keymap's layout, noting which are held down in
+    /// `held`
+    fn keypad(&self, ui: &mut egui::Ui, held: &mut [bool; 16]) {
+        let size = egui::vec2(KEYPAD_BUTTON, KEYPAD_BUTTON);
+        egui::Grid::new("keypad").spacing([6.0, 6.0]).show(ui, |ui| {
+            for row in self.keymap.keypad().rows() {
+                for key in row {
+                    match key {
+                        Some(key) => {
+                            let label = egui::RichText::new(format!("{:X}", key)).monospace().size(24.0);
+                            let pressed = self.cpu.keypad().is_pressed(key);
+                            let button = egui::Button::new(label).min_size(size).selected(pressed);
+                            held[key as usize] = ui.add(button).is_pointer_button_down_on();
+                        }
+                        None => {
+                            ui.allocate_space(size);
+                        }
+                    }
+                }
+                ui.end_row();
+            }
+        });
+    }
+
+    /// Presses and releases keys as the keypad panel's buttons change,
+    /// letting go of any held when the panel is closed
+    fn press_keypad(&mut self, held: [bool; 16]) {
+        for (key, &pressed) in held.iter().enumerate() {
+            if pressed != self.keypad_held[key] {
+                self.cpu.set_key(key as u8, pressed);
+            }
+        }
+        self.keypad_held = held;
+    }
+}
+
+/// Reads a hex address like `2A0` or `0x2A0`, if it's in memory
+fn parse_address(text: &str) -> Option<usize> {
+    let addr = usize::from_str_radix(text.trim().trim_start_matches("0x"), 16).ok()?;
+    Some(addr).filter(|&addr| addr < 0x1000)
+}
+
+/// Adds `addr` to the breakpoints, or removes it if it's already there
+fn toggle(breakpoints: &mut Vec<usize>, addr: usize) {
+    match breakpoints.iter().position(|&bp| bp == addr) {
+        Some(pos) => {
+            breakpoints.remove(pos);
+        }
+        None => breakpoints.push(addr),
+    }
+}
+
+impl eframe::App for DebugApp {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        self.handle_keys(ctx);
+
+        let elapsed = self.last_frame.elapsed().as_secs_f64();
+        self.last_frame = Instant::now();
+        if self.running {
+            for _ in 0..self.clock.frames_due(elapsed) {
+                self.run_frame();
+                if !self.running {
+                    break;
+                }
+            }
+        }
+
+        egui::TopBottomPanel::top("toolbar").show(ctx, |ui| self.toolbar(ui));
+        egui::CentralPanel::default().show(ctx, |ui| self.screen(ui));
+
+        // the windows close themselves through these, while their contents borrow self
+        let mut panels = self.panels;
+        egui::Window::new("Registers").open(&mut panels.registers).show(ctx, |ui| self.registers(ui));
+        egui::Window::new("Disassembly").open(&mut panels.disassembly).show(ctx, |ui| self.disassembly(ui));
+        egui::Window::new("Memory").open(&mut panels.memory).show(ctx, |ui| self.memory(ui));
+        egui::Window::new("Stack").open(&mut panels.stack).show(ctx, |ui| self.stack(ui));
+        egui::Window::new("Breakpoints").open(&mut panels.breakpoints).show(ctx, |ui| self.breakpoints(ui));
+        // stays all up unless the panel is open and drawn
+        let mut held = [false; 16];
+        egui::Window::new("Keypad").open(&mut panels.keypad).show(ctx, |ui| self.keypad(ui, &mut held));
+        self.panels = panels;
+        self.press_keypad(held);
+
+        ctx.request_repaint();
+    }
+}
+
+fn main() -> eframe::Result {
+    let path = env::args().nth(1).unwrap_or_else(|| String::from("./roms/sierpinski.ch8"));
+    let rom = match fs::read(&path) {
+        Ok(rom) => rom,
+        Err(err) => {
+            eprintln!("Couldn't read {}: {}", path, err);
+            process::exit(1);
+        }
+    };
+
+    let mut cpu = CpuBuilder::new().auto_configure(&rom).build();
+    if let Err(err) = cpu.load_program(&rom) {
+        eprintln!("Couldn't load {}: {}", path, err);
+        process::exit(1);
+    }
+
+    let keymap = match env::args().nth(2).or_else(|| rom_db::lookup(&rom)?.keymap.map(String::from)) {
+        Some(name) => match KeyMap::preset(&name).map(Ok).unwrap_or_else(|| KeyMap::load(&name)) {
+            Ok(keymap) => keymap,
+            Err(err) => {
+                eprintln!("Couldn't load keymap {}: {}", name, err);
+                process::exit(1);
+            }
+        },
+        None => KeyMap::default(),
+    };
+
+    let options = eframe::NativeOptions {
+        viewport: egui::ViewportBuilder::default().with_inner_size([1100.0, 700.0]),
+        ..Default::default()
+    };
+    eframe::run_native("CHIP-8", options, Box::new(|_| Ok(Box::new(DebugApp::new(cpu, keymap)))))
+}
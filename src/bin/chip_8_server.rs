@@ -0,0 +1,42 @@
+//! Runs a ROM headless, for watching and driving over WebSocket
+//!
+//! `chip_8_server [ROM] [ADDR]` runs the ROM at 60 frames a second and
+//! serves it on ADDR, `0.0.0.0:8060` unless given. See the `server` module
+//! for the messages clients send and receive.
+
+use std::env;
+use std::fs;
+use std::process;
+
+use chip_8::server::Server;
+use chip_8::{CpuBuilder, Emulator};
+
+fn main() {
+    let mut args = env::args().skip(1);
+    let path = args.next().unwrap_or_else(|| String::from("./roms/sierpinski.ch8"));
+    let addr = args.next().unwrap_or_else(|| String::from("0.0.0.0:8060"));
+
+    let rom = match fs::read(&path) {
+        Ok(rom) => rom,
+        Err(err) => {
+            eprintln!("Couldn't read {}: {}", path, err);
+            process::exit(1);
+        }
+    };
+
+    let mut emulator = Emulator::new(CpuBuilder::new().auto_configure(&rom).build());
+    if let Err(err) = emulator.load_rom(&rom) {
+        eprintln!("Couldn't load {}: {}", path, err);
+        process::exit(1);
+    }
+
+    let server = match Server::bind(&addr) {
+        Ok(server) => server,
+        Err(err) => {
+            eprintln!("Couldn't listen on {}: {}", addr, err);
+            process::exit(1);
+        }
+    };
+    println!("Serving {} on ws://{}", path, addr);
+    server.serve(emulator);
+}
@@ -0,0 +1,212 @@
+//! Screenshots and recordings of the screen, for sharing bug reports and demos
+//!
+//! Only available with the `image` feature. The screen is only 64x32, or
+//! 128x64 in SUPER-CHIP's high resolution, so both are scaled up by a whole
+//! number of pixels to be seen properly.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use chip_8::capture::{self, Recording};
+//! use chip_8::CpuBuilder;
+//!
+//! let mut cpu = CpuBuilder::new().build();
+//! let mut recording = Recording::new(5.0);
+//!
+//! // once a frame
+//! cpu.run_cycles(10);
+//! recording.record(cpu.display());
+//!
+//! capture::save_png(cpu.display(), 8, "screen.png").unwrap();
+//! recording.save_gif(8, "last-five-seconds.gif").unwrap();
+//! ```
+
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+
+use image::codecs::gif::{GifEncoder, Repeat};
+use image::imageops::{self, FilterType};
+use image::{Delay, DynamicImage, Frame, ImageResult, RgbImage};
+
+use crate::screen::{self, Display};
+use crate::timing::TIMER_HZ;
+
+/// Saves the screen as a PNG, each CHIP-8 pixel `scale` pixels square, lit
+/// pixels white or in MegaChip's colours
+pub fn save_png<P: AsRef<Path>>(display: &Display, scale: u32, path: P) -> ImageResult<()> {
+    scaled(display, scale).save_with_format(path, image::ImageFormat::Png)
+}
+
+/// The screen as an image, scaled up without any smoothing
+fn scaled(display: &Display, scale: u32) -> RgbImage {
+    let scale = scale.max(1);
+    let (width, height) = (display.width() as u32 * scale, display.height() as u32 * scale);
+    imageops::resize(&display.to_rgb_image(), width, height, FilterType::Nearest)
+}
+
+/// The last few seconds of frames, ready to be saved as an animated GIF
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Recording {
+    frames: VecDeque<Display>,
+    capacity: usize,
+}
+
+impl Recording {
+    /// Makes an empty Recording that keeps the last `seconds` of frames,
+    /// at 60 frames a second
+    pub fn new(seconds: f64) -> Recording {
+        let capacity = (seconds * TIMER_HZ).max(0.0) as usize;
+        Recording {
+            frames: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    /// Adds a frame, forgetting the oldest one if full
+    ///
+    /// Should be called once a frame, 60 times a second
+    pub fn record(&mut self, display: &Display) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.frames.len() == self.capacity {
+            self.frames.pop_front();
+        }
+        self.frames.push_back(display.clone());
+    }
+
+    /// How many frames are recorded
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Whether no frames are recorded
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// Forgets every recorded frame
+    pub fn clear(&mut self) {
+        self.frames.clear();
+    }
+
+    /// Saves the recorded frames as a looping GIF, each CHIP-8 pixel
+    /// `scale` pixels square
+    ///
+    /// Runs of identical frames are saved as one longer frame, which keeps
+    /// the file small when the game isn't doing much. If the game switched
+    /// resolution, the smaller screens' frames are scaled up further to be
+    /// as wide as the widest.
+    pub fn save_gif<P: AsRef<Path>>(&self, scale: u32, path: P) -> ImageResult<()> {
+        let widest = self.frames.iter().map(Display::width).max().unwrap_or(screen::WIDTH);
+        let mut encoder = GifEncoder::new(BufWriter::new(File::create(path)?));
+        encoder.set_repeat(Repeat::Infinite)?;
+        encoder.encode_frames(self.runs().into_iter().map(|(display, count)| {
+            let scale = scale.max(1) * (widest / display.width()) as u32;
+            let image = DynamicImage::ImageRgb8(scaled(display, scale)).to_rgba8();
+            let delay = Delay::from_numer_denom_ms(count as u32 * 1000, TIMER_HZ as u32);
+            Frame::from_parts(image, 0, 0, delay)
+        }))
+    }
+
+    /// Each distinct frame, with how many times in a row it was recorded
+    fn runs(&self) -> Vec<(&Display, usize)> {
+        let mut runs: Vec<(&Display, usize)> = vec![];
+        for display in &self.frames {
+            match runs.last_mut() {
+                Some((last, count)) if *last == display => *count += 1,
+                _ => runs.push((display, 1)),
+            }
+        }
+        runs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::BufReader;
+
+    use image::codecs::gif::GifDecoder;
+    use image::AnimationDecoder;
+
+    use super::*;
+    use crate::screen::Resolution;
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("chip_8_{}_{}", std::process::id(), name))
+    }
+
+    #[test]
+    fn record_keeps_only_the_last_frames() {
+        let mut recording = Recording::new(0.05);
+        for _ in 0..10 {
+            recording.record(&Display::new());
+        }
+
+        assert_eq!(recording.len(), 3);
+    }
+
+    #[test]
+    fn runs_merge_identical_frames() {
+        let mut lit = Display::new();
+        lit.toggle(0, 0);
+        let mut recording = Recording::new(1.0);
+        for display in [&lit, &lit, &Display::new(), &lit] {
+            recording.record(display);
+        }
+
+        let counts: Vec<usize> = recording.runs().iter().map(|(_, count)| *count).collect();
+        assert_eq!(counts, [2, 1, 1]);
+    }
+
+    #[test]
+    fn saves_png_and_gif() {
+        let mut recording = Recording::new(1.0);
+        recording.record(&Display::new());
+        let (png, gif) = (temp_path("screen.png"), temp_path("screen.gif"));
+
+        save_png(&Display::new(), 4, &png).unwrap();
+        recording.save_gif(4, &gif).unwrap();
+        let (png_bytes, gif_bytes) = (std::fs::read(&png).unwrap(), std::fs::read(&gif).unwrap());
+        std::fs::remove_file(&png).unwrap();
+        std::fs::remove_file(&gif).unwrap();
+
+        assert!(png_bytes.starts_with(b"\x89PNG"));
+        assert!(gif_bytes.starts_with(b"GIF89a"));
+        assert_eq!(image::load_from_memory(&png_bytes).unwrap().width(), 256);
+    }
+
+    #[test]
+    fn gif_frames_fill_the_high_resolution_size() {
+        let mut recording = Recording::new(1.0);
+        recording.record(&Display::new());
+        recording.record(&Display::with_resolution(Resolution::High));
+        let gif = temp_path("resolutions.gif");
+
+        recording.save_gif(2, &gif).unwrap();
+        let decoder = GifDecoder::new(BufReader::new(File::open(&gif).unwrap())).unwrap();
+        let frames = decoder.into_frames().collect_frames().unwrap();
+        std::fs::remove_file(&gif).unwrap();
+
+        let sizes: Vec<(u32, u32)> = frames.iter().map(|frame| frame.buffer().dimensions()).collect();
+        assert_eq!(sizes, [(256, 128), (256, 128)]);
+    }
+
+    #[test]
+    fn png_keeps_megachip_colours() {
+        let mut display = Display::with_resolution(Resolution::Mega);
+        display.load_palette(1, &[0xFF, 0xFF, 0x80, 0x00]);
+        display.paint(10, 20, 1);
+        let png = temp_path("mega.png");
+
+        save_png(&display, 1, &png).unwrap();
+        let image = image::open(&png).unwrap().to_rgb8();
+        std::fs::remove_file(&png).unwrap();
+
+        assert_eq!(image.dimensions(), (256, 192));
+        assert_eq!(image.get_pixel(10, 20).0, [0xFF, 0x80, 0x00]);
+        assert_eq!(image.get_pixel(11, 20).0, [0, 0, 0]);
+    }
+}
@@ -0,0 +1,233 @@
+//! Cheats: bytes patched into memory once, or frozen at a value every frame
+//!
+//! A cheat is written as a short code, like the Game Genie's. `3C5=03`
+//! freezes the byte at 0x3C5 at 3, for a lives counter that never goes
+//! down, and `2A1:05` writes 5 to 0x2A1 once, for starting on another
+//! level. Addresses and values are hex.
+//!
+//! [`Cheats`] holds a game's cheats and applies the ones switched on, once a
+//! frame, through [`Cpu::poke`], so they can't touch the interpreter's
+//! memory below the program start. ROMs can list theirs in the
+//! [settings beside them](crate::rom_config), by name:
+//!
+//! ```toml
+//! [cheats]
+//! "Infinite lives" = "3C5=03"
+//! "Start on level 5" = "2A1:05"
+//! ```
+//!
+//! # Example
+//!
+//! ```
+//! use chip_8::cheats::{Cheat, Cheats};
+//! use chip_8::CpuBuilder;
+//!
+//! let mut cheats = Cheats::new();
+//! cheats.add(Cheat::parse("Infinite lives", "3C5=03").unwrap());
+//!
+//! let mut cpu = CpuBuilder::new().build();
+//! cheats.apply(&mut cpu);
+//! assert_eq!(cpu.memory()[0x3C5], 3);
+//! ```
+
+use std::fmt;
+
+use crate::{Byte, Cpu, CpuError};
+
+/// One cheat: a byte, where it goes, and how often it's written
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cheat {
+    pub name: String,
+    pub addr: usize,
+    pub value: Byte,
+    /// Written every frame rather than once
+    pub freeze: bool,
+}
+
+impl Cheat {
+    /// Reads a cheat from its code, `ADDR=VALUE` to freeze the byte at
+    /// `ADDR` or `ADDR:VALUE` to write it once
+    ///
+    /// # Examples
+    /// ```
+    /// use chip_8::cheats::Cheat;
+    ///
+    /// let cheat = Cheat::parse("Level 5", "2a1:05").unwrap();
+    /// assert_eq!((cheat.addr, cheat.value, cheat.freeze), (0x2A1, 5, false));
+    /// assert!(Cheat::parse("Broken", "2A1").is_err());
+    /// ```
+    pub fn parse(name: &str, code: &str) -> Result<Cheat, String> {
+        let (addr, value, freeze) = match (code.split_once('='), code.split_once(':')) {
+            (Some((addr, value)), None) => (addr, value, true),
+            (None, Some((addr, value))) => (addr, value, false),
+            _ => return Err(format!("{}: `{}` isn't ADDR=VALUE or ADDR:VALUE", name, code)),
+        };
+        let addr = usize::from_str_radix(addr.trim(), 16)
+            .ok()
+            .filter(|&addr| addr <= 0xFFFF)
+            .ok_or_else(|| format!("{}: `{}` isn't an address in hex", name, addr.trim()))?;
+        let value = Byte::from_str_radix(value.trim(), 16)
+            .map_err(|_| format!("{}: `{}` isn't a byte in hex", name, value.trim()))?;
+        Ok(Cheat { name: name.to_string(), addr, value, freeze })
+    }
+
+    /// The cheat's code, as [`parse`](Cheat::parse) reads it
+    pub fn code(&self) -> String {
+        format!("{:03X}{}{:02X}", self.addr, if self.freeze { '=' } else { ':' }, self.value)
+    }
+}
+
+impl fmt::Display for Cheat {
+    /// The name, then the code: `Infinite lives (3C5=03)`
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} ({})", self.name, self.code())
+    }
+}
+
+/// A game's cheats, each switched on or off
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Cheats {
+    // each cheat, whether it's on, and whether a one-off has been written
+    cheats: Vec<(Cheat, bool, bool)>,
+}
+
+impl Cheats {
+    pub fn new() -> Cheats {
+        Cheats::default()
+    }
+
+    /// Adds a cheat, switched on, returning its index
+    pub fn add(&mut self, cheat: Cheat) -> usize {
+        self.cheats.push((cheat, true, false));
+        self.cheats.len() - 1
+    }
+
+    /// Every cheat in the order they were added, with whether it's on
+    pub fn iter(&self) -> impl Iterator<Item = (&Cheat, bool)> {
+        self.cheats.iter().map(|(cheat, on, _)| (cheat, *on))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cheats.is_empty()
+    }
+
+    /// Switches the cheat at `index` on or off, returning false if there's
+    /// no such cheat
+    ///
+    /// A one-off switched back on is written again.
+    pub fn set_enabled(&mut self, index: usize, on: bool) -> bool {
+        match self.cheats.get_mut(index) {
+            Some((_, enabled, written)) => {
+                *written &= *enabled && on;
+                *enabled = on;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Switches every cheat on or off at once
+    pub fn set_all(&mut self, on: bool) {
+        for index in 0..self.cheats.len() {
+            self.set_enabled(index, on);
+        }
+    }
+
+    /// Whether any cheat is on
+    pub fn any_enabled(&self) -> bool {
+        self.cheats.iter().any(|(_, on, _)| *on)
+    }
+
+    /// Writes the frozen values, and any one-offs that haven't been
+    /// written yet, for the start of a frame
+    ///
+    /// A cheat whose write is refused is switched off, and returned with
+    /// why, so it isn't tried every frame.
+    pub fn apply(&mut self, cpu: &mut Cpu) -> Vec<(Cheat, CpuError)> {
+        let mut refused = Vec::new();
+        for (cheat, on, written) in &mut self.cheats {
+            if !*on || *written {
+                continue;
+            }
+            match cpu.poke(cheat.addr, &[cheat.value]) {
+                Ok(()) => *written = !cheat.freeze,
+                Err(err) => {
+                    *on = false;
+                    refused.push((cheat.clone(), err));
+                }
+            }
+        }
+        refused
+    }
+
+    /// Has the one-offs written again at the next [`apply`](Cheats::apply),
+    /// after a reset or a new ROM has put memory back
+    pub fn rewrite(&mut self) {
+        for (_, _, written) in &mut self.cheats {
+            *written = false;
+        }
+    }
+}
+
+impl From<Vec<Cheat>> for Cheats {
+    /// All of `cheats`, switched on
+    fn from(cheats: Vec<Cheat>) -> Cheats {
+        Cheats { cheats: cheats.into_iter().map(|cheat| (cheat, true, false)).collect() }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CpuBuilder;
+
+    #[test]
+    fn freezes_hold_and_one_offs_dont() {
+        let mut cheats = Cheats::new();
+        cheats.add(Cheat::parse("Lives", "300=03").unwrap());
+        let level = cheats.add(Cheat::parse("Level", "301:05").unwrap());
+        let mut cpu = CpuBuilder::new().build();
+
+        assert!(cheats.apply(&mut cpu).is_empty());
+        cpu.poke(0x300, &[0, 0]).unwrap();
+        cheats.apply(&mut cpu);
+        assert_eq!(cpu.memory()[0x300..0x302], [3, 0]);
+
+        // switching a one-off back on, or a reset, writes it again
+        cheats.set_enabled(level, false);
+        cheats.set_enabled(level, true);
+        cheats.apply(&mut cpu);
+        assert_eq!(cpu.memory()[0x301], 5);
+        cpu.reset();
+        cheats.rewrite();
+        cheats.apply(&mut cpu);
+        assert_eq!(cpu.memory()[0x301], 5);
+
+        cheats.set_all(false);
+        cpu.poke(0x300, &[0]).unwrap();
+        cheats.apply(&mut cpu);
+        assert_eq!(cpu.memory()[0x300], 0);
+        assert!(!cheats.any_enabled());
+    }
+
+    #[test]
+    fn refused_cheats_are_switched_off() {
+        let mut cheats = Cheats::from(vec![Cheat::parse("Font", "050=FF").unwrap()]);
+        let mut cpu = CpuBuilder::new().build();
+
+        let refused = cheats.apply(&mut cpu);
+        assert_eq!(refused.len(), 1);
+        assert_eq!(refused[0].1, CpuError::ProtectedMemory(0x050));
+        assert_eq!(cheats.iter().next().map(|(_, on)| on), Some(false));
+        assert_eq!(refused[0].0.to_string(), "Font (050=FF)");
+    }
+
+    #[test]
+    fn parse_rejects_broken_codes() {
+        assert!(Cheat::parse("", "3C5").is_err());
+        assert!(Cheat::parse("", "3C5=03:04").is_err());
+        assert!(Cheat::parse("", "10000=03").is_err());
+        assert!(Cheat::parse("", "3C5=100").is_err());
+        assert!(Cheat::parse("", "XYZ:03").is_err());
+    }
+}
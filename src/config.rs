@@ -0,0 +1,149 @@
+//! The player's own defaults, for every ROM
+//!
+//! `config.toml`, in a `chip_8` directory under the platform's config
+//! directory (`~/.config/chip_8/config.toml` on Linux), holds the settings
+//! the player wants unless a ROM says otherwise. It takes everything a
+//! ROM's [`RomConfig`] file does apart from cheats, and the window's scale:
+//!
+//! ```toml
+//! variant = "schip"       # quirks to start from, as --variant takes them
+//! clock = 1000            # instructions a second, or "vip" or "hp48"
+//! keymap = "arrows"       # a preset, or a map file beside this one
+//! scale = 8               # window pixels per CHIP-8 pixel
+//!
+//! [palette]
+//! on = "#33FF66"
+//!
+//! [audio]
+//! volume = 40
+//! ```
+//!
+//! Each setting comes from the first of these that sets it:
+//!
+//! 1. the command line
+//! 2. the ROM's own settings file, beside it
+//! 3. the [`rom_db`](crate::rom_db), for the quirks and keymap it knows a
+//!    ROM needs
+//! 4. this file
+//! 5. the emulator's built-in defaults
+//!
+//! An [`Emulator`](crate::Emulator) follows the same order when it's
+//! configured with [`Config::defaults`] before loading a ROM file.
+//!
+//! # Example
+//!
+//! ```
+//! use chip_8::config::Config;
+//!
+//! let config: Config = "scale = 8\n[audio]\nmute = true".parse().unwrap();
+//! assert_eq!(config.scale, Some(8));
+//! assert_eq!(config.defaults.muted, Some(true));
+//! assert_eq!(config.defaults.quirks, None);
+//! ```
+
+use std::error::Error;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use toml::Value;
+
+use crate::rom_config::{self, RomConfig};
+
+/// The biggest scale the config file can ask for
+const MAX_SCALE: i64 = 64;
+
+/// What the player's config file asks for, with anything it leaves out
+/// unset
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Config {
+    /// The settings a ROM's own file could also set, whose cheats are
+    /// always empty
+    pub defaults: RomConfig,
+    /// How big a CHIP-8 pixel is in the window to begin with
+    pub scale: Option<u32>,
+}
+
+impl Config {
+    /// Where the player's config file is kept, if the platform has a
+    /// config directory
+    pub fn path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("chip_8").join("config.toml"))
+    }
+
+    /// Reads the player's config file, or nothing but the built-in defaults
+    /// if there isn't one
+    pub fn for_user() -> Result<Config, Box<dyn Error>> {
+        let path = match Config::path() {
+            Some(path) => path,
+            None => return Ok(Config::default()),
+        };
+        match Config::load(path) {
+            Ok(config) => Ok(config),
+            Err(err) if err.downcast_ref::<io::Error>().is_some_and(|err| err.kind() == io::ErrorKind::NotFound) => {
+                Ok(Config::default())
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Reads a config file
+    ///
+    /// As in a ROM's settings, a map file's path is taken as relative to
+    /// the config file.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Config, Box<dyn Error>> {
+        let path = path.as_ref();
+        let mut config: Config = fs::read_to_string(path)?.parse()?;
+        config.defaults.resolve_keymap(path);
+        Ok(config)
+    }
+}
+
+impl FromStr for Config {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Config, String> {
+        let mut table = rom_config::parse_table(text)?;
+        let scale = match table.remove("scale") {
+            None => None,
+            Some(Value::Integer(scale @ 1..=MAX_SCALE)) => Some(scale as u32),
+            Some(_) => return Err(format!("scale should be from 1 to {}", MAX_SCALE)),
+        };
+        if table.contains_key("cheats") {
+            return Err("cheats only go in a ROM's own settings".to_string());
+        }
+
+        Ok(Config { defaults: RomConfig::from_table(&table)?, scale })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analyze::Variant;
+    use crate::timing::TimingModel;
+
+    #[test]
+    fn reads_rom_settings_and_scale() {
+        let config: Config = "variant = \"chip48\"\nclock = \"vip\"\nscale = 6".parse().unwrap();
+        assert_eq!(config.defaults.quirks, Some(Variant::Chip48.quirks()));
+        assert_eq!(config.defaults.timing, Some(TimingModel::Vip));
+        assert_eq!(config.scale, Some(6));
+    }
+
+    #[test]
+    fn parse_rejects_cheats_and_bad_scales() {
+        assert!("[cheats]\nLives = \"3C5=03\"".parse::<Config>().is_err());
+        assert!("scale = 0".parse::<Config>().is_err());
+        assert!("scale = \"big\"".parse::<Config>().is_err());
+        assert!("speed = 10".parse::<Config>().is_err());
+    }
+
+    #[test]
+    fn lives_in_a_chip_8_directory() {
+        if let Some(path) = Config::path() {
+            assert!(path.ends_with("chip_8/config.toml"));
+        }
+    }
+}
@@ -0,0 +1,483 @@
+//! Differential testing against a small reference interpreter
+//!
+//! [`compare`] runs a ROM on the CPU and on a deliberately plain reference
+//! interpreter in lockstep, checking the program counter, registers, I,
+//! stack, timers, memory and screen after every instruction, and reports
+//! the first [`Divergence`]. The reference shares nothing with the CPU but
+//! the starting memory, so a refactor of the CPU that changes behaviour
+//! shows up as a divergence rather than slipping through.
+//!
+//! The reference follows the widely documented behaviour of each
+//! instruction under the given [`Quirks`]: arithmetic and shifts write VF
+//! last, CXNN writes VX, and 0NNN machine code calls are ignored. It knows
+//! SUPER-CHIP's high resolution but not MegaChip, so a ROM that turns
+//! MegaChip on diverges there. Both interpreters get the same random seed
+//! and no keys.
+//!
+//! # Example
+//!
+//! ```
+//! use chip_8::conformance;
+//! use chip_8::Quirks;
+//!
+//! // LD V0, 5; ADD V0, 0xFF; LD I, 0x300; BCD V0; terminate
+//! let rom = [0x60, 0x05, 0x70, 0xFF, 0xA3, 0x00, 0xF0, 0x33, 0x00, 0x00];
+//! assert_eq!(conformance::compare(&rom, Quirks::default(), 1000), Ok(5));
+//! ```
+
+use std::fmt;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::instruction::Instruction;
+use crate::screen::{screen_hash, HEIGHT, WIDTH};
+use crate::{Address, Byte, CpuBuilder, CpuStatus, OpCode, Quirks, INSTRUCTIONS_PER_TICK};
+
+/// The seed both interpreters' random numbers start from
+const SEED: u64 = 0xC8;
+
+/// What differed, expected being the reference's value and actual the CPU's
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Difference {
+    Pc { expected: Address, actual: Address },
+    Register { reg: Byte, expected: Byte, actual: Byte },
+    I { expected: Address, actual: Address },
+    /// The stack pointer or the addresses on the stack
+    Stack { expected: Vec<Address>, actual: Vec<Address> },
+    /// The delay or sound timer, as (delay, sound)
+    Timers { expected: (Byte, Byte), actual: (Byte, Byte) },
+    Memory { addr: usize, expected: Byte, actual: Byte },
+    /// Hashes of the two screens
+    Screen { expected: u64, actual: u64 },
+    /// One stopped and the other didn't, or they stopped differently
+    Status { expected: &'static str, actual: &'static str },
+}
+
+impl fmt::Display for Difference {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Difference::Pc { expected, actual } => write!(f, "PC expected 0x{:03X}, got 0x{:03X}", expected, actual),
+            Difference::Register { reg, expected, actual } => {
+                write!(f, "V{:X} expected 0x{:02X}, got 0x{:02X}", reg, expected, actual)
+            }
+            Difference::I { expected, actual } => write!(f, "I expected 0x{:03X}, got 0x{:03X}", expected, actual),
+            Difference::Stack { expected, actual } => write!(f, "stack expected {:03X?}, got {:03X?}", expected, actual),
+            Difference::Timers { expected, actual } => {
+                write!(f, "timers (DT, ST) expected {:02X?}, got {:02X?}", expected, actual)
+            }
+            Difference::Memory { addr, expected, actual } => {
+                write!(f, "memory at 0x{:03X} expected 0x{:02X}, got 0x{:02X}", addr, expected, actual)
+            }
+            Difference::Screen { expected, actual } => {
+                write!(f, "screen hash expected {:016X}, got {:016X}", expected, actual)
+            }
+            Difference::Status { expected, actual } => write!(f, "expected the program to be {}, it was {}", expected, actual),
+        }
+    }
+}
+
+/// The first instruction after which the CPU and the reference disagreed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Divergence {
+    /// How many instructions had run before it, counting from 0
+    pub step: u64,
+    /// Where the instruction was
+    pub address: usize,
+    pub opcode: OpCode,
+    pub difference: Difference,
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Diverged at step {}, 0x{:03X}  {:04X}  {}: {}",
+            self.step,
+            self.address,
+            self.opcode,
+            Instruction::decode(self.opcode),
+            self.difference
+        )
+    }
+}
+
+/// How the reference's last instruction went
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Ran {
+    Running,
+    Waiting,
+    /// Drew with the `display_wait` quirk, ending the frame
+    Blanking,
+    Halted,
+    Failed,
+}
+
+impl Ran {
+    fn describe(self) -> &'static str {
+        match self {
+            Ran::Running => "running",
+            Ran::Waiting => "waiting for a key",
+            Ran::Blanking => "waiting for the vertical blank",
+            Ran::Halted => "halted",
+            Ran::Failed => "stopped by an error",
+        }
+    }
+}
+
+/// A straightforward interpreter, written for obviousness over speed
+struct Reference {
+    memory: Vec<Byte>,
+    v: [Byte; 16],
+    i: u16,
+    pc: u16,
+    stack: Vec<u16>,
+    delay: Byte,
+    sound: Byte,
+    // SUPER-CHIP's high resolution, or the original 64x32
+    high_res: bool,
+    // row by row
+    screen: Vec<bool>,
+    flags: [Byte; 8],
+    quirks: Quirks,
+    rng: StdRng,
+}
+
+impl Reference {
+    fn opcode(&self) -> Option<OpCode> {
+        let pc = self.pc as usize;
+        Some((*self.memory.get(pc)? as OpCode) << 8 | *self.memory.get(pc + 1)? as OpCode)
+    }
+
+    /// The `len` addresses from I, if they're all in memory
+    fn at_i(&self, len: usize) -> Option<std::ops::Range<usize>> {
+        let start = self.i as usize;
+        Some(start..start + len).filter(|range| range.end <= self.memory.len())
+    }
+
+    fn step(&mut self) -> Ran {
+        let opcode = match self.opcode() {
+            Some(opcode) => opcode,
+            None => return Ran::Failed,
+        };
+        self.pc += 2;
+
+        let x = (opcode >> 8 & 0xF) as usize;
+        let y = (opcode >> 4 & 0xF) as usize;
+        let n = (opcode & 0xF) as usize;
+        let nn = (opcode & 0xFF) as Byte;
+        let nnn = opcode & 0xFFF;
+        let (vx, vy) = (self.v[x], self.v[y]);
+
+        match (opcode >> 12, x, y, n) {
+            (0x0, 0x0, 0x0, 0x0) => return Ran::Halted,
+            (0x0, 0x0, 0xE, 0x0) => self.screen = vec![false; self.screen.len()],
+            (0x0, 0x0, 0xE, 0xE) => match self.stack.pop() {
+                Some(addr) => self.pc = addr,
+                None => return Ran::Failed,
+            },
+            (0x0, 0x0, 0xF, 0xE) => self.set_high_res(false),
+            (0x0, 0x0, 0xF, 0xF) => self.set_high_res(true),
+            // machine code routines can't run here
+            (0x0, ..) => {}
+            (0x1, ..) => self.pc = nnn,
+            (0x2, ..) => {
+                if self.stack.len() == 16 {
+                    return Ran::Failed;
+                }
+                self.stack.push(self.pc);
+                self.pc = nnn;
+            }
+            (0x3, ..) => self.skip_if(vx == nn),
+            (0x4, ..) => self.skip_if(vx != nn),
+            (0x5, _, _, 0x0) => self.skip_if(vx == vy),
+            (0x6, ..) => self.v[x] = nn,
+            (0x7, ..) => self.v[x] = vx.wrapping_add(nn),
+            (0x8, _, _, 0x0) => self.v[x] = vy,
+            (0x8, _, _, 0x1) => self.logic(x, vx | vy),
+            (0x8, _, _, 0x2) => self.logic(x, vx & vy),
+            (0x8, _, _, 0x3) => self.logic(x, vx ^ vy),
+            (0x8, _, _, 0x4) => self.with_flag(x, vx.wrapping_add(vy), vx as u16 + vy as u16 > 0xFF),
+            (0x8, _, _, 0x5) => self.with_flag(x, vx.wrapping_sub(vy), vx >= vy),
+            (0x8, _, _, 0x6) => {
+                let value = if self.quirks.shift_uses_vy { vy } else { vx };
+                self.with_flag(x, value >> 1, value & 1 == 1);
+            }
+            (0x8, _, _, 0x7) => self.with_flag(x, vy.wrapping_sub(vx), vy >= vx),
+            (0x8, _, _, 0xE) => {
+                let value = if self.quirks.shift_uses_vy { vy } else { vx };
+                self.with_flag(x, value << 1, value & 0x80 != 0);
+            }
+            (0x9, _, _, 0x0) => self.skip_if(vx != vy),
+            (0xA, ..) => self.i = nnn,
+            (0xB, ..) => self.pc = nnn + if self.quirks.jump_uses_vx { vx } else { self.v[0] } as u16,
+            (0xC, ..) => self.v[x] = self.rng.gen_range(1..=255) & nn,
+            (0xD, ..) => return self.draw(vx as usize, vy as usize, n),
+            // nobody is pressing anything
+            (0xE, _, 0x9, 0xE) => {}
+            (0xE, _, 0xA, 0x1) => self.skip_if(true),
+            (0xF, _, 0x0, 0x7) => self.v[x] = self.delay,
+            (0xF, _, 0x0, 0xA) => return Ran::Waiting,
+            (0xF, _, 0x1, 0x5) => self.delay = vx,
+            (0xF, _, 0x1, 0x8) => self.sound = vx,
+            (0xF, _, 0x1, 0xE) => self.i = self.i.wrapping_add(vx as u16),
+            (0xF, _, 0x2, 0x9) => self.i = (vx & 0xF) as u16 * 5,
+            (0xF, _, 0x3, 0x3) => match self.at_i(3) {
+                Some(range) => self.memory[range].copy_from_slice(&[vx / 100, vx / 10 % 10, vx % 10]),
+                None => return Ran::Failed,
+            },
+            (0xF, _, 0x5, 0x5) => match self.at_i(x + 1) {
+                Some(range) => {
+                    self.memory[range].copy_from_slice(&self.v[..=x]);
+                    self.registers_moved(x);
+                }
+                None => return Ran::Failed,
+            },
+            (0xF, _, 0x6, 0x5) => match self.at_i(x + 1) {
+                Some(range) => {
+                    self.v[..=x].copy_from_slice(&self.memory[range]);
+                    self.registers_moved(x);
+                }
+                None => return Ran::Failed,
+            },
+            (0xF, _, 0x7, 0x5) => {
+                let count = x.min(7) + 1;
+                self.flags[..count].copy_from_slice(&self.v[..count]);
+            }
+            (0xF, _, 0x8, 0x5) => {
+                let count = x.min(7) + 1;
+                self.v[..count].copy_from_slice(&self.flags[..count]);
+            }
+            _ => return Ran::Failed,
+        }
+        Ran::Running
+    }
+
+    fn skip_if(&mut self, condition: bool) {
+        if condition {
+            self.pc += 2;
+        }
+    }
+
+    fn with_flag(&mut self, x: usize, value: Byte, flag: bool) {
+        self.v[x] = value;
+        self.v[0xF] = flag as Byte;
+    }
+
+    fn logic(&mut self, x: usize, value: Byte) {
+        self.v[x] = value;
+        if self.quirks.vf_reset {
+            self.v[0xF] = 0;
+        }
+    }
+
+    fn registers_moved(&mut self, x: usize) {
+        if self.quirks.memory_increments_i && self.quirks.memory_increments_i_by_x {
+            self.i = self.i.wrapping_add(x as u16);
+        } else if self.quirks.memory_increments_i {
+            self.i = self.i.wrapping_add(x as u16 + 1);
+        }
+    }
+
+    fn set_high_res(&mut self, high_res: bool) {
+        self.high_res = high_res;
+        let scale = if high_res { 4 } else { 1 };
+        self.screen = vec![false; WIDTH * HEIGHT * scale];
+    }
+
+    fn draw(&mut self, x: usize, y: usize, n: usize) -> Ran {
+        let (width, height) = if self.high_res { (WIDTH * 2, HEIGHT * 2) } else { (WIDTH, HEIGHT) };
+        // DXY0 is a 16x16 sprite in high resolution, two bytes to a row
+        let (rows, row_bytes) = if self.high_res && n == 0 { (16, 2) } else { (n, 1) };
+        let sprite = match self.at_i(rows * row_bytes) {
+            Some(range) => range,
+            None => return Ran::Failed,
+        };
+        // SUPER-CHIP only counts rows in high resolution
+        let count_rows = self.quirks.collision_rows && self.high_res;
+
+        let mut collided = 0;
+        for (row, bytes) in self.memory[sprite].chunks(row_bytes).enumerate() {
+            let bits = bytes.iter().fold(0u32, |bits, &byte| bits << 8 | byte as u32);
+            let cols = row_bytes * 8;
+            let mut py = y % height + row;
+            if py >= height {
+                if self.quirks.clip_sprites {
+                    // SUPER-CHIP counts clipped rows as collisions
+                    if count_rows {
+                        collided += rows - row;
+                    }
+                    break;
+                }
+                py %= height;
+            }
+            let mut row_collided = false;
+            for col in 0..cols {
+                let mut px = x % width + col;
+                if px >= width {
+                    if self.quirks.clip_sprites {
+                        break;
+                    }
+                    px %= width;
+                }
+                if bits & 1 << (cols - 1 - col) != 0 {
+                    row_collided |= self.screen[py * width + px];
+                    self.screen[py * width + px] ^= true;
+                }
+            }
+            collided += row_collided as usize;
+        }
+        self.v[0xF] = if count_rows { collided as Byte } else { (collided > 0) as Byte };
+        if self.quirks.display_wait {
+            Ran::Blanking
+        } else {
+            Ran::Running
+        }
+    }
+
+    fn tick(&mut self) {
+        self.delay = self.delay.saturating_sub(1);
+        self.sound = self.sound.saturating_sub(1);
+    }
+}
+
+/// Runs `rom` for up to `steps` instructions on the CPU and the reference,
+/// both with `quirks`, and returns how many ran before they both stopped
+/// or the steps ran out
+///
+/// The timers tick every [`INSTRUCTIONS_PER_TICK`] instructions, or straight
+/// after a draw with the `display_wait` quirk. Running
+/// stops once both interpreters halt, fail or wait for a key.
+///
+/// # Panics
+///
+/// Panics if the ROM is empty or doesn't fit in memory
+pub fn compare(rom: &[u8], quirks: Quirks, steps: u64) -> Result<u64, Divergence> {
+    let mut cpu = CpuBuilder::new().quirks(quirks).seed(SEED).build();
+    cpu.load_program(rom).expect("the ROM should fit in memory");
+
+    let state = cpu.state();
+    let mut reference = Reference {
+        memory: cpu.memory().to_vec(),
+        v: state.registers,
+        i: state.i,
+        pc: state.pc,
+        stack: Vec::new(),
+        delay: 0,
+        sound: 0,
+        high_res: false,
+        screen: vec![false; WIDTH * HEIGHT],
+        flags: [0; 8],
+        quirks,
+        rng: StdRng::seed_from_u64(SEED),
+    };
+
+    let mut since_tick = 0;
+    for step in 0..steps {
+        let address = reference.pc as usize;
+        let opcode = reference.opcode().unwrap_or(0);
+        let diverged = |difference| Err(Divergence { step, address, opcode, difference });
+
+        let expected = reference.step();
+        let actual = match cpu.step() {
+            Ok(CpuStatus::Running) => Ran::Running,
+            Ok(CpuStatus::WaitingForKey(_)) => Ran::Waiting,
+            Ok(CpuStatus::WaitingForVBlank) => Ran::Blanking,
+            Ok(CpuStatus::Halted) => Ran::Halted,
+            Err(_) => Ran::Failed,
+        };
+        if expected != actual {
+            return diverged(Difference::Status { expected: expected.describe(), actual: actual.describe() });
+        }
+        // a failed instruction leaves each interpreter wherever it stopped
+        if expected == Ran::Failed {
+            return Ok(step + 1);
+        }
+
+        let state = cpu.state();
+        if reference.pc != state.pc {
+            return diverged(Difference::Pc { expected: reference.pc, actual: state.pc });
+        }
+        if let Some(reg) = (0..16).find(|&reg| reference.v[reg] != state.registers[reg]) {
+            let (expected, actual) = (reference.v[reg], state.registers[reg]);
+            return diverged(Difference::Register { reg: reg as Byte, expected, actual });
+        }
+        if reference.i != state.i {
+            return diverged(Difference::I { expected: reference.i, actual: state.i });
+        }
+        if reference.stack != state.stack_in_use() {
+            return diverged(Difference::Stack { expected: reference.stack.clone(), actual: state.stack_in_use().to_vec() });
+        }
+        if (reference.delay, reference.sound) != (state.delay, state.sound) {
+            let (expected, actual) = ((reference.delay, reference.sound), (state.delay, state.sound));
+            return diverged(Difference::Timers { expected, actual });
+        }
+        if reference.memory != cpu.memory() {
+            let addr = reference.memory.iter().zip(cpu.memory()).position(|(expected, actual)| expected != actual);
+            let addr = addr.unwrap_or(0);
+            let (expected, actual) = (reference.memory[addr], cpu.memory()[addr]);
+            return diverged(Difference::Memory { addr, expected, actual });
+        }
+        if reference.screen != cpu.display().pixels() {
+            let (expected, actual) = (screen_hash(&reference.screen), screen_hash(cpu.display().pixels()));
+            return diverged(Difference::Screen { expected, actual });
+        }
+
+        if expected != Ran::Running && expected != Ran::Blanking {
+            return Ok(step + 1);
+        }
+        since_tick += 1;
+        if since_tick == INSTRUCTIONS_PER_TICK || expected == Ran::Blanking {
+            reference.tick();
+            cpu.tick_timers();
+            since_tick = 0;
+        }
+    }
+
+    Ok(steps)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matching_programs_run_until_they_stop() {
+        // LD V0, 3; LD DT, V0; LD V1, K
+        let rom = [0x60, 0x03, 0xF0, 0x15, 0xF1, 0x0A];
+        assert_eq!(compare(&rom, Quirks::default(), 100), Ok(3));
+
+        // JP 0x200, forever
+        assert_eq!(compare(&[0x12, 0x00], Quirks::default(), 100), Ok(100));
+    }
+
+    #[test]
+    fn divergences_name_the_first_difference() {
+        // LD V0, 1; SYS 0x300, a machine code call the reference skips
+        let rom = [0x60, 0x01, 0x03, 0x00, 0x00, 0x00];
+        let divergence = compare(&rom, Quirks::default(), 100).unwrap_err();
+
+        assert_eq!(divergence.step, 1);
+        assert_eq!((divergence.address, divergence.opcode), (0x202, 0x0300));
+        assert_eq!(divergence.difference, Difference::Pc { expected: 0x204, actual: 0x300 });
+        assert_eq!(divergence.to_string(), "Diverged at step 1, 0x202  0300  SYS 0x300: PC expected 0x204, got 0x300");
+    }
+
+    #[test]
+    fn high_resolution_sprites_match() {
+        let quirks = Quirks { collision_rows: true, clip_sprites: true, ..Quirks::default() };
+        // HIGH; LD V0, 120; LD V1, 56; LD I, 0x200; DRW V0, V1, 0 twice,
+        // clipped at the bottom right; LOW; DRW V0, V1, 0
+        let rom = [0x00, 0xFF, 0x60, 0x78, 0x61, 0x38, 0xA2, 0x00, 0xD0, 0x10, 0xD0, 0x10, 0x00, 0xFE, 0xD0, 0x10, 0x00, 0x00];
+        assert_eq!(compare(&rom, quirks, 100), Ok(9));
+    }
+
+    #[test]
+    fn status_differences_are_divergences() {
+        // 5XY1 isn't an instruction, but the CPU runs it as 5XY0
+        let rom = [0x50, 0x11, 0x00, 0x00];
+        let divergence = compare(&rom, Quirks::default(), 100).unwrap_err();
+
+        assert_eq!(divergence.difference, Difference::Status { expected: "stopped by an error", actual: "running" });
+    }
+}
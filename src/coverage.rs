@@ -0,0 +1,239 @@
+//! Instruction coverage: which instructions a run executed, and from where
+//!
+//! Set a [`Coverage`] as the CPU's observer, run the ROM, then ask it for a
+//! [`CoverageReport`] to find the handlers that never ran and the bytes of
+//! the ROM no instruction was run from, which are either data or dead code.
+//!
+//! # Example
+//!
+//! ```
+//! use std::sync::{Arc, Mutex};
+//!
+//! use chip_8::coverage::Coverage;
+//! use chip_8::CpuBuilder;
+//!
+//! // LD V0, 1; terminate; then two bytes of data
+//! let rom = [0x60, 0x01, 0x00, 0x00, 0xAB, 0xCD];
+//! let mut cpu = CpuBuilder::new().build();
+//! cpu.load_program(&rom).unwrap();
+//!
+//! let coverage = Arc::new(Mutex::new(Coverage::new()));
+//! cpu.set_observer(coverage.clone());
+//! while cpu.run().is_some() {}
+//!
+//! let report = coverage.lock().unwrap().report(0x200..0x200 + rom.len());
+//! assert_eq!(report.unexecuted, [0x204..0x206]);
+//! assert!(report.missing.contains(&"DXYN"));
+//! ```
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::ops::Range;
+
+use serde_json::json;
+
+use crate::instruction::{Instruction, PATTERNS};
+use crate::observer::Observer;
+
+/// Counts the instructions run, by address and by opcode pattern
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Coverage {
+    // how many instructions have run from each address that any have
+    hits: BTreeMap<usize, u64>,
+    // how many times each opcode pattern has run
+    opcodes: BTreeMap<&'static str, u64>,
+}
+
+impl Default for Coverage {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Coverage {
+    /// Coverage with nothing run yet
+    pub fn new() -> Coverage {
+        Coverage { hits: BTreeMap::new(), opcodes: BTreeMap::new() }
+    }
+
+    /// How many times an instruction has run from `addr`
+    pub fn hits(&self, addr: usize) -> u64 {
+        self.hits.get(&addr).copied().unwrap_or(0)
+    }
+
+    /// Forgets everything run so far, e.g. after resetting the CPU
+    pub fn clear(&mut self) {
+        *self = Coverage::new();
+    }
+
+    /// Sums up what has run, looking for unexecuted bytes in `rom`, the
+    /// addresses the ROM was loaded at
+    ///
+    /// A byte counts as executed if an instruction was run from it or the
+    /// byte before it.
+    pub fn report(&self, rom: Range<usize>) -> CoverageReport {
+        let covered = |addr: usize| self.hits.contains_key(&addr) || (addr > 0 && self.hits.contains_key(&(addr - 1)));
+
+        let mut unexecuted: Vec<Range<usize>> = Vec::new();
+        for addr in rom.clone().filter(|&addr| !covered(addr)) {
+            match unexecuted.last_mut() {
+                Some(run) if run.end == addr => run.end += 1,
+                _ => unexecuted.push(addr..addr + 1),
+            }
+        }
+
+        let rom_bytes = rom.len();
+        let uncovered: usize = unexecuted.iter().map(|run| run.len()).sum();
+        CoverageReport {
+            opcodes: self.opcodes.clone(),
+            missing: PATTERNS.iter().copied().filter(|pattern| !self.opcodes.contains_key(pattern)).collect(),
+            executed: self.hits.keys().copied().collect(),
+            covered_bytes: rom_bytes - uncovered,
+            rom_bytes,
+            unexecuted,
+        }
+    }
+}
+
+impl Observer for Coverage {
+    fn on_execute(&mut self, addr: usize, instruction: Instruction) {
+        *self.hits.entry(addr).or_insert(0) += 1;
+        *self.opcodes.entry(instruction.pattern()).or_insert(0) += 1;
+    }
+}
+
+/// What a run executed, from [`Coverage::report`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CoverageReport {
+    /// How many times each instruction ran, by opcode pattern like `"8XY4"`
+    pub opcodes: BTreeMap<&'static str, u64>,
+    /// The patterns of the instructions that never ran, in opcode order
+    pub missing: Vec<&'static str>,
+    /// Every address an instruction ran from, in or out of the ROM, lowest first
+    pub executed: Vec<usize>,
+    /// How many bytes of the ROM were part of an instruction that ran
+    pub covered_bytes: usize,
+    /// How many bytes the ROM is
+    pub rom_bytes: usize,
+    /// Runs of ROM addresses that were never part of an instruction that ran
+    pub unexecuted: Vec<Range<usize>>,
+}
+
+impl CoverageReport {
+    /// The share of ROM bytes that were executed, from 0 to 100
+    pub fn percent(&self) -> f64 {
+        if self.rom_bytes == 0 {
+            return 100.0;
+        }
+        self.covered_bytes as f64 * 100.0 / self.rom_bytes as f64
+    }
+
+    /// The report as JSON, with addresses as plain numbers and each
+    /// unexecuted run as a `start` and exclusive `end`
+    pub fn to_json(&self) -> String {
+        let unexecuted: Vec<_> = self.unexecuted.iter().map(|run| json!({ "start": run.start, "end": run.end })).collect();
+        json!({
+            "opcodes": self.opcodes,
+            "missing": self.missing,
+            "executed": self.executed,
+            "covered_bytes": self.covered_bytes,
+            "rom_bytes": self.rom_bytes,
+            "unexecuted": unexecuted,
+        })
+        .to_string()
+    }
+}
+
+impl fmt::Display for CoverageReport {
+    /// A short summary: the share of the ROM covered, where the unexecuted
+    /// bytes are, and the instructions that never ran
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Executed {} of {} ROM bytes ({:.1}%)", self.covered_bytes, self.rom_bytes, self.percent())?;
+
+        let unexecuted: Vec<String> = self
+            .unexecuted
+            .iter()
+            .map(|run| format!("0x{:03X}-0x{:03X}", run.start, run.end - 1))
+            .collect();
+        writeln!(f, "Unexecuted: {}", unexecuted.join(" "))?;
+        write!(f, "Never ran: {}", self.missing.join(" "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::{memory, CpuBuilder};
+
+    fn run(rom: &[u8], cycles: usize) -> Coverage {
+        // room for ROMs past 4K
+        let mut cpu = CpuBuilder::new().memory_size(memory::XO_CHIP_SIZE).build();
+        cpu.load_program(rom).unwrap();
+        let coverage = Arc::new(Mutex::new(Coverage::new()));
+        cpu.set_observer(coverage.clone());
+        cpu.run_cycles(cycles);
+
+        let coverage = coverage.lock().unwrap().clone();
+        coverage
+    }
+
+    #[test]
+    fn report_finds_skipped_code() {
+        // LD V0, 1; SE V0, 1; LD V1, 2; ADD V0, 1; JP 0x206
+        let rom = [0x60, 0x01, 0x30, 0x01, 0x61, 0x02, 0x70, 0x01, 0x12, 0x06];
+        let coverage = run(&rom, 10);
+        let report = coverage.report(0x200..0x200 + rom.len());
+
+        assert_eq!(coverage.hits(0x206), 4);
+        assert_eq!(report.opcodes["6XNN"], 1);
+        assert_eq!(report.opcodes["7XNN"], 4);
+        assert_eq!(report.executed, [0x200, 0x202, 0x206, 0x208]);
+        assert_eq!(report.unexecuted.len(), 1);
+        assert_eq!(report.unexecuted[0], 0x204..0x206);
+        assert_eq!(report.covered_bytes, 8);
+        assert!(report.missing.contains(&"8XY4"));
+        assert!(!report.missing.contains(&"1NNN"));
+        assert_eq!(report.missing.len(), PATTERNS.len() - 4);
+    }
+
+    #[test]
+    fn report_counts_misaligned_code() {
+        // JP 0x203; data byte; LD V0, 1; JP 0x203
+        let rom = [0x12, 0x03, 0xFF, 0x60, 0x01, 0x12, 0x03];
+        let report = run(&rom, 5).report(0x200..0x200 + rom.len());
+
+        assert_eq!(report.unexecuted.len(), 1);
+        assert_eq!(report.unexecuted[0], 0x202..0x203);
+        assert_eq!(report.executed, [0x200, 0x203, 0x205]);
+        assert!((report.percent() - 600.0 / 7.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn report_prints_summary_and_json() {
+        // LD V0, 1; terminate
+        let rom = [0x60, 0x01, 0x00, 0x00, 0xAB];
+        let report = run(&rom, 5).report(0x200..0x200 + rom.len());
+
+        let text = report.to_string();
+        assert!(text.starts_with("Executed 4 of 5 ROM bytes (80.0%)\nUnexecuted: 0x204-0x204\nNever ran: 0010 0011 00E0 00EE"));
+
+        let json: serde_json::Value = serde_json::from_str(&report.to_json()).unwrap();
+        assert_eq!(json["opcodes"]["0000"], 1);
+        assert_eq!(json["executed"], json!([0x200, 0x202]));
+        assert_eq!(json["unexecuted"], json!([{ "start": 0x204, "end": 0x205 }]));
+    }
+
+    #[test]
+    fn roms_running_past_4k_are_counted() {
+        // LD V0, 0 up to 0x1000, then terminate
+        let mut rom = [0x60, 0x00].repeat(0x700);
+        rom.extend_from_slice(&[0x00, 0x00]);
+        let coverage = run(&rom, 0x800);
+
+        assert_eq!(coverage.hits(0x1000), 1);
+        let report = coverage.report(0x200..0x200 + rom.len());
+        assert_eq!((report.covered_bytes, report.rom_bytes), (0xE02, 0xE02));
+    }
+}
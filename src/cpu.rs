@@ -0,0 +1,1748 @@
+//! The CPU itself: its state, how it's built, and the fetch, decode and
+//! execute loop that drives the instruction handlers in the other modules
+
+use std::borrow::Borrow;
+use std::fmt;
+use std::iter::FusedIterator;
+use std::ops::Range;
+use std::path::PathBuf;
+use std::thread;
+
+use rand::SeedableRng;
+use rand_chacha::ChaCha12Rng;
+
+use crate::flags::{FlagStorage, MemoryFlags};
+use crate::input::Keypad;
+use crate::instruction::Instruction;
+use crate::memory::{self, Battery, Bus};
+use crate::observer::Observer;
+use crate::screen::{Rect, Resolution};
+use crate::state::CpuStateDelta;
+use crate::{audio, rom_db, Address, Byte, CpuError, Display, Memory, OpCode, Quirks, Reg, Registers, RngState, RomError, Stack, PROGRAM_START};
+
+/// How many calls can be nested unless the builder says otherwise
+pub const DEFAULT_STACK_DEPTH: usize = 16;
+
+/// The deepest stack a CPU can be built with
+pub const MAX_STACK_DEPTH: usize = 64;
+
+/// Implements a CHIP-8 based CPU
+///
+/// Cloning gives a detached copy of the machine, for looking ahead or
+/// rolling back: everything the program can see comes along, but not the
+/// observer or the mapped devices, which belong to the original, and the
+/// copy keeps its RPL flags in memory starting from the original's.
+/// Comparing two CPUs compares the machines in the same way, leaving all
+/// three out; unseeded CPUs draw different random numbers, so they only
+/// compare equal to their own clones.
+pub struct Cpu {
+    pub(crate) program_counter: usize,
+    pub(crate) registers: Registers,
+    pub(crate) memory: Memory,
+    pub(crate) stack: Stack,
+    pub(crate) stack_pointer: usize,
+    // how many entries of `stack` calls can use
+    pub(crate) stack_depth: usize,
+    pub(crate) i: Address,
+    // the top byte of MegaChip's 24 bit I, only set by 01NN NNNN
+    pub(crate) i_high: Byte,
+    // MegaChip's sprite width and height, 0 meaning 256
+    pub(crate) sprite_size: (Byte, Byte),
+    pub(crate) delay_timer: Byte,
+    pub(crate) sound_timer: Byte,
+    // XO-CHIP's audio pattern, once F002 has loaded one, and FX3A's pitch
+    pub(crate) audio_pattern: Option<[Byte; 16]>,
+    pub(crate) pitch: Byte,
+    pub(crate) display: Display,
+    pub(crate) keys: Keypad,
+    // the register FX0A is waiting to put a key in
+    pub(crate) waiting_for_key: Option<Reg>,
+    // set by DXYN with the `display_wait` quirk until the next timer tick
+    pub(crate) waiting_for_vblank: bool,
+    pub(crate) quirks: Quirks,
+    pub(crate) on_unknown_opcode: OnUnknownOpcode,
+    pub(crate) on_zero_opcode: OnZeroOpcode,
+    // what the CPU was built with, for `reset`
+    pub(crate) program_start: usize,
+    pub(crate) initial_registers: Registers,
+    pub(crate) initial_memory: Memory,
+    // decoded instructions by address, only kept for the cached engine
+    pub(crate) decode_cache: Option<Vec<Option<Instruction>>>,
+    pub(crate) observer: Option<Box<dyn Observer>>,
+    // devices standing in for ranges of memory, checked in order
+    pub(crate) mapped: Vec<(Range<usize>, Box<dyn Bus>)>,
+    // the RPL flags, which `reset` leaves alone
+    pub(crate) flags: Box<dyn FlagStorage>,
+    // instructions run since the CPU was built or reset
+    pub(crate) cycles: u64,
+    // where CXNN's numbers come from, restarted from `seed` on reset if set
+    pub(crate) rng: ChaCha12Rng,
+    pub(crate) seed: Option<u64>,
+    // the part of the screen drawn on since `take_touched`
+    pub(crate) touched: Option<Rect>,
+}
+
+/// How the CPU turns opcodes into instructions
+///
+/// Both engines behave identically; the cached one is faster for programs
+/// that run the same code over and over.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ExecutionEngine {
+    /// Decodes every opcode as it's run
+    #[default]
+    Interpreter,
+    /// Remembers each decoded instruction by address, only decoding again
+    /// after the program writes to the memory it came from
+    Cached,
+}
+
+/// What the CPU does with an opcode that isn't part of the instruction set
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OnUnknownOpcode {
+    /// Fails with [`CpuError::UnknownOpcode`], leaving the program counter
+    /// on it
+    #[default]
+    Error,
+    /// Steps over it as if it did nothing, for exploring damaged or exotic
+    /// ROMs; with an observer or logging on, the opcodes skipped still show
+    /// which extension the ROM needs
+    Skip,
+    /// Treats it as the end of the program
+    Halt,
+}
+
+/// What the CPU does with opcode 0000
+///
+/// Ending programs with 0000 is this emulator's own invention, handy for
+/// tests and tools, but a real ROM that reaches 0000 has run into zeroed
+/// memory. [`CpuBuilder`] keeps to halting; [`Emulator::default`](crate::Emulator)
+/// fails instead, since a game getting there has gone wrong.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OnZeroOpcode {
+    /// Treats it as the end of the program
+    #[default]
+    Halt,
+    /// Fails with [`CpuError::ZeroOpcode`], leaving the program counter on
+    /// it
+    Error,
+    /// Steps over it as if it did nothing, for tools that pad with zeroes
+    Nop,
+}
+
+/// What the CPU is doing after an instruction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CpuStatus {
+    /// Ready for the next instruction
+    Running,
+    /// FX0A is waiting for a key to put in the register
+    ///
+    /// Nothing runs until [`Cpu::set_key`] finishes the wait, but the timers
+    /// go on ticking, so a delay set before the wait has run down by as
+    /// many frames as it took.
+    WaitingForKey(Reg),
+    /// A sprite was drawn with the `display_wait` quirk, and nothing more
+    /// runs until the timers next tick
+    WaitingForVBlank,
+    /// The program terminated
+    Halted,
+}
+
+/// Why [`Cpu::run_until_blocked`] stopped
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopReason {
+    /// FX0A is waiting for a key to be pressed and released
+    WaitingForKey,
+    /// The `display_wait` quirk is holding the program up until the timers
+    /// next tick, which ends the frame
+    WaitingForVBlank,
+    /// All of the cycles it was given have been run
+    BudgetSpent,
+    /// The program terminated
+    Halted,
+    /// An instruction failed
+    Error(CpuError),
+}
+
+/// Safety rails for [`Cpu::run_until_halt`], so a program that never halts
+/// can't hang whoever's running it
+///
+/// # Examples
+/// ```
+/// use chip_8::Watchdog;
+///
+/// // give up after a million instructions, yielding the thread every 10,000
+/// let watchdog = Watchdog::new(1_000_000).yield_every(10_000);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Watchdog {
+    max_instructions: u64,
+    yield_every: Option<u64>,
+}
+
+impl Default for Watchdog {
+    /// Ten million instructions, over four hours of a real CHIP-8's time,
+    /// without yielding
+    fn default() -> Self {
+        Watchdog::new(10_000_000)
+    }
+}
+
+impl Watchdog {
+    /// Gives up after `max_instructions` have run
+    pub fn new(max_instructions: u64) -> Watchdog {
+        Watchdog { max_instructions, yield_every: None }
+    }
+
+    /// Yields the thread every `instructions`, so a long run lets others on
+    /// the same core have a turn
+    pub fn yield_every(mut self, instructions: u64) -> Watchdog {
+        self.yield_every = Some(instructions.max(1));
+        self
+    }
+}
+
+/// What [`Cpu::run_until_halt`] did
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RunSummary {
+    /// How many instructions ran, not counting the halt
+    pub instructions: u64,
+    /// Why it stopped, [`StopReason::BudgetSpent`] if the watchdog stopped it
+    pub reason: StopReason,
+}
+
+/// Constructs a CPU with defaults, allowing for registers and memory to be
+/// optionally set
+///
+/// Each setting takes the builder and hands it back, so they chain straight
+/// off `new` and into `build`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[must_use = "the builder's settings do nothing until it's built"]
+pub struct CpuBuilder {
+    registers: Registers,
+    program: Vec<Byte>,
+    engine: ExecutionEngine,
+    quirks: Quirks,
+    on_unknown_opcode: OnUnknownOpcode,
+    on_zero_opcode: OnZeroOpcode,
+    program_start: usize,
+    seed: Option<u64>,
+    rng_state: Option<RngState>,
+    stack_depth: usize,
+    memory_size: usize,
+    battery: Option<PathBuf>,
+}
+
+impl Default for CpuBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// TODO: link to the 'build' function in the docs for 'new'
+impl CpuBuilder {
+    /// Makes a new CpuBuilder, defaulting to empty registers and memory
+    /// 
+    /// call `build` to generate a CPU from this builder
+    /// # Examples
+    /// ```
+    /// use chip_8::CpuBuilder;
+    ///
+    /// let default_builder = CpuBuilder::new();
+    /// ```
+    pub fn new() -> CpuBuilder {
+        CpuBuilder {
+            registers: [0; 16],
+            program: Vec::new(),
+            engine: ExecutionEngine::default(),
+            quirks: Quirks::default(),
+            on_unknown_opcode: OnUnknownOpcode::default(),
+            on_zero_opcode: OnZeroOpcode::default(),
+            program_start: PROGRAM_START,
+            seed: None,
+            rng_state: None,
+            stack_depth: DEFAULT_STACK_DEPTH,
+            memory_size: memory::CHIP_8_SIZE,
+            battery: None,
+        }
+    }
+
+    /// Set registers on the builder, from V0 up, leaving any not given at 0
+    ///
+    /// Takes anything that iterates over bytes, such as an array, a slice or
+    /// a `Vec`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there are more than 16 values
+    ///
+    /// # Examples
+    /// ```
+    /// use chip_8::CpuBuilder;
+    ///
+    /// let cpu = CpuBuilder::new().registers(&[1, 2, 3]).build();
+    /// assert_eq!(cpu.registers(2), 3);
+    /// assert_eq!(cpu.registers(15), 0);
+    /// ```
+    pub fn registers<R>(mut self, registers: R) -> CpuBuilder
+    where
+        R: IntoIterator,
+        R::Item: Borrow<Byte>,
+    {
+        self.registers = [0; 16];
+        let mut values = registers.into_iter();
+        for (register, value) in self.registers.iter_mut().zip(&mut values) {
+            *register = *value.borrow();
+        }
+        assert!(values.next().is_none(), "there are only 16 registers");
+        self
+    }
+
+    /// Set memory on the builder, from the program's bytes
+    ///
+    /// Index 0 lands at the program start, and anything past the end of
+    /// memory is cut off; [`Cpu::load_program`] refuses ROMs that don't fit
+    /// instead. The bytes are only borrowed, so a ROM can be passed as it
+    /// was read.
+    ///
+    /// # Examples
+    /// ```
+    /// use chip_8::CpuBuilder;
+    ///
+    /// let rom = vec![0x60, 0x01];
+    /// let cpu = CpuBuilder::new().memory(&rom).build();
+    /// assert_eq!(cpu.memory()[0x200..0x202], rom[..]);
+    /// ```
+    pub fn memory<P: AsRef<[Byte]>>(mut self, program: P) -> CpuBuilder {
+        self.program.clear();
+        self.program.extend_from_slice(program.as_ref());
+        self
+    }
+
+    /// Set how many bytes of memory the CPU has, defaulting to the 4K of
+    /// [`memory::CHIP_8_SIZE`]
+    ///
+    /// XO-CHIP programs expect [`memory::XO_CHIP_SIZE`], all 64K that I
+    /// can reach, and MegaChip programs [`memory::MEGA_CHIP_SIZE`].
+    ///
+    /// [`build`](CpuBuilder::build) panics if `size` is more than the 16MB
+    /// MegaChip's 24 bit I reaches, or doesn't leave room past the program
+    /// start for a program.
+    ///
+    /// # Examples
+    /// ```
+    /// use chip_8::{memory, CpuBuilder};
+    ///
+    /// let mut cpu = CpuBuilder::new().memory_size(memory::XO_CHIP_SIZE).build();
+    /// assert_eq!(cpu.memory().len(), 0x10000);
+    /// assert!(cpu.load_program(&[0x12; 0x2000]).is_ok());
+    /// ```
+    pub fn memory_size(mut self, size: usize) -> CpuBuilder {
+        self.memory_size = size;
+        self
+    }
+
+    /// Set the execution engine on the builder, defaulting to the interpreter
+    ///
+    /// # Examples
+    /// ```
+    /// use chip_8::{CpuBuilder, ExecutionEngine};
+    ///
+    /// let cpu = CpuBuilder::new().engine(ExecutionEngine::Cached).build();
+    /// ```
+    pub fn engine(mut self, engine: ExecutionEngine) -> CpuBuilder {
+        self.engine = engine;
+        self
+    }
+
+    /// Set what the CPU does with unknown opcodes, defaulting to failing
+    ///
+    /// # Examples
+    /// ```
+    /// use chip_8::{CpuBuilder, OnUnknownOpcode};
+    ///
+    /// let mut memory = [0; 4096];
+    /// // E000 isn't an instruction; LD V0, 1
+    /// memory[..4].copy_from_slice(&[0xE0, 0x00, 0x60, 0x01]);
+    /// let mut cpu = CpuBuilder::new().memory(memory).on_unknown_opcode(OnUnknownOpcode::Skip).build();
+    ///
+    /// cpu.run_cycles(2);
+    /// assert_eq!(cpu.registers(0), 1);
+    /// ```
+    pub fn on_unknown_opcode(mut self, policy: OnUnknownOpcode) -> CpuBuilder {
+        self.on_unknown_opcode = policy;
+        self
+    }
+
+    /// Set what the CPU does with opcode 0000, defaulting to halting
+    ///
+    /// # Examples
+    /// ```
+    /// use chip_8::{CpuBuilder, CpuError, OnZeroOpcode};
+    ///
+    /// // LD V0, 1, then zeroed memory
+    /// let mut cpu = CpuBuilder::new().memory([0x60, 0x01]).on_zero_opcode(OnZeroOpcode::Error).build();
+    ///
+    /// cpu.step().unwrap();
+    /// assert_eq!(cpu.step(), Err(CpuError::ZeroOpcode(0x202)));
+    /// ```
+    pub fn on_zero_opcode(mut self, policy: OnZeroOpcode) -> CpuBuilder {
+        self.on_zero_opcode = policy;
+        self
+    }
+
+    /// Set which interpreter quirks the CPU follows, defaulting to none
+    pub fn quirks(mut self, quirks: Quirks) -> CpuBuilder {
+        self.quirks = quirks;
+        self
+    }
+
+    /// Set where the program is loaded and starts running, defaulting to 0x200
+    ///
+    /// ETI-660 programs expect 0x600. Index 0 of the memory passed to
+    /// `memory` lands at this address, so anything past the first
+    /// `memory_size - addr` bytes is cut off, and
+    /// [`build`](CpuBuilder::build) panics if `addr` is past the end of
+    /// memory.
+    ///
+    /// # Examples
+    /// ```
+    /// use chip_8::CpuBuilder;
+    ///
+    /// let cpu = CpuBuilder::new().program_start(0x600).build();
+    /// assert_eq!(cpu.state().pc, 0x600);
+    /// ```
+    pub fn program_start(mut self, addr: Address) -> CpuBuilder {
+        self.program_start = addr as usize;
+        self
+    }
+
+    /// Seed the random numbers CXNN draws from, so runs repeat exactly,
+    /// defaulting to a fresh seed from the OS
+    ///
+    /// # Examples
+    /// ```
+    /// use chip_8::CpuBuilder;
+    ///
+    /// let mut cpus = [CpuBuilder::new().seed(8).build(), CpuBuilder::new().seed(8).build()];
+    /// for cpu in &mut cpus {
+    ///     // RND V0, 0xFF
+    ///     cpu.load_program(&[0xC0, 0xFF]).unwrap();
+    ///     cpu.run();
+    /// }
+    /// assert_eq!(cpus[0].registers(0), cpus[1].registers(0));
+    /// ```
+    pub fn seed(mut self, seed: u64) -> CpuBuilder {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Pick the random numbers up from a [`RngState`] saved from another
+    /// CPU, so it goes on to draw the same ones
+    ///
+    /// Takes over from [`seed`](CpuBuilder::seed) to begin with, though a
+    /// seeded CPU still starts over from its seed on reset.
+    pub fn rng_state(mut self, state: RngState) -> CpuBuilder {
+        self.rng_state = Some(state);
+        self
+    }
+
+    /// Set how many calls can be nested, defaulting to
+    /// [`DEFAULT_STACK_DEPTH`]
+    ///
+    /// The COSMAC VIP's interpreter had room for 12; most later ones have 16
+    /// or more. A call past the limit fails with
+    /// [`CpuError::StackOverflow`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `depth` is 0 or over [`MAX_STACK_DEPTH`]
+    ///
+    /// # Examples
+    /// ```
+    /// use chip_8::{CpuBuilder, CpuError};
+    ///
+    /// let mut cpu = CpuBuilder::new().stack_depth(12).build();
+    /// // CALL 0x200, over and over
+    /// cpu.load_program(&[0x22, 0x00]).unwrap();
+    /// for _ in 0..12 {
+    ///     cpu.step().unwrap();
+    /// }
+    /// assert_eq!(cpu.step(), Err(CpuError::StackOverflow(12)));
+    /// assert_eq!(cpu.stack_frames(), [0x202; 12]);
+    /// ```
+    pub fn stack_depth(mut self, depth: usize) -> CpuBuilder {
+        assert!((1..=MAX_STACK_DEPTH).contains(&depth), "stack depth {} isn't from 1 to {}", depth, MAX_STACK_DEPTH);
+        self.stack_depth = depth;
+        self
+    }
+
+    /// Keep the last page of memory, [`memory::PAGE_BYTES`] long, in a file
+    /// at `path`, read when the CPU is built and written back when it's
+    /// dropped
+    ///
+    /// Programs can save high scores and progress there that outlive the
+    /// emulator, as with a cartridge's battery-backed RAM. The page stays
+    /// the same through resets and new ROMs, and covers whatever the ROM
+    /// would have loaded there. The file is only written if the program
+    /// wrote to the page, and a file that can't be read is left alone.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use chip_8::CpuBuilder;
+    ///
+    /// let rom = std::fs::read("roms/homebrew.ch8").unwrap();
+    /// let cpu = CpuBuilder::new().memory(&rom).battery("roms/homebrew.sav").build();
+    /// ```
+    pub fn battery<P: Into<PathBuf>>(mut self, path: P) -> CpuBuilder {
+        self.battery = Some(path.into());
+        self
+    }
+
+    /// Picks the quirks for a ROM from the [`rom_db`], if it's a known one
+    ///
+    /// Unknown ROMs leave the builder as it was. This doesn't load the ROM,
+    /// that still needs passing to `memory`.
+    ///
+    /// # Examples
+    /// ```
+    /// use chip_8::CpuBuilder;
+    ///
+    /// let rom = include_bytes!("../roms/sierpinski.ch8");
+    /// let cpu = CpuBuilder::new().auto_configure(rom).memory(rom).build();
+    /// ```
+    pub fn auto_configure(mut self, rom: &[u8]) -> CpuBuilder {
+        if let Some(entry) = rom_db::lookup(rom) {
+            self.quirks = entry.quirks;
+        }
+        self
+    }
+
+    /// Generates a new CPU from this builder
+    ///
+    /// Sets registers and memory if those have been passed in
+    /// 
+    /// or defaults them to [0; 16] and all zeroes, respectively
+    ///
+    /// The program in memory is loaded at the program start, 0x200 unless
+    /// set otherwise
+    ///
+    /// # Panics
+    ///
+    /// Panics if the memory size is more than MegaChip's 16MB, or the
+    /// program start isn't inside memory
+    /// 
+    /// # Examples
+    /// ```
+    /// use chip_8::CpuBuilder;
+    ///
+    /// let default_cpu = CpuBuilder::new().build();
+    ///
+    /// let mut registers = [0; 16]; registers[5] = 12;
+    /// let mut memory = [0; 4096]; memory[100] = 0x80;
+    /// let specified_cpu = CpuBuilder::new()
+    ///                         .registers(registers)
+    ///                         .memory(memory)
+    ///                         .build();
+    /// ```
+    pub fn build(self) -> Cpu {
+        assert!(self.memory_size <= memory::MEGA_CHIP_SIZE, "0x{:X} bytes is more memory than I can reach", self.memory_size);
+        assert!(
+            self.program_start < self.memory_size,
+            "program start 0x{:X} is past the end of 0x{:X} bytes of memory",
+            self.program_start,
+            self.memory_size
+        );
+        let memory = memory::boot(&self.program, self.program_start, self.memory_size);
+        let registers = self.registers;
+
+        let mut cpu = Cpu {
+            program_counter: self.program_start,
+            registers,
+            memory: memory.clone(),
+            stack: [0; MAX_STACK_DEPTH],
+            stack_pointer: 0,
+            stack_depth: self.stack_depth,
+            i: 0,
+            i_high: 0,
+            sprite_size: (0, 0),
+            delay_timer: 0,
+            sound_timer: 0,
+            audio_pattern: None,
+            pitch: audio::DEFAULT_PITCH,
+            display: Display::new(),
+            keys: Keypad::new(),
+            waiting_for_key: None,
+            waiting_for_vblank: false,
+            quirks: self.quirks,
+            on_unknown_opcode: self.on_unknown_opcode,
+            on_zero_opcode: self.on_zero_opcode,
+            program_start: self.program_start,
+            initial_registers: registers,
+            decode_cache: match self.engine {
+                ExecutionEngine::Interpreter => None,
+                ExecutionEngine::Cached => Some(vec![None; memory.len()]),
+            },
+            initial_memory: memory,
+            observer: None,
+            mapped: Vec::new(),
+            flags: Box::new(MemoryFlags::default()),
+            cycles: 0,
+            rng: match (self.rng_state, self.seed) {
+                (Some(state), _) => state.generator(),
+                (None, Some(seed)) => ChaCha12Rng::seed_from_u64(seed),
+                (None, None) => ChaCha12Rng::from_entropy(),
+            },
+            seed: self.seed,
+            touched: None,
+        };
+        if let Some(path) = self.battery {
+            let start = self.memory_size.saturating_sub(memory::PAGE_BYTES);
+            cpu.map(start..self.memory_size, Battery::open(path, start));
+        }
+        cpu
+    }
+}
+
+impl Default for Cpu {
+    /// The CPU [`CpuBuilder::new`] builds, with nothing loaded
+    fn default() -> Self {
+        CpuBuilder::new().build()
+    }
+}
+
+impl Clone for Cpu {
+    /// # Examples
+    /// ```
+    /// use chip_8::CpuBuilder;
+    ///
+    /// // LD V0, 1; ADD V0, 1; JP 0x202
+    /// let mut cpu = CpuBuilder::new().memory([0x60, 0x01, 0x70, 0x01, 0x12, 0x02]).build();
+    /// cpu.run_cycles(2);
+    ///
+    /// let mut ahead = cpu.clone();
+    /// ahead.run_cycles(20);
+    /// assert_eq!((cpu.registers(0), ahead.registers(0)), (2, 12));
+    /// ```
+    fn clone(&self) -> Self {
+        Cpu {
+            program_counter: self.program_counter,
+            registers: self.registers,
+            memory: self.memory.clone(),
+            stack: self.stack,
+            stack_pointer: self.stack_pointer,
+            stack_depth: self.stack_depth,
+            i: self.i,
+            i_high: self.i_high,
+            sprite_size: self.sprite_size,
+            delay_timer: self.delay_timer,
+            sound_timer: self.sound_timer,
+            audio_pattern: self.audio_pattern,
+            pitch: self.pitch,
+            display: self.display.clone(),
+            keys: self.keys,
+            waiting_for_key: self.waiting_for_key,
+            waiting_for_vblank: self.waiting_for_vblank,
+            quirks: self.quirks,
+            on_unknown_opcode: self.on_unknown_opcode,
+            on_zero_opcode: self.on_zero_opcode,
+            program_start: self.program_start,
+            initial_registers: self.initial_registers,
+            initial_memory: self.initial_memory.clone(),
+            // with the mapped devices gone, what was decoded through them
+            // may not be what's in memory
+            decode_cache: self.decode_cache.as_ref().map(|cache| vec![None; cache.len()]),
+            observer: None,
+            mapped: Vec::new(),
+            flags: Box::new(MemoryFlags::from(self.flags.load())),
+            cycles: self.cycles,
+            rng: self.rng.clone(),
+            seed: self.seed,
+            touched: self.touched,
+        }
+    }
+}
+
+impl Cpu {
+    /// Goes back to `earlier`, a clone of this CPU, keeping the observer,
+    /// mapped devices and flag storage attached to this one
+    ///
+    /// Unlike [`restore`](Cpu::restore), everything a clone carries comes
+    /// back, the random numbers and held keys included, so the frames after
+    /// run exactly as they did from there.
+    pub(crate) fn roll_back(&mut self, earlier: &Cpu) {
+        let observer = self.observer.take();
+        let mapped = std::mem::take(&mut self.mapped);
+        let flags = std::mem::replace(&mut self.flags, Box::new(MemoryFlags::default()));
+
+        *self = earlier.clone();
+        self.observer = observer;
+        self.mapped = mapped;
+        self.flags = flags;
+    }
+}
+
+impl PartialEq for Cpu {
+    fn eq(&self, other: &Self) -> bool {
+        self.program_counter == other.program_counter
+            && self.registers == other.registers
+            && self.memory == other.memory
+            && self.stack_frames() == other.stack_frames()
+            && self.stack_depth == other.stack_depth
+            && self.i == other.i
+            && self.i_high == other.i_high
+            && self.sprite_size == other.sprite_size
+            && self.delay_timer == other.delay_timer
+            && self.sound_timer == other.sound_timer
+            && self.audio_pattern == other.audio_pattern
+            && self.pitch == other.pitch
+            && self.display == other.display
+            && self.keys == other.keys
+            && self.waiting_for_key == other.waiting_for_key
+            && self.waiting_for_vblank == other.waiting_for_vblank
+            && self.quirks == other.quirks
+            && self.on_unknown_opcode == other.on_unknown_opcode
+            && self.on_zero_opcode == other.on_zero_opcode
+            && self.program_start == other.program_start
+            && self.initial_registers == other.initial_registers
+            && self.initial_memory == other.initial_memory
+            && self.flags.load() == other.flags.load()
+            && self.cycles == other.cycles
+            && self.rng == other.rng
+            && self.seed == other.seed
+    }
+}
+
+impl fmt::Debug for Cpu {
+    /// Everything but memory and the screen, which are too big to be any
+    /// help printed out
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mapped: Vec<_> = self.mapped.iter().map(|(range, _)| range).collect();
+        f.debug_struct("Cpu")
+            .field("program_counter", &self.program_counter)
+            .field("registers", &self.registers)
+            .field("i", &self.i_address())
+            .field("stack", &self.stack_frames())
+            .field("delay_timer", &self.delay_timer)
+            .field("sound_timer", &self.sound_timer)
+            .field("waiting_for_key", &self.waiting_for_key)
+            .field("waiting_for_vblank", &self.waiting_for_vblank)
+            .field("quirks", &self.quirks)
+            .field("cycles", &self.cycles)
+            .field("flags", &self.flags.load())
+            .field("observed", &self.observer.is_some())
+            .field("mapped", &mapped)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Cpu {
+    // TODO: add some simple doc examples for doctests
+    /// Runs the program set in memory according to the CHIP-8 spec
+    ///
+    /// Does nothing while waiting for a key, see [`Cpu::set_key`]
+    ///
+    /// # Panics
+    ///
+    /// Panics if the instruction fails, use `step` to get the error instead
+    pub fn run(&mut self) -> Option<()> {
+        match self.step() {
+            Ok(CpuStatus::Halted) => None,
+            Ok(_) => Some(()),
+            Err(err) => panic!("{}", err),
+        }
+    }
+
+    /// Runs the program until it halts, the watchdog gives up, an
+    /// instruction fails, or it's held up waiting for a key or the timers
+    ///
+    /// [`run`](Cpu::run) only ever runs the one instruction, leaving the
+    /// loop to the caller; this is the loop, with a limit. Nothing can
+    /// press a key or tick the timers partway through, so a wait ends it
+    /// rather than spinning until the watchdog does.
+    ///
+    /// # Examples
+    /// ```
+    /// use chip_8::{CpuBuilder, StopReason, Watchdog};
+    ///
+    /// // ADD V0, 1; SE V0, 100; JP 0x200; terminate
+    /// let mut cpu = CpuBuilder::new().memory([0x70, 0x01, 0x30, 0x64, 0x12, 0x00, 0x00, 0x00]).build();
+    /// let summary = cpu.run_until_halt(Watchdog::default());
+    /// assert_eq!((summary.instructions, summary.reason), (299, StopReason::Halted));
+    ///
+    /// // JP 0x200, forever
+    /// let mut cpu = CpuBuilder::new().memory([0x12, 0x00]).build();
+    /// assert_eq!(cpu.run_until_halt(Watchdog::new(500)).reason, StopReason::BudgetSpent);
+    /// ```
+    pub fn run_until_halt(&mut self, watchdog: Watchdog) -> RunSummary {
+        let mut instructions = 0;
+        let reason = loop {
+            if instructions >= watchdog.max_instructions {
+                warn!("the watchdog stopped the program after {} instructions", instructions);
+                break StopReason::BudgetSpent;
+            }
+            match self.step_instruction() {
+                Ok((CpuStatus::Halted, _)) => break StopReason::Halted,
+                Ok((_, Some(_))) => instructions += 1,
+                Ok((CpuStatus::WaitingForVBlank, None)) => break StopReason::WaitingForVBlank,
+                Ok((_, None)) => break StopReason::WaitingForKey,
+                Err(err) => break StopReason::Error(err),
+            }
+            if watchdog.yield_every.is_some_and(|every| instructions % every == 0) {
+                thread::yield_now();
+            }
+        };
+        RunSummary { instructions, reason }
+    }
+
+    /// Runs a single instruction, returning what the CPU is doing now
+    ///
+    /// Never panics, whatever is in memory. If the instruction fails the
+    /// program counter is left on it.
+    ///
+    /// # Examples
+    /// ```
+    /// use chip_8::{CpuBuilder, CpuError, CpuStatus};
+    ///
+    /// let mut memory = [0; 4096];
+    /// // LD V0, 1; RET
+    /// memory[..4].copy_from_slice(&[0x60, 0x01, 0x00, 0xEE]);
+    /// let mut cpu = CpuBuilder::new().memory(memory).build();
+    ///
+    /// assert_eq!(cpu.step(), Ok(CpuStatus::Running));
+    /// assert_eq!(cpu.step(), Err(CpuError::StackUnderflow));
+    /// assert_eq!(cpu.state().pc, 0x202);
+    /// ```
+    pub fn step(&mut self) -> Result<CpuStatus, CpuError> {
+        self.step_instruction().map(|(status, _)| status)
+    }
+
+    /// Like `step`, also handing back the instruction that ran, or `None`
+    /// when the CPU was held up and nothing did
+    fn step_instruction(&mut self) -> Result<(CpuStatus, Option<Instruction>), CpuError> {
+        if let Some(x) = self.waiting_for_key {
+            return Ok((CpuStatus::WaitingForKey(x), None));
+        }
+        if self.waiting_for_vblank {
+            return Ok((CpuStatus::WaitingForVBlank, None));
+        }
+
+        let address = self.program_counter;
+        let instruction = self.fetch()?;
+        trace!("0x{:03X}  {:04X}  {}", address, instruction.encode(), instruction);
+        if let Some(observer) = &mut self.observer {
+            observer.on_execute(address, instruction);
+        }
+        self.program_counter += 2;
+
+        match self.execute(instruction) {
+            Ok(CpuStatus::Halted) => Ok((CpuStatus::Halted, Some(instruction))),
+            Ok(status) => {
+                self.cycles += 1;
+                Ok((status, Some(instruction)))
+            }
+            Err(err) => {
+                warn!("0x{:03X}  {}: {}", address, instruction, err);
+                self.program_counter = address;
+                Err(err)
+            }
+        }
+    }
+
+    /// Carries out an instruction, the program counter already being past it
+    fn execute(&mut self, instruction: Instruction) -> Result<CpuStatus, CpuError> {
+        match instruction {
+            Instruction::Halt => match self.on_zero_opcode {
+                OnZeroOpcode::Halt => return Ok(CpuStatus::Halted),
+                OnZeroOpcode::Error => return Err(CpuError::ZeroOpcode(self.program_counter - 2)),
+                OnZeroOpcode::Nop => {}
+            },
+            Instruction::MegaOff => self.set_resolution(Resolution::Low),
+            Instruction::MegaOn => self.set_resolution(Resolution::Mega),
+            Instruction::Clear => {
+                self.display.clear();
+                self.touch(self.display.rect());
+            }
+            Instruction::Return => self.ret()?,
+            Instruction::LowRes => self.set_resolution(Resolution::Low),
+            Instruction::HighRes => self.set_resolution(Resolution::High),
+            Instruction::LongI(nn) => self.set_long_i(nn)?,
+            Instruction::LoadPalette(nn) => self.load_palette(nn)?,
+            Instruction::SpriteWidth(nn) => self.sprite_size.0 = nn,
+            Instruction::SpriteHeight(nn) => self.sprite_size.1 = nn,
+            Instruction::Alpha(nn) => self.display.set_alpha(nn),
+            Instruction::Sys(nnn) => self.call(nnn)?, // todo: is this right?
+            Instruction::Jump(nnn) => self.jump(nnn),
+            Instruction::Call(nnn) => self.call(nnn)?,
+            Instruction::SkipEqual(x, nn) => self.skip_equal(x, nn),
+            Instruction::SkipNotEqual(x, nn) => self.skip_not_equal(x, nn),
+            Instruction::SkipEqualReg(x, y) => self.skip_equal_reg(x, y),
+            Instruction::SetRegister(x, nn) => self.set_register(x, nn),
+            Instruction::Add(x, nn) => self.add(x, nn),
+            Instruction::Assign(x, y) => self.assign(x, y),
+            Instruction::Or(x, y) => self.or(x, y),
+            Instruction::And(x, y) => self.and(x, y),
+            Instruction::Xor(x, y) => self.xor(x, y),
+            Instruction::AddReg(x, y) => self.add_xy(x, y),
+            Instruction::SubReg(x, y) => self.sub_xy(x, y),
+            Instruction::ShiftRight(x, y) => self.shift_right(x, y),
+            Instruction::SubN(x, y) => self.sub_n(x, y),
+            Instruction::ShiftLeft(x, y) => self.shift_left(x, y),
+            Instruction::SkipNotEqualReg(x, y) => self.skip_not_equal_reg(x, y),
+            Instruction::SetI(nnn) => self.set_i(nnn),
+            Instruction::JumpReg(nnn) => self.jump_reg(nnn),
+            Instruction::Rand(x, nn) => self.rand(x, nn),
+            Instruction::SkipKeyPressed(x) => self.skip_key_pressed(x),
+            Instruction::SkipKeyNotPressed(x) => self.skip_key_not_pressed(x),
+            Instruction::GetDelay(x) => self.get_delay(x),
+            Instruction::WaitKey(x) => self.waiting_for_key = Some(x),
+            Instruction::SetDelay(x) => self.set_delay(x),
+            Instruction::SetSound(x) => self.set_sound(x),
+            Instruction::LoadAudio => self.load_audio()?,
+            Instruction::SetPitch(x) => self.set_pitch(x),
+            Instruction::AddI(x) => self.set_i_reg(x),
+            Instruction::SetISprite(x) => self.set_i_sprite(x),
+            Instruction::Bcd(x) => self.bcd(x)?,
+            Instruction::RegDump(x) => self.reg_dump(x)?,
+            Instruction::RegLoad(x) => self.reg_load(x)?,
+            Instruction::SaveFlags(x) => self.save_flags(x)?,
+            Instruction::LoadFlags(x) => self.load_flags(x),
+            Instruction::Draw(x, y, d) => {
+                self.draw(x, y, d)?;
+                if self.quirks.display_wait {
+                    self.waiting_for_vblank = true;
+                    return Ok(CpuStatus::WaitingForVBlank);
+                }
+            }
+            Instruction::Unknown(opcode) => {
+                let address = self.program_counter - 2;
+                match self.on_unknown_opcode {
+                    OnUnknownOpcode::Error => return Err(CpuError::UnknownOpcode { opcode, address }),
+                    OnUnknownOpcode::Skip => warn!("skipped unknown opcode {:04X} at 0x{:03X}", opcode, address),
+                    OnUnknownOpcode::Halt => {
+                        warn!("halted on unknown opcode {:04X} at 0x{:03X}", opcode, address);
+                        return Ok(CpuStatus::Halted);
+                    }
+                }
+            }
+        }
+
+        Ok(match self.waiting_for_key {
+            Some(x) => CpuStatus::WaitingForKey(x),
+            None => CpuStatus::Running,
+        })
+    }
+
+    /// Puts the CPU back how it was built, as if the machine was switched
+    /// off and on again with the same ROM
+    ///
+    /// The program counter, stack, timers, audio pattern and screen are
+    /// cleared, the screen going back to low resolution, and the registers
+    /// and memory go back to their starting values, undoing anything the
+    /// program wrote. Quirks, the RPL flags and the held keys stay as they
+    /// are, and a seeded CPU starts its random numbers over from the seed.
+    ///
+    /// # Examples
+    /// ```
+    /// use chip_8::CpuBuilder;
+    ///
+    /// let mut memory = [0; 4096];
+    /// // LD V0, 7
+    /// memory[0] = 0x60; memory[1] = 0x07;
+    /// let mut cpu = CpuBuilder::new().memory(memory).build();
+    ///
+    /// cpu.run();
+    /// cpu.reset();
+    /// assert_eq!(cpu.registers(0), 0);
+    /// ```
+    pub fn reset(&mut self) {
+        debug!("reset after {} instructions", self.cycles);
+        self.program_counter = self.program_start;
+        self.registers = self.initial_registers;
+        // a restored snapshot may have left memory a different size
+        self.memory.clone_from(&self.initial_memory);
+        self.stack = [0; MAX_STACK_DEPTH];
+        self.stack_pointer = 0;
+        self.i = 0;
+        self.i_high = 0;
+        self.sprite_size = (0, 0);
+        self.delay_timer = 0;
+        self.sound_timer = 0;
+        self.audio_pattern = None;
+        self.pitch = audio::DEFAULT_PITCH;
+        self.display.set_resolution(Resolution::Low);
+        self.touch(self.display.rect());
+        self.waiting_for_key = None;
+        self.waiting_for_vblank = false;
+        self.cycles = 0;
+        if let Some(seed) = self.seed {
+            self.rng = ChaCha12Rng::seed_from_u64(seed);
+        }
+        self.clear_decode_cache();
+    }
+
+    /// Restarts the random numbers CXNN draws from at `seed`, and again
+    /// from there on every reset, like [`CpuBuilder::seed`]
+    pub fn set_seed(&mut self, seed: u64) {
+        self.seed = Some(seed);
+        self.rng = ChaCha12Rng::seed_from_u64(seed);
+    }
+
+    /// Changes what the CPU does with unknown opcodes from now on, like
+    /// [`CpuBuilder::on_unknown_opcode`]
+    pub fn set_on_unknown_opcode(&mut self, policy: OnUnknownOpcode) {
+        self.on_unknown_opcode = policy;
+    }
+
+    /// Changes what the CPU does with opcode 0000 from now on, like
+    /// [`CpuBuilder::on_zero_opcode`]
+    pub fn set_on_zero_opcode(&mut self, policy: OnZeroOpcode) {
+        self.on_zero_opcode = policy;
+    }
+
+    /// Swaps in a new ROM without rebuilding the CPU
+    ///
+    /// Memory from the program start up is cleared and the ROM is loaded
+    /// there, then the CPU is [`reset`](Cpu::reset) so the new program starts
+    /// from scratch. Quirks, the execution engine, the observer, the RPL
+    /// flags and the font all stay as they are, and resetting afterwards
+    /// restarts the new ROM.
+    ///
+    /// # Examples
+    /// ```
+    /// use chip_8::{CpuBuilder, RomError};
+    ///
+    /// let mut cpu = CpuBuilder::new().build();
+    /// // LD V0, 7
+    /// cpu.load_program(&[0x60, 0x07]).unwrap();
+    /// cpu.run();
+    /// assert_eq!(cpu.registers(0), 7);
+    ///
+    /// assert_eq!(cpu.load_program(&[]), Err(RomError::Empty));
+    /// ```
+    pub fn load_program(&mut self, rom: &[u8]) -> Result<(), RomError> {
+        let start = self.program_start;
+        let max = self.initial_memory.len() - start;
+        if rom.is_empty() {
+            return Err(RomError::Empty);
+        }
+        if rom.len() > max {
+            return Err(RomError::TooLarge { size: rom.len(), max });
+        }
+
+        self.initial_memory[start..].iter_mut().for_each(|byte| *byte = 0);
+        self.initial_memory[start..start + rom.len()].copy_from_slice(rom);
+        info!("loaded a {} byte ROM at 0x{:03X}", rom.len(), start);
+        self.reset();
+        Ok(())
+    }
+
+    /// Runs up to `cycles` instructions, stopping early if the program
+    /// terminates or is held up waiting for a key or the timers
+    ///
+    /// Returns how many instructions actually ran, which makes it handy for
+    /// measuring throughput. Nothing can end a wait partway through, so
+    /// there's no spinning on it and none of it counts.
+    ///
+    /// # Panics
+    ///
+    /// Panics if an instruction fails, like [`run`](Cpu::run)
+    pub fn run_cycles(&mut self, cycles: usize) -> usize {
+        for executed in 0..cycles {
+            match self.step_instruction() {
+                Ok((CpuStatus::Halted, _)) => return executed,
+                // held up before anything ran
+                Ok((_, None)) => return executed,
+                Ok(_) => {}
+                Err(err) => panic!("{}", err),
+            }
+        }
+
+        cycles
+    }
+
+    /// Runs up to `cycles` instructions, stopping early if the program
+    /// starts waiting for a key or terminates
+    ///
+    /// Lets a frontend run a frame's worth of instructions and then sleep
+    /// until the next frame or key press, rather than spinning on FX0A.
+    ///
+    /// # Examples
+    /// ```
+    /// use chip_8::{CpuBuilder, StopReason};
+    ///
+    /// let mut memory = [0; 4096];
+    /// // LD V3, K
+    /// memory[0] = 0xF3; memory[1] = 0x0A;
+    /// let mut cpu = CpuBuilder::new().memory(memory).build();
+    ///
+    /// assert_eq!(cpu.run_until_blocked(100), StopReason::WaitingForKey);
+    /// cpu.set_key(0x7, true);
+    /// cpu.set_key(0x7, false);
+    /// assert_eq!(cpu.registers(3), 0x7);
+    /// assert_eq!(cpu.run_until_blocked(100), StopReason::Halted);
+    /// ```
+    pub fn run_until_blocked(&mut self, cycles: usize) -> StopReason {
+        for _ in 0..cycles {
+            match self.step() {
+                Ok(CpuStatus::Running) => {}
+                Ok(CpuStatus::WaitingForKey(_)) => return StopReason::WaitingForKey,
+                Ok(CpuStatus::WaitingForVBlank) => return StopReason::WaitingForVBlank,
+                Ok(CpuStatus::Halted) => return StopReason::Halted,
+                Err(err) => return StopReason::Error(err),
+            }
+        }
+
+        if self.waiting_for_key.is_some() {
+            StopReason::WaitingForKey
+        } else if self.waiting_for_vblank {
+            StopReason::WaitingForVBlank
+        } else {
+            StopReason::BudgetSpent
+        }
+    }
+
+    /// Runs the program an instruction at a time, yielding where each one
+    /// was, the instruction, and what it changed
+    ///
+    /// Ends when the program terminates, fails, or is held up waiting for a
+    /// key or the timers, and [`Instructions::stopped`] then says which.
+    /// Working out the changes copies memory on every step, so this is for
+    /// analysis tools and tests rather than playing games.
+    ///
+    /// # Examples
+    /// ```
+    /// use chip_8::instruction::Instruction;
+    /// use chip_8::CpuBuilder;
+    ///
+    /// let mut cpu = CpuBuilder::new().build();
+    /// // LD V0, 0; ADD V0, 1; JP 0x202
+    /// cpu.load_program(&[0x60, 0x00, 0x70, 0x01, 0x12, 0x02]).unwrap();
+    ///
+    /// let adds = cpu
+    ///     .instructions()
+    ///     .take(1000)
+    ///     .filter(|(_, instruction, _)| matches!(instruction, Instruction::Add(..)))
+    ///     .count();
+    /// assert_eq!(adds, 500);
+    /// ```
+    pub fn instructions(&mut self) -> Instructions<'_> {
+        Instructions { cpu: self, stopped: None }
+    }
+
+    /// How many instructions have run since the CPU was built or last reset
+    ///
+    /// Sampling this once a second gives the effective instructions per second.
+    pub fn cycles(&self) -> u64 {
+        self.cycles
+    }
+
+    /// The return addresses on the stack, oldest call first
+    pub fn stack_frames(&self) -> &[Address] {
+        &self.stack[..self.stack_pointer]
+    }
+
+    /// How many addresses are on the stack
+    pub fn stack_pointer(&self) -> usize {
+        self.stack_pointer
+    }
+
+    /// How many calls can be nested before the stack overflows
+    pub fn stack_depth(&self) -> usize {
+        self.stack_depth
+    }
+
+    /// Whether FX0A is holding the program up until a key is pressed and
+    /// released
+    pub fn waiting_for_key(&self) -> bool {
+        self.waiting_for_key.is_some()
+    }
+
+    /// Whether the `display_wait` quirk is holding the program up until the
+    /// timers next tick
+    pub fn waiting_for_vblank(&self) -> bool {
+        self.waiting_for_vblank
+    }
+
+    /// Decodes the instruction at the program counter, or looks it up
+    /// if the cached engine has already decoded it
+    fn fetch(&mut self) -> Result<Instruction, CpuError> {
+        let pc = self.program_counter;
+        if pc + 1 >= self.memory.len() {
+            return Err(CpuError::ProgramCounterOutOfBounds(pc));
+        }
+
+        // devices can change what they hold without being written, so
+        // they're never cached
+        let mapped = self.is_mapped(pc) || self.is_mapped(pc + 1);
+        let decode = match self.display.resolution() {
+            Resolution::Mega => Instruction::decode_mega,
+            _ => Instruction::decode,
+        };
+        Ok(match &mut self.decode_cache {
+            Some(cache) if !mapped => {
+                let memory = &self.memory;
+                *cache[pc].get_or_insert_with(|| decode((memory[pc] as OpCode) << 8 | memory[pc + 1] as OpCode))
+            }
+            _ => decode(self.read_opcode()),
+        })
+    }
+
+    /// Sets registers[x], telling the observer
+    pub(crate) fn write_register(&mut self, x: Reg, value: Byte) {
+        let old = std::mem::replace(&mut self.registers[x], value);
+        if let Some(observer) = &mut self.observer {
+            observer.on_register_write(x.get(), old, value);
+        }
+    }
+
+    /// The screen as the program has drawn it so far
+    pub fn display(&self) -> &Display {
+        &self.display
+    }
+
+    /// The smallest rectangle holding every pixel drawn or cleared since
+    /// this was last asked, `None` if the screen hasn't been touched
+    ///
+    /// Pixels a sprite flips back to how they were still count, so this is
+    /// where to look for changes rather than proof of any.
+    ///
+    /// # Examples
+    /// ```
+    /// use chip_8::screen::Rect;
+    /// use chip_8::CpuBuilder;
+    ///
+    /// let mut registers = [0; 16]; registers[0] = 10; registers[1] = 4;
+    /// let mut cpu = CpuBuilder::new().registers(registers).build();
+    /// // LD I, 0 (the font's 0, four pixels wide); DRW V0, V1, 5
+    /// cpu.load_program(&[0xA0, 0x00, 0xD0, 0x15]).unwrap();
+    /// cpu.take_touched();
+    /// cpu.run_cycles(2);
+    ///
+    /// assert_eq!(cpu.take_touched(), Some(Rect { x: 10, y: 4, width: 4, height: 5 }));
+    /// assert_eq!(cpu.take_touched(), None);
+    /// ```
+    pub fn take_touched(&mut self) -> Option<Rect> {
+        self.touched.take()
+    }
+
+    /// Switches the screen to `resolution`, blanking it
+    pub(crate) fn set_resolution(&mut self, resolution: Resolution) {
+        // MegaChip mode reads some opcodes differently
+        if (resolution == Resolution::Mega) != (self.display.resolution() == Resolution::Mega) {
+            self.clear_decode_cache();
+        }
+        self.display.set_resolution(resolution);
+        self.touch(self.display.rect());
+    }
+
+    /// The whole of I, with MegaChip's top byte
+    pub(crate) fn i_address(&self) -> usize {
+        (self.i_high as usize) << 16 | self.i as usize
+    }
+
+    /// Notes that the program drew on or cleared `rect`
+    pub(crate) fn touch(&mut self, rect: Rect) {
+        self.touched = Some(self.touched.map_or(rect, |touched| touched.union(&rect)));
+    }
+
+    /// A convenience method for retrieving the value of a specific register
+    /// # Examples
+    /// ```
+    /// use chip_8::CpuBuilder;
+    ///
+    /// let mut registers = [0; 16]; registers[5] = 12;
+    /// let cpu = CpuBuilder::new().registers(registers).build();
+    /// assert_eq!(cpu.registers(5), 12);
+    /// ```
+    pub fn registers(&self, ind: usize) -> Byte {
+        self.registers[ind]
+    }
+}
+
+/// The instructions a CPU runs, from [`Cpu::instructions`]
+pub struct Instructions<'a> {
+    cpu: &'a mut Cpu,
+    stopped: Option<StopReason>,
+}
+
+impl Instructions<'_> {
+    /// Why no more instructions are coming, once the iterator has ended
+    pub fn stopped(&self) -> Option<StopReason> {
+        self.stopped
+    }
+
+    fn stop(&mut self, reason: StopReason) -> Option<(Address, Instruction, CpuStateDelta)> {
+        self.stopped = Some(reason);
+        None
+    }
+}
+
+impl Iterator for Instructions<'_> {
+    type Item = (Address, Instruction, CpuStateDelta);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.stopped.is_some() {
+            return None;
+        }
+
+        let before = self.cpu.state();
+        let memory = self.cpu.memory.clone();
+        let instruction = match self.cpu.step_instruction() {
+            Ok((CpuStatus::Halted, _)) => return self.stop(StopReason::Halted),
+            Ok((_, Some(instruction))) => instruction,
+            Ok((CpuStatus::WaitingForVBlank, None)) => return self.stop(StopReason::WaitingForVBlank),
+            Ok((_, None)) => return self.stop(StopReason::WaitingForKey),
+            Err(err) => return self.stop(StopReason::Error(err)),
+        };
+        let delta = CpuStateDelta::between(&before, &self.cpu.state(), &memory, &self.cpu.memory);
+        Some((before.pc, instruction, delta))
+    }
+}
+
+impl FusedIterator for Instructions<'_> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builder_creates_cpu() {
+        let cb = CpuBuilder::new();
+        let cpu = cb.build();
+        assert_eq!(cpu.registers, [0; 16]);
+        assert_eq!(cpu.memory[0..5], [0xF0, 0x90, 0x90, 0x90, 0xF0]);
+        assert_eq!(cpu.memory[0x200..], [0; 0xE00]);
+        assert_eq!(cpu.program_counter, 0x200);
+        assert_eq!(cpu.stack_pointer, 0);
+        assert_eq!(cpu.stack, [0; MAX_STACK_DEPTH]);
+    }
+
+    #[test]
+    fn builder_options_creates_cpu() {
+        let mut registers = [0; 16];
+        registers[5] = 10;
+
+        let mut memory = [0; 4096];
+        memory[0x001] = 0x80;
+
+        let cpu = CpuBuilder::new()
+            .registers(registers)
+            .memory(memory)
+            .build();
+
+        assert_eq!(cpu.registers(5), 10);
+        assert_eq!(cpu.memory[0x201], 0x80);
+        assert_eq!(cpu.program_counter, 0x200);
+        assert_eq!(cpu.stack_pointer, 0);
+        assert_eq!(cpu.stack, [0; MAX_STACK_DEPTH]);
+    }
+
+    #[test]
+    fn builder_takes_borrowed_and_partial_values() {
+        let rom: Vec<Byte> = vec![0x60, 0x01, 0x61, 0x02];
+        let cpu = CpuBuilder::new().registers(&rom).memory(&rom[..2]).build();
+        assert_eq!(cpu.registers[..5], [0x60, 0x01, 0x61, 0x02, 0x00]);
+        assert_eq!(cpu.memory[0x200..0x203], [0x60, 0x01, 0x00]);
+
+        let cpu = CpuBuilder::new().registers(vec![7, 8]).memory(rom).build();
+        assert_eq!(cpu.registers[..3], [7, 8, 0]);
+        assert_eq!(cpu.memory[0x200..0x205], [0x60, 0x01, 0x61, 0x02, 0x00]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn builder_rejects_too_many_registers() {
+        let _ = CpuBuilder::new().registers([0; 17]);
+    }
+
+    #[test]
+    fn clones_are_detached_copies() {
+        // LD V0, 0xAB; LD I, 0xF00; LD [I], V0; LD R, V0
+        let rom = [0x60, 0xAB, 0xAF, 0x00, 0xF0, 0x55, 0xF0, 0x75];
+        let mut cpu = CpuBuilder::new().memory(rom).engine(ExecutionEngine::Cached).seed(1).build();
+        cpu.set_observer(crate::coverage::Coverage::default());
+        cpu.map(0xF00..0xF01, [0; 0x1000]);
+        cpu.run_cycles(4);
+
+        let copy = cpu.clone();
+        assert_eq!(copy, cpu);
+        assert!(copy.observer.is_none() && copy.mapped.is_empty());
+        assert_eq!(copy.flags.load()[0], 0xAB);
+        assert_eq!(copy.memory[0xF00], 0);
+        assert!(format!("{:?}", copy).starts_with("Cpu { program_counter: 520, registers: [171,"));
+
+        let mut ahead = cpu.clone();
+        ahead.registers[1] = 1;
+        assert_ne!(ahead, cpu);
+        assert_eq!(Cpu::default().program_counter, 0x200);
+    }
+
+    #[test]
+    fn builder_loads_program_at_program_start() {
+        let mut memory = [0; 4096];
+        memory[0x000] = 0x60;
+        memory[0x9FF] = 0xAB;
+        memory[0xA00] = 0xCD;
+
+        let mut cpu = CpuBuilder::new().memory(memory).program_start(0x600).build();
+
+        assert_eq!(cpu.memory[0..5], [0xF0, 0x90, 0x90, 0x90, 0xF0]);
+        assert_eq!(cpu.memory[0x200..0x600], [0; 0x400]);
+        assert_eq!(cpu.memory[0x600], 0x60);
+        assert_eq!(cpu.memory[0xFFF], 0xAB);
+        assert_eq!(cpu.program_counter, 0x600);
+
+        cpu.program_counter = 0x300;
+        cpu.reset();
+        assert_eq!(cpu.program_counter, 0x600);
+    }
+
+    #[test]
+    #[should_panic]
+    fn builder_rejects_program_start_past_memory() {
+        let _ = CpuBuilder::new().program_start(0x1000).build();
+    }
+
+    #[test]
+    fn registers_gets_register_at_index() {
+        let mut registers = [0; 16];
+        registers[3] = 3;
+        let cpu = CpuBuilder::new().registers(registers).build();
+        for i in 0..16 {
+            assert_eq!(cpu.registers(i), if i == 3 { 3 } else { 0 });
+        }
+    }
+
+    #[test]
+    fn auto_configure_leaves_unknown_roms_alone() {
+        let quirks = Quirks { clip_sprites: true, ..Quirks::default() };
+        let cpu = CpuBuilder::new().quirks(quirks).auto_configure(&[0x12, 0x00]).build();
+
+        assert_eq!(cpu.quirks, quirks);
+    }
+
+    #[test]
+    fn reset_restores_built_state() {
+        let mut memory = [0; 0x1000];
+        // CALL 0x300, which loads 9 into V0
+        memory[..2].copy_from_slice(&[0x23, 0x00]);
+        memory[0x100..0x102].copy_from_slice(&[0x60, 0x09]);
+        let mut cpu = CpuBuilder::new().memory(memory).engine(ExecutionEngine::Cached).build();
+        let built_memory = cpu.memory.clone();
+
+        cpu.run_cycles(2);
+        cpu.delay_timer = 5;
+        cpu.display.toggle(0, 0);
+        cpu.i = 0x400;
+        cpu.reg_dump(Reg::V0).unwrap();
+
+        cpu.reset();
+        assert_eq!(cpu.program_counter, 0x200);
+        assert_eq!(cpu.stack_pointer, 0);
+        assert_eq!(cpu.registers, [0; 16]);
+        assert_eq!(cpu.i, 0);
+        assert_eq!(cpu.delay_timer, 0);
+        assert_eq!(cpu.display, Display::new());
+        assert_eq!(cpu.memory[..], built_memory[..]);
+
+        cpu.run_cycles(2);
+        assert_eq!(cpu.registers[0], 9);
+    }
+
+    #[test]
+    fn load_program_replaces_rom_and_keeps_configuration() {
+        let quirks = Quirks { jump_uses_vx: true, ..Quirks::default() };
+        let mut memory = [0; 0x1000];
+        memory[..4].copy_from_slice(&[0x60, 0x01, 0x61, 0x02]);
+        let mut cpu = CpuBuilder::new().memory(memory).quirks(quirks).program_start(0x300).build();
+        cpu.run_cycles(2);
+        cpu.memory[0xF00] = 0xAB;
+
+        cpu.load_program(&[0x62, 0x03]).unwrap();
+        assert_eq!(cpu.program_counter, 0x300);
+        assert_eq!(cpu.registers, [0; 16]);
+        assert_eq!(cpu.memory[0..5], [0xF0, 0x90, 0x90, 0x90, 0xF0]);
+        assert_eq!(cpu.memory[0x302..], [0; 0xCFE]);
+        assert_eq!(cpu.quirks, quirks);
+
+        cpu.run();
+        cpu.reset();
+        cpu.run();
+        assert_eq!(cpu.registers[..3], [0, 0, 3]);
+    }
+
+    #[test]
+    fn load_program_rejects_roms_that_dont_fit() {
+        let mut cpu = CpuBuilder::new().build();
+        cpu.registers[0] = 5;
+
+        assert_eq!(cpu.load_program(&[0; 0xE01]), Err(RomError::TooLarge { size: 0xE01, max: 0xE00 }));
+        assert_eq!(cpu.registers[0], 5);
+        assert!(cpu.load_program(&[0; 0xE00]).is_ok());
+    }
+
+    #[test]
+    fn memory_size_makes_room_for_bigger_roms() {
+        let mut cpu = CpuBuilder::new().memory_size(memory::XO_CHIP_SIZE).build();
+        assert_eq!(cpu.memory().len(), 0x10000);
+        assert_eq!(cpu.memory()[..5], [0xF0, 0x90, 0x90, 0x90, 0xF0]);
+
+        assert!(cpu.load_program(&[0x12; 0x2000]).is_ok());
+        assert_eq!(cpu.memory()[0x21FF], 0x12);
+        assert_eq!(cpu.load_program(&[0; 0xFE01]), Err(RomError::TooLarge { size: 0xFE01, max: 0xFE00 }));
+
+        let cpu = CpuBuilder::new().memory_size(0x800).build();
+        assert_eq!(cpu.memory().len(), 0x800);
+    }
+
+    #[test]
+    fn memory_size_and_program_start_go_in_either_order() {
+        let cpu = CpuBuilder::new().program_start(0x1200).memory_size(0x2000).build();
+        assert_eq!(cpu.program_counter, 0x1200);
+        let cpu = CpuBuilder::new().memory_size(0x2000).program_start(0x1200).build();
+        assert_eq!(cpu.program_counter, 0x1200);
+    }
+
+    #[test]
+    fn display_wait_holds_the_program_until_the_timers_tick() {
+        let mut memory = [0; 0x1000];
+        // DRW V0, V0, 1; ADD V1, 1; JP 0x200
+        memory[..6].copy_from_slice(&[0xD0, 0x01, 0x71, 0x01, 0x12, 0x00]);
+        let quirks = Quirks { display_wait: true, ..Quirks::default() };
+        let mut cpu = CpuBuilder::new().memory(memory).quirks(quirks).build();
+
+        assert_eq!(cpu.run_until_blocked(10), StopReason::WaitingForVBlank);
+        assert_eq!(cpu.step(), Ok(CpuStatus::WaitingForVBlank));
+        assert_eq!((cpu.cycles(), cpu.program_counter), (1, 0x202));
+
+        cpu.tick_timers();
+        assert_eq!(cpu.run_until_blocked(10), StopReason::WaitingForVBlank);
+        assert_eq!((cpu.cycles(), cpu.registers[1]), (4, 1));
+    }
+
+    #[test]
+    fn wait_key_blocks_until_release_while_timers_run() {
+        let mut memory = [0; 0x1000];
+        // LD V2, K; ADD V2, 1
+        memory[..4].copy_from_slice(&[0xF2, 0x0A, 0x72, 0x01]);
+        let mut cpu = CpuBuilder::new().memory(memory).build();
+        cpu.delay_timer = 3;
+
+        assert_eq!(cpu.run_until_blocked(10), StopReason::WaitingForKey);
+        assert_eq!(cpu.step(), Ok(CpuStatus::WaitingForKey(Reg::nibble(2))));
+        assert_eq!(cpu.run_cycles(5), 0);
+        cpu.tick_timers();
+        assert_eq!((cpu.program_counter, cpu.cycles()), (0x202, 1));
+        assert_eq!(cpu.delay_timer, 2);
+
+        // releasing a key that wasn't held doesn't count, nor does pressing
+        cpu.set_key(0xB, false);
+        assert!(cpu.waiting_for_key());
+        cpu.set_key(0xB, true);
+        assert!(cpu.waiting_for_key());
+        cpu.set_key(0xB, false);
+        assert!(!cpu.waiting_for_key());
+        assert_eq!(cpu.run_until_blocked(1), StopReason::BudgetSpent);
+        assert_eq!(cpu.registers[2], 0xC);
+    }
+
+    #[test]
+    fn run_until_halt_stops_at_waits_and_the_watchdog() {
+        // ADD V0, 1; LD V1, K; JP 0x200
+        let mut cpu = CpuBuilder::new().memory([0x70, 0x01, 0xF1, 0x0A, 0x12, 0x00]).build();
+
+        let summary = cpu.run_until_halt(Watchdog::default().yield_every(1));
+        assert_eq!(summary, RunSummary { instructions: 2, reason: StopReason::WaitingForKey });
+        cpu.set_key(0x3, true);
+        cpu.set_key(0x3, false);
+        let summary = cpu.run_until_halt(Watchdog::new(10));
+        assert_eq!(summary, RunSummary { instructions: 3, reason: StopReason::WaitingForKey });
+        assert_eq!((cpu.registers[0], cpu.registers[1]), (2, 3));
+
+        cpu.set_key(0x3, true);
+        cpu.set_key(0x3, false);
+        assert_eq!(cpu.run_until_halt(Watchdog::new(2)).reason, StopReason::BudgetSpent);
+        assert_eq!(cpu.program_counter, 0x202);
+    }
+
+    #[test]
+    fn zero_opcode_follows_the_policy() {
+        // ADD V0, 1; 0000; ADD V0, 1; 0000
+        let rom = [0x70, 0x01, 0x00, 0x00, 0x70, 0x01, 0x00, 0x00];
+        let mut cpu = CpuBuilder::new().memory(rom).on_zero_opcode(OnZeroOpcode::Nop).build();
+        assert_eq!(cpu.run_cycles(3), 3);
+        assert_eq!(cpu.registers[0], 2);
+
+        cpu.set_on_zero_opcode(OnZeroOpcode::Error);
+        assert_eq!(cpu.step(), Err(CpuError::ZeroOpcode(0x206)));
+        assert_eq!(cpu.program_counter, 0x206);
+        cpu.set_on_zero_opcode(OnZeroOpcode::Halt);
+        assert_eq!(cpu.step(), Ok(CpuStatus::Halted));
+    }
+
+    #[test]
+    fn run_cycles_stops_when_program_terminates() {
+        let mut memory = [0; 0x1000];
+        // ADD V0, 1 three times, then terminate
+        memory[..6].copy_from_slice(&[0x70, 0x01, 0x70, 0x01, 0x70, 0x01]);
+        let mut cpu = CpuBuilder::new().memory(memory).build();
+
+        assert_eq!(cpu.run_cycles(2), 2);
+        assert_eq!(cpu.run_cycles(100), 1);
+        assert_eq!(cpu.registers(0), 3);
+        assert_eq!(cpu.cycles(), 3);
+
+        cpu.reset();
+        assert_eq!(cpu.cycles(), 0);
+    }
+
+    #[test]
+    fn unknown_opcodes_follow_the_policy() {
+        let mut memory = [0; 4096];
+        // unknown; LD V0, 1
+        memory[..4].copy_from_slice(&[0xE0, 0x00, 0x60, 0x01]);
+        let mut cpu = CpuBuilder::new().memory(memory).build();
+
+        assert_eq!(cpu.step(), Err(CpuError::UnknownOpcode { opcode: 0xE000, address: 0x200 }));
+        assert_eq!(cpu.program_counter, 0x200);
+
+        cpu.set_on_unknown_opcode(OnUnknownOpcode::Halt);
+        assert_eq!(cpu.step(), Ok(CpuStatus::Halted));
+
+        cpu.reset();
+        cpu.set_on_unknown_opcode(OnUnknownOpcode::Skip);
+        assert_eq!(cpu.step(), Ok(CpuStatus::Running));
+        assert_eq!(cpu.step(), Ok(CpuStatus::Running));
+        assert_eq!(cpu.registers(0), 1);
+        assert_eq!(cpu.cycles(), 2);
+    }
+
+    #[test]
+    fn cached_engine_redecodes_after_memory_writes() {
+        let mut cpu = CpuBuilder::new().engine(ExecutionEngine::Cached).build();
+        // LD V0, 5
+        cpu.memory[0x300] = 0x60;
+        cpu.memory[0x301] = 0x05;
+        cpu.program_counter = 0x300;
+        cpu.run();
+        assert_eq!(cpu.registers[0], 5);
+
+        // overwrite it with LD V1, 7
+        cpu.registers[0] = 0x61;
+        cpu.registers[1] = 0x07;
+        cpu.i = 0x300;
+        cpu.reg_dump(Reg::nibble(1)).unwrap();
+        cpu.registers[1] = 0;
+
+        cpu.program_counter = 0x300;
+        cpu.run();
+        assert_eq!(cpu.registers[1], 7);
+    }
+
+    #[test]
+    fn cached_engine_matches_interpreter() {
+        let mut memory = [0; 0x1000];
+        let rom = include_bytes!("../roms/sierpinski.ch8");
+        memory[..rom.len()].copy_from_slice(rom);
+
+        let mut interpreted = CpuBuilder::new().memory(memory).build();
+        let mut cached = CpuBuilder::new().memory(memory).engine(ExecutionEngine::Cached).build();
+        interpreted.run_cycles(5000);
+        cached.run_cycles(5000);
+
+        assert_eq!(interpreted.registers, cached.registers);
+        assert_eq!(interpreted.program_counter, cached.program_counter);
+        assert_eq!(interpreted.i, cached.i);
+        assert_eq!(interpreted.memory[..], cached.memory[..]);
+        assert_eq!(interpreted.display, cached.display);
+    }
+
+    #[test]
+    fn megachip_draws_palette_sprites_once_it_is_on() {
+        let program = [
+            0x00, 0x11, // MEGAON
+            0x01, 0x00, 0x03, 0x00, // LDHI 0x000300
+            0x02, 0x01, // LDPAL 1
+            0x01, 0x00, 0x03, 0x04, // LDHI 0x000304
+            0x03, 0x02, // SPRW 2
+            0x04, 0x01, // SPRH 1
+            0xD0, 0x10, // DRW V0, V1
+        ];
+        let mut registers = [0; 16];
+        (registers[0], registers[1]) = (5, 7);
+        let mut cpu = CpuBuilder::new().memory(program).registers(registers).build();
+        // opaque blue, then a sprite of it and a see-through pixel
+        cpu.memory[0x300..0x306].copy_from_slice(&[0xFF, 0x00, 0x00, 0xFF, 0x01, 0x00]);
+        cpu.run_cycles(7);
+
+        assert_eq!(cpu.program_counter, 0x212);
+        assert_eq!((cpu.display.width(), cpu.display.height()), (256, 192));
+        assert_eq!(cpu.display.color(5, 7), Some([0x00, 0x00, 0xFF]));
+        assert!(!cpu.display.pixel(6, 7));
+        assert_eq!(cpu.registers[0xF], 0);
+    }
+
+    #[test]
+    fn switching_megachip_off_forgets_what_the_cache_decoded_in_it() {
+        // MEGAON; SPRW 2; MEGAOFF; JP 0x202, where 0302 is now SYS 0x302
+        let program = [0x00, 0x11, 0x03, 0x02, 0x00, 0x10, 0x12, 0x02];
+        for engine in [ExecutionEngine::Interpreter, ExecutionEngine::Cached] {
+            let mut cpu = CpuBuilder::new().memory(program).engine(engine).build();
+            cpu.run_cycles(5);
+
+            assert_eq!(cpu.sprite_size, (2, 0));
+            assert_eq!((cpu.program_counter, cpu.stack_pointer), (0x302, 1));
+        }
+    }
+
+    #[test]
+    fn seeded_cpu_repeats_random_numbers_after_reset() {
+        let mut cpu = CpuBuilder::new().seed(0xC8).build();
+        // RND V0, 0xFF; LD V1, V0; RND V0, 0xFF
+        cpu.load_program(&[0xC0, 0xFF, 0x81, 0x00, 0xC0, 0xFF]).unwrap();
+        cpu.run_cycles(3);
+        let first = (cpu.registers[0], cpu.registers[1]);
+
+        cpu.reset();
+        cpu.run_cycles(3);
+        assert_eq!((cpu.registers[0], cpu.registers[1]), first);
+
+        cpu.set_seed(0xC8);
+        cpu.program_counter = 0x200;
+        cpu.run_cycles(1);
+        assert_eq!(cpu.registers[0], first.1);
+    }
+
+    #[test]
+    fn instructions_yield_what_each_step_changed() {
+        let mut cpu = CpuBuilder::new().build();
+        // LD V1, 5; LD I, 0x300; LD [I], V1; LD V2, K
+        cpu.load_program(&[0x61, 0x05, 0xA3, 0x00, 0xF1, 0x55, 0xF2, 0x0A]).unwrap();
+
+        let mut instructions = cpu.instructions();
+        let (pc, instruction, delta) = instructions.next().unwrap();
+        assert_eq!((pc, instruction), (0x200, Instruction::SetRegister(Reg::nibble(1), 5)));
+        assert_eq!((delta.pc, delta.registers, delta.i), (0x202, vec![(Reg::nibble(1), 5)], None));
+
+        let (_, _, delta) = instructions.next().unwrap();
+        assert_eq!((delta.i, delta.memory), (Some(0x300), vec![]));
+        let (_, _, delta) = instructions.next().unwrap();
+        assert_eq!(delta.memory, vec![(0x301, 5)]);
+
+        assert_eq!(instructions.next().map(|(pc, ..)| pc), Some(0x206));
+        assert!(instructions.next().is_none());
+        assert_eq!(instructions.stopped(), Some(StopReason::WaitingForKey));
+
+        cpu.load_program(&[0x00, 0xEE]).unwrap();
+        let mut instructions = cpu.instructions();
+        assert!(instructions.next().is_none());
+        assert_eq!(instructions.stopped(), Some(StopReason::Error(CpuError::StackUnderflow)));
+    }
+
+    #[test]
+    fn cpu_and_emulator_can_move_between_threads() {
+        fn assert_send<T: Send>() {}
+        assert_send::<Cpu>();
+        assert_send::<crate::Emulator>();
+    }
+}
@@ -0,0 +1,171 @@
+//! Crash reports: what the CPU was doing when an instruction failed
+//!
+//! A [`Trace`] set as the CPU's observer keeps the last few instructions
+//! run. When a step fails, a [`CrashReport`] gathers them up with the
+//! error, the registers, memory and the screen, and writes the lot to a
+//! file that can be attached to a bug report.
+//!
+//! # Example
+//!
+//! ```
+//! use std::sync::{Arc, Mutex};
+//!
+//! use chip_8::crash::{CrashReport, Trace};
+//! use chip_8::{CpuBuilder, CpuError};
+//!
+//! // LD V0, 1; RET with nothing to return to
+//! let mut cpu = CpuBuilder::new().build();
+//! cpu.load_program(&[0x60, 0x01, 0x00, 0xEE]).unwrap();
+//!
+//! let trace = Arc::new(Mutex::new(Trace::new(16)));
+//! cpu.set_observer(trace.clone());
+//! let error = loop {
+//!     if let Err(error) = cpu.step() {
+//!         break error;
+//!     }
+//! };
+//!
+//! let report = CrashReport::new(&cpu, error, &trace.lock().unwrap());
+//! assert_eq!(report.error, CpuError::StackUnderflow);
+//! assert_eq!(report.trace.len(), 2);
+//! assert!(report.to_string().contains("0x202  00EE  RET"));
+//! ```
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::instruction::Instruction;
+use crate::memory::MemoryView;
+use crate::observer::Observer;
+use crate::{Cpu, CpuError, CpuState, Display, Memory};
+
+/// How many instructions the CLI keeps for its crash reports
+pub const DEFAULT_TRACE_LEN: usize = 64;
+
+/// The last few instructions run, oldest first, forgetting the oldest once
+/// it's full
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Trace {
+    len: usize,
+    entries: VecDeque<(usize, Instruction)>,
+}
+
+impl Default for Trace {
+    fn default() -> Self {
+        Self::new(DEFAULT_TRACE_LEN)
+    }
+}
+
+impl Trace {
+    /// A trace keeping the last `len` instructions
+    pub fn new(len: usize) -> Trace {
+        Trace { len, entries: VecDeque::with_capacity(len) }
+    }
+
+    /// The instructions kept so far, with the addresses they ran from,
+    /// oldest first
+    pub fn entries(&self) -> impl Iterator<Item = (usize, Instruction)> + '_ {
+        self.entries.iter().copied()
+    }
+
+    /// Forgets everything run so far
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+impl Observer for Trace {
+    fn on_execute(&mut self, addr: usize, instruction: Instruction) {
+        if self.len == 0 {
+            return;
+        }
+        if self.entries.len() == self.len {
+            self.entries.pop_front();
+        }
+        self.entries.push_back((addr, instruction));
+    }
+}
+
+/// Everything about the CPU at the moment an instruction failed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CrashReport {
+    /// Why the instruction failed
+    pub error: CpuError,
+    /// The registers, stack and timers after it failed
+    pub state: CpuState,
+    /// How many instructions had run since the CPU was built or reset
+    pub cycles: u64,
+    /// The last instructions run, with the addresses they ran from,
+    /// oldest first; the failing one is last
+    pub trace: Vec<(usize, Instruction)>,
+    /// All of memory
+    pub memory: Memory,
+    /// The screen as the program left it
+    pub screen: Display,
+}
+
+impl CrashReport {
+    /// Gathers up the CPU's state after `error`, with the instructions
+    /// that led up to it from `trace`
+    pub fn new(cpu: &Cpu, error: CpuError, trace: &Trace) -> CrashReport {
+        CrashReport {
+            error,
+            state: cpu.state(),
+            cycles: cpu.cycles(),
+            trace: trace.entries().collect(),
+            memory: cpu.memory().into(),
+            screen: cpu.display().clone(),
+        }
+    }
+
+    /// Writes the report to `path` as text, replacing anything there
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        fs::write(path, self.to_string())
+    }
+}
+
+impl fmt::Display for CrashReport {
+    /// The error, the registers, the trace, the screen drawn in `#` and
+    /// `.`, then a hex dump of memory
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Error: {}", self.error)?;
+        writeln!(f, "After {} instructions", self.cycles)?;
+        writeln!(f, "\n{}", self.state)?;
+
+        writeln!(f, "\nLast {} instructions:", self.trace.len())?;
+        for &(addr, instruction) in &self.trace {
+            writeln!(f, "0x{:03X}  {:04X}  {}", addr, instruction.encode(), instruction)?;
+        }
+
+        writeln!(f, "\nScreen:")?;
+        for row in self.screen.rows() {
+            let row: String = row.iter().map(|&lit| if lit { '#' } else { '.' }).collect();
+            writeln!(f, "{}", row)?;
+        }
+
+        write!(f, "\nMemory:\n{}", MemoryView::new(&self.memory, 0, self.memory.len()).render(&self.memory))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn trace_keeps_only_the_last_instructions() {
+        let mut trace = Trace::new(2);
+        for addr in [0x200, 0x202, 0x204] {
+            trace.on_execute(addr, Instruction::decode(0x6001));
+        }
+
+        let addrs: Vec<usize> = trace.entries().map(|(addr, _)| addr).collect();
+        assert_eq!(addrs, [0x202, 0x204]);
+
+        let mut empty = Trace::new(0);
+        empty.on_execute(0x200, Instruction::decode(0x6001));
+        assert_eq!(empty.entries().count(), 0);
+    }
+}
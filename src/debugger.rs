@@ -0,0 +1,709 @@
+//! Interactive terminal debugger
+//!
+//! [`Debugger::run`] reads commands line by line and redraws a split view
+//! of the screen, registers, stack and the disassembly around the program
+//! counter after each one:
+//!
+//! - `s [N]` (or just enter) steps one or N instructions
+//! - `n [N]` steps one or N lines of source
+//! - `c` continues until a breakpoint or the end of the program
+//! - `b ADDR` sets or clears a breakpoint
+//! - `w EXPR` pauses when a [`Watch`] expression becomes true, `w` on its
+//!   own clears them all
+//! - `d X Y WIDTH HEIGHT` pauses when a pixel flips in that rectangle of the
+//!   screen, columns and rows counting from 0, and says which instruction
+//!   drew there; `w` on its own clears these too
+//! - `m ADDR [LEN]` dumps memory
+//! - `e ADDR BYTES` writes hex bytes into memory, from the program start up
+//! - `q` quits
+//!
+//! Addresses are hex, with or without a leading `0x`, or the names of
+//! labels once [`Debugger::set_symbols`] has given it some. Labels are also
+//! shown in the disassembly and on the stack.
+//!
+//! Programs assembled from Octo source can be debugged by line: given the
+//! source with [`Debugger::set_source`], the view shows the line about to
+//! run, `n` steps a line at a time and `FILE:LINE` works as an address.
+
+use std::fmt::Write as _;
+use std::io::{self, BufRead, Write};
+
+use crate::instruction::Instruction;
+use crate::memory::MemoryView;
+use crate::screen::Rect;
+use crate::symbols::{LineTable, SymbolTable};
+use crate::{Address, CpuError, CpuStatus, Cpu, INSTRUCTIONS_PER_TICK};
+
+mod watch;
+
+pub use watch::{ScreenWatch, Watch};
+
+/// How many instructions `c` runs before giving up on reaching a breakpoint
+const CONTINUE_LIMIT: usize = 1_000_000;
+
+/// How many instructions are disassembled before and after the program counter
+const DISASSEMBLY_CONTEXT: usize = 4;
+
+/// The source a program was assembled from
+struct Source {
+    name: String,
+    text: Vec<String>,
+    lines: LineTable,
+}
+
+/// Steps through a program on a CPU, stopping at breakpoints
+pub struct Debugger<'a> {
+    cpu: &'a mut Cpu,
+    breakpoints: Vec<Address>,
+    symbols: SymbolTable,
+    source: Option<Source>,
+    // each watch, with whether it held after the last step
+    watches: Vec<(Watch, bool)>,
+    // the watch that stopped the last `resume`
+    triggered: Option<usize>,
+    screen_watches: Vec<ScreenWatch>,
+    // the screen watch that stopped the last `resume`, and the address of
+    // the instruction that drew in it
+    drawn: Option<(usize, Address)>,
+    executed: usize,
+    halted: bool,
+    error: Option<CpuError>,
+}
+
+impl<'a> Debugger<'a> {
+    /// Makes a new Debugger for the CPU, with no breakpoints
+    pub fn new(cpu: &'a mut Cpu) -> Debugger<'a> {
+        Debugger {
+            cpu,
+            breakpoints: vec![],
+            symbols: SymbolTable::new(),
+            source: None,
+            watches: vec![],
+            triggered: None,
+            screen_watches: vec![],
+            drawn: None,
+            executed: 0,
+            halted: false,
+            error: None,
+        }
+    }
+
+    /// Runs a single instruction, ticking the timers as if running in real time
+    ///
+    /// Returns false once the program has terminated or an instruction failed
+    pub fn step(&mut self) -> bool {
+        if self.halted {
+            return false;
+        }
+
+        match self.cpu.step() {
+            Ok(CpuStatus::Halted) => self.halted = true,
+            Err(err) => {
+                self.halted = true;
+                self.error = Some(err);
+            }
+            Ok(_) => {}
+        }
+        if self.halted {
+            return false;
+        }
+
+        self.executed += 1;
+        if self.executed.is_multiple_of(INSTRUCTIONS_PER_TICK) {
+            self.cpu.tick_timers();
+        }
+
+        true
+    }
+
+    /// Steps until the program counter is at the start of a different
+    /// source line, a breakpoint or the end of the program, returning how
+    /// many instructions ran
+    ///
+    /// Without any source, this is the same as [`step`](Debugger::step).
+    pub fn step_line(&mut self) -> usize {
+        let start = self.source_line();
+        for count in 1..=CONTINUE_LIMIT {
+            if !self.step() || self.breakpoints.contains(&(self.cpu.program_counter as Address)) {
+                return count;
+            }
+            match self.source_line() {
+                Some(line) if Some(line) != start => return count,
+                _ if self.source.is_none() => return count,
+                _ => {}
+            }
+        }
+
+        CONTINUE_LIMIT
+    }
+
+    /// Steps until the program counter reaches a breakpoint, a watch
+    /// becomes true, a watched part of the screen changes or the program
+    /// ends
+    ///
+    /// Gives up after a million instructions so an endless loop can't hang
+    /// the debugger. Returns how many instructions ran.
+    pub fn resume(&mut self) -> usize {
+        self.triggered = None;
+        self.drawn = None;
+        // whatever was drawn while stepping by hand isn't news
+        self.update_screen_watches();
+        for count in 1..=CONTINUE_LIMIT {
+            let addr = self.cpu.program_counter as Address;
+            if !self.step() || self.breakpoints.contains(&(self.cpu.program_counter as Address)) {
+                return count;
+            }
+            if let Some(ind) = self.update_watches() {
+                self.triggered = Some(ind);
+                return count;
+            }
+            if let Some(ind) = self.update_screen_watches() {
+                self.drawn = Some((ind, addr));
+                return count;
+            }
+        }
+
+        CONTINUE_LIMIT
+    }
+
+    /// Adds a watch that `resume` stops at once it becomes true
+    ///
+    /// A watch that's already true won't stop anything until it's been
+    /// false again.
+    pub fn add_watch(&mut self, watch: Watch) {
+        let holds = watch.eval(&self.cpu.state());
+        self.watches.push((watch, holds));
+    }
+
+    /// Adds a watch on `region` of the screen that `resume` stops at once
+    /// a pixel in it flips
+    pub fn add_screen_watch(&mut self, region: Rect) {
+        self.screen_watches.push(ScreenWatch::new(region, self.cpu.display()));
+    }
+
+    /// Removes every watch, on the screen as well
+    pub fn clear_watches(&mut self) {
+        self.watches.clear();
+        self.triggered = None;
+        self.screen_watches.clear();
+        self.drawn = None;
+    }
+
+    /// The watch that stopped the last `resume`, if one did
+    pub fn triggered_watch(&self) -> Option<&Watch> {
+        self.triggered.map(|ind| &self.watches[ind].0)
+    }
+
+    /// The screen watch that stopped the last `resume`, if one did, with
+    /// the address of the instruction that drew in it
+    pub fn triggered_screen_watch(&self) -> Option<(&ScreenWatch, Address)> {
+        self.drawn.map(|(ind, addr)| (&self.screen_watches[ind], addr))
+    }
+
+    /// Checks the screen watches over what was drawn since the last check,
+    /// returning the first whose pixels changed
+    fn update_screen_watches(&mut self) -> Option<usize> {
+        let touched = self.cpu.take_touched()?;
+        let display = self.cpu.display();
+        let mut triggered = None;
+
+        for (ind, watch) in self.screen_watches.iter_mut().enumerate() {
+            if watch.update(touched, display) && triggered.is_none() {
+                triggered = Some(ind);
+            }
+        }
+
+        triggered
+    }
+
+    /// Re-evaluates every watch, returning the first that just became true
+    fn update_watches(&mut self) -> Option<usize> {
+        let state = self.cpu.state();
+        let mut triggered = None;
+
+        for (ind, (watch, held)) in self.watches.iter_mut().enumerate() {
+            let holds = watch.eval(&state);
+            if holds && !*held && triggered.is_none() {
+                triggered = Some(ind);
+            }
+            *held = holds;
+        }
+
+        triggered
+    }
+
+    /// Sets a breakpoint at `addr`, or removes it if there already is one
+    ///
+    /// Returns whether there is now a breakpoint at `addr`
+    pub fn toggle_breakpoint(&mut self, addr: Address) -> bool {
+        match self.breakpoints.iter().position(|&bp| bp == addr) {
+            Some(pos) => {
+                self.breakpoints.remove(pos);
+                false
+            }
+            None => {
+                self.breakpoints.push(addr);
+                true
+            }
+        }
+    }
+
+    /// Names addresses after `symbols`' labels, and lets commands take
+    /// labels in place of addresses
+    pub fn set_symbols(&mut self, symbols: SymbolTable) {
+        self.symbols = symbols;
+    }
+
+    /// Lets the debugger show and step through `text`, the source of the
+    /// program, by the line each instruction came from
+    ///
+    /// `name` is what `FILE:LINE` addresses should call the file, usually
+    /// its path.
+    pub fn set_source(&mut self, name: &str, text: &str, lines: LineTable) {
+        let text = text.lines().map(String::from).collect();
+        self.source = Some(Source { name: name.to_string(), text, lines });
+    }
+
+    /// The source line of the instruction about to run, if it's the start
+    /// of one
+    pub fn source_line(&self) -> Option<usize> {
+        self.source.as_ref()?.lines.line(self.cpu.program_counter as Address)
+    }
+
+    /// `addr` in hex with the label it's in and the source line it came
+    /// from, as far as they're known
+    fn describe(&self, addr: Address) -> String {
+        let mut text = self.symbols.describe(addr);
+        if let Some(source) = &self.source {
+            if let Some(line) = source.lines.line(addr) {
+                let _ = write!(text, " ({}:{})", source.name, line);
+            }
+        }
+        text
+    }
+
+    /// Reads an address typed into a command: a label, `FILE:LINE`, or hex
+    /// like `2A0` or `0x2A0`
+    fn parse_address(&self, text: &str) -> Option<Address> {
+        if let (Some(source), Some((file, line))) = (&self.source, text.rsplit_once(':')) {
+            // a path on its own, or the file name at the end of one
+            let named = source.name == file || source.name.ends_with(&format!("/{}", file));
+            return line.parse().ok().filter(|_| named).and_then(|line| source.lines.addr(line));
+        }
+        self.symbols.addr(text).or_else(|| {
+            let digits = text.trim_start_matches("0x").trim_start_matches("0X");
+            Address::from_str_radix(digits, 16).ok()
+        })
+    }
+
+    /// Whether the program has terminated
+    pub fn halted(&self) -> bool {
+        self.halted
+    }
+
+    /// The error that stopped the program, if one did
+    pub fn error(&self) -> Option<CpuError> {
+        self.error
+    }
+
+    /// Says how the program ended, after `count` more instructions
+    fn halted_message(&self, count: usize) -> String {
+        match self.error {
+            Some(err) => format!("Program stopped after {} instructions: {}", count, err),
+            None => format!("Program terminated after {} instructions", count),
+        }
+    }
+
+    /// Formats `len` bytes of memory from `addr`, sixteen to a line with
+    /// their ASCII beside them
+    pub fn memory_dump(&self, addr: Address, len: usize) -> String {
+        MemoryView::new(&self.cpu.memory, addr as usize, len).render(&self.cpu.memory)
+    }
+
+    /// Draws the screen with the registers, stack and disassembly beside it
+    pub fn view(&self) -> String {
+        let display = &self.cpu.display;
+        let border = format!("+{}+", "-".repeat(display.width()));
+        let mut left = vec![border.clone()];
+        // two rows of pixels fit in one line of half blocks
+        let mut rows = display.rows();
+        while let (Some(top), Some(bottom)) = (rows.next(), rows.next()) {
+            let line: String = top
+                .iter()
+                .zip(bottom)
+                .map(|pixels| match pixels {
+                    (true, true) => '█',
+                    (true, false) => '▀',
+                    (false, true) => '▄',
+                    (false, false) => ' ',
+                })
+                .collect();
+            left.push(format!("|{}|", line));
+        }
+        left.push(border);
+
+        let mut view = String::new();
+        let right = self.side_panel();
+        for ind in 0..left.len().max(right.len()) {
+            let left = left.get(ind).map(String::as_str).unwrap_or("");
+            let right = right.get(ind).map(String::as_str).unwrap_or("");
+            let _ = writeln!(view, "{:width$}  {}", left, right, width = display.width() + 2);
+        }
+
+        view
+    }
+
+    /// Lines of registers, stack and disassembly for beside the screen
+    fn side_panel(&self) -> Vec<String> {
+        let cpu = &self.cpu;
+        let state = cpu.state();
+        let mut lines: Vec<String> = state.to_string().lines().map(String::from).collect();
+        if !self.symbols.is_empty() {
+            // the stack again, naming the routine each call came from
+            let frames: Vec<String> = state.stack_in_use().iter().map(|&addr| self.symbols.describe(addr)).collect();
+            lines.pop();
+            lines.push(format!("Stack: {}", frames.join(" ")));
+        }
+        lines.push(String::new());
+
+        let pc = cpu.program_counter;
+        if let (Some(source), Some(line)) = (&self.source, self.source_line()) {
+            let text = source.text.get(line - 1).map(|text| text.trim()).unwrap_or_default();
+            lines.push(format!("{}:{}  {}", source.name, line, text));
+        }
+        let start = pc.saturating_sub(DISASSEMBLY_CONTEXT * 2);
+        let end = (pc + DISASSEMBLY_CONTEXT * 2).min(cpu.memory.len() - 2);
+        for addr in (start..=end).step_by(2) {
+            let opcode = (cpu.memory[addr] as u16) << 8 | cpu.memory[addr + 1] as u16;
+            let marker = if addr == pc { '>' } else { ' ' };
+            let breakpoint = if self.breakpoints.contains(&(addr as Address)) { '*' } else { ' ' };
+            if let Some(name) = self.symbols.name(addr as Address) {
+                lines.push(format!("   {}:", name));
+            }
+            let instruction = Instruction::decode(opcode);
+            let target = match instruction {
+                Instruction::Sys(nnn) | Instruction::Jump(nnn) | Instruction::Call(nnn) | Instruction::SetI(nnn) => {
+                    self.symbols.name(nnn.get())
+                }
+                _ => None,
+            };
+            lines.push(format!(
+                "{}{} 0x{:03X}  {:04X}  {}{}",
+                marker,
+                breakpoint,
+                addr,
+                opcode,
+                instruction,
+                target.map(|name| format!("  <{}>", name)).unwrap_or_default()
+            ));
+        }
+
+        lines
+    }
+
+    /// Reads commands from `input` until it runs out or the user quits,
+    /// redrawing the view to `output` after each one
+    pub fn run<R: BufRead, W: Write>(&mut self, input: R, mut output: W) -> io::Result<()> {
+        let mut message = String::new();
+        let mut lines = input.lines();
+
+        loop {
+            // clear the terminal and go back to the top left corner
+            write!(output, "\x1b[2J\x1b[H{}", self.view())?;
+            writeln!(output, "{}", message)?;
+            write!(
+                output,
+                "[s]tep [N], [n]ext line [N], [c]ontinue, [b]reak ADDR, [w]atch EXPR, [d]raw X Y W H, [m]em ADDR [LEN], [q]uit > "
+            )?;
+            output.flush()?;
+
+            let line = match lines.next() {
+                Some(line) => line?,
+                None => return Ok(()),
+            };
+            let mut words = line.split_whitespace();
+
+            message = match (words.next(), words.next(), words.next()) {
+                (None, _, _) | (Some("s"), None, _) => self.step_message(1),
+                (Some("s"), Some(count), _) => match count.parse() {
+                    Ok(count) => self.step_message(count),
+                    Err(_) => format!("Not a number of steps: {}", count),
+                },
+                (Some("n"), count, _) => match count.map_or(Ok(1), str::parse) {
+                    Ok(count) => self.step_line_message(count),
+                    Err(_) => format!("Not a number of lines: {}", count.unwrap_or_default()),
+                },
+                (Some("c"), _, _) => {
+                    let count = self.resume();
+                    if self.halted {
+                        self.halted_message(count)
+                    } else if let Some(watch) = self.triggered_watch() {
+                        format!("Watch `{}` became true after {} instructions", watch, count)
+                    } else if let Some((watch, addr)) = self.triggered_screen_watch() {
+                        format!("{} drawn on at {} after {} instructions", watch, self.describe(addr), count)
+                    } else if count == CONTINUE_LIMIT {
+                        format!("Stopped after {} instructions without reaching a breakpoint", count)
+                    } else {
+                        format!("Breakpoint reached after {} instructions", count)
+                    }
+                }
+                (Some("b"), Some(addr), _) => match self.parse_address(addr) {
+                    Some(addr) if self.toggle_breakpoint(addr) => format!("Breakpoint set at {}", self.describe(addr)),
+                    Some(addr) => format!("Breakpoint cleared at {}", self.describe(addr)),
+                    None => format!("Not an address: {}", addr),
+                },
+                (Some("w"), None, _) => {
+                    self.clear_watches();
+                    String::from("Watches cleared")
+                }
+                (Some("w"), Some(_), _) => {
+                    let expr = line.trim_start()[1..].trim();
+                    match expr.parse() {
+                        Ok(watch) => {
+                            let message = format!("Watching `{}`", watch);
+                            self.add_watch(watch);
+                            message
+                        }
+                        Err(err) => err,
+                    }
+                }
+                (Some("d"), Some(_), _) => {
+                    let numbers: Result<Vec<usize>, _> = line.split_whitespace().skip(1).map(str::parse).collect();
+                    match numbers.as_deref() {
+                        Ok(&[x, y, width, height]) if width > 0 && height > 0 && self.cpu.display.rect().contains_rect(&Rect { x, y, width, height }) => {
+                            self.add_screen_watch(Rect { x, y, width, height });
+                            format!("Watching {}", self.screen_watches[self.screen_watches.len() - 1])
+                        }
+                        _ => {
+                            let display = &self.cpu.display;
+                            format!("Not a rectangle of the {}x{} screen: {}", display.width(), display.height(), line.trim())
+                        }
+                    }
+                }
+                (Some("m"), Some(addr), len) => {
+                    let len = len.and_then(|len| len.parse().ok()).unwrap_or(64);
+                    match self.parse_address(addr) {
+                        Some(addr) => self.memory_dump(addr, len),
+                        None => format!("Not an address: {}", addr),
+                    }
+                }
+                (Some("e"), Some(addr), Some(_)) => match self.parse_address(addr) {
+                    Some(addr) => {
+                        let bytes: Vec<&str> = line.split_whitespace().skip(2).collect();
+                        match MemoryView::edit(self.cpu, addr as usize, &bytes.join(" ")) {
+                            Ok(count) => format!("Wrote {} bytes at 0x{:03X}", count, addr),
+                            Err(err) => err,
+                        }
+                    }
+                    None => format!("Not an address: {}", addr),
+                },
+                (Some("q"), _, _) => return Ok(()),
+                (Some(command), _, _) => format!("Unknown command: {}", command),
+            };
+        }
+    }
+
+    /// Steps `count` times, describing where the program ended up
+    fn step_message(&mut self, count: usize) -> String {
+        for ind in 0..count {
+            if !self.step() {
+                return self.halted_message(ind);
+            }
+        }
+
+        String::new()
+    }
+
+    /// Steps `count` source lines, describing where the program ended up
+    fn step_line_message(&mut self, count: usize) -> String {
+        let mut ran = 0;
+        for _ in 0..count {
+            ran += self.step_line();
+            if self.halted {
+                return self.halted_message(ran);
+            }
+        }
+
+        String::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CpuBuilder;
+
+    fn cpu_with_program(program: &[u8]) -> Cpu {
+        let mut memory = [0; 0x1000];
+        memory[..program.len()].copy_from_slice(program);
+        CpuBuilder::new().memory(memory).build()
+    }
+
+    #[test]
+    fn resume_stops_at_breakpoints() {
+        // LD V0, 1; ADD V0, 1; ADD V0, 1; halt
+        let mut cpu = cpu_with_program(&[0x60, 0x01, 0x70, 0x01, 0x70, 0x01]);
+        let mut debugger = Debugger::new(&mut cpu);
+        debugger.toggle_breakpoint(0x204);
+
+        assert_eq!(debugger.resume(), 2);
+        assert!(!debugger.halted());
+
+        debugger.resume();
+        assert!(debugger.halted());
+        assert_eq!(cpu.registers(0), 3);
+    }
+
+    #[test]
+    fn resume_stops_when_watch_becomes_true() {
+        // LD V0, 1; ADD V0, 1 forever
+        let mut cpu = cpu_with_program(&[0x60, 0x01, 0x70, 0x01, 0x12, 0x02]);
+        let mut debugger = Debugger::new(&mut cpu);
+        debugger.add_watch("V0 >= 3".parse().unwrap());
+        debugger.add_watch("PC == 0x200".parse().unwrap());
+
+        assert_eq!(debugger.resume(), 4);
+        assert_eq!(debugger.triggered_watch().unwrap().to_string(), "V0 >= 3");
+
+        // already true, so it doesn't stop again
+        debugger.clear_watches();
+        debugger.add_watch("V0 >= 3".parse().unwrap());
+        debugger.toggle_breakpoint(0x204);
+        debugger.resume();
+        assert!(debugger.triggered_watch().is_none());
+        assert_eq!(cpu.registers(0), 4);
+    }
+
+    #[test]
+    fn resume_stops_when_a_watched_region_changes() {
+        // LD I, 0 (the font's 0); DRW V0, V0, 5; LD V0, 20; LD V1, 10; DRW V0, V1, 5; JP 0x20A
+        let mut cpu = cpu_with_program(&[0xA0, 0x00, 0xD0, 0x05, 0x60, 0x14, 0x61, 0x0A, 0xD0, 0x15, 0x12, 0x0A]);
+        let mut debugger = Debugger::new(&mut cpu);
+        debugger.add_screen_watch(Rect { x: 16, y: 8, width: 16, height: 8 });
+
+        assert_eq!(debugger.resume(), 5);
+        let (watch, addr) = debugger.triggered_screen_watch().unwrap();
+        assert_eq!((watch.to_string().as_str(), addr), ("columns 16-31, rows 8-15", 0x208));
+
+        debugger.toggle_breakpoint(0x20A);
+        debugger.resume();
+        assert!(debugger.triggered_screen_watch().is_none());
+    }
+
+    #[test]
+    fn screen_watches_only_take_rectangles_on_the_screen() {
+        let mut cpu = cpu_with_program(&[0x12, 0x00]);
+        let input = "d 0 0 16 8\nd 60 0 8 8\nd 1 2\nq\n".as_bytes();
+        let mut output = vec![];
+        Debugger::new(&mut cpu).run(input, &mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+
+        assert!(output.contains("Watching columns 0-15, rows 0-7"));
+        assert!(output.contains("Not a rectangle of the 64x32 screen: d 60 0 8 8"));
+        assert!(output.contains("Not a rectangle of the 64x32 screen: d 1 2"));
+    }
+
+    #[test]
+    fn step_stops_on_errors() {
+        // RET with nothing to return to
+        let mut cpu = cpu_with_program(&[0x00, 0xEE]);
+        let mut debugger = Debugger::new(&mut cpu);
+
+        assert!(!debugger.step());
+        assert!(debugger.halted());
+        assert_eq!(debugger.error(), Some(CpuError::StackUnderflow));
+        assert_eq!(debugger.step_message(1), "Program stopped after 0 instructions: Stack underflow");
+    }
+
+    #[test]
+    fn toggle_breakpoint_sets_and_clears() {
+        let mut cpu = CpuBuilder::new().build();
+        let mut debugger = Debugger::new(&mut cpu);
+
+        assert!(debugger.toggle_breakpoint(0x300));
+        assert!(!debugger.toggle_breakpoint(0x300));
+        assert!(debugger.breakpoints.is_empty());
+    }
+
+    #[test]
+    fn memory_dump_formats_rows_of_sixteen() {
+        let mut cpu = cpu_with_program(&[0xAB; 20]);
+        let debugger = Debugger::new(&mut cpu);
+
+        let dump = debugger.memory_dump(0x200, 20);
+        assert_eq!(dump.lines().count(), 2);
+        assert!(dump.starts_with("0x200: AB AB"));
+        assert!(dump.contains("0x210: AB AB AB AB "));
+        assert!(dump.ends_with("|....|\n"));
+    }
+
+    #[test]
+    fn view_shows_registers_and_disassembly() {
+        let mut cpu = cpu_with_program(&[0x6A, 0x02, 0x00, 0xE0]);
+        let view = Debugger::new(&mut cpu).view();
+
+        assert!(view.contains("PC 0x200"));
+        assert!(view.contains(">  0x200  6A02  LD VA, 0x02"));
+        assert!(view.contains("0x202  00E0  CLS"));
+    }
+
+    #[test]
+    fn symbols_name_addresses_in_the_view_and_commands() {
+        // CALL draw; halt; draw: LD I, sprite; RET; sprite
+        let mut cpu = cpu_with_program(&[0x22, 0x04, 0x00, 0x00, 0xA2, 0x08, 0x00, 0xEE, 0xFF]);
+        let mut debugger = Debugger::new(&mut cpu);
+        debugger.set_symbols("200 main\n204 draw\n208 sprite".parse().unwrap());
+        debugger.step();
+
+        let view = debugger.view();
+        assert!(view.contains("Stack: 0x202 <main+2>"));
+        assert!(view.contains("   draw:"));
+        assert!(view.contains("LD I, 0x208  <sprite>"));
+
+        let mut output = vec![];
+        debugger.run("b draw\nq\n".as_bytes(), &mut output).unwrap();
+        assert!(String::from_utf8(output).unwrap().contains("Breakpoint set at 0x204 <draw>"));
+    }
+
+    #[test]
+    fn source_lines_step_show_and_take_breakpoints() {
+        let source = ": main\n  v0 := 1  v1 := 2\n  v2 := 3\n\n  v3 := 4\n";
+        let program = crate::asm::assemble_program(source).unwrap();
+        let mut cpu = cpu_with_program(&program.rom);
+        let mut debugger = Debugger::new(&mut cpu);
+        debugger.set_source("games/demo.8o", source, program.lines);
+
+        assert!(debugger.view().contains("games/demo.8o:2  v0 := 1  v1 := 2"));
+        assert_eq!(debugger.step_line(), 2);
+        assert_eq!(debugger.source_line(), Some(3));
+
+        let mut output = vec![];
+        debugger.run("b demo.8o:4\nc\nb other.8o:4\nq\n".as_bytes(), &mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.contains("Breakpoint set at 0x206 (games/demo.8o:5)"));
+        assert!(output.contains("Not an address: other.8o:4"));
+        assert_eq!(debugger.source_line(), Some(5));
+    }
+
+    #[test]
+    fn run_reads_commands_until_quit() {
+        let mut cpu = cpu_with_program(&[0x60, 0x05, 0x61, 0x06, 0x62, 0x07]);
+        let input = "s\ns 1\nb 206\nw V1 == 6\nw VZ\nm 200 2\ne 100 FF\ne 204 63 09\nq\ns\n".as_bytes();
+        let mut output = vec![];
+        let mut debugger = Debugger::new(&mut cpu);
+
+        debugger.run(input, &mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+
+        assert!(output.contains("Breakpoint set at 0x206"));
+        assert!(output.contains("Watching `V1 == 6`"));
+        assert!(output.contains("Unknown register VZ"));
+        assert!(output.contains("0x200: 60 05"));
+        assert!(output.contains("Memory at 0x100 belongs to the interpreter"));
+        assert!(output.contains("Wrote 2 bytes at 0x204"));
+        assert_eq!(cpu.memory()[0x204..0x206], [0x63, 0x09]);
+        assert_eq!(cpu.registers(0), 5);
+        assert_eq!(cpu.registers(1), 6);
+        assert_eq!(cpu.registers(2), 0);
+    }
+}
@@ -0,0 +1,403 @@
+//! Watch expressions, for pausing when the CPU gets into a certain state
+//!
+//! A watch is a small expression over the registers, like
+//! `V3 > 0x10 && I == 0x300`. It can use:
+//!
+//! - `V0` to `VF`, `I`, `PC`, `SP`, `DT` and `ST`
+//! - numbers, hex with a leading `0x` or decimal
+//! - `==`, `!=`, `<`, `<=`, `>` and `>=`
+//! - `&&`, `||` and brackets
+//!
+//! A value on its own is true when it's non-zero.
+//!
+//! A [`ScreenWatch`] watches a rectangle of the screen instead, for finding
+//! the code that draws something.
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::screen::{Display, Rect};
+use crate::CpuState;
+
+/// A parsed watch expression
+///
+/// # Examples
+/// ```
+/// use chip_8::debugger::Watch;
+/// use chip_8::CpuBuilder;
+///
+/// let watch: Watch = "V3 > 0x10 && PC == 0x200".parse().unwrap();
+///
+/// let mut registers = [0; 16]; registers[3] = 0x11;
+/// let cpu = CpuBuilder::new().registers(registers).build();
+/// assert!(watch.eval(&cpu.state()));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Watch {
+    source: String,
+    expr: Expr,
+}
+
+impl Watch {
+    /// Whether the expression holds for `state`
+    pub fn eval(&self, state: &CpuState) -> bool {
+        self.expr.eval(state) != 0
+    }
+}
+
+impl FromStr for Watch {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Watch, String> {
+        let tokens = tokenize(text)?;
+        let mut parser = Parser { tokens: &tokens, pos: 0 };
+        let expr = parser.or()?;
+
+        match parser.tokens.get(parser.pos) {
+            None => Ok(Watch { source: text.trim().to_string(), expr }),
+            Some(token) => Err(format!("Unexpected {} in watch", token)),
+        }
+    }
+}
+
+impl fmt::Display for Watch {
+    /// Shows the expression as it was written
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+/// A rectangle of the screen to pause at when any pixel in it flips
+///
+/// It keeps its own copy of the pixels, only comparing them again after
+/// something is drawn over the rectangle.
+///
+/// # Examples
+/// ```
+/// use chip_8::debugger::ScreenWatch;
+/// use chip_8::screen::Rect;
+/// use chip_8::CpuBuilder;
+///
+/// let mut cpu = CpuBuilder::new().build();
+/// // LD I, 0 (the font's 0); DRW V0, V0, 5
+/// cpu.load_program(&[0xA0, 0x00, 0xD0, 0x05]).unwrap();
+/// let mut watch = ScreenWatch::new(Rect { x: 0, y: 0, width: 16, height: 8 }, cpu.display());
+///
+/// cpu.run_cycles(2);
+/// let touched = cpu.take_touched().unwrap();
+/// assert!(watch.update(touched, cpu.display()));
+/// assert_eq!(watch.to_string(), "columns 0-15, rows 0-7");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScreenWatch {
+    region: Rect,
+    // the region's pixels as of the last update, a row at a time
+    pixels: Vec<bool>,
+}
+
+impl ScreenWatch {
+    /// Watches `region` of `display`, cut down to what's on the screen
+    pub fn new(region: Rect, display: &Display) -> ScreenWatch {
+        let right = (region.x + region.width).min(display.width());
+        let bottom = (region.y + region.height).min(display.height());
+        let (x, y) = (region.x.min(right), region.y.min(bottom));
+        let region = Rect { x, y, width: right - x, height: bottom - y };
+        ScreenWatch { region, pixels: ScreenWatch::read(region, display) }
+    }
+
+    /// The rectangle watched
+    pub fn region(&self) -> Rect {
+        self.region
+    }
+
+    /// Whether any pixel in the rectangle flipped since the last update,
+    /// `touched` being what was drawn on since then, as
+    /// [`Cpu::take_touched`](crate::Cpu::take_touched) gives it
+    pub fn update(&mut self, touched: Rect, display: &Display) -> bool {
+        if !self.region.intersects(&touched) {
+            return false;
+        }
+        let pixels = ScreenWatch::read(self.region, display);
+        let changed = pixels != self.pixels;
+        self.pixels = pixels;
+        changed
+    }
+
+    fn read(region: Rect, display: &Display) -> Vec<bool> {
+        // a screen switched down to low resolution can leave part of the
+        // region off it, which counts as unlit
+        let lit = |x, y| x < display.width() && y < display.height() && display.pixel(x, y);
+        (region.y..region.y + region.height)
+            .flat_map(|y| (region.x..region.x + region.width).map(move |x| lit(x, y)))
+            .collect()
+    }
+}
+
+impl fmt::Display for ScreenWatch {
+    /// Names the columns and rows watched, counting from 0
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let Rect { x, y, width, height } = self.region;
+        write!(
+            f,
+            "columns {}-{}, rows {}-{}",
+            x,
+            (x + width).saturating_sub(1),
+            y,
+            (y + height).saturating_sub(1)
+        )
+    }
+}
+
+/// Something in the CPU state a watch can look at
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Operand {
+    Register(usize),
+    I,
+    Pc,
+    Sp,
+    Delay,
+    Sound,
+    Number(u32),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Comparison {
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Expr {
+    Operand(Operand),
+    Compare(Box<Expr>, Comparison, Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+}
+
+impl Expr {
+    /// Works the expression out, comparisons and logic giving 1 or 0
+    fn eval(&self, state: &CpuState) -> u32 {
+        match self {
+            Expr::Operand(operand) => match *operand {
+                Operand::Register(x) => state.registers[x] as u32,
+                Operand::I => state.i as u32,
+                Operand::Pc => state.pc as u32,
+                Operand::Sp => state.sp as u32,
+                Operand::Delay => state.delay as u32,
+                Operand::Sound => state.sound as u32,
+                Operand::Number(n) => n,
+            },
+            Expr::Compare(left, comparison, right) => {
+                let (left, right) = (left.eval(state), right.eval(state));
+                let result = match comparison {
+                    Comparison::Equal => left == right,
+                    Comparison::NotEqual => left != right,
+                    Comparison::Less => left < right,
+                    Comparison::LessEqual => left <= right,
+                    Comparison::Greater => left > right,
+                    Comparison::GreaterEqual => left >= right,
+                };
+                result as u32
+            }
+            Expr::And(left, right) => (left.eval(state) != 0 && right.eval(state) != 0) as u32,
+            Expr::Or(left, right) => (left.eval(state) != 0 || right.eval(state) != 0) as u32,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Token {
+    Operand(Operand),
+    Compare(Comparison),
+    And,
+    Or,
+    Open,
+    Close,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Token::Operand(_) => write!(f, "value"),
+            Token::Compare(_) => write!(f, "comparison"),
+            Token::And => write!(f, "`&&`"),
+            Token::Or => write!(f, "`||`"),
+            Token::Open => write!(f, "`(`"),
+            Token::Close => write!(f, "`)`"),
+        }
+    }
+}
+
+/// Operators and brackets, longest first so `<=` isn't read as `<`
+const SYMBOLS: [(&str, Token); 10] = [
+    ("==", Token::Compare(Comparison::Equal)),
+    ("!=", Token::Compare(Comparison::NotEqual)),
+    ("<=", Token::Compare(Comparison::LessEqual)),
+    (">=", Token::Compare(Comparison::GreaterEqual)),
+    ("<", Token::Compare(Comparison::Less)),
+    (">", Token::Compare(Comparison::Greater)),
+    ("&&", Token::And),
+    ("||", Token::Or),
+    ("(", Token::Open),
+    (")", Token::Close),
+];
+
+/// Splits a watch into tokens
+fn tokenize(text: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = vec![];
+    let mut rest = text.trim_start();
+
+    while let Some(c) = rest.chars().next() {
+        if let Some((symbol, token)) = SYMBOLS.iter().find(|(symbol, _)| rest.starts_with(symbol)) {
+            tokens.push(*token);
+            rest = &rest[symbol.len()..];
+        } else if c.is_ascii_alphanumeric() {
+            let len = rest.find(|c: char| !c.is_ascii_alphanumeric()).unwrap_or(rest.len());
+            tokens.push(Token::Operand(parse_operand(&rest[..len])?));
+            rest = &rest[len..];
+        } else {
+            return Err(format!("Unexpected `{}` in watch", c));
+        }
+
+        rest = rest.trim_start();
+    }
+
+    Ok(tokens)
+}
+
+/// Reads a register name or a number
+fn parse_operand(word: &str) -> Result<Operand, String> {
+    let upper = word.to_ascii_uppercase();
+    let operand = match upper.as_str() {
+        "I" => Operand::I,
+        "PC" => Operand::Pc,
+        "SP" => Operand::Sp,
+        "DT" => Operand::Delay,
+        "ST" => Operand::Sound,
+        _ if upper.len() == 2 && upper.starts_with('V') => match usize::from_str_radix(&upper[1..], 16) {
+            Ok(x) => Operand::Register(x),
+            Err(_) => return Err(format!("Unknown register {}", word)),
+        },
+        _ if upper.starts_with("0X") => match u32::from_str_radix(&upper[2..], 16) {
+            Ok(n) => Operand::Number(n),
+            Err(_) => return Err(format!("Not a number: {}", word)),
+        },
+        _ => match upper.parse() {
+            Ok(n) => Operand::Number(n),
+            Err(_) => return Err(format!("Unknown value {}", word)),
+        },
+    };
+
+    Ok(operand)
+}
+
+/// Recursive descent over the tokens, `||` binding loosest
+struct Parser<'a> {
+    tokens: &'a [Token],
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).copied();
+        self.pos += 1;
+        token
+    }
+
+    fn peek(&self) -> Option<Token> {
+        self.tokens.get(self.pos).copied()
+    }
+
+    fn or(&mut self) -> Result<Expr, String> {
+        let mut expr = self.and()?;
+        while self.peek() == Some(Token::Or) {
+            self.pos += 1;
+            expr = Expr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<Expr, String> {
+        let mut expr = self.compare()?;
+        while self.peek() == Some(Token::And) {
+            self.pos += 1;
+            expr = Expr::And(Box::new(expr), Box::new(self.compare()?));
+        }
+        Ok(expr)
+    }
+
+    fn compare(&mut self) -> Result<Expr, String> {
+        let left = self.value()?;
+        match self.peek() {
+            Some(Token::Compare(comparison)) => {
+                self.pos += 1;
+                Ok(Expr::Compare(Box::new(left), comparison, Box::new(self.value()?)))
+            }
+            _ => Ok(left),
+        }
+    }
+
+    fn value(&mut self) -> Result<Expr, String> {
+        match self.next() {
+            Some(Token::Operand(operand)) => Ok(Expr::Operand(operand)),
+            Some(Token::Open) => {
+                let expr = self.or()?;
+                match self.next() {
+                    Some(Token::Close) => Ok(expr),
+                    _ => Err(String::from("Missing `)` in watch")),
+                }
+            }
+            Some(token) => Err(format!("Expected a value but found {} in watch", token)),
+            None => Err(String::from("Watch ended early")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CpuBuilder;
+
+    fn state(registers: [u8; 16], i: u16) -> CpuState {
+        let mut state = CpuBuilder::new().registers(registers).build().state();
+        state.i = i;
+        state
+    }
+
+    #[test]
+    fn watch_compares_registers() {
+        let mut registers = [0; 16];
+        registers[3] = 0x11;
+        let watch: Watch = "V3 > 0x10 && I == 0x300".parse().unwrap();
+
+        assert!(watch.eval(&state(registers, 0x300)));
+        assert!(!watch.eval(&state(registers, 0x302)));
+        assert!(!watch.eval(&state([0; 16], 0x300)));
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or() {
+        let watch: Watch = "vf == 1 || v0 && (pc == 512)".parse().unwrap();
+        let mut registers = [0; 16];
+
+        assert!(!watch.eval(&state(registers, 0)));
+        registers[0] = 4;
+        assert!(watch.eval(&state(registers, 0)));
+        registers[0] = 0;
+        registers[0xF] = 1;
+        assert!(watch.eval(&state(registers, 0)));
+    }
+
+    #[test]
+    fn bad_watches_are_rejected() {
+        assert_eq!("VG == 1".parse::<Watch>(), Err(String::from("Unknown register VG")));
+        assert_eq!("V1 ==".parse::<Watch>(), Err(String::from("Watch ended early")));
+        assert_eq!("(V1".parse::<Watch>(), Err(String::from("Missing `)` in watch")));
+        assert_eq!("V1 $ 2".parse::<Watch>(), Err(String::from("Unexpected `$` in watch")));
+        assert_eq!("V1 V2".parse::<Watch>(), Err(String::from("Unexpected value in watch")));
+    }
+}
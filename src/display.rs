@@ -1,84 +1,791 @@
-extern crate glutin_window;
-extern crate graphics;
-extern crate opengl_graphics;
-extern crate piston;
+//! The game itself, with everything the player can do besides play it:
+//! pausing, rewinding, changing speed and so on, shown in whichever window
+//! the binary was built with
 
-use glutin_window::GlutinWindow as Window;
-use opengl_graphics::{GlGraphics, OpenGL};
-use piston::event_loop::{EventSettings, Events};
-use piston::input::{RenderArgs, RenderEvent};
-use piston::window::WindowSettings;
+use std::fs;
+use std::mem;
+use std::path::{Path, PathBuf};
+#[cfg(feature = "watch")]
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
 
-use chip_8::CPU;
+#[cfg(feature = "watch")]
+use notify::{RecursiveMode, Watcher};
 
-pub struct App {
-    gl: GlGraphics,
-}
-
-impl App {
-    fn render(&mut self, args: &RenderArgs, screen: &[[bool; 64]; 32]) {
-        use graphics::*;
+use chip_8::asm;
+use chip_8::audio::{AudioSettings, AudioSink, Tone};
+use chip_8::cheats::Cheats;
+#[cfg(feature = "image")]
+use chip_8::capture::{self, Recording};
+use chip_8::crash::{CrashReport, Trace};
+use chip_8::frontend::{Frontend, InputEvents};
+use chip_8::high_score::{self, ScoreKeeper};
+#[cfg(feature = "gamepad")]
+use chip_8::gamepad::Gamepads;
+use chip_8::instruction::Instruction;
+use chip_8::keymap::{KeyInput, MacroPlayer};
+use chip_8::library::Library;
+use chip_8::rewind::Rewind;
+use chip_8::rom_config::RomConfig;
+use chip_8::rom_file;
+use chip_8::timing::{Clock, TimingModel};
+use chip_8::screen::{Palette, Phosphor};
+use chip_8::{Autofire, Cpu, CpuError, Display, KeyMap, StopReason};
 
-        const GREEN: [f32; 4] = [0.0, 1.0, 0.0, 1.0];
-        const BLACK: [f32; 4] = [0.0, 0.0, 0.0, 1.0];
+/// How the game screen is fitted to the window
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ScaleMode {
+    /// Whole window pixels to a CHIP-8 pixel, as big as fits, centered
+    #[default]
+    Integer,
+    /// Fills the whole window, however that stretches the pixels
+    Stretch,
+}
 
-        let mut squares: Vec<types::Rectangle> = vec![];
+/// Where the game screen goes in a window: its top left corner, and how
+/// big a CHIP-8 pixel is
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Layout {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
 
-        for (row_ind, row) in screen.iter().enumerate() {
-            for (col_ind, col) in row.iter().enumerate() {
-                if *col {
-                    let square = rectangle::square((col_ind*12 + 4) as f64, (row_ind*12 + 4) as f64, 10.0);
-                    squares.push(square);
+impl Layout {
+    /// Fits a screen of `screen` pixels, width then height, into a window
+    /// of `size`
+    pub fn new(size: [f64; 2], screen: [usize; 2], mode: ScaleMode) -> Layout {
+        let (columns, rows) = (screen[0] as f64, screen[1] as f64);
+        let (width, height) = (size[0] / columns, size[1] / rows);
+        match mode {
+            ScaleMode::Integer => {
+                let cell = width.min(height).floor().max(1.0);
+                Layout {
+                    x: ((size[0] - cell * columns) / 2.0).floor(),
+                    y: ((size[1] - cell * rows) / 2.0).floor(),
+                    width: cell,
+                    height: cell,
                 }
             }
+            ScaleMode::Stretch => Layout { x: 0.0, y: 0.0, width, height },
         }
+    }
+}
 
-        self.gl.draw(args.viewport(), |c, gl| {
-            clear(BLACK, gl);
+/// The emulator's own keys, which the game never sees
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Hotkey {
+    /// Pauses and resumes the game, on P
+    Pause,
+    /// Pauses the game if it isn't already and runs one frame, on .
+    StepFrame,
+    /// Restarts the game from the beginning, on Backspace
+    Reset,
+    /// Runs the game backwards while held, on Tab
+    Rewind,
+    /// Halves the speed, down to a quarter, on [
+    Slower,
+    /// Doubles the speed, up to eight times, on ]
+    Faster,
+    /// Goes back to normal speed, on \
+    NormalSpeed,
+    /// Shows or hides the debug overlay, on F1
+    Overlay,
+    /// Goes back to the ROM list, or back to the game from it, with
+    /// `--library`, on F2
+    Library,
+    /// Switches the ROM's cheats off, or all back on, on F3
+    Cheats,
+    /// Mutes or unmutes the buzzer, on F4
+    Mute,
+    /// Saves a screenshot to the current directory, on F12
+    #[cfg(feature = "image")]
+    Screenshot,
+    /// Saves the last few seconds as a GIF to the current directory, on F11
+    #[cfg(feature = "image")]
+    Recording,
+}
 
-            for square in squares {
-                let transform = c.transform;
-                rectangle(GREEN, square, transform, gl);
-            }
-        });
+impl Hotkey {
+    /// The hotkey on the key a [`KeyMap`] would call `name`, if any
+    pub fn from_name(name: &str) -> Option<Hotkey> {
+        match name {
+            "P" => Some(Hotkey::Pause),
+            "Period" => Some(Hotkey::StepFrame),
+            "Backspace" => Some(Hotkey::Reset),
+            "Tab" => Some(Hotkey::Rewind),
+            "LeftBracket" => Some(Hotkey::Slower),
+            "RightBracket" => Some(Hotkey::Faster),
+            "Backslash" => Some(Hotkey::NormalSpeed),
+            "F1" => Some(Hotkey::Overlay),
+            "F2" => Some(Hotkey::Library),
+            "F3" => Some(Hotkey::Cheats),
+            "F4" => Some(Hotkey::Mute),
+            #[cfg(feature = "image")]
+            "F12" => Some(Hotkey::Screenshot),
+            #[cfg(feature = "image")]
+            "F11" => Some(Hotkey::Recording),
+            _ => None,
+        }
+    }
+}
+
+/// A host key going down or up, named both by where it is and by what it
+/// types, as far as the window can tell
+pub struct KeyPress {
+    /// The name of the key in its place on a US QWERTY keyboard
+    pub physical: Option<String>,
+    /// The name of the character it types in the player's layout
+    pub character: Option<String>,
+    pub pressed: bool,
+}
+
+impl KeyPress {
+    /// Sends the key to a hotkey, a CHIP-8 key or a macro, going by the name
+    /// the keymap's [`KeyInput`] asks for, and notes presses for menus by
+    /// what they type
+    pub fn route(
+        self,
+        keymap: &KeyMap,
+        input: &mut InputEvents,
+        hotkeys: &mut Vec<(Hotkey, bool)>,
+        named: &mut Vec<String>,
+    ) {
+        let KeyPress { physical, character, pressed } = self;
+        let name = match keymap.input() {
+            KeyInput::Physical => physical.or_else(|| character.clone()),
+            KeyInput::Character => character.clone().or(physical),
+        };
+        let name = match name {
+            Some(name) => name,
+            None => return,
+        };
+
+        if pressed {
+            named.push(character.unwrap_or_else(|| name.clone()));
+        }
+        if let Some(hotkey) = Hotkey::from_name(&name) {
+            hotkeys.push((hotkey, pressed));
+        } else if let Some(key) = keymap.get(&name) {
+            input.keys.push((key, pressed));
+        } else if let Some(key_macro) = keymap.get_macro(&name).filter(|_| pressed) {
+            input.macros.push(key_macro.clone());
+        }
+    }
+}
+
+/// What [`Game`] needs from a window besides drawing and the CHIP-8 keys
+pub trait GameWindow: Frontend {
+    /// Whether the next [`render`](Frontend::render) will draw anything
+    fn drawing(&self) -> bool;
+
+    /// The hotkeys pressed and released since this was last asked
+    fn take_hotkeys(&mut self) -> Vec<(Hotkey, bool)>;
+
+    /// The files dropped on the window since this was last asked
+    fn take_dropped(&mut self) -> Vec<PathBuf>;
+
+    /// The names of the keys pressed since this was last asked, whatever
+    /// they're bound to
+    fn take_pressed(&mut self) -> Vec<String>;
+
+    /// Sets the message along the bottom and the overlay lines, both drawn
+    /// over the game until they're changed
+    fn show(&mut self, message: Option<&str>, overlay: Vec<String>);
+}
+
+/// How a window starts off
+pub struct Settings {
+    /// How big a CHIP-8 pixel is to begin with
+    pub scale: u32,
+    pub scale_mode: ScaleMode,
+    pub keymap: KeyMap,
+    pub tone: Option<Tone<Box<dyn AudioSink>>>,
+    pub phosphor: Phosphor,
+    pub palette: Palette,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            scale: DEFAULT_SCALE,
+            scale_mode: ScaleMode::default(),
+            keymap: KeyMap::default(),
+            tone: None,
+            phosphor: Phosphor::default(),
+            palette: Palette::default(),
+        }
+    }
+}
+
+/// How many seconds the GIF recording goes back
+#[cfg(feature = "image")]
+const RECORDING_SECONDS: f64 = 10.0;
+
+/// How big a CHIP-8 pixel is in screenshots and recordings
+#[cfg(feature = "image")]
+const CAPTURE_SCALE: u32 = 8;
+
+/// How big a CHIP-8 pixel is in the window to begin with
+pub const DEFAULT_SCALE: u32 = 12;
+
+/// How long a message stays on screen, in seconds
+const MESSAGE_SECONDS: f64 = 3.0;
+
+/// How many ROMs the library shows at once
+const LIBRARY_LINES: usize = 20;
+
+/// Makes the CPU a ROM picked from the library runs on, from its path and
+/// contents, and the timing it runs with if not the default
+type Boot = Box<dyn Fn(&Path, &[u8]) -> (Cpu, Option<TimingModel>)>;
+
+/// Frame and instruction rates for the debug overlay, worked out about
+/// once a second from the wall clock
+struct Overlay {
+    visible: bool,
+    since: Instant,
+    frames: usize,
+    cycles: u64,
+    fps: f64,
+    ips: f64,
+}
+
+impl Overlay {
+    fn new() -> Overlay {
+        Overlay {
+            visible: false,
+            since: Instant::now(),
+            frames: 0,
+            cycles: 0,
+            fps: 0.0,
+            ips: 0.0,
+        }
     }
 
+    /// Counts a rendered frame, updating the rates once a second has passed
+    fn frame_rendered(&mut self, cpu: &Cpu) {
+        self.frames += 1;
+
+        let elapsed = self.since.elapsed().as_secs_f64();
+        if elapsed >= 1.0 {
+            self.fps = self.frames as f64 / elapsed;
+            // a reset sends the count back to zero
+            self.ips = cpu.cycles().saturating_sub(self.cycles) as f64 / elapsed;
+            self.since = Instant::now();
+            self.frames = 0;
+            self.cycles = cpu.cycles();
+        }
+    }
+
+    /// The lines to draw, none while hidden
+    fn lines(&self, cpu: &Cpu) -> Vec<String> {
+        if !self.visible {
+            return vec![];
+        }
+
+        let state = cpu.state();
+        let next = match cpu.next_opcode() {
+            Some(opcode) => format!("{:04X} {}", opcode, Instruction::decode(opcode)),
+            None => String::from("past the end of memory"),
+        };
+        vec![
+            format!("FPS {:.0}  IPS {:.0}", self.fps, self.ips),
+            format!("PC 0x{:03X} {}", state.pc, next),
+            format!("DT {:02X}  ST {:02X}", state.delay, state.sound),
+        ]
+    }
+}
+
+/// Notices when the ROM file changes, for reloading it
+#[cfg(feature = "watch")]
+struct Watch {
+    path: PathBuf,
+    // events stop once this is dropped
+    _watcher: notify::RecommendedWatcher,
+    events: mpsc::Receiver<notify::Result<notify::Event>>,
+}
+
+#[cfg(feature = "watch")]
+impl Watch {
+    fn new(path: &Path) -> notify::Result<Watch> {
+        let (sender, events) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(sender)?;
+        // editors and assemblers often save by replacing the file, which a
+        // watch on the file itself would lose track of
+        let dir = path.parent().filter(|dir| !dir.as_os_str().is_empty()).unwrap_or(Path::new("."));
+        watcher.watch(dir, RecursiveMode::NonRecursive)?;
+        Ok(Watch { path: path.to_path_buf(), _watcher: watcher, events })
+    }
+
+    /// Whether the file was written since this was last asked
+    fn changed(&self) -> bool {
+        let name = self.path.file_name();
+        let touched = |event: &notify::Event| {
+            (event.kind.is_create() || event.kind.is_modify()) && event.paths.iter().any(|path| path.file_name() == name)
+        };
+        // takes every pending event, so one save only reloads once
+        self.events.try_iter().flatten().filter(touched).count() > 0
+    }
 }
 
 pub struct Game {
-    cpu: CPU,
+    cpu: Cpu,
+    settings: Settings,
+    rewind: Rewind,
+    clock: Clock,
+    paused: bool,
+    // a frame to run even though the game is paused
+    step_frame: bool,
+    rewinding: bool,
+    macros: MacroPlayer,
+    autofire: Autofire,
+    // what to tell the player, and for how many more seconds
+    message: Option<(String, f64)>,
+    overlay: Overlay,
+    // where to write a crash report, and the trace to put in it
+    crash_report: Option<(PathBuf, Arc<Mutex<Trace>>)>,
+    // the game's best score, if a plugin knows where it keeps its score
+    high_score: Option<ScoreKeeper>,
+    cheats: Cheats,
+    audio: AudioSettings,
+    // the ROMs to pick from, whether the list is showing instead of the
+    // game, and whether a game has been picked from it yet
+    library: Option<Library>,
+    browsing: bool,
+    booted: bool,
+    // builds the CPU for each ROM picked from the library, and says how long
+    // its instructions take
+    boot: Option<Boot>,
+    #[cfg(feature = "image")]
+    recording: Recording,
+    #[cfg(feature = "watch")]
+    watch: Option<Watch>,
+    #[cfg(feature = "gamepad")]
+    gamepads: Option<Gamepads>,
 }
 
 impl Game {
-    pub fn new(cpu: CPU) -> Game {
-        Game { cpu }
+    pub fn new(cpu: Cpu, keymap: KeyMap) -> Game {
+        let autofire = keymap.autofire();
+        Game {
+            cpu,
+            settings: Settings { keymap, ..Settings::default() },
+            rewind: Rewind::default(),
+            clock: Clock::default(),
+            paused: false,
+            step_frame: false,
+            rewinding: false,
+            macros: MacroPlayer::new(),
+            autofire,
+            message: None,
+            overlay: Overlay::new(),
+            crash_report: None,
+            high_score: None,
+            cheats: Cheats::new(),
+            audio: AudioSettings::default(),
+            library: None,
+            browsing: false,
+            booted: false,
+            boot: None,
+            #[cfg(feature = "image")]
+            recording: Recording::new(RECORDING_SECONDS),
+            #[cfg(feature = "watch")]
+            watch: None,
+            #[cfg(feature = "gamepad")]
+            gamepads: None,
+        }
     }
 
-    pub fn run(&mut self) {
-        let opengl = OpenGL::V3_2;
+    /// Sets how much of its brightness a pixel keeps each frame after it's
+    /// turned off, to hide flicker; 0 turns pixels straight off
+    pub fn persistence(&mut self, persistence: f32) {
+        self.settings.phosphor = Phosphor::new(persistence);
+    }
+
+    /// Sets the colours lit and unlit pixels are drawn in
+    pub fn palette(&mut self, palette: Palette) {
+        self.settings.palette = palette;
+    }
+
+    /// Writes a [`CrashReport`] to `path` if an instruction fails, with the
+    /// instructions from `trace`, which should be watching the CPU
+    pub fn crash_reports(&mut self, path: PathBuf, trace: Arc<Mutex<Trace>>) {
+        self.crash_report = Some((path, trace));
+    }
+
+    /// Follows the score of `rom`, if a high score plugin knows the game,
+    /// keeping the best in `path`
+    pub fn high_score(&mut self, rom: &[u8], path: PathBuf) {
+        self.high_score = high_score::find(rom).and_then(|plugin| match ScoreKeeper::open(plugin, &path) {
+            Ok(keeper) => Some(keeper),
+            Err(err) => {
+                eprintln!("Couldn't read the high score from {}: {}", path.display(), err);
+                None
+            }
+        });
+        if let Some(keeper) = &self.high_score {
+            let message = format!("{} high score: {}", keeper.plugin().title(), keeper.best());
+            self.message = Some((message, MESSAGE_SECONDS));
+        }
+    }
+
+    /// Applies `cheats` at the start of every frame, until F3 switches
+    /// them off
+    pub fn cheats(&mut self, cheats: Cheats) {
+        self.cheats = cheats;
+    }
+
+    /// Starts on a list of the ROMs in `library`, booting whichever one is
+    /// picked on the CPU and timing `boot` makes for it; [`Hotkey::Library`]
+    /// goes back to the list
+    pub fn library<B>(&mut self, library: Library, boot: B)
+    where
+        B: Fn(&Path, &[u8]) -> (Cpu, Option<TimingModel>) + 'static,
+    {
+        self.library = Some(library);
+        self.browsing = true;
+        self.boot = Some(Box::new(boot));
+    }
+
+    /// Sets how long instructions take
+    pub fn timing(&mut self, model: TimingModel) {
+        self.clock = Clock::with_model(model);
+    }
+
+    /// Sets how big a CHIP-8 pixel starts off, and how the screen fits the
+    /// window once it's resized
+    pub fn scaling(&mut self, scale: u32, mode: ScaleMode) {
+        self.settings.scale = scale.max(1);
+        self.settings.scale_mode = mode;
+    }
+
+    /// Sets the buzzer's frequency, volume and mute, which F4 toggles
+    pub fn audio_settings(&mut self, settings: AudioSettings) {
+        self.audio = settings;
+    }
 
-        let mut window: Window = WindowSettings::new("CHIP-8", [800, 600])
-            .graphics_api(opengl)
-            .exit_on_esc(true)
-            .build()
-            .unwrap();
+    /// Plays the buzzer through the given sink while the game runs
+    #[cfg_attr(not(feature = "audio-cpal"), allow(dead_code))]
+    pub fn audio<S: AudioSink + 'static>(&mut self, sink: S) {
+        self.settings.tone = Some(Tone::new(Box::new(sink)));
+    }
+
+    /// Reloads the ROM at `path` whenever it changes, keeping the window and
+    /// settings as they are
+    #[cfg(feature = "watch")]
+    pub fn watch(&mut self, path: &Path) -> notify::Result<()> {
+        self.watch = Some(Watch::new(path)?);
+        Ok(())
+    }
+
+    /// Swaps in the ROM at `path`, assembling it first if it's Octo source or
+    /// unpacking it if it's a zip, and says on screen whether it worked
+    fn load_rom(&mut self, path: &Path) {
+        let name = path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+        let rom = if path.extension().is_some_and(|ext| ext == "8o") {
+            fs::read_to_string(path).map_err(|err| err.to_string()).and_then(|source| asm::assemble(&source).map_err(|err| err.to_string()))
+        } else {
+            rom_file::read(path).map_err(|err| err.to_string())
+        };
+        let loaded = match (rom, &self.boot) {
+            (Ok(rom), Some(boot)) => {
+                let (cpu, timing) = boot(path, &rom);
+                self.cpu = cpu;
+                self.clock = timing.map_or_else(Clock::default, Clock::with_model);
+                Ok(rom)
+            }
+            (rom, _) => rom.and_then(|rom| self.cpu.load_program(&rom).map_err(|err| err.to_string()).map(|()| rom)),
+        };
+
+        let message = match loaded {
+            Ok(rom) => {
+                self.rewind.clear();
+                self.paused = false;
+                self.high_score(&rom, path.with_extension("score"));
+                // a broken settings file was already reported when the ROM started
+                let config = RomConfig::for_rom(path).ok().flatten().unwrap_or_default();
+                self.cheats = Cheats::from(config.cheats);
+                format!("Loaded {}", name)
+            }
+            Err(err) => format!("Couldn't load {}: {}", name, err),
+        };
+        info!("{}", message);
+        self.message = Some((message, MESSAGE_SECONDS));
+    }
 
-        let mut screen = [[false; 64]; 32];
+    /// Moves through the library with the arrow keys, booting the picked ROM
+    /// on Enter
+    fn browse(&mut self, pressed: Vec<String>) {
+        let library = match &mut self.library {
+            Some(library) => library,
+            None => return,
+        };
+        for name in pressed {
+            match name.as_str() {
+                "Up" => library.select_previous(),
+                "Down" => library.select_next(),
+                // piston and winit name it differently
+                "Return" | "Enter" => {
+                    if let Some(path) = library.selected().map(|rom| rom.path.clone()) {
+                        self.browsing = false;
+                        self.booted = true;
+                        return self.load_rom(&path);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Goes back to the library once the game has stopped, returning false
+    /// if there isn't one and the window should close
+    fn stopped(&mut self) -> bool {
+        self.browsing = self.library.is_some();
+        self.browsing
+    }
+
+    /// Saves a screenshot or the recording, named after the current time so
+    /// nothing gets overwritten
+    #[cfg(feature = "image")]
+    fn capture(&mut self, hotkey: Hotkey) {
+        let seconds = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|time| time.as_secs())
+            .unwrap_or_default();
+
+        let (name, saved) = if hotkey == Hotkey::Screenshot {
+            let name = format!("chip_8-{}.png", seconds);
+            let saved = capture::save_png(self.cpu.display(), CAPTURE_SCALE, &name);
+            (name, saved)
+        } else {
+            let name = format!("chip_8-{}.gif", seconds);
+            let saved = self.recording.save_gif(CAPTURE_SCALE, &name);
+            (name, saved)
+        };
 
-        let mut app = App {
-            gl: GlGraphics::new(opengl),
+        let message = match saved {
+            Ok(()) => format!("Saved {}", name),
+            Err(err) => format!("Couldn't save {}: {}", name, err),
         };
+        self.message = Some((message, MESSAGE_SECONDS));
+    }
+
+    /// Runs one 1/60s frame: a frame's worth of instructions and a timer
+    /// tick, or a frame of rewinding, which is what rewinding steps through
+    ///
+    /// Returns false once the program has stopped
+    fn frame(&mut self, running: bool) -> bool {
+        if running {
+            self.rewind.record(&self.cpu);
+            for (key, pressed) in self.macros.frame().into_iter().chain(self.autofire.frame()) {
+                self.cpu.set_key(key, pressed);
+            }
+            for (cheat, err) in self.cheats.apply(&mut self.cpu) {
+                self.message = Some((format!("Switched off {}: {}", cheat, err), MESSAGE_SECONDS));
+            }
+
+            match self.clock.run_one_frame(&mut self.cpu) {
+                StopReason::Halted => return false,
+                StopReason::Error(err) => {
+                    self.crashed(err);
+                    return false;
+                }
+                _ => {}
+            }
+
+            let memory = self.cpu.memory();
+            if let Some(best) = self.high_score.as_mut().and_then(|keeper| keeper.update(memory)) {
+                self.new_high_score(best);
+            }
+
+            #[cfg(feature = "image")]
+            self.recording.record(self.cpu.display());
+        } else if self.rewinding && !self.paused && !self.browsing {
+            self.rewind.rewind(&mut self.cpu, 1);
+        }
+        true
+    }
+
+    /// Tells the player they've beaten the high score, saving it
+    fn new_high_score(&mut self, best: u32) {
+        let keeper = match &self.high_score {
+            Some(keeper) => keeper,
+            None => return,
+        };
+        let message = match keeper.save() {
+            Ok(()) => format!("New high score: {}", best),
+            Err(err) => format!("New high score: {}, but it couldn't be saved: {}", best, err),
+        };
+        self.message = Some((message, MESSAGE_SECONDS));
+    }
+
+    /// Tells the player why the program stopped, writing a crash report if
+    /// there's somewhere to
+    fn crashed(&self, err: CpuError) {
+        let (path, trace) = match &self.crash_report {
+            Some(crash_report) => crash_report,
+            None => return eprintln!("{}", err),
+        };
+
+        let report = CrashReport::new(&self.cpu, err, &trace.lock().unwrap());
+        match report.save(path) {
+            Ok(()) => eprintln!("{}; crash report written to {}", err, path.display()),
+            Err(save_err) => eprintln!("{}; couldn't write a crash report to {}: {}", err, path.display(), save_err),
+        }
+    }
 
-        let mut events = Events::new(EventSettings::new());
-        while let Some(e) = events.next(&mut window) {
-            if let None = self.cpu.run(&mut screen) {
-                break
+    /// Handles one of the emulator's own keys
+    fn hotkey(&mut self, hotkey: Hotkey, pressed: bool) {
+        debug!("hotkey {:?} {}", hotkey, if pressed { "pressed" } else { "released" });
+        match (hotkey, pressed) {
+            (Hotkey::Pause, true) => self.paused = !self.paused,
+            (Hotkey::StepFrame, true) => {
+                self.paused = true;
+                self.step_frame = true;
+            }
+            (Hotkey::Reset, true) => {
+                self.cpu.reset();
+                self.rewind.clear();
+                self.cheats.rewrite();
+            }
+            (Hotkey::Rewind, pressed) => self.rewinding = pressed,
+            (Hotkey::Slower, true) => self.clock.set_speed(self.clock.speed() / 2.0),
+            (Hotkey::Faster, true) => self.clock.set_speed(self.clock.speed() * 2.0),
+            (Hotkey::NormalSpeed, true) => self.clock.set_speed(1.0),
+            (Hotkey::Overlay, true) => self.overlay.visible = !self.overlay.visible,
+            (Hotkey::Library, true) if self.library.is_some() && self.booted => self.browsing = !self.browsing,
+            (Hotkey::Cheats, true) if !self.cheats.is_empty() => {
+                let on = !self.cheats.any_enabled();
+                self.cheats.set_all(on);
+                self.message = Some((String::from(if on { "Cheats on" } else { "Cheats off" }), MESSAGE_SECONDS));
             }
-            
-            if let Some(args) = e.render_args() {
-                app.render(&args, &screen);
+            (Hotkey::Mute, true) => {
+                self.audio.muted = !self.audio.muted;
+                self.message = Some((String::from(if self.audio.muted { "Sound off" } else { "Sound on" }), MESSAGE_SECONDS));
             }
-            
+            #[cfg(feature = "image")]
+            (Hotkey::Screenshot | Hotkey::Recording, true) => self.capture(hotkey),
+            _ => {}
         }
     }
-}
\ No newline at end of file
+
+    /// Opens a window and plays the game in it until the window closes or
+    /// the program stops
+    ///
+    /// The window is the pixels one, or the piston one when the
+    /// `frontend-piston` feature asks for it.
+    pub fn run(&mut self) {
+        #[cfg(feature = "gamepad")]
+        match Gamepads::new(&self.settings.keymap) {
+            Ok(gamepads) => self.gamepads = Some(gamepads),
+            Err(err) => eprintln!("Couldn't read controllers: {}", err),
+        }
+        let settings = mem::take(&mut self.settings);
+
+        #[cfg(feature = "frontend-piston")]
+        self.run_in(&mut crate::piston_frontend::PistonFrontend::new(settings));
+
+        #[cfg(all(feature = "frontend-pixels", not(feature = "frontend-piston")))]
+        match crate::pixels_frontend::PixelsFrontend::new(settings) {
+            Ok(mut window) => self.run_in(&mut window),
+            Err(err) => {
+                eprintln!("Couldn't open a window: {}", err);
+                std::process::exit(1);
+            }
+        }
+
+        #[cfg(not(any(feature = "frontend-pixels", feature = "frontend-piston")))]
+        {
+            drop(settings);
+            eprintln!("Can't open a window: chip_8 was built without `frontend-pixels` or `frontend-piston`");
+            std::process::exit(1);
+        }
+    }
+
+    fn run_in<W: GameWindow>(&mut self, window: &mut W) {
+        let mut last_update = window.now();
+        loop {
+            let input = window.poll_input();
+            if input.quit {
+                return;
+            }
+            for path in window.take_dropped() {
+                self.load_rom(&path);
+            }
+
+            let pressed = window.take_pressed();
+            if self.browsing {
+                self.browse(pressed);
+            }
+
+            let running = !self.paused && !self.rewinding && !self.browsing;
+            for (hotkey, pressed) in window.take_hotkeys() {
+                self.hotkey(hotkey, pressed);
+            }
+            #[cfg(feature = "gamepad")]
+            let buttons = self.gamepads.as_mut().map(Gamepads::poll).unwrap_or_default();
+            #[cfg(not(feature = "gamepad"))]
+            let buttons = Vec::new();
+            for (key, pressed) in input.keys.into_iter().chain(buttons) {
+                if let Some((key, pressed)) = self.autofire.key(key, pressed) {
+                    self.cpu.set_key(key, pressed);
+                }
+            }
+            for key_macro in input.macros {
+                self.macros.play(key_macro);
+            }
+
+            #[cfg(feature = "watch")]
+            if let Some(path) = self.watch.as_ref().filter(|watch| watch.changed()).map(|watch| watch.path.clone()) {
+                self.load_rom(&path);
+            }
+
+            // the wall clock decides how many fixed 1/60s frames to run, so
+            // the game runs at the same speed whatever rate the events come
+            // in at
+            let now = window.now();
+            let elapsed = now.duration_since(last_update).as_secs_f64();
+            last_update = now;
+
+            for _ in 0..self.clock.frames_due(elapsed) {
+                if !self.frame(running) {
+                    if !self.stopped() {
+                        return;
+                    }
+                    break;
+                }
+            }
+            // stepped frames are on top of the wall clock's, which are
+            // skipped while paused
+            if mem::take(&mut self.step_frame) && !self.browsing && !self.frame(true) && !self.stopped() {
+                return;
+            }
+
+            window.configure_audio(self.audio);
+            window.audio_pattern(self.cpu.audio_pattern());
+            window.audio(running && self.cpu.sound_active());
+
+            if let Some((message, seconds)) = self.message.take() {
+                if seconds > elapsed {
+                    self.message = Some((message, seconds - elapsed));
+                }
+            }
+
+            if window.drawing() {
+                self.overlay.frame_rendered(&self.cpu);
+                let message = self.message.as_ref().map(|(message, _)| message.as_str());
+                match self.library.as_ref().filter(|_| self.browsing) {
+                    Some(library) => window.show(Some("Up/Down to pick, Enter to play"), library.lines(LIBRARY_LINES)),
+                    None => window.show(message, self.overlay.lines(&self.cpu)),
+                }
+            }
+            if self.browsing {
+                window.render(&Display::new());
+            } else {
+                window.render(self.cpu.display());
+            }
+        }
+    }
+}
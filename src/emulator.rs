@@ -0,0 +1,350 @@
+//! The whole machine behind one type, for frontends that just want to play
+//! ROMs
+//!
+//! An [`Emulator`] owns the CPU and its clock, and takes care of running
+//! the right number of instructions each frame and ticking the timers, so a
+//! frontend only has to pass keys in and draw and play what comes out.
+//!
+//! # Example
+//!
+//! ```
+//! use chip_8::Emulator;
+//!
+//! let mut emulator = Emulator::default();
+//! // LD V0, 30; LD ST, V0; then loop forever
+//! emulator.load_rom(&[0x60, 0x1E, 0xF0, 0x18, 0x12, 0x04]).unwrap();
+//!
+//! // once per 60Hz frame
+//! emulator.key_down(0x5);
+//! emulator.run_frame();
+//! let _screen = emulator.frame();
+//! assert!(emulator.audio_state().playing);
+//! emulator.key_up(0x5);
+//! ```
+
+use std::error::Error;
+use std::path::Path;
+
+use crate::audio::{AudioSettings, Pattern};
+use crate::rom_config::RomConfig;
+use crate::rom_file;
+use crate::timing::{Clock, TimingModel, TIMER_HZ};
+use crate::{rom_db, Byte, Cpu, CpuBuilder, Display, OnZeroOpcode, RomError, StopReason};
+
+/// What the buzzer should be doing
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct AudioState {
+    /// The sound timer is running, so the tone should be playing
+    pub playing: bool,
+    /// The XO-CHIP pattern to play instead of the tone, if the program
+    /// has loaded one
+    pub pattern: Option<Pattern>,
+    /// How the player wants it to sound
+    pub settings: AudioSettings,
+}
+
+/// A CPU and clock, run a frame at a time
+///
+/// Clones are detached the way [`Cpu`] clones are.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Emulator {
+    cpu: Cpu,
+    clock: Clock,
+    // the program terminated, so frames do nothing until a reset
+    halted: bool,
+    audio: AudioSettings,
+}
+
+impl Default for Emulator {
+    /// A CPU with the builder's defaults, apart from failing on opcode 0000,
+    /// which a game only reaches by running into zeroed memory
+    fn default() -> Self {
+        Self::new(CpuBuilder::new().on_zero_opcode(OnZeroOpcode::Error).build())
+    }
+}
+
+impl Emulator {
+    /// Wraps an already built CPU, running at the default 600 instructions
+    /// a second
+    pub fn new(cpu: Cpu) -> Emulator {
+        Emulator { cpu, clock: Clock::default(), halted: false, audio: AudioSettings::default() }
+    }
+
+    /// Loads a ROM at the program start and starts it from scratch
+    ///
+    /// ROMs in the [`rom_db`] get the quirks and speed they were written
+    /// for; anything else keeps the CPU's quirks and the current speed. A
+    /// [`TimingModel::Vip`] or [`TimingModel::Hp48`] set with
+    /// [`set_timing`](Emulator::set_timing) stays, whatever speed the
+    /// database has.
+    pub fn load_rom(&mut self, rom: &[u8]) -> Result<(), RomError> {
+        self.cpu.load_program(rom)?;
+        self.halted = false;
+
+        if let Some(entry) = rom_db::lookup(rom) {
+            self.cpu.quirks = entry.quirks;
+            if let (Some(tickrate), TimingModel::Fixed(_)) = (entry.tickrate, self.clock.model()) {
+                self.clock = Clock::new((tickrate as f64 * TIMER_HZ) as u32);
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads a ROM file, or the ROM in a zip, and loads it like [`load_rom`](Emulator::load_rom),
+    /// then applies the [`RomConfig`] beside it, if there is one
+    ///
+    /// Returns the settings, so the frontend can pick up the keymap and
+    /// palette.
+    pub fn load_rom_file<P: AsRef<Path>>(&mut self, path: P) -> Result<Option<RomConfig>, Box<dyn Error>> {
+        let path = path.as_ref();
+        self.load_rom(&rom_file::read(path)?)?;
+
+        let config = RomConfig::for_rom(path)?;
+        if let Some(config) = &config {
+            self.configure(config);
+        }
+        Ok(config)
+    }
+
+    /// Uses the quirks, timing and audio settings `config` sets, leaving
+    /// the rest as it is
+    pub fn configure(&mut self, config: &RomConfig) {
+        if let Some(quirks) = config.quirks {
+            self.cpu.quirks = quirks;
+        }
+        if let Some(model) = config.timing {
+            self.set_timing(model);
+        }
+        self.audio = config.audio_over(self.audio);
+    }
+
+    /// Presses one of the 16 keys, 0 to F
+    ///
+    /// # Panics
+    ///
+    /// Panics if `key` is over 0xF
+    pub fn key_down(&mut self, key: Byte) {
+        self.cpu.set_key(key, true);
+    }
+
+    /// Releases one of the 16 keys, 0 to F
+    ///
+    /// # Panics
+    ///
+    /// Panics if `key` is over 0xF
+    pub fn key_up(&mut self, key: Byte) {
+        self.cpu.set_key(key, false);
+    }
+
+    /// Runs one 60th of a second: a frame's worth of instructions, then a
+    /// timer tick
+    ///
+    /// Returns why the instructions stopped. Once the program terminates,
+    /// frames do nothing and keep returning [`StopReason::Halted`] until
+    /// the next [`reset`](Emulator::reset) or [`load_rom`](Emulator::load_rom).
+    pub fn run_frame(&mut self) -> StopReason {
+        if self.halted {
+            return StopReason::Halted;
+        }
+
+        let reason = self.clock.run_one_frame(&mut self.cpu);
+        if reason == StopReason::Halted {
+            self.halted = true;
+        }
+        reason
+    }
+
+    /// The screen as the program has drawn it so far
+    pub fn frame(&self) -> &Display {
+        self.cpu.display()
+    }
+
+    /// What the buzzer should be doing after the last frame
+    pub fn audio_state(&self) -> AudioState {
+        AudioState { playing: self.cpu.sound_active(), pattern: self.cpu.audio_pattern(), settings: self.audio }
+    }
+
+    /// How the buzzer is set to sound
+    pub fn audio_settings(&self) -> AudioSettings {
+        self.audio
+    }
+
+    /// Sets the tone's frequency, volume and mute all at once
+    pub fn set_audio_settings(&mut self, settings: AudioSettings) {
+        self.audio = AudioSettings { volume: settings.volume.min(100), ..settings };
+    }
+
+    /// Sets the volume, from 0 to 100, turning anything louder down to 100
+    ///
+    /// # Examples
+    /// ```
+    /// use chip_8::Emulator;
+    ///
+    /// let mut emulator = Emulator::default();
+    /// emulator.set_volume(250);
+    /// emulator.set_muted(true);
+    /// let settings = emulator.audio_state().settings;
+    /// assert_eq!((settings.volume, settings.muted), (100, true));
+    /// ```
+    pub fn set_volume(&mut self, volume: u8) {
+        self.audio.volume = volume.min(100);
+    }
+
+    /// Mutes or unmutes the buzzer, keeping its volume
+    pub fn set_muted(&mut self, muted: bool) {
+        self.audio.muted = muted;
+    }
+
+    /// Sets the pitch of the tone in Hz
+    pub fn set_tone_frequency(&mut self, frequency: u32) {
+        self.audio.frequency = frequency;
+    }
+
+    /// Restarts the current ROM
+    pub fn reset(&mut self) {
+        self.cpu.reset();
+        self.halted = false;
+    }
+
+    /// How many frames to run for `seconds` of real time passing, carrying
+    /// any remainder over to the next call
+    pub fn frames_due(&mut self, seconds: f64) -> usize {
+        self.clock.frames_due(seconds)
+    }
+
+    /// Sets how long instructions take, keeping the speed
+    pub fn set_timing(&mut self, model: TimingModel) {
+        let speed = self.clock.speed();
+        self.clock = Clock::with_model(model);
+        self.clock.set_speed(speed);
+    }
+
+    /// Sets how much faster than real time to run, between 0.25 and 8
+    pub fn set_speed(&mut self, speed: f32) {
+        self.clock.set_speed(speed);
+    }
+
+    /// Goes back to `earlier`, a clone of this emulator, keeping whatever
+    /// is attached to the CPU
+    pub(crate) fn roll_back(&mut self, earlier: &Emulator) {
+        self.cpu.roll_back(&earlier.cpu);
+        self.clock = earlier.clock.clone();
+        self.halted = earlier.halted;
+    }
+
+    /// The CPU, for anything the emulator doesn't cover
+    pub fn cpu(&self) -> &Cpu {
+        &self.cpu
+    }
+
+    /// The CPU, for anything the emulator doesn't cover
+    pub fn cpu_mut(&mut self) -> &mut Cpu {
+        &mut self.cpu
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CpuError, Quirks};
+    use std::fs;
+
+    #[test]
+    fn run_frame_runs_ten_instructions_and_ticks_timers() {
+        let mut emulator = Emulator::default();
+        // LD V0, 5; LD DT, V0; then ADD V1, 1 forever
+        emulator.load_rom(&[0x60, 0x05, 0xF0, 0x15, 0x71, 0x01, 0x12, 0x04]).unwrap();
+
+        assert_eq!(emulator.run_frame(), StopReason::BudgetSpent);
+        assert_eq!(emulator.cpu().state().delay, 4);
+        // eight instructions of the ten were the loop, half of them adds
+        assert_eq!(emulator.cpu().registers(1), 4);
+    }
+
+    #[test]
+    fn run_frame_stays_halted_until_reset() {
+        let mut emulator = Emulator::new(CpuBuilder::new().build());
+        // LD V0, 1; terminate; LD V0, 2
+        emulator.load_rom(&[0x60, 0x01, 0x00, 0x00, 0x60, 0x02]).unwrap();
+
+        assert_eq!(emulator.run_frame(), StopReason::Halted);
+        assert_eq!(emulator.run_frame(), StopReason::Halted);
+        assert_eq!(emulator.cpu().registers(0), 1);
+
+        emulator.reset();
+        assert_eq!(emulator.run_frame(), StopReason::Halted);
+        assert_eq!(emulator.cpu().state().pc, 0x204);
+    }
+
+    #[test]
+    fn default_fails_on_zeroed_memory() {
+        let mut emulator = Emulator::default();
+        // LD V0, 1, then nothing
+        emulator.load_rom(&[0x60, 0x01]).unwrap();
+
+        assert_eq!(emulator.run_frame(), StopReason::Error(CpuError::ZeroOpcode(0x202)));
+        assert_eq!(emulator.cpu().state().pc, 0x202);
+    }
+
+    #[test]
+    fn keys_finish_waiting_for_a_key() {
+        let mut emulator = Emulator::default();
+        // LD V3, K; LD ST, V3
+        emulator.load_rom(&[0xF3, 0x0A, 0xF3, 0x18]).unwrap();
+
+        assert_eq!(emulator.run_frame(), StopReason::WaitingForKey);
+        assert_eq!(emulator.audio_state(), AudioState { playing: false, ..AudioState::default() });
+
+        emulator.key_down(0x9);
+        emulator.key_up(0x9);
+        emulator.run_frame();
+        assert_eq!(emulator.cpu().registers(3), 0x9);
+        assert!(emulator.audio_state().playing);
+    }
+
+    #[test]
+    fn timers_run_down_while_waiting_for_a_key() {
+        let mut emulator = Emulator::default();
+        // LD V0, 3; LD DT, V0; LD ST, V0; LD V1, K; LD V2, DT
+        emulator.load_rom(&[0x60, 0x03, 0xF0, 0x15, 0xF0, 0x18, 0xF1, 0x0A, 0xF2, 0x07]).unwrap();
+
+        assert_eq!(emulator.run_frame(), StopReason::WaitingForKey);
+        assert!(emulator.audio_state().playing);
+        for _ in 0..2 {
+            assert_eq!(emulator.run_frame(), StopReason::WaitingForKey);
+        }
+        assert_eq!(emulator.audio_state(), AudioState { playing: false, ..AudioState::default() });
+        assert_eq!(emulator.cpu().cycles(), 4);
+
+        emulator.key_down(0x1);
+        emulator.key_up(0x1);
+        emulator.run_frame();
+        assert_eq!((emulator.cpu().registers(1), emulator.cpu().registers(2)), (0x1, 0));
+    }
+
+    #[test]
+    fn load_rom_keeps_quirks_for_unknown_roms() {
+        let quirks = Quirks { clip_sprites: true, ..Quirks::default() };
+        let mut emulator = Emulator::new(CpuBuilder::new().quirks(quirks).build());
+        emulator.load_rom(&[0x12, 0x00]).unwrap();
+
+        assert_eq!(emulator.cpu().quirks, quirks);
+        assert_eq!(emulator.load_rom(&[]), Err(RomError::Empty));
+    }
+
+    #[test]
+    fn load_rom_file_applies_the_sidecar() {
+        let rom = std::env::temp_dir().join(format!("chip_8_sidecar_{}.ch8", std::process::id()));
+        fs::write(&rom, [0x12, 0x00]).unwrap();
+        fs::write(RomConfig::path_for(&rom), "variant = \"chip48\"\nclock = \"hp48\"\nkeymap = \"arrows\"").unwrap();
+
+        let mut emulator = Emulator::default();
+        let config = emulator.load_rom_file(&rom).unwrap().unwrap();
+        fs::remove_file(RomConfig::path_for(&rom)).unwrap();
+        fs::remove_file(&rom).unwrap();
+
+        assert_eq!(config.keymap.as_deref(), Some("arrows"));
+        assert!(emulator.cpu().quirks.jump_uses_vx);
+        assert_eq!(emulator.clock.model(), TimingModel::Hp48);
+    }
+}
@@ -0,0 +1,113 @@
+//! What can go wrong while running a program
+
+use std::error::Error;
+use std::fmt;
+
+use crate::OpCode;
+
+/// Why the CPU couldn't run an instruction, or refused a write to memory
+///
+/// The program counter is left on the instruction that failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CpuError {
+    /// A call with every stack entry already in use, of however many the
+    /// CPU was built with
+    StackOverflow(usize),
+    /// A return with nothing on the stack
+    StackUnderflow,
+    /// An opcode that isn't part of the instruction set
+    UnknownOpcode { opcode: OpCode, address: usize },
+    /// Opcode 0000, with [`OnZeroOpcode::Error`](crate::OnZeroOpcode::Error)
+    ZeroOpcode(usize),
+    /// The program counter ran off the end of memory
+    ProgramCounterOutOfBounds(usize),
+    /// An instruction tried to read or write past the end of memory
+    MemoryOutOfBounds(usize),
+    /// FX75 couldn't save the RPL flags
+    FlagStorage,
+    /// A write from outside the program to the interpreter's memory below
+    /// the program start
+    ProtectedMemory(usize),
+}
+
+impl fmt::Display for CpuError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CpuError::StackOverflow(depth) => write!(f, "Stack overflow, all {} entries in use", depth),
+            CpuError::StackUnderflow => write!(f, "Stack underflow"),
+            CpuError::UnknownOpcode { opcode, address } => {
+                write!(f, "Unknown opcode {:04X} at 0x{:03X}", opcode, address)
+            }
+            CpuError::ZeroOpcode(address) => {
+                write!(f, "Ran into 0000 at 0x{:03X}, which is zeroed memory rather than an instruction", address)
+            }
+            CpuError::ProgramCounterOutOfBounds(pc) => {
+                write!(f, "Program counter 0x{:03X} is past the end of memory", pc)
+            }
+            CpuError::MemoryOutOfBounds(addr) => {
+                write!(f, "Memory access at 0x{:03X} is past the end of memory", addr)
+            }
+            CpuError::FlagStorage => write!(f, "Couldn't save the RPL flags"),
+            CpuError::ProtectedMemory(addr) => {
+                write!(f, "Memory at 0x{:03X} belongs to the interpreter", addr)
+            }
+        }
+    }
+}
+
+impl Error for CpuError {}
+
+/// Why a ROM couldn't be loaded
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RomError {
+    /// The ROM has no bytes in it
+    Empty,
+    /// The ROM doesn't fit between the program start and the end of memory
+    TooLarge { size: usize, max: usize },
+}
+
+impl fmt::Display for RomError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RomError::Empty => write!(f, "ROM is empty"),
+            RomError::TooLarge { size, max } => {
+                write!(f, "ROM is {} bytes, but only {} fit in memory", size, max)
+            }
+        }
+    }
+}
+
+impl Error for RomError {}
+
+/// Why bytes couldn't be read as a save state
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SaveStateError {
+    /// The bytes don't start with a save state's magic
+    NotASaveState,
+    /// A version of the layout newer than this crate knows, or one that
+    /// never existed
+    UnsupportedVersion(u8),
+    /// The bytes end partway through
+    Truncated,
+    /// One of the sections every save state has isn't there, by its tag
+    MissingSection([u8; 4]),
+    /// A section holds something no CPU could be in, by its tag
+    BadSection([u8; 4]),
+}
+
+impl fmt::Display for SaveStateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = |tag: &[u8; 4]| String::from_utf8_lossy(tag).trim_end().to_string();
+        match self {
+            SaveStateError::NotASaveState => write!(f, "Not a save state"),
+            SaveStateError::UnsupportedVersion(version) => {
+                write!(f, "Save state version {} isn't supported", version)
+            }
+            SaveStateError::Truncated => write!(f, "Save state is cut short"),
+            SaveStateError::MissingSection(tag) => write!(f, "Save state has no {} section", name(tag)),
+            SaveStateError::BadSection(tag) => write!(f, "Save state's {} section is corrupt", name(tag)),
+        }
+    }
+}
+
+impl Error for SaveStateError {}
@@ -0,0 +1,124 @@
+//! Storage for the SUPER-CHIP RPL user flags
+//!
+//! On the HP-48, FX75 and FX85 saved and loaded up to eight registers to
+//! calculator flags that outlived the program, which games used for things
+//! like high scores. A [`FlagStorage`] keeps them somewhere: [`MemoryFlags`]
+//! lasts as long as the CPU (resets included), and [`FileFlags`] keeps them
+//! in a file between sessions.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use chip_8::flags::FileFlags;
+//! use chip_8::CpuBuilder;
+//!
+//! let mut cpu = CpuBuilder::new().build();
+//! cpu.set_flag_storage(FileFlags::open("game.flags").unwrap());
+//! ```
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::{Byte, Cpu};
+
+/// How many flags there are, so FX75 and FX85 go up to V7 at most
+pub const FLAG_COUNT: usize = 8;
+
+/// Somewhere to keep the RPL flags, `Send` so the CPU can move between threads
+pub trait FlagStorage: Send {
+    /// The flags as they were last saved, all zero if they never have been
+    fn load(&self) -> [Byte; FLAG_COUNT];
+
+    /// Replaces the saved flags
+    fn save(&mut self, flags: [Byte; FLAG_COUNT]) -> io::Result<()>;
+}
+
+/// Flags that only last as long as the CPU, which is the default
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MemoryFlags([Byte; FLAG_COUNT]);
+
+impl FlagStorage for MemoryFlags {
+    fn load(&self) -> [Byte; FLAG_COUNT] {
+        self.0
+    }
+
+    fn save(&mut self, flags: [Byte; FLAG_COUNT]) -> io::Result<()> {
+        self.0 = flags;
+        Ok(())
+    }
+}
+
+/// Flags starting out as `flags`, as if they'd already been saved
+impl From<[Byte; FLAG_COUNT]> for MemoryFlags {
+    fn from(flags: [Byte; FLAG_COUNT]) -> Self {
+        MemoryFlags(flags)
+    }
+}
+
+/// Flags kept in a file, so they survive between sessions
+///
+/// The file holds the eight flags as raw bytes and is rewritten on every save.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileFlags {
+    path: PathBuf,
+    flags: [Byte; FLAG_COUNT],
+}
+
+impl FileFlags {
+    /// Reads the flags from `path`, starting from all zero if the file
+    /// doesn't exist yet
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<FileFlags> {
+        let path = path.as_ref().to_path_buf();
+        let mut flags = [0; FLAG_COUNT];
+
+        match fs::read(&path) {
+            Ok(bytes) => {
+                let len = bytes.len().min(FLAG_COUNT);
+                flags[..len].copy_from_slice(&bytes[..len]);
+            }
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+            Err(err) => return Err(err),
+        }
+
+        Ok(FileFlags { path, flags })
+    }
+}
+
+impl FlagStorage for FileFlags {
+    fn load(&self) -> [Byte; FLAG_COUNT] {
+        self.flags
+    }
+
+    fn save(&mut self, flags: [Byte; FLAG_COUNT]) -> io::Result<()> {
+        fs::write(&self.path, flags)?;
+        self.flags = flags;
+        Ok(())
+    }
+}
+
+impl Cpu {
+    /// Keeps the RPL flags in `storage` from now on, instead of in memory
+    pub fn set_flag_storage(&mut self, storage: impl FlagStorage + 'static) {
+        self.flags = Box::new(storage);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn file_flags_survive_reopening() {
+        let path = std::env::temp_dir().join(format!("chip_8_flags_test_{}", std::process::id()));
+        let _ = fs::remove_file(&path);
+
+        let mut flags = FileFlags::open(&path).unwrap();
+        assert_eq!(flags.load(), [0; FLAG_COUNT]);
+        flags.save([1, 2, 3, 4, 5, 6, 7, 8]).unwrap();
+
+        let reopened = FileFlags::open(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(reopened.load(), [1, 2, 3, 4, 5, 6, 7, 8]);
+    }
+}
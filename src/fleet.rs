@@ -0,0 +1,153 @@
+//! Running batches of ROMs headless across threads, for regression testing
+//!
+//! Each [`Job`] is a ROM and how many instructions to give it. [`run`]
+//! spreads the jobs over every core and runs each to its limit with no
+//! keys pressed and a fixed random seed, so the same ROM on the same
+//! emulator always ends in the same state. Comparing the [`Outcome`]
+//! hashes from before and after a change shows which ROMs it affected.
+//!
+//! # Example
+//!
+//! ```
+//! use chip_8::fleet::{self, Job};
+//! use chip_8::StopReason;
+//!
+//! let jobs = [
+//!     Job::new("sierpinski", include_bytes!("../roms/sierpinski.ch8").to_vec(), 10_000),
+//!     // LD V0, 1; terminate
+//!     Job::new("tiny", vec![0x60, 0x01, 0x00, 0x00], 10_000),
+//! ];
+//! let outcomes = fleet::run(&jobs);
+//!
+//! assert_eq!(outcomes[1].as_ref().unwrap().stop, StopReason::Halted);
+//! assert_eq!(outcomes, fleet::run(&jobs));
+//! ```
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use rayon::prelude::*;
+
+use crate::{CpuBuilder, Quirks, RomError, StopReason, INSTRUCTIONS_PER_TICK};
+
+/// The seed every job's random numbers come from, unless it says otherwise
+pub const SEED: u64 = 0xC8;
+
+/// A ROM to run and how long to run it for
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Job {
+    /// What to call the ROM in the outcome, like its file name
+    pub name: String,
+    pub rom: Vec<u8>,
+    /// How many instructions to run at most
+    pub cycles: u64,
+    /// The quirks to run with, or `None` to look the ROM up in the
+    /// [`rom_db`](crate::rom_db)
+    pub quirks: Option<Quirks>,
+    /// Where CXNN's random numbers start
+    pub seed: u64,
+}
+
+impl Job {
+    /// A job with the ROM database's quirks and the default [`SEED`]
+    pub fn new(name: impl Into<String>, rom: Vec<u8>, cycles: u64) -> Job {
+        Job { name: name.into(), rom, cycles, quirks: None, seed: SEED }
+    }
+
+    /// A job for every `.ch8` file in `dir`, sorted by file name
+    pub fn from_dir<P: AsRef<Path>>(dir: P, cycles: u64) -> io::Result<Vec<Job>> {
+        let mut jobs = Vec::new();
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("ch8")) {
+                let name = path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+                jobs.push(Job::new(name, fs::read(&path)?, cycles));
+            }
+        }
+        jobs.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(jobs)
+    }
+
+    /// Runs the job on this thread
+    ///
+    /// The timers tick every [`INSTRUCTIONS_PER_TICK`] instructions, as they
+    /// would at the default speed. Running stops at the cycle limit, when
+    /// the program terminates or fails, or when it waits for a key, since
+    /// nobody is there to press one.
+    pub fn run(&self) -> Result<Outcome, RomError> {
+        let mut builder = CpuBuilder::new().auto_configure(&self.rom).seed(self.seed);
+        if let Some(quirks) = self.quirks {
+            builder = builder.quirks(quirks);
+        }
+        let mut cpu = builder.build();
+        cpu.load_program(&self.rom)?;
+
+        let mut stop = StopReason::BudgetSpent;
+        while cpu.cycles() < self.cycles {
+            let budget = (self.cycles - cpu.cycles()).min(INSTRUCTIONS_PER_TICK as u64);
+            stop = cpu.run_until_blocked(budget as usize);
+            if stop == StopReason::WaitingForVBlank {
+                // the display wait just ends the frame early
+                stop = StopReason::BudgetSpent;
+            } else if stop != StopReason::BudgetSpent {
+                break;
+            }
+            cpu.tick_timers();
+        }
+
+        Ok(Outcome { name: self.name.clone(), cycles: cpu.cycles(), stop, hash: cpu.state_hash() })
+    }
+}
+
+/// How a job ended
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Outcome {
+    /// The job's name
+    pub name: String,
+    /// How many instructions ran
+    pub cycles: u64,
+    /// Why it stopped; [`StopReason::BudgetSpent`] if it reached its limit
+    pub stop: StopReason,
+    /// The final [`state_hash`](crate::Cpu::state_hash)
+    pub hash: u64,
+}
+
+/// Runs every job in parallel, returning their outcomes in the same order
+pub fn run(jobs: &[Job]) -> Vec<Result<Outcome, RomError>> {
+    jobs.par_iter().map(Job::run).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn jobs_stop_at_their_limit_or_when_blocked() {
+        let jobs = [
+            // JP 0x200
+            Job::new("loop", vec![0x12, 0x00], 25),
+            // LD V0, 1; LD V1, K
+            Job::new("waits", vec![0x60, 0x01, 0xF1, 0x0A], 100),
+            Job::new("empty", vec![], 100),
+        ];
+        let outcomes = run(&jobs);
+
+        let looped = outcomes[0].as_ref().unwrap();
+        assert_eq!((looped.name.as_str(), looped.cycles, looped.stop), ("loop", 25, StopReason::BudgetSpent));
+        let waited = outcomes[1].as_ref().unwrap();
+        assert_eq!((waited.cycles, waited.stop), (2, StopReason::WaitingForKey));
+        assert_eq!(outcomes[2], Err(RomError::Empty));
+    }
+
+    #[test]
+    fn hashes_repeat_until_the_seed_changes() {
+        // RND V0, 0xFF; JP 0x200
+        let job = Job::new("random", vec![0xC0, 0xFF, 0x12, 0x00], 3);
+        let hash = job.run().unwrap().hash;
+        assert_eq!(job.run().unwrap().hash, hash);
+
+        let reseeded = Job { seed: 1, ..job.clone() };
+        assert_ne!(reseeded.run().unwrap().hash, hash);
+    }
+}
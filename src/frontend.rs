@@ -0,0 +1,308 @@
+//! The boundary between the emulator and whatever shows it on screen
+//!
+//! A [`Frontend`] only has to draw frames, report keys and switch the
+//! buzzer on and off; a [`Runner`] does everything else, turning wall clock
+//! time into [`Emulator`] frames and passing the keys in. Frontends that
+//! own their event loop hand themselves to [`run`]; ones living inside a
+//! host's loop, like winit's, call [`Runner::update`] once per iteration.
+//!
+//! Everything here is public and stable, so renderers can live outside
+//! this crate.
+//!
+//! # Example
+//!
+//! ```
+//! use std::time::{Duration, Instant};
+//!
+//! use chip_8::frontend::{self, Exit, Frontend, InputEvents};
+//! use chip_8::{CpuBuilder, Emulator, FrameBuffer};
+//!
+//! /// Counts lit pixels instead of drawing, with a clock that jumps a
+//! /// frame every poll
+//! struct Headless {
+//!     time: Instant,
+//!     lit: usize,
+//! }
+//!
+//! impl Frontend for Headless {
+//!     fn render(&mut self, fb: &FrameBuffer) {
+//!         self.lit = fb.pixels().iter().filter(|&&lit| lit).count();
+//!     }
+//!
+//!     fn poll_input(&mut self) -> InputEvents {
+//!         self.time += Duration::from_secs_f64(1.0 / 60.0);
+//!         InputEvents::default()
+//!     }
+//!
+//!     fn audio(&mut self, _on: bool) {}
+//!
+//!     fn now(&self) -> Instant {
+//!         self.time
+//!     }
+//! }
+//!
+//! // a CPU straight from the builder, which halts on 0000
+//! let mut emulator = Emulator::new(CpuBuilder::new().build());
+//! // LD I, 0 (the font's 0); DRW V0, V0, 5; terminate
+//! emulator.load_rom(&[0xA0, 0x00, 0xD0, 0x05, 0x00, 0x00]).unwrap();
+//!
+//! let mut headless = Headless { time: Instant::now(), lit: 0 };
+//! assert_eq!(frontend::run(&mut headless, &mut emulator), Exit::Halted);
+//! assert_eq!(headless.lit, 14);
+//! ```
+
+use std::time::Instant;
+
+use crate::audio::{AudioSettings, Pattern};
+use crate::keymap::{KeyMacro, MacroPlayer};
+use crate::{Autofire, Byte, CpuError, Emulator, FrameBuffer, StopReason};
+
+/// What the player did since the last [`Frontend::poll_input`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct InputEvents {
+    /// CHIP-8 keys, 0 to F, pressed (`true`) or released (`false`), in the
+    /// order it happened
+    pub keys: Vec<(Byte, bool)>,
+    /// Macros the player set off, to start playing from the next frame
+    pub macros: Vec<KeyMacro>,
+    /// The player closed the window or otherwise asked to stop
+    pub quit: bool,
+}
+
+/// Something that can show the emulator and take the player's keys
+pub trait Frontend {
+    /// Shows a frame, called after every update whether or not the screen
+    /// changed
+    fn render(&mut self, fb: &FrameBuffer);
+
+    /// The keys pressed and released since the last poll
+    ///
+    /// [`run`] calls this in a loop, so frontends driving their own events
+    /// should block here until an event comes in or it's time for the next
+    /// frame, as window event loops usually do.
+    fn poll_input(&mut self) -> InputEvents;
+
+    /// Starts or stops the buzzer, called after every update with whether
+    /// it should be sounding
+    fn audio(&mut self, on: bool);
+
+    /// Switches the buzzer to an XO-CHIP audio pattern, or back to its tone
+    /// with `None`, called before every `audio`
+    ///
+    /// Frontends that can only beep can leave this out.
+    fn audio_pattern(&mut self, pattern: Option<Pattern>) {
+        let _ = pattern;
+    }
+
+    /// Changes how the buzzer sounds, called before every `audio`
+    ///
+    /// A muted buzzer is never switched on, so frontends that can't change
+    /// the volume or frequency can leave this out.
+    fn configure_audio(&mut self, settings: AudioSettings) {
+        let _ = settings;
+    }
+
+    /// The time now, which paces the emulator
+    fn now(&self) -> Instant;
+}
+
+/// Why the emulator stopped running on a frontend
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Exit {
+    /// The player asked to stop
+    Quit,
+    /// The program terminated
+    Halted,
+    /// An instruction failed
+    Error(CpuError),
+}
+
+/// Runs an [`Emulator`] on a [`Frontend`] in real time
+#[derive(Debug, Clone, Default)]
+pub struct Runner {
+    // when the last update was, to work out how many frames are due
+    last: Option<Instant>,
+    macros: MacroPlayer,
+    autofire: Autofire,
+}
+
+impl Runner {
+    pub fn new() -> Runner {
+        Runner::default()
+    }
+
+    /// Makes keys fire over and over while held, usually the keymap's
+    /// [`autofire`](crate::KeyMap::autofire)
+    pub fn set_autofire(&mut self, autofire: Autofire) {
+        self.autofire = autofire;
+    }
+
+    /// Passes the keys in, runs whatever frames are due since the last
+    /// update, then renders and updates the buzzer
+    ///
+    /// Returns why the emulator stopped, if it did.
+    pub fn update<F: Frontend + ?Sized>(&mut self, frontend: &mut F, emulator: &mut Emulator) -> Option<Exit> {
+        let input = frontend.poll_input();
+        if input.quit {
+            info!("the player quit");
+            return Some(Exit::Quit);
+        }
+        let keys = input.keys.into_iter().filter_map(|(key, pressed)| self.autofire.key(key, pressed));
+        press_keys(emulator, keys);
+        for key_macro in input.macros {
+            self.macros.play(key_macro);
+        }
+
+        let now = frontend.now();
+        let elapsed = self.last.map_or(0.0, |last| now.saturating_duration_since(last).as_secs_f64());
+        self.last = Some(now);
+
+        let mut exit = None;
+        for _ in 0..emulator.frames_due(elapsed) {
+            press_keys(emulator, self.macros.frame().into_iter().chain(self.autofire.frame()));
+            match emulator.run_frame() {
+                StopReason::Halted => exit = Some(Exit::Halted),
+                StopReason::Error(err) => exit = Some(Exit::Error(err)),
+                _ => continue,
+            }
+            break;
+        }
+
+        frontend.render(emulator.frame());
+        let audio = emulator.audio_state();
+        frontend.configure_audio(audio.settings);
+        frontend.audio_pattern(audio.pattern);
+        frontend.audio(exit.is_none() && audio.playing && !audio.settings.muted);
+        if let Some(exit) = exit {
+            info!("stopped: {:?}", exit);
+        }
+        exit
+    }
+}
+
+/// Presses (`true`) or releases (`false`) keys on the emulator
+fn press_keys<I: IntoIterator<Item = (Byte, bool)>>(emulator: &mut Emulator, keys: I) {
+    for (key, pressed) in keys {
+        if pressed {
+            emulator.key_down(key);
+        } else {
+            emulator.key_up(key);
+        }
+    }
+}
+
+/// Runs `emulator` on `frontend` until the player quits or the program
+/// stops
+pub fn run<F: Frontend + ?Sized>(frontend: &mut F, emulator: &mut Emulator) -> Exit {
+    let mut runner = Runner::new();
+    loop {
+        if let Some(exit) = runner.update(frontend, emulator) {
+            return exit;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    /// Plays back scripted input, a frame of time passing each poll
+    struct Scripted {
+        time: Instant,
+        polls: Vec<InputEvents>,
+        audio: Vec<bool>,
+        renders: usize,
+    }
+
+    impl Frontend for Scripted {
+        fn render(&mut self, _fb: &FrameBuffer) {
+            self.renders += 1;
+        }
+
+        fn poll_input(&mut self) -> InputEvents {
+            self.time += Duration::from_secs_f64(1.0 / 60.0);
+            if self.polls.is_empty() {
+                InputEvents { quit: true, ..InputEvents::default() }
+            } else {
+                self.polls.remove(0)
+            }
+        }
+
+        fn audio(&mut self, on: bool) {
+            self.audio.push(on);
+        }
+
+        fn now(&self) -> Instant {
+            self.time
+        }
+    }
+
+    #[test]
+    fn keys_reach_the_emulator_and_quitting_stops_it() {
+        let mut emulator = Emulator::default();
+        // LD V1, K; LD ST, V1; JP 0x204
+        emulator.load_rom(&[0xF1, 0x0A, 0xF1, 0x18, 0x12, 0x04]).unwrap();
+
+        let press = InputEvents { keys: vec![(0x9, true), (0x9, false)], ..InputEvents::default() };
+        let mut scripted = Scripted {
+            time: Instant::now(),
+            polls: vec![InputEvents::default(), InputEvents::default(), press, InputEvents::default()],
+            audio: Vec::new(),
+            renders: 0,
+        };
+
+        assert_eq!(run(&mut scripted, &mut emulator), Exit::Quit);
+        assert_eq!(emulator.cpu().registers(1), 0x9);
+        assert_eq!(scripted.renders, 4);
+        // the first update only starts the clock
+        assert_eq!(scripted.audio, [false, false, true, true]);
+    }
+
+    #[test]
+    fn macros_play_a_frame_at_a_time() {
+        let mut emulator = Emulator::default();
+        // LD V1, K; LD V2, K; then loop forever
+        emulator.load_rom(&[0xF1, 0x0A, 0xF2, 0x0A, 0x12, 0x04]).unwrap();
+
+        let key_macro = "press(3, 2f); wait(1f); press(C, 1f)".parse().unwrap();
+        // a frame for each step and one more to let go of C
+        let mut polls = vec![InputEvents::default(); 6];
+        polls[0].macros.push(key_macro);
+        let mut scripted = Scripted { time: Instant::now(), polls, audio: Vec::new(), renders: 0 };
+
+        assert_eq!(run(&mut scripted, &mut emulator), Exit::Quit);
+        assert_eq!((emulator.cpu().registers(1), emulator.cpu().registers(2)), (0x3, 0xC));
+        assert!(emulator.cpu().keypad().pressed().next().is_none());
+    }
+
+    #[test]
+    fn autofire_presses_held_keys_over_and_over() {
+        let mut emulator = Emulator::default();
+        // LD V0, K; ADD V1, 1; JP 0x200
+        emulator.load_rom(&[0xF0, 0x0A, 0x71, 0x01, 0x12, 0x00]).unwrap();
+
+        let mut polls = vec![InputEvents::default(); 8];
+        polls[0].keys.push((0x4, true));
+        let mut scripted = Scripted { time: Instant::now(), polls, audio: Vec::new(), renders: 0 };
+        let mut runner = Runner::new();
+        let mut autofire = Autofire::new();
+        autofire.set_rate(0x4, Some(30));
+        runner.set_autofire(autofire);
+
+        while runner.update(&mut scripted, &mut emulator).is_none() {}
+        // released every other frame, over the seven frames run
+        assert_eq!(emulator.cpu().registers(1), 3);
+    }
+
+    #[test]
+    fn failures_end_the_run() {
+        let mut emulator = Emulator::default();
+        // RET with nothing to return to
+        emulator.load_rom(&[0x00, 0xEE]).unwrap();
+        let mut scripted = Scripted { time: Instant::now(), polls: vec![InputEvents::default(); 3], audio: Vec::new(), renders: 0 };
+
+        assert_eq!(run(&mut scripted, &mut emulator), Exit::Error(CpuError::StackUnderflow));
+        assert_eq!(scripted.audio.last(), Some(&false));
+    }
+}
@@ -0,0 +1,79 @@
+//! Game controllers, read through gilrs, pressing keypad keys
+//!
+//! Buttons go by the names gilrs gives them: `DPadUp`, `South` (A on an
+//! Xbox pad, cross on a PlayStation one), `Start` and so on. They're looked
+//! up in the [`KeyMap`] like keyboard keys, so the same keymap file remaps
+//! them:
+//!
+//! ```toml
+//! DPadUp = 0x2
+//! South = 0x5
+//! ```
+//!
+//! Buttons the keymap doesn't bind fall back to [`DEFAULT_BUTTONS`]. Any
+//! number of controllers can be plugged in, all pressing the same keypad.
+
+use std::error::Error;
+
+use gilrs::{EventType, Gilrs};
+
+use crate::{Byte, KeyMap};
+
+/// The d-pad on 2, 4, 6 and 8, which most games move with, the bottom
+/// face button on 5 and the rest of the buttons on keys nothing else uses
+pub const DEFAULT_BUTTONS: [(&str, Byte); 10] = [
+    ("DPadUp", 0x2), ("DPadLeft", 0x4), ("DPadRight", 0x6), ("DPadDown", 0x8),
+    ("South", 0x5), ("East", 0xA), ("West", 0xB), ("North", 0xC),
+    ("Select", 0x0), ("Start", 0xF),
+];
+
+/// The controllers plugged in, and which keys their buttons press
+pub struct Gamepads {
+    gilrs: Gilrs,
+    keymap: KeyMap,
+}
+
+impl Gamepads {
+    /// Starts listening to controllers, with buttons bound as in `keymap`
+    /// or else as in [`DEFAULT_BUTTONS`]
+    ///
+    /// Fails if the platform has no way to read controllers.
+    pub fn new(keymap: &KeyMap) -> Result<Gamepads, Box<dyn Error>> {
+        Ok(Gamepads { gilrs: Gilrs::new()?, keymap: keymap.clone() })
+    }
+
+    /// The keypad key a button presses, if it presses one
+    pub fn key(&self, button: &str) -> Option<Byte> {
+        self.keymap.get(button).or_else(|| {
+            DEFAULT_BUTTONS
+                .iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case(button))
+                .map(|&(_, key)| key)
+        })
+    }
+
+    /// The keys pressed (`true`) and released (`false`) since the last
+    /// poll, in the order it happened
+    pub fn poll(&mut self) -> Vec<(Byte, bool)> {
+        let mut keys = Vec::new();
+        while let Some(event) = self.gilrs.next_event() {
+            let (button, pressed) = match event.event {
+                EventType::ButtonPressed(button, _) => (button, true),
+                EventType::ButtonReleased(button, _) => (button, false),
+                EventType::Connected => {
+                    info!("controller {} connected", event.id);
+                    continue;
+                }
+                EventType::Disconnected => {
+                    info!("controller {} disconnected", event.id);
+                    continue;
+                }
+                _ => continue,
+            };
+            if let Some(key) = self.key(&format!("{:?}", button)) {
+                keys.push((key, pressed));
+            }
+        }
+        keys
+    }
+}
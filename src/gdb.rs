@@ -0,0 +1,381 @@
+//! GDB remote serial protocol server
+//!
+//! Only available with the `gdb` feature. [`serve`] waits for a debugger to
+//! connect over TCP and then lets it read and write registers and memory,
+//! set breakpoints, single-step and continue the program.
+//!
+//! GDB has no built-in CHIP-8 support, so the register layout is described
+//! with a target description: `v0` to `vf`, then `i`, `pc`, `sp`, `dt` and
+//! `st`. Memory addresses are the CHIP-8's own 12-bit addresses.
+//!
+//! ```no_run
+//! use chip_8::CpuBuilder;
+//!
+//! let mut cpu = CpuBuilder::new().build();
+//! // then `target remote localhost:9001` from gdb
+//! chip_8::gdb::serve(&mut cpu, "127.0.0.1:9001").unwrap();
+//! ```
+
+use std::error::Error;
+use std::marker::PhantomData;
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use std::num::NonZeroUsize;
+
+use gdbstub::arch::{Arch, RegId, Registers};
+use gdbstub::common::Signal;
+use gdbstub::conn::ConnectionExt;
+use gdbstub::stub::run_blocking::{self, BlockingEventLoop};
+use gdbstub::stub::{GdbStub, SingleThreadStopReason};
+use gdbstub::target::ext::base::singlethread::{
+    SingleThreadBase, SingleThreadResume, SingleThreadResumeOps, SingleThreadSingleStep,
+    SingleThreadSingleStepOps,
+};
+use gdbstub::target::ext::base::BaseOps;
+use gdbstub::target::ext::breakpoints::{Breakpoints, BreakpointsOps, SwBreakpoint, SwBreakpointOps};
+use gdbstub::target::{Target, TargetResult};
+
+use crate::{Address, CpuError, CpuStatus, Cpu, INSTRUCTIONS_PER_TICK};
+
+/// How many instructions run between checks for an interrupt from gdb
+const INSTRUCTIONS_PER_POLL: usize = 1024;
+
+const TARGET_XML: &str = r#"<?xml version="1.0"?>
+<!DOCTYPE target SYSTEM "gdb-target.dtd">
+<target version="1.0">
+  <feature name="org.chip8.core">
+    <reg name="v0" bitsize="8" type="uint8"/>
+    <reg name="v1" bitsize="8" type="uint8"/>
+    <reg name="v2" bitsize="8" type="uint8"/>
+    <reg name="v3" bitsize="8" type="uint8"/>
+    <reg name="v4" bitsize="8" type="uint8"/>
+    <reg name="v5" bitsize="8" type="uint8"/>
+    <reg name="v6" bitsize="8" type="uint8"/>
+    <reg name="v7" bitsize="8" type="uint8"/>
+    <reg name="v8" bitsize="8" type="uint8"/>
+    <reg name="v9" bitsize="8" type="uint8"/>
+    <reg name="va" bitsize="8" type="uint8"/>
+    <reg name="vb" bitsize="8" type="uint8"/>
+    <reg name="vc" bitsize="8" type="uint8"/>
+    <reg name="vd" bitsize="8" type="uint8"/>
+    <reg name="ve" bitsize="8" type="uint8"/>
+    <reg name="vf" bitsize="8" type="uint8"/>
+    <reg name="i" bitsize="16" type="data_ptr"/>
+    <reg name="pc" bitsize="16" type="code_ptr"/>
+    <reg name="sp" bitsize="8" type="uint8"/>
+    <reg name="dt" bitsize="8" type="uint8"/>
+    <reg name="st" bitsize="8" type="uint8"/>
+  </feature>
+</target>"#;
+
+/// Waits for gdb to connect on `addr`, then debugs the CPU until it disconnects
+///
+/// The program is paused until the debugger continues or steps it. Nothing
+/// is shown while debugging, but the CPU's display is kept up to date.
+pub fn serve<A: ToSocketAddrs>(cpu: &mut Cpu, addr: A) -> Result<(), Box<dyn Error>> {
+    let listener = TcpListener::bind(addr)?;
+    let (stream, _) = listener.accept()?;
+
+    let mut target = Chip8Target {
+        cpu,
+        breakpoints: vec![],
+        stepping: false,
+        executed: 0,
+    };
+
+    GdbStub::new(stream)
+        .run_blocking::<EventLoop>(&mut target)
+        .map_err(|err| err.to_string())?;
+
+    Ok(())
+}
+
+/// The CHIP-8 as GDB sees it
+enum Chip8Arch {}
+
+impl Arch for Chip8Arch {
+    type Usize = u16;
+    type Registers = Chip8Registers;
+    type BreakpointKind = usize;
+    type RegId = Chip8RegId;
+
+    fn target_description_xml() -> Option<&'static str> {
+        Some(TARGET_XML)
+    }
+}
+
+/// Registers in the order the target description lists them
+#[derive(Debug, Default, Clone, PartialEq)]
+struct Chip8Registers {
+    v: [u8; 16],
+    i: u16,
+    pc: u16,
+    sp: u8,
+    delay_timer: u8,
+    sound_timer: u8,
+}
+
+impl Registers for Chip8Registers {
+    type ProgramCounter = u16;
+
+    fn pc(&self) -> u16 {
+        self.pc
+    }
+
+    fn gdb_serialize(&self, mut write_byte: impl FnMut(Option<u8>)) {
+        let bytes = self
+            .v
+            .iter()
+            .copied()
+            .chain(self.i.to_le_bytes())
+            .chain(self.pc.to_le_bytes())
+            .chain([self.sp, self.delay_timer, self.sound_timer]);
+
+        for byte in bytes {
+            write_byte(Some(byte));
+        }
+    }
+
+    fn gdb_deserialize(&mut self, bytes: &[u8]) -> Result<(), ()> {
+        if bytes.len() != 23 {
+            return Err(());
+        }
+
+        self.v.copy_from_slice(&bytes[0..16]);
+        self.i = u16::from_le_bytes([bytes[16], bytes[17]]);
+        self.pc = u16::from_le_bytes([bytes[18], bytes[19]]);
+        self.sp = bytes[20];
+        self.delay_timer = bytes[21];
+        self.sound_timer = bytes[22];
+
+        Ok(())
+    }
+}
+
+/// Single registers aren't supported, so this only tells gdb their sizes
+#[derive(Debug)]
+struct Chip8RegId;
+
+impl RegId for Chip8RegId {
+    fn from_raw_id(id: usize) -> Option<(Self, Option<NonZeroUsize>)> {
+        let size = match id {
+            0..=15 | 18..=20 => 1,
+            16 | 17 => 2,
+            _ => return None,
+        };
+
+        Some((Chip8RegId, NonZeroUsize::new(size)))
+    }
+}
+
+struct Chip8Target<'a> {
+    cpu: &'a mut Cpu,
+    breakpoints: Vec<Address>,
+    stepping: bool,
+    executed: usize,
+}
+
+/// Why the program stopped running
+enum Stop {
+    Step,
+    Breakpoint,
+    Halted,
+    Fault(CpuError),
+    IncomingData,
+}
+
+impl Chip8Target<'_> {
+    /// Runs a single instruction, ticking the timers as if running in real time
+    ///
+    /// Returns why the program stopped, if it did
+    fn step(&mut self) -> Option<Stop> {
+        match self.cpu.step() {
+            Ok(CpuStatus::Halted) => return Some(Stop::Halted),
+            Err(err) => return Some(Stop::Fault(err)),
+            Ok(_) => {}
+        }
+
+        self.executed += 1;
+        if self.executed.is_multiple_of(INSTRUCTIONS_PER_TICK) {
+            self.cpu.tick_timers();
+        }
+
+        None
+    }
+
+    /// Steps once, or keeps going until a breakpoint, the end of the program,
+    /// or gdb sends something
+    fn resume(&mut self, mut incoming_data: impl FnMut() -> bool) -> Stop {
+        if self.stepping {
+            return self.step().unwrap_or(Stop::Step);
+        }
+
+        let mut count: usize = 0;
+        loop {
+            if count.is_multiple_of(INSTRUCTIONS_PER_POLL) && incoming_data() {
+                return Stop::IncomingData;
+            }
+            count += 1;
+
+            if let Some(stop) = self.step() {
+                return stop;
+            }
+
+            if self.breakpoints.contains(&(self.cpu.program_counter as Address)) {
+                return Stop::Breakpoint;
+            }
+        }
+    }
+}
+
+impl Target for Chip8Target<'_> {
+    type Arch = Chip8Arch;
+    type Error = &'static str;
+
+    fn base_ops(&mut self) -> BaseOps<'_, Chip8Arch, &'static str> {
+        BaseOps::SingleThread(self)
+    }
+
+    fn support_breakpoints(&mut self) -> Option<BreakpointsOps<'_, Self>> {
+        Some(self)
+    }
+}
+
+impl SingleThreadBase for Chip8Target<'_> {
+    fn read_registers(&mut self, regs: &mut Chip8Registers) -> TargetResult<(), Self> {
+        regs.v = self.cpu.registers;
+        regs.i = self.cpu.i;
+        regs.pc = self.cpu.program_counter as u16;
+        regs.sp = self.cpu.stack_pointer as u8;
+        regs.delay_timer = self.cpu.delay_timer;
+        regs.sound_timer = self.cpu.sound_timer;
+        Ok(())
+    }
+
+    fn write_registers(&mut self, regs: &Chip8Registers) -> TargetResult<(), Self> {
+        self.cpu.registers = regs.v;
+        self.cpu.i = regs.i;
+        self.cpu.program_counter = (regs.pc & 0xFFF) as usize;
+        self.cpu.stack_pointer = (regs.sp as usize).min(self.cpu.stack_depth);
+        self.cpu.delay_timer = regs.delay_timer;
+        self.cpu.sound_timer = regs.sound_timer;
+        Ok(())
+    }
+
+    fn read_addrs(&mut self, start_addr: u16, data: &mut [u8]) -> TargetResult<usize, Self> {
+        let memory = &self.cpu.memory;
+        let start = (start_addr as usize).min(memory.len());
+        let len = data.len().min(memory.len() - start);
+        data[..len].copy_from_slice(&memory[start..start + len]);
+        Ok(len)
+    }
+
+    fn write_addrs(&mut self, start_addr: u16, data: &[u8]) -> TargetResult<(), Self> {
+        let start = start_addr as usize;
+        if start + data.len() > self.cpu.memory.len() {
+            return Err(().into());
+        }
+
+        for (ind, &value) in data.iter().enumerate() {
+            self.cpu.write_memory(start + ind, value);
+        }
+        Ok(())
+    }
+
+    fn support_resume(&mut self) -> Option<SingleThreadResumeOps<'_, Self>> {
+        Some(self)
+    }
+}
+
+impl SingleThreadResume for Chip8Target<'_> {
+    fn resume(&mut self, signal: Option<Signal>) -> Result<(), &'static str> {
+        if signal.is_some() {
+            return Err("signals aren't supported");
+        }
+
+        self.stepping = false;
+        Ok(())
+    }
+
+    fn support_single_step(&mut self) -> Option<SingleThreadSingleStepOps<'_, Self>> {
+        Some(self)
+    }
+}
+
+impl SingleThreadSingleStep for Chip8Target<'_> {
+    fn step(&mut self, signal: Option<Signal>) -> Result<(), &'static str> {
+        if signal.is_some() {
+            return Err("signals aren't supported");
+        }
+
+        self.stepping = true;
+        Ok(())
+    }
+}
+
+impl Breakpoints for Chip8Target<'_> {
+    fn support_sw_breakpoint(&mut self) -> Option<SwBreakpointOps<'_, Self>> {
+        Some(self)
+    }
+}
+
+impl SwBreakpoint for Chip8Target<'_> {
+    fn add_sw_breakpoint(&mut self, addr: u16, _kind: usize) -> TargetResult<bool, Self> {
+        if !self.breakpoints.contains(&addr) {
+            self.breakpoints.push(addr);
+        }
+        Ok(true)
+    }
+
+    fn remove_sw_breakpoint(&mut self, addr: u16, _kind: usize) -> TargetResult<bool, Self> {
+        match self.breakpoints.iter().position(|&bp| bp == addr) {
+            Some(pos) => {
+                self.breakpoints.remove(pos);
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+}
+
+struct EventLoop<'a>(PhantomData<Chip8Target<'a>>);
+
+impl<'a> BlockingEventLoop for EventLoop<'a> {
+    type Target = Chip8Target<'a>;
+    type Connection = TcpStream;
+    type StopReason = SingleThreadStopReason<u16>;
+
+    #[allow(clippy::type_complexity)]
+    fn wait_for_stop_reason(
+        target: &mut Chip8Target<'a>,
+        conn: &mut TcpStream,
+    ) -> Result<
+        run_blocking::Event<SingleThreadStopReason<u16>>,
+        run_blocking::WaitForStopReasonError<&'static str, std::io::Error>,
+    > {
+        let incoming_data = || conn.peek().map(|byte| byte.is_some()).unwrap_or(true);
+
+        let reason = match target.resume(incoming_data) {
+            Stop::IncomingData => {
+                let byte = conn
+                    .read()
+                    .map_err(run_blocking::WaitForStopReasonError::Connection)?;
+                return Ok(run_blocking::Event::IncomingData(byte));
+            }
+            Stop::Step => SingleThreadStopReason::DoneStep,
+            Stop::Breakpoint => SingleThreadStopReason::SwBreak(()),
+            Stop::Halted => SingleThreadStopReason::Exited(0),
+            Stop::Fault(CpuError::UnknownOpcode { .. } | CpuError::ZeroOpcode(_)) => {
+                SingleThreadStopReason::Signal(Signal::SIGILL)
+            }
+            Stop::Fault(_) => SingleThreadStopReason::Signal(Signal::SIGSEGV),
+        };
+
+        Ok(run_blocking::Event::TargetStopped(reason))
+    }
+
+    fn on_interrupt(
+        _target: &mut Chip8Target<'a>,
+    ) -> Result<Option<SingleThreadStopReason<u16>>, &'static str> {
+        Ok(Some(SingleThreadStopReason::Signal(Signal::SIGINT)))
+    }
+}
@@ -0,0 +1,257 @@
+//! Golden traces: a plain text record of a run, one line per instruction,
+//! for checking a run against another emulator or an older build
+//!
+//! Each line holds the state just before an instruction ran, as
+//!
+//! ```text
+//! pc=200 op=6005 v=00000000000000000000000000000000 i=000 sp=0
+//! ```
+//!
+//! with the program counter, opcode, V0 to VF and I in hex and the stack
+//! pointer in decimal. Blank lines and lines starting with `#` are skipped,
+//! so traces can carry notes about where they came from. The format is
+//! simple enough for a line of logging in most emulators to produce.
+//!
+//! Timers tick every [`INSTRUCTIONS_PER_TICK`] instructions, and when the
+//! `display_wait` quirk ends a frame, the same as [`conformance`] does, so
+//! a trace taken from another emulator lines up as long as the program
+//! doesn't depend on exactly when the timers tick. No keys are pressed.
+//!
+//! # Example
+//!
+//! ```
+//! use chip_8::golden;
+//! use chip_8::CpuBuilder;
+//!
+//! // LD V0, 5; ADD V0, 0xFF; LD I, 0x300; terminate
+//! let rom = [0x60, 0x05, 0x70, 0xFF, 0xA3, 0x00, 0x00, 0x00];
+//! let mut cpu = CpuBuilder::new().build();
+//! cpu.load_program(&rom).unwrap();
+//!
+//! let mut trace = Vec::new();
+//! assert_eq!(golden::export_trace(&mut cpu, 1000, &mut trace).unwrap(), 4);
+//!
+//! cpu.reset();
+//! assert_eq!(golden::verify_against_trace(&mut cpu, &trace[..]).unwrap(), 4);
+//! ```
+//!
+//! [`conformance`]: crate::conformance
+
+use std::error::Error;
+use std::fmt;
+use std::io::{self, BufRead, Write};
+use std::str::FromStr;
+
+use crate::{Address, Cpu, CpuStatus, OpCode, Registers, StopReason, INSTRUCTIONS_PER_TICK};
+
+/// One line of a trace: the state just before an instruction ran
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TraceStep {
+    pub pc: Address,
+    pub opcode: OpCode,
+    /// V0 to VF
+    pub registers: Registers,
+    pub i: Address,
+    /// Stack pointer, how many addresses are on the stack
+    pub sp: usize,
+}
+
+impl TraceStep {
+    /// The CPU as it is now, about to run the instruction at the program
+    /// counter
+    pub fn of(cpu: &Cpu) -> TraceStep {
+        let state = cpu.state();
+        TraceStep {
+            pc: state.pc,
+            opcode: cpu.next_opcode().unwrap_or(0),
+            registers: state.registers,
+            i: state.i,
+            sp: state.sp,
+        }
+    }
+}
+
+impl fmt::Display for TraceStep {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "pc={:03X} op={:04X} v=", self.pc, self.opcode)?;
+        for value in &self.registers {
+            write!(f, "{:02X}", value)?;
+        }
+        write!(f, " i={:03X} sp={}", self.i, self.sp)
+    }
+}
+
+impl FromStr for TraceStep {
+    type Err = ();
+
+    fn from_str(line: &str) -> Result<TraceStep, ()> {
+        let mut fields = line.split_whitespace();
+        let mut field = |key: &str| fields.next().and_then(|field| field.strip_prefix(key)).ok_or(());
+        let hex = |digits: &str| Address::from_str_radix(digits, 16).map_err(|_| ());
+
+        let pc = hex(field("pc=")?)?;
+        let opcode = hex(field("op=")?)?;
+        let values = field("v=")?;
+        let i = hex(field("i=")?)?;
+        let sp = field("sp=")?.parse().map_err(|_| ())?;
+        if fields.next().is_some() || values.len() != 32 || !values.is_ascii() {
+            return Err(());
+        }
+
+        let mut registers = [0; 16];
+        for (reg, value) in registers.iter_mut().enumerate() {
+            *value = u8::from_str_radix(&values[reg * 2..reg * 2 + 2], 16).map_err(|_| ())?;
+        }
+        Ok(TraceStep { pc, opcode, registers, i, sp })
+    }
+}
+
+/// Why a run didn't match a trace
+#[derive(Debug)]
+pub enum TraceError {
+    /// The trace couldn't be read
+    Io(io::Error),
+    /// A line that isn't a trace step, numbered from 1
+    Parse { line: usize, text: String },
+    /// The CPU's state differed from the trace's before the instruction
+    /// on `line`
+    Mismatch { line: usize, expected: TraceStep, actual: TraceStep },
+    /// The CPU stopped before reaching the step on `line`
+    Stopped { line: usize, reason: StopReason },
+}
+
+impl fmt::Display for TraceError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TraceError::Io(err) => write!(f, "Couldn't read the trace: {}", err),
+            TraceError::Parse { line, text } => write!(f, "Line {} isn't a trace step: {:?}", line, text),
+            TraceError::Mismatch { line, expected, actual } => {
+                write!(f, "Line {} differs\n  expected {}\n  got      {}", line, expected, actual)
+            }
+            TraceError::Stopped { line, reason } => write!(f, "The CPU stopped before line {}: {:?}", line, reason),
+        }
+    }
+}
+
+impl Error for TraceError {}
+
+impl From<io::Error> for TraceError {
+    fn from(err: io::Error) -> TraceError {
+        TraceError::Io(err)
+    }
+}
+
+/// Runs one instruction, ticking the timers every [`INSTRUCTIONS_PER_TICK`],
+/// or returns why the CPU can't go on
+fn advance(cpu: &mut Cpu, since_tick: &mut usize) -> Result<(), StopReason> {
+    let blanking = match cpu.step() {
+        Ok(CpuStatus::Running) => false,
+        Ok(CpuStatus::WaitingForVBlank) => true,
+        Ok(CpuStatus::WaitingForKey(_)) => return Err(StopReason::WaitingForKey),
+        Ok(CpuStatus::Halted) => return Err(StopReason::Halted),
+        Err(err) => return Err(StopReason::Error(err)),
+    };
+    *since_tick += 1;
+    if *since_tick == INSTRUCTIONS_PER_TICK || blanking {
+        cpu.tick_timers();
+        *since_tick = 0;
+    }
+    Ok(())
+}
+
+/// Runs the CPU for up to `steps` instructions, writing a trace line for
+/// each, and returns how many it wrote
+///
+/// Stops early if the program terminates, fails or waits for a key. The
+/// instruction that stopped it is still traced, so its line shows what the
+/// CPU was doing.
+pub fn export_trace<W: Write>(cpu: &mut Cpu, steps: u64, mut out: W) -> io::Result<u64> {
+    let mut since_tick = 0;
+    for step in 0..steps {
+        writeln!(out, "{}", TraceStep::of(cpu))?;
+        if advance(cpu, &mut since_tick).is_err() {
+            return Ok(step + 1);
+        }
+    }
+    Ok(steps)
+}
+
+/// Runs the CPU along a trace, checking its state against each line before
+/// running the instruction, and returns how many steps matched
+///
+/// The CPU should be set up the way it was when the trace was taken, with
+/// the same ROM, quirks and seed.
+pub fn verify_against_trace<R: BufRead>(cpu: &mut Cpu, trace: R) -> Result<u64, TraceError> {
+    let mut since_tick = 0;
+    let mut stopped = None;
+    let mut matched = 0;
+    for (index, text) in trace.lines().enumerate() {
+        let text = text?;
+        let line = index + 1;
+        let trimmed = text.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        let expected: TraceStep = match trimmed.parse() {
+            Ok(expected) => expected,
+            Err(()) => return Err(TraceError::Parse { line, text }),
+        };
+        if let Some(reason) = stopped {
+            return Err(TraceError::Stopped { line, reason });
+        }
+
+        let actual = TraceStep::of(cpu);
+        if actual != expected {
+            return Err(TraceError::Mismatch { line, expected, actual });
+        }
+        stopped = advance(cpu, &mut since_tick).err();
+        matched += 1;
+    }
+    Ok(matched)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CpuBuilder, CpuError};
+
+    #[test]
+    fn steps_round_trip_through_text() {
+        let mut registers = [0; 16];
+        registers[0xA] = 0x2F;
+        let step = TraceStep { pc: 0x2FE, opcode: 0xD01F, registers, i: 0x1234, sp: 12 };
+        let line = step.to_string();
+
+        assert_eq!(line, "pc=2FE op=D01F v=000000000000000000002F0000000000 i=1234 sp=12");
+        assert_eq!(line.parse(), Ok(step));
+        assert_eq!("pc=2FE op=D01F v=00 i=1234 sp=12".parse::<TraceStep>(), Err(()));
+        assert_eq!(format!("{} extra", line).parse::<TraceStep>(), Err(()));
+    }
+
+    #[test]
+    fn verify_reports_where_the_run_leaves_the_trace() {
+        // LD V0, 5; ADD V0, 1; RET
+        let rom = [0x60, 0x05, 0x70, 0x01, 0x00, 0xEE];
+        let mut cpu = CpuBuilder::new().build();
+        cpu.load_program(&rom).unwrap();
+        let mut trace = Vec::new();
+        assert_eq!(export_trace(&mut cpu, 100, &mut trace).unwrap(), 3);
+        let trace = String::from_utf8(trace).unwrap();
+
+        let edited = trace.replacen("v=06", "v=07", 1);
+        cpu.reset();
+        match verify_against_trace(&mut cpu, format!("# from another emulator\n\n{}", edited).as_bytes()) {
+            Err(TraceError::Mismatch { line: 5, expected, actual }) => {
+                assert_eq!((expected.registers[0], actual.registers[0]), (7, 6))
+            }
+            other => panic!("expected a mismatch, got {:?}", other),
+        }
+
+        cpu.reset();
+        let longer = format!("{}{}", trace, trace.lines().last().unwrap());
+        match verify_against_trace(&mut cpu, longer.as_bytes()) {
+            Err(TraceError::Stopped { line: 4, reason }) => assert_eq!(reason, StopReason::Error(CpuError::StackUnderflow)),
+            other => panic!("expected the CPU to stop, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,126 @@
+//! Helpers for testing ROMs headlessly
+//!
+//! Test ROMs usually draw their results and then loop forever, so rather
+//! than guessing how many instructions that takes, [`run_until_stable`]
+//! runs whole frames until the screen stops changing. Frames are the same
+//! as [`Clock::run_one_frame`]'s at the default speed, so a run always
+//! ends on the same frame, given a seeded CPU.
+//!
+//! # Example
+//!
+//! ```
+//! use chip_8::{harness, CpuBuilder};
+//!
+//! let mut cpu = CpuBuilder::new().build();
+//! // LD I, the 0 sprite; DRW V0, V0, 5; then loop forever
+//! cpu.load_program(&[0xA0, 0x00, 0xD0, 0x05, 0x12, 0x04]).unwrap();
+//!
+//! let stable = harness::run_until_stable(&mut cpu, 600).unwrap();
+//! assert_eq!(stable.changed, 1);
+//! assert!(stable.display.pixel(0, 0));
+//! ```
+
+use std::error::Error;
+use std::fmt;
+
+use crate::timing::Clock;
+use crate::{Cpu, CpuError, Display, StopReason};
+
+/// How many frames in a row the screen has to stay the same for
+/// [`run_until_stable`], half a second
+pub const STABLE_FRAMES: usize = 30;
+
+/// The screen a program settled on
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Stable {
+    pub display: Display,
+    /// The frame the screen last changed in, counting from 1, or 0 if it
+    /// never did
+    pub changed: usize,
+    /// How many frames ran in all
+    pub frames: usize,
+}
+
+/// Why the screen didn't settle
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Unstable {
+    /// It was still changing after this many frames
+    Timeout { frames: usize },
+    /// An instruction failed
+    Error(CpuError),
+}
+
+impl fmt::Display for Unstable {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Unstable::Timeout { frames } => write!(f, "The screen was still changing after {} frames", frames),
+            Unstable::Error(err) => write!(f, "The program failed before the screen settled: {}", err),
+        }
+    }
+}
+
+impl Error for Unstable {}
+
+/// Runs `cpu` a frame at a time until the screen has stayed the same for
+/// [`STABLE_FRAMES`] frames, or the program terminates, giving up after
+/// `max_frames`
+pub fn run_until_stable(cpu: &mut Cpu, max_frames: usize) -> Result<Stable, Unstable> {
+    run_until_stable_for(cpu, STABLE_FRAMES, max_frames)
+}
+
+/// [`run_until_stable`], with the screen having to stay the same for
+/// `stable_frames` frames
+///
+/// Games that animate slowly may need more than [`STABLE_FRAMES`].
+pub fn run_until_stable_for(cpu: &mut Cpu, stable_frames: usize, max_frames: usize) -> Result<Stable, Unstable> {
+    let mut clock = Clock::default();
+    let mut hash = cpu.display().hash64();
+    let mut changed = 0;
+
+    for frame in 1..=max_frames {
+        let stop = clock.run_one_frame(cpu);
+        let now = cpu.display().hash64();
+        if now != hash {
+            hash = now;
+            changed = frame;
+        }
+
+        match stop {
+            StopReason::Error(err) => return Err(Unstable::Error(err)),
+            // nothing can change the screen again
+            StopReason::Halted => return Ok(Stable { display: cpu.display().clone(), changed, frames: frame }),
+            _ if frame - changed >= stable_frames => {
+                return Ok(Stable { display: cpu.display().clone(), changed, frames: frame })
+            }
+            _ => {}
+        }
+    }
+    Err(Unstable::Timeout { frames: max_frames })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CpuBuilder;
+
+    #[test]
+    fn flashing_screens_time_out() {
+        let mut cpu = CpuBuilder::new().build();
+        // LD I, the 0 sprite; DRW V0, V0, 5 forever
+        cpu.load_program(&[0xA0, 0x00, 0xD0, 0x05, 0x12, 0x02]).unwrap();
+
+        assert_eq!(run_until_stable(&mut cpu, 100), Err(Unstable::Timeout { frames: 100 }));
+    }
+
+    #[test]
+    fn halting_and_failing_end_the_run() {
+        let mut cpu = CpuBuilder::new().build();
+        // CLS; then halt
+        cpu.load_program(&[0x00, 0xE0]).unwrap();
+        assert_eq!(run_until_stable(&mut cpu, 100).map(|stable| stable.frames), Ok(1));
+
+        // RET with nothing to return to
+        cpu.load_program(&[0x00, 0xEE]).unwrap();
+        assert_eq!(run_until_stable_for(&mut cpu, 5, 100), Err(Unstable::Error(CpuError::StackUnderflow)));
+    }
+}
@@ -0,0 +1,244 @@
+//! High scores for games that keep their score somewhere it can be read
+//!
+//! A [`ScorePlugin`] knows one game: how to recognise its ROM and where in
+//! memory it keeps the score. [`find`] picks the plugin for a ROM from the
+//! ones built in, and a [`ScoreKeeper`] reads the score after every frame,
+//! remembering the best there's been and keeping it in a file between
+//! sessions.
+//!
+//! Most games keep their score as the three digits FX33 writes, so a
+//! [`BcdScore`] is all a plugin usually needs to be. The built in ones
+//! recognise their games by the code that writes the score, so they still
+//! work for copies whose bytes differ elsewhere.
+//!
+//! # Example
+//!
+//! ```
+//! use chip_8::high_score::{self, ScoreKeeper};
+//! use chip_8::CpuBuilder;
+//!
+//! // LD V5, 120; LD I, 0x314; LD B, V5, as Brix writes its score
+//! let rom = [0x65, 0x78, 0xA3, 0x14, 0xF5, 0x33];
+//! let plugin = high_score::find(&rom).unwrap();
+//! assert_eq!(plugin.title(), "Brix");
+//!
+//! let mut keeper = ScoreKeeper::new(plugin);
+//! let mut cpu = CpuBuilder::new().memory(rom).build();
+//! cpu.run_cycles(3);
+//! assert_eq!(keeper.update(cpu.memory()), Some(120));
+//! ```
+
+use std::fs;
+use std::io;
+use std::ops::Range;
+use std::path::PathBuf;
+
+use crate::Byte;
+
+/// What a game's high score plugin knows about it
+///
+/// Plugins are shared between every CPU running the game, so they have to
+/// be `Sync`.
+pub trait ScorePlugin: Sync {
+    /// The game's name, for telling the player whose score it is
+    fn title(&self) -> &'static str;
+
+    /// Whether `rom` is this game
+    fn matches(&self, rom: &[Byte]) -> bool;
+
+    /// Where in memory the game keeps its score
+    fn location(&self) -> Range<usize>;
+
+    /// The score, from the bytes at [`location`](ScorePlugin::location)
+    ///
+    /// By default they're read as FX33 writes them, a decimal digit a byte
+    /// with the most significant first.
+    fn decode(&self, bytes: &[Byte]) -> u32 {
+        bytes.iter().fold(0, |score, &digit| score * 10 + digit as u32)
+    }
+}
+
+/// A game that keeps its score where `LD I, addr` followed by `LD B, Vx`
+/// writes it, recognised by those two instructions
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BcdScore {
+    pub title: &'static str,
+    /// Where FX33 writes the three digits
+    pub addr: u16,
+    /// Which register holds the score, the X in FX33
+    pub register: Byte,
+}
+
+impl BcdScore {
+    /// The two instructions that write the score
+    fn code(&self) -> [Byte; 4] {
+        [0xA0 | (self.addr >> 8) as Byte & 0xF, self.addr as Byte, 0xF0 | self.register, 0x33]
+    }
+}
+
+impl ScorePlugin for BcdScore {
+    fn title(&self) -> &'static str {
+        self.title
+    }
+
+    fn matches(&self, rom: &[Byte]) -> bool {
+        rom.windows(4).any(|code| code == self.code())
+    }
+
+    fn location(&self) -> Range<usize> {
+        self.addr as usize..self.addr as usize + 3
+    }
+}
+
+/// Pong, which keeps both players' points in one register, the left
+/// player's as the tens and the right's as the units
+///
+/// The left player's are the score, since in the one player version the
+/// right is the computer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Pong;
+
+/// Where Pong writes the points
+const PONG: BcdScore = BcdScore { title: "Pong", addr: 0x2F2, register: 0xE };
+
+impl ScorePlugin for Pong {
+    fn title(&self) -> &'static str {
+        PONG.title
+    }
+
+    fn matches(&self, rom: &[Byte]) -> bool {
+        PONG.matches(rom)
+    }
+
+    fn location(&self) -> Range<usize> {
+        PONG.location()
+    }
+
+    fn decode(&self, bytes: &[Byte]) -> u32 {
+        bytes[1] as u32
+    }
+}
+
+/// Brix, which counts a point for every brick in V5
+pub const BRIX: BcdScore = BcdScore { title: "Brix", addr: 0x314, register: 0x5 };
+
+/// Tetris, which counts the lines cleared in VA and keeps the digits well
+/// away from the program, past 0x800
+pub const TETRIS: BcdScore = BcdScore { title: "Tetris", addr: 0x804, register: 0xA };
+
+/// The plugins [`find`] looks through, in order
+pub const PLUGINS: &[&dyn ScorePlugin] = &[&Pong, &BRIX, &TETRIS];
+
+/// The built in plugin for `rom`, if there is one
+pub fn find(rom: &[Byte]) -> Option<&'static dyn ScorePlugin> {
+    PLUGINS.iter().copied().find(|plugin| plugin.matches(rom))
+}
+
+/// Follows a game's score, remembering the best it's been
+///
+/// Opened with a file, the best score is read from it to begin with and
+/// [`save`](ScoreKeeper::save) writes it back, as a decimal number on a
+/// line of its own.
+pub struct ScoreKeeper {
+    plugin: &'static dyn ScorePlugin,
+    path: Option<PathBuf>,
+    score: u32,
+    best: u32,
+}
+
+impl ScoreKeeper {
+    /// Follows the game `plugin` knows, with no best score yet and nowhere
+    /// to save one
+    pub fn new(plugin: &'static dyn ScorePlugin) -> ScoreKeeper {
+        ScoreKeeper { plugin, path: None, score: 0, best: 0 }
+    }
+
+    /// Follows the game `plugin` knows, starting from the best score saved
+    /// at `path`, or none if the file doesn't exist yet
+    pub fn open<P: Into<PathBuf>>(plugin: &'static dyn ScorePlugin, path: P) -> io::Result<ScoreKeeper> {
+        let path = path.into();
+        let best = match fs::read_to_string(&path) {
+            Ok(text) => text.trim().parse().map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => 0,
+            Err(err) => return Err(err),
+        };
+        Ok(ScoreKeeper { plugin, path: Some(path), score: 0, best })
+    }
+
+    pub fn plugin(&self) -> &'static dyn ScorePlugin {
+        self.plugin
+    }
+
+    /// The score when [`update`](ScoreKeeper::update) last read it
+    pub fn score(&self) -> u32 {
+        self.score
+    }
+
+    pub fn best(&self) -> u32 {
+        self.best
+    }
+
+    /// Reads the score from `memory`, returning the new best score if it
+    /// beats the old one
+    ///
+    /// Meant to be called after every frame. Memory too small to hold the
+    /// score leaves it as it was.
+    pub fn update(&mut self, memory: &[Byte]) -> Option<u32> {
+        self.score = self.plugin.decode(memory.get(self.plugin.location())?);
+        if self.score <= self.best {
+            return None;
+        }
+        self.best = self.score;
+        Some(self.best)
+    }
+
+    /// Writes the best score to the file it was opened with, if any
+    pub fn save(&self) -> io::Result<()> {
+        match &self.path {
+            Some(path) => fs::write(path, format!("{}\n", self.best)),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CpuBuilder;
+
+    #[test]
+    fn plugins_recognise_the_code_writing_the_score() {
+        // LD I, 0x2F2; LD B, VE, as Pong draws its score
+        assert_eq!(find(&[0x00, 0xE0, 0xA2, 0xF2, 0xFE, 0x33]).unwrap().title(), "Pong");
+        // the right address from the wrong register
+        assert!(find(&[0xA3, 0x14, 0xF6, 0x33]).is_none());
+        // LD [I], V2; LD I, 0x804; LD B, VA, as Tetris saves its registers
+        // to draw the lines cleared
+        assert_eq!(find(&[0xF2, 0x55, 0xA8, 0x04, 0xFA, 0x33]).unwrap().title(), "Tetris");
+
+        let mut memory = vec![0; 0x1000];
+        memory[0x2F2..0x2F5].copy_from_slice(&[0, 3, 1]);
+        assert_eq!(ScoreKeeper::new(&Pong).update(&memory), Some(3));
+    }
+
+    #[test]
+    fn best_scores_are_kept_between_sessions() {
+        let path = std::env::temp_dir().join(format!("chip_8_score_test_{}", std::process::id()));
+        let _ = fs::remove_file(&path);
+        // LD V5, 40; LD I, 0x314; LD B, V5; LD V5, 20; LD B, V5
+        let rom = [0x65, 0x28, 0xA3, 0x14, 0xF5, 0x33, 0x65, 0x14, 0xF5, 0x33];
+        let mut cpu = CpuBuilder::new().memory(rom).build();
+
+        let mut keeper = ScoreKeeper::open(&BRIX, &path).unwrap();
+        cpu.run_cycles(3);
+        assert_eq!(keeper.update(cpu.memory()), Some(40));
+        cpu.run_cycles(2);
+        assert_eq!(keeper.update(cpu.memory()), None);
+        assert_eq!((keeper.score(), keeper.best()), (20, 40));
+        keeper.save().unwrap();
+
+        let reopened = ScoreKeeper::open(&BRIX, &path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(reopened.best(), 40);
+    }
+}
@@ -0,0 +1,340 @@
+//! The 16 key hex keypad, and the instructions that read it
+//!
+//! Besides which keys are held, the [`Keypad`] remembers which were
+//! pressed and released since the last frame, so frontends and tools can
+//! react to edges rather than levels. FX0A finishes when a key is
+//! released, as on the COSMAC VIP, unless the `wait_key_on_press` quirk
+//! asks for the press instead. [`Autofire`] turns holding a key into
+//! pressing it over and over.
+
+use crate::{Byte, Cpu, Reg};
+
+/// The fastest a key can autofire: pressed one frame and released the
+/// next, at 60 frames a second
+pub const MAX_AUTOFIRE_HZ: u32 = 30;
+
+/// Which of the keys 0 to F are held down, and which went down or up
+/// since the frame started
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Keypad {
+    keys: [bool; 16],
+    pressed_this_frame: [bool; 16],
+    released_this_frame: [bool; 16],
+}
+
+impl Keypad {
+    /// A keypad with nothing held down
+    pub fn new() -> Keypad {
+        Keypad::default()
+    }
+
+    /// Whether `key` is held down, only looking at its lowest nibble like
+    /// EX9E and EXA1 do
+    pub fn is_pressed(&self, key: Byte) -> bool {
+        self.keys[(key & 0xF) as usize]
+    }
+
+    /// Whether `key` went down since the frame started, even if it's been
+    /// released again
+    pub fn pressed_this_frame(&self, key: Byte) -> bool {
+        self.pressed_this_frame[(key & 0xF) as usize]
+    }
+
+    /// Whether `key` came up since the frame started, even if it's been
+    /// pressed again
+    pub fn released_this_frame(&self, key: Byte) -> bool {
+        self.released_this_frame[(key & 0xF) as usize]
+    }
+
+    /// Presses or releases `key`, noting the edge if it wasn't already that
+    /// way
+    ///
+    /// # Panics
+    ///
+    /// Panics if `key` is over 0xF
+    pub fn set(&mut self, key: Byte, pressed: bool) {
+        let key = key as usize;
+        if self.keys[key] != pressed {
+            if pressed {
+                self.pressed_this_frame[key] = true;
+            } else {
+                self.released_this_frame[key] = true;
+            }
+        }
+        self.keys[key] = pressed;
+    }
+
+    /// Forgets the presses and releases so far, keeping the held keys
+    ///
+    /// The CPU does this on every timer tick, which is its frame.
+    ///
+    /// # Examples
+    /// ```
+    /// use chip_8::Keypad;
+    ///
+    /// let mut keypad = Keypad::new();
+    /// keypad.set(0x5, true);
+    /// keypad.set(0x5, false);
+    /// assert!(keypad.pressed_this_frame(0x5) && keypad.released_this_frame(0x5));
+    ///
+    /// keypad.end_frame();
+    /// assert!(!keypad.pressed_this_frame(0x5) && !keypad.is_pressed(0x5));
+    /// ```
+    pub fn end_frame(&mut self) {
+        self.pressed_this_frame = [false; 16];
+        self.released_this_frame = [false; 16];
+    }
+
+    /// The keys held down, lowest first
+    ///
+    /// # Examples
+    /// ```
+    /// use chip_8::Keypad;
+    ///
+    /// let mut keypad = Keypad::new();
+    /// keypad.set(0xA, true);
+    /// keypad.set(0x3, true);
+    /// assert_eq!(keypad.pressed().collect::<Vec<_>>(), [0x3, 0xA]);
+    /// ```
+    pub fn pressed(&self) -> impl Iterator<Item = Byte> + '_ {
+        (0..16).filter(move |&key| self.keys[key as usize])
+    }
+
+    /// The keypad packed into 16 bits, key 0 in the lowest, for sending
+    /// over the network or recording
+    pub fn bits(&self) -> u16 {
+        self.pressed().fold(0, |bits, key| bits | 1 << key)
+    }
+
+    /// Unpacks a keypad from [`bits`](Keypad::bits), with no presses or
+    /// releases this frame
+    pub fn from_bits(bits: u16) -> Keypad {
+        let mut keypad = Keypad::new();
+        for key in 0..16 {
+            keypad.set(key, bits & 1 << key != 0);
+        }
+        keypad.end_frame();
+        keypad
+    }
+}
+
+/// Presses keys over and over while the player holds them, for games that
+/// want rapid presses
+///
+/// Frontends pass the player's presses and releases through
+/// [`key`](Autofire::key) and call [`frame`](Autofire::frame) once before
+/// each emulated frame, handing on what both return. Keys without a rate
+/// pass straight through.
+///
+/// # Examples
+/// ```
+/// use chip_8::Autofire;
+///
+/// let mut autofire = Autofire::new();
+/// // pressed for one frame and released for the next
+/// autofire.set_rate(0x5, Some(30));
+///
+/// assert_eq!(autofire.key(0x5, true), Some((0x5, true)));
+/// assert_eq!(autofire.frame(), []);
+/// assert_eq!(autofire.frame(), [(0x5, false)]);
+/// assert_eq!(autofire.frame(), [(0x5, true)]);
+/// assert_eq!(autofire.key(0x5, false), Some((0x5, false)));
+/// assert_eq!(autofire.key(0x6, true), Some((0x6, true)));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Autofire {
+    // presses a second, for the keys with autofire on
+    rates: [Option<u32>; 16],
+    // for the autofiring keys being held, how many frames they've been
+    // held and whether they're down right now
+    held: [Option<(u32, bool)>; 16],
+}
+
+impl Autofire {
+    /// Autofire with every key off
+    pub fn new() -> Autofire {
+        Autofire::default()
+    }
+
+    /// Fires `key` `hz` times a second while it's held, up to
+    /// [`MAX_AUTOFIRE_HZ`], or turns autofire off for it with `None`
+    ///
+    /// # Panics
+    ///
+    /// Panics if `key` is over 0xF
+    pub fn set_rate(&mut self, key: Byte, hz: Option<u32>) {
+        assert!(key <= 0xF, "Not a CHIP-8 key: 0x{:X}", key);
+        self.rates[key as usize] = hz.filter(|&hz| hz > 0).map(|hz| hz.min(MAX_AUTOFIRE_HZ));
+        self.held[key as usize] = None;
+    }
+
+    /// How many times a second `key` fires while held, if it autofires
+    pub fn rate(&self, key: Byte) -> Option<u32> {
+        self.rates[(key & 0xF) as usize]
+    }
+
+    /// Takes the player pressing or releasing `key`, returning what to pass
+    /// on to the CPU right away, if anything
+    ///
+    /// # Panics
+    ///
+    /// Panics if `key` is over 0xF
+    pub fn key(&mut self, key: Byte, pressed: bool) -> Option<(Byte, bool)> {
+        let held = &mut self.held[key as usize];
+        if self.rates[key as usize].is_none() {
+            return Some((key, pressed));
+        }
+        match (pressed, *held) {
+            (true, None) => {
+                *held = Some((0, true));
+                Some((key, true))
+            }
+            (false, Some((_, down))) => {
+                *held = None;
+                if down {
+                    Some((key, false))
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// Moves the held keys on a frame, returning the keys to press (`true`)
+    /// or release (`false`) for it
+    pub fn frame(&mut self) -> Vec<(Byte, bool)> {
+        let mut keys = Vec::new();
+        for key in 0..16 {
+            let (rate, held) = (self.rates[key], &mut self.held[key]);
+            if let (Some(hz), Some((frames, down))) = (rate, held.as_mut()) {
+                // each press takes 60 / hz frames, the first half down
+                let want_down = (*frames * hz / MAX_AUTOFIRE_HZ).is_multiple_of(2);
+                if want_down != *down {
+                    keys.push((key as Byte, want_down));
+                    *down = want_down;
+                }
+                *frames += 1;
+            }
+        }
+        keys
+    }
+}
+
+impl Cpu {
+    /// The keys held down right now
+    pub fn keypad(&self) -> &Keypad {
+        &self.keys
+    }
+
+    /// Skips the next instruction if the key in registers[x] is held down
+    pub(crate) fn skip_key_pressed(&mut self, x: Reg) {
+        if self.keys.is_pressed(self.registers[x]) {
+            self.program_counter += 2;
+        }
+    }
+
+    /// Skips the next instruction if the key in registers[x] is not held down
+    pub(crate) fn skip_key_not_pressed(&mut self, x: Reg) {
+        if !self.keys.is_pressed(self.registers[x]) {
+            self.program_counter += 2;
+        }
+    }
+
+    /// Presses or releases one of the 16 keys on the keypad
+    ///
+    /// Releasing a key finishes an FX0A that's waiting for one, or pressing
+    /// it does with the `wait_key_on_press` quirk.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `key` is over 0xF
+    pub fn set_key(&mut self, key: Byte, pressed: bool) {
+        debug!("key {:X} {}", key, if pressed { "pressed" } else { "released" });
+        let was_pressed = self.keys.is_pressed(key);
+        self.keys.set(key, pressed);
+
+        let finishes_wait = if self.quirks.wait_key_on_press { pressed } else { was_pressed && !pressed };
+        if finishes_wait {
+            if let Some(x) = self.waiting_for_key.take() {
+                debug!("key {:X} finished waiting for V{:X}", key, x);
+                self.write_register(x, key);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Keypad;
+    use crate::{CpuBuilder, Quirks, Reg};
+
+    #[test]
+    fn skip_key_pressed_follows_keypad() {
+        let mut cpu = CpuBuilder::new().build();
+        cpu.program_counter = 0x100;
+        cpu.registers[4] = 0xA;
+        cpu.skip_key_pressed(Reg::nibble(4));
+        assert_eq!(cpu.program_counter, 0x100);
+
+        cpu.set_key(0xA, true);
+        cpu.skip_key_pressed(Reg::nibble(4));
+        assert_eq!(cpu.program_counter, 0x102);
+    }
+
+    #[test]
+    fn skip_key_not_pressed_follows_keypad() {
+        let mut cpu = CpuBuilder::new().build();
+        cpu.program_counter = 0x100;
+        cpu.registers[4] = 0xA;
+        cpu.set_key(0xA, true);
+        cpu.skip_key_not_pressed(Reg::nibble(4));
+        assert_eq!(cpu.program_counter, 0x100);
+
+        cpu.set_key(0xA, false);
+        cpu.skip_key_not_pressed(Reg::nibble(4));
+        assert_eq!(cpu.program_counter, 0x102);
+    }
+
+    #[test]
+    fn keypad_tracks_edges_until_the_frame_ends() {
+        let mut keypad = Keypad::new();
+        keypad.set(0x1, true);
+        keypad.set(0x2, true);
+        keypad.end_frame();
+
+        keypad.set(0x1, false);
+        keypad.set(0x2, true);
+        keypad.set(0x3, true);
+        assert!(keypad.released_this_frame(0x1) && !keypad.pressed_this_frame(0x1));
+        assert!(!keypad.pressed_this_frame(0x2) && keypad.is_pressed(0x2));
+        assert!(keypad.pressed_this_frame(0x3));
+    }
+
+    #[test]
+    fn wait_key_finishes_on_release_unless_quirked() {
+        let mut cpu = CpuBuilder::new().build();
+        cpu.waiting_for_key = Some(Reg::nibble(2));
+        cpu.set_key(0x7, true);
+        assert!(cpu.waiting_for_key());
+        cpu.set_key(0x7, false);
+        assert_eq!((cpu.waiting_for_key(), cpu.registers[2]), (false, 0x7));
+
+        let quirks = Quirks { wait_key_on_press: true, ..Quirks::default() };
+        let mut cpu = CpuBuilder::new().quirks(quirks).build();
+        cpu.waiting_for_key = Some(Reg::nibble(2));
+        cpu.set_key(0x7, true);
+        assert_eq!((cpu.waiting_for_key(), cpu.registers[2]), (false, 0x7));
+    }
+
+    #[test]
+    fn keypad_round_trips_through_bits() {
+        let mut keypad = Keypad::new();
+        keypad.set(0x0, true);
+        keypad.set(0xF, true);
+        keypad.end_frame();
+
+        assert_eq!(keypad.bits(), 0x8001);
+        assert_eq!(Keypad::from_bits(0x8001), keypad);
+    }
+}
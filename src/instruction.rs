@@ -0,0 +1,527 @@
+//! Decoding opcodes into instructions
+//!
+//! [`Instruction::decode`] splits a two byte opcode into one of the CHIP-8
+//! instructions, and the `Display` impl prints it as assembly, e.g.
+//! [`Instruction::decode_mega`] does the same for a CPU in MegaChip mode.
+//!
+//! ```
+//! use chip_8::instruction::Instruction;
+//! use chip_8::Reg;
+//!
+//! let instruction = Instruction::decode(0x8014);
+//! assert_eq!(instruction, Instruction::AddReg(Reg::V0, Reg::new(1).unwrap()));
+//! assert_eq!(instruction.to_string(), "ADD V0, V1");
+//! ```
+
+use std::fmt;
+
+use crate::{Addr, Byte, OpCode, Reg};
+
+/// A single decoded CHIP-8 instruction
+///
+/// Register operands are register indices (`x` and `y` in `8XY4`),
+/// not the values in those registers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Instruction {
+    /// `0000` - stops the program
+    Halt,
+    /// `0010` - leaves MegaChip mode for the 64x32 low resolution screen
+    /// (MegaChip)
+    MegaOff,
+    /// `0011` - switches to MegaChip's 256x192 colour screen (MegaChip)
+    MegaOn,
+    /// `00E0` - clears the screen
+    Clear,
+    /// `00EE` - returns from a subroutine
+    Return,
+    /// `00FE` - switches to the 64x32 low resolution screen (SUPER-CHIP)
+    LowRes,
+    /// `00FF` - switches to the 128x64 high resolution screen (SUPER-CHIP)
+    HighRes,
+    /// `01NN NNNN` - sets I to the 24 bit address NNNNNN, its low 16 bits
+    /// read from the next two bytes (MegaChip)
+    LongI(Byte),
+    /// `02NN` - loads NN ARGB colours from I into the palette, from colour 1
+    /// on (MegaChip)
+    LoadPalette(Byte),
+    /// `03NN` - sets the width of sprites to NN pixels, 0 being 256
+    /// (MegaChip)
+    SpriteWidth(Byte),
+    /// `04NN` - sets the height of sprites to NN pixels, 0 being 256
+    /// (MegaChip)
+    SpriteHeight(Byte),
+    /// `05NN` - sets how opaque the screen is, from 0 to 0xFF (MegaChip)
+    Alpha(Byte),
+    /// `0NNN` - calls a machine code routine, treated the same as `Call`
+    Sys(Addr),
+    /// `1NNN` - jumps to NNN
+    Jump(Addr),
+    /// `2NNN` - calls the subroutine at NNN
+    Call(Addr),
+    /// `3XNN` - skips the next instruction if VX equals NN
+    SkipEqual(Reg, Byte),
+    /// `4XNN` - skips the next instruction if VX does not equal NN
+    SkipNotEqual(Reg, Byte),
+    /// `5XY0` - skips the next instruction if VX equals VY
+    SkipEqualReg(Reg, Reg),
+    /// `6XNN` - sets VX to NN
+    SetRegister(Reg, Byte),
+    /// `7XNN` - adds NN to VX
+    Add(Reg, Byte),
+    /// `8XY0` - sets VX to VY
+    Assign(Reg, Reg),
+    /// `8XY1` - sets VX to VX OR VY
+    Or(Reg, Reg),
+    /// `8XY2` - sets VX to VX AND VY
+    And(Reg, Reg),
+    /// `8XY3` - sets VX to VX XOR VY
+    Xor(Reg, Reg),
+    /// `8XY4` - adds VY to VX, setting VF on carry
+    AddReg(Reg, Reg),
+    /// `8XY5` - subtracts VY from VX, clearing VF on borrow
+    SubReg(Reg, Reg),
+    /// `8XY6` - shifts VX right by one
+    ShiftRight(Reg, Reg),
+    /// `8XY7` - sets VX to VY - VX, clearing VF on borrow
+    SubN(Reg, Reg),
+    /// `8XYE` - shifts VX left by one
+    ShiftLeft(Reg, Reg),
+    /// `9XY0` - skips the next instruction if VX does not equal VY
+    SkipNotEqualReg(Reg, Reg),
+    /// `ANNN` - sets I to NNN
+    SetI(Addr),
+    /// `BNNN` - jumps to NNN + V0
+    JumpReg(Addr),
+    /// `CXNN` - sets a register to a random number AND NN
+    Rand(Reg, Byte),
+    /// `DXYN` - draws an N row sprite from I at (VX, VY), or in MegaChip
+    /// mode a sprite of palette colours the size `03NN` and `04NN` set
+    Draw(Reg, Reg, Byte),
+    /// `EX9E` - skips the next instruction if the key in VX is pressed
+    SkipKeyPressed(Reg),
+    /// `EXA1` - skips the next instruction if the key in VX is not pressed
+    SkipKeyNotPressed(Reg),
+    /// `F002` - loads the 16 byte audio pattern from I (XO-CHIP)
+    LoadAudio,
+    /// `FX07` - sets VX to the delay timer
+    GetDelay(Reg),
+    /// `FX0A` - waits for a key to be pressed and released and stores it in VX
+    WaitKey(Reg),
+    /// `FX15` - sets the delay timer to VX
+    SetDelay(Reg),
+    /// `FX18` - sets the sound timer to VX
+    SetSound(Reg),
+    /// `FX1E` - adds VX to I
+    AddI(Reg),
+    /// `FX29` - points I at the font sprite for the digit in VX
+    SetISprite(Reg),
+    /// `FX33` - stores the binary-coded decimal of VX at I
+    Bcd(Reg),
+    /// `FX3A` - sets the audio pattern's pitch to VX (XO-CHIP)
+    SetPitch(Reg),
+    /// `FX55` - stores V0 to VX in memory starting at I
+    RegDump(Reg),
+    /// `FX65` - loads V0 to VX from memory starting at I
+    RegLoad(Reg),
+    /// `FX75` - saves V0 to VX to the RPL user flags (SUPER-CHIP)
+    SaveFlags(Reg),
+    /// `FX85` - loads V0 to VX from the RPL user flags (SUPER-CHIP)
+    LoadFlags(Reg),
+    /// Any opcode that isn't a known instruction
+    Unknown(OpCode),
+}
+
+impl Instruction {
+    /// Splits an opcode into its instruction and operands
+    pub fn decode(opcode: OpCode) -> Instruction {
+        let c = ((opcode & 0xF000) >> 12) as Byte;
+        let x = ((opcode & 0x0F00) >> 8) as Byte;
+        let y = ((opcode & 0x00F0) >> 4) as Byte;
+        let d = (opcode & 0x000F) as Byte;
+        let nnn = Addr::nnn(opcode);
+        let nn = (opcode & 0x00FF) as Byte;
+
+        let (vx, vy) = (Reg::nibble(x), Reg::nibble(y));
+        match (c, x, y, d) {
+            (0, 0, 0, 0) => Instruction::Halt,
+            (0, 0, 0x1, 0) => Instruction::MegaOff,
+            (0, 0, 0x1, 0x1) => Instruction::MegaOn,
+            (0, 0, 0xE, 0) => Instruction::Clear,
+            (0, 0, 0xE, 0xE) => Instruction::Return,
+            (0, 0, 0xF, 0xE) => Instruction::LowRes,
+            (0, 0, 0xF, 0xF) => Instruction::HighRes,
+            (0, _, _, _) => Instruction::Sys(nnn),
+            (0x1, _, _, _) => Instruction::Jump(nnn),
+            (0x2, _, _, _) => Instruction::Call(nnn),
+            (0x3, _, _, _) => Instruction::SkipEqual(vx, nn),
+            (0x4, _, _, _) => Instruction::SkipNotEqual(vx, nn),
+            (0x5, _, _, _) => Instruction::SkipEqualReg(vx, vy),
+            (0x6, _, _, _) => Instruction::SetRegister(vx, nn),
+            (0x7, _, _, _) => Instruction::Add(vx, nn),
+            (0x8, _, _, 0) => Instruction::Assign(vx, vy),
+            (0x8, _, _, 0x1) => Instruction::Or(vx, vy),
+            (0x8, _, _, 0x2) => Instruction::And(vx, vy),
+            (0x8, _, _, 0x3) => Instruction::Xor(vx, vy),
+            (0x8, _, _, 0x4) => Instruction::AddReg(vx, vy),
+            (0x8, _, _, 0x5) => Instruction::SubReg(vx, vy),
+            (0x8, _, _, 0x6) => Instruction::ShiftRight(vx, vy),
+            (0x8, _, _, 0x7) => Instruction::SubN(vx, vy),
+            (0x8, _, _, 0xE) => Instruction::ShiftLeft(vx, vy),
+            (0x9, _, _, 0) => Instruction::SkipNotEqualReg(vx, vy),
+            (0xA, _, _, _) => Instruction::SetI(nnn),
+            (0xB, _, _, _) => Instruction::JumpReg(nnn),
+            (0xC, _, _, _) => Instruction::Rand(vx, nn),
+            (0xD, _, _, _) => Instruction::Draw(vx, vy, d),
+            (0xE, _, 0x9, 0xE) => Instruction::SkipKeyPressed(vx),
+            (0xE, _, 0xA, 0x1) => Instruction::SkipKeyNotPressed(vx),
+            (0xF, 0, 0x0, 0x2) => Instruction::LoadAudio,
+            (0xF, _, 0x0, 0x7) => Instruction::GetDelay(vx),
+            (0xF, _, 0x0, 0xA) => Instruction::WaitKey(vx),
+            (0xF, _, 0x1, 0x5) => Instruction::SetDelay(vx),
+            (0xF, _, 0x1, 0x8) => Instruction::SetSound(vx),
+            (0xF, _, 0x1, 0xE) => Instruction::AddI(vx),
+            (0xF, _, 0x2, 0x9) => Instruction::SetISprite(vx),
+            (0xF, _, 0x3, 0x3) => Instruction::Bcd(vx),
+            (0xF, _, 0x3, 0xA) => Instruction::SetPitch(vx),
+            (0xF, _, 0x5, 0x5) => Instruction::RegDump(vx),
+            (0xF, _, 0x6, 0x5) => Instruction::RegLoad(vx),
+            (0xF, _, 0x7, 0x5) => Instruction::SaveFlags(vx),
+            (0xF, _, 0x8, 0x5) => Instruction::LoadFlags(vx),
+            _ => Instruction::Unknown(opcode),
+        }
+    }
+
+    /// Splits an opcode the way a CPU in MegaChip mode reads it, where
+    /// `01NN` to `05NN` are MegaChip's instructions rather than calls to
+    /// machine code
+    ///
+    /// # Examples
+    /// ```
+    /// use chip_8::instruction::Instruction;
+    ///
+    /// assert_eq!(Instruction::decode_mega(0x0304), Instruction::SpriteWidth(4));
+    /// assert_eq!(Instruction::decode(0x0304).to_string(), "SYS 0x304");
+    /// ```
+    pub fn decode_mega(opcode: OpCode) -> Instruction {
+        let nn = (opcode & 0x00FF) as Byte;
+        match opcode >> 8 {
+            0x01 => Instruction::LongI(nn),
+            0x02 => Instruction::LoadPalette(nn),
+            0x03 => Instruction::SpriteWidth(nn),
+            0x04 => Instruction::SpriteHeight(nn),
+            0x05 => Instruction::Alpha(nn),
+            _ => Instruction::decode(opcode),
+        }
+    }
+
+    /// Packs the instruction back into its opcode, the inverse of
+    /// [`decode`](Instruction::decode)
+    ///
+    /// A sprite height too wide for its nibble is cut down to fit.
+    pub fn encode(&self) -> OpCode {
+        let xy = |c: OpCode, x: Reg, y: Reg, d: OpCode| c << 12 | (x.get() as OpCode) << 8 | (y.get() as OpCode) << 4 | d;
+        let xnn = |c: OpCode, x: Reg, nn: Byte| c << 12 | (x.get() as OpCode) << 8 | nn as OpCode;
+        let nnn = |c: OpCode, nnn: Addr| c << 12 | nnn.get();
+        let fx = |x: Reg, low: OpCode| 0xF000 | (x.get() as OpCode) << 8 | low;
+
+        match *self {
+            Instruction::Halt => 0x0000,
+            Instruction::MegaOff => 0x0010,
+            Instruction::MegaOn => 0x0011,
+            Instruction::Clear => 0x00E0,
+            Instruction::Return => 0x00EE,
+            Instruction::LowRes => 0x00FE,
+            Instruction::HighRes => 0x00FF,
+            Instruction::LongI(nn) => 0x0100 | nn as OpCode,
+            Instruction::LoadPalette(nn) => 0x0200 | nn as OpCode,
+            Instruction::SpriteWidth(nn) => 0x0300 | nn as OpCode,
+            Instruction::SpriteHeight(nn) => 0x0400 | nn as OpCode,
+            Instruction::Alpha(nn) => 0x0500 | nn as OpCode,
+            Instruction::Sys(addr) => nnn(0x0, addr),
+            Instruction::Jump(addr) => nnn(0x1, addr),
+            Instruction::Call(addr) => nnn(0x2, addr),
+            Instruction::SkipEqual(x, nn) => xnn(0x3, x, nn),
+            Instruction::SkipNotEqual(x, nn) => xnn(0x4, x, nn),
+            Instruction::SkipEqualReg(x, y) => xy(0x5, x, y, 0x0),
+            Instruction::SetRegister(x, nn) => xnn(0x6, x, nn),
+            Instruction::Add(x, nn) => xnn(0x7, x, nn),
+            Instruction::Assign(x, y) => xy(0x8, x, y, 0x0),
+            Instruction::Or(x, y) => xy(0x8, x, y, 0x1),
+            Instruction::And(x, y) => xy(0x8, x, y, 0x2),
+            Instruction::Xor(x, y) => xy(0x8, x, y, 0x3),
+            Instruction::AddReg(x, y) => xy(0x8, x, y, 0x4),
+            Instruction::SubReg(x, y) => xy(0x8, x, y, 0x5),
+            Instruction::ShiftRight(x, y) => xy(0x8, x, y, 0x6),
+            Instruction::SubN(x, y) => xy(0x8, x, y, 0x7),
+            Instruction::ShiftLeft(x, y) => xy(0x8, x, y, 0xE),
+            Instruction::SkipNotEqualReg(x, y) => xy(0x9, x, y, 0x0),
+            Instruction::SetI(addr) => nnn(0xA, addr),
+            Instruction::JumpReg(addr) => nnn(0xB, addr),
+            Instruction::Rand(x, nn) => xnn(0xC, x, nn),
+            Instruction::Draw(x, y, n) => xy(0xD, x, y, n as OpCode & 0xF),
+            Instruction::SkipKeyPressed(x) => xnn(0xE, x, 0x9E),
+            Instruction::SkipKeyNotPressed(x) => xnn(0xE, x, 0xA1),
+            Instruction::LoadAudio => 0xF002,
+            Instruction::GetDelay(x) => fx(x, 0x07),
+            Instruction::WaitKey(x) => fx(x, 0x0A),
+            Instruction::SetDelay(x) => fx(x, 0x15),
+            Instruction::SetSound(x) => fx(x, 0x18),
+            Instruction::AddI(x) => fx(x, 0x1E),
+            Instruction::SetISprite(x) => fx(x, 0x29),
+            Instruction::Bcd(x) => fx(x, 0x33),
+            Instruction::SetPitch(x) => fx(x, 0x3A),
+            Instruction::RegDump(x) => fx(x, 0x55),
+            Instruction::RegLoad(x) => fx(x, 0x65),
+            Instruction::SaveFlags(x) => fx(x, 0x75),
+            Instruction::LoadFlags(x) => fx(x, 0x85),
+            Instruction::Unknown(opcode) => opcode,
+        }
+    }
+
+    /// The opcode pattern this instruction was decoded from, like `"8XY4"`,
+    /// or `"????"` for an unknown opcode
+    ///
+    /// Every instruction with the same handler has the same pattern,
+    /// whatever its operands.
+    pub fn pattern(&self) -> &'static str {
+        match self {
+            Instruction::Halt => "0000",
+            Instruction::MegaOff => "0010",
+            Instruction::MegaOn => "0011",
+            Instruction::Clear => "00E0",
+            Instruction::Return => "00EE",
+            Instruction::LowRes => "00FE",
+            Instruction::HighRes => "00FF",
+            Instruction::LongI(_) => "01NN",
+            Instruction::LoadPalette(_) => "02NN",
+            Instruction::SpriteWidth(_) => "03NN",
+            Instruction::SpriteHeight(_) => "04NN",
+            Instruction::Alpha(_) => "05NN",
+            Instruction::Sys(_) => "0NNN",
+            Instruction::Jump(_) => "1NNN",
+            Instruction::Call(_) => "2NNN",
+            Instruction::SkipEqual(..) => "3XNN",
+            Instruction::SkipNotEqual(..) => "4XNN",
+            Instruction::SkipEqualReg(..) => "5XY0",
+            Instruction::SetRegister(..) => "6XNN",
+            Instruction::Add(..) => "7XNN",
+            Instruction::Assign(..) => "8XY0",
+            Instruction::Or(..) => "8XY1",
+            Instruction::And(..) => "8XY2",
+            Instruction::Xor(..) => "8XY3",
+            Instruction::AddReg(..) => "8XY4",
+            Instruction::SubReg(..) => "8XY5",
+            Instruction::ShiftRight(..) => "8XY6",
+            Instruction::SubN(..) => "8XY7",
+            Instruction::ShiftLeft(..) => "8XYE",
+            Instruction::SkipNotEqualReg(..) => "9XY0",
+            Instruction::SetI(_) => "ANNN",
+            Instruction::JumpReg(_) => "BNNN",
+            Instruction::Rand(..) => "CXNN",
+            Instruction::Draw(..) => "DXYN",
+            Instruction::SkipKeyPressed(_) => "EX9E",
+            Instruction::SkipKeyNotPressed(_) => "EXA1",
+            Instruction::LoadAudio => "F002",
+            Instruction::GetDelay(_) => "FX07",
+            Instruction::WaitKey(_) => "FX0A",
+            Instruction::SetDelay(_) => "FX15",
+            Instruction::SetSound(_) => "FX18",
+            Instruction::AddI(_) => "FX1E",
+            Instruction::SetISprite(_) => "FX29",
+            Instruction::Bcd(_) => "FX33",
+            Instruction::SetPitch(_) => "FX3A",
+            Instruction::RegDump(_) => "FX55",
+            Instruction::RegLoad(_) => "FX65",
+            Instruction::SaveFlags(_) => "FX75",
+            Instruction::LoadFlags(_) => "FX85",
+            Instruction::Unknown(_) => "????",
+        }
+    }
+}
+
+/// The pattern of every known instruction, in opcode order
+pub const PATTERNS: [&str; 49] = [
+    "0000",
+    "0010",
+    "0011",
+    "00E0",
+    "00EE",
+    "00FE",
+    "00FF",
+    "01NN",
+    "02NN",
+    "03NN",
+    "04NN",
+    "05NN",
+    "0NNN",
+    "1NNN",
+    "2NNN",
+    "3XNN",
+    "4XNN",
+    "5XY0",
+    "6XNN",
+    "7XNN",
+    "8XY0",
+    "8XY1",
+    "8XY2",
+    "8XY3",
+    "8XY4",
+    "8XY5",
+    "8XY6",
+    "8XY7",
+    "8XYE",
+    "9XY0",
+    "ANNN",
+    "BNNN",
+    "CXNN",
+    "DXYN",
+    "EX9E",
+    "EXA1",
+    "F002",
+    "FX07",
+    "FX0A",
+    "FX15",
+    "FX18",
+    "FX1E",
+    "FX29",
+    "FX33",
+    "FX3A",
+    "FX55",
+    "FX65",
+    "FX75",
+    "FX85",
+];
+
+/// Prints the instruction as assembly, using the common Cowgod mnemonics
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Instruction::Halt => write!(f, "HALT"),
+            Instruction::MegaOff => write!(f, "MEGAOFF"),
+            Instruction::MegaOn => write!(f, "MEGAON"),
+            Instruction::Clear => write!(f, "CLS"),
+            Instruction::Return => write!(f, "RET"),
+            Instruction::LowRes => write!(f, "LOW"),
+            Instruction::HighRes => write!(f, "HIGH"),
+            Instruction::LongI(nn) => write!(f, "LDHI 0x{:02X}", nn),
+            Instruction::LoadPalette(nn) => write!(f, "LDPAL {}", nn),
+            Instruction::SpriteWidth(nn) => write!(f, "SPRW {}", nn),
+            Instruction::SpriteHeight(nn) => write!(f, "SPRH {}", nn),
+            Instruction::Alpha(nn) => write!(f, "ALPHA 0x{:02X}", nn),
+            Instruction::Sys(nnn) => write!(f, "SYS 0x{:03X}", nnn),
+            Instruction::Jump(nnn) => write!(f, "JP 0x{:03X}", nnn),
+            Instruction::Call(nnn) => write!(f, "CALL 0x{:03X}", nnn),
+            Instruction::SkipEqual(x, nn) => write!(f, "SE V{:X}, 0x{:02X}", x, nn),
+            Instruction::SkipNotEqual(x, nn) => write!(f, "SNE V{:X}, 0x{:02X}", x, nn),
+            Instruction::SkipEqualReg(x, y) => write!(f, "SE V{:X}, V{:X}", x, y),
+            Instruction::SetRegister(x, nn) => write!(f, "LD V{:X}, 0x{:02X}", x, nn),
+            Instruction::Add(x, nn) => write!(f, "ADD V{:X}, 0x{:02X}", x, nn),
+            Instruction::Assign(x, y) => write!(f, "LD V{:X}, V{:X}", x, y),
+            Instruction::Or(x, y) => write!(f, "OR V{:X}, V{:X}", x, y),
+            Instruction::And(x, y) => write!(f, "AND V{:X}, V{:X}", x, y),
+            Instruction::Xor(x, y) => write!(f, "XOR V{:X}, V{:X}", x, y),
+            Instruction::AddReg(x, y) => write!(f, "ADD V{:X}, V{:X}", x, y),
+            Instruction::SubReg(x, y) => write!(f, "SUB V{:X}, V{:X}", x, y),
+            Instruction::ShiftRight(x, y) => write!(f, "SHR V{:X}, V{:X}", x, y),
+            Instruction::SubN(x, y) => write!(f, "SUBN V{:X}, V{:X}", x, y),
+            Instruction::ShiftLeft(x, y) => write!(f, "SHL V{:X}, V{:X}", x, y),
+            Instruction::SkipNotEqualReg(x, y) => write!(f, "SNE V{:X}, V{:X}", x, y),
+            Instruction::SetI(nnn) => write!(f, "LD I, 0x{:03X}", nnn),
+            Instruction::JumpReg(nnn) => write!(f, "JP V0, 0x{:03X}", nnn),
+            Instruction::Rand(x, nn) => write!(f, "RND V{:X}, 0x{:02X}", x, nn),
+            Instruction::Draw(x, y, n) => write!(f, "DRW V{:X}, V{:X}, {}", x, y, n),
+            Instruction::SkipKeyPressed(x) => write!(f, "SKP V{:X}", x),
+            Instruction::SkipKeyNotPressed(x) => write!(f, "SKNP V{:X}", x),
+            Instruction::LoadAudio => write!(f, "AUDIO"),
+            Instruction::GetDelay(x) => write!(f, "LD V{:X}, DT", x),
+            Instruction::WaitKey(x) => write!(f, "LD V{:X}, K", x),
+            Instruction::SetDelay(x) => write!(f, "LD DT, V{:X}", x),
+            Instruction::SetSound(x) => write!(f, "LD ST, V{:X}", x),
+            Instruction::AddI(x) => write!(f, "ADD I, V{:X}", x),
+            Instruction::SetISprite(x) => write!(f, "LD F, V{:X}", x),
+            Instruction::Bcd(x) => write!(f, "LD B, V{:X}", x),
+            Instruction::SetPitch(x) => write!(f, "PITCH V{:X}", x),
+            Instruction::RegDump(x) => write!(f, "LD [I], V{:X}", x),
+            Instruction::RegLoad(x) => write!(f, "LD V{:X}, [I]", x),
+            Instruction::SaveFlags(x) => write!(f, "LD R, V{:X}", x),
+            Instruction::LoadFlags(x) => write!(f, "LD V{:X}, R", x),
+            Instruction::Unknown(opcode) => write!(f, "DW 0x{:04X}", opcode),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_splits_operands() {
+        assert_eq!(Instruction::decode(0x0000), Instruction::Halt);
+        assert_eq!(Instruction::decode(0x00EE), Instruction::Return);
+        assert_eq!(Instruction::decode(0x00FF), Instruction::HighRes);
+        assert_eq!(Instruction::decode(0x0011), Instruction::MegaOn);
+        let v = Reg::nibble;
+        assert_eq!(Instruction::decode(0x1234), Instruction::Jump(Addr::nnn(0x234)));
+        assert_eq!(Instruction::decode(0x3A7F), Instruction::SkipEqual(v(0xA), 0x7F));
+        assert_eq!(Instruction::decode(0x8CDE), Instruction::ShiftLeft(v(0xC), v(0xD)));
+        assert_eq!(Instruction::decode(0xD125), Instruction::Draw(v(1), v(2), 5));
+        assert_eq!(Instruction::decode(0xF965), Instruction::RegLoad(v(9)));
+        assert_eq!(Instruction::decode(0xF775), Instruction::SaveFlags(v(7)));
+        assert_eq!(Instruction::decode(0xF385), Instruction::LoadFlags(v(3)));
+        assert_eq!(Instruction::decode(0xF002), Instruction::LoadAudio);
+        assert_eq!(Instruction::decode(0xF43A), Instruction::SetPitch(v(4)));
+    }
+
+    #[test]
+    fn decode_marks_unknown_opcodes() {
+        assert_eq!(Instruction::decode(0x8008), Instruction::Unknown(0x8008));
+        assert_eq!(Instruction::decode(0x9001), Instruction::Unknown(0x9001));
+        assert_eq!(Instruction::decode(0xE000), Instruction::Unknown(0xE000));
+        assert_eq!(Instruction::decode(0xFFFF), Instruction::Unknown(0xFFFF));
+        assert_eq!(Instruction::decode(0xF102), Instruction::Unknown(0xF102));
+    }
+
+    #[test]
+    fn decode_mega_reads_megachip_instructions() {
+        assert_eq!(Instruction::decode_mega(0x0112), Instruction::LongI(0x12));
+        assert_eq!(Instruction::decode_mega(0x0210), Instruction::LoadPalette(0x10));
+        assert_eq!(Instruction::decode_mega(0x0400), Instruction::SpriteHeight(0));
+        assert_eq!(Instruction::decode_mega(0x05C0), Instruction::Alpha(0xC0));
+        assert_eq!(Instruction::decode_mega(0x0010), Instruction::MegaOff);
+        assert_eq!(Instruction::decode_mega(0x0623), Instruction::Sys(Addr::nnn(0x623)));
+        assert_eq!(Instruction::decode(0x0210), Instruction::Sys(Addr::nnn(0x210)));
+        assert_eq!(Instruction::decode_mega(0x0112).to_string(), "LDHI 0x12");
+    }
+
+    #[test]
+    fn display_prints_assembly() {
+        assert_eq!(Instruction::decode(0x00E0).to_string(), "CLS");
+        assert_eq!(Instruction::decode(0x2300).to_string(), "CALL 0x300");
+        assert_eq!(Instruction::decode(0x6A02).to_string(), "LD VA, 0x02");
+        assert_eq!(Instruction::decode(0xB200).to_string(), "JP V0, 0x200");
+        assert_eq!(Instruction::decode(0xD01F).to_string(), "DRW V0, V1, 15");
+        assert_eq!(Instruction::decode(0xF355).to_string(), "LD [I], V3");
+        assert_eq!(Instruction::decode(0xF0FF).to_string(), "DW 0xF0FF");
+    }
+
+    #[test]
+    fn encode_undoes_decode() {
+        for opcode in [0x0000, 0x00E0, 0x00FE, 0x0123, 0x2300, 0x5AB0, 0x8CDE, 0xB200, 0xD01F, 0xE3A1, 0xF002, 0xF23A, 0xF355, 0xF0FF] {
+            assert_eq!(Instruction::decode(opcode).encode(), opcode);
+        }
+        for opcode in [0x0011, 0x0134, 0x0203, 0x0300, 0x05FF] {
+            assert_eq!(Instruction::decode_mega(opcode).encode(), opcode);
+        }
+        assert_eq!(Instruction::Draw(Reg::V0, Reg::VF, 0x1F).encode(), 0xD0FF);
+    }
+
+    #[test]
+    fn pattern_matches_decode() {
+        for pattern in PATTERNS.iter() {
+            let opcode = pattern.replace(['X', 'Y', 'N'], "A");
+            let opcode = OpCode::from_str_radix(&opcode, 16).unwrap();
+            assert_eq!(Instruction::decode_mega(opcode).pattern(), *pattern);
+        }
+        assert_eq!(Instruction::decode(0xFFFF).pattern(), "????");
+    }
+}
@@ -0,0 +1,710 @@
+//! Which keys on the host keyboard press which CHIP-8 keys
+//!
+//! Host keys are named the way frontends print them, e.g. `"X"`, `"1"` or
+//! `"Up"`, and compared ignoring case. By default a name is where the key
+//! sits, named for the key in that place on a US QWERTY keyboard, so the
+//! keypad stays on the same 4x4 block on AZERTY or Dvorak keyboards; see
+//! [`KeyInput`]. A map file binds names to CHIP-8 keys:
+//!
+//! ```toml
+//! # host key = CHIP-8 key
+//! 1 = 0x1
+//! Q = 0x4
+//! Up = 0x2
+//! ```
+//!
+//! or the same thing as JSON, `{ "1": 1, "Q": 4, "Up": 2 }`.
+//!
+//! A host key can instead play a [`KeyMacro`], a timed sequence of CHIP-8
+//! keys, written as a string:
+//!
+//! ```toml
+//! T = "press(5, 10f); wait(30f); press(8, 10f)"
+//! ```
+//!
+//! Pressing T then holds 5 for ten frames, waits half a second and holds 8
+//! for ten frames. Frontends hand macros to a [`MacroPlayer`], which plays
+//! them a 60Hz frame at a time alongside the emulator.
+//!
+//! An `autofire` table makes CHIP-8 keys fire over and over while held, so
+//! many presses a second, through [`Autofire`]:
+//!
+//! ```toml
+//! [autofire]
+//! 5 = 10
+//! ```
+//!
+//! Frontends that draw the keypad on screen, for touchscreens, show the
+//! keys in a [`KeypadLayout`]. A `keypad` list trims it down to the keys a
+//! game uses:
+//!
+//! ```toml
+//! keypad = [0x2, 0x4, 0x6, 0x8, 0x5]
+//! ```
+//!
+//! and `input = "character"` names keys by what they type instead.
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+use serde_json::Value;
+
+use crate::{Autofire, Byte, MAX_AUTOFIRE_HZ};
+
+/// The COSMAC VIP's hex keypad laid over the left of a QWERTY keyboard
+///
+/// ```text
+/// 1 2 3 C      1 2 3 4
+/// 4 5 6 D  ->  Q W E R
+/// 7 8 9 E      A S D F
+/// A 0 B F      Z X C V
+/// ```
+const QWERTY: [(&str, Byte); 16] = [
+    ("1", 0x1), ("2", 0x2), ("3", 0x3), ("4", 0xC),
+    ("Q", 0x4), ("W", 0x5), ("E", 0x6), ("R", 0xD),
+    ("A", 0x7), ("S", 0x8), ("D", 0x9), ("F", 0xE),
+    ("Z", 0xA), ("X", 0x0), ("C", 0xB), ("V", 0xF),
+];
+
+/// The same physical keys as [`QWERTY`] on a French keyboard, by the
+/// characters they type
+const AZERTY: [(&str, Byte); 16] = [
+    ("1", 0x1), ("2", 0x2), ("3", 0x3), ("4", 0xC),
+    ("A", 0x4), ("Z", 0x5), ("E", 0x6), ("R", 0xD),
+    ("Q", 0x7), ("S", 0x8), ("D", 0x9), ("F", 0xE),
+    ("W", 0xA), ("X", 0x0), ("C", 0xB), ("V", 0xF),
+];
+
+/// Arrow keys for the 2/4/6/8 directions most games use, space for 5
+const ARROWS: [(&str, Byte); 5] = [
+    ("Up", 0x2), ("Left", 0x4), ("Right", 0x6), ("Down", 0x8), ("Space", 0x5),
+];
+
+/// The COSMAC VIP's keypad, top row first
+pub const KEYPAD_ROWS: [[Byte; 4]; 4] = [
+    [0x1, 0x2, 0x3, 0xC],
+    [0x4, 0x5, 0x6, 0xD],
+    [0x7, 0x8, 0x9, 0xE],
+    [0xA, 0x0, 0xB, 0xF],
+];
+
+/// How frontends name the host keys a [`KeyMap`] binds
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum KeyInput {
+    /// By where the key is, as the key in that place on a US QWERTY
+    /// keyboard: the top left letter is `Q` whatever it types
+    #[default]
+    Physical,
+    /// By the character the key types in the player's layout
+    Character,
+}
+
+impl KeyInput {
+    /// Looks up a mode by name, `physical` (or `scancode`) or `character`
+    pub fn from_name(name: &str) -> Option<KeyInput> {
+        match name.to_lowercase().as_str() {
+            "physical" | "scancode" => Some(KeyInput::Physical),
+            "character" => Some(KeyInput::Character),
+            _ => None,
+        }
+    }
+}
+
+/// The name a [`KeyMap`] knows a physical key by, from its W3C `code` as
+/// winit and Bevy print theirs
+///
+/// `KeyQ` is just `Q`, `Digit1` is `1` and `ArrowUp` is `Up`, matching
+/// what the same keys type on a US keyboard.
+///
+/// # Examples
+/// ```
+/// use chip_8::keymap::code_name;
+///
+/// assert_eq!(code_name("KeyQ"), "Q");
+/// assert_eq!(code_name("BracketLeft"), "LeftBracket");
+/// assert_eq!(code_name("F1"), "F1");
+/// ```
+pub fn code_name(code: &str) -> String {
+    match code {
+        "BracketLeft" => String::from("LeftBracket"),
+        "BracketRight" => String::from("RightBracket"),
+        _ => ["Key", "Digit", "Arrow"]
+            .iter()
+            .find_map(|prefix| code.strip_prefix(prefix))
+            .map(String::from)
+            .unwrap_or_else(|| String::from(code)),
+    }
+}
+
+/// Maps host keys to the 16 CHIP-8 keys
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyMap {
+    keys: HashMap<String, Byte>,
+    input: KeyInput,
+    macros: HashMap<String, KeyMacro>,
+    autofire: Autofire,
+    keypad: KeypadLayout,
+}
+
+impl Default for KeyMap {
+    fn default() -> Self {
+        Self::qwerty()
+    }
+}
+
+impl KeyMap {
+    /// Makes a KeyMap with nothing bound
+    pub fn new() -> KeyMap {
+        KeyMap {
+            keys: HashMap::new(),
+            input: KeyInput::default(),
+            macros: HashMap::new(),
+            autofire: Autofire::new(),
+            keypad: KeypadLayout::full(),
+        }
+    }
+
+    /// The usual layout, the keypad on 1-4, Q-R, A-F and Z-V
+    pub fn qwerty() -> KeyMap {
+        let mut map = KeyMap::new();
+        for (host, key) in QWERTY.iter() {
+            map.bind(host, *key);
+        }
+        map
+    }
+
+    /// Looks up one of the built in layouts: `qwerty`, `azerty` or `arrows`
+    ///
+    /// `arrows` is the QWERTY layout plus the arrow keys and space, for
+    /// games that move with 2, 4, 6 and 8, and shows just those keys on an
+    /// on-screen keypad. `azerty` goes by [`KeyInput::Character`], for
+    /// frontends that can't tell where keys are.
+    ///
+    /// # Examples
+    /// ```
+    /// use chip_8::KeyMap;
+    ///
+    /// let map = KeyMap::preset("arrows").unwrap();
+    /// assert_eq!(map.get("up"), Some(0x2));
+    /// assert_eq!(map.get("x"), Some(0x0));
+    /// ```
+    pub fn preset(name: &str) -> Option<KeyMap> {
+        let bindings: Vec<&(&str, Byte)> = match name.to_lowercase().as_str() {
+            "qwerty" => QWERTY.iter().collect(),
+            "azerty" => AZERTY.iter().collect(),
+            "arrows" => QWERTY.iter().chain(ARROWS.iter()).collect(),
+            _ => return None,
+        };
+
+        let mut map = KeyMap::new();
+        for (host, key) in bindings {
+            map.bind(host, *key);
+        }
+        if name.eq_ignore_ascii_case("arrows") {
+            map.set_keypad(KeypadLayout::only(&[0x2, 0x4, 0x5, 0x6, 0x8]));
+        }
+        if name.eq_ignore_ascii_case("azerty") {
+            map.set_input(KeyInput::Character);
+        }
+        Some(map)
+    }
+
+    /// Reads a map file, as JSON if the name ends in `.json` and TOML otherwise
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<KeyMap, Box<dyn Error>> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)?;
+
+        // keys are numbers, macros strings, autofire a table and the keypad
+        // a list, so both formats are read as JSON values
+        let bindings: HashMap<String, Value> = match path.extension() {
+            Some(ext) if ext == "json" => serde_json::from_str(&text)?,
+            _ => toml::from_str(&text)?,
+        };
+
+        let mut map = KeyMap::new();
+        for (host, value) in bindings {
+            match value {
+                Value::Object(rates) if host == "autofire" => {
+                    for (key, hz) in rates {
+                        let digits = key.strip_prefix("0x").unwrap_or(&key);
+                        let key = match Byte::from_str_radix(digits, 16) {
+                            Ok(key) if key <= 0xF => key,
+                            _ => return Err(format!("autofire is set for {}, which isn't a CHIP-8 key", key).into()),
+                        };
+                        let hz = hz.as_u64().ok_or_else(|| format!("autofire for {:X} should be presses a second", key))?;
+                        map.set_autofire(key, Some(hz.min(MAX_AUTOFIRE_HZ as u64) as u32));
+                    }
+                }
+                Value::Array(keys) if host == "keypad" => {
+                    let mut shown = Vec::new();
+                    for key in keys {
+                        match key.as_u64() {
+                            Some(key @ 0..=0xF) => shown.push(key as Byte),
+                            _ => return Err(format!("the keypad lists {}, which isn't a CHIP-8 key", key).into()),
+                        }
+                    }
+                    map.set_keypad(KeypadLayout::only(&shown));
+                }
+                Value::String(input) if host == "input" => {
+                    let input = KeyInput::from_name(&input)
+                        .ok_or_else(|| format!("input should be physical or character, not {}", input))?;
+                    map.set_input(input);
+                }
+                Value::String(key_macro) => {
+                    let key_macro = key_macro.parse().map_err(|err| format!("{}'s macro: {}", host, err))?;
+                    map.bind_macro(&host, key_macro);
+                }
+                value => match value.as_i64() {
+                    Some(key @ 0..=0xF) => {
+                        map.bind(&host, key as Byte);
+                    }
+                    Some(key) => return Err(format!("{} is bound to 0x{:X}, which isn't a CHIP-8 key", host, key).into()),
+                    None => return Err(format!("{} should be bound to a key or a macro", host).into()),
+                },
+            }
+        }
+        Ok(map)
+    }
+
+    /// Binds a host key to a CHIP-8 key, replacing whatever it pressed or
+    /// played before
+    ///
+    /// # Panics
+    ///
+    /// Panics if `key` is over 0xF
+    pub fn bind(&mut self, host: &str, key: Byte) -> &mut KeyMap {
+        assert!(key <= 0xF, "Not a CHIP-8 key: 0x{:X}", key);
+        let host = host.to_lowercase();
+        self.macros.remove(&host);
+        self.keys.insert(host, key);
+        self
+    }
+
+    /// The CHIP-8 key a host key presses, if it's bound
+    pub fn get(&self, host: &str) -> Option<Byte> {
+        self.keys.get(&host.to_lowercase()).copied()
+    }
+
+    /// Binds a host key to a macro, in place of any key it pressed before
+    pub fn bind_macro(&mut self, host: &str, key_macro: KeyMacro) -> &mut KeyMap {
+        let host = host.to_lowercase();
+        self.keys.remove(&host);
+        self.macros.insert(host, key_macro);
+        self
+    }
+
+    /// The macro a host key plays, if it's bound to one
+    pub fn get_macro(&self, host: &str) -> Option<&KeyMacro> {
+        self.macros.get(&host.to_lowercase())
+    }
+
+    /// Sets whether host keys are named by where they are or what they type
+    pub fn set_input(&mut self, input: KeyInput) -> &mut KeyMap {
+        self.input = input;
+        self
+    }
+
+    /// How frontends should name host keys for this map
+    pub fn input(&self) -> KeyInput {
+        self.input
+    }
+
+    /// Fires CHIP-8 key `key` `hz` times a second while it's held, or stops
+    /// it autofiring with `None`; see [`Autofire::set_rate`]
+    pub fn set_autofire(&mut self, key: Byte, hz: Option<u32>) -> &mut KeyMap {
+        self.autofire.set_rate(key, hz);
+        self
+    }
+
+    /// Which keys autofire and how fast, for whatever runs the frames
+    pub fn autofire(&self) -> Autofire {
+        self.autofire
+    }
+
+    /// Sets which keys an on-screen keypad shows
+    pub fn set_keypad(&mut self, layout: KeypadLayout) -> &mut KeyMap {
+        self.keypad = layout;
+        self
+    }
+
+    /// Which keys an on-screen keypad shows, all of them unless the map
+    /// says otherwise
+    pub fn keypad(&self) -> KeypadLayout {
+        self.keypad
+    }
+}
+
+/// Which keys an on-screen keypad shows
+///
+/// Keys keep their places from [`KEYPAD_ROWS`], so 2, 4, 6 and 8 still
+/// look like a d-pad, but rows and columns with nothing shown are left
+/// out to save room.
+///
+/// # Examples
+/// ```
+/// use chip_8::keymap::KeypadLayout;
+///
+/// let layout = KeypadLayout::only(&[0x2, 0x4, 0x6, 0x8]);
+/// assert_eq!(layout.rows(), [
+///     vec![None, Some(0x2), None],
+///     vec![Some(0x4), None, Some(0x6)],
+///     vec![None, Some(0x8), None],
+/// ]);
+/// assert_eq!(KeypadLayout::full().rows()[3], [Some(0xA), Some(0x0), Some(0xB), Some(0xF)]);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeypadLayout {
+    shown: [bool; 16],
+}
+
+impl Default for KeypadLayout {
+    fn default() -> Self {
+        Self::full()
+    }
+}
+
+impl KeypadLayout {
+    /// All 16 keys
+    pub fn full() -> KeypadLayout {
+        KeypadLayout { shown: [true; 16] }
+    }
+
+    /// Just `keys`
+    ///
+    /// # Panics
+    ///
+    /// Panics if a key is over 0xF
+    pub fn only(keys: &[Byte]) -> KeypadLayout {
+        let mut shown = [false; 16];
+        for &key in keys {
+            assert!(key <= 0xF, "Not a CHIP-8 key: 0x{:X}", key);
+            shown[key as usize] = true;
+        }
+        KeypadLayout { shown }
+    }
+
+    pub fn shows(&self, key: Byte) -> bool {
+        self.shown[(key & 0xF) as usize]
+    }
+
+    /// The keys to draw, a row at a time from the top, with `None` for a
+    /// gap
+    pub fn rows(&self) -> Vec<Vec<Option<Byte>>> {
+        let columns: Vec<usize> = (0..4).filter(|&col| KEYPAD_ROWS.iter().any(|row| self.shows(row[col]))).collect();
+        KEYPAD_ROWS
+            .iter()
+            .filter(|row| row.iter().any(|&key| self.shows(key)))
+            .map(|row| columns.iter().map(|&col| Some(row[col]).filter(|&key| self.shows(key))).collect())
+            .collect()
+    }
+}
+
+/// One step of a [`KeyMacro`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MacroStep {
+    /// Holds `key` down for `frames` frames, then lets it go
+    Press { key: Byte, frames: u32 },
+    /// Does nothing for `frames` frames
+    Wait { frames: u32 },
+}
+
+impl MacroStep {
+    fn frames(self) -> u32 {
+        match self {
+            MacroStep::Press { frames, .. } | MacroStep::Wait { frames } => frames,
+        }
+    }
+}
+
+/// A timed sequence of key presses, played by a [`MacroPlayer`]
+///
+/// Written as steps separated by `;`: `press(KEY, FRAMES)` holds a key,
+/// given in hex, and `wait(FRAMES)` pauses, frames being 60ths of a second
+/// and optionally followed by `f`.
+///
+/// # Examples
+/// ```
+/// use chip_8::keymap::{KeyMacro, MacroStep};
+///
+/// let key_macro: KeyMacro = "press(5, 10f); wait(30f); press(A, 1)".parse().unwrap();
+/// assert_eq!(key_macro.steps()[2], MacroStep::Press { key: 0xA, frames: 1 });
+/// assert_eq!(key_macro.frames(), 41);
+/// assert!("press(5)".parse::<KeyMacro>().is_err());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct KeyMacro {
+    steps: Vec<MacroStep>,
+}
+
+impl KeyMacro {
+    /// A macro playing `steps` in order
+    ///
+    /// # Panics
+    ///
+    /// Panics if a step presses a key over 0xF
+    pub fn new(steps: Vec<MacroStep>) -> KeyMacro {
+        for step in &steps {
+            if let MacroStep::Press { key, .. } = *step {
+                assert!(key <= 0xF, "Not a CHIP-8 key: 0x{:X}", key);
+            }
+        }
+        KeyMacro { steps }
+    }
+
+    pub fn steps(&self) -> &[MacroStep] {
+        &self.steps
+    }
+
+    /// How many frames the whole macro takes
+    pub fn frames(&self) -> u32 {
+        self.steps.iter().map(|step| step.frames()).sum()
+    }
+
+    /// Which step is playing `frame` frames in, and the key it holds, if
+    /// it's a press
+    fn key_at(&self, mut frame: u32) -> Option<(usize, Byte)> {
+        for (index, step) in self.steps.iter().enumerate() {
+            if frame < step.frames() {
+                return match *step {
+                    MacroStep::Press { key, .. } => Some((index, key)),
+                    MacroStep::Wait { .. } => None,
+                };
+            }
+            frame -= step.frames();
+        }
+        None
+    }
+}
+
+impl FromStr for KeyMacro {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<KeyMacro, String> {
+        let frames = |arg: &str| {
+            let digits = arg.strip_suffix('f').unwrap_or(arg);
+            digits.parse().map_err(|_| format!("`{}` isn't a number of frames", arg))
+        };
+
+        let mut steps = Vec::new();
+        for step in text.split(';').map(str::trim).filter(|step| !step.is_empty()) {
+            let (name, args) = step
+                .strip_suffix(')')
+                .and_then(|step| step.split_once('('))
+                .ok_or_else(|| format!("`{}` should look like `press(5, 10f)` or `wait(30f)`", step))?;
+            let args: Vec<&str> = args.split(',').map(str::trim).collect();
+
+            steps.push(match (name.trim(), args.as_slice()) {
+                ("press", [key, length]) => {
+                    let digits = key.strip_prefix("0x").unwrap_or(key);
+                    let key = match Byte::from_str_radix(digits, 16) {
+                        Ok(key) if key <= 0xF => key,
+                        _ => return Err(format!("`{}` isn't a CHIP-8 key", key)),
+                    };
+                    MacroStep::Press { key, frames: frames(length)? }
+                }
+                ("wait", [length]) => MacroStep::Wait { frames: frames(length)? },
+                _ => return Err(format!("`{}` should look like `press(5, 10f)` or `wait(30f)`", step)),
+            });
+        }
+        Ok(KeyMacro { steps })
+    }
+}
+
+impl fmt::Display for KeyMacro {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let steps: Vec<String> = self
+            .steps
+            .iter()
+            .map(|step| match step {
+                MacroStep::Press { key, frames } => format!("press({:X}, {}f)", key, frames),
+                MacroStep::Wait { frames } => format!("wait({}f)", frames),
+            })
+            .collect();
+        write!(f, "{}", steps.join("; "))
+    }
+}
+
+/// A macro part way through playing
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Playing {
+    key_macro: KeyMacro,
+    // the next frame to play, counting from 0
+    frame: u32,
+    // the step and key held down as of the last frame
+    held: Option<(usize, Byte)>,
+}
+
+/// Plays [`KeyMacro`]s, turning each frame into the key presses and
+/// releases due in it
+///
+/// Call [`frame`](MacroPlayer::frame) once before each emulated frame and
+/// pass what it returns to the CPU. Macros started together play over each
+/// other.
+///
+/// # Examples
+/// ```
+/// use chip_8::keymap::MacroPlayer;
+///
+/// let mut player = MacroPlayer::new();
+/// player.play("press(5, 2f); press(8, 1f)".parse().unwrap());
+///
+/// assert_eq!(player.frame(), [(0x5, true)]);
+/// assert_eq!(player.frame(), []);
+/// assert_eq!(player.frame(), [(0x5, false), (0x8, true)]);
+/// assert_eq!(player.frame(), [(0x8, false)]);
+/// assert!(!player.is_playing());
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MacroPlayer {
+    playing: Vec<Playing>,
+}
+
+impl MacroPlayer {
+    pub fn new() -> MacroPlayer {
+        MacroPlayer::default()
+    }
+
+    /// Starts playing `key_macro` from the next frame
+    pub fn play(&mut self, key_macro: KeyMacro) {
+        self.playing.push(Playing { key_macro, frame: 0, held: None });
+    }
+
+    /// Whether any macro is still playing
+    pub fn is_playing(&self) -> bool {
+        !self.playing.is_empty()
+    }
+
+    /// Moves every macro on a frame, returning the CHIP-8 keys to press
+    /// (`true`) or release (`false`) for it, in order
+    pub fn frame(&mut self) -> Vec<(Byte, bool)> {
+        let mut keys = Vec::new();
+        for playing in &mut self.playing {
+            let now = playing.key_macro.key_at(playing.frame);
+            if now != playing.held {
+                if let Some((_, key)) = playing.held {
+                    keys.push((key, false));
+                }
+                if let Some((_, key)) = now {
+                    keys.push((key, true));
+                }
+                playing.held = now;
+            }
+            playing.frame += 1;
+        }
+        // a macro is done once the frame after its last has let go
+        self.playing.retain(|playing| playing.frame <= playing.key_macro.frames());
+        keys
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    #[test]
+    fn qwerty_binds_all_sixteen_keys() {
+        let map = KeyMap::qwerty();
+        let mut keys: Vec<Byte> = map.keys.values().copied().collect();
+        keys.sort_unstable();
+
+        assert_eq!(keys, (0..16).collect::<Vec<Byte>>());
+        assert_eq!(map.get("V"), Some(0xF));
+        assert_eq!(map.get("P"), None);
+    }
+
+    #[test]
+    fn presets_are_case_insensitive() {
+        assert_eq!(KeyMap::preset("AZERTY").unwrap().get("w"), Some(0xA));
+        assert!(KeyMap::preset("dvorak").is_none());
+    }
+
+    #[test]
+    fn load_reads_toml_and_json() {
+        let dir = env::temp_dir();
+        let toml_path = dir.join(format!("chip_8_keymap_{}.toml", std::process::id()));
+        let json_path = dir.join(format!("chip_8_keymap_{}.json", std::process::id()));
+        fs::write(&toml_path, "1 = 0x1\nUp = 0xA\n").unwrap();
+        fs::write(&json_path, r#"{ "1": 1, "Up": 10 }"#).unwrap();
+
+        let from_toml = KeyMap::load(&toml_path).unwrap();
+        let from_json = KeyMap::load(&json_path).unwrap();
+        fs::remove_file(toml_path).unwrap();
+        fs::remove_file(json_path).unwrap();
+
+        assert_eq!(from_toml.get("up"), Some(0xA));
+        assert_eq!(from_toml, from_json);
+    }
+
+    #[test]
+    fn load_reads_macros_and_autofire() {
+        let path = env::temp_dir().join(format!("chip_8_macro_keymap_{}.toml", std::process::id()));
+        fs::write(&path, "1 = 0x1\nT = \"press(5,10f); wait(30f); press(8,10f)\"\n[autofire]\nA = 12\n").unwrap();
+
+        let map = KeyMap::load(&path).unwrap();
+        fs::remove_file(path).unwrap();
+
+        let key_macro = map.get_macro("t").unwrap();
+        assert_eq!(key_macro.to_string(), "press(5, 10f); wait(30f); press(8, 10f)");
+        assert_eq!((map.get("t"), map.get("1")), (None, Some(0x1)));
+        assert_eq!((map.autofire().rate(0xA), map.autofire().rate(0x5)), (Some(12), None));
+    }
+
+    #[test]
+    fn load_reads_the_input_mode() {
+        let path = env::temp_dir().join(format!("chip_8_input_keymap_{}.toml", std::process::id()));
+        fs::write(&path, "input = \"Character\"\nW = 0x5\n").unwrap();
+
+        let map = KeyMap::load(&path).unwrap();
+        fs::write(&path, "input = \"dvorak\"\n").unwrap();
+        let bad = KeyMap::load(&path);
+        fs::remove_file(path).unwrap();
+
+        assert_eq!((map.input(), map.get("w")), (KeyInput::Character, Some(0x5)));
+        assert!(map.get_macro("input").is_none());
+        assert!(bad.is_err());
+        assert_eq!(KeyMap::qwerty().input(), KeyInput::Physical);
+        assert_eq!(KeyMap::preset("azerty").unwrap().input(), KeyInput::Character);
+    }
+
+    #[test]
+    fn load_reads_the_keypad_layout() {
+        let path = env::temp_dir().join(format!("chip_8_keypad_keymap_{}.toml", std::process::id()));
+        fs::write(&path, "keypad = [0x4, 0x6]\n").unwrap();
+
+        let map = KeyMap::load(&path).unwrap();
+        fs::write(&path, "keypad = [0x4, 0x10]\n").unwrap();
+        let bad = KeyMap::load(&path);
+        fs::remove_file(path).unwrap();
+
+        assert_eq!(map.keypad().rows(), [vec![Some(0x4), Some(0x6)]]);
+        assert_eq!(map.get("keypad"), None);
+        assert!(bad.is_err());
+        assert_eq!(KeyMap::qwerty().keypad(), KeypadLayout::full());
+    }
+
+    #[test]
+    fn macros_release_between_presses_of_the_same_key() {
+        let mut player = MacroPlayer::new();
+        player.play("press(5, 1f); press(5, 1f); wait(1f)".parse().unwrap());
+
+        let frames: Vec<Vec<(Byte, bool)>> = (0..4).map(|_| player.frame()).collect();
+        assert_eq!(frames, [vec![(5, true)], vec![(5, false), (5, true)], vec![(5, false)], vec![]]);
+        assert!(!player.is_playing());
+    }
+
+    #[test]
+    fn load_rejects_keys_past_f() {
+        let path = env::temp_dir().join(format!("chip_8_bad_keymap_{}.toml", std::process::id()));
+        fs::write(&path, "X = 16\n").unwrap();
+
+        let result = KeyMap::load(&path);
+        fs::remove_file(path).unwrap();
+
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,159 @@
+//! A directory of ROMs to pick a game from
+//!
+//! `chip_8 --library DIR` lists the ROMs in a directory by title, taking
+//! titles from the [`rom_db`] where it knows the ROM and from the file name
+//! otherwise, and boots whichever one is picked.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use chip_8::library::Library;
+//!
+//! let mut library = Library::scan("roms").unwrap();
+//! library.select_next();
+//! for line in library.lines(10) {
+//!     println!("{}", line);
+//! }
+//! ```
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::{rom_db, rom_file};
+
+/// The file extensions listed: ROMs, zips of them, and Octo source for `8o`
+pub const ROM_EXTENSIONS: &[&str] = &["ch8", "c8", "sc8", "xo8", "mc8", "zip", "8o"];
+
+/// One game in a [`Library`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LibraryRom {
+    pub path: PathBuf,
+    pub title: String,
+}
+
+/// The ROMs in a directory, sorted by title, and which one is picked
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Library {
+    roms: Vec<LibraryRom>,
+    selected: usize,
+}
+
+impl Library {
+    /// Lists the ROMs in `dir`, leaving out anything in subdirectories
+    pub fn scan<P: AsRef<Path>>(dir: P) -> io::Result<Library> {
+        let mut roms = Vec::new();
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            let is_rom = path
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .is_some_and(|ext| ROM_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()));
+            if !is_rom || !path.is_file() {
+                continue;
+            }
+
+            let stem = path.file_stem().map(|stem| stem.to_string_lossy().into_owned()).unwrap_or_default();
+            let title = rom_file::read(&path)
+                .ok()
+                .and_then(|rom| rom_db::lookup(&rom))
+                .map(|entry| entry.title.to_string())
+                .unwrap_or(stem);
+            roms.push(LibraryRom { path, title });
+        }
+
+        roms.sort_by_key(|rom| (rom.title.to_lowercase(), rom.path.clone()));
+        Ok(Library { roms, selected: 0 })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.roms.is_empty()
+    }
+
+    /// Every ROM, in the order they're listed
+    pub fn roms(&self) -> &[LibraryRom] {
+        &self.roms
+    }
+
+    /// The ROM picked at the moment, if there are any
+    pub fn selected(&self) -> Option<&LibraryRom> {
+        self.roms.get(self.selected)
+    }
+
+    /// Picks the ROM below, going round to the top after the last one
+    pub fn select_next(&mut self) {
+        if !self.roms.is_empty() {
+            self.selected = (self.selected + 1) % self.roms.len();
+        }
+    }
+
+    /// Picks the ROM above, going round to the bottom before the first one
+    pub fn select_previous(&mut self) {
+        if !self.roms.is_empty() {
+            self.selected = (self.selected + self.roms.len() - 1) % self.roms.len();
+        }
+    }
+
+    /// Up to `count` titles around the picked ROM, which is marked with `>`
+    pub fn lines(&self, count: usize) -> Vec<String> {
+        // scrolls once the picked ROM gets past the middle
+        let first = self.selected.saturating_sub(count / 2).min(self.roms.len().saturating_sub(count));
+        self.roms
+            .iter()
+            .enumerate()
+            .skip(first)
+            .take(count)
+            .map(|(ind, rom)| format!("{} {}", if ind == self.selected { ">" } else { " " }, rom.title))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    fn library(titles: &[&str]) -> Library {
+        let roms = titles.iter().map(|title| LibraryRom { path: PathBuf::from(title), title: title.to_string() });
+        Library { roms: roms.collect(), selected: 0 }
+    }
+
+    #[test]
+    fn scan_titles_known_roms_and_skips_other_files() {
+        let dir = env::temp_dir().join(format!("chip_8_library_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("zz.ch8"), include_bytes!("../roms/sierpinski.ch8")).unwrap();
+        fs::write(dir.join("blinky.CH8"), [0x12, 0x00]).unwrap();
+        fs::write(dir.join("notes.txt"), "not a game").unwrap();
+
+        let library = Library::scan(&dir).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        let titles: Vec<_> = library.roms().iter().map(|rom| rom.title.as_str()).collect();
+        assert_eq!(titles, ["blinky", "Sierpinski"]);
+        assert_eq!(library.selected().unwrap().path, dir.join("blinky.CH8"));
+    }
+
+    #[test]
+    fn selection_wraps_around() {
+        let mut library = library(&["a", "b", "c"]);
+        library.select_previous();
+        assert_eq!(library.selected().unwrap().title, "c");
+        library.select_next();
+        assert_eq!(library.selected().unwrap().title, "a");
+
+        assert_eq!(Library::default().selected(), None);
+    }
+
+    #[test]
+    fn lines_scroll_to_keep_the_selection_in_view() {
+        let mut library = library(&["a", "b", "c", "d", "e"]);
+        assert_eq!(library.lines(3), ["> a", "  b", "  c"]);
+
+        for _ in 0..3 {
+            library.select_next();
+        }
+        assert_eq!(library.lines(3), ["  c", "> d", "  e"]);
+        assert_eq!(library.lines(10).len(), 5);
+    }
+}
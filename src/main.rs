@@ -1,29 +1,619 @@
+// the library's logging macros, for the frontend's own events
+#[macro_use]
+#[allow(unused_macros)]
+mod logging;
+
+// built without either window, the game can only say it has nowhere to run
+#[cfg_attr(not(any(feature = "frontend-pixels", feature = "frontend-piston")), allow(dead_code))]
 mod display;
+#[cfg(feature = "frontend-piston")]
+mod piston_frontend;
+// piston wins when both are asked for, being the one that has to be asked for
+#[cfg(all(feature = "frontend-pixels", not(feature = "frontend-piston")))]
+mod pixels_frontend;
+#[cfg_attr(not(any(feature = "frontend-pixels", feature = "frontend-piston")), allow(dead_code))]
+mod text;
 
-use chip_8::CPUBuilder;
-use crate::display::Game;
+use chip_8::analyze::{CompatibilityIssue, Variant};
+use chip_8::audio::AudioSettings;
+use chip_8::cheats::Cheats;
+use chip_8::config::Config;
+use chip_8::coverage::Coverage;
+use chip_8::crash::Trace;
+use chip_8::debugger::Debugger;
+use chip_8::flags::FileFlags;
+use chip_8::keymap::KeyInput;
+use chip_8::library::{self, Library};
+use chip_8::profile::Profiler;
+use chip_8::rom_config::RomConfig;
+use chip_8::rom_info::RomInfo;
+use chip_8::rom_patch;
+use chip_8::asm::Program;
+use chip_8::symbols::{LineTable, SymbolTable};
+use chip_8::timing::{TimingModel, TIMER_HZ};
+use chip_8::{
+    analyze, asm, conformance, memory, rom_db, rom_file, CpuBuilder, KeyMap, OnUnknownOpcode, OnZeroOpcode,
+    MAX_STACK_DEPTH,
+};
+use crate::display::{Game, ScaleMode, DEFAULT_SCALE};
 
+use std::env;
+use std::fs;
 use std::io;
-use std::io::Read;
-use std::io::BufReader;
-use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// Command line options
+struct Options {
+    rom: String,
+    gdb: Option<String>,
+    debug: bool,
+    symbols: Option<String>,
+    keymap: Option<String>,
+    keys: Option<KeyInput>,
+    variant: Option<Variant>,
+    on_unknown: OnUnknownOpcode,
+    on_zero: OnZeroOpcode,
+    stack_depth: Option<usize>,
+    scale: Option<u32>,
+    stretch: bool,
+    persistence: f32,
+    timing: Option<TimingModel>,
+    coverage: Option<String>,
+    profile: bool,
+    conformance: bool,
+    watch: bool,
+    battery: bool,
+    patch: Option<String>,
+    library: Option<String>,
+    volume: Option<u8>,
+    mute: bool,
+    tone: Option<u32>,
+    config: Option<String>,
+}
+
+impl Options {
+    /// Reads `chip_8 [ROM|ROM.zip|URL|SOURCE.8o] [--gdb ADDR] [--debug] [--symbols FILE] [--keymap PRESET|FILE]
+    /// [--keys physical|character] [--variant NAME] [--unknown-opcodes error|skip|halt] [--zero-opcode halt|error|nop] [--stack-depth N]
+    /// [--scale N] [--stretch] [--persistence F] [--timing vip|hp48|HZ] [--coverage FILE] [--profile] [--conformance]
+    /// [--watch] [--battery] [--patch FILE.ips|FILE.bps] [--volume 0-100] [--mute] [--tone HZ] [--config FILE]`,
+    /// or `chip_8 --library DIR` with the window options
+    fn parse() -> Options {
+        let mut options = Options {
+            rom: String::from("./roms/sierpinski.ch8"),
+            gdb: None,
+            debug: false,
+            symbols: None,
+            keymap: None,
+            keys: None,
+            variant: None,
+            on_unknown: OnUnknownOpcode::default(),
+            on_zero: OnZeroOpcode::default(),
+            stack_depth: None,
+            scale: None,
+            stretch: false,
+            persistence: 0.0,
+            timing: None,
+            coverage: None,
+            profile: false,
+            conformance: false,
+            watch: false,
+            battery: false,
+            patch: None,
+            library: None,
+            volume: None,
+            mute: false,
+            tone: None,
+            config: None,
+        };
+
+        let mut args = env::args().skip(1);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--gdb" => options.gdb = args.next(),
+                "--debug" => options.debug = true,
+                "--symbols" => options.symbols = args.next(),
+                "--keymap" => options.keymap = args.next(),
+                "--keys" => match args.next().as_deref().and_then(KeyInput::from_name) {
+                    Some(input) => options.keys = Some(input),
+                    None => {
+                        eprintln!("--keys needs physical or character");
+                        std::process::exit(1);
+                    }
+                },
+                "--library" => options.library = args.next(),
+                "--variant" => match args.next().as_deref().and_then(Variant::from_name) {
+                    Some(variant) => options.variant = Some(variant),
+                    None => {
+                        eprintln!("--variant needs chip8, chip48, schip, megachip or xochip");
+                        std::process::exit(1);
+                    }
+                },
+                "--unknown-opcodes" => match args.next().as_deref() {
+                    Some("error") => options.on_unknown = OnUnknownOpcode::Error,
+                    Some("skip") => options.on_unknown = OnUnknownOpcode::Skip,
+                    Some("halt") => options.on_unknown = OnUnknownOpcode::Halt,
+                    _ => {
+                        eprintln!("--unknown-opcodes needs error, skip or halt");
+                        std::process::exit(1);
+                    }
+                },
+                "--zero-opcode" => match args.next().as_deref() {
+                    Some("halt") => options.on_zero = OnZeroOpcode::Halt,
+                    Some("error") => options.on_zero = OnZeroOpcode::Error,
+                    Some("nop") => options.on_zero = OnZeroOpcode::Nop,
+                    _ => {
+                        eprintln!("--zero-opcode needs halt, error or nop");
+                        std::process::exit(1);
+                    }
+                },
+                "--stack-depth" => match args.next().and_then(|depth| depth.parse().ok()) {
+                    Some(depth @ 1..=MAX_STACK_DEPTH) => options.stack_depth = Some(depth),
+                    _ => {
+                        eprintln!("--stack-depth needs how many calls can be nested, from 1 to {}", MAX_STACK_DEPTH);
+                        std::process::exit(1);
+                    }
+                },
+                "--scale" => match args.next().and_then(|scale| scale.parse().ok()) {
+                    Some(scale) => options.scale = Some(scale),
+                    None => {
+                        eprintln!("--scale needs a whole number of window pixels per CHIP-8 pixel");
+                        std::process::exit(1);
+                    }
+                },
+                "--stretch" => options.stretch = true,
+                "--persistence" => match args.next().and_then(|persistence| persistence.parse().ok()) {
+                    Some(persistence) => options.persistence = persistence,
+                    None => {
+                        eprintln!("--persistence needs how much brightness pixels keep a frame, from 0 to 1");
+                        std::process::exit(1);
+                    }
+                },
+                "--timing" => {
+                    options.timing = args.next().and_then(|model| model.parse().ok());
+                    if options.timing.is_none() {
+                        eprintln!("--timing needs `vip`, `hp48` or a number of instructions a second");
+                        std::process::exit(1);
+                    }
+                }
+                "--coverage" => options.coverage = args.next(),
+                "--profile" => options.profile = true,
+                "--conformance" => options.conformance = true,
+                "--watch" => options.watch = true,
+                "--battery" => options.battery = true,
+                "--patch" => options.patch = args.next(),
+                "--volume" => match args.next().and_then(|volume| volume.parse().ok()) {
+                    Some(volume @ 0..=100) => options.volume = Some(volume),
+                    _ => {
+                        eprintln!("--volume needs how loud the buzzer is, from 0 to 100");
+                        std::process::exit(1);
+                    }
+                },
+                "--mute" => options.mute = true,
+                "--config" => options.config = args.next(),
+                "--tone" => match args.next().and_then(|hz| hz.parse().ok()) {
+                    Some(hz @ 1..=20_000) => options.tone = Some(hz),
+                    _ => {
+                        eprintln!("--tone needs the buzzer's pitch, from 1 to 20000Hz");
+                        std::process::exit(1);
+                    }
+                },
+                _ => options.rom = arg,
+            }
+        }
+
+        options
+    }
+}
+
+impl Options {
+    /// The player's defaults, from the file `--config` names or else their
+    /// usual config file
+    fn user_config(&self) -> io::Result<Config> {
+        let (config, path) = match &self.config {
+            Some(path) => (Config::load(path), PathBuf::from(path)),
+            None => (Config::for_user(), Config::path().unwrap_or_default()),
+        };
+        config.map_err(|err| io::Error::other(format!("{}: {}", path.display(), err)))
+    }
+
+    /// The buzzer's settings from the command line, over `settings`
+    fn audio_over(&self, settings: AudioSettings) -> AudioSettings {
+        AudioSettings {
+            frequency: self.tone.unwrap_or(settings.frequency),
+            volume: self.volume.unwrap_or(settings.volume),
+            muted: self.mute || settings.muted,
+        }
+    }
+}
+
+/// Assembles the Octo source at `path`, saying where it went wrong if it
+/// couldn't
+fn assemble(path: &str) -> io::Result<Program> {
+    let source = fs::read_to_string(path)?;
+    asm::assemble_program(&source)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, format!("{}, {}", path, err)))
+}
+
+/// `chip_8 build SOURCE [ROM]` assembles SOURCE into ROM, which defaults to
+/// SOURCE with a `.ch8` extension, and saves its labels beside it with a
+/// `.sym` one for `--symbols`
+fn build(mut args: impl Iterator<Item = String>) -> io::Result<()> {
+    let source = match args.next() {
+        Some(source) => source,
+        None => {
+            eprintln!("usage: chip_8 build SOURCE [ROM]");
+            std::process::exit(1);
+        }
+    };
+    let rom = args.next().unwrap_or_else(|| Path::new(&source).with_extension("ch8").to_string_lossy().into_owned());
+
+    let program = assemble(&source)?;
+    fs::write(&rom, &program.rom)?;
+    fs::write(Path::new(&rom).with_extension("sym"), program.symbols.to_string())?;
+    println!("Assembled {} into {} ({} bytes)", source, rom, program.rom.len());
+    Ok(())
+}
+
+/// A builder for running `buffer` with as much memory as it needs, the
+/// player's quirks if the database doesn't know it, overridden by its
+/// settings file's and then the command line's variant
+fn rom_builder(options: &Options, user: &Config, config: &RomConfig, buffer: &[u8]) -> CpuBuilder {
+    let mut builder = CpuBuilder::new();
+    // the player's quirks are only for ROMs the database doesn't know
+    if let Some(quirks) = user.defaults.quirks {
+        builder = builder.quirks(quirks);
+    }
+    let mut builder = builder
+        .auto_configure(buffer)
+        .memory(buffer)
+        .on_unknown_opcode(options.on_unknown)
+        .on_zero_opcode(options.on_zero);
+    // only XO-CHIP and MegaChip ROMs can be too big for 4K, and they
+    // expect all of their memory anyway
+    if options.variant == Some(Variant::MegaChip) || buffer.len() > memory::XO_CHIP_SIZE - 0x200 {
+        builder = builder.memory_size(memory::MEGA_CHIP_SIZE);
+    } else if options.variant == Some(Variant::XoChip) || buffer.len() > memory::CHIP_8_SIZE - 0x200 {
+        builder = builder.memory_size(memory::XO_CHIP_SIZE);
+    }
+    if let Some(quirks) = config.quirks {
+        builder = builder.quirks(quirks);
+    }
+    // the variant from the command line wins over both
+    if let Some(variant) = options.variant {
+        builder = builder.quirks(variant.quirks());
+    }
+    if let Some(depth) = options.stack_depth {
+        builder = builder.stack_depth(depth);
+    }
+    builder
+}
+
+/// How long `buffer`'s instructions take: the command line's timing, then
+/// its settings file's, then the speed the database has for it, then the
+/// player's
+fn rom_timing(options: &Options, user: &Config, config: &RomConfig, buffer: &[u8]) -> Option<TimingModel> {
+    let tickrate = rom_db::lookup(buffer)
+        .and_then(|entry| entry.tickrate)
+        .map(|tickrate| TimingModel::Fixed((tickrate as f64 * TIMER_HZ) as u32));
+    options.timing.or(config.timing).or(tickrate).or(user.defaults.timing)
+}
+
+/// `chip_8 --library DIR` lists the ROMs in DIR to pick from, going back to
+/// the list on F2 or once the game stops
+///
+/// Each ROM picked gets its own CPU, built the way it would be if it were
+/// run on its own.
+fn browse_library(mut options: Options, user: Config, dir: &str) -> io::Result<()> {
+    let library = Library::scan(dir).map_err(|err| io::Error::other(format!("{}: {}", dir, err)))?;
+    if library.is_empty() {
+        eprintln!("There are no ROMs in {} (looking for .{})", dir, library::ROM_EXTENSIONS.join(", ."));
+        std::process::exit(1);
+    }
+
+    let audio = options.audio_over(user.defaults.audio_over(AudioSettings::default()));
+    let scale = options.scale.or(user.scale).unwrap_or(DEFAULT_SCALE);
+    let mut keymap = match options.keymap.take().or_else(|| user.defaults.keymap.clone()) {
+        Some(name) => match KeyMap::preset(&name) {
+            Some(keymap) => keymap,
+            None => KeyMap::load(&name).map_err(|err| io::Error::other(format!("{}: {}", name, err)))?,
+        },
+        None => KeyMap::default(),
+    };
+    if let Some(input) = options.keys {
+        keymap.set_input(input);
+    }
+
+    // nothing runs until a ROM is picked
+    let mut game = Game::new(CpuBuilder::new().build(), keymap);
+    let mode = if options.stretch { ScaleMode::Stretch } else { ScaleMode::Integer };
+    game.scaling(scale, mode);
+    game.persistence(options.persistence);
+    game.audio_settings(audio);
+    if let Some(palette) = user.defaults.palette {
+        game.palette(palette);
+    }
+    #[cfg(feature = "audio-cpal")]
+    match chip_8::audio::CpalBeeper::new() {
+        Ok(beeper) => game.audio(beeper),
+        Err(err) => eprintln!("Couldn't open audio, running without sound: {}", err),
+    }
+    game.library(library, move |path, rom| {
+        // a broken settings file leaves the ROM with the defaults
+        let config = RomConfig::for_rom(path).ok().flatten().unwrap_or_default();
+        let cpu = rom_builder(&options, &user, &config, rom).build();
+        (cpu, rom_timing(&options, &user, &config, rom))
+    });
+
+    game.run();
+    Ok(())
+}
+
+/// `chip_8 info ROM` prints ROM's size, checksums and anything that looks
+/// wrong with it, failing if it can't be loaded at all
+fn rom_report(mut args: impl Iterator<Item = String>) -> io::Result<()> {
+    let rom = match args.next() {
+        Some(rom) => rom,
+        None => {
+            eprintln!("usage: chip_8 info ROM");
+            std::process::exit(1);
+        }
+    };
+
+    let buffer = rom_file::load(&rom).map_err(|err| io::Error::other(format!("{}: {}", rom, err)))?;
+    match RomInfo::check(&buffer) {
+        Ok(info) => print!("{}", info),
+        Err(err) => {
+            eprintln!("{}: {}", rom, err);
+            std::process::exit(1);
+        }
+    }
+    Ok(())
+}
+
+/// `chip_8 cfg ROM [--json]` prints ROM's control flow graph, as Graphviz
+/// DOT unless JSON is asked for
+fn control_flow(args: impl Iterator<Item = String>) -> io::Result<()> {
+    let (flags, rest): (Vec<String>, Vec<String>) = args.partition(|arg| arg == "--json");
+    let rom = match &rest[..] {
+        [rom] => rom,
+        _ => {
+            eprintln!("usage: chip_8 cfg ROM [--json]");
+            std::process::exit(1);
+        }
+    };
+
+    let buffer = rom_file::load(rom).map_err(|err| io::Error::other(format!("{}: {}", rom, err)))?;
+    let graph = analyze::cfg(&buffer);
+    if flags.is_empty() {
+        print!("{}", graph.to_dot());
+    } else {
+        println!("{}", graph.to_json());
+    }
+    Ok(())
+}
 
 fn main() -> io::Result<()> {
-    let f = File::open("./roms/sierpinski.ch8")?;
-    let mut reader = BufReader::new(f);
-    let mut buffer = Vec::new();
-    let mut memory = [0; 0x1000];
-    
-    // Read file into vector.
-    reader.read_to_end(&mut buffer)?;
-    
-    for (ind, value) in buffer.iter().enumerate() {
-        memory[ind] = *value;
-    }
-
-    let cpu = CPUBuilder::new().memory(memory).build();
-    let mut game = Game::new(cpu);
+    match env::args().nth(1).as_deref() {
+        Some("build") => return build(env::args().skip(2)),
+        Some("info") => return rom_report(env::args().skip(2)),
+        Some("cfg") => return control_flow(env::args().skip(2)),
+        _ => {}
+    }
+
+    // RUST_LOG picks what gets logged, e.g. `chip_8::cpu=trace`
+    #[cfg(feature = "tracing")]
+    tracing_subscriber::fmt().with_env_filter(tracing_subscriber::EnvFilter::from_default_env()).init();
+
+    let options = Options::parse();
+    // the player's own defaults, under everything else
+    let user = options.user_config()?;
+    if let Some(dir) = options.library.clone() {
+        return browse_library(options, user, &dir);
+    }
+
+    // Octo sources are assembled on the way in, so they can be run directly
+    let (mut buffer, mut symbols, lines) = if Path::new(&options.rom).extension().is_some_and(|ext| ext == "8o") {
+        let program = assemble(&options.rom)?;
+        (program.rom, program.symbols, program.lines)
+    } else {
+        let rom = rom_file::load(&options.rom).map_err(|err| io::Error::other(format!("{}: {}", options.rom, err)))?;
+        (rom, SymbolTable::new(), LineTable::new())
+    };
+    // fixes and hacks handed around as patches go on before anything looks
+    // at the ROM
+    if let Some(patch) = &options.patch {
+        buffer = fs::read(patch)
+            .map_err(|err| err.into())
+            .and_then(|patch| rom_patch::apply(&buffer, &patch))
+            .map_err(|err| io::Error::other(format!("{}: {}", patch, err)))?;
+    }
+    // downloaded ROMs keep their flags and crash reports in the current
+    // directory, under the name they were downloaded as
+    let saves = if rom_file::is_url(&options.rom) {
+        PathBuf::from(options.rom.rsplit('/').next().unwrap_or_default())
+    } else {
+        PathBuf::from(&options.rom)
+    };
+
+    // a ROM too big for memory stops here rather than being cut off to fit
+    let rom_info = RomInfo::check(&buffer).map_err(|err| io::Error::other(format!("{}: {}", options.rom, err)))?;
+    info!("{} is {} bytes, CRC32 {:08X}, SHA-1 {}", options.rom, rom_info.size, rom_info.crc32, rom_info.sha1);
+    for warning in &rom_info.warnings {
+        eprintln!("warning: {}", warning);
+    }
+
+    // settings kept beside the ROM sit between the ROM database's and the
+    // command line's
+    let config = RomConfig::for_rom(&options.rom)
+        .map_err(|err| io::Error::other(format!("{}: {}", RomConfig::path_for(&options.rom).display(), err)))?
+        .unwrap_or_default();
+
+    // one warning per instruction is plenty, wherever else it turns up
+    let mut warned = Vec::new();
+    // a variant asked for is checked first, so a ROM written for another
+    // says so before it goes wrong partway through
+    if let Some(variant) = options.variant {
+        for issue in analyze::verify(&buffer, variant) {
+            let pattern = match issue {
+                CompatibilityIssue::Missing(extension, _) => extension.pattern,
+                CompatibilityIssue::Unknown { .. } => "unknown",
+            };
+            if !warned.contains(&pattern) {
+                warned.push(pattern);
+                eprintln!("warning: {}", issue);
+            }
+        }
+    }
+    for extension in analyze::scan(&buffer).unsupported() {
+        if !warned.contains(&extension.pattern) {
+            warned.push(extension.pattern);
+            eprintln!(
+                "warning: this ROM uses {:04X} at 0x{:03X}, a {} instruction chip_8 can't run",
+                extension.opcode, extension.address, extension.variant
+            );
+        }
+    }
+
+    if options.conformance {
+        let quirks = config
+            .quirks
+            .or_else(|| Some(rom_db::lookup(&buffer)?.quirks))
+            .or(user.defaults.quirks)
+            .unwrap_or_default();
+        match conformance::compare(&buffer, quirks, 100_000) {
+            Ok(steps) => println!("Matched the reference interpreter for {} instructions", steps),
+            Err(divergence) => {
+                println!("{}", divergence);
+                std::process::exit(1);
+            }
+        }
+        return Ok(());
+    }
+
+    let mut builder = rom_builder(&options, &user, &config, &buffer);
+    // homebrew that saves to the last page of memory keeps it next to the ROM
+    if options.battery {
+        builder = builder.battery(saves.with_extension("sav"));
+    }
+    let mut cpu = builder.build();
+    // SUPER-CHIP games keep their RPL flags next to the ROM
+    cpu.set_flag_storage(FileFlags::open(saves.with_extension("flags"))?);
+
+    if let Some(addr) = options.gdb {
+        #[cfg(feature = "gdb")]
+        {
+            println!("Waiting for gdb to connect on {}", addr);
+            return chip_8::gdb::serve(&mut cpu, addr).map_err(|err| io::Error::other(err.to_string()));
+        }
+
+        #[cfg(not(feature = "gdb"))]
+        {
+            eprintln!("Can't debug on {}: chip_8 was built without the `gdb` feature", addr);
+            std::process::exit(1);
+        }
+    }
+
+    if options.debug {
+        let stdin = io::stdin();
+        if let Some(path) = &options.symbols {
+            symbols = SymbolTable::load(path).map_err(|err| io::Error::other(format!("{}: {}", path, err)))?;
+        }
+        let mut debugger = Debugger::new(&mut cpu);
+        debugger.set_symbols(symbols);
+        // Octo sources are debugged by line too
+        if !lines.is_empty() {
+            debugger.set_source(&options.rom, &fs::read_to_string(&options.rom)?, lines);
+        }
+        return debugger.run(stdin.lock(), io::stdout());
+    }
+
+    // the ROM's audio settings over the player's defaults, and the command
+    // line's over both
+    let audio = options.audio_over(config.audio_over(user.defaults.audio_over(AudioSettings::default())));
+    let timing = rom_timing(&options, &user, &config, &buffer);
+
+    // a preset or file from the command line wins over the ROM's settings,
+    // which win over its preset and controls in the database
+    let database = rom_db::lookup(&buffer);
+    let chosen = options.keymap.or(config.keymap);
+    let database_keys = if chosen.is_none() { database.map_or(&[][..], |entry| entry.keys) } else { &[] };
+    let database_keymap = || database?.keymap.map(String::from);
+    let mut keymap = match chosen.or_else(database_keymap).or(user.defaults.keymap) {
+        Some(name) => match KeyMap::preset(&name) {
+            Some(keymap) => keymap,
+            None => KeyMap::load(&name).map_err(|err| io::Error::other(format!("{}: {}", name, err)))?,
+        },
+        None => KeyMap::default(),
+    };
+    for (host, key) in database_keys {
+        keymap.bind(host, *key);
+    }
+    // the command line can say how to name keys whatever the map does
+    if let Some(input) = options.keys {
+        keymap.set_input(input);
+    }
+
+    // kept hold of here so they can be read back once the window closes
+    let coverage = options.coverage.map(|path| (path, Arc::new(Mutex::new(Coverage::new()))));
+    let profiler = options.profile.then(|| Arc::new(Mutex::new(Profiler::new())));
+    // always on, so there's something to go on when a ROM crashes
+    let trace = Arc::new(Mutex::new(Trace::default()));
+    match (&coverage, &profiler) {
+        (Some((_, coverage)), Some(profiler)) => {
+            cpu.set_observer((trace.clone(), (coverage.clone(), profiler.clone())))
+        }
+        (Some((_, coverage)), None) => cpu.set_observer((trace.clone(), coverage.clone())),
+        (None, Some(profiler)) => cpu.set_observer((trace.clone(), profiler.clone())),
+        (None, None) => cpu.set_observer(trace.clone()),
+    }
+
+    let mut game = Game::new(cpu, keymap);
+    game.crash_reports(saves.with_extension("crash"), trace);
+    game.high_score(&buffer, saves.with_extension("score"));
+    let mode = if options.stretch { ScaleMode::Stretch } else { ScaleMode::Integer };
+    game.scaling(options.scale.or(user.scale).unwrap_or(DEFAULT_SCALE), mode);
+    game.persistence(options.persistence);
+    if let Some(model) = timing {
+        game.timing(model);
+    }
+    if let Some(palette) = config.palette.or(user.defaults.palette) {
+        game.palette(palette);
+    }
+    game.cheats(Cheats::from(config.cheats));
+    game.audio_settings(audio);
+
+    if options.watch {
+        #[cfg(feature = "watch")]
+        if let Err(err) = game.watch(Path::new(&options.rom)) {
+            eprintln!("Can't watch {}: {}", options.rom, err);
+            std::process::exit(1);
+        }
+
+        #[cfg(not(feature = "watch"))]
+        {
+            eprintln!("Can't watch {}: chip_8 was built without the `watch` feature", options.rom);
+            std::process::exit(1);
+        }
+    }
+
+    #[cfg(feature = "audio-cpal")]
+    match chip_8::audio::CpalBeeper::new() {
+        Ok(beeper) => game.audio(beeper),
+        Err(err) => eprintln!("Couldn't open audio, running without sound: {}", err),
+    }
+
     game.run();
 
+    if let Some((path, coverage)) = coverage {
+        let report = coverage.lock().unwrap().report(0x200..0x200 + buffer.len());
+        println!("{}", report);
+        fs::write(path, report.to_json())?;
+    }
+
+    if let Some(profiler) = profiler {
+        println!("{}", profiler.lock().unwrap().report());
+    }
+
     Ok(())
-}
\ No newline at end of file
+}
@@ -0,0 +1,655 @@
+//! The CPU's memory, 4K unless the builder asks for more: the built-in
+//! font, where programs are loaded, and the reads and writes that keep the
+//! decode cache and observer in step
+//!
+//! [`MemoryView`] formats memory as hex and ASCII rows for debuggers and
+//! other tools, and routes edits through [`Cpu::poke`] so they can't touch
+//! the interpreter's own memory below the program start. [`image`] and
+//! [`include_chip8_rom!`](crate::include_chip8_rom) lay ROMs out for the
+//! builder at compile time.
+//!
+//! A [`Bus`] can stand in for a range of addresses, so peripherals like a
+//! serial port or battery-backed RAM can be mapped in with [`Cpu::map`]
+//! without touching the CPU. Everything unmapped is plain memory, and
+//! a CPU with nothing mapped only pays for checking that nothing is. The
+//! builder's [`battery`](crate::CpuBuilder::battery) maps one in itself:
+//! the last page of memory, kept in a file between runs.
+//!
+//! # Example
+//!
+//! ```
+//! use chip_8::memory::MemoryView;
+//! use chip_8::CpuBuilder;
+//!
+//! let mut cpu = CpuBuilder::new().build();
+//! MemoryView::edit(&mut cpu, 0x200, "48 69").unwrap();
+//!
+//! let view = MemoryView::new(cpu.memory(), 0x200, 4);
+//! assert_eq!(view.render(cpu.memory()), format!("0x200: 48 69 00 00{}|Hi..|\n", " ".repeat(38)));
+//! assert!(MemoryView::edit(&mut cpu, 0x050, "FF").is_err());
+//! ```
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::ops::Range;
+use std::path::PathBuf;
+
+use crate::{Byte, Cpu, CpuError, Memory, OpCode, PROGRAM_START};
+
+/// How much memory the original CHIP-8 had, which CPUs get by default
+pub const CHIP_8_SIZE: usize = 0x1000;
+
+/// How much memory XO-CHIP has, everything a 16 bit I can reach
+pub const XO_CHIP_SIZE: usize = 0x10000;
+
+/// How much memory MegaChip has, everything its 24 bit I can reach
+pub const MEGA_CHIP_SIZE: usize = 0x100_0000;
+
+/// How many bytes each row of a [`MemoryView`] shows
+pub const ROW_BYTES: usize = 16;
+
+/// How many bytes a page of memory is, for paging through a [`MemoryView`]
+pub const PAGE_BYTES: usize = 0x100;
+
+/// The hex digit sprites, 0 to F, five bytes each from address 0
+const FONT: [Byte; 80] = [
+    0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
+    0x20, 0x60, 0x20, 0x20, 0x70, // 1
+    0xF0, 0x10, 0xF0, 0x80, 0xF0, // 2
+    0xF0, 0x10, 0xF0, 0x10, 0xF0, // 3
+    0x90, 0x90, 0xF0, 0x10, 0x10, // 4
+    0xF0, 0x80, 0xF0, 0x10, 0xF0, // 5
+    0xF0, 0x80, 0xF0, 0x90, 0xF0, // 6
+    0xF0, 0x10, 0x20, 0x40, 0x40, // 7
+    0xF0, 0x90, 0xF0, 0x90, 0xF0, // 8
+    0xF0, 0x90, 0xF0, 0x10, 0xF0, // 9
+    0xF0, 0x90, 0xF0, 0x90, 0x90, // A
+    0xE0, 0x90, 0xE0, 0x90, 0xE0, // B
+    0xF0, 0x80, 0x80, 0x80, 0xF0, // C
+    0xE0, 0x90, 0x90, 0x90, 0xE0, // D
+    0xF0, 0x80, 0xF0, 0x80, 0xF0, // E
+    0xF0, 0x80, 0xF0, 0x80, 0x80, // F
+];
+
+/// Something mapped into memory, which the program's reads and writes in
+/// its range go to instead of the plain memory underneath
+///
+/// Addresses are passed as the program sees them, not from the start of
+/// the range. Only the program goes through the bus: [`Cpu::memory`],
+/// [`Cpu::poke`], snapshots and the debuggers all see the plain memory.
+/// Devices have to be `Send` so a CPU with them can still move between
+/// threads.
+///
+/// # Example
+///
+/// ```
+/// use std::sync::mpsc::{self, Sender};
+///
+/// use chip_8::memory::Bus;
+/// use chip_8::CpuBuilder;
+///
+/// /// Sends whatever the program writes to it, and reads back as 0xFF
+/// struct SerialPort(Sender<u8>);
+///
+/// impl Bus for SerialPort {
+///     fn read(&mut self, _addr: usize) -> u8 {
+///         0xFF
+///     }
+///
+///     fn write(&mut self, _addr: usize, value: u8) {
+///         self.0.send(value).unwrap();
+///     }
+/// }
+///
+/// let (sender, receiver) = mpsc::channel();
+/// let mut cpu = CpuBuilder::new().build();
+/// cpu.map(0xF00..0xF01, SerialPort(sender));
+///
+/// // LD V0, 0x2A; LD I, 0xF00; LD [I], V0; LD V1, [I]
+/// cpu.load_program(&[0x60, 0x2A, 0xAF, 0x00, 0xF0, 0x55, 0xF1, 0x65]).unwrap();
+/// cpu.run_cycles(4);
+/// assert_eq!(receiver.try_recv(), Ok(0x2A));
+/// assert_eq!(cpu.registers(0), 0xFF);
+/// assert_eq!(cpu.memory()[0xF00], 0);
+/// ```
+pub trait Bus: Send {
+    /// The byte the program reads from `addr`
+    fn read(&mut self, addr: usize) -> Byte;
+
+    /// The program wrote `value` to `addr`
+    fn write(&mut self, addr: usize, value: Byte);
+}
+
+/// Plain RAM, indexed by the program's addresses, for mapping over a range
+/// whose contents should outlive resets and new ROMs
+impl Bus for Memory {
+    fn read(&mut self, addr: usize) -> Byte {
+        self[addr]
+    }
+
+    fn write(&mut self, addr: usize, value: Byte) {
+        self[addr] = value;
+    }
+}
+
+/// Plain RAM as an array, like [`Memory`]
+impl<const N: usize> Bus for [Byte; N] {
+    fn read(&mut self, addr: usize) -> Byte {
+        self[addr]
+    }
+
+    fn write(&mut self, addr: usize, value: Byte) {
+        self[addr] = value;
+    }
+}
+
+/// The last page of memory kept in a file, so homebrew can save high
+/// scores and progress the way cartridges did with battery-backed RAM
+///
+/// The file is read when the CPU is built and written back when it's
+/// dropped, if the program wrote to the page.
+pub(crate) struct Battery {
+    // `None` when the file couldn't be read, so it isn't overwritten
+    path: Option<PathBuf>,
+    start: usize,
+    ram: [Byte; PAGE_BYTES],
+    written: bool,
+}
+
+impl Battery {
+    /// The page from `start`, as `path` last left it or blank if it
+    /// doesn't exist yet
+    pub(crate) fn open(path: PathBuf, start: usize) -> Battery {
+        let mut ram = [0; PAGE_BYTES];
+        let path = match fs::read(&path) {
+            Ok(bytes) => {
+                let len = bytes.len().min(PAGE_BYTES);
+                ram[..len].copy_from_slice(&bytes[..len]);
+                Some(path)
+            }
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Some(path),
+            Err(err) => {
+                warn!("couldn't read the battery from {}, so it won't be saved: {}", path.display(), err);
+                None
+            }
+        };
+        Battery { path, start, ram, written: false }
+    }
+}
+
+impl Bus for Battery {
+    fn read(&mut self, addr: usize) -> Byte {
+        self.ram[addr - self.start]
+    }
+
+    fn write(&mut self, addr: usize, value: Byte) {
+        self.ram[addr - self.start] = value;
+        self.written = true;
+    }
+}
+
+impl Drop for Battery {
+    fn drop(&mut self) {
+        if let (Some(path), true) = (&self.path, self.written) {
+            if let Err(err) = fs::write(path, self.ram) {
+                warn!("couldn't save the battery to {}: {}", path.display(), err);
+            }
+        }
+    }
+}
+
+/// Lays `rom` out as a memory image for [`CpuBuilder::memory`], which
+/// loads index 0 at the program start
+///
+/// This is a `const fn`, so ROMs can be laid out at compile time; see
+/// [`include_chip8_rom!`](crate::include_chip8_rom).
+///
+/// # Panics
+///
+/// Panics if `rom` is bigger than the 0xE00 bytes between 0x200 and the end
+/// of memory, which in a constant stops the build:
+///
+/// ```compile_fail
+/// const TOO_BIG: [u8; 0x1000] = chip_8::memory::image(&[0; 0xE01]);
+/// ```
+///
+/// [`CpuBuilder::memory`]: crate::CpuBuilder::memory
+pub const fn image(rom: &[Byte]) -> [Byte; CHIP_8_SIZE] {
+    assert!(rom.len() <= 0x1000 - PROGRAM_START, "the ROM is too big to fit in memory after 0x200");
+    let mut memory = [0; 0x1000];
+    let mut i = 0;
+    while i < rom.len() {
+        memory[i] = rom[i];
+        i += 1;
+    }
+    memory
+}
+
+/// Bakes the ROM at a path into the binary as a memory image for
+/// [`CpuBuilder::memory`](crate::CpuBuilder::memory), so it can be run
+/// without reading any files
+///
+/// The path is relative to the file the macro is used in, as with
+/// `include_bytes!`, and a ROM too big for memory stops the build.
+///
+/// # Example
+///
+/// ```
+/// use chip_8::{include_chip8_rom, CpuBuilder};
+///
+/// const SIERPINSKI: [u8; 0x1000] = include_chip8_rom!("../roms/sierpinski.ch8");
+///
+/// let cpu = CpuBuilder::new().memory(SIERPINSKI).build();
+/// assert_eq!(cpu.memory()[0x200..0x202], SIERPINSKI[..2]);
+/// ```
+#[macro_export]
+macro_rules! include_chip8_rom {
+    ($path:expr) => {{
+        const IMAGE: [u8; 0x1000] = $crate::memory::image(include_bytes!($path));
+        IMAGE
+    }};
+}
+
+/// Lays `size` bytes of memory out the way the machine boots: the font at
+/// the bottom and `program` copied in from `start`, anything past the end
+/// cut off
+///
+/// Everything else below the program start is left to the interpreter.
+pub(crate) fn boot(program: &[Byte], start: usize, size: usize) -> Memory {
+    let mut memory = vec![0; size].into_boxed_slice();
+    memory[..FONT.len()].copy_from_slice(&FONT);
+    let len = program.len().min(size - start);
+    memory[start..start + len].copy_from_slice(&program[..len]);
+    memory
+}
+
+impl Cpu {
+    /// All of memory as it is now, font and program included
+    pub fn memory(&self) -> &[Byte] {
+        &self.memory
+    }
+
+    /// Sends the program's reads and writes from `range` to `device`
+    /// from now on, in place of the plain memory there
+    ///
+    /// Devices stay mapped through resets and new ROMs. If ranges overlap,
+    /// the one mapped first wins.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `range` runs past the end of memory
+    pub fn map(&mut self, range: Range<usize>, device: impl Bus + 'static) {
+        assert!(range.end <= self.memory.len(), "0x{:X}..0x{:X} is past the end of memory", range.start, range.end);
+        if let Some(cache) = &mut self.decode_cache {
+            // including the instruction straddling the start
+            cache[range.start.saturating_sub(1)..range.end].iter_mut().for_each(|instruction| *instruction = None);
+        }
+        self.mapped.push((range, Box::new(device)));
+    }
+
+    /// Whether a device is mapped over `addr`
+    pub(crate) fn is_mapped(&self, addr: usize) -> bool {
+        self.mapped.iter().any(|(range, _)| range.contains(&addr))
+    }
+
+    /// Reads a byte for the program, from whatever is mapped there
+    pub(crate) fn read_memory(&mut self, addr: usize) -> Byte {
+        match self.mapped.iter_mut().find(|(range, _)| range.contains(&addr)) {
+            Some((_, device)) => device.read(addr),
+            None => self.memory[addr],
+        }
+    }
+
+    /// Writes bytes into memory from outside the program, for debuggers and
+    /// memory editors, starting at `addr`
+    ///
+    /// Nothing is written unless every byte fits: writes past the end of
+    /// memory, or into the interpreter's memory below the program start
+    /// where the font lives, are refused. Bytes go into the plain memory
+    /// even where a [`Bus`] device is mapped over it, like everything else
+    /// from outside the program.
+    pub fn poke(&mut self, addr: usize, bytes: &[Byte]) -> Result<(), CpuError> {
+        if addr < self.program_start {
+            return Err(CpuError::ProtectedMemory(addr));
+        }
+        if addr + bytes.len() > self.memory.len() {
+            return Err(CpuError::MemoryOutOfBounds(addr + bytes.len().max(1) - 1));
+        }
+
+        for (ind, &value) in bytes.iter().enumerate() {
+            self.write_plain_memory(addr + ind, value);
+        }
+        Ok(())
+    }
+
+    /// The addresses from I to I + len - 1, if they're all in memory
+    pub(crate) fn i_range(&self, len: usize) -> Result<std::ops::Range<usize>, CpuError> {
+        let start = self.i_address();
+        if start + len > self.memory.len() {
+            return Err(CpuError::MemoryOutOfBounds(start + len - 1));
+        }
+
+        Ok(start..start + len)
+    }
+
+    /// Writes a byte of memory, forgetting any cached instructions that
+    /// were decoded from it and telling the observer
+    ///
+    /// Writes to a mapped device go to it alone; the device can watch
+    /// them itself.
+    pub(crate) fn write_memory(&mut self, addr: usize, value: Byte) {
+        if let Some((_, device)) = self.mapped.iter_mut().find(|(range, _)| range.contains(&addr)) {
+            device.write(addr, value);
+            return;
+        }
+        self.write_plain_memory(addr, value);
+    }
+
+    /// Writes a byte of the plain memory, under any device mapped there,
+    /// forgetting cached instructions and telling the observer like
+    /// [`write_memory`](Cpu::write_memory)
+    fn write_plain_memory(&mut self, addr: usize, value: Byte) {
+        let old = std::mem::replace(&mut self.memory[addr], value);
+        if let Some(observer) = &mut self.observer {
+            observer.on_memory_write(addr, old, value);
+        }
+
+        if let Some(cache) = &mut self.decode_cache {
+            // instructions start at `addr` or the byte before it
+            cache[addr] = None;
+            if addr > 0 {
+                cache[addr - 1] = None;
+            }
+        }
+    }
+
+    /// Forgets every cached instruction, after memory has been replaced
+    /// wholesale, perhaps by memory of a different size
+    pub(crate) fn clear_decode_cache(&mut self) {
+        if let Some(cache) = &mut self.decode_cache {
+            cache.clear();
+            cache.resize(self.memory.len(), None);
+        }
+    }
+
+    /// Returns the next two bytes of memory concatenated as a u16
+    pub(crate) fn read_opcode(&mut self) -> OpCode {
+        let p = self.program_counter;
+        let byte1 = self.read_memory(p) as OpCode;
+        let byte2 = self.read_memory(p + 1) as OpCode;
+        byte1 << 8 | byte2
+    }
+}
+
+/// A window onto memory, formatted as rows of hex bytes and their ASCII
+///
+/// Rows are [`ROW_BYTES`] long and start at the view's start address, so
+/// views that start on a multiple of 16 line up with the usual hex dump.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryView {
+    start: usize,
+    len: usize,
+    size: usize,
+}
+
+impl MemoryView {
+    /// Views `len` bytes of `memory` from `start`, cut off at the end of it
+    pub fn new(memory: &[Byte], start: usize, len: usize) -> MemoryView {
+        let size = memory.len();
+        let start = start.min(size);
+        MemoryView { start, len: len.min(size - start), size }
+    }
+
+    /// Views the `page`th [`PAGE_BYTES`] of `memory`, so page 2 starts at 0x200
+    pub fn page(memory: &[Byte], page: usize) -> MemoryView {
+        MemoryView::new(memory, page * PAGE_BYTES, PAGE_BYTES)
+    }
+
+    /// The addresses in view
+    pub fn range(&self) -> Range<usize> {
+        self.start..self.start + self.len
+    }
+
+    /// Moves the view by `rows` rows, negative for back towards 0, keeping
+    /// it inside memory
+    pub fn scroll(&mut self, rows: isize) {
+        let last = self.size - self.len;
+        let start = self.start as isize + rows * ROW_BYTES as isize;
+        self.start = start.clamp(0, last as isize) as usize;
+    }
+
+    /// The rows of bytes in view, read from `memory`
+    pub fn rows<'m>(&self, memory: &'m [Byte]) -> impl Iterator<Item = MemoryRow<'m>> + 'm {
+        let range = self.start.min(memory.len())..(self.start + self.len).min(memory.len());
+        let start = range.start;
+        memory[range]
+            .chunks(ROW_BYTES)
+            .enumerate()
+            .map(move |(row, bytes)| MemoryRow { addr: start + row * ROW_BYTES, bytes })
+    }
+
+    /// Every row in view, one per line
+    pub fn render(&self, memory: &[Byte]) -> String {
+        self.rows(memory).map(|row| format!("{}\n", row)).collect()
+    }
+
+    /// Parses `text` as hex bytes, like `"A2 1F"` or `"a21f"`, and writes
+    /// them from `addr` through [`Cpu::poke`], returning how many were
+    /// written
+    ///
+    /// Nothing is written if the text isn't hex or the write is refused.
+    pub fn edit(cpu: &mut Cpu, addr: usize, text: &str) -> Result<usize, String> {
+        let digits: String = text.split_whitespace().collect();
+        if digits.is_empty() {
+            return Err(format!("Not whole hex bytes: {}", text.trim()));
+        }
+
+        let bytes = (0..digits.len())
+            .step_by(2)
+            .map(|ind| digits.get(ind..ind + 2).and_then(|byte| Byte::from_str_radix(byte, 16).ok()))
+            .collect::<Option<Vec<Byte>>>()
+            .ok_or_else(|| format!("Not whole hex bytes: {}", text.trim()))?;
+
+        cpu.poke(addr, &bytes).map_err(|err| err.to_string())?;
+        Ok(bytes.len())
+    }
+}
+
+/// One row of a [`MemoryView`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryRow<'m> {
+    /// The address of the first byte
+    pub addr: usize,
+    /// Up to [`ROW_BYTES`] bytes; only the last row of a view can be short
+    pub bytes: &'m [Byte],
+}
+
+impl MemoryRow<'_> {
+    /// The bytes as two digit hex separated by spaces, like `"60 05 A2"`
+    pub fn hex(&self) -> String {
+        let bytes: Vec<String> = self.bytes.iter().map(|byte| format!("{:02X}", byte)).collect();
+        bytes.join(" ")
+    }
+
+    /// The bytes as ASCII, with `.` for anything unprintable
+    pub fn ascii(&self) -> String {
+        self.bytes
+            .iter()
+            .map(|&byte| if (0x20..0x7F).contains(&byte) { byte as char } else { '.' })
+            .collect()
+    }
+}
+
+impl fmt::Display for MemoryRow<'_> {
+    /// The address, the hex padded out to a full row, then the ASCII
+    /// between bars: `0x200: 48 69 ...  |Hi..|`
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "0x{:03X}: {:width$}  |{}|", self.addr, self.hex(), self.ascii(), width = ROW_BYTES * 3 - 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CpuBuilder, ExecutionEngine};
+
+    #[test]
+    fn read_opcode_concats_next_two_bytes() {
+        let byte1 = 0x81;
+        let byte2 = 0x56;
+        let start = 0x123;
+        let mut memory = [0; 0x1000];
+        memory[start] = byte1;
+        memory[start + 1] = byte2;
+        let mut cpu = CpuBuilder::new().build();
+        cpu.memory = Box::new(memory);
+        cpu.program_counter = start;
+
+        let expected = (memory[start] as u16) << 8 | (memory[start + 1] as u16);
+        assert_eq!(expected, cpu.read_opcode());
+    }
+
+    #[test]
+    fn view_renders_hex_and_ascii_rows() {
+        let mut memory = [0; 0x1000];
+        memory[0x200..0x205].copy_from_slice(b"CHIP8");
+        let view = MemoryView::new(&memory, 0x200, 20);
+
+        let rows: Vec<_> = view.rows(&memory).collect();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].hex(), "43 48 49 50 38 00 00 00 00 00 00 00 00 00 00 00");
+        assert_eq!(rows[0].ascii(), "CHIP8...........");
+        assert_eq!(rows[1].to_string(), format!("0x210: 00 00 00 00{}|....|", " ".repeat(38)));
+    }
+
+    #[test]
+    fn view_pages_and_scrolls_inside_memory() {
+        let memory = [0; 0x1000];
+        let mut view = MemoryView::page(&memory, 0xF);
+        assert_eq!(view.range(), 0xF00..0x1000);
+
+        view.scroll(1);
+        assert_eq!(view.range(), 0xF00..0x1000);
+        view.scroll(-2);
+        assert_eq!(view.range(), 0xEE0..0xFE0);
+        view.scroll(-1000);
+        assert_eq!(view.range(), 0..0x100);
+
+        assert_eq!(MemoryView::new(&memory, 0xFF8, 0x100).range(), 0xFF8..0x1000);
+    }
+
+    #[test]
+    fn view_reaches_the_end_of_bigger_memory() {
+        let memory = vec![0; XO_CHIP_SIZE];
+        let mut view = MemoryView::page(&memory, 0xFF);
+        assert_eq!(view.range(), 0xFF00..0x10000);
+
+        view.scroll(-0x10);
+        view.scroll(0x10);
+        assert_eq!(view.range(), 0xFF00..0x10000);
+    }
+
+    #[test]
+    fn edit_writes_through_poke() {
+        let mut cpu = CpuBuilder::new().build();
+
+        assert_eq!(MemoryView::edit(&mut cpu, 0x300, "a2 1F 00"), Ok(3));
+        assert_eq!(cpu.memory[0x300..0x303], [0xA2, 0x1F, 0x00]);
+
+        assert!(MemoryView::edit(&mut cpu, 0x300, "A2F").is_err());
+        assert!(MemoryView::edit(&mut cpu, 0x300, "ZZ").is_err());
+        assert_eq!(cpu.memory[0x300], 0xA2);
+    }
+
+    #[test]
+    fn mapped_ram_outlives_resets() {
+        let mut cpu = CpuBuilder::new().build();
+        cpu.map(0xE00..0x1000, [0; 0x1000]);
+
+        // LD I, 0xE00; LD V0, [I]; ADD V0, 1; LD [I], V0
+        cpu.load_program(&[0xAE, 0x00, 0xF0, 0x65, 0x70, 0x01, 0xF0, 0x55]).unwrap();
+        cpu.run_cycles(4);
+        cpu.reset();
+        cpu.run_cycles(4);
+
+        assert_eq!(cpu.registers(0), 2);
+        assert_eq!(cpu.memory[0xE00], 0);
+    }
+
+    #[test]
+    fn instructions_from_devices_are_never_cached() {
+        // hands out JP 0x200, then LD V0, 1
+        struct Toggle(bool);
+        impl Bus for Toggle {
+            fn read(&mut self, addr: usize) -> Byte {
+                if addr == 0x200 {
+                    self.0 = !self.0;
+                }
+                match (addr, self.0) {
+                    (0x200, true) => 0x12,
+                    (0x200, false) => 0x60,
+                    (_, true) => 0x00,
+                    (_, false) => 0x01,
+                }
+            }
+
+            fn write(&mut self, _addr: usize, _value: Byte) {}
+        }
+
+        let mut cpu = CpuBuilder::new().engine(ExecutionEngine::Cached).build();
+        cpu.map(0x200..0x202, Toggle(false));
+        cpu.run_cycles(2);
+
+        assert_eq!(cpu.registers(0), 1);
+    }
+
+    #[test]
+    fn poke_writes_the_memory_under_mapped_devices() {
+        let mut cpu = CpuBuilder::new().build();
+        cpu.map(0xE00..0x1000, [0x55; 0x1000]);
+        cpu.poke(0xE00, &[0xAB]).unwrap();
+        assert_eq!(cpu.memory()[0xE00], 0xAB);
+
+        // the program still sees the device
+        // LD I, 0xE00; LD V0, [I]
+        cpu.load_program(&[0xAE, 0x00, 0xF0, 0x65]).unwrap();
+        cpu.run_cycles(2);
+        assert_eq!(cpu.registers(0), 0x55);
+    }
+
+    #[test]
+    fn poke_refuses_the_interpreter_and_past_the_end() {
+        let mut cpu = CpuBuilder::new().build();
+        let font = cpu.memory[0];
+
+        assert_eq!(cpu.poke(0x000, &[0xFF]), Err(CpuError::ProtectedMemory(0x000)));
+        assert_eq!(cpu.poke(0xFFF, &[1, 2]), Err(CpuError::MemoryOutOfBounds(0x1000)));
+        assert_eq!(cpu.memory[0], font);
+        assert_eq!(cpu.memory[0xFFF], 0);
+
+        assert_eq!(cpu.poke(0x200, &[0x12, 0x00]), Ok(()));
+        assert_eq!(cpu.memory[0x200], 0x12);
+    }
+
+    #[test]
+    fn the_battery_outlives_the_cpu() {
+        let path = std::env::temp_dir().join(format!("chip_8_battery_test_{}", std::process::id()));
+        let _ = fs::remove_file(&path);
+        // LD I, 0xF00; LD V0, [I]; ADD V0, 1; LD [I], V0
+        let rom = [0xAF, 0x00, 0xF0, 0x65, 0x70, 0x01, 0xF0, 0x55];
+
+        for _ in 0..2 {
+            let mut cpu = CpuBuilder::new().memory(rom).battery(&path).build();
+            cpu.run_cycles(4);
+        }
+        let mut cpu = CpuBuilder::new().memory(rom).battery(&path).build();
+        cpu.run_cycles(4);
+        drop(cpu);
+
+        let saved = fs::read(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(saved.len(), PAGE_BYTES);
+        assert_eq!(saved[0], 3);
+    }
+}
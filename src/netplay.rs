@@ -0,0 +1,374 @@
+//! Two player netplay: two machines running the same ROM in lockstep over TCP
+//!
+//! Plenty of CHIP-8 games put both players on the one keypad. A [`Session`]
+//! on each side sends its player's keys every frame and runs the frame with
+//! both players' keys held, so the two machines run exactly the same
+//! instructions. The host picks the seed for CXNN's random numbers so those
+//! agree too, and every packet carries a hash of the sender's machine, so a
+//! desync stops the session instead of being played through.
+//!
+//! Keys are sent `delay` frames ahead of when they're pressed, which gives
+//! them that long to cross the network before the other side needs them.
+//! More delay hides more latency but makes the controls feel slower; at
+//! 60 frames a second, 3 or 4 frames covers most home connections.
+//!
+//! A [`Rollback`] session doesn't wait for the other player's keys at all:
+//! it guesses them, and when they turn out different it goes back to the
+//! frame they were for and runs forward again. That keeps the controls
+//! responsive whatever the latency, at the cost of the odd correction on
+//! screen.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use chip_8::netplay::Session;
+//! use chip_8::{Emulator, Keypad};
+//!
+//! let rom = std::fs::read("roms/pong.ch8").unwrap();
+//! // the other player runs Session::connect("host:8080", Emulator::default(), &rom)
+//! let mut session = Session::listen("0.0.0.0:8080", Emulator::default(), &rom, 3).unwrap();
+//!
+//! loop {
+//!     let mut keys = Keypad::new();
+//!     keys.set(0x1, true);
+//!     session.run_frame(keys).unwrap();
+//!     let _screen = session.emulator().frame();
+//! }
+//! ```
+
+use std::collections::VecDeque;
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+
+use sha1::{Digest, Sha1};
+
+use crate::{Emulator, Keypad, RomError, StopReason};
+
+mod rollback;
+
+pub use rollback::{Prediction, Rollback};
+
+/// Starts every handshake, so a connection from anything else is refused
+const MAGIC: &[u8; 4] = b"C8NP";
+
+/// Bumped whenever the packets change
+const VERSION: u8 = 2;
+
+/// Why a session couldn't start or carry on
+#[derive(Debug)]
+pub enum NetplayError {
+    /// The connection failed or closed
+    Io(io::Error),
+    /// The ROM couldn't be loaded
+    Rom(RomError),
+    /// The other side isn't a compatible chip_8, or is running another ROM
+    Handshake(String),
+    /// The two machines stopped matching at the start of this frame
+    Desync { frame: u32 },
+}
+
+impl fmt::Display for NetplayError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            NetplayError::Io(err) => write!(f, "Connection failed: {}", err),
+            NetplayError::Rom(err) => write!(f, "Couldn't load the ROM: {}", err),
+            NetplayError::Handshake(reason) => write!(f, "Couldn't start the session: {}", reason),
+            NetplayError::Desync { frame } => write!(f, "The machines stopped matching at frame {}", frame),
+        }
+    }
+}
+
+impl Error for NetplayError {}
+
+impl From<io::Error> for NetplayError {
+    fn from(err: io::Error) -> Self {
+        NetplayError::Io(err)
+    }
+}
+
+impl From<RomError> for NetplayError {
+    fn from(err: RomError) -> Self {
+        NetplayError::Rom(err)
+    }
+}
+
+/// One side of a two player game, run a frame at a time
+pub struct Session<S> {
+    stream: S,
+    emulator: Emulator,
+    delay: u32,
+    frame: u32,
+    // the keys this side has sent but not yet played, oldest first
+    local: VecDeque<Keypad>,
+    // this side's hash at the start of the frames the other side's packets
+    // still have to be checked against, oldest first
+    hashes: VecDeque<u32>,
+}
+
+impl Session<TcpStream> {
+    /// Waits on `addr` for the other player to connect, then hosts a
+    /// session with `delay` frames of input delay
+    pub fn listen<A: ToSocketAddrs>(addr: A, emulator: Emulator, rom: &[u8], delay: u8) -> Result<Self, NetplayError> {
+        let (stream, _) = TcpListener::bind(addr)?.accept()?;
+        stream.set_nodelay(true)?;
+        Session::host(stream, emulator, rom, delay)
+    }
+
+    /// Connects to a player waiting in [`listen`](Session::listen) and
+    /// joins their session
+    pub fn connect<A: ToSocketAddrs>(addr: A, emulator: Emulator, rom: &[u8]) -> Result<Self, NetplayError> {
+        let stream = TcpStream::connect(addr)?;
+        stream.set_nodelay(true)?;
+        Session::join(stream, emulator, rom)
+    }
+}
+
+impl<S: Read + Write> Session<S> {
+    /// Hosts a session over an already open connection, picking the seed
+    /// and input delay, then loads `rom` from scratch
+    pub fn host(mut stream: S, emulator: Emulator, rom: &[u8], delay: u8) -> Result<Self, NetplayError> {
+        let seed = greet_guest(&mut stream, rom, delay, 0)?;
+        Session::start(stream, emulator, rom, seed, delay)
+    }
+
+    /// Joins a session hosted over an already open connection, then loads
+    /// `rom` from scratch
+    pub fn join(mut stream: S, emulator: Emulator, rom: &[u8]) -> Result<Self, NetplayError> {
+        let (seed, delay, _) = greet_host(&mut stream, rom, false)?;
+        Session::start(stream, emulator, rom, seed, delay)
+    }
+
+    fn start(stream: S, mut emulator: Emulator, rom: &[u8], seed: u64, delay: u8) -> Result<Self, NetplayError> {
+        emulator.cpu_mut().set_seed(seed);
+        emulator.load_rom(rom)?;
+
+        let delay = delay as u32;
+        Ok(Session {
+            stream,
+            emulator,
+            delay,
+            frame: 0,
+            // nobody has pressed anything during the first `delay` frames
+            local: (0..delay).map(|_| Keypad::new()).collect(),
+            hashes: VecDeque::new(),
+        })
+    }
+
+    /// Sends this player's `keys` and runs the next frame with both
+    /// players' keys held
+    ///
+    /// `keys` take effect `delay` frames from now. Blocks until the other
+    /// player's keys for this frame arrive.
+    pub fn run_frame(&mut self, keys: Keypad) -> Result<StopReason, NetplayError> {
+        let hash = self.hash();
+        self.hashes.push_back(hash);
+
+        let mut packet = (self.frame + self.delay).to_le_bytes().to_vec();
+        packet.extend_from_slice(&keys.bits().to_le_bytes());
+        packet.extend_from_slice(&hash.to_le_bytes());
+        self.stream.write_all(&packet)?;
+        self.stream.flush()?;
+        self.local.push_back(keys);
+
+        let remote = if self.frame < self.delay { Keypad::new() } else { self.receive()? };
+        let local = self.local.pop_front().unwrap_or_default();
+        hold(&mut self.emulator, local, remote);
+
+        self.frame += 1;
+        Ok(self.emulator.run_frame())
+    }
+
+    /// Reads the other player's keys for this frame, checking their machine
+    /// matched this one `delay` frames ago
+    fn receive(&mut self) -> Result<Keypad, NetplayError> {
+        let mut packet = [0; 10];
+        self.stream.read_exact(&mut packet)?;
+        let frame = u32::from_le_bytes([packet[0], packet[1], packet[2], packet[3]]);
+        let keys = u16::from_le_bytes([packet[4], packet[5]]);
+        let hash = u32::from_le_bytes([packet[6], packet[7], packet[8], packet[9]]);
+
+        if frame != self.frame {
+            let message = format!("expected keys for frame {}, got frame {}", self.frame, frame);
+            return Err(io::Error::new(io::ErrorKind::InvalidData, message).into());
+        }
+        if self.hashes.pop_front() != Some(hash) {
+            return Err(NetplayError::Desync { frame: self.frame - self.delay });
+        }
+        Ok(Keypad::from_bits(keys))
+    }
+
+    /// The low half of the machine's [`state_hash`](crate::Cpu::state_hash),
+    /// which any desync shows up in sooner or later
+    fn hash(&self) -> u32 {
+        self.emulator.cpu().state_hash() as u32
+    }
+
+    /// The machine being played, for drawing the screen and playing the tone
+    pub fn emulator(&self) -> &Emulator {
+        &self.emulator
+    }
+
+    /// How many frames have run
+    pub fn frame(&self) -> u32 {
+        self.frame
+    }
+
+    /// How many frames after they're pressed keys take effect
+    pub fn delay(&self) -> u32 {
+        self.delay
+    }
+}
+
+/// Presses whatever either player holds on the shared keypad, and releases
+/// everything else
+fn hold(emulator: &mut Emulator, local: Keypad, remote: Keypad) {
+    let held = Keypad::from_bits(local.bits() | remote.bits());
+    for key in 0..16 {
+        if held.is_pressed(key) != emulator.cpu().keypad().is_pressed(key) {
+            if held.is_pressed(key) {
+                emulator.key_down(key);
+            } else {
+                emulator.key_up(key);
+            }
+        }
+    }
+}
+
+/// The host's half of the handshake: sends the seed it picks, the input
+/// delay and the rollback window, 0 for lockstep, and checks the guest's
+/// reply, returning the seed
+fn greet_guest<S: Read + Write>(stream: &mut S, rom: &[u8], delay: u8, window: u8) -> Result<u64, NetplayError> {
+    let seed: u64 = rand::random();
+    let mut hello = MAGIC.to_vec();
+    hello.push(VERSION);
+    hello.extend_from_slice(&seed.to_le_bytes());
+    hello.push(delay);
+    hello.push(window);
+    hello.extend_from_slice(&Sha1::digest(rom));
+    stream.write_all(&hello)?;
+    stream.flush()?;
+
+    let mut reply = [0; 26];
+    stream.read_exact(&mut reply)?;
+    check_hello(&reply[..5], &reply[6..], rom)?;
+    check_mode(reply[5] == 1, window > 0, "the other side")?;
+    Ok(seed)
+}
+
+/// The guest's half of the handshake, returning the seed, input delay and
+/// rollback window the host picked
+fn greet_host<S: Read + Write>(stream: &mut S, rom: &[u8], rollback: bool) -> Result<(u64, u8, u8), NetplayError> {
+    let mut hello = [0; 35];
+    stream.read_exact(&mut hello)?;
+
+    // reply before checking, so the host finds out what went wrong too
+    let mut reply = MAGIC.to_vec();
+    reply.push(VERSION);
+    reply.push(rollback as u8);
+    reply.extend_from_slice(&Sha1::digest(rom));
+    stream.write_all(&reply)?;
+    stream.flush()?;
+
+    check_hello(&hello[..5], &hello[15..], rom)?;
+    check_mode(hello[14] > 0, rollback, "the host")?;
+    let mut seed = [0; 8];
+    seed.copy_from_slice(&hello[5..13]);
+    Ok((u64::from_le_bytes(seed), hello[13], hello[14]))
+}
+
+/// Checks both sides are playing with rollback, or both in lockstep
+fn check_mode(theirs: bool, ours: bool, who: &str) -> Result<(), NetplayError> {
+    let name = |rollback| if rollback { "rollback" } else { "lockstep" };
+    if theirs != ours {
+        return Err(NetplayError::Handshake(format!("{} is playing {}, not {}", who, name(theirs), name(ours))));
+    }
+    Ok(())
+}
+
+/// Checks the other side's magic, version and ROM hash against ours
+fn check_hello(header: &[u8], rom_hash: &[u8], rom: &[u8]) -> Result<(), NetplayError> {
+    if &header[..4] != MAGIC {
+        return Err(NetplayError::Handshake(String::from("the other side isn't chip_8 netplay")));
+    }
+    if header[4] != VERSION {
+        return Err(NetplayError::Handshake(format!("the other side speaks version {}, not {}", header[4], VERSION)));
+    }
+    if rom_hash != Sha1::digest(rom).as_slice() {
+        return Err(NetplayError::Handshake(String::from("the other side is running a different ROM")));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Barrier};
+    use std::thread;
+
+    use super::*;
+    use crate::{CpuState, Reg};
+
+    // LD V2, 1; SKNP V2; ADD V3, 1; LD V2, 2; SKNP V2; ADD V4, 1; RND V5, 0xFF; JP 0x200
+    const ROM: [u8; 16] = [0x62, 0x01, 0xE2, 0xA1, 0x73, 0x01, 0x62, 0x02, 0xE2, 0xA1, 0x74, 0x01, 0xC5, 0xFF, 0x12, 0x00];
+
+    /// Runs a host and guest for `frames`, the host holding key 1 and the
+    /// guest key 2, and returns both machines' states
+    fn play(guest_rom: &'static [u8], frames: u32, desync: bool) -> (Result<CpuState, NetplayError>, Result<CpuState, NetplayError>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        // neither side hangs up until both are done, even on a desync, or
+        // the other side's last packets would find the connection closed
+        let done = Arc::new(Barrier::new(2));
+        let guest_done = done.clone();
+        let guest = thread::spawn(move || {
+            let mut session = Session::connect(addr, Emulator::default(), guest_rom)?;
+            if desync {
+                session.emulator.cpu_mut().write_register(Reg::nibble(0xA), 1);
+            }
+            let mut keys = Keypad::new();
+            keys.set(0x2, true);
+            let played = (0..frames).try_for_each(|_| session.run_frame(keys).map(|_| ()));
+            guest_done.wait();
+            played.map(|_| session.emulator().cpu().state())
+        });
+
+        let stream = listener.accept().unwrap().0;
+        let host = Session::host(stream, Emulator::default(), &ROM, 2).and_then(|mut session| {
+            let mut keys = Keypad::new();
+            keys.set(0x1, true);
+            let played = (0..frames).try_for_each(|_| session.run_frame(keys).map(|_| ()));
+            done.wait();
+            played.map(|_| session.emulator().cpu().state())
+        });
+
+        (host, guest.join().unwrap())
+    }
+
+    #[test]
+    fn both_sides_run_the_same_frames() {
+        let (host, guest) = play(&ROM, 20, false);
+        let (host, guest) = (host.unwrap(), guest.unwrap());
+
+        assert_eq!(host, guest);
+        // both players' keys counted, and both from the same frame on
+        assert!(host.registers[4] > 0);
+        assert!((host.registers[3] as i32 - host.registers[4] as i32).abs() <= 1);
+    }
+
+    #[test]
+    fn different_roms_are_refused() {
+        let (host, guest) = play(&[0x12, 0x00], 1, false);
+
+        assert!(matches!(host, Err(NetplayError::Handshake(_))));
+        assert!(matches!(guest, Err(NetplayError::Handshake(_))));
+    }
+
+    #[test]
+    fn desyncs_stop_the_session() {
+        let (host, _) = play(&ROM, 5, true);
+
+        assert!(matches!(host, Err(NetplayError::Desync { frame: 0 })));
+    }
+}
@@ -0,0 +1,493 @@
+//! Rollback netplay: guessing the other player's keys and correcting
+//! course when they turn out different
+
+use std::collections::VecDeque;
+use std::io::{self, Read, Write};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use std::thread;
+use std::time::Duration;
+
+use super::{greet_guest, greet_host, hold, NetplayError};
+use crate::{Emulator, Keypad, StopReason};
+
+/// How long to sleep between looks at the connection while waiting for
+/// the other player's keys
+const POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+/// A frame number, keys, and the sender's last confirmed frame and hash
+const PACKET_SIZE: usize = 14;
+
+/// How a [`Rollback`] session hides latency, picked by the host
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Prediction {
+    /// Frames of input delay, as in lockstep; a frame or two means fewer
+    /// and shorter corrections
+    pub delay: u8,
+    /// The most frames to run ahead on guesses before waiting for the other
+    /// player's keys, at least 1
+    pub window: u8,
+}
+
+impl Default for Prediction {
+    fn default() -> Self {
+        Prediction { delay: 1, window: 8 }
+    }
+}
+
+/// One side of a two player game with rollback, run a frame at a time
+///
+/// The other player's keys are guessed to be whatever they last held. Each
+/// frame keeps a clone of the machine from its start, so when their real
+/// keys arrive and differ, the session goes back to that clone and runs the
+/// frames since again. Only the frames since the last one both players'
+/// keys are known for are kept; anything attached to the CPU, such as an
+/// observer, sees the frames that are run again twice.
+///
+/// Reading the connection shouldn't block: [`listen`](Rollback::listen) and
+/// [`connect`](Rollback::connect) see to that, and a blocking stream passed
+/// to [`host`](Rollback::host) or [`join`](Rollback::join) makes every
+/// frame wait for the other player, like lockstep.
+pub struct Rollback<S> {
+    stream: S,
+    emulator: Emulator,
+    prediction: Prediction,
+    frame: u32,
+    // every frame before this has both players' keys, so is never run again
+    confirmed: u32,
+    // the machine at the start of each frame from `confirmed` up to `frame`
+    history: VecDeque<Emulator>,
+    // this side's keys for each frame from `confirmed` on
+    local: VecDeque<Keypad>,
+    // the other side's keys for each frame from `confirmed` on, as far as
+    // they've arrived
+    remote: VecDeque<Keypad>,
+    // the other side's keys each frame from `confirmed` on was last run with
+    played: VecDeque<Keypad>,
+    // the other side's keys for the frame before `confirmed`
+    last_remote: Keypad,
+    // bytes read that don't make up a whole packet yet
+    incoming: Vec<u8>,
+    // each side's hash at the start of confirmed frames, still to be
+    // compared with the other's, oldest first
+    local_hashes: VecDeque<(u32, u32)>,
+    remote_hashes: VecDeque<(u32, u32)>,
+    rollbacks: u64,
+}
+
+impl Rollback<TcpStream> {
+    /// Waits on `addr` for the other player to connect, then hosts a
+    /// session with the given `prediction`
+    pub fn listen<A: ToSocketAddrs>(
+        addr: A,
+        emulator: Emulator,
+        rom: &[u8],
+        prediction: Prediction,
+    ) -> Result<Self, NetplayError> {
+        let (stream, _) = TcpListener::bind(addr)?.accept()?;
+        stream.set_nodelay(true)?;
+        let session = Rollback::host(stream, emulator, rom, prediction)?;
+        session.stream.set_nonblocking(true)?;
+        Ok(session)
+    }
+
+    /// Connects to a player waiting in [`listen`](Rollback::listen) and
+    /// joins their session
+    pub fn connect<A: ToSocketAddrs>(addr: A, emulator: Emulator, rom: &[u8]) -> Result<Self, NetplayError> {
+        let stream = TcpStream::connect(addr)?;
+        stream.set_nodelay(true)?;
+        let session = Rollback::join(stream, emulator, rom)?;
+        session.stream.set_nonblocking(true)?;
+        Ok(session)
+    }
+}
+
+impl<S: Read + Write> Rollback<S> {
+    /// Hosts a session over an already open connection, picking the seed
+    /// and `prediction`, then loads `rom` from scratch
+    pub fn host(mut stream: S, emulator: Emulator, rom: &[u8], prediction: Prediction) -> Result<Self, NetplayError> {
+        let prediction = Prediction { window: prediction.window.max(1), ..prediction };
+        let seed = greet_guest(&mut stream, rom, prediction.delay, prediction.window)?;
+        Rollback::start(stream, emulator, rom, seed, prediction)
+    }
+
+    /// Joins a session hosted over an already open connection, then loads
+    /// `rom` from scratch
+    pub fn join(mut stream: S, emulator: Emulator, rom: &[u8]) -> Result<Self, NetplayError> {
+        let (seed, delay, window) = greet_host(&mut stream, rom, true)?;
+        Rollback::start(stream, emulator, rom, seed, Prediction { delay, window })
+    }
+
+    fn start(
+        stream: S,
+        mut emulator: Emulator,
+        rom: &[u8],
+        seed: u64,
+        prediction: Prediction,
+    ) -> Result<Self, NetplayError> {
+        emulator.cpu_mut().set_seed(seed);
+        emulator.load_rom(rom)?;
+
+        // nobody has pressed anything during the first `delay` frames
+        let idle: VecDeque<_> = (0..prediction.delay).map(|_| Keypad::new()).collect();
+        let hash = emulator.cpu().state_hash() as u32;
+        Ok(Rollback {
+            stream,
+            emulator,
+            prediction,
+            frame: 0,
+            confirmed: 0,
+            history: VecDeque::new(),
+            local: idle.clone(),
+            remote: idle,
+            played: VecDeque::new(),
+            last_remote: Keypad::new(),
+            incoming: Vec::new(),
+            local_hashes: VecDeque::from(vec![(0, hash)]),
+            remote_hashes: VecDeque::new(),
+            rollbacks: 0,
+        })
+    }
+
+    /// Sends this player's `keys` and runs the next frame, guessing the
+    /// other player's keys if they haven't arrived
+    ///
+    /// `keys` take effect `delay` frames from now. Only blocks when the
+    /// session is a whole window of frames ahead of the other player's keys.
+    pub fn run_frame(&mut self, keys: Keypad) -> Result<StopReason, NetplayError> {
+        self.send(keys)?;
+        self.local.push_back(keys);
+        self.poll()?;
+        while self.frame - self.confirmed >= self.prediction.window as u32 {
+            self.wait()?;
+        }
+
+        self.history.push_back(self.emulator.clone());
+        let remote = self.guess(self.frame);
+        self.played.push_back(remote);
+        hold(&mut self.emulator, self.local[(self.frame - self.confirmed) as usize], remote);
+        self.frame += 1;
+        let reason = self.emulator.run_frame();
+
+        // the frame may have run on keys that had already arrived
+        self.confirm();
+        self.check_hashes()?;
+        Ok(reason)
+    }
+
+    /// Waits until the other player's keys have arrived for every frame
+    /// run, so the machine is exactly what it is on the other side
+    pub fn settle(&mut self) -> Result<(), NetplayError> {
+        self.poll()?;
+        while self.confirmed < self.frame {
+            self.wait()?;
+        }
+        Ok(())
+    }
+
+    /// Sends this side's keys for the frame `delay` from now, with the
+    /// last frame both players' keys are known for and its hash
+    fn send(&mut self, keys: Keypad) -> Result<(), NetplayError> {
+        let confirmed = self.history.front().unwrap_or(&self.emulator);
+        let mut packet = (self.frame + self.prediction.delay as u32).to_le_bytes().to_vec();
+        packet.extend_from_slice(&keys.bits().to_le_bytes());
+        packet.extend_from_slice(&self.confirmed.to_le_bytes());
+        packet.extend_from_slice(&(confirmed.cpu().state_hash() as u32).to_le_bytes());
+        self.stream.write_all(&packet)?;
+        self.stream.flush()?;
+        Ok(())
+    }
+
+    /// Sleeps until something more arrives
+    fn wait(&mut self) -> Result<(), NetplayError> {
+        while !self.poll()? {
+            thread::sleep(POLL_INTERVAL);
+        }
+        Ok(())
+    }
+
+    /// Takes in whatever the other player has sent, going back and running
+    /// again from the first frame it shows was guessed wrong
+    ///
+    /// Returns whether anything arrived.
+    fn poll(&mut self) -> Result<bool, NetplayError> {
+        let mut buffer = [0; 1024];
+        let read = match self.stream.read(&mut buffer) {
+            Ok(0) => return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into()),
+            Ok(read) => read,
+            Err(err) if err.kind() == io::ErrorKind::WouldBlock || err.kind() == io::ErrorKind::Interrupted => {
+                return Ok(false)
+            }
+            Err(err) => return Err(err.into()),
+        };
+        self.incoming.extend_from_slice(&buffer[..read]);
+
+        let mut wrong = None;
+        let whole = self.incoming.len() - self.incoming.len() % PACKET_SIZE;
+        let packets: Vec<u8> = self.incoming.drain(..whole).collect();
+        for packet in packets.chunks(PACKET_SIZE) {
+            let frame = u32::from_le_bytes([packet[0], packet[1], packet[2], packet[3]]);
+            let keys = Keypad::from_bits(u16::from_le_bytes([packet[4], packet[5]]));
+            let confirmed = u32::from_le_bytes([packet[6], packet[7], packet[8], packet[9]]);
+            let hash = u32::from_le_bytes([packet[10], packet[11], packet[12], packet[13]]);
+
+            let expected = self.confirmed + self.remote.len() as u32;
+            if frame != expected {
+                let message = format!("expected keys for frame {}, got frame {}", expected, frame);
+                return Err(io::Error::new(io::ErrorKind::InvalidData, message).into());
+            }
+            let guessed = self.played.get(self.remote.len()).copied();
+            if wrong.is_none() && guessed.is_some_and(|guessed| guessed != keys) {
+                wrong = Some(frame);
+            }
+            self.remote.push_back(keys);
+            if self.remote_hashes.back().is_none_or(|&(last, _)| confirmed > last) {
+                self.remote_hashes.push_back((confirmed, hash));
+            }
+        }
+
+        if let Some(frame) = wrong {
+            self.replay_from(frame);
+        }
+        self.confirm();
+        self.check_hashes()?;
+        Ok(true)
+    }
+
+    /// Goes back to the start of `from` and runs up to the current frame
+    /// again with what's known of the other player's keys now
+    fn replay_from(&mut self, from: u32) {
+        self.rollbacks += 1;
+        self.emulator.roll_back(&self.history[(from - self.confirmed) as usize]);
+        for frame in from..self.frame {
+            let ind = (frame - self.confirmed) as usize;
+            self.history[ind] = self.emulator.clone();
+            let remote = self.guess(frame);
+            self.played[ind] = remote;
+            hold(&mut self.emulator, self.local[ind], remote);
+            self.emulator.run_frame();
+        }
+    }
+
+    /// Lets go of the frames that both players' keys have arrived for
+    fn confirm(&mut self) {
+        while self.confirmed < self.frame && !self.remote.is_empty() {
+            self.last_remote = self.remote.pop_front().unwrap_or_default();
+            self.local.pop_front();
+            self.played.pop_front();
+            self.history.pop_front();
+            self.confirmed += 1;
+
+            let state = self.history.front().unwrap_or(&self.emulator);
+            self.local_hashes.push_back((self.confirmed, state.cpu().state_hash() as u32));
+        }
+    }
+
+    /// Compares the hashes both sides have for the same confirmed frames,
+    /// which any desync shows up in sooner or later
+    fn check_hashes(&mut self) -> Result<(), NetplayError> {
+        while let (Some(&(ours, local)), Some(&(theirs, remote))) =
+            (self.local_hashes.front(), self.remote_hashes.front())
+        {
+            if ours == theirs && local != remote {
+                return Err(NetplayError::Desync { frame: ours });
+            }
+            if ours <= theirs {
+                self.local_hashes.pop_front();
+            }
+            if theirs <= ours {
+                self.remote_hashes.pop_front();
+            }
+        }
+        Ok(())
+    }
+
+    /// The other player's keys for `frame`, or the last they're known to
+    /// have held if they haven't arrived
+    fn guess(&self, frame: u32) -> Keypad {
+        let ind = (frame - self.confirmed) as usize;
+        match self.remote.get(ind) {
+            Some(&keys) => keys,
+            None => self.remote.back().copied().unwrap_or(self.last_remote),
+        }
+    }
+
+    /// The machine being played, for drawing the screen and playing the tone
+    pub fn emulator(&self) -> &Emulator {
+        &self.emulator
+    }
+
+    /// How many frames have run
+    pub fn frame(&self) -> u32 {
+        self.frame
+    }
+
+    /// How the session is hiding latency
+    pub fn prediction(&self) -> Prediction {
+        self.prediction
+    }
+
+    /// How many frames have been run on guesses that still need the other
+    /// player's keys
+    pub fn frames_ahead(&self) -> u32 {
+        self.frame - self.confirmed
+    }
+
+    /// How many times a wrong guess has meant going back
+    pub fn rollbacks(&self) -> u64 {
+        self.rollbacks
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use std::sync::{Arc, Barrier};
+
+    use super::*;
+    use crate::netplay::Session;
+    use crate::CpuState;
+
+    // LD V2, 1; SKNP V2; ADD V3, 1; LD V2, 2; SKNP V2; ADD V4, 1; RND V5, 0xFF; JP 0x200
+    const ROM: [u8; 16] = [0x62, 0x01, 0xE2, 0xA1, 0x73, 0x01, 0x62, 0x02, 0xE2, 0xA1, 0x74, 0x01, 0xC5, 0xFF, 0x12, 0x00];
+
+    /// One end of an in-memory connection that never blocks
+    struct Pipe {
+        incoming: Rc<RefCell<VecDeque<u8>>>,
+        outgoing: Rc<RefCell<VecDeque<u8>>>,
+    }
+
+    impl Read for Pipe {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let mut incoming = self.incoming.borrow_mut();
+            if incoming.is_empty() {
+                return Err(io::ErrorKind::WouldBlock.into());
+            }
+            let read = buf.len().min(incoming.len());
+            buf.iter_mut().zip(incoming.drain(..read)).for_each(|(byte, sent)| *byte = sent);
+            Ok(read)
+        }
+    }
+
+    impl Write for Pipe {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.outgoing.borrow_mut().extend(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// Two sessions joined by pipes, with no input delay
+    fn pair(seed: u64) -> (Rollback<Pipe>, Rollback<Pipe>) {
+        let (there, back) = (Rc::new(RefCell::new(VecDeque::new())), Rc::new(RefCell::new(VecDeque::new())));
+        let prediction = Prediction { delay: 0, window: 8 };
+        let host = Pipe { incoming: back.clone(), outgoing: there.clone() };
+        let guest = Pipe { incoming: there, outgoing: back };
+        (
+            Rollback::start(host, Emulator::default(), &ROM, seed, prediction).unwrap(),
+            Rollback::start(guest, Emulator::default(), &ROM, seed, prediction).unwrap(),
+        )
+    }
+
+    fn keys(key: u8) -> Keypad {
+        let mut keys = Keypad::new();
+        keys.set(key, true);
+        keys
+    }
+
+    #[test]
+    fn wrong_guesses_are_rolled_back() {
+        let (mut host, mut guest) = pair(7);
+
+        // the host runs ahead guessing the guest holds nothing, which is wrong
+        for _ in 0..5 {
+            host.run_frame(keys(0x1)).unwrap();
+        }
+        assert_eq!(host.frames_ahead(), 5);
+        for _ in 0..6 {
+            guest.run_frame(keys(0x2)).unwrap();
+        }
+        host.run_frame(keys(0x1)).unwrap();
+        host.settle().unwrap();
+        guest.settle().unwrap();
+
+        let mut played = Emulator::default();
+        played.cpu_mut().set_seed(7);
+        played.load_rom(&ROM).unwrap();
+        played.key_down(0x1);
+        played.key_down(0x2);
+        for _ in 0..6 {
+            played.run_frame();
+        }
+        assert_eq!((host.rollbacks(), guest.rollbacks()), (1, 0));
+        assert_eq!(host.emulator(), &played);
+        assert_eq!(guest.emulator(), &played);
+    }
+
+    #[test]
+    fn desyncs_stop_the_session() {
+        let (mut host, mut guest) = pair(7);
+        guest.emulator.cpu_mut().set_seed(8);
+
+        host.run_frame(Keypad::new()).unwrap();
+        guest.run_frame(Keypad::new()).unwrap();
+        guest.run_frame(Keypad::new()).unwrap();
+        assert!(matches!(host.run_frame(Keypad::new()), Err(NetplayError::Desync { frame: 1 })));
+    }
+
+    /// Runs a host and guest over TCP for `frames`, each pressing their key
+    /// every other frame, and returns both machines' states once settled
+    fn play(frames: u32) -> (Result<CpuState, NetplayError>, Result<CpuState, NetplayError>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let done = Arc::new(Barrier::new(2));
+        let guest_done = done.clone();
+        let guest = thread::spawn(move || {
+            let mut session = Rollback::connect(addr, Emulator::default(), &ROM)?;
+            let played = (0..frames).try_for_each(|frame| {
+                session.run_frame(if frame % 2 == 0 { keys(0x2) } else { Keypad::new() }).map(|_| ())
+            });
+            let settled = played.and_then(|_| session.settle());
+            guest_done.wait();
+            settled.map(|_| session.emulator().cpu().state())
+        });
+
+        let stream = listener.accept().unwrap().0;
+        stream.set_nodelay(true).unwrap();
+        let host = Rollback::host(stream, Emulator::default(), &ROM, Prediction::default()).and_then(|mut session| {
+            session.stream.set_nonblocking(true)?;
+            let played = (0..frames).try_for_each(|frame| {
+                session.run_frame(if frame % 2 == 1 { keys(0x1) } else { Keypad::new() }).map(|_| ())
+            });
+            let settled = played.and_then(|_| session.settle());
+            done.wait();
+            settled.map(|_| session.emulator().cpu().state())
+        });
+
+        (host, guest.join().unwrap())
+    }
+
+    #[test]
+    fn both_sides_end_up_the_same_over_tcp() {
+        let (host, guest) = play(30);
+        let (host, guest) = (host.unwrap(), guest.unwrap());
+
+        assert_eq!(host, guest);
+        assert!(host.registers[3] > 0 && host.registers[4] > 0);
+    }
+
+    #[test]
+    fn lockstep_and_rollback_dont_mix() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let guest = thread::spawn(move || Rollback::connect(addr, Emulator::default(), &ROM).map(|_| ()));
+
+        let host = Session::host(listener.accept().unwrap().0, Emulator::default(), &ROM, 2);
+        assert!(matches!(host, Err(NetplayError::Handshake(_))));
+        assert!(matches!(guest.join().unwrap(), Err(NetplayError::Handshake(_))));
+    }
+}
@@ -0,0 +1,203 @@
+//! Hooks for watching what a program runs and writes
+//!
+//! An [`Observer`] is told about every instruction as it's about to run,
+//! and every register and memory write it makes along with the value it
+//! replaced, so tools like memory heatmaps don't have to diff whole dumps
+//! after every step. A CPU without one only pays for checking that it's
+//! missing.
+//!
+//! # Example
+//!
+//! ```
+//! use std::sync::{Arc, Mutex};
+//!
+//! use chip_8::observer::Observer;
+//! use chip_8::CpuBuilder;
+//!
+//! #[derive(Default)]
+//! struct Heatmap {
+//!     writes: Vec<usize>,
+//! }
+//!
+//! impl Observer for Heatmap {
+//!     fn on_memory_write(&mut self, addr: usize, _old: u8, _new: u8) {
+//!         self.writes.push(addr);
+//!     }
+//! }
+//!
+//! let mut memory = [0; 4096];
+//! // LD I, 0x300; LD [I], V0
+//! memory[..4].copy_from_slice(&[0xA3, 0x00, 0xF0, 0x55]);
+//! let mut cpu = CpuBuilder::new().memory(memory).build();
+//!
+//! let heatmap = Arc::new(Mutex::new(Heatmap::default()));
+//! cpu.set_observer(heatmap.clone());
+//! cpu.run_cycles(2);
+//!
+//! assert_eq!(heatmap.lock().unwrap().writes, [0x300]);
+//! ```
+
+use std::sync::{Arc, Mutex};
+
+use crate::instruction::Instruction;
+use crate::{Byte, Cpu};
+
+/// Something that wants to hear about instructions and writes as they happen
+///
+/// The callbacks do nothing by default, so only the interesting ones need
+/// implementing. The write callbacks are called for every write, even ones
+/// that store the value that was already there.
+///
+/// Observers have to be `Send` so a CPU with one can still move between
+/// threads.
+pub trait Observer: Send {
+    /// `instruction`, decoded from `addr`, is about to run
+    ///
+    /// Called even if the instruction goes on to fail or halts the program.
+    fn on_execute(&mut self, _addr: usize, _instruction: Instruction) {}
+
+    /// An instruction wrote `new` to memory at `addr`, replacing `old`
+    fn on_memory_write(&mut self, _addr: usize, _old: Byte, _new: Byte) {}
+
+    /// An instruction (or a key press finishing FX0A) wrote `new` to
+    /// register `reg`, replacing `old`
+    fn on_register_write(&mut self, _reg: Byte, _old: Byte, _new: Byte) {}
+}
+
+impl<O: Observer + ?Sized> Observer for Box<O> {
+    fn on_execute(&mut self, addr: usize, instruction: Instruction) {
+        (**self).on_execute(addr, instruction);
+    }
+
+    fn on_memory_write(&mut self, addr: usize, old: Byte, new: Byte) {
+        (**self).on_memory_write(addr, old, new);
+    }
+
+    fn on_register_write(&mut self, reg: Byte, old: Byte, new: Byte) {
+        (**self).on_register_write(reg, old, new);
+    }
+}
+
+/// Lets the caller keep a handle on the observer to read it back later
+///
+/// # Panics
+///
+/// The callbacks panic if the mutex has been poisoned
+impl<O: Observer + ?Sized> Observer for Arc<Mutex<O>> {
+    fn on_execute(&mut self, addr: usize, instruction: Instruction) {
+        self.lock().unwrap().on_execute(addr, instruction);
+    }
+
+    fn on_memory_write(&mut self, addr: usize, old: Byte, new: Byte) {
+        self.lock().unwrap().on_memory_write(addr, old, new);
+    }
+
+    fn on_register_write(&mut self, reg: Byte, old: Byte, new: Byte) {
+        self.lock().unwrap().on_register_write(reg, old, new);
+    }
+}
+
+/// Tells both observers, the first one first, so a CPU can feed two
+/// tools at once
+impl<A: Observer, B: Observer> Observer for (A, B) {
+    fn on_execute(&mut self, addr: usize, instruction: Instruction) {
+        self.0.on_execute(addr, instruction);
+        self.1.on_execute(addr, instruction);
+    }
+
+    fn on_memory_write(&mut self, addr: usize, old: Byte, new: Byte) {
+        self.0.on_memory_write(addr, old, new);
+        self.1.on_memory_write(addr, old, new);
+    }
+
+    fn on_register_write(&mut self, reg: Byte, old: Byte, new: Byte) {
+        self.0.on_register_write(reg, old, new);
+        self.1.on_register_write(reg, old, new);
+    }
+}
+
+impl Cpu {
+    /// Starts telling `observer` about every write, replacing any observer
+    /// that was already set
+    pub fn set_observer(&mut self, observer: impl Observer + 'static) {
+        self.observer = Some(Box::new(observer));
+    }
+
+    /// Stops observing, handing back the observer if there was one
+    ///
+    /// Resetting or restoring a snapshot replaces memory and registers
+    /// wholesale without telling the observer, so it may want to start over.
+    pub fn take_observer(&mut self) -> Option<Box<dyn Observer>> {
+        self.observer.take()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CpuBuilder;
+
+    #[derive(Default)]
+    struct Recorder {
+        memory: Vec<(usize, Byte, Byte)>,
+        registers: Vec<(Byte, Byte, Byte)>,
+    }
+
+    impl Observer for Recorder {
+        fn on_memory_write(&mut self, addr: usize, old: Byte, new: Byte) {
+            self.memory.push((addr, old, new));
+        }
+
+        fn on_register_write(&mut self, reg: Byte, old: Byte, new: Byte) {
+            self.registers.push((reg, old, new));
+        }
+    }
+
+    #[test]
+    fn observer_sees_writes_with_old_values() {
+        let mut memory = [0; 0x1000];
+        // LD V1, 0x12; ADD V1, 0xF0; LD I, 0x300; BCD V1
+        memory[..8].copy_from_slice(&[0x61, 0x12, 0x71, 0xF0, 0xA3, 0x00, 0xF1, 0x33]);
+        let mut cpu = CpuBuilder::new().memory(memory).build();
+        cpu.memory[0x301] = 9;
+
+        let recorder = Arc::new(Mutex::new(Recorder::default()));
+        cpu.set_observer(recorder.clone());
+        cpu.run_cycles(4);
+
+        let recorder = recorder.lock().unwrap();
+        assert_eq!(recorder.registers, [(1, 0, 0x12), (1, 0x12, 0x02)]);
+        assert_eq!(recorder.memory, [(0x300, 0, 0), (0x301, 9, 0), (0x302, 0, 2)]);
+    }
+
+    #[test]
+    fn observer_hears_fx0a_key_press() {
+        let mut memory = [0; 0x1000];
+        // LD V4, K
+        memory[..2].copy_from_slice(&[0xF4, 0x0A]);
+        let mut cpu = CpuBuilder::new().memory(memory).build();
+
+        let recorder = Arc::new(Mutex::new(Recorder::default()));
+        cpu.set_observer(recorder.clone());
+        cpu.run();
+        cpu.set_key(0xC, true);
+        cpu.set_key(0xC, false);
+
+        assert_eq!(recorder.lock().unwrap().registers, [(4, 0, 0xC)]);
+    }
+
+    #[test]
+    fn take_observer_stops_callbacks() {
+        let mut memory = [0; 0x1000];
+        // LD V0, 1
+        memory[..2].copy_from_slice(&[0x60, 0x01]);
+        let mut cpu = CpuBuilder::new().memory(memory).build();
+
+        let recorder = Arc::new(Mutex::new(Recorder::default()));
+        cpu.set_observer(recorder.clone());
+        assert!(cpu.take_observer().is_some());
+        cpu.run();
+
+        assert!(recorder.lock().unwrap().registers.is_empty());
+    }
+}
@@ -0,0 +1,1200 @@
+//! The instruction handlers, one per opcode, that `Cpu::step` dispatches to
+//!
+//! The timer and keypad instructions live with the rest of the timers and
+//! keypad, in their own modules.
+
+use rand::Rng;
+
+use crate::flags::FLAG_COUNT;
+use crate::screen::{Rect, Resolution};
+use crate::{Addr, Address, Byte, Cpu, CpuError, Reg};
+
+impl Cpu {
+    /// Draws a sprite at coordinate (VX, VY) that has a width 
+    /// of 8 pixels and a height of N pixels. Each row of 8 pixels 
+    /// is read as bit-coded starting from memory location I; I value 
+    /// does not change after the execution of this instruction. As 
+    /// described above, VF is set to 1 if any screen pixels are flipped 
+    /// from set to unset when the sprite is drawn, and to 0 if that does not happen
+    ///
+    /// In SUPER-CHIP's high resolution, DXY0 draws a 16x16 sprite instead,
+    /// each row two bytes. With the `collision_rows` quirk, VF is there
+    /// the number of rows that flipped a pixel off or were clipped off the
+    /// bottom. MegaChip mode draws [its own sprites](Cpu::draw_mega).
+    // todo: implement wrapping for indices outside of screen (? not sure if needed)
+    pub(crate) fn draw(&mut self, x: Reg, y: Reg, d: Byte) -> Result<(), CpuError> {
+        if self.display.resolution() == Resolution::Mega {
+            return self.draw_mega(x, y);
+        }
+        let x_coord = self.registers[x] as usize;
+        let y_coord = self.registers[y] as usize;
+        let (width, height) = (self.display.width(), self.display.height());
+        let high_res = self.display.resolution() == Resolution::High;
+        let (rows, row_bytes) = if high_res && d == 0 { (16, 2) } else { (d as usize, 1) };
+        let sprite = self.i_range(rows * row_bytes)?;
+        if x_coord >= width || y_coord >= height {
+            warn!("sprite drawn off the screen at ({}, {}), wrapped back onto it", x_coord, y_coord);
+        }
+
+        let mut flip_vf = false;
+        // the pixels flipped, as left, top, right and bottom edges
+        let mut drawn = (width, height, 0, 0);
+        // rows that collided or were clipped off the bottom, for the
+        // `collision_rows` quirk
+        let mut rows_hit = 0;
+        for row in 0..rows {
+            let start = sprite.start + row * row_bytes;
+            let bits = (start..start + row_bytes).fold(0u16, |bits, addr| bits << 8 | self.read_memory(addr) as u16);
+            if self.quirks.clip_sprites && y_coord % height + row >= height {
+                rows_hit += rows - row;
+                break;
+            }
+            let y = (y_coord + row) % height;
+            let mut row_collided = false;
+
+            // the most significant bit is the leftmost pixel
+            let cols = row_bytes * 8;
+            for col in 0..cols {
+                if bits & (1 << (cols - 1 - col)) == 0 {
+                    continue;
+                }
+                if self.quirks.clip_sprites && x_coord % width + col >= width {
+                    break;
+                }
+
+                let x = (x_coord + col) % width;
+                drawn = (drawn.0.min(x), drawn.1.min(y), drawn.2.max(x + 1), drawn.3.max(y + 1));
+
+                // if a bit was set before, and just got unset, need to flip vf at end
+                if self.display.toggle(x, y) {
+                    flip_vf = true;
+                    row_collided = true;
+                }
+            }
+            rows_hit += row_collided as usize;
+        }
+        let (left, top, right, bottom) = drawn;
+        if left < right {
+            self.touch(Rect { x: left, y: top, width: right - left, height: bottom - top });
+        }
+
+        // SUPER-CHIP only counts rows in high resolution
+        if self.quirks.collision_rows && high_res {
+            self.write_register(Reg::VF, rows_hit as Byte);
+        } else {
+            self.write_register(Reg::VF, flip_vf as Byte);
+        }
+        Ok(())
+    }
+
+    /// Draws a MegaChip sprite at (VX, VY), as wide and tall as 03NN and
+    /// 04NN set, from one byte per pixel starting at I, each a palette colour
+    ///
+    /// Colour 0 is see-through, and the rest paint over whatever was there
+    /// rather than flipping it. VF is set to 1 if a lit pixel was painted
+    /// over. Sprites are cut off at the edges of the screen.
+    pub(crate) fn draw_mega(&mut self, x: Reg, y: Reg) -> Result<(), CpuError> {
+        let (left, top) = (self.registers[x] as usize, self.registers[y] as usize);
+        let size = |n: Byte| if n == 0 { 256 } else { n as usize };
+        let (width, height) = (size(self.sprite_size.0), size(self.sprite_size.1));
+        let sprite = self.i_range(width * height)?;
+        let shown_width = width.min(self.display.width().saturating_sub(left));
+        let shown_height = height.min(self.display.height().saturating_sub(top));
+
+        let mut collided = false;
+        for row in 0..shown_height {
+            for col in 0..shown_width {
+                let index = self.read_memory(sprite.start + row * width + col);
+                if index != 0 {
+                    collided |= self.display.paint(left + col, top + row, index);
+                }
+            }
+        }
+        if shown_width > 0 && shown_height > 0 {
+            self.touch(Rect { x: left, y: top, width: shown_width, height: shown_height });
+        }
+
+        self.write_register(Reg::VF, collided as Byte);
+        Ok(())
+    }
+
+    /// Moves the program_counter to the given address
+    pub(crate) fn jump(&mut self, addr: Addr) {
+        self.program_counter = addr.index();
+    }
+
+    /// Moves the program_counter to the given address + registers[0]
+    ///
+    /// With the `jump_uses_vx` quirk, adds registers[x] instead, x being
+    /// the highest nibble of the address
+    ///
+    /// This can land past the end of memory, which the next fetch reports
+    pub(crate) fn jump_reg(&mut self, addr: Addr) {
+        let x = if self.quirks.jump_uses_vx { addr.high_nibble() } else { Reg::V0 };
+        self.program_counter = self.registers[x] as usize + addr.index();
+    }
+
+    /// Moves the program_counter to the given address, maintaining
+    /// the old program_counter in the stack.
+    ///
+    /// Fails if the stack is as deep as it's allowed to go
+    pub(crate) fn call(&mut self, addr: Addr) -> Result<(), CpuError> {
+        if self.stack_pointer >= self.stack_depth {
+            return Err(CpuError::StackOverflow(self.stack_depth));
+        }
+
+        self.stack[self.stack_pointer] = self.program_counter as Address;
+        self.stack_pointer += 1;
+        self.program_counter = addr.index();
+        Ok(())
+    }
+
+    /// Moves the program_counter to the previous memory location
+    /// on the stack.
+    ///
+    /// Fails if the stack is empty
+    pub(crate) fn ret(&mut self) -> Result<(), CpuError> {
+        if self.stack_pointer == 0 {
+            return Err(CpuError::StackUnderflow);
+        }
+
+        self.stack_pointer -= 1;
+        let mem = self.stack[self.stack_pointer];
+        self.program_counter = mem as usize;
+        Ok(())
+    }
+
+    /// Increments the value in register `x` by the value in register `y`
+    ///
+    /// If this operation overflows the register size, the borrow register
+    ///
+    /// `0xF` is set to `1`
+    pub(crate) fn add_xy(&mut self, x: Reg, y: Reg) {
+        let arg1 = self.registers[x];
+        let arg2 = self.registers[y];
+        let (val, overflow) = arg1.overflowing_add(arg2);
+        self.write_with_flag(x, val, overflow);
+    }
+
+    /// Decrements the value in register `x` by the value in register `y`
+    ///
+    /// If this operation _does not_ underflow the register, the 'borrow' register
+    ///
+    /// `0xF` is set to `1`
+    pub(crate) fn sub_xy(&mut self, x: Reg, y: Reg) {
+        let arg1 = self.registers[x];
+        let arg2 = self.registers[y];
+        let (val, overflow) = arg1.overflowing_sub(arg2);
+        self.write_with_flag(x, val, !overflow);
+    }
+
+    /// Sets register[x] = register[y] - register[x]
+    ///
+    /// If this operation _does not_ underflow the register, the 'borrow' register
+    ///
+    /// `0xF` is set to `1`
+    pub(crate) fn sub_n(&mut self, x: Reg, y: Reg) {
+        let arg1 = self.registers[x];
+        let arg2 = self.registers[y];
+        let (val, overflow) = arg2.overflowing_sub(arg1);
+        self.write_with_flag(x, val, !overflow);
+    }
+
+    /// Skips the next instruction if registers[x] equals NN
+    pub(crate) fn skip_equal(&mut self, x: Reg, nn: Byte) {
+        if self.registers[x] == nn {
+            self.program_counter += 2;
+        }
+    }
+
+    /// Skips the next instruction if registers[x] does not equal NN
+    pub(crate) fn skip_not_equal(&mut self, x: Reg, nn: Byte) {
+        if self.registers[x] != nn {
+            self.program_counter += 2;
+        }
+    }
+
+    /// Skips the next instruction if registers[x] equals registers[y]
+    pub(crate) fn skip_equal_reg(&mut self, x: Reg, y: Reg) {
+        if self.registers[x] == self.registers[y] {
+            self.program_counter += 2;
+        }
+    }
+
+    /// Skips the next instruction if registers[x] does not equal registers[y]
+    pub(crate) fn skip_not_equal_reg(&mut self, x: Reg, y: Reg) {
+        if self.registers[x] != self.registers[y] {
+            self.program_counter += 2;
+        }
+    }
+
+    /// Sets registers[x] to nn
+    pub(crate) fn set_register(&mut self, x: Reg, nn: Byte) {
+        self.write_register(x, nn);
+    }
+
+    /// Adds nn to register[x], wrapping around past 255
+    ///
+    /// Unlike 8XY4, the carry is thrown away and VF is left untouched
+    pub(crate) fn add(&mut self, x: Reg, nn: Byte) {
+        self.write_register(x, self.registers[x].wrapping_add(nn));
+    }
+
+    /// Sets register[x] to the value in register[y]
+    pub(crate) fn assign(&mut self, x: Reg, y: Reg) {
+        self.write_register(x, self.registers[y]);
+    }
+
+    /// Sets register[x] to register[x] bitwise OR register[y]
+    pub(crate) fn or(&mut self, x: Reg, y: Reg) {
+        self.write_register(x, self.registers[x] | self.registers[y]);
+        self.reset_vf();
+    }
+
+    /// Sets register[x] to register[x] bitwise AND register[y]
+    pub(crate) fn and(&mut self, x: Reg, y: Reg) {
+        self.write_register(x, self.registers[x] & self.registers[y]);
+        self.reset_vf();
+    }
+
+    /// Sets register[x] to register[x] bitwise XOR register[y]
+    pub(crate) fn xor(&mut self, x: Reg, y: Reg) {
+        self.write_register(x, self.registers[x] ^ self.registers[y]);
+        self.reset_vf();
+    }
+
+    /// Writes the result of an arithmetic or shift instruction to register[x],
+    /// then its carry, borrow or shifted out bit to VF
+    ///
+    /// The flag always goes last, so when VF is the destination it's left
+    /// holding the flag and the result is lost, as on the COSMAC VIP and in
+    /// the test suites. Callers read both operands before calling this, so
+    /// X == Y works out too.
+    pub(crate) fn write_with_flag(&mut self, x: Reg, value: Byte, flag: bool) {
+        self.write_register(x, value);
+        self.write_register(Reg::VF, flag as Byte);
+    }
+
+    /// Clears the borrow register after a logic instruction, if the
+    /// `vf_reset` quirk is on
+    pub(crate) fn reset_vf(&mut self) {
+        if self.quirks.vf_reset {
+            self.write_register(Reg::VF, 0);
+        }
+    }
+
+    /// Shifts register[x] right 1, storing the bit shifted out in the borrow
+    /// register
+    ///
+    /// With the `shift_uses_vy` quirk, shifts register[y] into register[x] instead
+    pub(crate) fn shift_right(&mut self, x: Reg, y: Reg) {
+        let value = self.shift_source(x, y);
+        self.write_with_flag(x, value >> 1, value & 0b00000001 != 0);
+    }
+
+    /// Shifts register[x] left 1, storing the bit shifted out in the borrow
+    /// register
+    ///
+    /// With the `shift_uses_vy` quirk, shifts register[y] into register[x] instead
+    pub(crate) fn shift_left(&mut self, x: Reg, y: Reg) {
+        let value = self.shift_source(x, y);
+        self.write_with_flag(x, value << 1, value & 0b10000000 != 0);
+    }
+
+    /// The register a shift reads from, depending on the `shift_uses_vy` quirk
+    pub(crate) fn shift_source(&self, x: Reg, y: Reg) -> Byte {
+        if self.quirks.shift_uses_vy {
+            self.registers[y]
+        } else {
+            self.registers[x]
+        }
+    }
+
+    /// Sets the I register
+    pub(crate) fn set_i(&mut self, addr: Addr) {
+        self.i = addr.get();
+        self.i_high = 0;
+    }
+
+    /// Sets I to MegaChip's 24 bit address, `nn` being its top byte and
+    /// the two bytes after the instruction the rest, and steps over them
+    pub(crate) fn set_long_i(&mut self, nn: Byte) -> Result<(), CpuError> {
+        let pc = self.program_counter;
+        if pc + 1 >= self.memory.len() {
+            return Err(CpuError::ProgramCounterOutOfBounds(pc));
+        }
+
+        self.i = (self.read_memory(pc) as Address) << 8 | self.read_memory(pc + 1) as Address;
+        self.i_high = nn;
+        self.program_counter += 2;
+        Ok(())
+    }
+
+    /// Loads `count` ARGB colours from I into MegaChip's palette, from
+    /// colour 1 on
+    pub(crate) fn load_palette(&mut self, count: Byte) -> Result<(), CpuError> {
+        let colors: Vec<Byte> = self.i_range(count as usize * 4)?.map(|addr| self.read_memory(addr)).collect();
+        self.display.load_palette(1, &colors);
+        Ok(())
+    }
+
+    /// Adds registers[x] to the I register, wrapping around past 0xFFFF
+    ///
+    /// VF is left untouched
+    pub(crate) fn set_i_reg(&mut self, x: Reg) {
+        self.i = self.i.wrapping_add(self.registers[x] as Address);
+    }
+
+    /// Sets registers[x] to some random number (1-255) AND nn
+    pub(crate) fn rand(&mut self, x: Reg, nn: Byte) {
+        let random = self.rng.gen_range(1..=255);
+        self.write_register(x, nn & random);
+    }
+
+    /// Points I at the font sprite for the hex digit in registers[x]
+    pub(crate) fn set_i_sprite(&mut self, x: Reg) {
+        // each digit is 5 bytes, starting from address 0
+        self.i = (self.registers[x] & 0xF) as Address * 5;
+        self.i_high = 0;
+    }
+
+    /// Stores from V0 to VX (including VX) in memory, starting at address I
+    ///
+    /// With the `memory_increments_i` quirk, I is then moved past them
+    pub(crate) fn reg_dump(&mut self, x: Reg) -> Result<(), CpuError> {
+        for (ind, addr) in self.i_range(x.index() + 1)?.enumerate() {
+            self.write_memory(addr, self.registers[ind]);
+        }
+        self.increment_i(x);
+        Ok(())
+    }
+
+    /// Fills from V0 to VX (including VX) in memory, starting at address I
+    ///
+    /// With the `memory_increments_i` quirk, I is then moved past them
+    pub(crate) fn reg_load(&mut self, x: Reg) -> Result<(), CpuError> {
+        for (ind, addr) in self.i_range(x.index() + 1)?.enumerate() {
+            let value = self.read_memory(addr);
+            self.write_register(Reg::nibble(ind as Byte), value);
+        }
+        self.increment_i(x);
+        Ok(())
+    }
+
+    /// Adds X + 1 to I after a register dump or load, if the
+    /// `memory_increments_i` quirk is on, or just X with
+    /// `memory_increments_i_by_x` too
+    pub(crate) fn increment_i(&mut self, x: Reg) {
+        if self.quirks.memory_increments_i {
+            let past = !self.quirks.memory_increments_i_by_x as Address;
+            self.i = self.i.wrapping_add(x.get() as Address + past);
+        }
+    }
+
+    /// Saves from V0 to VX (including VX) to the RPL flags
+    ///
+    /// There are only eight flags, so VX past V7 is treated as V7
+    pub(crate) fn save_flags(&mut self, x: Reg) -> Result<(), CpuError> {
+        let count = x.index().min(FLAG_COUNT - 1) + 1;
+        let mut flags = self.flags.load();
+        flags[..count].copy_from_slice(&self.registers[..count]);
+        self.flags.save(flags).map_err(|_| CpuError::FlagStorage)
+    }
+
+    /// Fills from V0 to VX (including VX) from the RPL flags
+    ///
+    /// There are only eight flags, so VX past V7 is treated as V7
+    pub(crate) fn load_flags(&mut self, x: Reg) {
+        let count = x.index().min(FLAG_COUNT - 1) + 1;
+        let flags = self.flags.load();
+        for (ind, value) in flags[..count].iter().enumerate() {
+            self.write_register(Reg::nibble(ind as Byte), *value);
+        }
+    }
+
+    /// Stores the binary-coded decimal representation of VX in memory starting at address I
+    pub(crate) fn bcd(&mut self, x: Reg) -> Result<(), CpuError> {
+        let start = self.i_range(3)?.start;
+        let hundreds = self.registers[x] / 100;
+        let tens = (self.registers[x] / 10) % 10;
+        let ones = self.registers[x] % 10;
+
+        self.write_memory(start, hundreds);
+        self.write_memory(start + 1, tens);
+        self.write_memory(start + 2, ones);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::screen::{Rect, Resolution};
+    use crate::{memory, Addr, Address, Byte, CpuBuilder, CpuError, CpuStatus, Display, Quirks, Reg};
+
+    fn v(x: Byte) -> Reg {
+        Reg::nibble(x)
+    }
+
+    fn a(addr: Address) -> Addr {
+        Addr::nnn(addr)
+    }
+
+    #[test]
+    fn add_xy_adds_registers_no_overflow() {
+        let mut registers = [0; 16];
+        registers[0] = 3;
+        registers[1] = 5;
+        let mut cpu = CpuBuilder::new().registers(registers).build();
+        cpu.add_xy(v(0), v(1));
+
+        assert_eq!(8, cpu.registers(0));
+        assert_eq!(5, cpu.registers(1));
+        assert_eq!(0, cpu.registers(15));
+    }
+
+    #[test]
+    fn add_xy_adds_registers_overflow() {
+        let mut registers = [0; 16];
+        registers[0] = 255;
+        registers[1] = 1;
+        let mut cpu = CpuBuilder::new().registers(registers).build();
+        cpu.add_xy(v(0), v(1));
+
+        assert_eq!(0, cpu.registers(0));
+        assert_eq!(1, cpu.registers(15));
+    }
+
+    #[test]
+    fn jump_sets_program_counter() {
+        let mut cpu = CpuBuilder::new().build();
+        cpu.program_counter = 0x100;
+        cpu.jump(a(0x200));
+
+        assert_eq!(cpu.program_counter, 0x200);
+    }
+
+    #[test]
+    fn jump_reg_sets_program_counter() {
+        let mut cpu = CpuBuilder::new().build();
+        cpu.registers[0] = 0x0FF;
+        cpu.jump_reg(a(0x100));
+
+        assert_eq!(cpu.program_counter, 0x1FF);
+    }
+
+    #[test]
+    fn skip_equal_sets_program_counter_when_equal() {
+        let mut cpu = CpuBuilder::new().build();
+        cpu.program_counter = 0x100;
+        cpu.registers[2] = 8;
+        cpu.skip_equal(v(2), 8);
+
+        assert_eq!(cpu.program_counter, 0x102);
+    }
+
+    #[test]
+    fn skip_equal_continues_when_not_equal() {
+        let mut cpu = CpuBuilder::new().build();
+        cpu.program_counter = 0x100;
+        cpu.registers[2] = 7;
+        cpu.skip_equal(v(2), 8);
+
+        assert_eq!(cpu.program_counter, 0x100);
+    }
+
+    #[test]
+    fn skip_not_equal_continues_when_equal() {
+        let mut cpu = CpuBuilder::new().build();
+        cpu.program_counter = 0x100;
+        cpu.registers[2] = 8;
+        cpu.skip_not_equal(v(2), 8);
+
+        assert_eq!(cpu.program_counter, 0x100);
+    }
+
+    #[test]
+    fn skip_not_equal_sets_program_counter_when_not_equal() {
+        let mut cpu = CpuBuilder::new().build();
+        cpu.program_counter = 0x100;
+        cpu.registers[2] = 7;
+        cpu.skip_not_equal(v(2), 8);
+
+        assert_eq!(cpu.program_counter, 0x102);
+    }
+
+    #[test]
+    fn skip_equal_reg_sets_program_counter_when_equal() {
+        let mut cpu = CpuBuilder::new().build();
+        cpu.program_counter = 0x100;
+        cpu.registers[2] = 8;
+        cpu.registers[7] = 8;
+        cpu.skip_equal_reg(v(2), v(7));
+
+        assert_eq!(cpu.program_counter, 0x102);
+    }
+
+    #[test]
+    fn skip_equal_reg_continues_when_not_equal() {
+        let mut cpu = CpuBuilder::new().build();
+        cpu.program_counter = 0x100;
+        cpu.registers[2] = 7;
+        cpu.registers[7] = 2;
+        cpu.skip_equal_reg(v(2), v(7));
+
+        assert_eq!(cpu.program_counter, 0x100);
+    }
+
+    #[test]
+    fn skip_not_equal_reg_continues_when_equal() {
+        let mut cpu = CpuBuilder::new().build();
+        cpu.program_counter = 0x100;
+        cpu.registers[2] = 8;
+        cpu.registers[7] = 8;
+        cpu.skip_not_equal_reg(v(2), v(7));
+
+        assert_eq!(cpu.program_counter, 0x100);
+    }
+
+    #[test]
+    fn skip_not_equal_reg_sets_program_counter_when_not_equal() {
+        let mut cpu = CpuBuilder::new().build();
+        cpu.program_counter = 0x100;
+        cpu.registers[2] = 7;
+        cpu.registers[7] = 2;
+        cpu.skip_not_equal_reg(v(2), v(7));
+
+        assert_eq!(cpu.program_counter, 0x102);
+    }
+
+    #[test]
+    fn set_register_sets_register() {
+        let mut cpu = CpuBuilder::new().build();
+        cpu.set_register(v(2), 7);
+
+        assert_eq!(cpu.registers[2], 7);
+    }
+
+    #[test]
+    fn add_increments_register() {
+        let mut cpu = CpuBuilder::new().build();
+        cpu.add(v(3), 5);
+        cpu.add(v(3), 1);
+
+        assert_eq!(cpu.registers[3], 6);
+    }
+
+    #[test]
+    fn add_wraps_on_overflow_without_touching_vf() {
+        let mut cpu = CpuBuilder::new().build();
+        cpu.registers[3] = 0xFE;
+        cpu.registers[0xF] = 7;
+        cpu.add(v(3), 5);
+
+        assert_eq!(cpu.registers[3], 3);
+        assert_eq!(cpu.registers[0xF], 7);
+
+        cpu.add(v(0xF), 0xFF);
+        assert_eq!(cpu.registers[0xF], 6);
+    }
+
+    #[test]
+    fn assign_sets_register_from_other_register() {
+        let mut cpu = CpuBuilder::new().build();
+        cpu.registers[1] = 6;
+        cpu.registers[10] = 4;
+        cpu.assign(v(1), v(10));
+
+        assert_eq!(cpu.registers[1], 4);
+        assert_eq!(cpu.registers[10], 4);
+    }
+
+    #[test]
+    fn or_sets_register_from_other_register() {
+        let mut cpu = CpuBuilder::new().build();
+        cpu.registers[2] = 0x001;
+        cpu.registers[5] = 0x010;
+        cpu.or(v(2), v(5));
+
+        assert_eq!(cpu.registers[2], 0x011);
+        assert_eq!(cpu.registers[5], 0x010);
+    }
+
+    #[test]
+    fn and_sets_register_from_other_register() {
+        let mut cpu = CpuBuilder::new().build();
+        cpu.registers[2] = 0x011;
+        cpu.registers[5] = 0x010;
+        cpu.and(v(2), v(5));
+
+        assert_eq!(cpu.registers[2], 0x010);
+        assert_eq!(cpu.registers[5], 0x010);
+    }
+
+    #[test]
+    fn xor_sets_register_from_other_register() {
+        let mut cpu = CpuBuilder::new().build();
+        cpu.registers[2] = 0x011;
+        cpu.registers[5] = 0x010;
+        cpu.xor(v(2), v(5));
+
+        assert_eq!(cpu.registers[2], 0x001);
+        assert_eq!(cpu.registers[5], 0x010);
+    }
+
+    #[test]
+    fn sub_xy_subtracts_registers_no_underflow() {
+        let mut registers = [0; 16];
+        registers[0] = 5;
+        registers[1] = 3;
+        let mut cpu = CpuBuilder::new().registers(registers).build();
+        cpu.sub_xy(v(0), v(1));
+
+        assert_eq!(2, cpu.registers(0));
+        assert_eq!(3, cpu.registers(1));
+        assert_eq!(1, cpu.registers(15));
+    }
+
+    #[test]
+    fn sub_xy_subtracts_registers_underflow() {
+        let mut registers = [0; 16];
+        registers[0] = 0;
+        registers[1] = 1;
+        let mut cpu = CpuBuilder::new().registers(registers).build();
+        cpu.sub_xy(v(0), v(1));
+
+        assert_eq!(255, cpu.registers(0));
+        assert_eq!(0, cpu.registers(15));
+    }
+
+    #[test]
+    fn shift_right_halves_register_and_stores_in_borrow_register() {
+        let mut cpu = CpuBuilder::new().build();
+        cpu.registers[3] = 0x011;
+        cpu.registers[5] = 0x0F0;
+
+        cpu.shift_right(v(3), v(5));
+        assert_eq!(cpu.registers[3], 0x008);
+        assert_eq!(cpu.registers[0xF], 1);
+
+        cpu.shift_right(v(5), v(3));
+        assert_eq!(cpu.registers[5], 0x078);
+        assert_eq!(cpu.registers[0xF], 0);
+    }
+
+    #[test]
+    fn shift_left_doubles_register_and_stores_in_borrow_register() {
+        let mut cpu = CpuBuilder::new().build();
+        cpu.registers[3] = 0b01111111;
+
+        cpu.shift_left(v(3), v(4));
+        assert_eq!(cpu.registers[3], 0b11111110);
+        assert_eq!(cpu.registers[0xF], 0);
+    }
+
+    #[test]
+    fn shift_right_reads_vy_with_quirk() {
+        let quirks = Quirks { shift_uses_vy: true, ..Quirks::default() };
+        let mut cpu = CpuBuilder::new().quirks(quirks).build();
+        cpu.registers[3] = 0xFF;
+        cpu.registers[5] = 0b00000110;
+
+        cpu.shift_right(v(3), v(5));
+        assert_eq!(cpu.registers[3], 0b00000011);
+        assert_eq!(cpu.registers[5], 0b00000110);
+        assert_eq!(cpu.registers[0xF], 0);
+    }
+
+    #[test]
+    fn shift_left_reads_vy_with_quirk() {
+        let quirks = Quirks { shift_uses_vy: true, ..Quirks::default() };
+        let mut cpu = CpuBuilder::new().quirks(quirks).build();
+        cpu.registers[3] = 0x01;
+        cpu.registers[5] = 0b10000001;
+
+        cpu.shift_left(v(3), v(5));
+        assert_eq!(cpu.registers[3], 0b00000010);
+        assert_eq!(cpu.registers[5], 0b10000001);
+        assert_eq!(cpu.registers[0xF], 1);
+    }
+
+    #[test]
+    fn subn_subtracts_registers_no_borrow() {
+        let mut cpu = CpuBuilder::new().build();
+        cpu.registers[5] = 9;
+        cpu.registers[2] = 10;
+        cpu.sub_n(v(5), v(2));
+
+        assert_eq!(cpu.registers[5], 1);
+        assert_eq!(cpu.registers[0xF], 1);
+    }
+
+    #[test]
+    fn subn_subtracts_registers_with_borrow() {
+        let mut cpu = CpuBuilder::new().build();
+        cpu.registers[5] = 1;
+        cpu.registers[2] = 0;
+        cpu.sub_n(v(5), v(2));
+
+        assert_eq!(cpu.registers[5], 255);
+        assert_eq!(cpu.registers[0xF], 0);
+    }
+
+    #[test]
+    fn call_can_overflow_stack() {
+        let mut cpu = CpuBuilder::new().build();
+        cpu.stack_pointer = 16;
+
+        assert_eq!(cpu.call(a(0x100)), Err(CpuError::StackOverflow(16)), "Expected the stack to overflow");
+    }
+
+    #[test]
+    fn call_overflows_at_the_configured_depth() {
+        let mut cpu = CpuBuilder::new().stack_depth(12).build();
+        cpu.stack_pointer = 11;
+        cpu.call(a(0x100)).unwrap();
+
+        assert_eq!(cpu.call(a(0x100)), Err(CpuError::StackOverflow(12)));
+
+        let mut deep = CpuBuilder::new().stack_depth(32).build();
+        deep.stack_pointer = 16;
+        deep.call(a(0x100)).unwrap();
+        assert_eq!(deep.stack_frames().len(), 17);
+    }
+
+    #[test]
+    fn call_sets_stack_and_pointers() {
+        let start = 5;
+        let pc = 0x100;
+        let addr = 200;
+
+        let mut cpu = CpuBuilder::new().build();
+        cpu.stack_pointer = start;
+        cpu.program_counter = pc;
+
+        cpu.call(a(addr)).unwrap();
+
+        assert_eq!(cpu.stack[start], pc as u16);
+        assert_eq!(cpu.stack_pointer, start + 1);
+        assert_eq!(cpu.program_counter, addr as usize);
+    }
+
+    #[test]
+    fn ret_can_underflow_stack() {
+        let mut cpu = CpuBuilder::new().build();
+
+        assert_eq!(cpu.ret(), Err(CpuError::StackUnderflow), "Expected the stack to underflow");
+    }
+
+    #[test]
+    fn ret_sets_pointers() {
+        let start = 5;
+        let pc = 0x100;
+
+        let mut cpu = CpuBuilder::new().build();
+        cpu.stack_pointer = start;
+        cpu.stack[start - 1] = pc;
+
+        cpu.ret().unwrap();
+
+        assert_eq!(cpu.stack_pointer, start - 1);
+        assert_eq!(cpu.program_counter, pc as usize);
+    }
+
+    #[test]
+    fn set_i_sets_i_register() {
+        let mut cpu = CpuBuilder::new().build();
+        cpu.set_i(a(512));
+
+        assert_eq!(cpu.i, 512);
+    }
+
+    #[test]
+    fn rand_sets_its_register() {
+        let mut cpu = CpuBuilder::new().build();
+        cpu.rand(v(3), 0xFF);
+
+        assert_ne!(cpu.registers[3], 0);
+        assert_eq!(cpu.registers[0], 0);
+    }
+
+    #[test]
+    fn set_i_reg_sets_i_from_register() {
+        let mut cpu = CpuBuilder::new().build();
+        cpu.registers[4] = 18;
+        cpu.i = 22;
+        cpu.set_i_reg(v(4));
+
+        assert_eq!(cpu.i, 40);
+    }
+
+    #[test]
+    fn set_i_reg_wraps_on_overflow() {
+        let mut cpu = CpuBuilder::new().build();
+        cpu.registers[4] = 0x10;
+        cpu.registers[0xF] = 1;
+        cpu.i = 0xFFF8;
+        cpu.set_i_reg(v(4));
+
+        assert_eq!(cpu.i, 0x0008);
+        assert_eq!(cpu.registers[0xF], 1);
+    }
+
+    #[test]
+    fn jump_reg_past_memory_fails_on_next_step() {
+        let mut memory = [0; 0x1000];
+        // LD V0, 0xFF; JP V0, 0xFFF
+        memory[..4].copy_from_slice(&[0x60, 0xFF, 0xBF, 0xFF]);
+        let mut cpu = CpuBuilder::new().memory(memory).build();
+
+        cpu.run_cycles(2);
+        assert_eq!(cpu.program_counter, 0x10FE);
+        assert_eq!(cpu.step(), Err(CpuError::ProgramCounterOutOfBounds(0x10FE)));
+    }
+
+    #[test]
+    fn skip_at_end_of_memory_fails_on_next_step() {
+        let mut cpu = CpuBuilder::new().build();
+        // SE V0, 0 as the last instruction in memory
+        cpu.memory[0xFFE] = 0x30;
+        cpu.memory[0xFFF] = 0x00;
+        cpu.program_counter = 0xFFE;
+
+        assert_eq!(cpu.step(), Ok(CpuStatus::Running));
+        assert_eq!(cpu.program_counter, 0x1002);
+        assert_eq!(cpu.step(), Err(CpuError::ProgramCounterOutOfBounds(0x1002)));
+    }
+
+    #[test]
+    fn reg_dump_sets_memory_from_registers() {
+        let mut cpu = CpuBuilder::new().build();
+        cpu.i = 0x100;
+        cpu.registers[0] = 0x80;
+        cpu.registers[1] = 0x14;
+        cpu.registers[2] = 0x77;
+        cpu.registers[3] = 0xEE;
+
+        cpu.reg_dump(v(2)).unwrap();
+        assert_eq!(cpu.memory[0x100], 0x80);
+        assert_eq!(cpu.memory[0x101], 0x14);
+        assert_eq!(cpu.memory[0x102], 0x77);
+        assert_eq!(cpu.memory[0x103], 0);
+
+        cpu.reg_dump(v(3)).unwrap();
+        assert_eq!(cpu.memory[0x100], 0x80);
+        assert_eq!(cpu.memory[0x101], 0x14);
+        assert_eq!(cpu.memory[0x102], 0x77);
+        assert_eq!(cpu.memory[0x103], 0xEE);
+        assert_eq!(cpu.i, 0x100);
+    }
+
+    #[test]
+    fn reg_dump_increments_i_with_quirk() {
+        let quirks = Quirks { memory_increments_i: true, ..Quirks::default() };
+        let mut cpu = CpuBuilder::new().quirks(quirks).build();
+        cpu.i = 0x100;
+        cpu.registers[0] = 0x80;
+        cpu.registers[1] = 0x14;
+
+        cpu.reg_dump(v(1)).unwrap();
+        assert_eq!(cpu.memory[0x100], 0x80);
+        assert_eq!(cpu.memory[0x101], 0x14);
+        assert_eq!(cpu.i, 0x102);
+    }
+
+    #[test]
+    fn reg_dump_leaves_i_on_the_last_register_with_chip48_quirk() {
+        let quirks = Quirks { memory_increments_i: true, memory_increments_i_by_x: true, ..Quirks::default() };
+        let mut cpu = CpuBuilder::new().quirks(quirks).build();
+        cpu.i = 0x100;
+
+        cpu.reg_dump(v(1)).unwrap();
+        assert_eq!(cpu.i, 0x101);
+    }
+
+    #[test]
+    fn reg_load_sets_registers_from_memory() {
+        let mut cpu = CpuBuilder::new().build();
+        cpu.i = 0x100;
+        cpu.memory[0x100] = 0x80;
+        cpu.memory[0x101] = 0x14;
+        cpu.memory[0x102] = 0x77;
+        cpu.memory[0x103] = 0xEE;
+
+        cpu.reg_load(v(2)).unwrap();
+        assert_eq!(cpu.registers[0], 0x80);
+        assert_eq!(cpu.registers[1], 0x14);
+        assert_eq!(cpu.registers[2], 0x77);
+        assert_eq!(cpu.registers[3], 0);
+
+        cpu.reg_load(v(3)).unwrap();
+        assert_eq!(cpu.registers[0], 0x80);
+        assert_eq!(cpu.registers[1], 0x14);
+        assert_eq!(cpu.registers[2], 0x77);
+        assert_eq!(cpu.registers[3], 0xEE);
+        assert_eq!(cpu.i, 0x100);
+    }
+
+    #[test]
+    fn reg_load_increments_i_with_quirk() {
+        let quirks = Quirks { memory_increments_i: true, ..Quirks::default() };
+        let mut cpu = CpuBuilder::new().quirks(quirks).build();
+        cpu.i = 0x100;
+        cpu.memory[0x100] = 0x80;
+        cpu.memory[0x101] = 0x14;
+        cpu.memory[0x102] = 0x77;
+
+        cpu.reg_load(v(2)).unwrap();
+        assert_eq!(cpu.registers[..3], [0x80, 0x14, 0x77]);
+        assert_eq!(cpu.i, 0x103);
+    }
+
+    #[test]
+    fn flags_survive_reset() {
+        let mut memory = [0; 0x1000];
+        // LD V0, 4; LD V1, 9; LD R, V1
+        memory[..6].copy_from_slice(&[0x60, 0x04, 0x61, 0x09, 0xF1, 0x75]);
+        let mut cpu = CpuBuilder::new().memory(memory).build();
+        cpu.run_cycles(3);
+
+        cpu.reset();
+        cpu.registers[1] = 0xAA;
+        cpu.load_flags(v(0));
+        assert_eq!(cpu.registers[..2], [4, 0xAA]);
+
+        cpu.load_flags(v(0xF));
+        assert_eq!(cpu.registers[..9], [4, 9, 0, 0, 0, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn save_flags_stops_at_v7() {
+        let mut cpu = CpuBuilder::new().build();
+        cpu.registers = [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16];
+        cpu.save_flags(v(0xF)).unwrap();
+
+        assert_eq!(cpu.flags.load(), [1, 2, 3, 4, 5, 6, 7, 8]);
+    }
+
+    #[test]
+    fn bcd_sets_memory_from_binary_coded_register() {
+        let mut cpu = CpuBuilder::new().build();
+        cpu.registers[3] = 213;
+        cpu.registers[7] = 176;
+        cpu.registers[11] = 54;
+        cpu.registers[13] = 1;
+
+        cpu.i = 0x100;
+        cpu.bcd(v(3)).unwrap();
+        assert_eq!(cpu.memory[cpu.i as usize], 2);
+        assert_eq!(cpu.memory[cpu.i as usize + 1], 1);
+        assert_eq!(cpu.memory[cpu.i as usize + 2], 3);
+
+        cpu.i = 0x120;
+        cpu.bcd(v(7)).unwrap();
+        assert_eq!(cpu.memory[cpu.i as usize], 1);
+        assert_eq!(cpu.memory[cpu.i as usize + 1], 7);
+        assert_eq!(cpu.memory[cpu.i as usize + 2], 6);
+
+        cpu.i = 0x140;
+        cpu.bcd(v(11)).unwrap();
+        assert_eq!(cpu.memory[cpu.i as usize], 0);
+        assert_eq!(cpu.memory[cpu.i as usize + 1], 5);
+        assert_eq!(cpu.memory[cpu.i as usize + 2], 4);
+
+        cpu.i = 0x160;
+        cpu.bcd(v(13)).unwrap();
+        assert_eq!(cpu.memory[cpu.i as usize], 0);
+        assert_eq!(cpu.memory[cpu.i as usize + 1], 0);
+        assert_eq!(cpu.memory[cpu.i as usize + 2], 1);
+    }
+
+    #[test]
+    fn draw_counts_collided_and_clipped_rows_with_quirk_in_high_res() {
+        let quirks = Quirks { collision_rows: true, clip_sprites: true, ..Quirks::default() };
+        let mut cpu = CpuBuilder::new().quirks(quirks).build();
+        cpu.set_resolution(Resolution::High);
+        cpu.i = 0x300;
+        cpu.memory[0x300..0x304].copy_from_slice(&[0xFF, 0x00, 0x81, 0x18]);
+        cpu.registers[0] = 10;
+        cpu.registers[1] = 4;
+
+        cpu.draw(v(0), v(1), 4).unwrap();
+        assert_eq!(cpu.registers[0xF], 0);
+
+        // every row but the empty one hits something
+        cpu.draw(v(0), v(1), 4).unwrap();
+        assert_eq!(cpu.registers[0xF], 3);
+
+        // two rows on screen, neither hitting anything, and two clipped off
+        cpu.registers[1] = 62;
+        cpu.draw(v(0), v(1), 4).unwrap();
+        assert_eq!(cpu.registers[0xF], 2);
+    }
+
+    #[test]
+    fn draw_only_flags_collisions_with_quirk_in_low_res() {
+        let quirks = Quirks { collision_rows: true, clip_sprites: true, ..Quirks::default() };
+        let mut cpu = CpuBuilder::new().quirks(quirks).build();
+        cpu.i = 0x300;
+        cpu.memory[0x300..0x304].copy_from_slice(&[0xFF, 0x00, 0x81, 0x18]);
+        cpu.registers[0] = 10;
+        cpu.registers[1] = 4;
+
+        cpu.draw(v(0), v(1), 4).unwrap();
+        cpu.draw(v(0), v(1), 4).unwrap();
+        assert_eq!(cpu.registers[0xF], 1);
+
+        // clipped rows don't count either
+        cpu.registers[1] = 30;
+        cpu.draw(v(0), v(1), 4).unwrap();
+        assert_eq!(cpu.registers[0xF], 0);
+    }
+
+    #[test]
+    fn draw_zero_rows_is_a_16x16_sprite_in_high_res() {
+        let mut cpu = CpuBuilder::new().build();
+        cpu.i = 0x300;
+        for row in 0..16 {
+            cpu.memory[0x300 + row * 2..0x302 + row * 2].copy_from_slice(&[0x80, 0x01]);
+        }
+        cpu.registers[0] = 100;
+        cpu.registers[1] = 40;
+
+        // nothing at all in low resolution
+        cpu.draw(v(0), v(1), 0).unwrap();
+        assert_eq!(cpu.display, Display::new());
+
+        cpu.set_resolution(Resolution::High);
+        cpu.take_touched();
+        cpu.draw(v(0), v(1), 0).unwrap();
+        assert!(cpu.display.pixel(100, 40) && cpu.display.pixel(115, 55));
+        assert!(!cpu.display.pixel(101, 40) && !cpu.display.pixel(100, 56));
+        assert_eq!(cpu.take_touched(), Some(Rect { x: 100, y: 40, width: 16, height: 16 }));
+    }
+
+    #[test]
+    fn draw_in_megachip_mode_paints_palette_colours_from_a_24_bit_i() {
+        let mut cpu = CpuBuilder::new().memory_size(memory::MEGA_CHIP_SIZE).build();
+        cpu.set_resolution(Resolution::Mega);
+        (cpu.i_high, cpu.i) = (0x01, 0x0000);
+        cpu.memory[0x10000..0x10004].copy_from_slice(&[3, 0, 4, 5]);
+        cpu.sprite_size = (2, 2);
+        cpu.registers[0] = 255;
+        cpu.registers[1] = 190;
+
+        // only the left column fits on the screen
+        cpu.take_touched();
+        cpu.draw(v(0), v(1), 0).unwrap();
+        assert_eq!(cpu.display.indices()[190 * 256 + 255], 3);
+        assert_eq!(cpu.display.indices()[191 * 256 + 255], 4);
+        assert_eq!(cpu.registers[0xF], 0);
+        assert_eq!(cpu.take_touched(), Some(Rect { x: 255, y: 190, width: 1, height: 2 }));
+
+        // painting over lit pixels sets VF, and colour 0 leaves them be
+        cpu.memory[0x10000] = 0;
+        cpu.draw(v(0), v(1), 0).unwrap();
+        assert_eq!(cpu.display.indices()[190 * 256 + 255], 3);
+        assert_eq!(cpu.registers[0xF], 1);
+    }
+
+    #[test]
+    fn long_i_reads_the_next_word_and_steps_over_it() {
+        let mut cpu = CpuBuilder::new().build();
+        cpu.memory[0x202..0x204].copy_from_slice(&[0x34, 0x56]);
+        cpu.program_counter = 0x202;
+        cpu.set_long_i(0x12).unwrap();
+        assert_eq!((cpu.i_address(), cpu.program_counter), (0x12_3456, 0x204));
+
+        cpu.set_i(a(0x300));
+        assert_eq!(cpu.i_address(), 0x300);
+
+        cpu.program_counter = 0xFFF;
+        assert_eq!(cpu.set_long_i(0x12), Err(CpuError::ProgramCounterOutOfBounds(0xFFF)));
+    }
+
+    #[test]
+    fn load_palette_reads_argb_colours_into_colour_1_on() {
+        let mut cpu = CpuBuilder::new().build();
+        cpu.set_resolution(Resolution::Mega);
+        cpu.i = 0x300;
+        cpu.memory[0x300..0x308].copy_from_slice(&[0xFF, 0x11, 0x22, 0x33, 0x80, 0x44, 0x55, 0x66]);
+        cpu.load_palette(2).unwrap();
+
+        assert_eq!(cpu.display.palette()[..3], [[0; 4], [0x11, 0x22, 0x33, 0xFF], [0x44, 0x55, 0x66, 0x80]]);
+        cpu.i = 0xFFC;
+        assert_eq!(cpu.load_palette(2), Err(CpuError::MemoryOutOfBounds(0x1003)));
+    }
+
+    #[test]
+    fn draw_xors_sprite_onto_screen() {
+        let mut cpu = CpuBuilder::new().build();
+        cpu.i = 0x300;
+        cpu.memory[0x300] = 0b10100000;
+        cpu.memory[0x301] = 0b01000001;
+        cpu.registers[0] = 2;
+        cpu.registers[1] = 5;
+
+        cpu.draw(v(0), v(1), 2).unwrap();
+        assert_eq!(cpu.display.rows().nth(5).unwrap()[2..10], [true, false, true, false, false, false, false, false]);
+        assert_eq!(cpu.display.rows().nth(6).unwrap()[2..10], [false, true, false, false, false, false, false, true]);
+        assert_eq!(cpu.registers[0xF], 0);
+
+        cpu.draw(v(0), v(1), 1).unwrap();
+        assert_eq!(cpu.display.rows().nth(5).unwrap()[2..10], [false; 8]);
+        assert_eq!(cpu.registers[0xF], 1);
+    }
+
+    #[test]
+    fn draw_wraps_around_screen_edges() {
+        let mut cpu = CpuBuilder::new().build();
+        cpu.i = 0x300;
+        cpu.memory[0x300] = 0xFF;
+        cpu.memory[0x301] = 0x80;
+        cpu.registers[0] = 60;
+        cpu.registers[1] = 31;
+
+        cpu.draw(v(0), v(1), 2).unwrap();
+        assert_eq!(cpu.display.rows().nth(31).unwrap()[60..], [true; 4]);
+        assert_eq!(cpu.display.rows().nth(31).unwrap()[..4], [true; 4]);
+        assert!(cpu.display.pixel(60, 0));
+    }
+
+    #[test]
+    fn draw_clips_sprites_with_quirk() {
+        let quirks = Quirks { clip_sprites: true, ..Quirks::default() };
+        let mut cpu = CpuBuilder::new().quirks(quirks).build();
+        cpu.i = 0x300;
+        cpu.memory[0x300] = 0xFF;
+        cpu.memory[0x301] = 0x80;
+        cpu.registers[0] = 60;
+        cpu.registers[1] = 31;
+
+        cpu.draw(v(0), v(1), 2).unwrap();
+        assert_eq!(cpu.display.rows().nth(31).unwrap()[60..], [true; 4]);
+        assert_eq!(cpu.display.rows().nth(31).unwrap()[..4], [false; 4]);
+        assert!(!cpu.display.pixel(60, 0));
+    }
+
+    #[test]
+    fn logic_resets_vf_with_quirk() {
+        let quirks = Quirks { vf_reset: true, ..Quirks::default() };
+        let mut cpu = CpuBuilder::new().quirks(quirks).build();
+        cpu.registers[0xF] = 1;
+        cpu.or(v(2), v(5));
+        assert_eq!(cpu.registers[0xF], 0);
+
+        cpu.registers[0xF] = 1;
+        cpu.xor(v(2), v(5));
+        assert_eq!(cpu.registers[0xF], 0);
+    }
+
+    #[test]
+    fn jump_reg_uses_vx_with_quirk() {
+        let quirks = Quirks { jump_uses_vx: true, ..Quirks::default() };
+        let mut cpu = CpuBuilder::new().quirks(quirks).build();
+        cpu.registers[0] = 0x10;
+        cpu.registers[3] = 0x02;
+        cpu.jump_reg(a(0x340));
+
+        assert_eq!(cpu.program_counter, 0x342);
+    }
+}
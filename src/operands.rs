@@ -0,0 +1,168 @@
+//! The register and address operands instructions carry
+//!
+//! A [`Reg`] is always one of V0 to VF and an [`Addr`] always somewhere in
+//! the 4K of memory, so the handlers can index registers and memory with
+//! them directly, and mixing up a register with a byte value, like writing
+//! to V0 instead of VX, shows up as a type error.
+
+use std::fmt;
+use std::ops::{Index, IndexMut};
+
+use crate::{Address, Byte, Registers};
+
+/// One of the sixteen registers, V0 to VF
+///
+/// Formats as the register's name, `VA`, or as its hex digit with `{:X}`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Reg(Byte);
+
+impl Reg {
+    /// V0, which BNNN adds to its address
+    pub const V0: Reg = Reg(0);
+    /// VF, the flag register
+    pub const VF: Reg = Reg(0xF);
+
+    /// Register `x`, if it's 0 to 0xF
+    ///
+    /// # Examples
+    /// ```
+    /// use chip_8::Reg;
+    ///
+    /// assert_eq!(Reg::new(0xF), Some(Reg::VF));
+    /// assert_eq!(Reg::new(0x10), None);
+    /// ```
+    pub const fn new(x: Byte) -> Option<Reg> {
+        if x < 16 {
+            Some(Reg(x))
+        } else {
+            None
+        }
+    }
+
+    /// The register named by the low nibble of `x`, for decoding opcodes
+    pub(crate) const fn nibble(x: Byte) -> Reg {
+        Reg(x & 0xF)
+    }
+
+    /// The register's number, 0 to 0xF
+    pub const fn get(self) -> Byte {
+        self.0
+    }
+
+    /// The register's number as an index into the registers
+    pub const fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+impl fmt::Display for Reg {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "V{:X}", self.0)
+    }
+}
+
+impl fmt::UpperHex for Reg {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::UpperHex::fmt(&self.0, f)
+    }
+}
+
+impl Index<Reg> for Registers {
+    type Output = Byte;
+
+    fn index(&self, reg: Reg) -> &Byte {
+        &self[reg.index()]
+    }
+}
+
+impl IndexMut<Reg> for Registers {
+    fn index_mut(&mut self, reg: Reg) -> &mut Byte {
+        &mut self[reg.index()]
+    }
+}
+
+/// An address in the 4K of memory, 0x000 to 0xFFF
+///
+/// Formats as hex with `{:X}`, like the addresses it stands in for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Addr(Address);
+
+impl Addr {
+    /// Address `addr`, if it's in memory
+    ///
+    /// # Examples
+    /// ```
+    /// use chip_8::Addr;
+    ///
+    /// assert_eq!(Addr::new(0x200).map(Addr::get), Some(0x200));
+    /// assert_eq!(Addr::new(0x1000), None);
+    /// ```
+    pub const fn new(addr: Address) -> Option<Addr> {
+        if addr < 0x1000 {
+            Some(Addr(addr))
+        } else {
+            None
+        }
+    }
+
+    /// The address in the low 12 bits of `nnn`, for decoding opcodes
+    pub(crate) const fn nnn(nnn: Address) -> Addr {
+        Addr(nnn & 0xFFF)
+    }
+
+    /// The address as a number
+    pub const fn get(self) -> Address {
+        self.0
+    }
+
+    /// The address as an index into memory
+    pub const fn index(self) -> usize {
+        self.0 as usize
+    }
+
+    /// The highest nibble of the address, which BXNN with the
+    /// `jump_uses_vx` quirk takes as its register
+    pub const fn high_nibble(self) -> Reg {
+        Reg((self.0 >> 8) as Byte)
+    }
+}
+
+impl fmt::UpperHex for Addr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::UpperHex::fmt(&self.0, f)
+    }
+}
+
+impl Index<Addr> for [Byte; 0x1000] {
+    type Output = Byte;
+
+    fn index(&self, addr: Addr) -> &Byte {
+        &self[addr.index()]
+    }
+}
+
+impl IndexMut<Addr> for [Byte; 0x1000] {
+    fn index_mut(&mut self, addr: Addr) -> &mut Byte {
+        &mut self[addr.index()]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn operands_index_and_format() {
+        let mut registers: Registers = [0; 16];
+        registers[Reg::VF] = 1;
+        assert_eq!(registers[15], 1);
+
+        let mut memory = [0; 0x1000];
+        memory[Addr::nnn(0x1FFF)] = 7;
+        assert_eq!(memory[0xFFF], 7);
+
+        assert_eq!(Reg::nibble(0x1A).to_string(), "VA");
+        assert_eq!(format!("V{:X} 0x{:03X}", Reg::nibble(0xB), Addr::nnn(0x2A)), "VB 0x02A");
+        assert_eq!(Addr::nnn(0xB3FF).high_nibble(), Reg::nibble(3));
+    }
+}
@@ -0,0 +1,235 @@
+//! The original piston window, kept behind the `frontend-piston` feature
+//! for anyone who'd rather not move to the pixels one yet
+
+extern crate glutin_window;
+extern crate graphics;
+extern crate opengl_graphics;
+extern crate piston;
+
+use glutin_window::GlutinWindow as Window;
+use opengl_graphics::{GlGraphics, OpenGL};
+use piston::event_loop::{EventLoop, EventSettings, Events};
+use piston::input::{
+    Button, Event, FileDrag, Input, Key, PressEvent, ReleaseEvent, RenderArgs, RenderEvent, UpdateEvent,
+};
+use piston::window::WindowSettings;
+
+use std::mem;
+use std::path::PathBuf;
+use std::time::Instant;
+
+use chip_8::audio::{AudioSettings, AudioSink, Pattern, Tone};
+use chip_8::frontend::{Frontend, InputEvents};
+use chip_8::screen::{Palette, Phosphor, HEIGHT, WIDTH};
+use chip_8::{FrameBuffer, KeyMap};
+
+use crate::display::{GameWindow, Hotkey, KeyPress, Layout, ScaleMode, Settings};
+
+pub struct App {
+    gl: GlGraphics,
+    scale_mode: ScaleMode,
+    palette: Palette,
+}
+
+impl App {
+    fn render(&mut self, args: &RenderArgs, screen: &Phosphor, message: Option<&str>, overlay: &[String]) {
+        use graphics::*;
+
+        const GREEN: [f32; 4] = [0.0, 1.0, 0.0, 1.0];
+        const SHADE: [f32; 4] = [0.0, 0.0, 0.0, 0.75];
+
+        // worked out every frame, so resizing the window just works
+        let layout = Layout::new(args.window_size, [screen.width(), screen.height()], self.scale_mode);
+        let palette = self.palette;
+        let mut squares: Vec<(types::Rectangle, [f32; 4])> = vec![];
+
+        for (row_ind, row) in screen.rows().enumerate() {
+            for (col_ind, &glow) in row.iter().enumerate() {
+                if glow > 0.0 {
+                    let x = layout.x + col_ind as f64 * layout.width;
+                    let y = layout.y + row_ind as f64 * layout.height;
+                    squares.push(([x, y, layout.width, layout.height], screen.color(col_ind, row_ind, &palette)));
+                }
+            }
+        }
+
+        self.gl.draw(args.viewport(), |c, gl| {
+            clear(palette.color(0.0), gl);
+
+            for (square, color) in squares {
+                let transform = c.transform;
+                rectangle(color, square, transform, gl);
+            }
+
+            // along the bottom of the window, on a dark backing so it can
+            // be read over the pixels
+            if let Some(message) = message {
+                let y = args.window_size[1] - 14.0;
+                rectangle(SHADE, [0.0, y - 4.0, crate::text::width(message, 2.0) + 6.0, 18.0], c.transform, gl);
+                crate::text::draw(message, 4.0, y, 2.0, |pixel| rectangle(GREEN, pixel, c.transform, gl));
+            }
+
+            // over the top left of the window
+            if !overlay.is_empty() {
+                let width = overlay.iter().map(|line| crate::text::width(line, 2.0)).fold(0.0, f64::max);
+                rectangle(SHADE, [0.0, 0.0, width + 6.0, overlay.len() as f64 * 12.0 + 6.0], c.transform, gl);
+                for (ind, line) in overlay.iter().enumerate() {
+                    let y = 4.0 + ind as f64 * 12.0;
+                    crate::text::draw(line, 4.0, y, 2.0, |pixel| rectangle(GREEN, pixel, c.transform, gl));
+                }
+            }
+        });
+    }
+}
+
+/// The name a KeyMap knows a piston key by, e.g. `D1` is just `1`
+fn key_name(key: Key) -> String {
+    let name = format!("{:?}", key);
+    match name.strip_prefix('D') {
+        Some(digit) if digit.len() == 1 => digit.to_string(),
+        _ => name,
+    }
+}
+
+/// A piston window, showing the game and taking keys from the keyboard
+///
+/// Besides the CHIP-8 keys, it holds on to the emulator's own hotkeys and
+/// any ROMs dropped on the window for [`Game`](crate::display::Game) to
+/// pick up.
+pub struct PistonFrontend {
+    window: Window,
+    app: App,
+    events: Events,
+    keymap: KeyMap,
+    tone: Option<Tone<Box<dyn AudioSink>>>,
+    phosphor: Phosphor,
+    // the window wants drawing, as of the last poll
+    render_args: Option<RenderArgs>,
+    hotkeys: Vec<(Hotkey, bool)>,
+    dropped: Vec<PathBuf>,
+    // every key pressed, by name, for menus
+    pressed: Vec<String>,
+    message: Option<String>,
+    overlay: Vec<String>,
+}
+
+impl PistonFrontend {
+    /// Opens a window `settings.scale` times the size of the screen
+    pub fn new(settings: Settings) -> PistonFrontend {
+        let opengl = OpenGL::V3_2;
+
+        let size = [WIDTH as u32 * settings.scale, HEIGHT as u32 * settings.scale];
+        let window: Window = WindowSettings::new("CHIP-8", size)
+            .graphics_api(opengl)
+            .exit_on_esc(true)
+            .resizable(true)
+            .build()
+            .unwrap();
+
+        PistonFrontend {
+            window,
+            app: App { gl: GlGraphics::new(opengl), scale_mode: settings.scale_mode, palette: settings.palette },
+            events: Events::new(EventSettings::new().ups(60)),
+            keymap: settings.keymap,
+            tone: settings.tone,
+            phosphor: settings.phosphor,
+            render_args: None,
+            hotkeys: vec![],
+            dropped: vec![],
+            pressed: vec![],
+            message: None,
+            overlay: vec![],
+        }
+    }
+}
+
+impl GameWindow for PistonFrontend {
+    fn drawing(&self) -> bool {
+        self.render_args.is_some()
+    }
+
+    fn take_hotkeys(&mut self) -> Vec<(Hotkey, bool)> {
+        mem::take(&mut self.hotkeys)
+    }
+
+    fn take_dropped(&mut self) -> Vec<PathBuf> {
+        mem::take(&mut self.dropped)
+    }
+
+    fn take_pressed(&mut self) -> Vec<String> {
+        mem::take(&mut self.pressed)
+    }
+
+    fn show(&mut self, message: Option<&str>, overlay: Vec<String>) {
+        self.message = message.map(String::from);
+        self.overlay = overlay;
+    }
+}
+
+impl Frontend for PistonFrontend {
+    /// Draws the frame, if the window asked to be drawn since the last
+    /// poll
+    fn render(&mut self, fb: &FrameBuffer) {
+        if let Some(args) = self.render_args.take() {
+            self.phosphor.update(fb);
+            self.app.render(&args, &self.phosphor, self.message.as_deref(), &self.overlay);
+        }
+    }
+
+    /// Handles window events until it's time to update or draw
+    fn poll_input(&mut self) -> InputEvents {
+        let mut input = InputEvents::default();
+        loop {
+            let e = match self.events.next(&mut self.window) {
+                Some(e) => e,
+                None => {
+                    input.quit = true;
+                    return input;
+                }
+            };
+
+            if let Event::Input(Input::FileDrag(FileDrag::Drop(path)), _) = &e {
+                self.dropped.push(path.clone());
+            }
+
+            let pressed = e.press_args().map(|button| (button, true));
+            let released = e.release_args().map(|button| (button, false));
+            if let Some((Button::Keyboard(key), pressed)) = pressed.or(released) {
+                // piston's scancodes are the platform's own, so keys only
+                // go by what they type
+                let press = KeyPress { physical: None, character: Some(key_name(key)), pressed };
+                press.route(&self.keymap, &mut input, &mut self.hotkeys, &mut self.pressed);
+            }
+
+            if e.update_args().is_some() {
+                return input;
+            }
+            if let Some(args) = e.render_args() {
+                self.render_args = Some(args);
+                return input;
+            }
+        }
+    }
+
+    fn audio(&mut self, on: bool) {
+        if let Some(tone) = &mut self.tone {
+            tone.update(on);
+        }
+    }
+
+    fn audio_pattern(&mut self, pattern: Option<Pattern>) {
+        if let Some(tone) = &mut self.tone {
+            tone.update_pattern(pattern);
+        }
+    }
+
+    fn configure_audio(&mut self, settings: AudioSettings) {
+        if let Some(tone) = &mut self.tone {
+            tone.configure(settings);
+        }
+    }
+
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
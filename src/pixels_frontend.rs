@@ -0,0 +1,303 @@
+//! The default window, drawing through the `pixels` crate on winit
+//!
+//! The whole window is one buffer, filled in on the CPU and handed to the
+//! GPU as a texture each frame, presented with vsync.
+
+use std::error::Error;
+use std::mem;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use pixels::{Pixels, PixelsBuilder, SurfaceTexture};
+use winit::dpi::{LogicalSize, PhysicalSize};
+use winit::event::{ElementState, Event, KeyEvent, WindowEvent};
+use winit::event_loop::EventLoop;
+use winit::keyboard::{Key, NamedKey, PhysicalKey};
+use winit::platform::pump_events::{EventLoopExtPumpEvents, PumpStatus};
+use winit::window::{Window, WindowBuilder};
+
+use chip_8::audio::{AudioSettings, AudioSink, Pattern, Tone};
+use chip_8::frontend::{Frontend, InputEvents};
+use chip_8::screen::{Palette, Phosphor, HEIGHT, WIDTH};
+use chip_8::{keymap, FrameBuffer, KeyMap};
+
+use crate::display::{GameWindow, Hotkey, KeyPress, Layout, ScaleMode, Settings};
+
+const GREEN: [f32; 4] = [0.0, 1.0, 0.0, 1.0];
+const SHADE: [f32; 4] = [0.0, 0.0, 0.0, 0.75];
+
+/// How long a frame is on screen
+const FRAME: Duration = Duration::from_nanos(1_000_000_000 / 60);
+
+/// The name a KeyMap knows the character a winit key types by, e.g.
+/// `ArrowUp` is just `Up`
+fn key_name(key: &Key) -> Option<String> {
+    match key {
+        Key::Character(c) => Some(match c.as_str() {
+            "[" => String::from("LeftBracket"),
+            "]" => String::from("RightBracket"),
+            "\\" => String::from("Backslash"),
+            "." => String::from("Period"),
+            c => c.to_uppercase(),
+        }),
+        Key::Named(named) => {
+            let name = format!("{:?}", named);
+            Some(name.strip_prefix("Arrow").map(String::from).unwrap_or(name))
+        }
+        _ => None,
+    }
+}
+
+/// Draws into a buffer of RGBA pixels `width` wide
+struct Canvas<'a> {
+    frame: &'a mut [u8],
+    width: usize,
+    height: usize,
+}
+
+impl Canvas<'_> {
+    /// Fills a rectangle, given as x, y, width and height, blending `color`
+    /// over what's there by its alpha
+    fn fill(&mut self, rect: [f64; 4], color: [f32; 4]) {
+        let left = (rect[0].max(0.0) as usize).min(self.width);
+        let top = (rect[1].max(0.0) as usize).min(self.height);
+        let right = ((rect[0] + rect[2]).max(0.0) as usize).min(self.width);
+        let bottom = ((rect[1] + rect[3]).max(0.0) as usize).min(self.height);
+
+        for y in top..bottom {
+            let row = &mut self.frame[(y * self.width + left) * 4..(y * self.width + right) * 4];
+            for pixel in row.chunks_exact_mut(4) {
+                for (channel, &value) in pixel.iter_mut().zip(&color[..3]) {
+                    let blended = value * color[3] + *channel as f32 / 255.0 * (1.0 - color[3]);
+                    *channel = (blended * 255.0).round() as u8;
+                }
+                pixel[3] = 0xFF;
+            }
+        }
+    }
+
+    /// Writes `text` with its top left corner at (x, y)
+    fn text(&mut self, text: &str, x: f64, y: f64, size: f64) {
+        crate::text::draw(text, x, y, size, |pixel| self.fill(pixel, GREEN));
+    }
+}
+
+/// A winit window drawn with `pixels`, taking keys from the keyboard
+///
+/// Besides the CHIP-8 keys, it holds on to the emulator's own hotkeys and
+/// any ROMs dropped on the window for [`Game`](crate::display::Game) to
+/// pick up.
+pub struct PixelsFrontend {
+    // the surface keeps its own handle on the window
+    pixels: Pixels<'static>,
+    window: Arc<Window>,
+    event_loop: EventLoop<()>,
+    size: PhysicalSize<u32>,
+    scale_mode: ScaleMode,
+    keymap: KeyMap,
+    tone: Option<Tone<Box<dyn AudioSink>>>,
+    phosphor: Phosphor,
+    palette: Palette,
+    // when the next frame should be drawn, and whether it's time yet
+    next_frame: Instant,
+    redraw: bool,
+    hotkeys: Vec<(Hotkey, bool)>,
+    dropped: Vec<PathBuf>,
+    // every key pressed, by name, for menus
+    pressed: Vec<String>,
+    message: Option<String>,
+    overlay: Vec<String>,
+}
+
+impl PixelsFrontend {
+    /// Opens a window `settings.scale` times the size of the screen
+    pub fn new(settings: Settings) -> Result<PixelsFrontend, Box<dyn Error>> {
+        let event_loop = EventLoop::new()?;
+        let logical = LogicalSize::new(WIDTH as u32 * settings.scale, HEIGHT as u32 * settings.scale);
+        let window = WindowBuilder::new()
+            .with_title("CHIP-8")
+            .with_inner_size(logical)
+            .with_resizable(true)
+            .build(&event_loop)?;
+        let window = Arc::new(window);
+
+        let size = window.inner_size();
+        let surface = SurfaceTexture::new(size.width, size.height, Arc::clone(&window));
+        let pixels = PixelsBuilder::new(size.width, size.height, surface).enable_vsync(true).build()?;
+
+        Ok(PixelsFrontend {
+            pixels,
+            window,
+            event_loop,
+            size,
+            scale_mode: settings.scale_mode,
+            keymap: settings.keymap,
+            tone: settings.tone,
+            phosphor: settings.phosphor,
+            palette: settings.palette,
+            next_frame: Instant::now(),
+            redraw: false,
+            hotkeys: vec![],
+            dropped: vec![],
+            pressed: vec![],
+            message: None,
+            overlay: vec![],
+        })
+    }
+
+    /// Draws the phosphor, message and overlay into the buffer
+    fn draw(&mut self) {
+        let (width, height) = (self.size.width as usize, self.size.height as usize);
+        let mut canvas = Canvas { frame: self.pixels.frame_mut(), width, height };
+        canvas.frame.fill(0);
+
+        let screen = [self.phosphor.width(), self.phosphor.height()];
+        let layout = Layout::new([width as f64, height as f64], screen, self.scale_mode);
+        for (row_ind, row) in self.phosphor.rows().enumerate() {
+            for (col_ind, _) in row.iter().enumerate() {
+                let x = layout.x + col_ind as f64 * layout.width;
+                let y = layout.y + row_ind as f64 * layout.height;
+                canvas.fill([x, y, layout.width, layout.height], self.phosphor.color(col_ind, row_ind, &self.palette));
+            }
+        }
+
+        // the font is sized for logical pixels, like the piston window's
+        let size = (2.0 * self.window.scale_factor()).round();
+
+        // along the bottom of the window, on a dark backing so it can be
+        // read over the pixels
+        if let Some(message) = &self.message {
+            let y = height as f64 - 7.0 * size;
+            canvas.fill([0.0, y - 2.0 * size, crate::text::width(message, size) + 3.0 * size, 9.0 * size], SHADE);
+            canvas.text(message, 2.0 * size, y, size);
+        }
+
+        // over the top left of the window
+        if !self.overlay.is_empty() {
+            let widest = self.overlay.iter().map(|line| crate::text::width(line, size)).fold(0.0, f64::max);
+            canvas.fill([0.0, 0.0, widest + 3.0 * size, (self.overlay.len() as f64 * 6.0 + 3.0) * size], SHADE);
+            for (ind, line) in self.overlay.iter().enumerate() {
+                canvas.text(line, 2.0 * size, (2.0 + ind as f64 * 6.0) * size, size);
+            }
+        }
+    }
+}
+
+impl GameWindow for PixelsFrontend {
+    fn drawing(&self) -> bool {
+        self.redraw
+    }
+
+    fn take_hotkeys(&mut self) -> Vec<(Hotkey, bool)> {
+        mem::take(&mut self.hotkeys)
+    }
+
+    fn take_dropped(&mut self) -> Vec<PathBuf> {
+        mem::take(&mut self.dropped)
+    }
+
+    fn take_pressed(&mut self) -> Vec<String> {
+        mem::take(&mut self.pressed)
+    }
+
+    fn show(&mut self, message: Option<&str>, overlay: Vec<String>) {
+        self.message = message.map(String::from);
+        self.overlay = overlay;
+    }
+}
+
+impl Frontend for PixelsFrontend {
+    /// Draws the frame, if one is due since the last poll
+    fn render(&mut self, fb: &FrameBuffer) {
+        if !mem::take(&mut self.redraw) {
+            return;
+        }
+
+        self.phosphor.update(fb);
+        self.draw();
+        if let Err(err) = self.pixels.render() {
+            eprintln!("Couldn't draw the frame: {}", err);
+        }
+    }
+
+    /// Handles window events until one comes in or the next frame is due
+    fn poll_input(&mut self) -> InputEvents {
+        let mut input = InputEvents::default();
+        let timeout = self.next_frame.saturating_duration_since(Instant::now());
+
+        let PixelsFrontend { pixels, event_loop, size, keymap, hotkeys, dropped, pressed: named, .. } = self;
+        let status = event_loop.pump_events(Some(timeout), |event, target| {
+            let event = match event {
+                Event::WindowEvent { event, .. } => event,
+                _ => return,
+            };
+            match event {
+                WindowEvent::CloseRequested => target.exit(),
+                WindowEvent::DroppedFile(path) => dropped.push(path),
+                // minimizing takes the size to nothing, which there's
+                // nothing to draw into
+                WindowEvent::Resized(new) if new.width > 0 && new.height > 0 => {
+                    *size = new;
+                    let resized = pixels.resize_surface(new.width, new.height);
+                    if let Err(err) = resized.and_then(|()| pixels.resize_buffer(new.width, new.height)) {
+                        eprintln!("Couldn't resize the window: {}", err);
+                    }
+                }
+                WindowEvent::KeyboardInput {
+                    event: KeyEvent { physical_key, logical_key, state, repeat: false, .. }, ..
+                } => {
+                    if logical_key == Key::Named(NamedKey::Escape) {
+                        target.exit();
+                    } else {
+                        let physical = match physical_key {
+                            PhysicalKey::Code(code) => Some(keymap::code_name(&format!("{:?}", code))),
+                            PhysicalKey::Unidentified(_) => None,
+                        };
+                        let press = KeyPress {
+                            physical,
+                            character: key_name(&logical_key),
+                            pressed: state == ElementState::Pressed,
+                        };
+                        press.route(keymap, &mut input, hotkeys, named);
+                    }
+                }
+                _ => {}
+            }
+        });
+        if let PumpStatus::Exit(_) = status {
+            input.quit = true;
+        }
+
+        let now = Instant::now();
+        if now >= self.next_frame {
+            self.redraw = true;
+            // a frame running late pushes the next one back, rather than
+            // drawing a burst to catch up
+            self.next_frame = (self.next_frame + FRAME).max(now);
+        }
+        input
+    }
+
+    fn audio(&mut self, on: bool) {
+        if let Some(tone) = &mut self.tone {
+            tone.update(on);
+        }
+    }
+
+    fn audio_pattern(&mut self, pattern: Option<Pattern>) {
+        if let Some(tone) = &mut self.tone {
+            tone.update_pattern(pattern);
+        }
+    }
+
+    fn configure_audio(&mut self, settings: AudioSettings) {
+        if let Some(tone) = &mut self.tone {
+            tone.configure(settings);
+        }
+    }
+
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
@@ -0,0 +1,200 @@
+//! Profiling: which instructions a ROM spends its time running
+//!
+//! Set a [`Profiler`] as the CPU's observer, run the ROM, then ask it for a
+//! [`ProfileReport`], which lists the opcode patterns and addresses that
+//! ran, busiest first. Every instruction costs the same here, so the
+//! busiest addresses are the loops worth looking at when a ROM runs slowly.
+//!
+//! # Example
+//!
+//! ```
+//! use std::sync::{Arc, Mutex};
+//!
+//! use chip_8::profile::Profiler;
+//! use chip_8::CpuBuilder;
+//!
+//! // LD V0, 0; ADD V0, 1; SE V0, 5; JP 0x202; terminate
+//! let rom = [0x60, 0x00, 0x70, 0x01, 0x30, 0x05, 0x12, 0x02, 0x00, 0x00];
+//! let mut cpu = CpuBuilder::new().build();
+//! cpu.load_program(&rom).unwrap();
+//!
+//! let profiler = Arc::new(Mutex::new(Profiler::new()));
+//! cpu.set_observer(profiler.clone());
+//! while cpu.run().is_some() {}
+//!
+//! let report = profiler.lock().unwrap().report();
+//! assert_eq!(report.opcodes[..2], [("3XNN", 5), ("7XNN", 5)]);
+//! assert_eq!(report.addresses[0], (0x202, 5));
+//! ```
+
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::instruction::Instruction;
+use crate::observer::Observer;
+
+/// How many of the busiest addresses a report prints
+const PRINTED_ADDRESSES: usize = 16;
+
+/// Tallies the instructions run, by opcode pattern and by address
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Profiler {
+    // how many instructions have run from each address that any have
+    addresses: BTreeMap<usize, u64>,
+    // how many times each opcode pattern has run
+    opcodes: BTreeMap<&'static str, u64>,
+}
+
+impl Default for Profiler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Profiler {
+    /// A profiler with nothing run yet
+    pub fn new() -> Profiler {
+        Profiler { addresses: BTreeMap::new(), opcodes: BTreeMap::new() }
+    }
+
+    /// How many instructions have run in all
+    pub fn total(&self) -> u64 {
+        self.opcodes.values().sum()
+    }
+
+    /// Forgets everything run so far, e.g. after resetting the CPU
+    pub fn clear(&mut self) {
+        *self = Profiler::new();
+    }
+
+    /// The tallies so far, busiest first
+    pub fn report(&self) -> ProfileReport {
+        let mut opcodes: Vec<_> = self.opcodes.iter().map(|(&pattern, &count)| (pattern, count)).collect();
+        // stable, so ties stay in opcode order
+        opcodes.sort_by_key(|&(_, count)| Reverse(count));
+
+        let mut addresses: Vec<_> = self.addresses.iter().map(|(&addr, &count)| (addr, count)).collect();
+        addresses.sort_by_key(|&(_, count)| Reverse(count));
+
+        ProfileReport { total: self.total(), opcodes, addresses }
+    }
+}
+
+impl Observer for Profiler {
+    fn on_execute(&mut self, addr: usize, instruction: Instruction) {
+        *self.addresses.entry(addr).or_insert(0) += 1;
+        *self.opcodes.entry(instruction.pattern()).or_insert(0) += 1;
+    }
+}
+
+/// Where a run spent its instructions, from [`Profiler::report`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProfileReport {
+    /// How many instructions ran in all
+    pub total: u64,
+    /// How many times each opcode pattern like `"8XY4"` ran, busiest first
+    pub opcodes: Vec<(&'static str, u64)>,
+    /// How many times an instruction ran from each address, busiest first,
+    /// leaving out addresses nothing ran from
+    pub addresses: Vec<(usize, u64)>,
+}
+
+impl ProfileReport {
+    /// The share of all instructions that `count` is, from 0 to 100
+    fn percent(&self, count: u64) -> f64 {
+        if self.total == 0 {
+            return 0.0;
+        }
+        count as f64 * 100.0 / self.total as f64
+    }
+}
+
+impl fmt::Display for ProfileReport {
+    /// Every opcode pattern that ran and the busiest addresses, each with
+    /// its count and share of the total
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Ran {} instructions", self.total)?;
+
+        writeln!(f, "By opcode:")?;
+        for &(pattern, count) in &self.opcodes {
+            writeln!(f, "  {}  {:>10}  {:5.1}%", pattern, count, self.percent(count))?;
+        }
+
+        write!(f, "Busiest addresses:")?;
+        for &(addr, count) in self.addresses.iter().take(PRINTED_ADDRESSES) {
+            write!(f, "\n  0x{:03X}  {:>10}  {:5.1}%", addr, count, self.percent(count))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::{memory, CpuBuilder};
+
+    fn run(rom: &[u8], cycles: usize) -> Profiler {
+        // room for ROMs past 4K
+        let mut cpu = CpuBuilder::new().memory_size(memory::XO_CHIP_SIZE).build();
+        cpu.load_program(rom).unwrap();
+        let profiler = Arc::new(Mutex::new(Profiler::new()));
+        cpu.set_observer(profiler.clone());
+        cpu.run_cycles(cycles);
+
+        let profiler = profiler.lock().unwrap().clone();
+        profiler
+    }
+
+    #[test]
+    fn report_sorts_busiest_first() {
+        // LD V0, 1; LD V1, 2; ADD V0, V1; JP 0x204
+        let rom = [0x60, 0x01, 0x61, 0x02, 0x80, 0x14, 0x12, 0x04];
+        let report = run(&rom, 10).report();
+
+        assert_eq!(report.total, 10);
+        // ties stay in opcode and address order
+        assert_eq!(report.opcodes, [("1NNN", 4), ("8XY4", 4), ("6XNN", 2)]);
+        assert_eq!(report.addresses, [(0x204, 4), (0x206, 4), (0x200, 1), (0x202, 1)]);
+    }
+
+    #[test]
+    fn report_prints_counts_and_shares() {
+        // LD V0, 1; terminate
+        let rom = [0x60, 0x01, 0x00, 0x00];
+        let text = run(&rom, 5).report().to_string();
+
+        assert_eq!(
+            text,
+            "Ran 2 instructions\n\
+             By opcode:\n  \
+             0000           1   50.0%\n  \
+             6XNN           1   50.0%\n\
+             Busiest addresses:\n  \
+             0x200           1   50.0%\n  \
+             0x202           1   50.0%"
+        );
+    }
+
+    #[test]
+    fn clear_forgets_everything() {
+        let mut profiler = run(&[0x60, 0x01], 1);
+        assert_eq!(profiler.total(), 1);
+
+        profiler.clear();
+        assert_eq!(profiler.report(), ProfileReport { total: 0, opcodes: vec![], addresses: vec![] });
+    }
+
+    #[test]
+    fn roms_running_past_4k_are_counted() {
+        // LD V0, 0 up to 0x1000, then terminate
+        let mut rom = [0x60, 0x00].repeat(0x700);
+        rom.extend_from_slice(&[0x00, 0x00]);
+        let report = run(&rom, 0x800).report();
+
+        assert_eq!(report.total, 0x701);
+        assert!(report.addresses.contains(&(0x1000, 1)));
+    }
+}
@@ -0,0 +1,69 @@
+//! Behaviours that differ between CHIP-8 interpreters
+//!
+//! Programs written for one interpreter often rely on its particular take on
+//! a few ambiguous instructions, and misbehave anywhere else.
+
+/// Which interpreter behaviours a program expects
+///
+/// Everything is off by default, which is how this emulator has always run,
+/// apart from FX0A waiting for a key's release.
+///
+/// # Examples
+/// ```
+/// use chip_8::{CpuBuilder, Quirks};
+///
+/// let cpu = CpuBuilder::new()
+///     .quirks(Quirks { jump_uses_vx: true, ..Quirks::default() })
+///     .build();
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Quirks {
+    /// 8XY1, 8XY2 and 8XY3 reset VF to 0, like the original COSMAC VIP
+    pub vf_reset: bool,
+    /// 8XY6 and 8XYE shift VY and store the result in VX, like the original
+    /// COSMAC VIP, rather than shifting VX in place like SUPER-CHIP
+    pub shift_uses_vy: bool,
+    /// FX55 and FX65 leave I pointing just past the last register they
+    /// touched, like the original COSMAC VIP, rather than leaving it alone
+    pub memory_increments_i: bool,
+    /// With `memory_increments_i`, I is left pointing at the last register
+    /// touched instead, one short, like CHIP-48
+    pub memory_increments_i_by_x: bool,
+    /// BXNN jumps to XNN + VX instead of NNN + V0, like CHIP-48 and SUPER-CHIP
+    pub jump_uses_vx: bool,
+    /// Sprites are cut off at the edges of the screen instead of wrapping
+    /// around to the other side
+    pub clip_sprites: bool,
+    /// In high resolution, DXYN sets VF to how many sprite rows collided or
+    /// were cut off at the bottom of the screen, like SUPER-CHIP 1.1, rather
+    /// than to 1 for any collision; low resolution is unaffected
+    pub collision_rows: bool,
+    /// DXYN holds the program up until the next timer tick, the 60Hz
+    /// vertical blank, like the original COSMAC VIP, so at most one sprite
+    /// is drawn a frame
+    pub display_wait: bool,
+    /// FX0A finishes as soon as a key is pressed, like many later
+    /// interpreters, rather than once it's released like the COSMAC VIP
+    pub wait_key_on_press: bool,
+}
+
+impl Quirks {
+    /// Turns the quirk with the same name as its field on or off, returning
+    /// false if there's no such quirk
+    pub(crate) fn set(&mut self, name: &str, on: bool) -> bool {
+        let quirk = match name {
+            "vf_reset" => &mut self.vf_reset,
+            "shift_uses_vy" => &mut self.shift_uses_vy,
+            "memory_increments_i" => &mut self.memory_increments_i,
+            "memory_increments_i_by_x" => &mut self.memory_increments_i_by_x,
+            "jump_uses_vx" => &mut self.jump_uses_vx,
+            "clip_sprites" => &mut self.clip_sprites,
+            "collision_rows" => &mut self.collision_rows,
+            "display_wait" => &mut self.display_wait,
+            "wait_key_on_press" => &mut self.wait_key_on_press,
+            _ => return false,
+        };
+        *quirk = on;
+        true
+    }
+}
@@ -0,0 +1,93 @@
+//! Where CXNN's random numbers come from, and saving the generator's place
+//! so a restored CPU draws the same numbers it would have
+
+use rand::SeedableRng;
+use rand_chacha::ChaCha12Rng;
+
+use crate::Cpu;
+
+/// Everything about the random number generator that decides what CXNN
+/// draws next: the seed it started from and how far along it's got
+///
+/// Save states carry it, so restoring one, rewinding, or rolling back a
+/// netplay session replays the same random numbers exactly.
+///
+/// # Examples
+/// ```
+/// use chip_8::CpuBuilder;
+///
+/// let mut cpu = CpuBuilder::new().build();
+/// // RND V0, 0xFF; RND V0, 0xFF
+/// cpu.load_program(&[0xC0, 0xFF, 0xC0, 0xFF]).unwrap();
+/// cpu.run();
+/// let state = cpu.rng_state();
+/// cpu.run();
+/// let drawn = cpu.registers(0);
+///
+/// let mut copy = CpuBuilder::new().rng_state(state).build();
+/// copy.load_program(&[0xC0, 0xFF]).unwrap();
+/// copy.run();
+/// assert_eq!(copy.registers(0), drawn);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RngState {
+    /// The ChaCha12 key, as expanded from the CPU's seed or taken from the OS
+    pub seed: [u8; 32],
+    /// How many 32 bit words of the stream have been used
+    pub position: u128,
+}
+
+impl RngState {
+    /// Where `rng` is up to
+    pub(crate) fn of(rng: &ChaCha12Rng) -> RngState {
+        RngState { seed: rng.get_seed(), position: rng.get_word_pos() }
+    }
+
+    /// A generator picking up from here
+    pub(crate) fn generator(&self) -> ChaCha12Rng {
+        let mut rng = ChaCha12Rng::from_seed(self.seed);
+        rng.set_word_pos(self.position);
+        rng
+    }
+}
+
+impl Cpu {
+    /// Where CXNN's random numbers are up to
+    pub fn rng_state(&self) -> RngState {
+        RngState::of(&self.rng)
+    }
+
+    /// Picks the random numbers up from `state`, taken from this or another
+    /// CPU by [`rng_state`](Cpu::rng_state)
+    ///
+    /// A seeded CPU still starts over from its seed on reset.
+    pub fn set_rng_state(&mut self, state: RngState) {
+        self.rng = state.generator();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::CpuBuilder;
+
+    #[test]
+    fn state_survives_part_used_blocks() {
+        let mut cpu = CpuBuilder::new().seed(3).build();
+        // RND V0, 0xFF, over and over
+        cpu.load_program(&[0xC0, 0xFF, 0x12, 0x00]).unwrap();
+        // odd numbers of draws leave the generator part way through a block
+        cpu.run_cycles(37);
+        let state = cpu.rng_state();
+        let mut copy = CpuBuilder::new().build();
+        copy.set_rng_state(state);
+        assert_eq!(copy.rng_state(), state);
+
+        copy.load_program(&[0xC0, 0xFF, 0x12, 0x00]).unwrap();
+        cpu.program_counter = 0x200;
+        for _ in 0..20 {
+            cpu.run_cycles(2);
+            copy.run_cycles(2);
+            assert_eq!(cpu.registers[0], copy.registers[0]);
+        }
+    }
+}
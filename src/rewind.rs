@@ -0,0 +1,274 @@
+//! Stepping a game backwards through recent frames
+//!
+//! A frontend calls [`Rewind::record`] once a frame while the game runs and
+//! [`Rewind::rewind`] to go back. Memory can be as big as MegaChip's 16MB, so
+//! rather than a copy of it every frame only the blocks each frame changed
+//! are kept, which for most games is a few hundred bytes a frame.
+
+use std::collections::VecDeque;
+
+use crate::snapshot::Snapshot;
+use crate::{Byte, Cpu, Memory};
+
+/// How many bytes of memory are compared and kept together
+const BLOCK_SIZE: usize = 256;
+
+/// Ten seconds at 60 frames a second
+pub const DEFAULT_CAPACITY: usize = 600;
+
+/// A ring buffer of the most recent frames' states
+pub struct Rewind {
+    frames: VecDeque<Frame>,
+    // memory as it was in the newest frame
+    memory: Vec<Byte>,
+    capacity: usize,
+}
+
+/// A recorded frame
+struct Frame {
+    // everything but memory, which is left empty
+    state: Snapshot,
+    // where each block the next frame changed starts, and what it held in
+    // this one
+    changed: Vec<(usize, Box<[Byte]>)>,
+}
+
+impl Default for Rewind {
+    fn default() -> Self {
+        Self::new(DEFAULT_CAPACITY)
+    }
+}
+
+impl Rewind {
+    /// Makes an empty Rewind that remembers up to `capacity` frames
+    pub fn new(capacity: usize) -> Rewind {
+        Rewind {
+            frames: VecDeque::with_capacity(capacity),
+            memory: Vec::new(),
+            capacity,
+        }
+    }
+
+    /// Remembers the CPU's current state, forgetting the oldest one if full
+    ///
+    /// The frames recorded before are forgotten if the CPU's memory isn't the
+    /// size it was, e.g. after restoring a save state from a bigger one.
+    pub fn record(&mut self, cpu: &Cpu) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        if cpu.memory.len() != self.memory.len() {
+            self.frames.clear();
+            self.memory = cpu.memory.to_vec();
+        } else if let Some(newest) = self.frames.back_mut() {
+            let blocks = self.memory.chunks_mut(BLOCK_SIZE).zip(cpu.memory.chunks(BLOCK_SIZE));
+            for (ind, (old, new)) in blocks.enumerate() {
+                if old != new {
+                    newest.changed.push((ind * BLOCK_SIZE, old.into()));
+                    old.copy_from_slice(new);
+                }
+            }
+        } else {
+            self.memory.copy_from_slice(&cpu.memory);
+        }
+
+        if self.frames.len() == self.capacity {
+            self.frames.pop_front();
+        }
+        self.frames.push_back(Frame {
+            state: cpu.snapshot_with(Memory::default()),
+            changed: Vec::new(),
+        });
+    }
+
+    /// Puts the CPU back `frames` recorded frames, or as far as it can go
+    ///
+    /// The frames rewound over are forgotten. Returns how many frames the
+    /// CPU actually went back, which is zero when nothing is recorded.
+    ///
+    /// # Examples
+    /// ```
+    /// use chip_8::rewind::Rewind;
+    /// use chip_8::CpuBuilder;
+    ///
+    /// let mut memory = [0; 4096];
+    /// // ADD V0, 1 over and over
+    /// for ind in (0..0x20).step_by(2) {
+    ///     memory[ind] = 0x70; memory[ind + 1] = 0x01;
+    /// }
+    /// let mut cpu = CpuBuilder::new().memory(memory).build();
+    /// let mut rewind = Rewind::new(10);
+    ///
+    /// for _ in 0..5 {
+    ///     rewind.record(&cpu);
+    ///     cpu.run();
+    /// }
+    ///
+    /// assert_eq!(rewind.rewind(&mut cpu, 2), 2);
+    /// assert_eq!(cpu.registers(0), 3);
+    /// ```
+    pub fn rewind(&mut self, cpu: &mut Cpu, frames: usize) -> usize {
+        let frames = frames.min(self.frames.len());
+        if frames == 0 {
+            return 0;
+        }
+
+        // undo each newer frame's changes, newest first, to get back to the
+        // memory of the frame going back to
+        let target = self.frames.len() - frames;
+        for frame in self.frames.range(target..).rev() {
+            undo(&mut self.memory, &frame.changed);
+        }
+        self.frames.truncate(target + 1);
+        if let Some(frame) = self.frames.pop_back() {
+            cpu.restore_with(&frame.state, &self.memory);
+        }
+
+        // and the frame before it is the newest now
+        if let Some(newest) = self.frames.back_mut() {
+            undo(&mut self.memory, &newest.changed);
+            newest.changed.clear();
+        }
+
+        frames
+    }
+
+    /// How many frames are recorded
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Whether there are no frames to go back to
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// Forgets every recorded frame, e.g. after the game is reset
+    pub fn clear(&mut self) {
+        self.frames.clear();
+    }
+}
+
+/// Puts back the blocks of memory a frame's successor changed
+fn undo(memory: &mut [Byte], changed: &[(usize, Box<[Byte]>)]) {
+    for (start, block) in changed {
+        memory[*start..*start + block.len()].copy_from_slice(block);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::MEGA_CHIP_SIZE;
+    use crate::CpuBuilder;
+
+    fn counting_cpu() -> Cpu {
+        let mut memory = [0; 0x1000];
+        // ADD V0, 1 then jump back to it
+        memory[..4].copy_from_slice(&[0x70, 0x01, 0x12, 0x00]);
+        CpuBuilder::new().memory(memory).build()
+    }
+
+    #[test]
+    fn record_forgets_oldest_frames_when_full() {
+        let mut cpu = counting_cpu();
+        let mut rewind = Rewind::new(3);
+
+        for _ in 0..5 {
+            rewind.record(&cpu);
+            cpu.run_cycles(2);
+        }
+
+        assert_eq!(rewind.len(), 3);
+        assert_eq!(rewind.rewind(&mut cpu, 10), 3);
+        assert_eq!(cpu.registers(0), 2);
+        assert!(rewind.is_empty());
+    }
+
+    #[test]
+    fn rewind_steps_back_one_frame_at_a_time() {
+        let mut cpu = counting_cpu();
+        let mut rewind = Rewind::default();
+
+        for _ in 0..4 {
+            rewind.record(&cpu);
+            cpu.run_cycles(2);
+        }
+
+        rewind.rewind(&mut cpu, 1);
+        assert_eq!(cpu.registers(0), 3);
+        rewind.rewind(&mut cpu, 1);
+        assert_eq!(cpu.registers(0), 2);
+        assert_eq!(rewind.len(), 2);
+    }
+
+    fn storing_cpu(memory_size: usize) -> Cpu {
+        let mut cpu = CpuBuilder::new().memory_size(memory_size).build();
+        // ADD V0, 1; LD I, 0x300; LD [I], V0; then jump back to the start
+        cpu.load_program(&[0x70, 0x01, 0xA3, 0x00, 0xF0, 0x55, 0x12, 0x00]).unwrap();
+        cpu
+    }
+
+    #[test]
+    fn rewind_puts_memory_back() {
+        let mut cpu = storing_cpu(0x1000);
+        let mut rewind = Rewind::default();
+
+        for _ in 0..6 {
+            rewind.record(&cpu);
+            cpu.run_cycles(4);
+        }
+
+        rewind.rewind(&mut cpu, 2);
+        assert_eq!((cpu.registers(0), cpu.memory()[0x300]), (4, 4));
+        rewind.rewind(&mut cpu, 3);
+        assert_eq!((cpu.registers(0), cpu.memory()[0x300]), (1, 1));
+
+        // recording again carries on from the rewound memory
+        rewind.record(&cpu);
+        cpu.run_cycles(4);
+        rewind.record(&cpu);
+        rewind.rewind(&mut cpu, 3);
+        assert_eq!((cpu.registers(0), cpu.memory()[0x300]), (0, 0));
+    }
+
+    #[test]
+    fn only_changed_memory_is_kept() {
+        let mut cpu = storing_cpu(MEGA_CHIP_SIZE);
+        let mut rewind = Rewind::default();
+
+        for _ in 0..10 {
+            rewind.record(&cpu);
+            cpu.run_cycles(4);
+        }
+
+        let kept: usize = rewind.frames.iter().flat_map(|frame| &frame.changed).map(|(_, block)| block.len()).sum();
+        assert_eq!(kept, 9 * BLOCK_SIZE);
+    }
+
+    #[test]
+    fn memory_changing_size_starts_over() {
+        let mut cpu = storing_cpu(0x1000);
+        let mut rewind = Rewind::default();
+        rewind.record(&cpu);
+        cpu.run_cycles(4);
+        rewind.record(&cpu);
+
+        cpu.restore(&storing_cpu(0x2000).snapshot());
+        rewind.record(&cpu);
+
+        assert_eq!(rewind.len(), 1);
+        assert_eq!(rewind.rewind(&mut cpu, 5), 1);
+        assert_eq!(cpu.memory().len(), 0x2000);
+    }
+
+    #[test]
+    fn zero_capacity_records_nothing() {
+        let mut cpu = counting_cpu();
+        let mut rewind = Rewind::new(0);
+        rewind.record(&cpu);
+
+        assert_eq!(rewind.rewind(&mut cpu, 1), 0);
+    }
+}
@@ -0,0 +1,253 @@
+//! Settings kept beside a ROM, for games that need more than the defaults
+//!
+//! A ROM's settings live in a TOML file named after it, so `game.ch8` is
+//! set up by `game.ch8.toml` in the same directory, if there is one. Every
+//! setting is optional:
+//!
+//! ```toml
+//! # game.ch8.toml
+//! variant = "schip"       # quirks to start from, as --variant takes them
+//! clock = 1000            # instructions a second, or "vip" or "hp48"
+//! keymap = "arrows"       # a preset, or a map file beside this one
+//!
+//! [quirks]                # changes to the variant's quirks
+//! clip_sprites = false
+//!
+//! [palette]
+//! on = "#FFAA00"
+//! off = "#1A1000"
+//!
+//! [cheats]                # names and codes, as cheats::Cheat::parse reads them
+//! "Infinite lives" = "3C5=03"
+//!
+//! [audio]
+//! volume = 40             # 0 to 100
+//! mute = false
+//! frequency = 220         # the tone's pitch in Hz
+//! ```
+//!
+//! The quirks are named after the fields of [`Quirks`]. Settings given here
+//! win over the [`rom_db`](crate::rom_db)'s and the player's
+//! [`config`](crate::config) file, and lose to the command line's.
+//!
+//! # Example
+//!
+//! ```
+//! use chip_8::rom_config::RomConfig;
+//! use chip_8::timing::TimingModel;
+//!
+//! let config: RomConfig = "variant = \"chip48\"\nclock = \"hp48\"".parse().unwrap();
+//! assert!(config.quirks.unwrap().jump_uses_vx);
+//! assert_eq!(config.timing, Some(TimingModel::Hp48));
+//! assert_eq!(config.keymap, None);
+//! ```
+
+use std::error::Error;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use toml::value::{Table, Value};
+
+use crate::analyze::Variant;
+use crate::audio::AudioSettings;
+use crate::cheats::Cheat;
+use crate::screen::{parse_color, Palette};
+use crate::timing::TimingModel;
+use crate::{KeyMap, Quirks};
+
+/// What a ROM's settings file asks for, with anything it leaves out unset
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct RomConfig {
+    pub quirks: Option<Quirks>,
+    pub timing: Option<TimingModel>,
+    /// A [`KeyMap`] preset or the path of a map file
+    pub keymap: Option<String>,
+    pub palette: Option<Palette>,
+    /// Sorted by name, however they're listed
+    pub cheats: Vec<Cheat>,
+    /// From 0 to 100
+    pub volume: Option<u8>,
+    pub muted: Option<bool>,
+    /// The tone's pitch in Hz
+    pub frequency: Option<u32>,
+}
+
+impl RomConfig {
+    /// `settings` with whichever of the volume, mute and frequency this sets
+    /// changed
+    pub fn audio_over(&self, settings: AudioSettings) -> AudioSettings {
+        AudioSettings {
+            frequency: self.frequency.unwrap_or(settings.frequency),
+            volume: self.volume.unwrap_or(settings.volume),
+            muted: self.muted.unwrap_or(settings.muted),
+        }
+    }
+
+    /// Where the settings for the ROM at `rom` are kept, the ROM's own path
+    /// with `.toml` on the end
+    pub fn path_for<P: AsRef<Path>>(rom: P) -> PathBuf {
+        let mut path = rom.as_ref().as_os_str().to_owned();
+        path.push(".toml");
+        PathBuf::from(path)
+    }
+
+    /// Reads the settings for the ROM at `rom`, if it has any
+    pub fn for_rom<P: AsRef<Path>>(rom: P) -> Result<Option<RomConfig>, Box<dyn Error>> {
+        match RomConfig::load(RomConfig::path_for(rom)) {
+            Ok(config) => Ok(Some(config)),
+            Err(err) if err.downcast_ref::<io::Error>().is_some_and(|err| err.kind() == io::ErrorKind::NotFound) => {
+                Ok(None)
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Reads a settings file
+    ///
+    /// A map file's path is taken as relative to the settings file, so the
+    /// two can be moved around together.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<RomConfig, Box<dyn Error>> {
+        let path = path.as_ref();
+        let mut config: RomConfig = fs::read_to_string(path)?.parse()?;
+        config.resolve_keymap(path);
+        Ok(config)
+    }
+
+    /// Makes a map file's path relative to the settings file at `path`
+    /// rather than the current directory
+    pub(crate) fn resolve_keymap(&mut self, path: &Path) {
+        if let (Some(keymap), Some(dir)) = (&mut self.keymap, path.parent()) {
+            if KeyMap::preset(keymap).is_none() {
+                *keymap = dir.join(&*keymap).to_string_lossy().into_owned();
+            }
+        }
+    }
+}
+
+impl FromStr for RomConfig {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<RomConfig, String> {
+        RomConfig::from_table(&parse_table(text)?)
+    }
+}
+
+/// Reads a settings file's TOML, which has to be a table
+pub(crate) fn parse_table(text: &str) -> Result<Table, String> {
+    match text.parse::<Value>().map_err(|err| err.to_string())? {
+        Value::Table(table) => Ok(table),
+        _ => Err("the settings should be a table".to_string()),
+    }
+}
+
+impl RomConfig {
+    /// Reads the settings from an already parsed file
+    pub(crate) fn from_table(table: &Table) -> Result<RomConfig, String> {
+        let mut config = RomConfig::default();
+        for (name, value) in table {
+            match (name.as_str(), value) {
+                ("variant", Value::String(variant)) => match Variant::from_name(variant) {
+                    // the [quirks] table is applied over these once everything is read
+                    Some(variant) => config.quirks = Some(variant.quirks()),
+                    None => return Err(format!("variant: `{}` isn't a CHIP-8 variant", variant)),
+                },
+                ("quirks", Value::Table(_)) => {}
+                ("clock", Value::Integer(hz)) => config.timing = Some(hz.to_string().parse()?),
+                ("clock", Value::String(model)) => config.timing = Some(model.parse()?),
+                ("keymap", Value::String(keymap)) => config.keymap = Some(keymap.clone()),
+                ("palette", Value::Table(colors)) => {
+                    let mut palette = Palette::default();
+                    for (which, color) in colors {
+                        match (which.as_str(), color.as_str()) {
+                            ("on", Some(color)) => palette.on = parse_color(color)?,
+                            ("off", Some(color)) => palette.off = parse_color(color)?,
+                            _ => return Err(format!("palette: {} should be `on` or `off`, set to a colour", which)),
+                        }
+                    }
+                    config.palette = Some(palette);
+                }
+                ("cheats", Value::Table(cheats)) => {
+                    for (name, code) in cheats {
+                        match code.as_str() {
+                            Some(code) => config.cheats.push(Cheat::parse(name, code)?),
+                            None => return Err(format!("cheats: {} should be set to a code", name)),
+                        }
+                    }
+                }
+                ("audio", Value::Table(audio)) => {
+                    for (which, value) in audio {
+                        match (which.as_str(), value) {
+                            ("volume", Value::Integer(volume @ 0..=100)) => config.volume = Some(*volume as u8),
+                            ("mute", Value::Boolean(muted)) => config.muted = Some(*muted),
+                            ("frequency", Value::Integer(hz @ 1..=20_000)) => config.frequency = Some(*hz as u32),
+                            ("volume", _) => return Err("audio: volume should be from 0 to 100".to_string()),
+                            ("mute", _) => return Err("audio: mute should be true or false".to_string()),
+                            ("frequency", _) => return Err("audio: frequency should be from 1 to 20000Hz".to_string()),
+                            _ => return Err(format!("audio: {} isn't an audio setting", which)),
+                        }
+                    }
+                }
+                ("variant" | "quirks" | "clock" | "keymap" | "palette" | "cheats" | "audio", _) => {
+                    return Err(format!("{} is set to {}, which it can't be", name, value))
+                }
+                _ => return Err(format!("{} isn't a ROM setting", name)),
+            }
+        }
+
+        if let Some(Value::Table(quirks)) = table.get("quirks") {
+            let mut changed = config.quirks.unwrap_or_default();
+            for (name, on) in quirks {
+                match on.as_bool() {
+                    Some(on) if changed.set(name, on) => {}
+                    Some(_) => return Err(format!("quirks: {} isn't a quirk", name)),
+                    None => return Err(format!("quirks: {} should be true or false", name)),
+                }
+            }
+            config.quirks = Some(changed);
+        }
+        Ok(config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quirks_change_the_variant() {
+        let config: RomConfig = "[quirks]\nclip_sprites = false\n\n[palette]\noff = \"#102030\"\n".parse().unwrap();
+        assert_eq!(config.quirks, Some(Quirks::default()));
+        assert_eq!(config.palette, Some(Palette { off: [0x10, 0x20, 0x30], ..Palette::default() }));
+
+        let config: RomConfig = "variant = \"schip\"\n[quirks]\nclip_sprites = false".parse().unwrap();
+        let quirks = Quirks { clip_sprites: false, ..Variant::SuperChip.quirks() };
+        assert_eq!(config.quirks, Some(quirks));
+    }
+
+    #[test]
+    fn parse_rejects_unknown_settings() {
+        assert!("speed = 10".parse::<RomConfig>().is_err());
+        assert!("clock = 0".parse::<RomConfig>().is_err());
+        assert!("variant = \"gameboy\"".parse::<RomConfig>().is_err());
+        assert!("[quirks]\nwrap = true".parse::<RomConfig>().is_err());
+        assert!("[palette]\non = \"green\"".parse::<RomConfig>().is_err());
+        assert!("[cheats]\nLives = \"3C5\"".parse::<RomConfig>().is_err());
+        assert!("[audio]\nvolume = 101".parse::<RomConfig>().is_err());
+        assert!("[audio]\nloud = true".parse::<RomConfig>().is_err());
+    }
+
+    #[test]
+    fn audio_changes_only_what_it_sets() {
+        let config: RomConfig = "[audio]\nvolume = 60\nmute = true".parse().unwrap();
+        let settings = config.audio_over(AudioSettings { frequency: 300, ..AudioSettings::default() });
+        assert_eq!(settings, AudioSettings { frequency: 300, volume: 60, muted: true });
+    }
+
+    #[test]
+    fn sidecars_sit_beside_the_rom() {
+        assert_eq!(RomConfig::path_for("roms/game.ch8"), PathBuf::from("roms/game.ch8.toml"));
+        assert!(matches!(RomConfig::for_rom("no/such/game.ch8"), Ok(None)));
+    }
+}
@@ -0,0 +1,657 @@
+//! Known ROMs and the settings they need to run properly
+//!
+//! Entries are keyed by the SHA-1 of the ROM file and come from the
+//! [CHIP-8 community database](https://github.com/chip-8/chip-8-database)
+//! (MIT licensed), so new ones can be copied across from there.
+
+use sha1::{Digest, Sha1};
+
+use crate::{Byte, Quirks};
+
+/// What's known about a particular ROM
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RomEntry {
+    /// Lowercase hex SHA-1 of the ROM file
+    pub sha1: &'static str,
+    pub title: &'static str,
+    pub quirks: Quirks,
+    /// How many instructions a frame (1/60th of a second) the ROM was
+    /// written for, if it needs something specific
+    pub tickrate: Option<usize>,
+    /// The [`KeyMap::preset`](crate::KeyMap::preset) that suits the ROM's
+    /// controls, if the usual layout is awkward
+    pub keymap: Option<&'static str>,
+    /// Host keys for the ROM's controls, as (key name, CHIP-8 key), bound
+    /// over the keymap in use: the arrow keys, then Space and Enter for its
+    /// two buttons
+    pub keys: &'static [(&'static str, Byte)],
+}
+
+// Each platform's quirks as the database's platforms.json has them. It
+// doesn't say when FX0A finishes or how DXYN counts collisions, so those
+// follow the interpreters themselves.
+const COSMAC_VIP: Quirks = Quirks {
+    vf_reset: true,
+    shift_uses_vy: true,
+    memory_increments_i: true,
+    memory_increments_i_by_x: false,
+    jump_uses_vx: false,
+    clip_sprites: true,
+    collision_rows: false,
+    display_wait: true,
+    wait_key_on_press: false,
+};
+const MODERN_CHIP_8: Quirks = Quirks { vf_reset: false, display_wait: false, ..COSMAC_VIP };
+const CHIP_48: Quirks = Quirks {
+    vf_reset: false,
+    shift_uses_vy: false,
+    memory_increments_i: true,
+    memory_increments_i_by_x: true,
+    jump_uses_vx: true,
+    clip_sprites: true,
+    collision_rows: false,
+    display_wait: false,
+    wait_key_on_press: false,
+};
+const SUPER_CHIP_1_0: Quirks = Quirks { wait_key_on_press: true, ..CHIP_48 };
+const SUPER_CHIP: Quirks = Quirks {
+    memory_increments_i: false,
+    memory_increments_i_by_x: false,
+    collision_rows: true,
+    ..SUPER_CHIP_1_0
+};
+const XO_CHIP: Quirks = Quirks {
+    vf_reset: false,
+    shift_uses_vy: true,
+    memory_increments_i: true,
+    memory_increments_i_by_x: false,
+    jump_uses_vx: false,
+    clip_sprites: false,
+    collision_rows: false,
+    display_wait: false,
+    wait_key_on_press: true,
+};
+
+// generated from the database's programs.json, one entry per ROM, using
+// the first platform it lists
+const ROMS: &[RomEntry] = &[
+    RomEntry { sha1: "5abf3dcf4ce0e396a3a5bf977b1ea988535d35d5", title: "00Schip8 Life demo", quirks: SUPER_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "77d5d2d9c5fe19c72d6564b3601a8d17cfedcb41", title: "10 Bytes Pattern", quirks: SUPER_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "ea9af3c09b0d9e265fcd92bcc5d51a2939fdf27a", title: "15 Puzzle", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "cf3a8c546038c63cd4cc1de8d171b9bf0d57c0ee", title: "15PUZZLE", quirks: COSMAC_VIP, tickrate: None, keymap: Some("arrows"), keys: &[] },
+    RomEntry { sha1: "018442698067c95d67e27a94e6642c11f049f108", title: "1D Cellular Automata", quirks: MODERN_CHIP_8, tickrate: Some(1000), keymap: None, keys: &[] },
+    RomEntry { sha1: "c32175db0c0508065709fc9cb42b233b24dad7fe", title: "2048", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "ea6fc1ff6e57800e2322641f6f02ebd462dda2b8", title: "2048", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "fed518f92023db76cd9fb4616c44c7be1cede2d2", title: "2048game", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "dea204fbfda4ed63fe2a2be255617bb9ee770a61", title: "3D VIP'r Maze", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[("Up", 0x5), ("Down", 0x8), ("Left", 0x7), ("Right", 0x9)] },
+    RomEntry { sha1: "6dc9b7bee24f9793b929d20a7757725f3183d12e", title: "3D VIP'r Maze", quirks: SUPER_CHIP, tickrate: None, keymap: None, keys: &[("Up", 0x5), ("Down", 0x8), ("Left", 0x7), ("Right", 0x9)] },
+    RomEntry { sha1: "62e204572ac05be3748a746ac7831d6844f43003", title: "3D Viper Maze", quirks: XO_CHIP, tickrate: None, keymap: None, keys: &[("Up", 0x5), ("Down", 0x8), ("Left", 0x7), ("Right", 0x9), ("Space", 0x1)] },
+    RomEntry { sha1: "3ddf7b76b8f63d0089e00e3b518f78c213b74b1e", title: "8CE Attourny - Disc 1", quirks: COSMAC_VIP, tickrate: Some(7), keymap: None, keys: &[] },
+    RomEntry { sha1: "82fd0d202a068bedfb869fc303fdeae0c814024f", title: "8CE Attourny - Disc 2", quirks: COSMAC_VIP, tickrate: Some(7), keymap: None, keys: &[] },
+    RomEntry { sha1: "419a0110d41332457c15ae09fff62cbd7ad197fc", title: "8CE Attourny - Disc 3", quirks: COSMAC_VIP, tickrate: Some(7), keymap: None, keys: &[] },
+    RomEntry { sha1: "ee7fb407da5f17ea7be9d16ce8a7ff38028ca924", title: "8min", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "d97a7e1d952ed70d00715d92291ef08fc9a4c909", title: "Acey Deucy", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "feaa2b999737630a6402e990df4d0558f79ba43e", title: "Addition Problems", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "f0b6e192b9589cc9ee9bc89bacdab00be6ac360d", title: "Advanced Warfare", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "fca71182a8838b686573e69b22aff945d79fe1d0", title: "Airplane", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "10fe2d629a3cebdbfe23fb9310ca74a3574e5a67", title: "Akahad_v1", quirks: XO_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "565b40c19d653521a0257c28a92671d0e594f22a", title: "Akir8", quirks: XO_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "f4e50d6e209324906b7899ed785a0d849a397abc", title: "Alien Hunter", quirks: SUPER_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "bc5faf54f04da3f4dbde50d3b31ccfc2bf8b9e06", title: "Alien", quirks: SUPER_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "24ef21009527ee674de44ccb37e37081654883f9", title: "Alien-Inv8sion", quirks: XO_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "7cd0334fc30cbbb21d3c5a909fa2c69927ec4a6c", title: "Alternate", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "afd9fee7565c54970b6bd7758aa8aa7843dd2e86", title: "An Evening to Die For", quirks: XO_CHIP, tickrate: Some(500), keymap: None, keys: &[] },
+    RomEntry { sha1: "0893dd3b5fafa013f07acc9aa98876f84f328d54", title: "An Evening to Die For", quirks: XO_CHIP, tickrate: Some(500), keymap: None, keys: &[] },
+    RomEntry { sha1: "e85ade7412e8affc4a8590fc0c928f1f00c5eb6b", title: "Angle of Death", quirks: XO_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "a27dcf88a931f70c3ccf3c01a5410b263bac48bc", title: "Animal Race", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "e9ce37041ac752ef910bb5c47ee9a031403223be", title: "Animal Race", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "a56c09537df0f32e2d49fb68cb2ba8216b38f632", title: "Ant - In Search of Coke", quirks: SUPER_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "67efbd5a84fe1337c3c9cb3040981ec4ce52577b", title: "Applejack", quirks: SUPER_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "bcbf36a68cf389e87dd54a9707cf35c4436dcb92", title: "Applejak", quirks: SUPER_CHIP, tickrate: Some(100), keymap: None, keys: &[] },
+    RomEntry { sha1: "d867b0d0fe1e96ba60910c64d9362da5a986774e", title: "Area F", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "8166328ddd1deb0df718323c0c63c76b267cec4a", title: "Arrows", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "6a846ca9fed73a7ef0e6695a665d2f15dd6a8141", title: "Artifac", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "e99657c8a3bfbfb5a9cb70e7d330346802ce20ce", title: "Asphyxiation Advanced", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "c05d1316bbb8acb1ba425c3ebdd0123632a73fd8", title: "Asphyxiation", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "048659b97e0cf9506eba85ef7baaf21ada22c6f2", title: "Astro Dodge", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "0b1040d556cba3b8e086f44ea76f78e26fd28093", title: "Astro Dodge", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "ac621d9fcada302ba6965768229ef130630bc525", title: "Astro Dodge", quirks: Quirks { vf_reset: true, shift_uses_vy: true, memory_increments_i: false, memory_increments_i_by_x: false, jump_uses_vx: false, clip_sprites: true, collision_rows: false, display_wait: true, wait_key_on_press: false }, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "c2a361700209116a300457eacbf33a8c40c01b83", title: "Astro Dodge", quirks: SUPER_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "0f479a10fec51d159866e5760069cd18bdfd293f", title: "Bad Kaiju Ju", quirks: MODERN_CHIP_8, tickrate: Some(7), keymap: None, keys: &[] },
+    RomEntry { sha1: "ec00e355117ed6385b51c0819f85954c3b765ed0", title: "Ball Breaker", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "681eaf2c6422cdd0e0ca0cf9f4c3a436b7b6f292", title: "Binding of COSMAC", quirks: SUPER_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "3368d56efeb584c509bafb548f1ee5e71ac1bc70", title: "Biorhythm", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "f23ee6f22c3ada8c638096ec1209a65dd036cc52", title: "Black Lives Matter demo", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "e6af47843f0ecc3302027a3756dd7b389a15e437", title: "Black Rainbow", quirks: SUPER_CHIP, tickrate: Some(20), keymap: None, keys: &[] },
+    RomEntry { sha1: "5370ecf9ae444c71b63dab9b1f9968a4fe67c9dd", title: "Blinky", quirks: MODERN_CHIP_8, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "5b733a60e7208f6aa0d15c99390ce4f670b2b886", title: "Blinky", quirks: SUPER_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "d40abc54374e4343639f993e897e00904ddf85d9", title: "Blinky", quirks: SUPER_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "f4169141735d8d60e51409ca7e73f4adedcefef2", title: "Blinky", quirks: SUPER_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "6f6509f38220e057a7e32ebb22dd353c1078e3e7", title: "Blitz", quirks: COSMAC_VIP, tickrate: None, keymap: Some("arrows"), keys: &[] },
+    RomEntry { sha1: "a3b80d4a9efa8e7700d348d3e3ddf81d3c7e92a9", title: "Blitz", quirks: COSMAC_VIP, tickrate: None, keymap: Some("arrows"), keys: &[] },
+    RomEntry { sha1: "72c2cbfea48000e25891dd4968ae9f1adef1e7e3", title: "BMP Viewer", quirks: SUPER_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "f199e23cbe29bb36f43373818b10bf72b35e9d05", title: "Bongocat", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "11d66c2ff456ca3aea5f384a5a11503a6c8f85ed", title: "Boot Super Chip8x", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "ff5276bfd203634ef3034475ff7bc8bd9033a03d", title: "Bounce", quirks: SUPER_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "b3fed4ed1eb0ed693c9731dbe53b29a76236c781", title: "Bowling", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "31fc1c53cc610a9f4b9c5705c5a0f33fc028d123", title: "Br8kout", quirks: MODERN_CHIP_8, tickrate: Some(7), keymap: None, keys: &[] },
+    RomEntry { sha1: "c9a13c00d8391f352488661fc3b15217f0e8d9fd", title: "Br8kout", quirks: MODERN_CHIP_8, tickrate: Some(7), keymap: None, keys: &[] },
+    RomEntry { sha1: "7d38669b1542d2352b900eed11b78dd1c8d144ec", title: "Breakfree", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[("Up", 0x1), ("Down", 0x4)] },
+    RomEntry { sha1: "193915dcde1365ae054c4eaa21a35baa27cd3356", title: "Breakout", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "237756a4014fb3aa82a29246a7cdd534f8dc2dbb", title: "Breakout", quirks: COSMAC_VIP, tickrate: None, keymap: Some("arrows"), keys: &[] },
+    RomEntry { sha1: "b92ffba5ccd708c0422d77b9af63ca4b2f67b443", title: "Brick Breaker", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "91442577a6bbf8c3267f2df95fdfc50baebe176d", title: "Brick", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "f13766c14aeb02ad8d4d103cb5eadd282d20cddc", title: "Brix", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "b277c053b5b4ff9e40cd52cd4125a35ec22ccd0a", title: "Bulb", quirks: SUPER_CHIP, tickrate: Some(100), keymap: None, keys: &[] },
+    RomEntry { sha1: "7851dd47c67217426f31b27778b19d39407a9bf2", title: "Bull8 H3ll", quirks: SUPER_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "f84ad99d0095ef1281b55c779783b99cb53d2ade", title: "Bullet Patterns", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "33ec2f3081bed56438dc207477f06cd77f3f07d9", title: "Business Is Contagious", quirks: XO_CHIP, tickrate: Some(1000), keymap: None, keys: &[] },
+    RomEntry { sha1: "e2d86d6c70877e99ed4253c9a83d4da42e5a14ee", title: "Bustin", quirks: XO_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "17d775833f073be77f2834751523996e0a398edd", title: "By the Moon", quirks: SUPER_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "244c746b4f81c9c3df9cea69389387da67589bb8", title: "C-Tetris", quirks: SUPER_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "eb548f0a0ceca4da0475112ab14e223a63350c89", title: "C-Zero", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "e6d4a8598999b3d95047babf67b529d83eaa9554", title: "Car Race Demo", quirks: SUPER_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "2cd26a9a84ed2be6aaa6916d49b2e5c503196400", title: "Car Race", quirks: SUPER_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "2e0f2268c9a3be0fbb839f918336f161815bb80a", title: "Carbon8", quirks: COSMAC_VIP, tickrate: Some(15), keymap: None, keys: &[] },
+    RomEntry { sha1: "400dbd1aa2b79b9b8546bc615bfb735c1bd1d268", title: "Cave Explorer", quirks: MODERN_CHIP_8, tickrate: Some(20), keymap: None, keys: &[] },
+    RomEntry { sha1: "5c82520906073287a3ef781746c67207ca084d93", title: "Cave", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "17238bcd1cb8e21142a1d7533f878c833ef19caa", title: "Cavern", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "40c33f5ae6f11def69a445220b3c96a6009f92ed", title: "Chesmac", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "2cc98ab06cd250960118585971e842b56af3085e", title: "Chicken Scratch", quirks: XO_CHIP, tickrate: None, keymap: None, keys: &[("Up", 0x5), ("Down", 0x8), ("Left", 0x7), ("Right", 0x9), ("Space", 0x6), ("Enter", 0x4)] },
+    RomEntry { sha1: "c606d52970b86edcca4e87e9f6fae4b1ccbbbb0f", title: "Chicken Scratch", quirks: XO_CHIP, tickrate: Some(500), keymap: None, keys: &[("Up", 0x5), ("Down", 0x8), ("Left", 0x7), ("Right", 0x9), ("Space", 0x6), ("Enter", 0x4)] },
+    RomEntry { sha1: "acfd0d29a83882de19dc37a56ee6c7d63ac309c4", title: "Chip War", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "66d44799bc15637f742cf30d84007f412a9c9fb5", title: "Chip-8 Calculator", quirks: XO_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "417ba551bc92751d3e0dc25e01e76373d0e504ea", title: "Chip-8 Dino", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "27868be46213718792ab3b8415855a1975366dbe", title: "Chip-8 Snake", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "b05dfd6bc0dca5106fb51ebc185406d633c96b44", title: "CHIP-8 Snake", quirks: XO_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "949b661091efe706a32fb0d89991005783243bb9", title: "CHIP-8 test suite: Corax+ opcode test", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "0572f188fc25ccda14b0c306c4156fe4b1d21ae1", title: "CHIP-8 test suite: Flags test", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "d3554b9789728294d881823126ba6eb8103bd42c", title: "CHIP-8 test suite: IBM logo", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "8c7f101c61f82cacaacc45f8c11c1a00c8cc451e", title: "CHIP-8 test suite: Keypad test", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[("Up", 0xE), ("Down", 0xF), ("Space", 0xA)] },
+    RomEntry { sha1: "4309cba3fb0b96761fcba01acaf233e0ca585b4d", title: "CHIP-8 test suite: Quirks test", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[("Up", 0xE), ("Down", 0xF), ("Space", 0xA)] },
+    RomEntry { sha1: "0df2789f661358d8f7370e6cf93490c5bcd44b01", title: "CHIP-8 test suite: splash screen", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "f55ab7c3776fd9a94ffac82f0feb965e93c057f1", title: "Chip-84 3D Title", quirks: SUPER_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "ebada8eb97ce40a91554386696f7daa33023cc8c", title: "Chip-84 Scratch", quirks: SUPER_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "b4be55185804a19d7d46c4b340531ecf1fc2abc5", title: "Chip-Chess", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "c617cd419bb3b51c2224b247782d73c46bc075c8", title: "Chip-Otto logo 1", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "5a183cc0530410c0887175ccaf6d5d4deb5d8fff", title: "Chip-Otto logo 2", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "4ac6414b1fd502074a6aab4de4b206a7273dcfb8", title: "Chip2048", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "d92c71b955b7634370571bd707715cf8bb0e2fb4", title: "Chip8 emulator Logo", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "a82ca5c53e1dcedfab4f65efef02229145771b7d", title: "Chip8 Picture", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "0317e94014ebc3a9a1a2a33c46bc766a9cf44cb0", title: "Chip8-Multiply", quirks: XO_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "20c2b4baf40c2c30c7db91107d4b5af980626f1c", title: "Chip8stein 3D", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "83300ff710acdd8417376b88adf40f68171f7ec7", title: "Chipcross", quirks: XO_CHIP, tickrate: Some(20), keymap: None, keys: &[] },
+    RomEntry { sha1: "dbb5b085117d513f1ce403959d7136b767bb3dd3", title: "ChipMark'77", quirks: SUPER_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "ce7a5355d90c4aabe0d96c5add93f4efb21f099b", title: "Chipolarium", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "f4392681b1fa38d7ad0a7d7a59cecf247ac1457a", title: "Chipquarium", quirks: COSMAC_VIP, tickrate: Some(15), keymap: None, keys: &[] },
+    RomEntry { sha1: "ab5cbf267d74c168e174041b9594ae856cbd671d", title: "ChipWar", quirks: MODERN_CHIP_8, tickrate: Some(15), keymap: None, keys: &[] },
+    RomEntry { sha1: "76a770000b314659ac792e17724b783a464ab67e", title: "Civiliz8n", quirks: XO_CHIP, tickrate: Some(100), keymap: None, keys: &[] },
+    RomEntry { sha1: "69956a514173f08926e7f4388c8c8fc6b5b465a1", title: "Ckosmic", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "4a68389601eafe3adf014576681eb30232acdac9", title: "Classic Snek", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "9d9f88509b5033152b7b49d2c7ea3c3c5fce2bd6", title: "Climax Slideshow - Part 1", quirks: SUPER_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "6bb78d8a0aba93ea18eabdd0134cbdccd1dc2d16", title: "Climax Slideshow - Part 2", quirks: SUPER_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "016345d75eef34448840845a9590d41e6bfdf46a", title: "Clock Program", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "89929eb46c0682caad909d30561a934f28941963", title: "Clostro", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "f56134c8196fdff347264a985add4d2648bac76a", title: "Codegrid", quirks: SUPER_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "614a2b3d0bb5d62a16d963ac2d3a79eb3dd22742", title: "Coin Flipping", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "46b281516a3e9d1526bea224b79cc18ddd71833d", title: "Computer Simulator", quirks: XO_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "5303be6c79bff9426b2f4b1fa9af1f4a5bbcd525", title: "Computer", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "c8d2ebbc16551a4bee1f0e2b33f0510e4170afcf", title: "Connect 4", quirks: SUPER_CHIP, tickrate: None, keymap: Some("arrows"), keys: &[] },
+    RomEntry { sha1: "2d10c07b532f4fa7c07a07324ba26ca39fe484fd", title: "Connect 4", quirks: SUPER_CHIP, tickrate: None, keymap: Some("arrows"), keys: &[] },
+    RomEntry { sha1: "852f506c6a56bd9f59592c4a1cb5a0aaaf31381c", title: "Cool 3D Spinning Octo", quirks: XO_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "29bc3a658b1607b6458571d5fe99f495306a6a4f", title: "Corners Game", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "788661c6a49c4e081492416bf2ce86342116bb1d", title: "Crack Me", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "35158696bd94ea22ef34e899fff1f15f7154d4fd", title: "Craps", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "8198311054b6cd440dde42d6efed0eda1b1e461d", title: "D8GN", quirks: XO_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "b8be39922f38d0160e257de75899119dc5137e6e", title: "Danm8ku", quirks: MODERN_CHIP_8, tickrate: None, keymap: None, keys: &[("Up", 0x5), ("Down", 0x8), ("Left", 0x7), ("Right", 0x9)] },
+    RomEntry { sha1: "ff6b8ac59bf281cd4b5ab6e161600b00f85a0265", title: "Danm8ku", quirks: MODERN_CHIP_8, tickrate: Some(1000), keymap: None, keys: &[("Up", 0x5), ("Down", 0x8), ("Left", 0x7), ("Right", 0x9)] },
+    RomEntry { sha1: "d54aaedefbf74f56b7446a5108885ddc33fb6fa1", title: "Death Star vs Yoda", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "09d8e40f143f808ff379f04a473f58cbba5f3838", title: "Ded-Lok", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[("Left", 0x7), ("Right", 0x9), ("Space", 0x5), ("Enter", 0x6)] },
+    RomEntry { sha1: "044021b046cf207c0b555ea884d61a726f7a3c22", title: "Ded-Lok", quirks: SUPER_CHIP, tickrate: None, keymap: None, keys: &[("Left", 0x7), ("Right", 0x9), ("Space", 0x5), ("Enter", 0x6)] },
+    RomEntry { sha1: "b41cc0b5b2faabafd532d705b804abb3e8f97baf", title: "Deep8", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "96c0ae3b45839a570d180760835ceab9ed503fd0", title: "Deflap", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "8e5f19d8ae9f3346779613359610967a5ed95fa8", title: "Deflection", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "082c71b67e36e033c2e615ad89ba4ed5d55a56d0", title: "Delay Timer Test", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "9ffb063f600f670b682bf6a010292d5aa0a67efd", title: "Demo-Poo", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "0dc782f0607d34b8355c150e81bc280de7472d94", title: "Dig Site 8", quirks: XO_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "9ddbccdef6b5d4b9740103ce79d19607e0b785a1", title: "Display Numbers", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "064492173cf4ccac3cce8fe307fc164b397013b9", title: "Division Test", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "7fb69647e6b10e2b12f9357d5c1c177349028236", title: "Dodge", quirks: SUPER_CHIP, tickrate: Some(1000), keymap: None, keys: &[] },
+    RomEntry { sha1: "238e6fb829b03522d60568cac3d8f00de4a53bcf", title: "Dogfight", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "0ce13060abe94e2b73404fc78186b786121ddeeb", title: "Dot-Dash", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "75fac059356e7f47c7ac27afb8523162a9ffa2b5", title: "Down8", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "1368d7eae124661aacaf3411819ca9c113c0c10c", title: "down8", quirks: MODERN_CHIP_8, tickrate: Some(15), keymap: None, keys: &[] },
+    RomEntry { sha1: "a804e02641ef61a720d4f0056eca4af0ea453fa3", title: "Drag Chip-8 Games Here intro", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "8713062b1983c26090b742a9ffc30777c007ff93", title: "Drag RAM", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "9468a94294997009a2c50c1a18376947d3d3d3bb", title: "Driving Simulator", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "02972781f36cd9ccf36162789ec9687fa3f1a733", title: "Drop Your Program Here", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "adcfece2c527a68d8d74e6cfe7e84a8a04ad8182", title: "Duel of the F8s", quirks: XO_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "4cce9f3a79c8d7ee33a9bfde7099568e0f3274cd", title: "Dump Trump", quirks: XO_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "627f01b20ce4d33f6df1aa88acb405a3a732bde0", title: "DVN8", quirks: SUPER_CHIP, tickrate: Some(20), keymap: None, keys: &[] },
+    RomEntry { sha1: "fcaa793332a83c93f4ed79f5ffbc8403c8b8aea0", title: "Eaty The Alien", quirks: XO_CHIP, tickrate: Some(200), keymap: None, keys: &[] },
+    RomEntry { sha1: "a28c25586a38b0e6147092a2bc50899b463528df", title: "Elite International Golf", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "1539d55e2dda1dd2affa584d8e8e19a7d1f4a41e", title: "Elm8tal", quirks: XO_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "7dbd54b5adc7e409b64a716ceafba864301128b8", title: "Enchantment Enhanced", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "a6f237c853c19160ed7375a93c7f554e5a41aae3", title: "Enchantment Extra Enhanced", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "d2b0a8cdab1d0bdb4186953abcd75c3a8d660033", title: "Enchantment", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "945fa6dd1ac72f1ede1cb829ef31b5328a32f67a", title: "Etch-a-sketch", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "77dc518e6779ccd862205cfeb0f3f7772caed60e", title: "Expedition.", quirks: XO_CHIP, tickrate: Some(1000), keymap: None, keys: &[] },
+    RomEntry { sha1: "7e53264cda0014e108182e449fdd3034b6bd53c3", title: "F8Z", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "f60bdb428e747b0a379063d7cc96d099ab2db18d", title: "Falling stars", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "f7510be8f3299f8e350626ef5cb88041a5c95f3c", title: "Falling", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "d5ddd7d5071951c682cd4214474acbdd852234c4", title: "Fest", quirks: XO_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "31fe380556d65600ef293d99aabd3b6bb119aa01", title: "Field!", quirks: SUPER_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "3b2bf5dc7ffb5f3fbe168e802079f79730535ca8", title: "Figures", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "ae71a7b081a947f1760cdc147759803aea45e751", title: "Filter", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "5c0fff21df64f3fe8683a115353c293d435ca01a", title: "First depth search of Maze", quirks: SUPER_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "49c7234a1733db355560a13c57b26f055533c233", title: "Fishie", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "b1bf08cccffc56320f3b98c96a7911a58c1475b0", title: "Fizzbuzz", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "eb0076f3dd33b16fd040640b4b67bab19e491bef", title: "Fl8ppy Mouse", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "4edb2848edbec6c79a2ae208490e12013e94ee98", title: "Flappy Pong", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "b0eec238f877ad6b17f2be33454353ab95584c79", title: "Flaps", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "821751787374cc362f4c58759961f0aa7a2fd410", title: "Flight Runner", quirks: MODERN_CHIP_8, tickrate: Some(15), keymap: None, keys: &[] },
+    RomEntry { sha1: "93cc9ed25534f9b143206f846c2a9145df691d6c", title: "Flip-8 - Think-A-Dot edition", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "70ccd390c90f586bcd75bbbc1c89e53e67179ff1", title: "FlipFlipBoom", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "b1917346eaae178c6f4e154e83cc89dc5b83c72f", title: "Floppy Bird", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "808aeb072604809e0ef13c245115a81f40422d1d", title: "Flutter By", quirks: SUPER_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "a4a9351775b2a64bbd14e3980968db19c254a988", title: "Flutter By", quirks: XO_CHIP, tickrate: Some(7), keymap: None, keys: &[] },
+    RomEntry { sha1: "a2807d2b9591a2cb061e3c3a64c2766b4bab4327", title: "Fractal Set", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "ac7c8db7865beb22c9ec9001c9c0319e02f5d5c2", title: "Framed MK1", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "eb72a25bd58e122e65a540807e7a1816abaa4f41", title: "Framed MK2", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "3dade9be601637ca2d96aeafaa086b93a0b83352", title: "Frog", quirks: XO_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "0cd895dc3d489d0e40656218900a04310e95f560", title: "Fuse", quirks: MODERN_CHIP_8, tickrate: Some(15), keymap: None, keys: &[] },
+    RomEntry { sha1: "ea7c12f458932527802fdd4a18e4c6700dd91138", title: "Game 16", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "19c64fc12bfdefb8c3c608a37b433ceff4286e52", title: "Gem Catcher", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "c33af07674dbbec5365bd91954c8bfed4a7467bd", title: "Ghost Escape!", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "1e3be162480380b6276d0848e1c71576b4c041f2", title: "Ghost Escape", quirks: MODERN_CHIP_8, tickrate: Some(7), keymap: None, keys: &[] },
+    RomEntry { sha1: "f5c666c33ed66a9662cef78b1ef62f80a33b0358", title: "Ghostbusters!", quirks: XO_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "ad612a1409c96cc24fc5fc1368fab71463480e9b", title: "Glitch Ghost", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[("Up", 0x5), ("Down", 0x8), ("Left", 0x7), ("Right", 0x9), ("Space", 0x6)] },
+    RomEntry { sha1: "2cdcb3c29a5f013a991db5909ca8e18e27b3c42b", title: "Glitch Ghost", quirks: MODERN_CHIP_8, tickrate: Some(200), keymap: None, keys: &[("Up", 0x5), ("Down", 0x8), ("Left", 0x7), ("Right", 0x9), ("Space", 0x6)] },
+    RomEntry { sha1: "72f071d5197497519d301ec32baef749f3191a4d", title: "Golf", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "9797a7eaf1e80ec19c085c60bb37991420f54678", title: "Grad School Simulator 2014", quirks: XO_CHIP, tickrate: Some(20), keymap: None, keys: &[] },
+    RomEntry { sha1: "3b644b6d5a5591999094b22478a8efa3739da85d", title: "Grave Digger", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "835880c85c4c1c318b04dc940e89cb6e7466e652", title: "Greet", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "137cb8397456f53fcab216124458238bc18c0965", title: "Guess the Number", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[("Space", 0x5), ("Enter", 0x6)] },
+    RomEntry { sha1: "01ffe488efbe14ca63de1c23053806533e329f3f", title: "H", quirks: SUPER_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "3ee8a64a9af37a8d24aab9e73410b94cc0a4018f", title: "H8", quirks: SUPER_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "5551471e152afcbf61707393ce79cde360bbc23c", title: "Heart Monitor Demo", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "89247fc70ab073b36cb1b6a6ea3770ac4a877b9b", title: "Hedgehog the Drug Dog", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "f64e87b8a4161806b4dad9bfc317d4341b410beb", title: "Heh, I chipped your mom's eight last night", quirks: XO_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "a8ed3c25c00130838b3ee36cc82fbf32ce6cea83", title: "Hello World", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "2dabe15f846041b24faa21a6dc3632fdebe82b89", title: "Hello World", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "3cb8831051c0b6235b64f057a6a848a57d8900df", title: "Hello World", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "d9389d564baced03192503a58ad930110bb0fe03", title: "Hex Mixt", quirks: SUPER_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "dbb52193db4063149c3d8768ab47dd740d90955c", title: "Hi-Lo", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "050f07a54371da79f924dd0227b89d07b4f2aed0", title: "Hidden", quirks: COSMAC_VIP, tickrate: None, keymap: Some("arrows"), keys: &[] },
+    RomEntry { sha1: "9b72b6656cb714cd64de00ac78dc7bf8374adec6", title: "Hidden", quirks: COSMAC_VIP, tickrate: None, keymap: Some("arrows"), keys: &[] },
+    RomEntry { sha1: "2229606a59bbcdeb81408f75e8646ea05553a580", title: "Horde", quirks: XO_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "b8be672909554abc17ed1ea0c694726f9a87b43d", title: "Hors", quirks: SUPER_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "84d612c7eccf24835eb585711a49964572444737", title: "Horse World Online", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "28ac3467fbb4544a3e3a1ec3cd27d9e819ac7323", title: "Horse World Online", quirks: MODERN_CHIP_8, tickrate: Some(30), keymap: None, keys: &[] },
+    RomEntry { sha1: "45f7c33b284b0f3e1393f0dd97e4b3b9fd9c63c9", title: "Horsey Jump", quirks: Quirks { vf_reset: false, shift_uses_vy: true, memory_increments_i: true, memory_increments_i_by_x: false, jump_uses_vx: true, clip_sprites: true, collision_rows: true, display_wait: false, wait_key_on_press: true }, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "df5ced9c20d00bf7be7d3361d76f27d0d577abfb", title: "Horsey Jump", quirks: Quirks { vf_reset: false, shift_uses_vy: true, memory_increments_i: true, memory_increments_i_by_x: false, jump_uses_vx: true, clip_sprites: true, collision_rows: true, display_wait: false, wait_key_on_press: true }, tickrate: Some(20), keymap: None, keys: &[] },
+    RomEntry { sha1: "e251b6132b15d411a9fe5d1e91a6579e3e057527", title: "I'll Be back", quirks: XO_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "1ba58656810b67fd131eb9af3e3987863bf26c90", title: "IBM Logo", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "2cda3b309234e693e5ab6179767a8f019dfd5c6e", title: "Infini Br8kr", quirks: XO_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "b693e60f161e69c98b0bb2bc1761cf434f8fbb0e", title: "Into The GarlicScape", quirks: XO_CHIP, tickrate: Some(1000), keymap: None, keys: &[] },
+    RomEntry { sha1: "b1ec426de267f4335a672243f7d93de5fd03b356", title: "InvisibleMan", quirks: XO_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "1261b79da4d25792c05eaed47a0285b48dd7b7f4", title: "Jackpot", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "2079134ecaaaa356724d1f856b2a00153b176cc7", title: "Jeff Quest", quirks: XO_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "5d99d0c763cf528660a10a390abe89f2d12b024a", title: "Jeff's Quest", quirks: XO_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "6d677bb44500a5ee4754b3a75516cfd9e73947fc", title: "Joust", quirks: SUPER_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "a902480e6e18c5287388b6797da36d640db9992b", title: "Joust", quirks: XO_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "ab36ced6e34affacd57b2874ede3f95b669a424c", title: "jub8 Song 1", quirks: XO_CHIP, tickrate: Some(1000), keymap: None, keys: &[] },
+    RomEntry { sha1: "12e053d66be67836deff1c07af93fe1d33a8eec5", title: "jub8 Song 2", quirks: XO_CHIP, tickrate: Some(1000), keymap: None, keys: &[] },
+    RomEntry { sha1: "9bf96e23963995c6d702ae21c9b8741cbb688f47", title: "jub8 Song 3", quirks: XO_CHIP, tickrate: Some(1000), keymap: None, keys: &[] },
+    RomEntry { sha1: "7e8d5a79cabeb9a791524ea7126867a539d825ee", title: "jub8 Song 4", quirks: XO_CHIP, tickrate: Some(1000), keymap: None, keys: &[] },
+    RomEntry { sha1: "b274ab30ed7678400dc2283431a45f7d98d9fced", title: "jub8 Song 5", quirks: XO_CHIP, tickrate: Some(1000), keymap: None, keys: &[] },
+    RomEntry { sha1: "3fd62ae2bfe2572ceb194f1d3d1bd5a01695b86c", title: "jub8 Song 6", quirks: XO_CHIP, tickrate: Some(1000), keymap: None, keys: &[] },
+    RomEntry { sha1: "416763e940918ee7cfc5c277d7f2b66de71a46a1", title: "Jump Heart", quirks: SUPER_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "8cf29db367b7db4760dee8252dfc88066a45ce4a", title: "Jumpfill", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "680e265a128870091ed71410891b64d5ead303fb", title: "Jumping Sprite", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "5b29263763be401c31d805bc35a4cd211d552881", title: "Jumping X and O", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "fc724ae0125f5f1ac94a79fe3afc6318b1f57556", title: "Kaleidoscope", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[("Up", 0x2), ("Down", 0x8), ("Left", 0x4), ("Right", 0x6), ("Space", 0x5), ("Enter", 0x0)] },
+    RomEntry { sha1: "d6fa9dc9005dc0496f39ba52fef56f9fd0a5a158", title: "Kaleidoscope", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[("Up", 0x2), ("Down", 0x8), ("Left", 0x4), ("Right", 0x6), ("Space", 0x5), ("Enter", 0x0)] },
+    RomEntry { sha1: "b63bcd4e96a71717b84a6334cceffdf5f032e85e", title: "Kemono Friends logo", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "92a325c36ad2116a5256946b8bf711ed9befd319", title: "Kesha Was Biird", quirks: XO_CHIP, tickrate: Some(1000), keymap: None, keys: &[] },
+    RomEntry { sha1: "fbc7711ad068015b957e91d8714636b2ac90d9cb", title: "Kesha Was Bird", quirks: XO_CHIP, tickrate: Some(1000), keymap: None, keys: &[] },
+    RomEntry { sha1: "4564a1bf149e5ab9777d33813a92cfd6ffc7a0bb", title: "Kesha Was Niiinja", quirks: XO_CHIP, tickrate: Some(1000), keymap: None, keys: &[] },
+    RomEntry { sha1: "6363d99ed28b958faa1ff03f3e86724fa498ad47", title: "KEYPAD TEST", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "0ebc4b92c6059d6193565644fb00108161d03d23", title: "KEYPAD TEST", quirks: SUPER_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "b3dcfd85a76a678960359e1ce9f742a4f9c35ed8", title: "Knight", quirks: SUPER_CHIP, tickrate: None, keymap: None, keys: &[("Up", 0x5), ("Down", 0x8), ("Left", 0x7), ("Right", 0x9), ("Space", 0x6), ("Enter", 0x4)] },
+    RomEntry { sha1: "fcecf90496dadd214486a7a769e3a07f2b8f4eab", title: "Knight", quirks: SUPER_CHIP, tickrate: Some(200), keymap: None, keys: &[("Up", 0x5), ("Down", 0x8), ("Left", 0x7), ("Right", 0x9), ("Space", 0x6), ("Enter", 0x4)] },
+    RomEntry { sha1: "fb8d0807a00353ae8071238a2eb7f1e555afe525", title: "LabVIEW Splash Screen", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "4333eff4cbb49e57f8c0fb12f1e4cd0ac1dedd56", title: "LabVIEW Splash Screen", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "716fc9634c39f73afe795004589d353448a6c8e3", title: "Labyrinthine", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "65e3432c942df6ce18db2c2d01d3260e56dd1e53", title: "Lady Runner", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "cbbbc76a440b4020ecb9a6c95e95e636a8b23214", title: "Lainchain", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "004fa49c91fbd387484bda62f843e8c5bd2c53d2", title: "Lainchain", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "a168709fcf09b28cd9b9519698d3d8a383944f43", title: "Lan8ton's Ant", quirks: XO_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "72fb3e0a4572bdb81f484df7948a8bc736fe78d0", title: "Landing", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "59aca79b4b18e1bfbc71065bb34448fed5e1db1e", title: "Laser Defence", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "f31a8912ffb8a2920eb7ad5d645aa65a413b6ae9", title: "Laser", quirks: SUPER_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "5efc16ddebc1585b3c4d4cb27ce9fd76218c5d0a", title: "Legboy's Adventure 8 - Doki Doki Property Planning Panic", quirks: XO_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "e6a027d00c524ab7ae00b720f64a06ad1137836c", title: "Letter Scroll", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "858b55ce47e98a7b2238f8db33463f76fd15b18b", title: "Life is Gr8", quirks: XO_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "efa6bc8f1f35baaa16700d68a83dc4919797e2fe", title: "Life", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "234d1688bf4d1b34786cb9171b5f0800b3889874", title: "Lights Out", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "a9bf29597674c39b4e11d964b352b1e52c4ebb2f", title: "Line Demo", quirks: SUPER_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "0b5522b1ce775879092be840b0e840cb1dea74fd", title: "Link Demo", quirks: SUPER_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "ed9a9510aba2227ca9bb2d521adcaa903f433450", title: "Locked in a Room with a Ghost", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "31bb555e6a1b06502425500a7fc61bc9d1a49164", title: "Lombat Lombat Asoy!", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "3c444e43e5f02dac4324b7b24cd38ef4938a4b56", title: "Loopz", quirks: SUPER_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "a2788177b820a28cd27e6d2d180340cb7f4948fb", title: "Loopz", quirks: SUPER_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "238585615069ec905aa56f0048880fc6eb456d4e", title: "Loose Cannon", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "ed96881bf0d1e97157b04d8a4632f911067fe9e6", title: "Loose Cannon", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "e55f36b9ecd6fbbeb626a78f222011bddd5e5197", title: "Love8 intro", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "72e8f3a10a32bd7fb91322ecab87249f95e81e57", title: "Lunar Lander", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "7d6cc6068ea324f81873353d8a28fe2aa2cf8862", title: "M'Lady", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "ce33f148bfd5f1ca9edc68988b900a256905d057", title: "M8ze", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "ce15f2f4281b1069d33e00c801d5ed4390049a76", title: "Mabe Village from Link's Awakening", quirks: XO_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "f27d2375671fba01f87045f1f1fb67bcc3b284ee", title: "Magic Sprite", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "e4ef6fff9813c43bd7ad2ecaf02d1a3135d68418", title: "Magic Square", quirks: SUPER_CHIP, tickrate: None, keymap: Some("arrows"), keys: &[] },
+    RomEntry { sha1: "3643118e2e237deab98151b742f34caf9533dc05", title: "Mandelbrot Program", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "310e523071c697503f0da385997f9c77f9ad0ea9", title: "Mario Demo", quirks: XO_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "fb48e162c7f2e8853909acc5534b55fb55030f9f", title: "Masquer8", quirks: MODERN_CHIP_8, tickrate: Some(15), keymap: None, keys: &[] },
+    RomEntry { sha1: "0068ff5421f5d62a1ae1c814c68716ddb65cec5b", title: "Master B8", quirks: XO_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "669e32b6f42f52da658e428f501aabcdfa37fb2e", title: "Mastermind FourRow", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "d2fa3927b31f81fc06cd9466123309c59264fa41", title: "Mastermind", quirks: MODERN_CHIP_8, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "898ef1505c874065697ffc6cba688367e143d82e", title: "Mastermind", quirks: MODERN_CHIP_8, tickrate: Some(20), keymap: None, keys: &[] },
+    RomEntry { sha1: "24fd50a95b84e3a42e336a06567a9752f17b9979", title: "Matches", quirks: SUPER_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "b9272ae1acdaaa79ab649f6b48b72088ca2b1d74", title: "Maze", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "8b70080adbac44513ec60005734a816372b845ec", title: "Maze", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "95384fbb895b6420da690bc06cb16739c9a5d800", title: "Maze276", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "6b9f23e6433b7d7ccfcb18015c5fc1348006d386", title: "Memory Shift", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "d979858bb9ffd07b48f52f92a8bcac0199f3623e", title: "Merlin", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "6d4514ae3a43c307763648b0bdd485fb77bcf20d", title: "Mines! - The minehunter", quirks: SUPER_CHIP, tickrate: None, keymap: None, keys: &[("Up", 0x2), ("Down", 0x8), ("Left", 0x4), ("Right", 0x6), ("Space", 0x5), ("Enter", 0xC)] },
+    RomEntry { sha1: "80d8baefbc2c2c2eab78a7b09c621f7618357b84", title: "Minesweep8r", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "99a97c772fc93d669b73016761ea6fee0210497e", title: "Minesweeper", quirks: SUPER_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "2e2d9b370e08d6994fd2ded938a56b32b07ad768", title: "Mini Lights Out", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "a3f0eae99964b873eb1adbd3e8bcb90d15f762c3", title: "Mini Lights Out", quirks: MODERN_CHIP_8, tickrate: Some(7), keymap: None, keys: &[] },
+    RomEntry { sha1: "b5b66c3b0b2a109bfb166fdc4d2d2a352c32da53", title: "Miniature Golf", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "4a4123320d841ed04d8c1cd2ad6132a06b83dfa0", title: "Minimal game", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "6ba06eb27ad56e6f26b7d809e06394f719a89d01", title: "Minimal Nethack", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "0d0cc129dad3c45ba672f85fec71a668232212cc", title: "Missile Command", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[("Space", 0x8)] },
+    RomEntry { sha1: "1e981dac636d88d26a3fc056a53b28175f1d9b82", title: "Modem Dialing", quirks: XO_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "63e787fc3e78e5fb3a394cf1bc654ad9633d8907", title: "Mondri8", quirks: SUPER_CHIP, tickrate: Some(100), keymap: None, keys: &[] },
+    RomEntry { sha1: "58b4865fec81427fd3c52bdc62b2230d412c12ea", title: "Monty Hall", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "2925c79f35e4ced1923b5ef8ba3e795951e6de21", title: "Morse Code Demo", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "fa7c04f68d78e0faf6d136a3babe3943fc2e02f1", title: "Most Dangerous Game", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "dc5a12fa3ad88ea6c42dff1720be14f6772aef59", title: "Move Figure", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "6f8e85158be98f30bf3cd5df60d7a7ad71c5f3e1", title: "Mr", quirks: SUPER_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "30c8ee7181173d7f213e8148cdc9a5157caec9f7", title: "Mueve", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "2b48aa674707878bf6d22496a402985a9f7db9cb", title: "Multiply Routine demo by", quirks: XO_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "59bdc7f990322d274d711b6b6982c7e8c9098e9e", title: "Music Player", quirks: XO_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "3ea97f251de6e72798234a2930205256a8f5d8cf", title: "Mysterious", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "4031dae5c7545a1adc160a661be36f19fc1d47b2", title: "Nim", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "4cc4eff70802ac7a3b374a442411a13415f5e4d8", title: "No Internet", quirks: XO_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "4cb8bc4ddcfd23822c4a38990ac7e4225a323cec", title: "No_Rom_Selected", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "61931487c694c5bc6978ae22c0a36aca5a647e24", title: "Nokia 3310 Template", quirks: SUPER_CHIP, tickrate: Some(30), keymap: None, keys: &[] },
+    RomEntry { sha1: "de259351c65f790af035a4607a508c366bf4eaf2", title: "Nonogram", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "04e18ff4ae42e3056c502e0c99d4740ecea65966", title: "Nyan Cat", quirks: XO_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "3450e0d92e0bbf8e9d3065fd088cd6dfa5f9441d", title: "Octo Bird", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "e2cf46c544bee2ef8a8b21dba1c583d5121b1b96", title: "Octo Crawl", quirks: XO_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "bcb80940a8ed339a97917025d67d3217d8b89717", title: "Octo Lander", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "33abb5f1ba7db3166636911c6cfa81a5ce5b861c", title: "Octo paint", quirks: XO_CHIP, tickrate: Some(30), keymap: None, keys: &[] },
+    RomEntry { sha1: "d52c2f85f56c963ff5e48a096afc61d3c8a71c11", title: "Octo Party Mix!", quirks: XO_CHIP, tickrate: Some(1000), keymap: None, keys: &[] },
+    RomEntry { sha1: "8263bac7d98d94097171f0a5dc6f210f77543080", title: "Octo Rancher", quirks: MODERN_CHIP_8, tickrate: Some(7), keymap: None, keys: &[] },
+    RomEntry { sha1: "8603e177fcbb04a5b1a685c216380bee6a05b0f2", title: "Octo Roads", quirks: XO_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "28e8f7b405d48647eb090a550ec679327c57f2f5", title: "Octo Slam", quirks: SUPER_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "9dc674f4a7c8662671e9337421acea49e0447090", title: "Octo Slam-Home Run Derby", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "838706ee2d7001e6e909360f9a02f48d81d3a0d6", title: "Octo Space Program", quirks: XO_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "39970ccfd3a3f00180d53464d4fd7862193eaf0f", title: "Octo: a Chip 8 Story", quirks: MODERN_CHIP_8, tickrate: Some(7), keymap: None, keys: &[] },
+    RomEntry { sha1: "5634ce5f7f08fee69eec2327529c646d5f596be0", title: "Octoamp", quirks: XO_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "eb1a09cc11c73938f39ce8d52c8e06576dec3a32", title: "Octofonts", quirks: XO_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "f26993a4afd5cda2fea19935773fd3db54866623", title: "Octojam 1 Title", quirks: SUPER_CHIP, tickrate: Some(7), keymap: None, keys: &[] },
+    RomEntry { sha1: "11c68038d64a09be549a6c1e50724808914d8991", title: "Octojam 2 Title", quirks: MODERN_CHIP_8, tickrate: Some(7), keymap: None, keys: &[] },
+    RomEntry { sha1: "493c76b9d9252e1d349d408d341daff5267f35fa", title: "Octojam 3 Title", quirks: MODERN_CHIP_8, tickrate: Some(7), keymap: None, keys: &[] },
+    RomEntry { sha1: "9f55f7abc8f2bc4b59a01515f1d887a6568a8ab4", title: "Octojam 4 Title", quirks: MODERN_CHIP_8, tickrate: Some(30), keymap: None, keys: &[] },
+    RomEntry { sha1: "d6d8efef811350e7fba6197024c4973b360749b8", title: "Octojam 5 Title", quirks: COSMAC_VIP, tickrate: Some(7), keymap: None, keys: &[] },
+    RomEntry { sha1: "18aef6d2d3b560681038d0dda2273d780dc1daa5", title: "Octojam 6 Title", quirks: MODERN_CHIP_8, tickrate: Some(7), keymap: None, keys: &[] },
+    RomEntry { sha1: "9a9c341571ace516c9789b1eb92590833af13239", title: "Octojam 7 Title", quirks: MODERN_CHIP_8, tickrate: Some(30), keymap: None, keys: &[] },
+    RomEntry { sha1: "959ed7d6b61e667bb59d1b497401258463f88454", title: "Octojam 8 Title", quirks: COSMAC_VIP, tickrate: Some(7), keymap: None, keys: &[] },
+    RomEntry { sha1: "5a6366decb08df66da8bd685b497ccce0884c307", title: "Octojam 9 Title", quirks: COSMAC_VIP, tickrate: Some(15), keymap: None, keys: &[] },
+    RomEntry { sha1: "80feda2028aa31788d3d1d9e062d77d2fd9308cc", title: "Octoma", quirks: XO_CHIP, tickrate: Some(10000), keymap: None, keys: &[] },
+    RomEntry { sha1: "7a4a89870f2ab23c28024dd1c3dd52cf1af1ad00", title: "Octopeg", quirks: SUPER_CHIP, tickrate: Some(200), keymap: None, keys: &[] },
+    RomEntry { sha1: "23a08dc955d6afe95b9a4880b0e75d7fbf0b4dac", title: "OctoPi", quirks: XO_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "733d41d4c367214cd177071ee6a783a46cf14bf4", title: "OctoRoads", quirks: SUPER_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "1ff6e2a8920c5b48def34348df65226285f39ce9", title: "Octovore", quirks: SUPER_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "74936ffb0db233c722f5a39932d75240b6437a72", title: "Octovore", quirks: XO_CHIP, tickrate: Some(120), keymap: None, keys: &[] },
+    RomEntry { sha1: "8af7d183230de959a53ec84418b9e2609838d3fb", title: "Only the Good Die Neil Young", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "f9d0bdf4a80d5570a9af9fd13769e528dff411df", title: "Ordinary Idle Garden", quirks: XO_CHIP, tickrate: Some(200), keymap: None, keys: &[] },
+    RomEntry { sha1: "a9d3c975a5e733646a04f6e61deebcd0ad50f700", title: "Outlaw", quirks: MODERN_CHIP_8, tickrate: Some(15), keymap: None, keys: &[] },
+    RomEntry { sha1: "a18f1e3897416180b32e47ddc82cba9aca2c8d52", title: "Paddles", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "a7aba6032d4a01336eb0cf4f43ce28709ac451e6", title: "Pancake Panic", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "507e7dc6783565071dfe4b72154af431d4466958", title: "Particle Demo", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "67ee534ad376d89f0d5d78a99c006a847e28c016", title: "Patrick's CHIP-8 Challenge", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "4a4c47e886d576c8e5172d797a276601084004bb", title: "Patterns", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "66c15e550c9cda39b50220c49d22578dabbfe319", title: "Patterns", quirks: XO_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "3be683d1ac0b27ae47a09984e420853fff0b7e0d", title: "Pet Dog", quirks: MODERN_CHIP_8, tickrate: Some(7), keymap: None, keys: &[] },
+    RomEntry { sha1: "e5564c1662d3f144507782784ae4e2f79eaf66d7", title: "Pich8-Logo", quirks: SUPER_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "7da3eba52a8d8025ddf14ee40d28f151585529a0", title: "Piper", quirks: SUPER_CHIP, tickrate: Some(200), keymap: None, keys: &[] },
+    RomEntry { sha1: "693ba52f822c2e2713c5329ae77ff3271e5b954f", title: "Pixlar", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "ccec955da264cd92fdbb18c4971419497513ae42", title: "Pizza Topping Panic!", quirks: XO_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "6dde2db154ea508431d38b43a0f8b4a641e0439e", title: "Planet of the Eights", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "1830eb401ba8789a477dfcf294873a5479ebcfe8", title: "Pong 2", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[("Up", 0x1), ("Down", 0x4)] },
+    RomEntry { sha1: "a60611339661e3ab2d8af024ad1da5880a6f8665", title: "Pong", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[("Up", 0x1), ("Down", 0x4)] },
+    RomEntry { sha1: "b232ef880bd6060fb45fa6effed7edf0ae95670e", title: "Pong", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "607c4f7f4e4dce9f99d96b3182bfe7e88bb090ee", title: "Pong", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "f6ee978a1dfded9262f08dc95bfb3071c5767e78", title: "Prads Demo", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "3a840c33442ad9e912df1fa2aa61833bf571af34", title: "Private Eye", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "726cb39afa7e17725af7fab37d153277d86bff77", title: "Programmable Spacefighters", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "5a2c897da9cc78f6d75123818e04db4cd1044b63", title: "PSX Boot Sim", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "b2abb5312f0ad28421c1190a65a73d98d4ebf401", title: "Pumpkin \"Dreess\" Up", quirks: MODERN_CHIP_8, tickrate: Some(7), keymap: None, keys: &[] },
+    RomEntry { sha1: "1293db0ccccbe7dd3fc5a09a2abc5d7b175e18e0", title: "Puzzle", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "f1e036fb93b482b1ddfcb2bc1a4de43c8cf51def", title: "Random Number Test", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "e14350d3b19443e5ad2848172bef9719a8680b01", title: "Red October V", quirks: XO_CHIP, tickrate: Some(10000), keymap: None, keys: &[] },
+    RomEntry { sha1: "642e6174ac7b2bccb7d0845eb5f18d2defbe98b4", title: "Replicator", quirks: XO_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "ff639eceaf221ae66151a03779b41fae7118d2d8", title: "Reversi", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "dd6ef80cadef1e7b42f71ad99573b1af2299e27d", title: "Robot", quirks: SUPER_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "5e70f91ca08e9b9e9de61670492e3db2d7f7d57a", title: "Rocket Launch", quirks: SUPER_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "e2005db6391f589534dd2d63a95b429338bd667c", title: "Rocket Launcher", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "3d1d029d6e31206d245c0ba881c0d1f003953bad", title: "Rocket", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "466ce147503c536b23a7548d6adf027c26d28df3", title: "Rocket", quirks: XO_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "b5cc3bf3a5da556a33f2621be0f51c19e751292d", title: "Rocket-70", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "b76fbca2ec089c7e77f4a2f754db37854b99debc", title: "Rockto", quirks: SUPER_CHIP, tickrate: Some(15), keymap: None, keys: &[] },
+    RomEntry { sha1: "a6f3ac2d89cdc1d7b22013301863bad6a4fb7318", title: "RPS", quirks: MODERN_CHIP_8, tickrate: Some(7), keymap: None, keys: &[] },
+    RomEntry { sha1: "fb6a79a1f42cd4539c3da2783d4f7f035d9b3a2c", title: "Rule 30 Improved", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "c8a3ccbdde2a289992077779cb02f1200cfed4bb", title: "Rule 30", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "7dc6605ed7b139330ee7e1dec33efba76486f4d7", title: "RulerBrain", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "4639f86beb0a203ae512b85d3b56d813b2dea7b4", title: "Rush Hour", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "24960090b2afc9de2a4cb3ee7daf6a21456bb49b", title: "Russian Roulette", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "57b4b5fa3251dee9d6b588327cf5ff8d194d4a04", title: "Safecracker", quirks: XO_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "ecfe1354f04a8bc60adb84637c97c7d8b6809097", title: "Sand", quirks: XO_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "dcf6852e937aecedbe16bc93009624ef8590bce3", title: "Screenwipe", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "66068809c482a30aa4475dc554a18d8d727d3521", title: "Scroll Pattern 1", quirks: XO_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "ea4ec4c07c97e1ad77eb9bfe237d2a1578795fbf", title: "Seconds Counter", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "51a31cc51414b4dd6c5c54081574f915e6f53744", title: "Seconds Counter", quirks: SUPER_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "58f7ce407aedf456dc8992342f4a6f9f0647383b", title: "Sens8tion", quirks: SUPER_CHIP, tickrate: Some(20), keymap: None, keys: &[] },
+    RomEntry { sha1: "448f9d30d2157ab42679b809d4fb0b43d145f74f", title: "Sequence Shoot", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "29f83328069205a1cdb7020846cca34d6988c83c", title: "Serpinski", quirks: SUPER_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "100dea0037219d82a090e35eb93526ba4413ffe4", title: "Shooter", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "2498050e4f5645574daefaa8a679576374c55973", title: "Shooth3rd II plus", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "41f2a4e7f372795e3d6ad657de8622c0169248db", title: "Shooth3rd II", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "e78144bb9bdf7b48b096e1cdd0f4db430bfd731e", title: "Shooth3rd", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "443550abf646bc7f475ef0466f8e1232ec7474f3", title: "Shooting Stars", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "9be0cc119f8e3c18b7f0203c54b30c50b8f438a9", title: "SierpChaos", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "ec1824ccdcc6c3b4390004946e551d4aa2058820", title: "SierpChaos", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "a0073e944d5ae9ca14324543fdf818907de80449", title: "Sierpinski", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "981e7029587172681765243b45445fcc382139b3", title: "Simple Dodge", quirks: SUPER_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "7c680cd427c2d0eecde208ebbce667707c0f13a2", title: "Simple Snek", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "d4339dac64038f30130af02fbe73b57cd7d481a1", title: "Singing Voice", quirks: XO_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "6b6502b03183e492f8170172308df9876c29d1d9", title: "Single Dragon", quirks: SUPER_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "d6cbd3af85b4c55b83c4e01f3a17c66fcebe9ccc", title: "Single Dragon", quirks: SUPER_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "f12038dcd28ca71661162bfb6fc92a8826f7d6b9", title: "SK8 H8 1988", quirks: XO_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "1b6dcf8c02ea0b89a4f04ce28e7c39a5e7a513d6", title: "SK8 H8 1988", quirks: XO_CHIP, tickrate: Some(20), keymap: None, keys: &[] },
+    RomEntry { sha1: "7143cb2e8c895eccbc1d768417c932bde8337b94", title: "Skellespresso", quirks: XO_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "fdb2da9e06a07bb11dee8a7dc1a9589759a9c57d", title: "Skipper", quirks: XO_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "8ebf74e790e58a8d5a7beff598bb32ed7eeeabf7", title: "Skyward", quirks: Quirks { vf_reset: false, shift_uses_vy: true, memory_increments_i: false, memory_increments_i_by_x: false, jump_uses_vx: false, clip_sprites: false, collision_rows: false, display_wait: false, wait_key_on_press: true }, tickrate: Some(1000), keymap: None, keys: &[] },
+    RomEntry { sha1: "7623fa0fa915979226566b24107360e7537735f4", title: "Slide", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "d73d48484a8fc60e8650f4228d6963a19a4de6c3", title: "Slippery Slope", quirks: MODERN_CHIP_8, tickrate: None, keymap: None, keys: &[("Up", 0x5), ("Down", 0x8), ("Left", 0x7), ("Right", 0x9), ("Space", 0x6)] },
+    RomEntry { sha1: "9d834860f455aec7e95fb886984497e5be501610", title: "Slippery Slope", quirks: MODERN_CHIP_8, tickrate: Some(30), keymap: None, keys: &[("Up", 0x5), ("Down", 0x8), ("Left", 0x7), ("Right", 0x9), ("Space", 0x6)] },
+    RomEntry { sha1: "a6ad3ff3a6e969f87535e9733850630e9d221e51", title: "Smile", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "97a65f7c877f923a93e7b423ad39187e91e938fd", title: "Snafu", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "fe25659856e1921ea629d3f8fce977c0cae57ff3", title: "Snake", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "6881684726d8bf97379d8eb988a9cdfb373c1698", title: "Snake", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "0920bfcaf974a10621af7ef0e48929c86dd0df2e", title: "Snake", quirks: MODERN_CHIP_8, tickrate: None, keymap: None, keys: &[("Up", 0x5), ("Down", 0x8), ("Left", 0x7), ("Right", 0x9)] },
+    RomEntry { sha1: "06a6692c92eb8077329b6d4e59d55479d60574a8", title: "Snake", quirks: SUPER_CHIP, tickrate: Some(15), keymap: None, keys: &[("Up", 0x5), ("Down", 0x8), ("Left", 0x7), ("Right", 0x9)] },
+    RomEntry { sha1: "7d9abc18f187fafeb6f799f42882ff6007d340ac", title: "Sneak Surround", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "bb5740042385cae10724b051208bb95e5341f56d", title: "Snek", quirks: COSMAC_VIP, tickrate: Some(1000), keymap: None, keys: &[] },
+    RomEntry { sha1: "b7cfb02cfb357ab5bf7fb3069f730fb4bf5df5f0", title: "Snoopy Cosmac picture", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "ef54d110d2ac9d4a172f523b011b05fe4caece5b", title: "Snoopy picture", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "332e892ad054cf182e1ca4c465b603b8261ccec9", title: "Snow Daze", quirks: SUPER_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "6df358d77961a0bf21e98876f9f616791cba31e3", title: "Soccer", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "c7c59b38129fdcec5bb0775a9a141b6ba936e706", title: "Sokoban", quirks: SUPER_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "107366630b4e0449add7ab00f93cce65f38f9713", title: "Space Defense!", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "4200636c4d2a4495d10d6348049d21b887e8d1be", title: "Space Explorer", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "aa4f1a282bd64a2364102abf5737a4205365a2b4", title: "Space Flight", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "ed829190e37815771e7a8c675ba0074996a2ddb0", title: "Space Intercept", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "5c28a5f85289c9d859f95fd5eadbdcb1c30bb08b", title: "Space Invaders", quirks: SUPER_CHIP, tickrate: None, keymap: Some("arrows"), keys: &[] },
+    RomEntry { sha1: "f100197f0f2f05b4f3c8c31ab9c2c3930d3e9571", title: "Space Invaders", quirks: SUPER_CHIP, tickrate: None, keymap: Some("arrows"), keys: &[] },
+    RomEntry { sha1: "d11e76793c231cdce513c09f0511202ed076834d", title: "Space Racer", quirks: MODERN_CHIP_8, tickrate: None, keymap: None, keys: &[("Up", 0x5), ("Down", 0x8), ("Left", 0x7), ("Right", 0x9)] },
+    RomEntry { sha1: "659cb966e976fcbcae76f6a8a07c65be4d18aae8", title: "Space Racer", quirks: MODERN_CHIP_8, tickrate: Some(20), keymap: None, keys: &[("Up", 0x5), ("Down", 0x8), ("Left", 0x7), ("Right", 0x9)] },
+    RomEntry { sha1: "a05844df3305738e4030512f0063db2fe4f3bd11", title: "Spacefight 2091", quirks: SUPER_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "9f9a4affbf7afd70bb594fb321e16579318c0164", title: "Spacejam!", quirks: MODERN_CHIP_8, tickrate: Some(100), keymap: None, keys: &[] },
+    RomEntry { sha1: "b66f55f83eb264d2b73c0b4ac81ea5044bf73138", title: "Spock Paper Scissors", quirks: MODERN_CHIP_8, tickrate: Some(15), keymap: None, keys: &[] },
+    RomEntry { sha1: "1bd92042717c3bc4f7f34cab34be2887145a6704", title: "Spooky Spot", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "318c6359405f8b1512b325c53eb119a6f9d57aef", title: "Spoong", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "2dbb5b53121ec84cb2377fcb645e57cc8b5eaa09", title: "SQRT TEST PROGRAM", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "abfce04ddd0f72838dd887f3db3106066fd675b3", title: "Squad", quirks: SUPER_CHIP, tickrate: Some(500), keymap: None, keys: &[] },
+    RomEntry { sha1: "a58ec7cc63707f9e7274026de27c15ec1d9945bd", title: "Squash", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[("Up", 0x1), ("Down", 0x4)] },
+    RomEntry { sha1: "c314300d1630a479678167e4e786cce2c17831cd", title: "Starfield", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "0085dd8fce4f7ac2e39ba73cf67cc043f9ba4812", title: "Stars", quirks: SUPER_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "a4c8e14b43dc75bc960a42a5300f64dc6e52cf32", title: "Stars", quirks: SUPER_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "085394b959f03a0e525b404d8a68a36e56d6446a", title: "Static Organ", quirks: XO_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "64536d549c986e9edf25de9fa89db60d2ade85c0", title: "Sub-Terr8nia", quirks: XO_CHIP, tickrate: Some(60), keymap: None, keys: &[] },
+    RomEntry { sha1: "89aadf7c28bcd1c11e71ad9bd6eeaf0e7be474f3", title: "Submarine", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "83a2f9c8153be955c28e788bd803aa1d25131330", title: "Sum Fun", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "68b6f9336c1bdc4dcaf7fca78c3a719894bdd376", title: "Super Block", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "f505bdc0b1f2da3cc4a69e3baaba8c3bf5303692", title: "Super Etch-a-sketch", quirks: SUPER_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "c8375d6a626ea21532cde178a7a0a22b7e511414", title: "Super Gem Catcher", quirks: SUPER_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "e6d910b7c9f9680df462662ce16336ebcb0eab1e", title: "Super Maze", quirks: SUPER_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "440c5fbe9f5f840e76c308738fb0d37772d66674", title: "Super NeatBoy", quirks: XO_CHIP, tickrate: Some(1000), keymap: None, keys: &[] },
+    RomEntry { sha1: "6e556d92f30a75e7fa8016891438ae082ef33ad4", title: "Super Octo Track X-O", quirks: XO_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "a98ed56f88f11156871d871d9200fc4bb45190a4", title: "Super Octo Track XO", quirks: Quirks { vf_reset: false, shift_uses_vy: true, memory_increments_i: false, memory_increments_i_by_x: false, jump_uses_vx: false, clip_sprites: false, collision_rows: false, display_wait: false, wait_key_on_press: true }, tickrate: Some(100), keymap: None, keys: &[] },
+    RomEntry { sha1: "bb1e786cb921f51d0540cca9a216c0b72bceb443", title: "Super Octogon", quirks: SUPER_CHIP, tickrate: Some(200), keymap: None, keys: &[] },
+    RomEntry { sha1: "f11793f86baae9f5f0c77e5d7aa216c2180c3d07", title: "Super Particle Demo", quirks: SUPER_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "2c761f70a44e521ee848834cfdd2bd1646157d29", title: "Super Pong", quirks: MODERN_CHIP_8, tickrate: Some(30), keymap: None, keys: &[] },
+    RomEntry { sha1: "0663449e1cc8d79ee38075fe86d6b9439a7e43d7", title: "Super Sierp Chaos", quirks: SUPER_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "9f7cf6fe0025878c26b317160c57edd06b3361ba", title: "Super Square", quirks: SUPER_CHIP, tickrate: Some(500), keymap: None, keys: &[] },
+    RomEntry { sha1: "480b4dfa0918d034aea0bf8d8ef5b5a55e94b50b", title: "Super Trip8 Demo", quirks: SUPER_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "f8008875a4b35dc7188eeca2a05535116371eaf0", title: "SuperWorm V3", quirks: SUPER_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "a1c1e0e7b01004be3ee77c69030e6b536cb316e6", title: "SuperWorm V4", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "2d415bf1f31777b22ad73208c4d1ad27d5d4f367", title: "SuperWorm V4", quirks: SUPER_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "531c44e8204d8ab8c078bad36e34067baddfccdb", title: "Sw8 Copter", quirks: SUPER_CHIP, tickrate: Some(100), keymap: None, keys: &[] },
+    RomEntry { sha1: "1bdb4ddaa7049266fa3226851f28855a365cfd12", title: "Syzygy", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "8b2fc2e08830b8a9e604d11c9b319e2cc0a581b3", title: "T8NKS", quirks: XO_CHIP, tickrate: Some(1000), keymap: None, keys: &[] },
+    RomEntry { sha1: "18b9d15f4c159e1f0ed58c2d8ec1d89325d3a3b6", title: "Tank Battle", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "9441cd611eb019217621a11ebeba15b499bbd31e", title: "Tank Warfare", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "3f9ef8dec999574a188ec3b9615cff9888283c85", title: "Tank!", quirks: MODERN_CHIP_8, tickrate: Some(200), keymap: None, keys: &[] },
+    RomEntry { sha1: "63458c204bd24234a33d263d965ea8d16dd5c9e8", title: "Tank-Viper", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "775e82a36c93f1b41b42eca94b55acbc4a48cebe", title: "Tapeworm", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "9c05f5295282abfd89483790191ea59f9d031de5", title: "Tapeworm", quirks: XO_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "54d892ef1ac3ac2d2ff2b58d8662d0374eb77cdd", title: "Team Chipotle Intro", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "5f518084744bf3cb8733f6e5454dfd1634320563", title: "Tetris", quirks: CHIP_48, tickrate: None, keymap: None, keys: &[("Down", 0x7), ("Left", 0x5), ("Right", 0x6), ("Space", 0x4)] },
+    RomEntry { sha1: "6194da2a89a3f431674d7323bf30f5ffe2f7190d", title: "Tetris", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "e74f20f234753e0cc2f58e29dc02d6128a6a3d97", title: "The Binding of COSMAC", quirks: SUPER_CHIP, tickrate: Some(1000), keymap: None, keys: &[] },
+    RomEntry { sha1: "fa1b7ad92e0dd498a1c0b1d9bfc7296f3b96fca8", title: "The Maze", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "d84494c47c5f63cf32fea555e8938c27941c2869", title: "The Rude Street", quirks: SUPER_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "f274bf62145ba9f7740aab9d83e5b15db8047a1d", title: "Thom8s Was Alone", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "40329847cb898f9b34a6aea1095be0a1be0b4546", title: "TIC-TAC-TOE", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "429d455a4bc53167942bf6fd934d72b0f648dce3", title: "TIC-TAC-TOE", quirks: SUPER_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "8c404dc15f854456cafe9b22fcdbaf16830ffde5", title: "Tick-Tack-Toe", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "67996195539c0ddcd98533a01dffeec6a53a6da1", title: "Timebomb", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "8fd0212f4b8b491e8eb260e995313bdb210b1d6b", title: "TMNT-NES", quirks: XO_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "6e7cb52ec99e10f934b76eaf3fddeb8f2e2e14e1", title: "TOMB STON TIPP", quirks: MODERN_CHIP_8, tickrate: Some(7), keymap: None, keys: &[] },
+    RomEntry { sha1: "80ffa819cfa42f2f5f9f836b67c666d01a915970", title: "Tower of Hanoi", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "1f386e1ae47957dec485d3e4034dff706d316d15", title: "Traffic", quirks: SUPER_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "032408f1f1d8e6058ecf0f23f421783c87701b39", title: "Trip-8 Demo", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "a6a6cb2351c20b8f904da07c0ce91bd8161e9317", title: "Tron", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "018e6da9937173b1ac44d4261e848af485dcd305", title: "Truck Simul8or", quirks: XO_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "19279f8cfbb58a925a80b52e690ad71ee0907134", title: "Truck Simul8or", quirks: XO_CHIP, tickrate: Some(1000), keymap: None, keys: &[] },
+    RomEntry { sha1: "175bbb8b3b671c13ff6d9f5b80e31218956e7281", title: "Turm8", quirks: SUPER_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "d03f27f85a1cf68465e0853cc0c4abee4a94a4e5", title: "Turnover '77", quirks: SUPER_CHIP, tickrate: Some(200), keymap: None, keys: &[] },
+    RomEntry { sha1: "0268a789a6c1e281b6fc472c41bbdd00a40e2850", title: "Turtle", quirks: XO_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "7321e1bbe885a749b2ca875d1f49fb6c01f54f91", title: "U-Boat", quirks: SUPER_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "bdb92475acfe11bc7814a2f5eade13fcd09b756a", title: "UFO", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "12572c9e957cace53076d1656ea1b12cd0f331af", title: "Ultimate Tic-tac-toe", quirks: SUPER_CHIP, tickrate: Some(7), keymap: None, keys: &[] },
+    RomEntry { sha1: "ade839585ddeb0e3633177df03c1d91589e629eb", title: "Vers", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "da710f631f8e35534d0b9170bcf892a60f49c43d", title: "Vertical Brix", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "738c6a6ef6a285c3cdd708960413c0a3b1682c7e", title: "Videah Logo", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "e60257f0718aa6aab249667bd90af598d21b97bc", title: "VIP Demo - King Kong", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "7e46d8b67ccc71be45591089d2ec187f7ca8883e", title: "VIP Demo", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "7171deb1dabdf37d7f87507a11d4c07d11690b97", title: "Walking Dog", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "09ce01c54ddddda42ca5cd171f1ffcfd47355d12", title: "Wall", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[("Up", 0x1), ("Down", 0x4)] },
+    RomEntry { sha1: "c9eb637f750e7ca11e5ab1f30b7a69db475e5e23", title: "Warshaws Revenge", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "738f8dd1aa53e5043ee9b45521b0af118c3970ee", title: "WAVE Logo", quirks: XO_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "a8d6e9b1976c99ddc0c4818828a6d3cb3ae6f348", title: "WDL", quirks: COSMAC_VIP, tickrate: Some(15), keymap: None, keys: &[] },
+    RomEntry { sha1: "cda3f758c2566c2067cfe6dd984747f6d24ca759", title: "Whack-A-Mole", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "d666688a8fce468a7d88b536bc1ef5f35ba12031", title: "Wipe Off", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "518c1d40f5d768ee49d2b7951d998588ef8238ba", title: "Wonky Pong", quirks: XO_CHIP, tickrate: Some(100), keymap: None, keys: &[] },
+    RomEntry { sha1: "2d6369d3e2ecfe3f180a5c4b8ad3513efacfd04f", title: "Worm", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "709328365147967f434d1bf78430e9ec160cc24f", title: "Worms demo", quirks: SUPER_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "bc158d819890f16f105b8a316eeeefe4a0bad875", title: "X-Mirror", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "4d223e2919e0f2463f2561d836015c6cd6c18eeb", title: "XOTrackerDemov0", quirks: XO_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "2f34cace9cda8f04829b0cd0b39a3a1726fd4193", title: "YAS", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "2e87573b0fe9a49123bbb86d8384d00a302dc2e4", title: "You're Correct Horse", quirks: XO_CHIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "09f47bea104b86169b9aeb3bdee6e26315ed0a53", title: "Zero Demo", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+    RomEntry { sha1: "f2e9c480af31a4039af02dd7a2b8d5d1f859704d", title: "ZeroPong", quirks: COSMAC_VIP, tickrate: None, keymap: None, keys: &[] },
+];
+
+/// Looks a ROM up by its contents
+///
+/// # Examples
+/// ```
+/// use chip_8::rom_db;
+///
+/// let rom = include_bytes!("../roms/sierpinski.ch8");
+/// assert_eq!(rom_db::lookup(rom).unwrap().title, "Sierpinski");
+/// assert!(rom_db::lookup(&[0x00, 0xE0]).is_none());
+/// ```
+pub fn lookup(rom: &[u8]) -> Option<&'static RomEntry> {
+    let hash = sha1_hex(rom);
+    ROMS.iter().find(|entry| entry.sha1 == hash)
+}
+
+/// Formats the SHA-1 of `data` as lowercase hex
+pub(crate) fn sha1_hex(data: &[u8]) -> String {
+    Sha1::digest(data)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sha1_hex_matches_known_digest() {
+        assert_eq!(sha1_hex(b"abc"), "a9993e364706816aba3e25717850c26c9cd0d89d");
+    }
+
+    #[test]
+    fn lookup_finds_database_entries_by_hash() {
+        let entry = ROMS.iter().find(|entry| entry.sha1 == "5f518084744bf3cb8733f6e5454dfd1634320563").unwrap();
+        assert_eq!(entry.title, "Tetris");
+        assert_eq!(entry.quirks, CHIP_48);
+        assert_eq!(entry.keys, [("Down", 0x7), ("Left", 0x5), ("Right", 0x6), ("Space", 0x4)]);
+
+        let entry = lookup(include_bytes!("../roms/sierpinski.ch8")).unwrap();
+        assert_eq!((entry.quirks, entry.tickrate), (COSMAC_VIP, None));
+        let superpong = ROMS.iter().find(|entry| entry.title == "Super Pong").unwrap();
+        assert_eq!(superpong.tickrate, Some(30));
+    }
+
+    #[test]
+    fn entries_have_lowercase_hex_hashes() {
+        for entry in ROMS {
+            assert_eq!(entry.sha1.len(), 40, "{}", entry.title);
+            assert!(entry.sha1.chars().all(|c| matches!(c, '0'..='9' | 'a'..='f')), "{}", entry.title);
+        }
+        let mut hashes: Vec<_> = ROMS.iter().map(|entry| entry.sha1).collect();
+        hashes.sort_unstable();
+        hashes.dedup();
+        assert_eq!(hashes.len(), ROMS.len());
+    }
+}
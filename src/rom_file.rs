@@ -0,0 +1,174 @@
+//! Reading ROMs from files, zip archives and the web
+//!
+//! ROM packs are usually downloaded as zip files, so [`load`] takes a
+//! `.zip` holding a single ROM as readily as the ROM itself, and with the
+//! `http` feature an `https://` URL to either. Anything read is checked
+//! before it gets near [`Cpu::load_program`](crate::Cpu::load_program): it
+//! has to fit in memory, and archives and downloads have to be named like
+//! ROMs.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use chip_8::{rom_file, CpuBuilder};
+//!
+//! let mut cpu = CpuBuilder::new().build();
+//! cpu.load_program(&rom_file::load("roms/pack.zip").unwrap()).unwrap();
+//! ```
+
+use std::error::Error;
+use std::fs::File;
+use std::io::{Read, Seek};
+use std::path::Path;
+
+use zip::ZipArchive;
+
+use crate::{memory, RomError};
+
+/// The file extensions of ROMs, as opposed to archives or Octo source
+pub const ROM_EXTENSIONS: &[&str] = &["ch8", "c8", "sc8", "xo8", "mc8"];
+
+/// The most a ROM can be, everything from the usual program start to the
+/// end of MegaChip's 16MB of memory
+pub const MAX_ROM_SIZE: usize = memory::MEGA_CHIP_SIZE - 0x200;
+
+/// Whether `source` is a web address rather than a path
+pub fn is_url(source: &str) -> bool {
+    source.starts_with("https://") || source.starts_with("http://")
+}
+
+/// Reads the ROM at `source`, a path or, with the `http` feature, an
+/// `https://` URL
+pub fn load(source: &str) -> Result<Vec<u8>, Box<dyn Error>> {
+    if !is_url(source) {
+        return read(source);
+    }
+
+    #[cfg(feature = "http")]
+    return download(source);
+
+    #[cfg(not(feature = "http"))]
+    Err("chip_8 was built without the `http` feature, so it can't download ROMs".into())
+}
+
+/// Reads a ROM file, or the ROM in a `.zip`
+pub fn read<P: AsRef<Path>>(path: P) -> Result<Vec<u8>, Box<dyn Error>> {
+    let path = path.as_ref();
+    if has_extension(&path.to_string_lossy(), &["zip"]) {
+        return read_zip(File::open(path)?);
+    }
+    read_limited(File::open(path)?)
+}
+
+/// Reads the one ROM in a zip archive, skipping directories and anything
+/// else that isn't named like a ROM, such as a readme
+pub fn read_zip<R: Read + Seek>(archive: R) -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut archive = ZipArchive::new(archive)?;
+    let roms: Vec<String> = archive
+        .file_names()
+        .filter(|name| !name.ends_with('/') && has_extension(name, ROM_EXTENSIONS))
+        .map(String::from)
+        .collect();
+
+    match roms.as_slice() {
+        [name] => read_limited(archive.by_name(name)?),
+        [] => Err(format!("the archive has no ROM in it (looking for .{})", ROM_EXTENSIONS.join(", .")).into()),
+        _ => {
+            let names = roms.join(", ");
+            Err(format!("the archive has {} ROMs in it, so which to play isn't clear: {}", roms.len(), names).into())
+        }
+    }
+}
+
+/// Downloads the ROM, or the zip holding it, at an `https://` URL
+#[cfg(feature = "http")]
+pub fn download(url: &str) -> Result<Vec<u8>, Box<dyn Error>> {
+    if !url.starts_with("https://") {
+        return Err(format!("{} isn't an https:// URL", url).into());
+    }
+    // the query and fragment aren't part of the name
+    let name = url.split(['?', '#']).next().unwrap_or(url);
+    let zipped = has_extension(name, &["zip"]);
+    if !zipped && !has_extension(name, ROM_EXTENSIONS) {
+        return Err(format!("{} isn't named like a ROM or a .zip", url).into());
+    }
+
+    let response = ureq::get(url).call()?;
+    if zipped {
+        // archives are read from the end, so it all has to arrive first;
+        // the compressed ROM can't sensibly be bigger than the ROM
+        let mut archive = Vec::new();
+        response.into_reader().take(MAX_ROM_SIZE as u64 * 2).read_to_end(&mut archive)?;
+        return read_zip(std::io::Cursor::new(archive));
+    }
+    read_limited(response.into_reader())
+}
+
+/// Reads a whole ROM, stopping as soon as it's too big for memory
+fn read_limited<R: Read>(reader: R) -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut rom = Vec::new();
+    reader.take(MAX_ROM_SIZE as u64 + 1).read_to_end(&mut rom)?;
+    match rom.len() {
+        0 => Err(RomError::Empty.into()),
+        size if size > MAX_ROM_SIZE => Err(RomError::TooLarge { size, max: MAX_ROM_SIZE }.into()),
+        _ => Ok(rom),
+    }
+}
+
+/// Whether the file `name` ends in one of `extensions`, in any case
+fn has_extension(name: &str, extensions: &[&str]) -> bool {
+    match name.rsplit_once('.') {
+        Some((_, ext)) => extensions.iter().any(|known| ext.eq_ignore_ascii_case(known)),
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Cursor, Write};
+    use zip::write::FileOptions;
+    use zip::ZipWriter;
+
+    /// A zip archive holding `files`, the way a ROM pack would come
+    fn archive(files: &[(&str, &[u8])]) -> Cursor<Vec<u8>> {
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, contents) in files {
+            zip.start_file(*name, FileOptions::default()).unwrap();
+            zip.write_all(contents).unwrap();
+        }
+        let mut archive = zip.finish().unwrap();
+        archive.set_position(0);
+        archive
+    }
+
+    #[test]
+    fn read_zip_finds_the_one_rom() {
+        let pack = archive(&[("readme.txt", b"Use 4 and 6 to move"), ("games/pong.CH8", &[0x12, 0x00])]);
+        assert_eq!(read_zip(pack).unwrap(), [0x12, 0x00]);
+
+        let pack = archive(&[("pong.ch8", &[0x12, 0x00]), ("tetris.ch8", &[0x12, 0x00])]);
+        assert!(read_zip(pack).unwrap_err().to_string().contains("2 ROMs"));
+        assert!(read_zip(archive(&[("readme.txt", b"")])).is_err());
+    }
+
+    #[test]
+    fn roms_have_to_fit_in_memory() {
+        let big = vec![0; MAX_ROM_SIZE + 1];
+        let err = read_zip(archive(&[("big.ch8", &big)])).unwrap_err();
+        assert_eq!(err.downcast_ref(), Some(&RomError::TooLarge { size: MAX_ROM_SIZE + 1, max: MAX_ROM_SIZE }));
+
+        let err = read_zip(archive(&[("empty.ch8", &[])])).unwrap_err();
+        assert_eq!(err.downcast_ref(), Some(&RomError::Empty));
+    }
+
+    #[test]
+    fn urls_need_the_http_feature() {
+        assert!(is_url("https://example.com/pong.ch8"));
+        assert!(!is_url("roms/pong.ch8"));
+        #[cfg(not(feature = "http"))]
+        assert!(load("https://example.com/pong.ch8").unwrap_err().to_string().contains("`http` feature"));
+        #[cfg(feature = "http")]
+        assert!(download("https://example.com/readme.txt").is_err());
+    }
+}
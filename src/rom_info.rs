@@ -0,0 +1,152 @@
+//! What a ROM file is, and whether it looks like it'll run
+//!
+//! [`RomInfo::check`] is the step between reading a ROM and loading it. It
+//! fails on a ROM that can't be loaded at all, and otherwise works out its
+//! checksums, for looking it up in ROM databases, and warns about anything
+//! suspicious: an odd length, a first instruction that isn't one, or code
+//! running into the memory the COSMAC VIP's interpreter kept for itself.
+//!
+//! # Example
+//!
+//! ```
+//! use chip_8::rom_info::{RomInfo, RomWarning};
+//!
+//! // LD V0, 1; then a stray byte
+//! let info = RomInfo::check(&[0x60, 0x01, 0xFF]).unwrap();
+//! assert_eq!(info.size, 3);
+//! assert_eq!(info.crc32, 0x838F_CCFE);
+//! assert_eq!(info.warnings, [RomWarning::OddLength]);
+//! ```
+
+use std::fmt;
+
+use crate::instruction::Instruction;
+use crate::{rom_db, rom_file, Address, RomError};
+
+/// Where programs start on the COSMAC VIP, and on most interpreters since
+const PROGRAM_START: Address = 0x200;
+
+/// The end of the memory programs could use on a 4K COSMAC VIP, the rest
+/// holding the interpreter's variables, stack and screen
+pub const VIP_RAM_END: Address = 0xE8F;
+
+/// Something about a ROM that will likely stop it running properly
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RomWarning {
+    /// Instructions are two bytes, so a ROM is usually an even length
+    OddLength,
+    /// The first instruction isn't a CHIP-8 one, or halts straight away
+    BadEntryPoint { opcode: u16 },
+    /// The ROM runs past [`VIP_RAM_END`], so it won't run on a real VIP
+    PastVipRam { end: Address },
+}
+
+impl fmt::Display for RomWarning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RomWarning::OddLength => write!(f, "the ROM is an odd number of bytes long"),
+            RomWarning::BadEntryPoint { opcode } => {
+                write!(f, "the ROM starts with {:04X}, which isn't an instruction to start on", opcode)
+            }
+            RomWarning::PastVipRam { end } => write!(
+                f,
+                "the ROM runs up to 0x{:03X}, past the 0x{:03X} a COSMAC VIP leaves for programs",
+                end, VIP_RAM_END
+            ),
+        }
+    }
+}
+
+/// A ROM's size, checksums and warnings
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RomInfo {
+    pub size: usize,
+    pub crc32: u32,
+    /// Lowercase hex, as [`rom_db`] keys ROMs by
+    pub sha1: String,
+    /// The title from the [`rom_db`], if it knows the ROM
+    pub title: Option<&'static str>,
+    pub warnings: Vec<RomWarning>,
+}
+
+impl RomInfo {
+    /// Looks `rom` over, failing if it's empty or too big to load at the
+    /// usual 0x200
+    pub fn check(rom: &[u8]) -> Result<RomInfo, RomError> {
+        if rom.is_empty() {
+            return Err(RomError::Empty);
+        }
+        if rom.len() > rom_file::MAX_ROM_SIZE {
+            return Err(RomError::TooLarge { size: rom.len(), max: rom_file::MAX_ROM_SIZE });
+        }
+
+        let mut warnings = Vec::new();
+        if !rom.len().is_multiple_of(2) {
+            warnings.push(RomWarning::OddLength);
+        }
+        let opcode = u16::from_be_bytes([rom[0], rom.get(1).copied().unwrap_or_default()]);
+        if let Instruction::Unknown(_) | Instruction::Halt = Instruction::decode(opcode) {
+            warnings.push(RomWarning::BadEntryPoint { opcode });
+        }
+        let end = PROGRAM_START + rom.len() as Address - 1;
+        if end > VIP_RAM_END {
+            warnings.push(RomWarning::PastVipRam { end });
+        }
+
+        Ok(RomInfo {
+            size: rom.len(),
+            crc32: crc32fast::hash(rom),
+            sha1: rom_db::sha1_hex(rom),
+            title: rom_db::lookup(rom).map(|entry| entry.title),
+            warnings,
+        })
+    }
+}
+
+impl fmt::Display for RomInfo {
+    /// Writes a report, a line for each thing known and each warning
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Title  {}", self.title.unwrap_or("unknown"))?;
+        writeln!(f, "Size   {} bytes", self.size)?;
+        writeln!(f, "CRC32  {:08X}", self.crc32)?;
+        writeln!(f, "SHA-1  {}", self.sha1)?;
+        for warning in &self.warnings {
+            writeln!(f, "warning: {}", warning)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn known_roms_check_out() {
+        let info = RomInfo::check(include_bytes!("../roms/sierpinski.ch8")).unwrap();
+
+        assert_eq!(info.title, Some("Sierpinski"));
+        assert_eq!(info.sha1, "a0073e944d5ae9ca14324543fdf818907de80449");
+        // it ends in padding, one zero byte past the last pair
+        assert_eq!(info.warnings, [RomWarning::OddLength]);
+    }
+
+    #[test]
+    fn check_warns_about_suspicious_roms() {
+        let info = RomInfo::check(&[0x00, 0x00]).unwrap();
+        assert_eq!(info.warnings, [RomWarning::BadEntryPoint { opcode: 0x0000 }]);
+
+        // JP 0x200, then padding running up to 0xE90
+        let mut rom = vec![0; 0xC91];
+        rom[..2].copy_from_slice(&[0x12, 0x00]);
+        let info = RomInfo::check(&rom).unwrap();
+        assert_eq!(info.warnings, [RomWarning::OddLength, RomWarning::PastVipRam { end: 0xE90 }]);
+    }
+
+    #[test]
+    fn check_rejects_roms_that_cant_load() {
+        assert_eq!(RomInfo::check(&[]), Err(RomError::Empty));
+        let max = rom_file::MAX_ROM_SIZE;
+        assert_eq!(RomInfo::check(&vec![0x12; max + 2]), Err(RomError::TooLarge { size: max + 2, max }));
+    }
+}
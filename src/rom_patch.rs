@@ -0,0 +1,270 @@
+//! Applying IPS and BPS patches to ROMs as they're loaded
+//!
+//! Bug fixes and hacks for classic ROMs are handed around as patches
+//! rather than as the patched ROMs themselves. [`apply`] takes either
+//! kind, telling them apart by their headers:
+//!
+//! - IPS, starting `PATCH`: records of bytes to write at an offset, or a
+//!   byte repeated, ending `EOF`, with the size to cut the ROM down to
+//!   after that if the ROM shrinks
+//! - BPS, starting `BPS1`: the whole patched ROM described in terms of the
+//!   original, with CRC32s of both and of the patch itself, so a patch
+//!   meant for a different ROM is caught rather than making a mess of it
+//!
+//! Every offset is checked against the memory the ROM was written for,
+//! so a patch can't grow a 4K ROM past 0xE00 bytes, or an XO-CHIP ROM
+//! already bigger than that past [`MAX_ROM_SIZE`].
+//!
+//! # Example
+//!
+//! ```
+//! use chip_8::rom_patch;
+//!
+//! // PATCH, then 2 bytes at offset 1, then EOF
+//! let patch = b"PATCH\x00\x00\x01\x00\x02\x60\x05EOF";
+//! assert_eq!(rom_patch::apply(&[0x00, 0xE0, 0x00], patch).unwrap(), [0x00, 0x60, 0x05]);
+//! ```
+
+use std::error::Error;
+
+use crate::memory::CHIP_8_SIZE;
+use crate::rom_file::MAX_ROM_SIZE;
+use crate::PROGRAM_START;
+
+const IPS_MAGIC: &[u8] = b"PATCH";
+const IPS_EOF: &[u8] = b"EOF";
+const BPS_MAGIC: &[u8] = b"BPS1";
+
+/// The patched copy of `rom`, `patch` being an IPS or BPS patch
+pub fn apply(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>, Box<dyn Error>> {
+    // a 4K ROM has to stay one
+    let max = if rom.len() <= CHIP_8_SIZE - PROGRAM_START { CHIP_8_SIZE - PROGRAM_START } else { MAX_ROM_SIZE };
+    if let Some(records) = patch.strip_prefix(IPS_MAGIC) {
+        ips(rom, records, max)
+    } else if patch.starts_with(BPS_MAGIC) {
+        bps(rom, patch, max)
+    } else {
+        Err("the patch isn't an IPS or BPS patch".into())
+    }
+}
+
+/// Applies IPS records, everything after the `PATCH` header
+fn ips(rom: &[u8], mut records: &[u8], max: usize) -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut patched = rom.to_vec();
+    let take = |records: &mut &[u8], len: usize| -> Result<Vec<u8>, Box<dyn Error>> {
+        if records.len() < len {
+            return Err("the IPS patch ends partway through a record".into());
+        }
+        let (taken, rest) = records.split_at(len);
+        *records = rest;
+        Ok(taken.to_vec())
+    };
+    let number = |bytes: Vec<u8>| bytes.iter().fold(0, |number, &byte| number << 8 | byte as usize);
+
+    loop {
+        if let Some(truncate) = records.strip_prefix(IPS_EOF) {
+            match truncate.len() {
+                0 => return Ok(patched),
+                3 => {
+                    patched.truncate(number(truncate.to_vec()));
+                    return Ok(patched);
+                }
+                _ => return Err("the IPS patch has something after EOF besides a size to cut the ROM to".into()),
+            }
+        }
+
+        let offset = number(take(&mut records, 3)?);
+        let bytes = match number(take(&mut records, 2)?) {
+            // a run of one byte
+            0 => {
+                let len = number(take(&mut records, 2)?);
+                vec![take(&mut records, 1)?[0]; len]
+            }
+            len => take(&mut records, len)?,
+        };
+
+        let end = offset + bytes.len();
+        if end > max {
+            let err = format!("the IPS patch writes up to offset 0x{:X}, past the 0x{:X} a ROM has room for", end, max);
+            return Err(err.into());
+        }
+        if end > patched.len() {
+            patched.resize(end, 0);
+        }
+        patched[offset..end].copy_from_slice(&bytes);
+    }
+}
+
+/// Builds the ROM a BPS patch describes, checking it was meant for `rom`
+fn bps(rom: &[u8], patch: &[u8], max: usize) -> Result<Vec<u8>, Box<dyn Error>> {
+    if patch.len() < BPS_MAGIC.len() + 12 {
+        return Err("the BPS patch is too short to be one".into());
+    }
+    let (body, checksums) = patch.split_at(patch.len() - 12);
+    let crc = |at: usize| u32::from_le_bytes([checksums[at], checksums[at + 1], checksums[at + 2], checksums[at + 3]]);
+    if crc32fast::hash(&patch[..patch.len() - 4]) != crc(8) {
+        return Err("the BPS patch is corrupt, its CRC32 doesn't match".into());
+    }
+    if crc32fast::hash(rom) != crc(0) {
+        return Err("the BPS patch is for a different ROM, the ROM's CRC32 doesn't match".into());
+    }
+
+    let mut reader = Varints { bytes: body, at: BPS_MAGIC.len() };
+    let source_size = reader.next()?;
+    let target_size = reader.next()?;
+    let metadata = reader.next()?;
+    reader.skip(metadata)?;
+    if source_size != rom.len() {
+        return Err(format!("the BPS patch is for a ROM of {} bytes, not {}", source_size, rom.len()).into());
+    }
+    if target_size > max {
+        let err = format!("the BPS patch makes a ROM of {} bytes, but only {} fit in memory", target_size, max);
+        return Err(err.into());
+    }
+
+    let mut target = Vec::with_capacity(target_size);
+    let (mut source_at, mut target_at) = (0usize, 0usize);
+    let out_of_range = || -> Box<dyn Error> { "the BPS patch copies from outside the ROM".into() };
+    while reader.at < body.len() {
+        let action = reader.next()?;
+        let len = (action >> 2) + 1;
+        if target.len() + len > target_size {
+            return Err("the BPS patch writes past the size it gives".into());
+        }
+        match action & 3 {
+            // the original's bytes where they are
+            0 => target.extend_from_slice(rom.get(target.len()..target.len() + len).ok_or_else(out_of_range)?),
+            // bytes from the patch
+            1 => target.extend_from_slice(reader.skip(len)?),
+            // the original's bytes from elsewhere
+            2 => {
+                source_at = reader.relative(source_at)?;
+                target.extend_from_slice(rom.get(source_at..source_at + len).ok_or_else(out_of_range)?);
+                source_at += len;
+            }
+            // bytes already written, which can overlap the ones being written
+            _ => {
+                target_at = reader.relative(target_at)?;
+                for _ in 0..len {
+                    let byte = *target.get(target_at).ok_or_else(out_of_range)?;
+                    target.push(byte);
+                    target_at += 1;
+                }
+            }
+        }
+    }
+
+    if target.len() != target_size || crc32fast::hash(&target) != crc(4) {
+        return Err("the BPS patch didn't make the ROM it was meant to, its CRC32 doesn't match".into());
+    }
+    Ok(target)
+}
+
+/// Reads the numbers BPS patches are made of
+struct Varints<'p> {
+    bytes: &'p [u8],
+    at: usize,
+}
+
+impl<'p> Varints<'p> {
+    /// The next number, seven bits to a byte with the top bit marking the last
+    fn next(&mut self) -> Result<usize, Box<dyn Error>> {
+        let (mut number, mut shift) = (0usize, 1usize);
+        loop {
+            let byte = *self.bytes.get(self.at).ok_or("the BPS patch ends partway through a number")?;
+            self.at += 1;
+            number = number
+                .checked_add((byte & 0x7F) as usize * shift)
+                .ok_or("the BPS patch has a number too big to be real")?;
+            if byte & 0x80 != 0 {
+                return Ok(number);
+            }
+            shift = shift
+                .checked_shl(7)
+                .filter(|&shift| shift < 1 << 48)
+                .ok_or("the BPS patch has a number too big to be real")?;
+            number += shift;
+        }
+    }
+
+    /// Moves `from` by the signed offset that comes next
+    fn relative(&mut self, from: usize) -> Result<usize, Box<dyn Error>> {
+        let offset = self.next()?;
+        let moved = if offset & 1 == 0 { from.checked_add(offset >> 1) } else { from.checked_sub(offset >> 1) };
+        Ok(moved.ok_or("the BPS patch copies from before the start of the ROM")?)
+    }
+
+    /// Skips `len` bytes, returning them
+    fn skip(&mut self, len: usize) -> Result<&'p [u8], Box<dyn Error>> {
+        let bytes = self.bytes.get(self.at..self.at + len).ok_or("the BPS patch ends partway through an action")?;
+        self.at += len;
+        Ok(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A number as BPS writes them
+    fn varint(mut number: usize) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        loop {
+            let low = (number & 0x7F) as u8;
+            number >>= 7;
+            if number == 0 {
+                bytes.push(0x80 | low);
+                return bytes;
+            }
+            bytes.push(low);
+            number -= 1;
+        }
+    }
+
+    /// A BPS patch turning `source` into `target` with `actions`
+    fn bps_patch(source: &[u8], target: &[u8], actions: &[u8]) -> Vec<u8> {
+        let mut patch = BPS_MAGIC.to_vec();
+        patch.extend(varint(source.len()));
+        patch.extend(varint(target.len()));
+        patch.extend(varint(0));
+        patch.extend_from_slice(actions);
+        patch.extend_from_slice(&crc32fast::hash(source).to_le_bytes());
+        patch.extend_from_slice(&crc32fast::hash(target).to_le_bytes());
+        let crc = crc32fast::hash(&patch);
+        patch.extend_from_slice(&crc.to_le_bytes());
+        patch
+    }
+
+    #[test]
+    fn ips_writes_runs_and_truncates() {
+        // 3 bytes at 1; a run of four 0xAA at 6; then cut to 8 bytes
+        let patch = b"PATCH\x00\x00\x01\x00\x03\x01\x02\x03\x00\x00\x06\x00\x00\x00\x04\xAAEOF\x00\x00\x08";
+        assert_eq!(apply(&[0; 4], patch).unwrap(), [0, 1, 2, 3, 0, 0, 0xAA, 0xAA]);
+
+        let past = b"PATCH\x00\x0D\xFF\x00\x02\x12\x00EOF";
+        assert!(apply(&[0; 4], past).unwrap_err().to_string().contains("0xE01"));
+        assert!(apply(&[0; 4], b"PATCH\x00\x00\x01\x00\x03\x01").is_err());
+        assert!(apply(&[0; 4], b"not a patch").is_err());
+    }
+
+    #[test]
+    fn bps_rebuilds_the_target() {
+        let source = [0x60, 0x01, 0x61, 0x02, 0x12, 0x00];
+        let target = [0x60, 0x01, 0x00, 0xE0, 0x00, 0xE0, 0x00, 0xE0, 0x61, 0x02];
+        let mut actions = varint(1 << 2); // keep the 2 bytes at 0
+        actions.extend(varint(1 << 2 | 1)); // 2 new bytes
+        actions.extend_from_slice(&[0x00, 0xE0]);
+        actions.extend(varint(3 << 2 | 3)); // 4 bytes from 2 in the target, repeating
+        actions.extend(varint(2 << 1));
+        actions.extend(varint(1 << 2 | 2)); // 2 bytes from 2 in the source
+        actions.extend(varint(2 << 1));
+
+        let patch = bps_patch(&source, &target, &actions);
+        assert_eq!(apply(&source, &patch).unwrap(), target);
+        assert!(apply(&[0x12, 0x00], &patch).unwrap_err().to_string().contains("different ROM"));
+
+        let mut corrupt = patch.clone();
+        corrupt[6] ^= 1;
+        assert!(apply(&source, &corrupt).unwrap_err().to_string().contains("corrupt"));
+    }
+}
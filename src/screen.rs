@@ -0,0 +1,756 @@
+//! The CHIP-8's monochrome display, 64x32 or SUPER-CHIP's 128x64, and
+//! MegaChip's palettized 256x192
+
+/// Width of the low resolution screen in pixels
+pub const WIDTH: usize = 64;
+
+/// Height of the low resolution screen in pixels
+pub const HEIGHT: usize = 32;
+
+/// How many pixels the screen has, switched between by SUPER-CHIP's 00FE
+/// and 00FF and MegaChip's 0010 and 0011
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Resolution {
+    /// The original 64x32
+    #[default]
+    Low,
+    /// SUPER-CHIP's 128x64
+    High,
+    /// MegaChip's 256x192, each pixel one of 255 colours
+    Mega,
+}
+
+impl Resolution {
+    /// Width of the screen in pixels
+    pub fn width(self) -> usize {
+        match self {
+            Resolution::Low => WIDTH,
+            Resolution::High => WIDTH * 2,
+            Resolution::Mega => WIDTH * 4,
+        }
+    }
+
+    /// Height of the screen in pixels
+    pub fn height(self) -> usize {
+        match self {
+            Resolution::Low => HEIGHT,
+            Resolution::High => HEIGHT * 2,
+            Resolution::Mega => HEIGHT * 6,
+        }
+    }
+
+    /// The whole screen
+    pub fn rect(self) -> Rect {
+        Rect { x: 0, y: 0, width: self.width(), height: self.height() }
+    }
+}
+
+/// The pixels a program has drawn, `true` being lit
+///
+/// In MegaChip mode each lit pixel also has a colour, picked from a palette
+/// the program loads.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Display {
+    resolution: Resolution,
+    // row by row, `resolution.width()` to a row
+    pixels: Vec<bool>,
+    // MegaChip's palette index of each pixel, 0 being unlit; empty in the
+    // other resolutions, like `palette`
+    indices: Vec<u8>,
+    // MegaChip's 256 colours as RGBA
+    palette: Vec<[u8; 4]>,
+    // how opaque MegaChip's picture is over black
+    alpha: u8,
+}
+
+/// A rectangle of pixels, from column `x` and row `y`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rect {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Rect {
+    /// The whole low resolution screen
+    pub const SCREEN: Rect = Rect { x: 0, y: 0, width: WIDTH, height: HEIGHT };
+
+    /// Whether the pixel at column `x` and row `y` is inside
+    pub fn contains(&self, x: usize, y: usize) -> bool {
+        (self.x..self.x + self.width).contains(&x) && (self.y..self.y + self.height).contains(&y)
+    }
+
+    /// Whether all of `other` is inside
+    pub fn contains_rect(&self, other: &Rect) -> bool {
+        other.x >= self.x
+            && other.y >= self.y
+            && other.x + other.width <= self.x + self.width
+            && other.y + other.height <= self.y + self.height
+    }
+
+    /// Whether any pixel is in both
+    pub fn intersects(&self, other: &Rect) -> bool {
+        self.x < other.x + other.width
+            && other.x < self.x + self.width
+            && self.y < other.y + other.height
+            && other.y < self.y + self.height
+    }
+
+    /// The smallest rectangle holding both
+    pub fn union(&self, other: &Rect) -> Rect {
+        let (left, top) = (self.x.min(other.x), self.y.min(other.y));
+        let right = (self.x + self.width).max(other.x + other.width);
+        let bottom = (self.y + self.height).max(other.y + other.height);
+        Rect { x: left, y: top, width: right - left, height: bottom - top }
+    }
+}
+
+/// What changed between two screens, from [`Display::diff`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameDiff {
+    /// How many pixels are lit in one and not the other
+    pub changed_pixels: usize,
+    /// The smallest rectangle holding every changed pixel, `None` if none
+    /// did
+    pub bounding_box: Option<Rect>,
+}
+
+impl FrameDiff {
+    /// Whether the screens were the same
+    pub fn is_empty(&self) -> bool {
+        self.changed_pixels == 0
+    }
+
+    /// Whether every changed pixel is inside `region`, which is true of no
+    /// change at all
+    pub fn within(&self, region: Rect) -> bool {
+        self.bounding_box.is_none_or(|changed| region.contains_rect(&changed))
+    }
+}
+
+/// The name frontends know a [`Display`] by, as what
+/// [`Frontend::render`](crate::frontend::Frontend::render) is given
+pub type FrameBuffer = Display;
+
+impl Default for Display {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Display {
+    /// Makes a new, blank Display in low resolution
+    pub fn new() -> Display {
+        Display::with_resolution(Resolution::Low)
+    }
+
+    /// Makes a new, blank Display with `resolution`'s pixels
+    pub fn with_resolution(resolution: Resolution) -> Display {
+        let size = resolution.width() * resolution.height();
+        let (indices, palette) =
+            if resolution == Resolution::Mega { (vec![0; size], vec![[0; 4]; 256]) } else { (Vec::new(), Vec::new()) };
+        Display { resolution, pixels: vec![false; size], indices, palette, alpha: 0xFF }
+    }
+
+    /// How many pixels the screen has at the moment
+    pub fn resolution(&self) -> Resolution {
+        self.resolution
+    }
+
+    /// Width of the screen in pixels
+    pub fn width(&self) -> usize {
+        self.resolution.width()
+    }
+
+    /// Height of the screen in pixels
+    pub fn height(&self) -> usize {
+        self.resolution.height()
+    }
+
+    /// The whole screen
+    pub fn rect(&self) -> Rect {
+        self.resolution.rect()
+    }
+
+    /// Whether the pixel at column `x` and row `y` is lit
+    ///
+    /// # Panics
+    ///
+    /// Panics if the coordinate is off the screen
+    pub fn pixel(&self, x: usize, y: usize) -> bool {
+        assert!(x < self.width(), "column {} is off the {} pixel wide screen", x, self.width());
+        self.pixels[y * self.width() + x]
+    }
+
+    /// The colour of the pixel at column `x` and row `y` in MegaChip mode, as
+    /// RGB faded by the palette's and the screen's alpha
+    ///
+    /// `None` for unlit pixels and in the monochrome resolutions, which are
+    /// left to the frontend's [`Palette`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the coordinate is off the screen
+    pub fn color(&self, x: usize, y: usize) -> Option<[u8; 3]> {
+        assert!(x < self.width(), "column {} is off the {} pixel wide screen", x, self.width());
+        let index = *self.indices.get(y * self.width() + x)?;
+        if index == 0 {
+            return None;
+        }
+        let [red, green, blue, alpha] = self.palette[index as usize];
+        let opacity = alpha as u32 * self.alpha as u32;
+        Some([red, green, blue].map(|channel| (channel as u32 * opacity / (255 * 255)) as u8))
+    }
+
+    /// All of the pixels, row by row from the top left
+    pub fn pixels(&self) -> &[bool] {
+        &self.pixels
+    }
+
+    /// The pixels a row at a time, from the top
+    ///
+    /// # Examples
+    /// ```
+    /// use chip_8::Display;
+    ///
+    /// let display = Display::new();
+    /// assert_eq!(display.rows().len(), 32);
+    /// assert!(display.rows().all(|row| row == [false; 64]));
+    /// ```
+    pub fn rows(&self) -> std::slice::ChunksExact<'_, bool> {
+        self.pixels.chunks_exact(self.width())
+    }
+
+    /// Turns every pixel off
+    pub fn clear(&mut self) {
+        self.pixels.iter_mut().for_each(|pixel| *pixel = false);
+        self.indices.iter_mut().for_each(|index| *index = 0);
+    }
+
+    /// Switches to `resolution`, blanking the screen and MegaChip's palette
+    pub(crate) fn set_resolution(&mut self, resolution: Resolution) {
+        *self = Display::with_resolution(resolution);
+    }
+
+    /// MegaChip's palette index of every pixel, row by row, and empty in the
+    /// other resolutions
+    pub(crate) fn indices(&self) -> &[u8] {
+        &self.indices
+    }
+
+    /// MegaChip's colours as RGBA, and empty in the other resolutions
+    pub(crate) fn palette(&self) -> &[[u8; 4]] {
+        &self.palette
+    }
+
+    /// How opaque MegaChip's picture is, from 0 to 0xFF
+    pub(crate) fn alpha(&self) -> u8 {
+        self.alpha
+    }
+
+    /// Sets MegaChip's colours from `first` on to `colors`, each four bytes
+    /// of ARGB the way 02NN reads them; ignored outside MegaChip mode
+    pub(crate) fn load_palette(&mut self, first: usize, colors: &[u8]) {
+        for (entry, argb) in self.palette.iter_mut().skip(first).zip(colors.chunks_exact(4)) {
+            *entry = [argb[1], argb[2], argb[3], argb[0]];
+        }
+    }
+
+    /// Sets how opaque MegaChip's picture is, from 0 to 0xFF
+    pub(crate) fn set_alpha(&mut self, alpha: u8) {
+        self.alpha = alpha;
+    }
+
+    /// A hash of the pixels, and their colours in MegaChip mode, for telling
+    /// screens apart without keeping them
+    ///
+    /// It's FNV-1a, so the same screen hashes the same on every platform and
+    /// in every version, and can be written into tests.
+    pub fn hash64(&self) -> u64 {
+        self.indices.iter().fold(screen_hash(&self.pixels), |hash, &index| fnv(hash, index as u64))
+    }
+
+    /// What changed from `other` to this screen
+    ///
+    /// Screens of different resolutions differ in every pixel of this one.
+    /// In MegaChip mode a pixel that changed colour has changed too.
+    ///
+    /// # Examples
+    /// ```
+    /// use chip_8::screen::Rect;
+    /// use chip_8::CpuBuilder;
+    ///
+    /// // LD V0, 60; LD I, the 0 sprite; DRW V0, V1, 5
+    /// let mut cpu = CpuBuilder::new().memory([0x60, 0x3C, 0xA0, 0x00, 0xD0, 0x15]).build();
+    /// let before = cpu.display().clone();
+    /// cpu.run_cycles(3);
+    ///
+    /// // the top right corner changed, and nothing else
+    /// let diff = cpu.display().diff(&before);
+    /// assert_eq!(diff.changed_pixels, 14);
+    /// assert!(diff.within(Rect { x: 56, y: 0, width: 8, height: 8 }));
+    /// ```
+    pub fn diff(&self, other: &Display) -> FrameDiff {
+        if self.resolution != other.resolution {
+            return FrameDiff { changed_pixels: self.pixels.len(), bounding_box: Some(self.rect()) };
+        }
+
+        let mut changed_pixels = 0;
+        let (mut left, mut top, mut right, mut bottom) = (self.width(), self.height(), 0, 0);
+        let changed = |index: usize| {
+            self.pixels[index] != other.pixels[index] || self.indices.get(index) != other.indices.get(index)
+        };
+        for index in (0..self.pixels.len()).filter(|&index| changed(index)) {
+            let (x, y) = (index % self.width(), index / self.width());
+            changed_pixels += 1;
+            left = left.min(x);
+            top = top.min(y);
+            right = right.max(x + 1);
+            bottom = bottom.max(y + 1);
+        }
+
+        let bounding_box =
+            (changed_pixels > 0).then(|| Rect { x: left, y: top, width: right - left, height: bottom - top });
+        FrameDiff { changed_pixels, bounding_box }
+    }
+
+    /// Flips the pixel at column `x` and row `y`, returning whether it was lit
+    pub(crate) fn toggle(&mut self, x: usize, y: usize) -> bool {
+        let index = y * self.width() + x;
+        let previous = self.pixels[index];
+        self.pixels[index] = !previous;
+        previous
+    }
+
+    /// Paints the pixel at column `x` and row `y` with MegaChip's colour
+    /// `index`, returning whether it was lit
+    pub(crate) fn paint(&mut self, x: usize, y: usize, index: u8) -> bool {
+        let at = y * self.width() + x;
+        let previous = self.pixels[at];
+        self.pixels[at] = index != 0;
+        self.indices[at] = index;
+        previous
+    }
+
+    /// Writes the screen as a plain (ASCII) PPM image, lit pixels white
+    ///
+    /// Each row of pixels is one line, so snapshots diff nicely.
+    ///
+    /// # Examples
+    /// ```
+    /// use chip_8::Display;
+    ///
+    /// let ppm = Display::new().to_ppm_string();
+    /// assert!(ppm.starts_with("P3\n64 32\n1\n0 0 0 0 0 0"));
+    /// ```
+    pub fn to_ppm_string(&self) -> String {
+        let mut ppm = format!("P3\n{} {}\n1\n", self.width(), self.height());
+
+        for row in self.rows() {
+            let line: Vec<&str> = row
+                .iter()
+                .map(|&lit| if lit { "1 1 1" } else { "0 0 0" })
+                .collect();
+            ppm.push_str(&line.join(" "));
+            ppm.push('\n');
+        }
+
+        ppm
+    }
+
+    /// Converts the screen to a grayscale image, lit pixels white
+    ///
+    /// Only available with the `image` feature. [`to_rgb_image`](Display::to_rgb_image)
+    /// keeps MegaChip's colours.
+    #[cfg(feature = "image")]
+    pub fn to_image(&self) -> image::GrayImage {
+        image::GrayImage::from_fn(self.width() as u32, self.height() as u32, |x, y| {
+            if self.pixel(x as usize, y as usize) {
+                image::Luma([255])
+            } else {
+                image::Luma([0])
+            }
+        })
+    }
+
+    /// Converts the screen to a colour image, in MegaChip's colours or with
+    /// lit pixels white
+    ///
+    /// Only available with the `image` feature
+    #[cfg(feature = "image")]
+    pub fn to_rgb_image(&self) -> image::RgbImage {
+        image::RgbImage::from_fn(self.width() as u32, self.height() as u32, |x, y| {
+            let (x, y) = (x as usize, y as usize);
+            match self.color(x, y) {
+                Some(color) => image::Rgb(color),
+                None if self.resolution != Resolution::Mega && self.pixel(x, y) => image::Rgb([255; 3]),
+                None => image::Rgb([0; 3]),
+            }
+        })
+    }
+}
+
+/// FNV-1a over the screen's pixels, row by row
+pub(crate) fn screen_hash(pixels: &[bool]) -> u64 {
+    pixels.iter().fold(0xCBF2_9CE4_8422_2325, |hash, &on| fnv(hash, on as u64))
+}
+
+/// One step of FNV-1a
+fn fnv(hash: u64, value: u64) -> u64 {
+    (hash ^ value).wrapping_mul(0x0000_0100_0000_01B3)
+}
+
+/// Dimmer than this and a fading pixel is treated as off
+const FADED: f32 = 0.05;
+
+/// How brightly each pixel glows, with pixels that were just turned off
+/// fading out over a few frames like on a CRT's phosphor
+///
+/// Games XOR sprites off and back on every frame to move them, which
+/// flickers badly when drawn as-is; real screens hid that by glowing for a
+/// moment after a pixel was turned off.
+///
+/// # Examples
+/// ```
+/// use chip_8::screen::Phosphor;
+/// use chip_8::CpuBuilder;
+///
+/// let cpu = CpuBuilder::new().build();
+/// let mut phosphor = Phosphor::new(0.5);
+///
+/// // once a frame
+/// phosphor.update(cpu.display());
+/// assert_eq!(phosphor.brightness(0, 0), 0.0);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Phosphor {
+    persistence: f32,
+    resolution: Resolution,
+    // row by row, like the display's pixels
+    brightness: Vec<f32>,
+    // MegaChip's colour of each pixel, kept while it fades; empty in the
+    // monochrome resolutions
+    colors: Vec<[u8; 3]>,
+}
+
+impl Default for Phosphor {
+    fn default() -> Self {
+        Self::new(0.0)
+    }
+}
+
+impl Phosphor {
+    /// Makes a dark Phosphor, where a pixel keeps `persistence` of its
+    /// brightness each frame after it's turned off
+    ///
+    /// `persistence` is clamped between 0, where pixels go straight off,
+    /// and 0.95
+    pub fn new(persistence: f32) -> Phosphor {
+        Phosphor {
+            persistence: persistence.clamp(0.0, 0.95),
+            resolution: Resolution::Low,
+            brightness: vec![0.0; WIDTH * HEIGHT],
+            colors: Vec::new(),
+        }
+    }
+
+    /// Moves on a frame: lit pixels glow fully and the rest fade
+    ///
+    /// Should be called 60 times a second, however often the screen is drawn.
+    /// A screen that's switched resolution starts over dark.
+    pub fn update(&mut self, display: &Display) {
+        if self.resolution != display.resolution {
+            self.resolution = display.resolution;
+            self.brightness = vec![0.0; display.pixels.len()];
+            self.colors = vec![[0; 3]; display.indices.len()];
+        }
+        for (index, color) in self.colors.iter_mut().enumerate() {
+            if let Some(lit) = display.color(index % display.width(), index / display.width()) {
+                *color = lit;
+            }
+        }
+        for (glow, &lit) in self.brightness.iter_mut().zip(&display.pixels) {
+            *glow = if lit { 1.0 } else { *glow * self.persistence };
+            if *glow < FADED {
+                *glow = 0.0;
+            }
+        }
+    }
+
+    /// Width of the screen it was last updated with, in pixels
+    pub fn width(&self) -> usize {
+        self.resolution.width()
+    }
+
+    /// Height of the screen it was last updated with, in pixels
+    pub fn height(&self) -> usize {
+        self.resolution.height()
+    }
+
+    /// How brightly the pixel at column `x` and row `y` glows, from 0 to 1
+    ///
+    /// # Panics
+    ///
+    /// Panics if the coordinate is off the screen
+    pub fn brightness(&self, x: usize, y: usize) -> f32 {
+        assert!(x < self.width(), "column {} is off the {} pixel wide screen", x, self.width());
+        self.brightness[y * self.width() + x]
+    }
+
+    /// The colour of the pixel at column `x` and row `y`, as RGBA from 0 to 1
+    ///
+    /// Pixels glow in `palette`'s colours, or in MegaChip's own over
+    /// `palette`'s off colour.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the coordinate is off the screen
+    pub fn color(&self, x: usize, y: usize, palette: &Palette) -> [f32; 4] {
+        let glow = self.brightness(x, y);
+        match self.colors.get(y * self.width() + x) {
+            Some(&on) => Palette { on, ..*palette }.color(glow),
+            None => palette.color(glow),
+        }
+    }
+
+    /// The brightness of every pixel, a row at a time from the top
+    pub fn rows(&self) -> std::slice::ChunksExact<'_, f32> {
+        self.brightness.chunks_exact(self.width())
+    }
+}
+
+/// The colours a window draws lit and unlit pixels in
+///
+/// Colours are written as `#RRGGBB` hex, the way the
+/// [`rom_config`](crate::rom_config) file takes them.
+///
+/// # Examples
+/// ```
+/// use chip_8::screen::Palette;
+///
+/// let palette = Palette::parse("#FFAA00", "#000000").unwrap();
+/// assert_eq!(palette.on, [0xFF, 0xAA, 0x00]);
+/// assert_eq!(palette.color(0.5), [0.5, 1.0 / 3.0, 0.0, 1.0]);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Palette {
+    pub on: [u8; 3],
+    pub off: [u8; 3],
+}
+
+impl Default for Palette {
+    /// Green on black
+    fn default() -> Self {
+        Palette { on: [0x00, 0xFF, 0x00], off: [0x00, 0x00, 0x00] }
+    }
+}
+
+impl Palette {
+    /// Reads a palette from two `#RRGGBB` colours
+    pub fn parse(on: &str, off: &str) -> Result<Palette, String> {
+        Ok(Palette { on: parse_color(on)?, off: parse_color(off)? })
+    }
+
+    /// The colour of a pixel glowing `glow` of the way from off to on, as
+    /// RGBA from 0 to 1
+    pub fn color(&self, glow: f32) -> [f32; 4] {
+        let mut color = [1.0; 4];
+        for (channel, (&on, &off)) in color.iter_mut().zip(self.on.iter().zip(&self.off)) {
+            *channel = (off as f32 + (on as f32 - off as f32) * glow) / 255.0;
+        }
+        color
+    }
+}
+
+/// Reads a colour written `#RRGGBB`
+pub(crate) fn parse_color(text: &str) -> Result<[u8; 3], String> {
+    let digits = text.strip_prefix('#').unwrap_or(text);
+    let value = match u32::from_str_radix(digits, 16) {
+        Ok(value) if digits.len() == 6 => value,
+        _ => return Err(format!("`{}` isn't a colour like #FFAA00", text)),
+    };
+    Ok([(value >> 16) as u8, (value >> 8) as u8, value as u8])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn toggle_flips_pixel_and_returns_previous() {
+        let mut display = Display::new();
+
+        assert!(!display.toggle(3, 4));
+        assert!(display.pixel(3, 4));
+        assert!(display.toggle(3, 4));
+        assert!(!display.pixel(3, 4));
+    }
+
+    #[test]
+    fn clear_turns_off_all_pixels() {
+        let mut display = Display::new();
+        display.toggle(0, 0);
+        display.toggle(63, 31);
+        display.clear();
+
+        assert_eq!(display, Display::new());
+    }
+
+    #[test]
+    fn to_ppm_string_writes_one_line_per_row() {
+        let mut display = Display::new();
+        display.toggle(1, 0);
+        let ppm = display.to_ppm_string();
+        let lines: Vec<&str> = ppm.lines().collect();
+
+        assert_eq!(lines.len(), 3 + HEIGHT);
+        assert!(lines[3].starts_with("0 0 0 1 1 1 0 0 0"));
+        assert_eq!(lines[4].split(' ').count(), WIDTH * 3);
+    }
+
+    #[test]
+    fn diff_boxes_the_changed_pixels() {
+        let mut display = Display::new();
+        display.toggle(10, 5);
+        let before = display.clone();
+        display.toggle(10, 5);
+        display.toggle(12, 9);
+        display.toggle(11, 7);
+
+        let diff = display.diff(&before);
+        assert_eq!(diff.changed_pixels, 3);
+        assert_eq!(diff.bounding_box, Some(Rect { x: 10, y: 5, width: 3, height: 5 }));
+        assert!(diff.within(Rect { x: 8, y: 4, width: 8, height: 8 }));
+        assert!(!diff.within(Rect { x: 11, y: 4, width: 8, height: 8 }));
+        assert!(display.diff(&display).is_empty());
+
+        assert_ne!(display.hash64(), before.hash64());
+        assert_eq!(Display::new().hash64(), 0x28C3_1CF8_DF2E_C325);
+    }
+
+    #[test]
+    fn switching_resolution_blanks_and_resizes_the_screen() {
+        let mut display = Display::new();
+        display.toggle(3, 4);
+        display.set_resolution(Resolution::High);
+
+        assert_eq!((display.width(), display.height()), (128, 64));
+        assert!(display.pixels().iter().all(|&lit| !lit));
+        display.toggle(127, 63);
+        assert!(display.pixel(127, 63));
+        assert_eq!(display.rows().len(), 64);
+
+        let diff = Display::new().diff(&display);
+        assert_eq!(diff.changed_pixels, WIDTH * HEIGHT);
+        assert_eq!(diff.bounding_box, Some(Rect::SCREEN));
+        assert_ne!(display.hash64(), Display::with_resolution(Resolution::High).hash64());
+    }
+
+    #[test]
+    fn phosphor_follows_the_resolution() {
+        let mut display = Display::with_resolution(Resolution::High);
+        display.toggle(100, 50);
+        let mut phosphor = Phosphor::new(0.5);
+        phosphor.update(&display);
+        assert_eq!((phosphor.width(), phosphor.height()), (128, 64));
+        assert_eq!(phosphor.brightness(100, 50), 1.0);
+
+        phosphor.update(&Display::new());
+        assert_eq!(phosphor.rows().len(), HEIGHT);
+        assert!(phosphor.rows().flatten().all(|&glow| glow == 0.0));
+    }
+
+    #[test]
+    fn rects_intersect_when_they_share_a_pixel() {
+        let rect = Rect { x: 4, y: 2, width: 8, height: 4 };
+
+        assert!(rect.intersects(&Rect { x: 11, y: 5, width: 10, height: 10 }));
+        assert!(!rect.intersects(&Rect { x: 12, y: 2, width: 1, height: 1 }));
+        assert!(!rect.intersects(&Rect { x: 4, y: 6, width: 8, height: 4 }));
+        assert!(Rect::SCREEN.intersects(&rect));
+        assert_eq!(rect.union(&Rect { x: 0, y: 5, width: 2, height: 3 }), Rect { x: 0, y: 2, width: 12, height: 6 });
+    }
+
+    #[test]
+    fn phosphor_fades_pixels_out() {
+        let mut display = Display::new();
+        display.toggle(2, 3);
+        let mut phosphor = Phosphor::new(0.5);
+        phosphor.update(&display);
+        assert_eq!(phosphor.brightness(2, 3), 1.0);
+
+        display.toggle(2, 3);
+        phosphor.update(&display);
+        assert_eq!(phosphor.brightness(2, 3), 0.5);
+        for _ in 0..4 {
+            phosphor.update(&display);
+        }
+        assert_eq!(phosphor.brightness(2, 3), 0.0);
+    }
+
+    #[test]
+    fn megachip_pixels_take_their_palette_colour_faded_by_alpha() {
+        let mut display = Display::with_resolution(Resolution::Mega);
+        display.load_palette(1, &[0xFF, 0xFF, 0x80, 0x00, 0x80, 0xFF, 0xFF, 0xFF]);
+        display.paint(0, 0, 1);
+        display.paint(255, 191, 2);
+
+        assert_eq!((display.width(), display.height()), (256, 192));
+        assert_eq!(display.color(0, 0), Some([0xFF, 0x80, 0x00]));
+        assert_eq!(display.color(255, 191), Some([0x80, 0x80, 0x80]));
+        assert_eq!(display.color(1, 0), None);
+        display.set_alpha(0);
+        assert_eq!(display.color(0, 0), Some([0, 0, 0]));
+
+        // a pixel changing colour is a change
+        let before = display.clone();
+        assert!(display.paint(0, 0, 2));
+        assert_eq!(display.diff(&before).changed_pixels, 1);
+        assert_ne!(display.hash64(), before.hash64());
+
+        display.clear();
+        assert_eq!(display.color(255, 191), None);
+        assert_eq!(Display::new().color(0, 0), None);
+    }
+
+    #[test]
+    fn phosphor_keeps_megachip_colours_while_they_fade() {
+        let mut display = Display::with_resolution(Resolution::Mega);
+        display.load_palette(1, &[0xFF, 0xFF, 0x00, 0x00]);
+        display.paint(3, 4, 1);
+        let mut phosphor = Phosphor::new(0.5);
+        let palette = Palette::default();
+        phosphor.update(&display);
+        assert_eq!(phosphor.color(3, 4, &palette), [1.0, 0.0, 0.0, 1.0]);
+
+        display.paint(3, 4, 0);
+        phosphor.update(&display);
+        assert_eq!(phosphor.color(3, 4, &palette), [0.5, 0.0, 0.0, 1.0]);
+        assert_eq!(phosphor.color(4, 4, &palette), palette.color(0.0));
+    }
+
+    #[test]
+    fn phosphor_without_persistence_matches_display() {
+        let mut display = Display::new();
+        display.toggle(2, 3);
+        let mut phosphor = Phosphor::default();
+        phosphor.update(&display);
+        display.toggle(2, 3);
+        phosphor.update(&display);
+
+        assert!(phosphor.rows().flatten().all(|&glow| glow == 0.0));
+    }
+
+    #[cfg(feature = "image")]
+    #[test]
+    fn to_image_lights_pixels_white() {
+        let mut display = Display::new();
+        display.toggle(5, 6);
+        let image = display.to_image();
+
+        assert_eq!(image.dimensions(), (64, 32));
+        assert_eq!(image.get_pixel(5, 6).0, [255]);
+        assert_eq!(image.get_pixel(6, 5).0, [0]);
+    }
+}
@@ -0,0 +1,343 @@
+//! Rhai scripts that drive a ROM, for regression suites and cheats
+//!
+//! A [`Script`] runs a CPU a frame at a time and hands a script an `emu`
+//! handle to read and write the registers and memory, press keys and set
+//! breakpoints. The script's top level runs once before the first frame,
+//! then these functions are called if it defines them:
+//!
+//! - `on_frame(emu, frame)` at the start of every frame, counting from 0
+//! - `on_breakpoint(emu, addr)` before the instruction at a breakpoint runs
+//!
+//! On `emu`:
+//!
+//! - `emu.reg(x)` and `emu.set_reg(x, value)` for V0 to VF
+//! - `emu.pc`, `emu.i` and `emu.frame`, with `emu.set_i(value)`
+//! - `emu.peek(addr)` and `emu.poke(addr, value)`, which goes through
+//!   [`Cpu::poke`] so it can't write below the program start
+//! - `emu.press(key)` and `emu.release(key)` for keys 0 to F
+//! - `emu.break_at(addr)` to call `on_breakpoint` there
+//! - `emu.finish()` to stop [`Script::run`] after this frame
+//!
+//! `assert(condition, message)` stops the script with an error if the
+//! condition is false.
+//!
+//! # Example
+//!
+//! ```
+//! use chip_8::script::Script;
+//! use chip_8::CpuBuilder;
+//!
+//! let mut cpu = CpuBuilder::new().build();
+//! // ADD V0, 1; JP 0x200
+//! cpu.load_program(&[0x70, 0x01, 0x12, 0x00]).unwrap();
+//!
+//! let mut script = Script::new(cpu, r#"
+//!     fn on_frame(emu, frame) {
+//!         if frame == 3 {
+//!             assert(emu.reg(0) == 15, "V0 should have counted to 15");
+//!             emu.finish();
+//!         }
+//!     }
+//! "#).unwrap();
+//!
+//! assert_eq!(script.run(600), Ok(4));
+//! ```
+
+use std::cell::{Ref, RefCell};
+use std::error::Error;
+use std::fmt;
+use std::rc::Rc;
+
+use rhai::{CallFnOptions, Dynamic, Engine, EvalAltResult, Scope, AST, INT};
+
+use crate::timing::Clock;
+use crate::{Byte, Cpu, CpuError, CpuStatus, Reg, StopReason};
+
+/// Why a script stopped
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScriptError {
+    /// The script isn't valid Rhai
+    Parse(String),
+    /// The script failed while running, including failed asserts
+    Runtime(String),
+    /// The ROM failed
+    Cpu(CpuError),
+}
+
+impl fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ScriptError::Parse(err) => write!(f, "Couldn't parse the script: {}", err),
+            ScriptError::Runtime(err) => write!(f, "Script failed: {}", err),
+            ScriptError::Cpu(err) => write!(f, "Program failed: {}", err),
+        }
+    }
+}
+
+impl Error for ScriptError {}
+
+impl From<Box<EvalAltResult>> for ScriptError {
+    fn from(err: Box<EvalAltResult>) -> Self {
+        ScriptError::Runtime(err.to_string())
+    }
+}
+
+/// What the script's `emu` handle shares with the [`Script`] running it
+struct Machine {
+    cpu: Cpu,
+    breakpoints: Vec<usize>,
+    frame: INT,
+    finished: bool,
+}
+
+/// The `emu` handle scripts are given
+#[derive(Clone)]
+struct Handle(Rc<RefCell<Machine>>);
+
+type ScriptResult<T> = Result<T, Box<EvalAltResult>>;
+
+/// Checks a script's number fits under `limit`, naming what it was for
+fn index(value: INT, limit: usize, what: &str) -> ScriptResult<usize> {
+    if value < 0 || value as usize >= limit {
+        return Err(format!("{} {} is out of range", what, value).into());
+    }
+    Ok(value as usize)
+}
+
+/// Checks a script's number fits in a byte
+fn byte(value: INT) -> ScriptResult<Byte> {
+    Ok(index(value, 0x100, "Byte")? as Byte)
+}
+
+impl Handle {
+    fn reg(&mut self, x: INT) -> ScriptResult<INT> {
+        let x = index(x, 16, "Register")?;
+        Ok(self.0.borrow().cpu.registers(x) as INT)
+    }
+
+    fn set_reg(&mut self, x: INT, value: INT) -> ScriptResult<()> {
+        let x = index(x, 16, "Register")?;
+        self.0.borrow_mut().cpu.write_register(Reg::nibble(x as Byte), byte(value)?);
+        Ok(())
+    }
+
+    fn peek(&mut self, addr: INT) -> ScriptResult<INT> {
+        let addr = index(addr, 0x1000, "Address")?;
+        Ok(self.0.borrow().cpu.memory()[addr] as INT)
+    }
+
+    fn poke(&mut self, addr: INT, value: INT) -> ScriptResult<()> {
+        let addr = index(addr, 0x1000, "Address")?;
+        let value = byte(value)?;
+        self.0.borrow_mut().cpu.poke(addr, &[value]).map_err(|err| err.to_string().into())
+    }
+
+    fn set_key(&mut self, key: INT, pressed: bool) -> ScriptResult<()> {
+        let key = index(key, 16, "Key")?;
+        self.0.borrow_mut().cpu.set_key(key as Byte, pressed);
+        Ok(())
+    }
+
+    fn set_i(&mut self, value: INT) -> ScriptResult<()> {
+        let value = index(value, 0x10000, "I")?;
+        self.0.borrow_mut().cpu.i = value as u16;
+        Ok(())
+    }
+
+    fn break_at(&mut self, addr: INT) -> ScriptResult<()> {
+        let addr = index(addr, 0x1000, "Address")?;
+        let breakpoints = &mut self.0.borrow_mut().breakpoints;
+        if !breakpoints.contains(&addr) {
+            breakpoints.push(addr);
+        }
+        Ok(())
+    }
+}
+
+/// The engine with `emu`'s methods and `assert` registered
+fn engine() -> Engine {
+    let mut engine = Engine::new();
+    engine
+        .register_type_with_name::<Handle>("Emulator")
+        .register_fn("reg", Handle::reg)
+        .register_fn("set_reg", Handle::set_reg)
+        .register_fn("peek", Handle::peek)
+        .register_fn("poke", Handle::poke)
+        .register_fn("press", |emu: &mut Handle, key: INT| emu.set_key(key, true))
+        .register_fn("release", |emu: &mut Handle, key: INT| emu.set_key(key, false))
+        .register_fn("set_i", Handle::set_i)
+        .register_fn("break_at", Handle::break_at)
+        .register_fn("finish", |emu: &mut Handle| emu.0.borrow_mut().finished = true)
+        .register_get("pc", |emu: &mut Handle| emu.0.borrow().cpu.state().pc as INT)
+        .register_get("i", |emu: &mut Handle| emu.0.borrow().cpu.state().i as INT)
+        .register_get("frame", |emu: &mut Handle| emu.0.borrow().frame)
+        .register_fn("assert", |condition: bool, message: &str| -> ScriptResult<()> {
+            if condition {
+                Ok(())
+            } else {
+                Err(format!("Assertion failed: {}", message).into())
+            }
+        });
+    engine
+}
+
+/// A CPU run a frame at a time under the control of a script
+pub struct Script {
+    engine: Engine,
+    ast: AST,
+    handle: Handle,
+    clock: Clock,
+    on_frame: bool,
+    on_breakpoint: bool,
+}
+
+impl Script {
+    /// Compiles `source` and runs its top level, which can already use `emu`
+    pub fn new(cpu: Cpu, source: &str) -> Result<Script, ScriptError> {
+        let engine = engine();
+        let ast = engine.compile(source).map_err(|err| ScriptError::Parse(err.to_string()))?;
+        let defines = |name: &str| ast.iter_functions().any(|function| function.name == name && function.params.len() == 2);
+        let on_frame = defines("on_frame");
+        let on_breakpoint = defines("on_breakpoint");
+
+        let machine = Machine { cpu, breakpoints: Vec::new(), frame: 0, finished: false };
+        let handle = Handle(Rc::new(RefCell::new(machine)));
+        let mut scope = Scope::new();
+        scope.push("emu", handle.clone());
+        engine.run_ast_with_scope(&mut scope, &ast)?;
+
+        Ok(Script { engine, ast, handle, clock: Clock::default(), on_frame, on_breakpoint })
+    }
+
+    /// Calls one of the script's functions with `emu` and `arg`
+    fn call(&mut self, name: &str, arg: INT) -> Result<(), ScriptError> {
+        let options = CallFnOptions::new().eval_ast(false);
+        let args = (self.handle.clone(), arg);
+        let _ = self.engine.call_fn_with_options::<Dynamic>(options, &mut Scope::new(), &self.ast, name, args)?;
+        Ok(())
+    }
+
+    /// Runs one 60th of a second: `on_frame`, a frame's worth of
+    /// instructions stopping for `on_breakpoint` on the way, then a timer tick
+    pub fn run_frame(&mut self) -> Result<StopReason, ScriptError> {
+        if self.on_frame {
+            let frame = self.handle.0.borrow().frame;
+            self.call("on_frame", frame)?;
+        }
+
+        let mut reason = StopReason::BudgetSpent;
+        for _ in 0..self.clock.frame_instructions() {
+            let pc = self.handle.0.borrow().cpu.state().pc as usize;
+            if self.on_breakpoint && self.handle.0.borrow().breakpoints.contains(&pc) {
+                self.call("on_breakpoint", pc as INT)?;
+            }
+
+            let status = self.handle.0.borrow_mut().cpu.step();
+            match status {
+                Ok(CpuStatus::Running) => {}
+                Ok(CpuStatus::WaitingForKey(_)) => {
+                    reason = StopReason::WaitingForKey;
+                    break;
+                }
+                Ok(CpuStatus::WaitingForVBlank) => {
+                    reason = StopReason::WaitingForVBlank;
+                    break;
+                }
+                Ok(CpuStatus::Halted) => return Ok(StopReason::Halted),
+                Err(err) => return Err(ScriptError::Cpu(err)),
+            }
+        }
+
+        let mut machine = self.handle.0.borrow_mut();
+        machine.cpu.tick_timers();
+        machine.frame += 1;
+        Ok(reason)
+    }
+
+    /// Runs up to `frames` frames, stopping early if the script calls
+    /// `emu.finish()` or the program terminates, and returns how many ran
+    pub fn run(&mut self, frames: u64) -> Result<u64, ScriptError> {
+        for frame in 0..frames {
+            let reason = self.run_frame()?;
+            if self.handle.0.borrow().finished || reason == StopReason::Halted {
+                return Ok(frame + 1);
+            }
+        }
+        Ok(frames)
+    }
+
+    /// The CPU, as the script has left it
+    pub fn cpu(&self) -> Ref<'_, Cpu> {
+        Ref::map(self.handle.0.borrow(), |machine| &machine.cpu)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CpuBuilder;
+
+    fn script(rom: &[u8], source: &str) -> Result<Script, ScriptError> {
+        let mut cpu = CpuBuilder::new().build();
+        cpu.load_program(rom).unwrap();
+        Script::new(cpu, source)
+    }
+
+    #[test]
+    fn scripts_press_keys_on_frames() {
+        // LD V1, K; ADD V0, V1; JP 0x200
+        let rom = [0xF1, 0x0A, 0x80, 0x14, 0x12, 0x00];
+        let mut script = script(
+            &rom,
+            r#"
+            fn on_frame(emu, frame) {
+                if frame % 2 == 0 { emu.press(5) } else { emu.release(5) }
+                if emu.frame == 10 { emu.finish() }
+            }
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(script.run(600), Ok(11));
+        assert_eq!(script.cpu().registers(0), 25);
+    }
+
+    #[test]
+    fn breakpoints_call_on_breakpoint_before_the_instruction() {
+        // LD V0, 1; LD V1, V0; JP 0x204
+        let rom = [0x60, 0x01, 0x81, 0x00, 0x12, 0x04];
+        let mut script = script(
+            &rom,
+            r#"
+            emu.break_at(0x202);
+            fn on_breakpoint(emu, addr) {
+                emu.set_reg(0, 7);
+                emu.poke(0x300, addr % 256);
+            }
+            "#,
+        )
+        .unwrap();
+
+        script.run(1).unwrap();
+        assert_eq!(script.cpu().registers(1), 7);
+        assert_eq!(script.cpu().memory()[0x300], 0x02);
+    }
+
+    #[test]
+    fn failures_become_errors() {
+        let rom = [0x12, 0x00];
+        let fail = |source| script(&rom, source).and_then(|mut script| script.run(5));
+
+        assert!(matches!(fail("fn on_frame(emu, frame) {"), Err(ScriptError::Parse(_))));
+        match fail(r#"fn on_frame(emu, frame) { assert(frame < 3, "too slow") }"#) {
+            Err(ScriptError::Runtime(err)) => assert!(err.contains("Assertion failed: too slow")),
+            other => panic!("Expected a failed assert, got {:?}", other),
+        }
+        match fail("emu.poke(0x100, 1)") {
+            Err(ScriptError::Runtime(err)) => assert!(err.contains("belongs to the interpreter")),
+            other => panic!("Expected a refused poke, got {:?}", other),
+        }
+        assert!(matches!(fail("emu.set_reg(16, 1)"), Err(ScriptError::Runtime(_))));
+        assert_eq!(script(&[0x00, 0xEE], "").unwrap().run(5), Err(ScriptError::Cpu(CpuError::StackUnderflow)));
+    }
+}
@@ -0,0 +1,357 @@
+//! A WebSocket server for watching and driving an emulator from elsewhere,
+//! like a browser dashboard for a machine running headless
+//!
+//! Every frame, each connected client is sent the CPU state as a JSON text
+//! message, and the screen as a binary message whenever it has changed:
+//!
+//! - `{"type":"state","frame":120,"paused":false,"status":"running","pc":514,
+//!   "i":0,"sp":0,"registers":[...],"stack":[...],"delay":0,"sound":0,"playing":false}`
+//! - the screen, a bit per pixel, row by row from the top left, with the
+//!   leftmost pixel of each group of 8 in the highest bit: 256 bytes for
+//!   the 64x32 screen, 1024 for SUPER-CHIP's 128x64, or 6144 for
+//!   MegaChip's 256x192, where any colour but 0 is lit
+//!
+//! Clients drive the emulator with JSON text messages:
+//!
+//! - `{"cmd":"load","rom":[96,1,18,2]}` loads and starts a ROM
+//! - `{"cmd":"key","key":5,"pressed":true}` presses or releases a key
+//! - `{"cmd":"pause"}`, `{"cmd":"resume"}` and `{"cmd":"reset"}`
+//! - `{"cmd":"step"}` runs one instruction while paused
+//!
+//! A command that can't be carried out is answered with
+//! `{"type":"error","message":"..."}`.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use chip_8::server::Server;
+//! use chip_8::Emulator;
+//!
+//! let mut emulator = Emulator::default();
+//! emulator.load_rom(include_bytes!("../roms/sierpinski.ch8")).unwrap();
+//! Server::bind("0.0.0.0:8060").unwrap().serve(emulator);
+//! ```
+
+use std::io::{self, ErrorKind};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde_json::{json, Value};
+use tungstenite::{Message, WebSocket};
+
+use crate::timing::TIMER_HZ;
+use crate::{Byte, CpuStatus, Display, Emulator, StopReason};
+
+/// Something a client asked the server to do
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    /// Load and start a ROM
+    Load(Vec<u8>),
+    /// Press or release one of the keys 0 to F
+    Key { key: Byte, pressed: bool },
+    /// Stop running frames
+    Pause,
+    /// Start running frames again
+    Resume,
+    /// Run one instruction, while paused
+    Step,
+    /// Restart the ROM
+    Reset,
+}
+
+impl Command {
+    /// Reads a command from a client's JSON message
+    pub fn parse(text: &str) -> Result<Command, String> {
+        let message: Value = serde_json::from_str(text).map_err(|err| format!("Not JSON: {}", err))?;
+        match message["cmd"].as_str() {
+            Some("load") => {
+                let rom = message["rom"].as_array().ok_or("load needs a rom, as an array of bytes")?;
+                rom.iter()
+                    .map(|byte| byte.as_u64().filter(|&byte| byte <= 0xFF).map(|byte| byte as u8))
+                    .collect::<Option<Vec<u8>>>()
+                    .map(Command::Load)
+                    .ok_or_else(|| String::from("load needs a rom, as an array of bytes"))
+            }
+            Some("key") => match (message["key"].as_u64(), message["pressed"].as_bool()) {
+                (Some(key), Some(pressed)) if key <= 0xF => Ok(Command::Key { key: key as Byte, pressed }),
+                _ => Err(String::from("key needs a key from 0 to 15 and whether it's pressed")),
+            },
+            Some("pause") => Ok(Command::Pause),
+            Some("resume") => Ok(Command::Resume),
+            Some("step") => Ok(Command::Step),
+            Some("reset") => Ok(Command::Reset),
+            Some(cmd) => Err(format!("Unknown command: {}", cmd)),
+            None => Err(String::from("Messages need a cmd")),
+        }
+    }
+}
+
+/// Accepts WebSocket clients and keeps them in step with an emulator
+pub struct Server {
+    listener: TcpListener,
+    clients: Vec<WebSocket<TcpStream>>,
+    paused: bool,
+    frame: u64,
+    // why the last frame or step stopped, for the state messages
+    status: StopReason,
+    // the screen the clients were last sent, so it's only sent on changes
+    sent_screen: Option<Display>,
+}
+
+impl Server {
+    /// Listens for clients on `addr`
+    pub fn bind<A: ToSocketAddrs>(addr: A) -> io::Result<Server> {
+        let listener = TcpListener::bind(addr)?;
+        listener.set_nonblocking(true)?;
+        Ok(Server {
+            listener,
+            clients: Vec::new(),
+            paused: false,
+            frame: 0,
+            status: StopReason::BudgetSpent,
+            sent_screen: None,
+        })
+    }
+
+    /// Where the server is listening, handy after binding port 0
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// Whether a client has paused the emulator
+    pub fn paused(&self) -> bool {
+        self.paused
+    }
+
+    /// How many clients are connected
+    pub fn clients(&self) -> usize {
+        self.clients.len()
+    }
+
+    /// Takes in new clients and carries out the commands waiting from all
+    /// of them, without blocking
+    pub fn poll(&mut self, emulator: &mut Emulator) {
+        while let Ok((stream, _)) = self.listener.accept() {
+            if let Some(client) = handshake(stream) {
+                self.clients.push(client);
+                // the newcomer hasn't seen the screen yet
+                self.sent_screen = None;
+            }
+        }
+
+        let mut ind = 0;
+        while ind < self.clients.len() {
+            match self.clients[ind].read() {
+                Ok(Message::Text(text)) => {
+                    if let Err(message) = Command::parse(&text).and_then(|command| self.apply(command, emulator)) {
+                        let error = json!({ "type": "error", "message": message }).to_string();
+                        let _ = self.clients[ind].send(Message::Text(error));
+                    }
+                }
+                Ok(_) => {}
+                Err(tungstenite::Error::Io(err)) if err.kind() == ErrorKind::WouldBlock => ind += 1,
+                Err(_) => {
+                    self.clients.remove(ind);
+                }
+            }
+        }
+    }
+
+    /// Carries out a command on the emulator
+    fn apply(&mut self, command: Command, emulator: &mut Emulator) -> Result<(), String> {
+        match command {
+            Command::Load(rom) => {
+                emulator.load_rom(&rom).map_err(|err| err.to_string())?;
+                self.frame = 0;
+                self.status = StopReason::BudgetSpent;
+            }
+            Command::Key { key, pressed: true } => emulator.key_down(key),
+            Command::Key { key, pressed: false } => emulator.key_up(key),
+            Command::Pause => self.paused = true,
+            Command::Resume => self.paused = false,
+            Command::Step if !self.paused => return Err(String::from("Pause before stepping")),
+            Command::Step => {
+                self.status = match emulator.cpu_mut().step() {
+                    Ok(CpuStatus::Running) => StopReason::BudgetSpent,
+                    Ok(CpuStatus::WaitingForKey(_)) => StopReason::WaitingForKey,
+                    Ok(CpuStatus::WaitingForVBlank) => StopReason::WaitingForVBlank,
+                    Ok(CpuStatus::Halted) => StopReason::Halted,
+                    Err(err) => StopReason::Error(err),
+                }
+            }
+            Command::Reset => {
+                emulator.reset();
+                self.frame = 0;
+                self.status = StopReason::BudgetSpent;
+            }
+        }
+        Ok(())
+    }
+
+    /// Sends every client the state, and the screen if it has changed,
+    /// dropping any that have gone away
+    pub fn publish(&mut self, emulator: &Emulator) {
+        let mut messages = vec![Message::Text(self.state(emulator).to_string())];
+        if self.sent_screen.as_ref() != Some(emulator.frame()) {
+            messages.push(Message::Binary(pack(emulator.frame())));
+            self.sent_screen = Some(emulator.frame().clone());
+        }
+
+        self.clients.retain_mut(|client| {
+            messages.iter().all(|message| match client.send(message.clone()) {
+                // queued, and sent on a later write once there's room
+                Err(tungstenite::Error::Io(err)) => err.kind() == ErrorKind::WouldBlock,
+                result => result.is_ok(),
+            })
+        });
+    }
+
+    fn state(&self, emulator: &Emulator) -> Value {
+        let state = emulator.cpu().state();
+        let status = match self.status {
+            StopReason::BudgetSpent => String::from("running"),
+            StopReason::WaitingForKey => String::from("waiting for key"),
+            StopReason::WaitingForVBlank => String::from("waiting for vblank"),
+            StopReason::Halted => String::from("halted"),
+            StopReason::Error(err) => err.to_string(),
+        };
+        json!({
+            "type": "state",
+            "frame": self.frame,
+            "paused": self.paused,
+            "status": status,
+            "pc": state.pc,
+            "i": state.i,
+            "sp": state.sp,
+            "registers": state.registers,
+            "stack": state.stack_in_use(),
+            "delay": state.delay,
+            "sound": state.sound,
+            "playing": emulator.audio_state().playing,
+        })
+    }
+
+    /// Handles the clients' commands, runs a frame unless paused, then
+    /// sends the clients what happened
+    pub fn run_frame(&mut self, emulator: &mut Emulator) {
+        self.poll(emulator);
+        if !self.paused {
+            self.status = emulator.run_frame();
+            self.frame += 1;
+        }
+        self.publish(emulator);
+    }
+
+    /// Runs the emulator at 60 frames a second for as long as the process
+    /// lives, serving clients along the way
+    pub fn serve(mut self, mut emulator: Emulator) -> ! {
+        let frame = Duration::from_secs_f64(1.0 / TIMER_HZ);
+        let mut next = Instant::now();
+        loop {
+            self.run_frame(&mut emulator);
+            next += frame;
+            match next.checked_duration_since(Instant::now()) {
+                Some(wait) => thread::sleep(wait),
+                // running behind, so don't try to catch up in a burst
+                None => next = Instant::now(),
+            }
+        }
+    }
+}
+
+/// Upgrades a new connection to a WebSocket, giving up on anything that
+/// doesn't finish the handshake within a second
+fn handshake(stream: TcpStream) -> Option<WebSocket<TcpStream>> {
+    stream.set_nonblocking(false).ok()?;
+    stream.set_read_timeout(Some(Duration::from_secs(1))).ok()?;
+    let client = tungstenite::accept(stream).ok()?;
+    client.get_ref().set_read_timeout(None).ok()?;
+    client.get_ref().set_nonblocking(true).ok()?;
+    Some(client)
+}
+
+/// Packs the screen a bit per pixel, row by row, the leftmost pixel of
+/// each byte in its highest bit
+fn pack(display: &Display) -> Vec<u8> {
+    let mut bytes = vec![0; display.pixels().len() / 8];
+    for (bit, _) in display.pixels().iter().enumerate().filter(|(_, on)| **on) {
+        bytes[bit / 8] |= 0x80 >> (bit % 8);
+    }
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_reads_commands() {
+        assert_eq!(Command::parse(r#"{"cmd":"load","rom":[96,1]}"#), Ok(Command::Load(vec![0x60, 0x01])));
+        assert_eq!(Command::parse(r#"{"cmd":"key","key":15,"pressed":false}"#), Ok(Command::Key { key: 0xF, pressed: false }));
+        assert_eq!(Command::parse(r#"{"cmd":"step"}"#), Ok(Command::Step));
+
+        assert!(Command::parse(r#"{"cmd":"load","rom":[256]}"#).is_err());
+        assert!(Command::parse(r#"{"cmd":"key","key":16,"pressed":true}"#).is_err());
+        assert_eq!(Command::parse(r#"{"cmd":"jump"}"#), Err(String::from("Unknown command: jump")));
+        assert!(Command::parse("pause").is_err());
+    }
+
+    #[test]
+    fn pack_puts_the_top_left_pixel_in_the_highest_bit() {
+        let mut emulator = Emulator::default();
+        // LD I, 0 (the font's 0); DRW V0, V0, 1
+        emulator.load_rom(&[0xA0, 0x00, 0xD0, 0x01]).unwrap();
+        emulator.run_frame();
+
+        let bytes = pack(emulator.frame());
+        assert_eq!(bytes.len(), 256);
+        assert_eq!(bytes[0], 0xF0);
+        assert!(bytes[1..].iter().all(|&byte| byte == 0));
+    }
+
+    #[test]
+    fn clients_drive_the_emulator_and_see_its_state() {
+        let mut server = Server::bind("127.0.0.1:0").unwrap();
+        let addr = server.local_addr().unwrap();
+        let mut emulator = Emulator::default();
+
+        let client = thread::spawn(move || {
+            let (mut socket, _) = tungstenite::connect(format!("ws://{}", addr)).unwrap();
+            socket.send(Message::Text(String::from(r#"{"cmd":"load","rom":[96,7,18,2]}"#))).unwrap();
+            socket.send(Message::Text(String::from(r#"{"cmd":"pause"}"#))).unwrap();
+            socket.send(Message::Text(String::from(r#"{"cmd":"reset"}"#))).unwrap();
+            socket.send(Message::Text(String::from(r#"{"cmd":"step"}"#))).unwrap();
+            socket.send(Message::Text(String::from(r#"{"cmd":"fly"}"#))).unwrap();
+
+            let (mut state, mut error, mut screen) = (None, None, None);
+            while state.is_none() || error.is_none() || screen.is_none() {
+                match socket.read().unwrap() {
+                    Message::Binary(bytes) => screen = Some(bytes.len()),
+                    Message::Text(text) => {
+                        let message: Value = serde_json::from_str(&text).unwrap();
+                        if message["type"] == "error" {
+                            error = Some(message["message"].clone());
+                        } else if message["paused"] == true && message["pc"] == 0x202 {
+                            state = Some(message);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            (state.unwrap(), error.unwrap(), screen.unwrap())
+        });
+
+        while !client.is_finished() {
+            server.run_frame(&mut emulator);
+            thread::sleep(Duration::from_millis(1));
+        }
+        let (state, error, screen) = client.join().unwrap();
+
+        assert_eq!(state["registers"][0], 7);
+        assert_eq!(state["frame"], 0);
+        assert_eq!(error, "Unknown command: fly");
+        assert_eq!(screen, 256);
+        assert!(server.paused());
+    }
+}
@@ -0,0 +1,257 @@
+//! An emulator shared between threads, so a UI can draw while another
+//! thread runs the CPU
+//!
+//! A [`SharedEmulator`] is a cheap handle to one [`Emulator`]. Clone it and
+//! give the clone to a worker thread that runs frames, either by calling
+//! [`run_frame`](SharedEmulator::run_frame) or by letting
+//! [`spawn`](SharedEmulator::spawn) pace them in real time, and read the
+//! screen and registers from the UI thread with
+//! [`view`](SharedEmulator::view).
+//!
+//! The UI never waits on a frame. Each frame's results are copied into a
+//! [`View`] behind a lock of their own, held just long enough to copy them
+//! in or out, and keys are queued to be pressed before the next frame
+//! rather than pressed straight away.
+//!
+//! # Example
+//!
+//! ```
+//! use std::thread;
+//!
+//! use chip_8::shared::SharedEmulator;
+//! use chip_8::Emulator;
+//!
+//! let shared = SharedEmulator::new(Emulator::default());
+//! // LD V0, 0x2A; JP 0x202
+//! shared.with(|emulator| emulator.load_rom(&[0x60, 0x2A, 0x12, 0x02])).unwrap();
+//!
+//! let worker = shared.clone();
+//! thread::spawn(move || worker.run_frame()).join().unwrap();
+//! assert_eq!(shared.view().state.registers[0], 0x2A);
+//! ```
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::emulator::AudioState;
+use crate::{Byte, CpuState, Emulator, FrameBuffer, StopReason};
+
+/// How long a worker from [`SharedEmulator::spawn`] sleeps between looks at
+/// the clock, a quarter of a frame
+const TICK: Duration = Duration::from_micros(4167);
+
+/// What the UI needs after each frame, copied out of the emulator
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct View {
+    pub frame: FrameBuffer,
+    pub state: CpuState,
+    pub audio: AudioState,
+    /// How many frames have run since the handle was made
+    pub frames: u64,
+    /// Why the last frame's instructions stopped, `None` before the first
+    pub stop: Option<StopReason>,
+}
+
+impl View {
+    fn new(emulator: &Emulator) -> View {
+        View {
+            frame: emulator.frame().clone(),
+            state: emulator.cpu().state(),
+            audio: emulator.audio_state(),
+            frames: 0,
+            stop: None,
+        }
+    }
+}
+
+struct Inner {
+    emulator: Mutex<Emulator>,
+    view: RwLock<View>,
+    // key presses and releases waiting for the next frame, oldest first
+    keys: Mutex<Vec<(Byte, bool)>>,
+}
+
+/// A handle to an [`Emulator`] that any number of threads can hold
+#[derive(Clone)]
+pub struct SharedEmulator {
+    inner: Arc<Inner>,
+}
+
+impl SharedEmulator {
+    /// Shares `emulator`, which is the only copy from now on
+    pub fn new(emulator: Emulator) -> SharedEmulator {
+        let view = RwLock::new(View::new(&emulator));
+        SharedEmulator { inner: Arc::new(Inner { emulator: Mutex::new(emulator), view, keys: Mutex::new(Vec::new()) }) }
+    }
+
+    /// Presses the queued keys and runs one frame, then updates the view
+    ///
+    /// A key pressed and let go since the last frame stays held for this
+    /// one, so the program still sees the tap.
+    pub fn run_frame(&self) -> StopReason {
+        let mut emulator = self.inner.emulator.lock().unwrap();
+        {
+            let mut queued = self.inner.keys.lock().unwrap();
+            let (mut pressed, mut deferred) = (0u16, 0u16);
+            let mut later = Vec::new();
+            for (key, down) in queued.drain(..) {
+                let bit = 1 << key;
+                if deferred & bit != 0 || (!down && pressed & bit != 0) {
+                    deferred |= bit;
+                    later.push((key, down));
+                } else if down {
+                    emulator.key_down(key);
+                    pressed |= bit;
+                } else {
+                    emulator.key_up(key);
+                }
+            }
+            *queued = later;
+        }
+
+        let reason = emulator.run_frame();
+        let mut view = self.inner.view.write().unwrap();
+        view.frame.clone_from(emulator.frame());
+        view.state = emulator.cpu().state();
+        view.audio = emulator.audio_state();
+        view.frames += 1;
+        view.stop = Some(reason);
+        reason
+    }
+
+    /// Queues one of the 16 keys, 0 to F, to be pressed before the next frame
+    ///
+    /// # Panics
+    ///
+    /// Panics if `key` is over 0xF
+    pub fn key_down(&self, key: Byte) {
+        assert!(key <= 0xF, "there's no key {:X}", key);
+        self.inner.keys.lock().unwrap().push((key, true));
+    }
+
+    /// Queues one of the 16 keys, 0 to F, to be let go before the next frame
+    ///
+    /// # Panics
+    ///
+    /// Panics if `key` is over 0xF
+    pub fn key_up(&self, key: Byte) {
+        assert!(key <= 0xF, "there's no key {:X}", key);
+        self.inner.keys.lock().unwrap().push((key, false));
+    }
+
+    /// A copy of what the last frame left, without waiting on the CPU
+    pub fn view(&self) -> View {
+        self.inner.view.read().unwrap().clone()
+    }
+
+    /// Runs `f` on the emulator itself, for loading ROMs, debugging and
+    /// anything else the view doesn't cover, then updates the view
+    ///
+    /// Waits for any frame being run to finish first.
+    pub fn with<R, F: FnOnce(&mut Emulator) -> R>(&self, f: F) -> R {
+        let mut emulator = self.inner.emulator.lock().unwrap();
+        let result = f(&mut emulator);
+
+        let mut view = self.inner.view.write().unwrap();
+        let frames = view.frames;
+        *view = View { frames, ..View::new(&emulator) };
+        result
+    }
+
+    /// Starts a thread running frames in real time, at the emulator's
+    /// speed, until the [`Worker`] is stopped or dropped
+    pub fn spawn(&self) -> Worker {
+        let shared = self.clone();
+        let running = Arc::new(AtomicBool::new(true));
+        let still_running = running.clone();
+        let thread = thread::spawn(move || {
+            let mut last = Instant::now();
+            while still_running.load(Ordering::Relaxed) {
+                let now = Instant::now();
+                let due = shared.inner.emulator.lock().unwrap().frames_due((now - last).as_secs_f64());
+                last = now;
+                for _ in 0..due {
+                    shared.run_frame();
+                }
+                thread::sleep(TICK);
+            }
+        });
+        Worker { running, thread: Some(thread) }
+    }
+}
+
+/// A thread started by [`SharedEmulator::spawn`], stopped when dropped
+pub struct Worker {
+    running: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Worker {
+    /// Stops running frames, waiting for the one being run to finish
+    pub fn stop(self) {}
+}
+
+impl Drop for Worker {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            // a panic on the worker has already been reported
+            let _ = thread.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // LD V0, 1; SKNP V0; ADD V1, 1; JP 0x200
+    const ROM: [u8; 8] = [0x60, 0x01, 0xE0, 0xA1, 0x71, 0x01, 0x12, 0x00];
+
+    fn shared() -> SharedEmulator {
+        let shared = SharedEmulator::new(Emulator::default());
+        shared.with(|emulator| emulator.load_rom(&ROM)).unwrap();
+        shared
+    }
+
+    #[test]
+    fn taps_between_frames_are_held_for_one() {
+        let shared = shared();
+        shared.key_down(0x1);
+        shared.key_up(0x1);
+
+        shared.run_frame();
+        assert!(shared.view().state.registers[1] > 0);
+
+        // the tap's frame can end between SKNP and the ADD it let through
+        shared.run_frame();
+        let counted = shared.view().state.registers[1];
+        shared.run_frame();
+        assert_eq!(shared.view().state.registers[1], counted);
+        assert_eq!(shared.view().frames, 3);
+    }
+
+    #[test]
+    fn with_updates_the_view() {
+        let shared = shared();
+        shared.run_frame();
+        shared.with(|emulator| emulator.cpu_mut().write_register(crate::Reg::nibble(0x5), 9));
+        assert_eq!(shared.view().state.registers[5], 9);
+        assert_eq!(shared.view().frames, 1);
+    }
+
+    #[test]
+    fn workers_run_frames_until_stopped() {
+        let shared = shared();
+        let worker = shared.spawn();
+        thread::sleep(Duration::from_millis(100));
+        worker.stop();
+
+        let frames = shared.view().frames;
+        assert!(frames > 0);
+        thread::sleep(Duration::from_millis(50));
+        assert_eq!(shared.view().frames, frames);
+    }
+}
@@ -0,0 +1,678 @@
+//! Save states: everything a running program can change, copied out of the
+//! CPU so it can be put back later
+
+use crate::audio::DEFAULT_PITCH;
+use crate::memory::MEGA_CHIP_SIZE;
+use crate::screen::Resolution;
+use crate::{Address, Byte, Display, Memory, Reg, Registers, RngState, SaveStateError, Stack, Cpu, MAX_STACK_DEPTH};
+
+/// What every save state starts with
+const MAGIC: &[u8; 4] = b"C8SS";
+
+/// The version [`Snapshot::to_bytes`] writes
+const VERSION: u8 = 1;
+
+/// Rewrites one version's sections as the next version's, the first step
+/// being from version 1 to 2
+type Migration = fn(&mut Vec<Section>);
+
+/// Every step from version 1 to [`VERSION`], in order
+const MIGRATIONS: [Migration; VERSION as usize - 1] = [];
+
+const CPU: [u8; 4] = *b"CPU ";
+const STACK: [u8; 4] = *b"STAK";
+const MEMORY: [u8; 4] = *b"MEM ";
+const DISPLAY: [u8; 4] = *b"DISP";
+const HIGH_RES: [u8; 4] = *b"HRES";
+const MEGA: [u8; 4] = *b"MEGA";
+const AUDIO: [u8; 4] = *b"AUDI";
+const RANDOM: [u8; 4] = *b"RAND";
+
+/// How long the `CPU ` section is in version 1, which it can't be shorter than
+const CPU_LEN: usize = 27;
+
+/// How long the `MEGA` section is: the alpha, 256 colours and a byte for
+/// every pixel
+const MEGA_LEN: usize = 1 + 256 * 4 + 256 * 192;
+
+/// How long the `AUDI` section is
+const AUDIO_LEN: usize = 18;
+
+/// How long the `RAND` section is
+const RANDOM_LEN: usize = 48;
+
+/// What FX0A's register is written as when it isn't waiting
+const NOT_WAITING: Byte = 0xFF;
+
+/// One tagged section of a save state
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Section {
+    tag: [u8; 4],
+    data: Vec<u8>,
+}
+
+/// A copy of the CPU's state at one moment
+///
+/// Only covers what the program itself changes, so the quirks, execution
+/// engine and held keys stay as they are when it's restored. That includes
+/// where CXNN's random numbers are up to, so a restored CPU draws the same
+/// ones again.
+///
+/// # Binary layout
+///
+/// [`to_bytes`](Snapshot::to_bytes) writes a layout that later versions of
+/// the crate, and other tools, can read back. Numbers are little endian.
+/// It starts with the magic `C8SS` and a version byte, currently 1,
+/// followed by sections until the end, each a four byte ASCII tag, a `u32`
+/// length and that many bytes:
+///
+/// - `CPU `: the program counter as a `u32`, I as a `u16`, the stack
+///   pointer, V0 to VF, the delay and sound timers, the register FX0A is
+///   waiting to fill or 0xFF if it isn't waiting, and 1 if DXYN is waiting
+///   for the next frame or 0 if it isn't, 27 bytes in all; then MegaChip's
+///   top byte of I and its sprite width and height, 30 bytes in all
+/// - `STAK`: every entry of the stack, in use or not, as `u16`s
+/// - `MEM `: all of memory, however big it is, up to MegaChip's 16MB
+/// - `DISP`: the low resolution screen, a row at a time, eight pixels to a
+///   byte with the leftmost in the top bit, as sprites are drawn; blank
+///   while the screen is in high resolution or MegaChip mode
+/// - `HRES`: only there while the screen is in SUPER-CHIP's high
+///   resolution, the 128x64 screen laid out the same way as `DISP`
+/// - `MEGA`: only there in MegaChip mode, the screen's alpha, the 256
+///   palette colours as ARGB, then the 256x192 screen a row at a time, a
+///   byte of palette colour to a pixel
+/// - `AUDI`: 1 if F002 has loaded an XO-CHIP audio pattern or 0 if it
+///   hasn't, FX3A's pitch, then the 16 bytes of the pattern, 18 bytes in
+///   all; save states without it have no pattern and the default pitch
+/// - `RAND`: the random number generator's 32 byte seed, then how far
+///   along its stream it is as a `u128`, 48 bytes in all; restoring a save
+///   state without it leaves the generator where it is
+///
+/// So that save states survive upgrades in both directions:
+///
+/// - readers skip sections they don't know, so a new section never needs a
+///   new version
+/// - sections only grow: new fields go on the end, readers ignore bytes past
+///   the fields they know, and a field missing from an older, shorter
+///   section takes the value a new CPU would have (stack entries past those
+///   written are 0)
+/// - anything else, changing what an existing field means, bumps the
+///   version and adds a step to [`migrate`](Snapshot::migrate) that
+///   rewrites the previous version's sections as the new version's
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snapshot {
+    program_counter: usize,
+    registers: Registers,
+    memory: Memory,
+    stack: Stack,
+    stack_pointer: usize,
+    i: Address,
+    i_high: Byte,
+    sprite_size: (Byte, Byte),
+    delay_timer: Byte,
+    sound_timer: Byte,
+    audio_pattern: Option<[Byte; 16]>,
+    pitch: Byte,
+    rng: Option<RngState>,
+    display: Display,
+    waiting_for_key: Option<Reg>,
+    waiting_for_vblank: bool,
+}
+
+impl Cpu {
+    /// Copies out the current state
+    ///
+    /// # Examples
+    /// ```
+    /// use chip_8::CpuBuilder;
+    ///
+    /// let mut memory = [0; 4096];
+    /// // LD V0, 7
+    /// memory[0] = 0x60; memory[1] = 0x07;
+    /// let mut cpu = CpuBuilder::new().memory(memory).build();
+    ///
+    /// let before = cpu.snapshot();
+    /// cpu.run();
+    /// cpu.restore(&before);
+    /// assert_eq!(cpu.registers(0), 0);
+    /// ```
+    pub fn snapshot(&self) -> Snapshot {
+        self.snapshot_with(self.memory.clone())
+    }
+
+    /// Copies out the current state with `memory` in place of the CPU's, for
+    /// [`Rewind`](crate::rewind::Rewind), which keeps memory itself
+    pub(crate) fn snapshot_with(&self, memory: Memory) -> Snapshot {
+        Snapshot {
+            program_counter: self.program_counter,
+            registers: self.registers,
+            memory,
+            stack: self.stack,
+            stack_pointer: self.stack_pointer,
+            i: self.i,
+            i_high: self.i_high,
+            sprite_size: self.sprite_size,
+            delay_timer: self.delay_timer,
+            sound_timer: self.sound_timer,
+            audio_pattern: self.audio_pattern,
+            pitch: self.pitch,
+            rng: Some(self.rng_state()),
+            display: self.display.clone(),
+            waiting_for_key: self.waiting_for_key,
+            waiting_for_vblank: self.waiting_for_vblank,
+        }
+    }
+
+    /// Goes back to a state taken by `snapshot`
+    ///
+    /// Memory takes on the size it had in the snapshot, even if that isn't
+    /// the size this CPU was built with; resetting goes back to the built
+    /// size.
+    pub fn restore(&mut self, snapshot: &Snapshot) {
+        self.restore_with(snapshot, &snapshot.memory);
+    }
+
+    /// Goes back to a state taken by `snapshot` with `memory` in place of the
+    /// snapshot's
+    pub(crate) fn restore_with(&mut self, snapshot: &Snapshot, memory: &[Byte]) {
+        self.program_counter = snapshot.program_counter;
+        self.registers = snapshot.registers;
+        if self.memory.len() == memory.len() {
+            self.memory.copy_from_slice(memory);
+        } else {
+            self.memory = memory.into();
+        }
+        self.stack = snapshot.stack;
+        self.stack_pointer = snapshot.stack_pointer;
+        self.i = snapshot.i;
+        self.i_high = snapshot.i_high;
+        self.sprite_size = snapshot.sprite_size;
+        self.delay_timer = snapshot.delay_timer;
+        self.sound_timer = snapshot.sound_timer;
+        self.audio_pattern = snapshot.audio_pattern;
+        self.pitch = snapshot.pitch;
+        if let Some(state) = snapshot.rng {
+            self.set_rng_state(state);
+        }
+        self.display = snapshot.display.clone();
+        self.touch(self.display.rect());
+        self.waiting_for_key = snapshot.waiting_for_key;
+        self.waiting_for_vblank = snapshot.waiting_for_vblank;
+
+        // the restored memory may hold different code
+        self.clear_decode_cache();
+    }
+}
+
+impl Snapshot {
+    /// Writes the state out in the versioned layout described on
+    /// [`Snapshot`]
+    ///
+    /// # Examples
+    /// ```
+    /// use chip_8::{CpuBuilder, Snapshot};
+    ///
+    /// let mut cpu = CpuBuilder::new().build();
+    /// // LD V0, 7
+    /// cpu.load_program(&[0x60, 0x07]).unwrap();
+    /// cpu.run();
+    ///
+    /// let saved = cpu.snapshot().to_bytes();
+    /// let mut restored = CpuBuilder::new().build();
+    /// restored.restore(&Snapshot::from_bytes(&saved).unwrap());
+    /// assert_eq!(restored.registers(0), 7);
+    /// ```
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut cpu = Vec::with_capacity(CPU_LEN);
+        cpu.extend_from_slice(&(self.program_counter as u32).to_le_bytes());
+        cpu.extend_from_slice(&self.i.to_le_bytes());
+        cpu.push(self.stack_pointer as Byte);
+        cpu.extend_from_slice(&self.registers);
+        cpu.push(self.delay_timer);
+        cpu.push(self.sound_timer);
+        cpu.push(self.waiting_for_key.map_or(NOT_WAITING, Reg::get));
+        cpu.push(self.waiting_for_vblank as Byte);
+        cpu.extend_from_slice(&[self.i_high, self.sprite_size.0, self.sprite_size.1]);
+
+        let mut audio = Vec::with_capacity(AUDIO_LEN);
+        audio.push(self.audio_pattern.is_some() as Byte);
+        audio.push(self.pitch);
+        audio.extend_from_slice(&self.audio_pattern.unwrap_or_default());
+
+        let stack = self.stack.iter().flat_map(|addr| addr.to_le_bytes()).collect();
+        // readers from before the other resolutions find a blank screen
+        let low_res = match self.display.resolution() {
+            Resolution::Low => pack(&self.display),
+            Resolution::High | Resolution::Mega => pack(&Display::new()),
+        };
+
+        let mut sections = vec![
+            Section { tag: CPU, data: cpu },
+            Section { tag: STACK, data: stack },
+            Section { tag: MEMORY, data: self.memory.to_vec() },
+            Section { tag: DISPLAY, data: low_res },
+            Section { tag: AUDIO, data: audio },
+        ];
+        match self.display.resolution() {
+            Resolution::Low => {}
+            Resolution::High => sections.push(Section { tag: HIGH_RES, data: pack(&self.display) }),
+            Resolution::Mega => sections.push(Section { tag: MEGA, data: pack_mega(&self.display) }),
+        }
+        if let Some(RngState { seed, position }) = self.rng {
+            let mut random = Vec::with_capacity(RANDOM_LEN);
+            random.extend_from_slice(&seed);
+            random.extend_from_slice(&position.to_le_bytes());
+            sections.push(Section { tag: RANDOM, data: random });
+        }
+        write(&sections)
+    }
+
+    /// Reads a save state written by [`to_bytes`](Snapshot::to_bytes), in
+    /// this version of the layout or any before it
+    pub fn from_bytes(bytes: &[u8]) -> Result<Snapshot, SaveStateError> {
+        let sections = read(bytes)?;
+        let find = |tag: [u8; 4]| {
+            sections
+                .iter()
+                .find(|section| section.tag == tag)
+                .map(|section| section.data.as_slice())
+                .ok_or(SaveStateError::MissingSection(tag))
+        };
+
+        let cpu = find(CPU)?;
+        if cpu.len() < CPU_LEN {
+            return Err(SaveStateError::BadSection(CPU));
+        }
+        let stack_pointer = cpu[6] as usize;
+        let waiting_for_key = match cpu[25] {
+            NOT_WAITING => None,
+            x => Some(Reg::new(x).ok_or(SaveStateError::BadSection(CPU))?),
+        };
+        if stack_pointer > MAX_STACK_DEPTH {
+            return Err(SaveStateError::BadSection(CPU));
+        }
+
+        let mut stack = [0; MAX_STACK_DEPTH];
+        for (entry, bytes) in stack.iter_mut().zip(find(STACK)?.chunks_exact(2)) {
+            *entry = u16::from_le_bytes([bytes[0], bytes[1]]);
+        }
+
+        let memory = find(MEMORY)?;
+        if memory.is_empty() || memory.len() > MEGA_CHIP_SIZE {
+            return Err(SaveStateError::BadSection(MEMORY));
+        }
+
+        let display = match (find(MEGA), find(HIGH_RES)) {
+            (Ok(mega), _) => unpack_mega(mega).ok_or(SaveStateError::BadSection(MEGA))?,
+            (_, Ok(rows)) => unpack(rows, Resolution::High).ok_or(SaveStateError::BadSection(HIGH_RES))?,
+            _ => unpack(find(DISPLAY)?, Resolution::Low).ok_or(SaveStateError::BadSection(DISPLAY))?,
+        };
+
+        let (audio_pattern, pitch) = match find(AUDIO) {
+            Ok(audio) if audio.len() < AUDIO_LEN => return Err(SaveStateError::BadSection(AUDIO)),
+            Ok(audio) => {
+                let mut bits = [0; 16];
+                bits.copy_from_slice(&audio[2..AUDIO_LEN]);
+                (if audio[0] != 0 { Some(bits) } else { None }, audio[1])
+            }
+            Err(_) => (None, DEFAULT_PITCH),
+        };
+
+        let rng = match find(RANDOM) {
+            Ok(random) if random.len() < RANDOM_LEN => return Err(SaveStateError::BadSection(RANDOM)),
+            Ok(random) => {
+                let mut seed = [0; 32];
+                seed.copy_from_slice(&random[..32]);
+                let mut position = [0; 16];
+                position.copy_from_slice(&random[32..RANDOM_LEN]);
+                Some(RngState { seed, position: u128::from_le_bytes(position) })
+            }
+            Err(_) => None,
+        };
+
+        let mut registers: Registers = [0; 16];
+        registers.copy_from_slice(&cpu[7..23]);
+        Ok(Snapshot {
+            program_counter: u32::from_le_bytes([cpu[0], cpu[1], cpu[2], cpu[3]]) as usize,
+            registers,
+            memory: Memory::from(memory),
+            stack,
+            stack_pointer,
+            i: Address::from_le_bytes([cpu[4], cpu[5]]),
+            i_high: cpu.get(27).copied().unwrap_or(0),
+            sprite_size: (cpu.get(28).copied().unwrap_or(0), cpu.get(29).copied().unwrap_or(0)),
+            delay_timer: cpu[23],
+            sound_timer: cpu[24],
+            audio_pattern,
+            pitch,
+            rng,
+            display,
+            waiting_for_key,
+            waiting_for_vblank: cpu[26] != 0,
+        })
+    }
+
+    /// Rewrites a save state from an earlier version of the layout as the
+    /// current version, keeping any sections this version doesn't know
+    ///
+    /// [`from_bytes`](Snapshot::from_bytes) reads older versions itself, so
+    /// this is for bringing files on disk up to date, or for tools that only
+    /// read the current version.
+    pub fn migrate(bytes: &[u8]) -> Result<Vec<u8>, SaveStateError> {
+        Ok(write(&read(bytes)?))
+    }
+}
+
+/// The screen a row at a time, eight pixels to a byte with the leftmost in
+/// the top bit
+fn pack(display: &Display) -> Vec<u8> {
+    display.pixels().chunks(8).map(|pixels| pixels.iter().fold(0, |byte, &lit| byte << 1 | lit as Byte)).collect()
+}
+
+/// A screen of `resolution` read back from what `pack` wrote, `None` if
+/// there are too few bytes for it
+fn unpack(rows: &[u8], resolution: Resolution) -> Option<Display> {
+    let mut display = Display::with_resolution(resolution);
+    let (width, height) = (resolution.width(), resolution.height());
+    if rows.len() < width * height / 8 {
+        return None;
+    }
+    for y in 0..height {
+        for x in 0..width {
+            if rows[(y * width + x) / 8] & 0x80 >> (x % 8) != 0 {
+                display.toggle(x, y);
+            }
+        }
+    }
+    Some(display)
+}
+
+/// MegaChip's screen: its alpha, palette as ARGB and a palette colour for
+/// every pixel
+fn pack_mega(display: &Display) -> Vec<u8> {
+    let mut mega = Vec::with_capacity(MEGA_LEN);
+    mega.push(display.alpha());
+    mega.extend(display.palette().iter().flat_map(|&[red, green, blue, alpha]| [alpha, red, green, blue]));
+    mega.extend_from_slice(display.indices());
+    mega
+}
+
+/// A MegaChip screen read back from what `pack_mega` wrote, `None` if
+/// there are too few bytes for it
+fn unpack_mega(mega: &[u8]) -> Option<Display> {
+    if mega.len() < MEGA_LEN {
+        return None;
+    }
+    let mut display = Display::with_resolution(Resolution::Mega);
+    display.set_alpha(mega[0]);
+    display.load_palette(0, &mega[1..1 + 256 * 4]);
+    for (at, &index) in mega[1 + 256 * 4..MEGA_LEN].iter().enumerate() {
+        display.paint(at % display.width(), at / display.width(), index);
+    }
+    Some(display)
+}
+
+/// The sections of a save state, migrated to the current version
+fn read(bytes: &[u8]) -> Result<Vec<Section>, SaveStateError> {
+    if bytes.len() < MAGIC.len() || &bytes[..MAGIC.len()] != MAGIC {
+        return Err(SaveStateError::NotASaveState);
+    }
+    let version = *bytes.get(MAGIC.len()).ok_or(SaveStateError::Truncated)?;
+    if version == 0 || version > VERSION {
+        return Err(SaveStateError::UnsupportedVersion(version));
+    }
+
+    let mut sections = Vec::new();
+    let mut rest = &bytes[MAGIC.len() + 1..];
+    while !rest.is_empty() {
+        if rest.len() < 8 {
+            return Err(SaveStateError::Truncated);
+        }
+        let tag = [rest[0], rest[1], rest[2], rest[3]];
+        let len = u32::from_le_bytes([rest[4], rest[5], rest[6], rest[7]]) as usize;
+        let data = rest.get(8..8 + len).ok_or(SaveStateError::Truncated)?;
+        sections.push(Section { tag, data: data.to_vec() });
+        rest = &rest[8 + len..];
+    }
+
+    for migration in &MIGRATIONS[version as usize - 1..] {
+        migration(&mut sections);
+    }
+    Ok(sections)
+}
+
+/// A save state of the current version holding `sections`
+fn write(sections: &[Section]) -> Vec<u8> {
+    let mut bytes = MAGIC.to_vec();
+    bytes.push(VERSION);
+    for section in sections {
+        bytes.extend_from_slice(&section.tag);
+        bytes.extend_from_slice(&(section.data.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(&section.data);
+    }
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::XO_CHIP_SIZE;
+    use crate::{Addr, CpuBuilder, ExecutionEngine};
+
+    #[test]
+    fn restore_undoes_memory_writes_for_cached_engine() {
+        let mut cpu = CpuBuilder::new().engine(ExecutionEngine::Cached).build();
+        // LD V0, 5
+        cpu.memory[0x300] = 0x60;
+        cpu.memory[0x301] = 0x05;
+        cpu.program_counter = 0x300;
+        let before = cpu.snapshot();
+
+        // overwrite it with LD V0, 9 and run that, caching it
+        cpu.write_memory(0x301, 0x09);
+        cpu.run();
+        assert_eq!(cpu.registers[0], 9);
+
+        cpu.restore(&before);
+        assert_eq!(cpu.snapshot(), before);
+        cpu.run();
+        assert_eq!(cpu.registers[0], 5);
+    }
+
+    #[test]
+    fn restoring_bigger_memory_survives_reset_and_the_cached_engine() {
+        let mut big = CpuBuilder::new().memory_size(XO_CHIP_SIZE).build();
+        big.memory[0xFFFE] = 0x6A;
+        big.memory[0xFFFF] = 0x07;
+        big.program_counter = 0xFFFE;
+        let bytes = big.snapshot().to_bytes();
+
+        for engine in [ExecutionEngine::Interpreter, ExecutionEngine::Cached] {
+            let mut cpu = CpuBuilder::new().engine(engine).build();
+            cpu.restore(&Snapshot::from_bytes(&bytes).unwrap());
+            assert_eq!(cpu.memory.len(), XO_CHIP_SIZE);
+            cpu.step().unwrap();
+            assert_eq!(cpu.registers[0xA], 7);
+
+            cpu.reset();
+            assert_eq!(cpu.memory.len(), 0x1000);
+            assert!(cpu.load_program(&[0x12, 0x00]).is_ok());
+            cpu.step().unwrap();
+        }
+    }
+
+    #[test]
+    fn snapshots_move_between_cpus_with_different_memory() {
+        let mut big = CpuBuilder::new().memory_size(XO_CHIP_SIZE).engine(ExecutionEngine::Cached).build();
+        let mut small = CpuBuilder::new().engine(ExecutionEngine::Cached).build();
+        let (big_state, small_state) = (big.snapshot(), small.snapshot());
+
+        small.restore(&big_state);
+        big.restore(&small_state);
+        assert_eq!((small.memory.len(), big.memory.len()), (XO_CHIP_SIZE, 0x1000));
+        for cpu in [&mut small, &mut big] {
+            cpu.run_cycles(4);
+            cpu.reset();
+            cpu.run_cycles(4);
+        }
+        assert_eq!((small.memory.len(), big.memory.len()), (0x1000, XO_CHIP_SIZE));
+    }
+
+    #[test]
+    fn memory_past_16mb_is_rejected() {
+        let mut sections = read(&busy_cpu().snapshot().to_bytes()).unwrap();
+        for section in &mut sections {
+            if section.tag == MEMORY {
+                section.data.resize(MEGA_CHIP_SIZE + 1, 0);
+            }
+        }
+        assert_eq!(Snapshot::from_bytes(&write(&sections)), Err(SaveStateError::BadSection(MEMORY)));
+    }
+
+    /// A CPU partway through a program, with something in every field
+    fn busy_cpu() -> Cpu {
+        let mut cpu = CpuBuilder::new().build();
+        // LD I, 0 (the font's 0); DRW V0, V0, 5; LD V3, 0x2A; LD DT, V3; LD V4, K
+        cpu.load_program(&[0xA0, 0x00, 0xD0, 0x05, 0x63, 0x2A, 0xF3, 0x15, 0xF4, 0x0A]).unwrap();
+        cpu.call(Addr::nnn(0x200)).unwrap();
+        for _ in 0..5 {
+            cpu.run();
+        }
+        cpu
+    }
+
+    #[test]
+    fn bytes_round_trip() {
+        let snapshot = busy_cpu().snapshot();
+        assert_eq!(snapshot.waiting_for_key, Reg::new(4));
+        assert!(snapshot.display.pixel(0, 0));
+
+        let bytes = snapshot.to_bytes();
+        assert_eq!(&bytes[..5], b"C8SS\x01");
+        assert_eq!(Snapshot::from_bytes(&bytes), Ok(snapshot));
+        assert_eq!(Snapshot::migrate(&bytes), Ok(bytes));
+    }
+
+    #[test]
+    fn later_additions_are_skipped() {
+        let snapshot = busy_cpu().snapshot();
+        let mut sections = read(&snapshot.to_bytes()).unwrap();
+        // a field on the end of a section, and a whole new section
+        sections[0].data.push(0x99);
+        sections.push(Section { tag: *b"XOPL", data: vec![1, 2, 3] });
+        let bytes = write(&sections);
+
+        assert_eq!(Snapshot::from_bytes(&bytes), Ok(snapshot));
+        assert_eq!(Snapshot::migrate(&bytes), Ok(bytes));
+    }
+
+    #[test]
+    fn high_resolution_screens_round_trip_beside_a_blank_low_one() {
+        let mut cpu = busy_cpu();
+        cpu.set_resolution(Resolution::High);
+        cpu.display.toggle(127, 63);
+        let snapshot = cpu.snapshot();
+        let bytes = snapshot.to_bytes();
+        assert_eq!(Snapshot::from_bytes(&bytes), Ok(snapshot));
+
+        // readers that don't know HRES see a blank screen
+        let mut sections = read(&bytes).unwrap();
+        sections.retain(|section| section.tag != HIGH_RES);
+        assert_eq!(Snapshot::from_bytes(&write(&sections)).unwrap().display, Display::new());
+
+        let mut restored = CpuBuilder::new().build();
+        restored.restore(&Snapshot::from_bytes(&bytes).unwrap());
+        assert_eq!(restored.display.resolution(), Resolution::High);
+        assert!(restored.display.pixel(127, 63));
+        restored.reset();
+        assert_eq!(restored.display, Display::new());
+    }
+
+    #[test]
+    fn megachip_screens_and_registers_round_trip() {
+        let mut cpu = busy_cpu();
+        cpu.set_resolution(Resolution::Mega);
+        cpu.display.load_palette(1, &[0x80, 0x10, 0x20, 0x30]);
+        cpu.display.set_alpha(0x40);
+        cpu.display.paint(255, 191, 1);
+        (cpu.i_high, cpu.sprite_size) = (0x12, (16, 0));
+        let snapshot = cpu.snapshot();
+        let bytes = snapshot.to_bytes();
+        assert_eq!(Snapshot::from_bytes(&bytes), Ok(snapshot));
+
+        let mut restored = CpuBuilder::new().build();
+        restored.restore(&Snapshot::from_bytes(&bytes).unwrap());
+        assert_eq!(restored.display, cpu.display);
+        assert_eq!((restored.i_address(), restored.sprite_size), (cpu.i_address(), (16, 0)));
+
+        // older save states have no top byte of I or sprite size
+        let mut sections = read(&bytes).unwrap();
+        sections.retain(|section| section.tag != MEGA);
+        sections[0].data.truncate(CPU_LEN);
+        let older = Snapshot::from_bytes(&write(&sections)).unwrap();
+        assert_eq!((older.i_high, older.sprite_size, older.display), (0, (0, 0), Display::new()));
+    }
+
+    #[test]
+    fn audio_pattern_round_trips_and_defaults_when_missing() {
+        let mut cpu = busy_cpu();
+        cpu.audio_pattern = Some([0x5A; 16]);
+        cpu.pitch = 100;
+        let snapshot = cpu.snapshot();
+        let bytes = snapshot.to_bytes();
+        assert_eq!(Snapshot::from_bytes(&bytes), Ok(snapshot));
+
+        let mut sections = read(&bytes).unwrap();
+        sections.retain(|section| section.tag != AUDIO);
+        let older = Snapshot::from_bytes(&write(&sections)).unwrap();
+        assert_eq!((older.audio_pattern, older.pitch), (None, DEFAULT_PITCH));
+    }
+
+    #[test]
+    fn restored_states_draw_the_same_random_numbers() {
+        let mut cpu = CpuBuilder::new().build();
+        // RND V0, 0xFF, over and over
+        cpu.load_program(&[0xC0, 0xFF, 0x12, 0x00]).unwrap();
+        cpu.run_cycles(5);
+        let saved = cpu.snapshot().to_bytes();
+        let drawn: Vec<Byte> = (0..10)
+            .map(|_| {
+                cpu.run_cycles(2);
+                cpu.registers[0]
+            })
+            .collect();
+
+        let mut restored = CpuBuilder::new().build();
+        restored.restore(&Snapshot::from_bytes(&saved).unwrap());
+        let redrawn: Vec<Byte> = (0..10)
+            .map(|_| {
+                restored.run_cycles(2);
+                restored.registers[0]
+            })
+            .collect();
+        assert_eq!(redrawn, drawn);
+
+        let mut sections = read(&saved).unwrap();
+        sections.retain(|section| section.tag != RANDOM);
+        assert_eq!(Snapshot::from_bytes(&write(&sections)).unwrap().rng, None);
+        sections.push(Section { tag: RANDOM, data: vec![0; 40] });
+        assert_eq!(Snapshot::from_bytes(&write(&sections)), Err(SaveStateError::BadSection(RANDOM)));
+    }
+
+    #[test]
+    fn bad_save_states_are_refused() {
+        let bytes = busy_cpu().snapshot().to_bytes();
+        assert_eq!(Snapshot::from_bytes(b"PK\x03\x04"), Err(SaveStateError::NotASaveState));
+        assert_eq!(Snapshot::from_bytes(&bytes[..bytes.len() - 1]), Err(SaveStateError::Truncated));
+
+        let mut newer = bytes.clone();
+        newer[4] = VERSION + 1;
+        assert_eq!(Snapshot::from_bytes(&newer), Err(SaveStateError::UnsupportedVersion(VERSION + 1)));
+
+        let mut sections = read(&bytes).unwrap();
+        sections.retain(|section| section.tag != MEMORY);
+        assert_eq!(Snapshot::from_bytes(&write(&sections)), Err(SaveStateError::MissingSection(MEMORY)));
+        sections[0].data[25] = 0x10;
+        assert_eq!(Snapshot::from_bytes(&write(&sections)), Err(SaveStateError::BadSection(CPU)));
+    }
+}
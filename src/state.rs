@@ -0,0 +1,239 @@
+//! A read-only look at the CPU's registers, for register panels, debug
+//! dumps and the like
+
+use std::fmt;
+
+use crate::instruction::Instruction;
+use crate::{Address, Byte, Memory, OpCode, Reg, Registers, Stack, Cpu};
+
+/// The CPU's registers, stack and timers at one moment
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CpuState {
+    /// Program counter
+    pub pc: Address,
+    pub i: Address,
+    /// Stack pointer, how many addresses are on the stack
+    pub sp: usize,
+    /// V0 to VF
+    pub registers: Registers,
+    /// Only the first `sp` entries are in use
+    pub stack: Stack,
+    /// Delay timer
+    pub delay: Byte,
+    /// Sound timer
+    pub sound: Byte,
+}
+
+impl CpuState {
+    /// The addresses currently on the stack, oldest first
+    pub fn stack_in_use(&self) -> &[Address] {
+        &self.stack[..self.sp]
+    }
+}
+
+impl fmt::Display for CpuState {
+    /// Lays the registers out four to a line, under the program counter,
+    /// I, stack pointer and timers, with the stack last
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "PC 0x{:03X}  I 0x{:03X}  SP {}", self.pc, self.i, self.sp)?;
+        writeln!(f, "DT 0x{:02X}   ST 0x{:02X}", self.delay, self.sound)?;
+
+        for (row, values) in self.registers.chunks(4).enumerate() {
+            let registers: Vec<String> = values
+                .iter()
+                .enumerate()
+                .map(|(col, value)| format!("V{:X} {:02X}", row * 4 + col, value))
+                .collect();
+            writeln!(f, "{}", registers.join("  "))?;
+        }
+
+        let stack: Vec<String> = self
+            .stack_in_use()
+            .iter()
+            .map(|addr| format!("0x{:03X}", addr))
+            .collect();
+        write!(f, "Stack: {}", stack.join(" "))
+    }
+}
+
+/// What one instruction changed, as yielded by [`Cpu::instructions`]
+///
+/// Only what differs afterwards is listed, so an instruction that writes a
+/// register with the value it already held doesn't show up as changing it.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct CpuStateDelta {
+    /// Where the program counter went
+    pub pc: Address,
+    /// The registers that changed, with their new values
+    pub registers: Vec<(Reg, Byte)>,
+    pub i: Option<Address>,
+    /// The stack pointer, if a call or return moved it
+    pub sp: Option<usize>,
+    pub delay: Option<Byte>,
+    pub sound: Option<Byte>,
+    /// The memory that changed, by address, with the new values
+    pub memory: Vec<(Address, Byte)>,
+}
+
+impl CpuStateDelta {
+    /// The differences between two moments, `before` and `after`
+    pub(crate) fn between(before: &CpuState, after: &CpuState, old_memory: &Memory, memory: &Memory) -> CpuStateDelta {
+        fn changed<T: PartialEq>(old: T, new: T) -> Option<T> {
+            if old != new {
+                Some(new)
+            } else {
+                None
+            }
+        }
+
+        let registers = (0..16)
+            .filter(|&x| before.registers[x] != after.registers[x])
+            .map(|x| (Reg::nibble(x as Byte), after.registers[x]))
+            .collect();
+        // most instructions write no memory, and comparing is far quicker
+        // than walking it
+        let memory = if old_memory == memory {
+            Vec::new()
+        } else {
+            (0..memory.len())
+                .filter(|&addr| old_memory[addr] != memory[addr])
+                .map(|addr| (addr as Address, memory[addr]))
+                .collect()
+        };
+
+        CpuStateDelta {
+            pc: after.pc,
+            registers,
+            i: changed(before.i, after.i),
+            sp: changed(before.sp, after.sp),
+            delay: changed(before.delay, after.delay),
+            sound: changed(before.sound, after.sound),
+            memory,
+        }
+    }
+}
+
+impl Cpu {
+    /// The registers, stack and timers as they are now
+    ///
+    /// # Examples
+    /// ```
+    /// use chip_8::CpuBuilder;
+    ///
+    /// let mut registers = [0; 16]; registers[0xA] = 0x2F;
+    /// let state = CpuBuilder::new().registers(registers).build().state();
+    ///
+    /// assert_eq!(state.pc, 0x200);
+    /// assert!(state.to_string().contains("VA 2F"));
+    /// ```
+    pub fn state(&self) -> CpuState {
+        CpuState {
+            pc: self.program_counter as Address,
+            i: self.i,
+            sp: self.stack_pointer,
+            registers: self.registers,
+            stack: self.stack,
+            delay: self.delay_timer,
+            sound: self.sound_timer,
+        }
+    }
+
+    /// The opcode at the program counter, if it isn't past the end of memory
+    pub fn next_opcode(&self) -> Option<OpCode> {
+        let pc = self.program_counter;
+        let bytes = self.memory.get(pc..pc + 2)?;
+        Some((bytes[0] as OpCode) << 8 | bytes[1] as OpCode)
+    }
+
+    /// A 64 bit FNV-1a hash of everything a program can see: the registers,
+    /// the stack in use, the timers, memory and the screen
+    ///
+    /// Two CPUs that ran the same ROM the same way hash the same, which
+    /// makes it a cheap check for regressions and desyncs.
+    ///
+    /// # Examples
+    /// ```
+    /// use chip_8::CpuBuilder;
+    ///
+    /// let mut cpu = CpuBuilder::new().build();
+    /// let before = cpu.state_hash();
+    /// // LD V0, 1
+    /// cpu.load_program(&[0x60, 0x01]).unwrap();
+    /// assert_ne!(cpu.state_hash(), before);
+    /// ```
+    pub fn state_hash(&self) -> u64 {
+        let state = self.state();
+        let mut bytes = vec![state.delay, state.sound, state.sp as Byte];
+        bytes.extend_from_slice(&state.pc.to_le_bytes());
+        bytes.extend_from_slice(&state.i.to_le_bytes());
+        bytes.extend_from_slice(&state.registers);
+        for addr in state.stack_in_use() {
+            bytes.extend_from_slice(&addr.to_le_bytes());
+        }
+        let pixels = self.display.pixels().iter().map(|&on| on as Byte);
+
+        bytes
+            .into_iter()
+            .chain(self.memory.iter().copied())
+            .chain(pixels)
+            .chain(self.display.indices().iter().copied())
+            .fold(0xCBF2_9CE4_8422_2325, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01B3))
+    }
+
+    /// Prints the CPU to stderr, for a quick look while chasing down
+    /// why a ROM misbehaves
+    pub fn dump(&self) {
+        eprintln!("{}", self);
+    }
+}
+
+impl fmt::Display for Cpu {
+    /// The registers, stack and timers, then the instruction about to run
+    ///
+    /// # Examples
+    /// ```
+    /// use chip_8::CpuBuilder;
+    ///
+    /// let mut memory = [0; 4096];
+    /// memory[0] = 0x6A; memory[1] = 0x02;
+    /// let cpu = CpuBuilder::new().memory(memory).build();
+    ///
+    /// assert!(cpu.to_string().ends_with("Next: 0x200  6A02  LD VA, 0x02"));
+    /// ```
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{}", self.state())?;
+
+        let pc = self.program_counter;
+        match self.next_opcode() {
+            Some(opcode) => write!(f, "Next: 0x{:03X}  {:04X}  {}", pc, opcode, Instruction::decode(opcode)),
+            None => write!(f, "Next: 0x{:03X}  past the end of memory", pc),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Addr, CpuBuilder};
+
+    #[test]
+    fn display_shows_only_stack_in_use() {
+        let mut cpu = CpuBuilder::new().build();
+        cpu.call(Addr::nnn(0x300)).unwrap();
+        cpu.call(Addr::nnn(0x400)).unwrap();
+        cpu.delay_timer = 0x3C;
+        let text = cpu.state().to_string();
+
+        assert!(text.contains("PC 0x400  I 0x000  SP 2"));
+        assert!(text.contains("DT 0x3C"));
+        assert!(text.ends_with("Stack: 0x200 0x300"));
+        assert_eq!(text.lines().count(), 7);
+    }
+
+    #[test]
+    fn cpu_display_handles_pc_past_memory() {
+        let mut cpu = CpuBuilder::new().build();
+        cpu.program_counter = 0xFFF;
+
+        assert!(cpu.to_string().ends_with("Next: 0xFFF  past the end of memory"));
+    }
+}
@@ -0,0 +1,229 @@
+//! Names for addresses, so debuggers can show labels instead of numbers
+//!
+//! [`asm::assemble_with_symbols`](crate::asm::assemble_with_symbols) gives
+//! back a [`SymbolTable`] of the source's labels, and `chip_8 build` saves
+//! one beside the ROM. A symbol file holds one label a line, its address in
+//! hex and then its name:
+//!
+//! ```text
+//! # draw.sym
+//! 0x200 main
+//! 20A draw_player
+//! ```
+//!
+//! Blank lines and lines starting with `#` are skipped, so the table can
+//! be written out by hand, or from another assembler's listing.
+//!
+//! The assembler also gives back a [`LineTable`], saying which line of the
+//! source each instruction came from, for debugging a line at a time.
+//!
+//! # Example
+//!
+//! ```
+//! use chip_8::symbols::SymbolTable;
+//!
+//! let symbols: SymbolTable = "0x200 main\n20A draw_player".parse().unwrap();
+//! assert_eq!(symbols.addr("draw_player"), Some(0x20A));
+//! assert_eq!(symbols.describe(0x20E), "0x20E <draw_player+4>");
+//! assert_eq!(symbols.describe(0x1FE), "0x1FE");
+//! ```
+
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::Address;
+
+/// Labels by address
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct SymbolTable {
+    names: BTreeMap<Address, String>,
+}
+
+impl SymbolTable {
+    /// Makes a SymbolTable with no labels
+    pub fn new() -> SymbolTable {
+        SymbolTable::default()
+    }
+
+    /// Reads a symbol file
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<SymbolTable, Box<dyn Error>> {
+        Ok(fs::read_to_string(path)?.parse()?)
+    }
+
+    /// Names `addr`, replacing any name it had
+    pub fn insert(&mut self, addr: Address, name: &str) -> &mut SymbolTable {
+        self.names.insert(addr, name.to_string());
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    /// The label at exactly `addr`
+    pub fn name(&self, addr: Address) -> Option<&str> {
+        self.names.get(&addr).map(String::as_str)
+    }
+
+    /// Where the label `name` is
+    pub fn addr(&self, name: &str) -> Option<Address> {
+        self.names.iter().find(|(_, label)| *label == name).map(|(&addr, _)| addr)
+    }
+
+    /// The nearest label at or before `addr`, and how far past it `addr` is
+    ///
+    /// Code runs on from a label until the next one, so this is the routine
+    /// `addr` is most likely in.
+    pub fn locate(&self, addr: Address) -> Option<(&str, Address)> {
+        let (&start, name) = self.names.range(..=addr).next_back()?;
+        Some((name, addr - start))
+    }
+
+    /// `addr` in hex, followed by the label it's in, like `0x20E
+    /// <draw_player+4>`, if there is one
+    pub fn describe(&self, addr: Address) -> String {
+        match self.locate(addr) {
+            Some((name, 0)) => format!("0x{:03X} <{}>", addr, name),
+            Some((name, offset)) => format!("0x{:03X} <{}+{}>", addr, name, offset),
+            None => format!("0x{:03X}", addr),
+        }
+    }
+
+    /// Every label, lowest address first
+    pub fn iter(&self) -> impl Iterator<Item = (Address, &str)> {
+        self.names.iter().map(|(&addr, name)| (addr, name.as_str()))
+    }
+}
+
+impl FromStr for SymbolTable {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<SymbolTable, String> {
+        let mut symbols = SymbolTable::new();
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let mut fields = line.split_whitespace();
+            let (addr, name) = match (fields.next(), fields.next(), fields.next()) {
+                (Some(addr), Some(name), None) => (addr, name),
+                _ => return Err(format!("line {} should be an address and a name: {:?}", index + 1, line)),
+            };
+            let digits = addr.strip_prefix("0x").unwrap_or(addr);
+            match Address::from_str_radix(digits, 16) {
+                Ok(addr) if addr < 0x1000 => symbols.insert(addr, name),
+                _ => return Err(format!("line {}: {} isn't an address", index + 1, addr)),
+            };
+        }
+        Ok(symbols)
+    }
+}
+
+impl fmt::Display for SymbolTable {
+    /// Writes the table as a symbol file
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (addr, name) in self.iter() {
+            writeln!(f, "0x{:03X} {}", addr, name)?;
+        }
+        Ok(())
+    }
+}
+
+/// Which source line each instruction came from
+///
+/// # Examples
+/// ```
+/// use chip_8::asm;
+///
+/// let program = asm::assemble_program(": main\n  v0 := 1\n\n  v1 := 2").unwrap();
+/// assert_eq!(program.lines.line(0x202), Some(4));
+/// // line 3 is blank, so a breakpoint there goes on the next instruction
+/// assert_eq!(program.lines.addr(3), Some(0x202));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct LineTable {
+    lines: BTreeMap<Address, usize>,
+}
+
+impl LineTable {
+    /// Makes a LineTable with no instructions in it
+    pub fn new() -> LineTable {
+        LineTable::default()
+    }
+
+    /// Notes that the instruction at `addr` came from `line`, counting
+    /// from 1
+    pub fn insert(&mut self, addr: Address, line: usize) -> &mut LineTable {
+        self.lines.insert(addr, line);
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty()
+    }
+
+    /// The line the instruction at `addr` came from, if one starts there
+    pub fn line(&self, addr: Address) -> Option<usize> {
+        self.lines.get(&addr).copied()
+    }
+
+    /// Where the first instruction from `line` is, or from the next line
+    /// with any if it has none, the way breakpoints on blank lines and
+    /// comments usually work
+    pub fn addr(&self, line: usize) -> Option<Address> {
+        self.lines
+            .iter()
+            .filter(|(_, &from)| from >= line)
+            .min_by_key(|(&addr, &from)| (from, addr))
+            .map(|(&addr, _)| addr)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn symbol_files_round_trip() {
+        let mut symbols = SymbolTable::new();
+        symbols.insert(0x2A0, "loop").insert(0x200, "main");
+        let text = symbols.to_string();
+
+        assert_eq!(text, "0x200 main\n0x2A0 loop\n");
+        assert_eq!(format!("# labels\n\n{}", text).parse(), Ok(symbols));
+    }
+
+    #[test]
+    fn parse_rejects_bad_lines() {
+        assert!("0x200".parse::<SymbolTable>().is_err());
+        assert!("0x200 main extra".parse::<SymbolTable>().is_err());
+        assert!("0x1000 main".parse::<SymbolTable>().unwrap_err().starts_with("line 1"));
+    }
+
+    #[test]
+    fn locate_finds_the_label_before() {
+        let symbols: SymbolTable = "200 main\n210 draw".parse().unwrap();
+
+        assert_eq!(symbols.locate(0x20E), Some(("main", 0xE)));
+        assert_eq!(symbols.locate(0x210), Some(("draw", 0)));
+        assert_eq!(symbols.locate(0x100), None);
+    }
+
+    #[test]
+    fn line_addresses_prefer_the_earliest_instruction() {
+        let mut lines = LineTable::new();
+        // a loop's `again` jumps back from later in the program
+        lines.insert(0x200, 2).insert(0x202, 5).insert(0x204, 3).insert(0x206, 3);
+
+        assert_eq!(lines.addr(3), Some(0x204));
+        assert_eq!(lines.addr(4), Some(0x202));
+        assert_eq!(lines.addr(6), None);
+        assert_eq!(lines.line(0x203), None);
+    }
+}
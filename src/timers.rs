@@ -0,0 +1,155 @@
+//! The delay and sound timers, which count down at 60Hz whatever speed
+//! the CPU runs at, and the XO-CHIP audio pattern the sound timer plays
+
+use crate::audio::Pattern;
+use crate::{Cpu, CpuError, Reg};
+
+impl Cpu {
+    /// Sets registers[x] to the value of the delay timer
+    pub(crate) fn get_delay(&mut self, x: Reg) {
+        self.write_register(x, self.delay_timer);
+    }
+
+    /// Sets the delay timer to the value in registers[x]
+    pub(crate) fn set_delay(&mut self, x: Reg) {
+        self.delay_timer = self.registers[x];
+    }
+
+    /// Sets the sound timer to the value in registers[x]
+    pub(crate) fn set_sound(&mut self, x: Reg) {
+        self.sound_timer = self.registers[x];
+        trace!("buzzer on for {} ticks", self.sound_timer);
+    }
+
+    /// Loads the 16 byte audio pattern from memory starting at I
+    pub(crate) fn load_audio(&mut self) -> Result<(), CpuError> {
+        let mut bits = [0; 16];
+        for (bit, addr) in bits.iter_mut().zip(self.i_range(16)?) {
+            *bit = self.read_memory(addr);
+        }
+        self.audio_pattern = Some(bits);
+        trace!("audio pattern loaded from 0x{:03X}", self.i);
+        Ok(())
+    }
+
+    /// Sets the audio pattern's pitch to the value in registers[x]
+    pub(crate) fn set_pitch(&mut self, x: Reg) {
+        self.pitch = self.registers[x];
+    }
+
+    /// Counts the delay and sound timers down by one, stopping at zero
+    ///
+    /// Should be called at 60Hz, independently of how fast instructions run
+    pub fn tick_timers(&mut self) {
+        // the tick is the vertical blank the `display_wait` quirk waits for,
+        // and the end of the keypad's frame
+        self.waiting_for_vblank = false;
+        self.keys.end_frame();
+        self.delay_timer = self.delay_timer.saturating_sub(1);
+        self.sound_timer = self.sound_timer.saturating_sub(1);
+        trace!("timers ticked, DT {} ST {}", self.delay_timer, self.sound_timer);
+    }
+
+    /// Whether the buzzer should be sounding, i.e. the sound timer is non-zero
+    ///
+    /// Feed this to a [`Tone`](crate::audio::Tone) after every timer tick
+    pub fn sound_active(&self) -> bool {
+        self.sound_timer > 0
+    }
+
+    /// The XO-CHIP audio pattern the buzzer plays instead of its tone,
+    /// once the program has loaded one with F002
+    ///
+    /// Feed this to [`Tone::update_pattern`](crate::audio::Tone::update_pattern)
+    /// along with `sound_active`.
+    ///
+    /// # Examples
+    /// ```
+    /// use chip_8::CpuBuilder;
+    ///
+    /// // LD I, 0x208; LD V0, 0x70; PITCH V0; AUDIO; then the pattern
+    /// let mut rom = vec![0xA2, 0x08, 0x60, 0x70, 0xF0, 0x3A, 0xF0, 0x02];
+    /// rom.extend([0xAA; 16]);
+    /// let mut cpu = CpuBuilder::new().memory(&rom).build();
+    ///
+    /// assert_eq!(cpu.audio_pattern(), None);
+    /// cpu.run_cycles(4);
+    /// let pattern = cpu.audio_pattern().unwrap();
+    /// assert_eq!((pattern.bits, pattern.pitch), ([0xAA; 16], 0x70));
+    /// ```
+    pub fn audio_pattern(&self) -> Option<Pattern> {
+        self.audio_pattern.map(|bits| Pattern { bits, pitch: self.pitch })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::audio::DEFAULT_PITCH;
+    use crate::{CpuBuilder, CpuError, Reg};
+
+    #[test]
+    fn set_delay_and_get_delay_round_trip() {
+        let mut cpu = CpuBuilder::new().build();
+        cpu.registers[3] = 42;
+        cpu.set_delay(Reg::nibble(3));
+        cpu.get_delay(Reg::nibble(7));
+
+        assert_eq!(cpu.delay_timer, 42);
+        assert_eq!(cpu.registers[7], 42);
+    }
+
+    #[test]
+    fn tick_timers_counts_down_to_zero() {
+        let mut cpu = CpuBuilder::new().build();
+        cpu.delay_timer = 2;
+        cpu.sound_timer = 1;
+        assert!(cpu.sound_active());
+
+        cpu.tick_timers();
+        assert_eq!(cpu.delay_timer, 1);
+        assert_eq!(cpu.sound_timer, 0);
+        assert!(!cpu.sound_active());
+
+        cpu.tick_timers();
+        cpu.tick_timers();
+        assert_eq!(cpu.delay_timer, 0);
+        assert_eq!(cpu.sound_timer, 0);
+    }
+
+    #[test]
+    fn set_sound_sets_sound_timer_from_register() {
+        let mut cpu = CpuBuilder::new().build();
+        cpu.registers[1] = 30;
+        cpu.set_sound(Reg::nibble(1));
+
+        assert_eq!(cpu.sound_timer, 30);
+        assert!(cpu.sound_active());
+    }
+
+    #[test]
+    fn load_audio_reads_the_pattern_from_i() {
+        let mut cpu = CpuBuilder::new().build();
+        cpu.i = 0x300;
+        cpu.memory[0x300..0x310].copy_from_slice(&[0x0F; 16]);
+        cpu.registers[2] = 90;
+        cpu.load_audio().unwrap();
+        cpu.set_pitch(Reg::nibble(2));
+
+        let pattern = cpu.audio_pattern().unwrap();
+        assert_eq!(pattern.bits, [0x0F; 16]);
+        assert_eq!(pattern.pitch, 90);
+        assert_eq!(cpu.i, 0x300);
+
+        cpu.reset();
+        assert_eq!(cpu.audio_pattern(), None);
+        assert_eq!(cpu.pitch, DEFAULT_PITCH);
+    }
+
+    #[test]
+    fn load_audio_fails_past_the_end_of_memory() {
+        let mut cpu = CpuBuilder::new().build();
+        cpu.i = 0xFF8;
+        assert_eq!(cpu.load_audio(), Err(CpuError::MemoryOutOfBounds(0x1007)));
+        assert_eq!(cpu.audio_pattern(), None);
+    }
+}
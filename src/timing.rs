@@ -0,0 +1,559 @@
+//! Turning wall clock time into instructions and timer ticks
+//!
+//! The CHIP-8's timers always count down at 60Hz, whatever speed its
+//! instructions run at, so the two are tracked separately. Both are scaled
+//! by the same speed, so a game at double speed is simply twice as fast.
+//!
+//! How many instructions make a frame is up to the [`TimingModel`]: a flat
+//! rate by default, what each instruction cost on the COSMAC VIP, where a
+//! screen clear took most of a frame and a register load hardly any, or the
+//! HP48's much faster rate for SUPER-CHIP games.
+
+use std::str::FromStr;
+
+use crate::instruction::Instruction;
+use crate::{Cpu, CpuStatus, Reg, StopReason, INSTRUCTIONS_PER_TICK};
+
+/// How often the delay and sound timers count down, in Hz
+pub const TIMER_HZ: f64 = 60.0;
+
+/// The slowest speed [`Clock::set_speed`] allows
+pub const MIN_SPEED: f32 = 0.25;
+
+/// The fastest speed [`Clock::set_speed`] allows
+pub const MAX_SPEED: f32 = 8.0;
+
+/// The most frames [`Clock::frames_due`] hands out at once, so a stall
+/// (a breakpoint, the window being dragged) doesn't come back as a long
+/// burst of catching up
+pub const MAX_CATCH_UP_FRAMES: usize = 5;
+
+/// How many 1802 machine cycles the COSMAC VIP had each 60Hz frame: its
+/// 1.7609MHz clock, 8 clock periods a machine cycle, ignoring the display
+/// interrupt
+pub const VIP_FRAME_CYCLES: u32 = 3668;
+
+/// How many instructions SUPER-CHIP 1.1 runs each 60Hz frame on an HP48
+///
+/// The calculator's interpreter had no fixed rate, running as fast as its
+/// Saturn CPU allowed; 30 a frame, 1800 a second, is what SCHIP games were
+/// tuned against, and what emulators commonly settle on for them.
+pub const HP48_FRAME_INSTRUCTIONS: u32 = 30;
+
+/// What every VIP instruction costs before its own work: fetching it,
+/// decoding it and jumping to its routine
+const VIP_FETCH_CYCLES: u32 = 40;
+
+/// How long instructions take, which decides how many run each frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimingModel {
+    /// Every instruction takes the same time, this many a second
+    Fixed(u32),
+    /// Each instruction takes as many machine cycles as it did in the
+    /// COSMAC VIP's interpreter, out of [`VIP_FRAME_CYCLES`] a frame
+    ///
+    /// Drawing and clearing the screen are slow, and skips cost a little
+    /// more when they skip, so games pace themselves the way they did on
+    /// the real machine.
+    Vip,
+    /// Every instruction takes the same time, [`HP48_FRAME_INSTRUCTIONS`]
+    /// a frame, the much faster rate SUPER-CHIP games expect
+    Hp48,
+}
+
+impl Default for TimingModel {
+    fn default() -> Self {
+        TimingModel::Fixed((INSTRUCTIONS_PER_TICK as f64 * TIMER_HZ) as u32)
+    }
+}
+
+impl FromStr for TimingModel {
+    type Err = String;
+
+    /// Reads `vip`, `hp48` or a number of instructions a second
+    fn from_str(text: &str) -> Result<TimingModel, String> {
+        match text {
+            "vip" => Ok(TimingModel::Vip),
+            "hp48" => Ok(TimingModel::Hp48),
+            _ => match text.parse() {
+                Ok(hz) if hz > 0 => Ok(TimingModel::Fixed(hz)),
+                _ => Err(format!("`{}` isn't `vip`, `hp48` or a number of instructions a second", text)),
+            },
+        }
+    }
+}
+
+impl TimingModel {
+    /// How many cycles a frame has to spend, which for [`Fixed`] and
+    /// [`Hp48`] is instructions, and may be fractional
+    ///
+    /// [`Fixed`]: TimingModel::Fixed
+    /// [`Hp48`]: TimingModel::Hp48
+    pub fn frame_cycles(&self) -> f64 {
+        match self {
+            TimingModel::Fixed(hz) => *hz as f64 / TIMER_HZ,
+            TimingModel::Vip => VIP_FRAME_CYCLES as f64,
+            TimingModel::Hp48 => HP48_FRAME_INSTRUCTIONS as f64,
+        }
+    }
+
+    /// How many cycles the instruction `cpu` is about to run will take
+    ///
+    /// # Examples
+    /// ```
+    /// use chip_8::timing::TimingModel;
+    /// use chip_8::CpuBuilder;
+    ///
+    /// let mut cpu = CpuBuilder::new().build();
+    /// // CLS; LD V0, 1
+    /// cpu.load_program(&[0x00, 0xE0, 0x60, 0x01]).unwrap();
+    ///
+    /// let clear = TimingModel::Vip.cost(&cpu);
+    /// cpu.step().unwrap();
+    /// assert!(clear > 50 * TimingModel::Vip.cost(&cpu));
+    /// assert_eq!(TimingModel::Fixed(600).cost(&cpu), 1);
+    /// ```
+    pub fn cost(&self, cpu: &Cpu) -> u32 {
+        match self {
+            TimingModel::Fixed(_) | TimingModel::Hp48 => 1,
+            TimingModel::Vip => vip_cost(cpu),
+        }
+    }
+}
+
+/// The machine cycles the VIP's interpreter took over the instruction at
+/// the program counter, worked out from its routines and rounded
+fn vip_cost(cpu: &Cpu) -> u32 {
+    let instruction = match cpu.next_opcode() {
+        Some(opcode) => Instruction::decode(opcode),
+        None => return VIP_FETCH_CYCLES,
+    };
+    let v = |reg: Reg| cpu.registers[reg];
+    // taking a skip means moving the program counter on again
+    let skip = |taken: bool| if taken { 14 } else { 10 };
+
+    let work = match instruction {
+        Instruction::Halt | Instruction::Sys(_) | Instruction::Unknown(_) => 0,
+        Instruction::SaveFlags(_) | Instruction::LoadFlags(_) => 0,
+        Instruction::LowRes | Instruction::HighRes => 0,
+        Instruction::MegaOff | Instruction::MegaOn | Instruction::LongI(_) | Instruction::LoadPalette(_) => 0,
+        Instruction::SpriteWidth(_) | Instruction::SpriteHeight(_) | Instruction::Alpha(_) => 0,
+        Instruction::LoadAudio | Instruction::SetPitch(_) => 0,
+        // a loop over all 256 bytes of display memory
+        Instruction::Clear => 3078,
+        Instruction::Return => 10,
+        Instruction::Jump(_) => 12,
+        Instruction::Call(_) => 26,
+        Instruction::SkipEqual(x, nn) => skip(v(x) == nn),
+        Instruction::SkipNotEqual(x, nn) => skip(v(x) != nn),
+        Instruction::SkipEqualReg(x, y) => 4 + skip(v(x) == v(y)),
+        Instruction::SkipNotEqualReg(x, y) => 4 + skip(v(x) != v(y)),
+        Instruction::SetRegister(..) => 6,
+        Instruction::Add(..) => 10,
+        // the 8XYN instructions build and run a little 1802 routine
+        Instruction::Assign(..)
+        | Instruction::Or(..)
+        | Instruction::And(..)
+        | Instruction::Xor(..)
+        | Instruction::AddReg(..)
+        | Instruction::SubReg(..)
+        | Instruction::ShiftRight(..)
+        | Instruction::SubN(..)
+        | Instruction::ShiftLeft(..) => 44,
+        Instruction::SetI(_) => 12,
+        Instruction::JumpReg(_) => 22,
+        Instruction::Rand(..) => 36,
+        // sprites not lined up with a display byte are shifted across two
+        Instruction::Draw(x, _, rows) => {
+            let row = if v(x) % 8 == 0 { 46 } else { 68 };
+            26 + rows as u32 * row
+        }
+        Instruction::SkipKeyPressed(x) => 4 + skip(cpu.keys.is_pressed(v(x) & 0xF)),
+        Instruction::SkipKeyNotPressed(x) => 4 + skip(!cpu.keys.is_pressed(v(x) & 0xF)),
+        Instruction::GetDelay(_) | Instruction::SetDelay(_) | Instruction::SetSound(_) => 10,
+        Instruction::WaitKey(_) => 18,
+        Instruction::AddI(_) | Instruction::SetISprite(_) => 16,
+        // each digit is found by subtracting over and over
+        Instruction::Bcd(x) => {
+            let value = v(x) as u32;
+            80 + 16 * (value / 100 + value / 10 % 10 + value % 10)
+        }
+        Instruction::RegDump(x) | Instruction::RegLoad(x) => 14 + 14 * (x.get() as u32 + 1),
+    };
+    VIP_FETCH_CYCLES + work
+}
+
+/// What's due after some time has passed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Due {
+    pub instructions: usize,
+    pub timer_ticks: usize,
+}
+
+/// Keeps track of how many instructions and timer ticks are owed as time passes
+#[derive(Debug, Clone, PartialEq)]
+pub struct Clock {
+    model: TimingModel,
+    clock_hz: f64,
+    speed: f32,
+    // fractions of an instruction or tick carried between calls to `advance`
+    instructions_owed: f64,
+    ticks_owed: f64,
+    // wall clock time, scaled by the speed, not yet run as whole frames
+    frame_time: f64,
+    // cycles `run_frame` has left over, or has overspent when negative
+    cycles_owed: f64,
+}
+
+impl Default for Clock {
+    fn default() -> Self {
+        Self::with_model(TimingModel::default())
+    }
+}
+
+impl Clock {
+    /// Makes a Clock running `clock_hz` instructions a second at normal speed
+    pub fn new(clock_hz: u32) -> Clock {
+        Clock::with_model(TimingModel::Fixed(clock_hz))
+    }
+
+    /// Makes a Clock timing instructions by `model`
+    ///
+    /// [`advance`](Clock::advance) and
+    /// [`frame_instructions`](Clock::frame_instructions) count every
+    /// instruction the same, so under [`TimingModel::Vip`] they run at the
+    /// default 600 a second; only [`run_frame`](Clock::run_frame) follows
+    /// the VIP's costs.
+    pub fn with_model(model: TimingModel) -> Clock {
+        let clock_hz = match model {
+            TimingModel::Fixed(hz) => hz,
+            TimingModel::Vip => (INSTRUCTIONS_PER_TICK as f64 * TIMER_HZ) as u32,
+            TimingModel::Hp48 => (HP48_FRAME_INSTRUCTIONS as f64 * TIMER_HZ) as u32,
+        };
+        Clock {
+            model,
+            clock_hz: clock_hz as f64,
+            speed: 1.0,
+            instructions_owed: 0.0,
+            ticks_owed: 0.0,
+            frame_time: 0.0,
+            cycles_owed: 0.0,
+        }
+    }
+
+    /// How long the clock takes instructions to be
+    pub fn model(&self) -> TimingModel {
+        self.model
+    }
+
+    /// Sets how much faster than real time to run, between 0.25 and 8
+    ///
+    /// # Examples
+    /// ```
+    /// use chip_8::timing::Clock;
+    ///
+    /// let mut clock = Clock::new(600);
+    /// clock.set_speed(2.0);
+    ///
+    /// let due = clock.advance(1.0);
+    /// assert_eq!(due.instructions, 1200);
+    /// assert_eq!(due.timer_ticks, 120);
+    /// ```
+    pub fn set_speed(&mut self, speed: f32) {
+        self.speed = speed.clamp(MIN_SPEED, MAX_SPEED);
+    }
+
+    /// How much faster than real time the clock is running
+    pub fn speed(&self) -> f32 {
+        self.speed
+    }
+
+    /// Moves the clock on by `seconds` of wall clock time, returning how many
+    /// instructions and timer ticks are now due
+    pub fn advance(&mut self, seconds: f64) -> Due {
+        let seconds = seconds * self.speed as f64;
+        self.instructions_owed += seconds * self.clock_hz;
+        self.ticks_owed += seconds * TIMER_HZ;
+
+        let due = Due {
+            instructions: self.instructions_owed as usize,
+            timer_ticks: self.ticks_owed as usize,
+        };
+        self.instructions_owed -= due.instructions as f64;
+        self.ticks_owed -= due.timer_ticks as f64;
+
+        due
+    }
+
+    /// Moves the clock on by `seconds` of wall clock time, returning how many
+    /// whole 60Hz frames are now due, at most [`MAX_CATCH_UP_FRAMES`]
+    ///
+    /// Each frame is [`frame_instructions`](Clock::frame_instructions)
+    /// instructions and one timer tick, so the game runs at the same speed
+    /// however often this is called. Speeding the clock up makes frames due
+    /// more often.
+    ///
+    /// # Examples
+    /// ```
+    /// use chip_8::timing::Clock;
+    ///
+    /// let mut clock = Clock::new(600);
+    /// assert_eq!(clock.frames_due(0.01), 0);
+    /// assert_eq!(clock.frames_due(0.01), 1);
+    /// assert_eq!(clock.frame_instructions(), 10);
+    /// ```
+    pub fn frames_due(&mut self, seconds: f64) -> usize {
+        self.frame_time += seconds * self.speed as f64;
+        let frames = (self.frame_time * TIMER_HZ) as usize;
+
+        if frames > MAX_CATCH_UP_FRAMES {
+            warn!("{} frames behind, skipping all but {}", frames, MAX_CATCH_UP_FRAMES);
+            self.frame_time = 0.0;
+            return MAX_CATCH_UP_FRAMES;
+        }
+        self.frame_time -= frames as f64 / TIMER_HZ;
+        frames
+    }
+
+    /// How many instructions to run in the next frame
+    ///
+    /// A clock speed that doesn't divide evenly into 60 carries the
+    /// fraction over to later frames.
+    pub fn frame_instructions(&mut self) -> usize {
+        self.instructions_owed += self.clock_hz / TIMER_HZ;
+        let instructions = self.instructions_owed as usize;
+        self.instructions_owed -= instructions as f64;
+        instructions
+    }
+
+    /// Runs one frame's worth of `cpu`'s instructions by the timing model,
+    /// stopping early if the program waits for a key or the display, or
+    /// stops
+    ///
+    /// An instruction that costs more than the frame has left still runs,
+    /// and the overspend comes out of the next frame. Time spent waiting is
+    /// just gone.
+    ///
+    /// # Examples
+    /// ```
+    /// use chip_8::timing::{Clock, TimingModel};
+    /// use chip_8::{CpuBuilder, StopReason};
+    ///
+    /// let mut cpu = CpuBuilder::new().build();
+    /// // CLS; JP 0x200
+    /// cpu.load_program(&[0x00, 0xE0, 0x12, 0x00]).unwrap();
+    ///
+    /// let mut clock = Clock::with_model(TimingModel::Vip);
+    /// assert_eq!(clock.run_frame(&mut cpu), StopReason::BudgetSpent);
+    /// // clearing the screen takes most of a frame on the VIP, so this is
+    /// // CLS, JP and then a CLS overspending into the next frame
+    /// assert_eq!(cpu.cycles(), 3);
+    /// ```
+    pub fn run_frame(&mut self, cpu: &mut Cpu) -> StopReason {
+        #[cfg(feature = "tracing")]
+        let _frame = tracing::trace_span!("frame", model = ?self.model).entered();
+        if let TimingModel::Fixed(_) | TimingModel::Hp48 = self.model {
+            return cpu.run_until_blocked(self.frame_instructions());
+        }
+
+        self.cycles_owed += self.model.frame_cycles();
+        while self.cycles_owed > 0.0 {
+            let cost = self.model.cost(cpu);
+            let reason = match cpu.step() {
+                Ok(CpuStatus::Running) => {
+                    self.cycles_owed -= cost as f64;
+                    continue;
+                }
+                Ok(CpuStatus::WaitingForKey(_)) => StopReason::WaitingForKey,
+                Ok(CpuStatus::WaitingForVBlank) => StopReason::WaitingForVBlank,
+                Ok(CpuStatus::Halted) => StopReason::Halted,
+                Err(err) => StopReason::Error(err),
+            };
+            // the rest of the frame goes on waiting
+            self.cycles_owed = self.cycles_owed.min(0.0);
+            return reason;
+        }
+        StopReason::BudgetSpent
+    }
+
+    /// Runs exactly one 60Hz frame: [`run_frame`](Clock::run_frame) and then
+    /// a timer tick, unless the program stopped or failed
+    ///
+    /// That's everything a frame does to the CPU, so a frontend can call
+    /// this while paused to step the game on a frame at a time, exactly as
+    /// running would have. Wall clock time plays no part, and the timers
+    /// only ever tick here, so they stay put for as long as the game is
+    /// paused.
+    ///
+    /// # Examples
+    /// ```
+    /// use chip_8::timing::Clock;
+    /// use chip_8::{CpuBuilder, StopReason};
+    ///
+    /// let mut cpu = CpuBuilder::new().build();
+    /// // LD V0, 5; LD DT, V0; then loop forever
+    /// cpu.load_program(&[0x60, 0x05, 0xF0, 0x15, 0x12, 0x04]).unwrap();
+    ///
+    /// let mut clock = Clock::new(600);
+    /// assert_eq!(clock.run_one_frame(&mut cpu), StopReason::BudgetSpent);
+    /// assert_eq!((cpu.cycles(), cpu.state().delay), (10, 4));
+    /// ```
+    pub fn run_one_frame(&mut self, cpu: &mut Cpu) -> StopReason {
+        let reason = self.run_frame(cpu);
+        if !matches!(reason, StopReason::Halted | StopReason::Error(_)) {
+            cpu.tick_timers();
+        }
+        reason
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn advance_runs_ten_instructions_a_tick_by_default() {
+        let mut clock = Clock::default();
+
+        assert_eq!(clock.advance(0.5), Due { instructions: 300, timer_ticks: 30 });
+    }
+
+    #[test]
+    fn advance_carries_fractions_over() {
+        let mut clock = Clock::new(600);
+
+        assert_eq!(clock.advance(1.0 / 120.0).timer_ticks, 0);
+        assert_eq!(clock.advance(1.0 / 120.0).timer_ticks, 1);
+    }
+
+    #[test]
+    fn slow_motion_slows_timers_too() {
+        let mut clock = Clock::new(600);
+        clock.set_speed(0.5);
+
+        assert_eq!(clock.advance(1.0), Due { instructions: 300, timer_ticks: 30 });
+    }
+
+    #[test]
+    fn frames_due_carries_time_over() {
+        let mut clock = Clock::new(600);
+
+        assert_eq!(clock.frames_due(1.5 / TIMER_HZ), 1);
+        assert_eq!(clock.frames_due(0.6 / TIMER_HZ), 1);
+        assert_eq!(clock.frames_due(0.0), 0);
+    }
+
+    #[test]
+    fn frames_due_scales_with_speed_and_caps_catch_up() {
+        let mut clock = Clock::new(600);
+        clock.set_speed(2.0);
+        assert_eq!(clock.frames_due(1.0 / TIMER_HZ), 2);
+
+        assert_eq!(clock.frames_due(10.0), MAX_CATCH_UP_FRAMES);
+        assert_eq!(clock.frames_due(0.0), 0);
+    }
+
+    #[test]
+    fn frame_instructions_carries_fractions_over() {
+        let mut clock = Clock::new(1000);
+        let instructions: Vec<usize> = (0..3).map(|_| clock.frame_instructions()).collect();
+
+        assert_eq!(instructions, [16, 17, 17]);
+    }
+
+    #[test]
+    fn run_one_frame_is_the_same_from_the_same_state() {
+        let build = || {
+            let mut cpu = crate::CpuBuilder::new().seed(7).build();
+            // RND V0, 0xFF; LD ST, V0; JP 0x200
+            cpu.load_program(&[0xC0, 0xFF, 0xF0, 0x18, 0x12, 0x00]).unwrap();
+            cpu
+        };
+        let (mut cpu, mut again) = (build(), build());
+        let (mut clock, mut clock_again) = (Clock::new(600), Clock::new(600));
+
+        for _ in 0..3 {
+            clock.run_one_frame(&mut cpu);
+            clock_again.run_one_frame(&mut again);
+        }
+        assert_eq!(cpu.state(), again.state());
+        assert_eq!(cpu.cycles(), 30);
+    }
+
+    #[test]
+    fn set_speed_clamps_to_range() {
+        let mut clock = Clock::new(600);
+
+        clock.set_speed(100.0);
+        assert_eq!(clock.speed(), MAX_SPEED);
+        clock.set_speed(0.0);
+        assert_eq!(clock.speed(), MIN_SPEED);
+    }
+
+    #[test]
+    fn vip_skips_cost_more_when_taken() {
+        let mut cpu = crate::CpuBuilder::new().build();
+        // SE V0, 0; SE V0, 1
+        cpu.load_program(&[0x30, 0x00, 0x30, 0x01]).unwrap();
+        let taken = TimingModel::Vip.cost(&cpu);
+        cpu.program_counter += 2;
+
+        assert_eq!(taken, TimingModel::Vip.cost(&cpu) + 4);
+    }
+
+    #[test]
+    fn vip_draws_cost_by_row_and_alignment() {
+        let mut cpu = crate::CpuBuilder::new().build();
+        // DRW V0, V0, 2
+        cpu.load_program(&[0xD0, 0x02]).unwrap();
+        let aligned = TimingModel::Vip.cost(&cpu);
+        cpu.registers[0] = 3;
+
+        assert_eq!(aligned, VIP_FETCH_CYCLES + 26 + 2 * 46);
+        assert_eq!(TimingModel::Vip.cost(&cpu), VIP_FETCH_CYCLES + 26 + 2 * 68);
+    }
+
+    #[test]
+    fn run_frame_carries_vip_overspend_into_the_next_frame() {
+        let mut cpu = crate::CpuBuilder::new().build();
+        // LD V0, 1; JP 0x200, costing 46 and 52 cycles
+        cpu.load_program(&[0x60, 0x01, 0x12, 0x00]).unwrap();
+        let mut clock = Clock::with_model(TimingModel::Vip);
+
+        let frames: Vec<u64> = (0..3)
+            .map(|_| {
+                let before = cpu.cycles();
+                assert_eq!(clock.run_frame(&mut cpu), StopReason::BudgetSpent);
+                cpu.cycles() - before
+            })
+            .collect();
+        // 3668 cycles is 74.9 instructions, the last of each frame running
+        // on into the next
+        assert_eq!(frames, [75, 75, 75]);
+        assert_eq!(clock.cycles_owed, 3.0 * 3668.0 - 112.0 * 98.0 - 46.0);
+    }
+
+    #[test]
+    fn run_frame_forgets_time_spent_waiting() {
+        let mut cpu = crate::CpuBuilder::new().build();
+        // LD V0, K
+        cpu.load_program(&[0xF0, 0x0A]).unwrap();
+        let mut clock = Clock::with_model(TimingModel::Vip);
+
+        assert_eq!(clock.run_frame(&mut cpu), StopReason::WaitingForKey);
+        assert_eq!(clock.run_frame(&mut cpu), StopReason::WaitingForKey);
+        assert_eq!(clock.cycles_owed, 0.0);
+    }
+
+    #[test]
+    fn hp48_runs_thirty_instructions_a_frame() {
+        let mut cpu = crate::CpuBuilder::new().build();
+        // JP 0x200
+        cpu.load_program(&[0x12, 0x00]).unwrap();
+        let mut clock = Clock::with_model(TimingModel::Hp48);
+
+        assert_eq!(clock.run_frame(&mut cpu), StopReason::BudgetSpent);
+        assert_eq!(cpu.cycles(), HP48_FRAME_INSTRUCTIONS as u64);
+        assert_eq!(clock.advance(1.0).instructions, 1800);
+    }
+}
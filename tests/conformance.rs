@@ -0,0 +1,83 @@
+//! The CPU should agree with the reference interpreter instruction for
+//! instruction on the bundled ROMs and on programs touching every opcode
+
+use chip_8::conformance;
+use chip_8::Quirks;
+
+/// Every combination of the quirks
+fn all_quirks() -> impl Iterator<Item = Quirks> {
+    (0..=511).map(|bits: u16| Quirks {
+        vf_reset: bits & 1 != 0,
+        shift_uses_vy: bits & 2 != 0,
+        memory_increments_i: bits & 4 != 0,
+        memory_increments_i_by_x: bits & 256 != 0,
+        jump_uses_vx: bits & 8 != 0,
+        clip_sprites: bits & 16 != 0,
+        collision_rows: bits & 32 != 0,
+        display_wait: bits & 64 != 0,
+        wait_key_on_press: bits & 128 != 0,
+    })
+}
+
+fn assert_conforms(rom: &[u8], steps: u64) {
+    for quirks in all_quirks() {
+        if let Err(divergence) = conformance::compare(rom, quirks, steps) {
+            panic!("{:?}: {}", quirks, divergence);
+        }
+    }
+}
+
+#[test]
+fn bundled_roms_conform() {
+    assert_conforms(include_bytes!("../roms/sierpinski.ch8"), 50_000);
+}
+
+#[test]
+fn every_opcode_conforms() {
+    #[rustfmt::skip]
+    let rom = [
+        0x00, 0xE0, // 200: CLS
+        0x60, 0x9C, // 202: LD V0, 0x9C
+        0x61, 0x7B, // 204: LD V1, 0x7B
+        0x80, 0x14, // 206: ADD V0, V1
+        0x82, 0x10, // 208: LD V2, V1
+        0x82, 0x05, // 20A: SUB V2, V0
+        0x83, 0x07, // 20C: SUBN V3, V0
+        0x84, 0x16, // 20E: SHR V4, V1
+        0x85, 0x0E, // 210: SHL V5, V0
+        0x86, 0x11, // 212: OR V6, V1
+        0x86, 0x02, // 214: AND V6, V0
+        0x86, 0x13, // 216: XOR V6, V1
+        0x30, 0x17, // 218: SE V0, 0x17
+        0x40, 0x17, // 21A: SNE V0, 0x17
+        0x50, 0x10, // 21C: SE V0, V1
+        0x90, 0x10, // 21E: SNE V0, V1
+        0x22, 0x40, // 220: CALL 0x240
+        0xC0, 0xFF, // 222: RND V0, 0xFF
+        0xA3, 0x00, // 224: LD I, 0x300
+        0xF0, 0x33, // 226: BCD V0
+        0xF5, 0x55, // 228: LD [I], V5
+        0xF3, 0x65, // 22A: LD V3, [I]
+        0xF2, 0x75, // 22C: LD R, V2
+        0xF1, 0x85, // 22E: LD V1, R
+        0xF0, 0x1E, // 230: ADD I, V0
+        0xE0, 0x9E, // 232: SKP V0
+        0xE0, 0xA1, // 234: SKNP V0
+        0x00, 0x00, // 236: skipped
+        0x60, 0x02, // 238: LD V0, 2
+        0xB2, 0x3E, // 23A: JP V0, 0x23E
+        0x00, 0x00, // 23C: skipped unless the jump uses VX
+        0xF0, 0x0A, // 23E: LD V0, K
+        0x60, 0x3C, // 240: LD V0, 60
+        0xF0, 0x15, // 242: LD DT, V0
+        0xF0, 0x18, // 244: LD ST, V0
+        0xF0, 0x29, // 246: LD F, V0
+        0x67, 0x3E, // 248: LD V7, 62
+        0x68, 0x1E, // 24A: LD V8, 30
+        0xD7, 0x85, // 24C: DRW V7, V8, 5
+        0xD7, 0x85, // 24E: DRW V7, V8, 5
+        0xF9, 0x07, // 250: LD V9, DT
+        0x00, 0xEE, // 252: RET
+    ];
+    assert_conforms(&rom, 1000);
+}
@@ -0,0 +1,65 @@
+//! Random programs and opcodes should never be able to crash the emulator,
+//! only make `step` return an error
+
+use chip_8::instruction::Instruction;
+use chip_8::{CpuBuilder, CpuStatus, ExecutionEngine, Quirks};
+use proptest::prelude::*;
+
+/// Builds a CPU with `program` loaded, running whatever it's given
+fn run_program(program: &[u8], engine: ExecutionEngine, quirks: Quirks, steps: usize) {
+    let mut memory = [0; 0x1000];
+    memory[..program.len()].copy_from_slice(program);
+    let mut cpu = CpuBuilder::new().memory(memory).engine(engine).quirks(quirks).build();
+
+    for step in 0..steps {
+        match cpu.step() {
+            Ok(CpuStatus::Running | CpuStatus::WaitingForVBlank) => {}
+            Ok(CpuStatus::WaitingForKey(_)) => cpu.set_key((step % 16) as u8, step % 2 == 0),
+            Ok(CpuStatus::Halted) | Err(_) => break,
+        }
+        cpu.tick_timers();
+    }
+
+    // whatever happened, the CPU can still be looked at
+    let _ = cpu.to_string();
+}
+
+fn quirks() -> impl Strategy<Value = Quirks> {
+    any::<[bool; 9]>().prop_map(|flags| Quirks {
+        vf_reset: flags[0],
+        shift_uses_vy: flags[1],
+        memory_increments_i: flags[2],
+        memory_increments_i_by_x: flags[8],
+        jump_uses_vx: flags[3],
+        clip_sprites: flags[4],
+        collision_rows: flags[5],
+        display_wait: flags[6],
+        wait_key_on_press: flags[7],
+    })
+}
+
+proptest! {
+    #[test]
+    fn random_programs_never_panic(
+        program in proptest::collection::vec(any::<u8>(), 0..0xE00),
+        quirks in quirks(),
+    ) {
+        run_program(&program, ExecutionEngine::Interpreter, quirks, 2000);
+    }
+
+    #[test]
+    fn random_programs_never_panic_cached(
+        program in proptest::collection::vec(any::<u8>(), 0..0xE00),
+        quirks in quirks(),
+    ) {
+        run_program(&program, ExecutionEngine::Cached, quirks, 2000);
+    }
+
+}
+
+#[test]
+fn every_opcode_decodes_and_disassembles() {
+    for opcode in 0..=0xFFFF {
+        let _ = Instruction::decode(opcode).to_string();
+    }
+}
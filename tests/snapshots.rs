@@ -0,0 +1,45 @@
+//! Compares what ROMs draw against PPM snapshots in `tests/snapshots`
+//!
+//! Run with `UPDATE_SNAPSHOTS=1` to rewrite the snapshots after an
+//! intentional change to drawing, then check the new images by eye.
+
+use std::env;
+use std::fs;
+use std::path::Path;
+
+use chip_8::{harness, CpuBuilder};
+
+/// How many frames the screen has to stay the same for, long enough for
+/// the pauses in Sierpinski's drawing
+const SETTLE_FRAMES: usize = 300;
+
+/// How long a ROM gets to settle on a screen, nearly three minutes of frames
+const MAX_FRAMES: usize = 10_000;
+
+/// Runs a ROM until its screen settles and checks it against
+/// `tests/snapshots/<name>.ppm`
+fn assert_snapshot(name: &str, rom: &[u8]) {
+    let mut cpu = CpuBuilder::new().build();
+    cpu.load_program(rom).unwrap();
+    let stable = harness::run_until_stable_for(&mut cpu, SETTLE_FRAMES, MAX_FRAMES)
+        .unwrap_or_else(|err| panic!("{}: {}", name, err));
+
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/snapshots")
+        .join(format!("{}.ppm", name));
+    let actual = stable.display.to_ppm_string();
+
+    if env::var_os("UPDATE_SNAPSHOTS").is_some() {
+        fs::write(&path, &actual).unwrap();
+        return;
+    }
+
+    let expected = fs::read_to_string(&path)
+        .unwrap_or_else(|_| panic!("missing snapshot {:?}, run with UPDATE_SNAPSHOTS=1", path));
+    assert!(expected == actual, "{} drew something different to {:?}", name, path);
+}
+
+#[test]
+fn sierpinski_draws_triangle() {
+    assert_snapshot("sierpinski", include_bytes!("../roms/sierpinski.ch8"));
+}
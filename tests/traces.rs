@@ -0,0 +1,38 @@
+//! Replays ROMs against golden traces in `tests/traces`
+//!
+//! Run with `UPDATE_TRACES=1` to rewrite the traces after an intentional
+//! change to how instructions behave, then check the differences.
+
+use std::env;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+use chip_8::golden;
+use chip_8::CpuBuilder;
+
+/// Runs a ROM for up to `steps` instructions and checks every step against
+/// `tests/traces/<name>.trace`
+fn assert_trace(name: &str, rom: &[u8], steps: u64) {
+    let mut cpu = CpuBuilder::new().build();
+    cpu.load_program(rom).unwrap();
+
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/traces")
+        .join(format!("{}.trace", name));
+
+    if env::var_os("UPDATE_TRACES").is_some() {
+        golden::export_trace(&mut cpu, steps, File::create(&path).unwrap()).unwrap();
+        return;
+    }
+
+    let trace = File::open(&path).unwrap_or_else(|_| panic!("missing trace {:?}, run with UPDATE_TRACES=1", path));
+    if let Err(err) = golden::verify_against_trace(&mut cpu, BufReader::new(trace)) {
+        panic!("{} left {:?}: {}", name, path, err);
+    }
+}
+
+#[test]
+fn sierpinski_follows_its_trace() {
+    assert_trace("sierpinski", include_bytes!("../roms/sierpinski.ch8"), 1000);
+}
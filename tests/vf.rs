@@ -0,0 +1,73 @@
+//! VF semantics for the instructions that set a flag: the result goes to VX
+//! first and the flag to VF last, so with VF as the destination the flag
+//! wins, and operands are read before anything's written, so X == Y works
+
+use chip_8::{conformance, CpuBuilder, Quirks};
+
+/// Runs `LD VX, a; LD VY, b; 8XYn` and returns VX and VF
+fn run(x: u8, y: u8, n: u8, a: u8, b: u8, quirks: Quirks) -> (u8, u8) {
+    let rom = [0x60 | x, a, 0x60 | y, b, 0x80 | x, y << 4 | n, 0x00, 0x00];
+    let mut cpu = CpuBuilder::new().quirks(quirks).build();
+    cpu.load_program(&rom).unwrap();
+    while cpu.run().is_some() {}
+    (cpu.registers(x as usize), cpu.registers(0xF))
+}
+
+#[test]
+fn flag_overwrites_the_result_when_vf_is_the_destination() {
+    let quirks = Quirks::default();
+    // 0xFF + 0x02 carries
+    assert_eq!(run(0xF, 0x1, 0x4, 0xFF, 0x02, quirks), (1, 1));
+    // 0x01 - 0x02 borrows
+    assert_eq!(run(0xF, 0x1, 0x5, 0x01, 0x02, quirks), (0, 0));
+    // 0x02 - 0x01 doesn't
+    assert_eq!(run(0xF, 0x1, 0x7, 0x01, 0x02, quirks), (1, 1));
+    assert_eq!(run(0xF, 0x1, 0x6, 0x03, 0x00, quirks), (1, 1));
+    assert_eq!(run(0xF, 0x1, 0xE, 0x40, 0x00, quirks), (0, 0));
+}
+
+#[test]
+fn flag_reflects_vf_as_the_source() {
+    let quirks = Quirks::default();
+    // VY is VF, so it's read as an operand before being overwritten
+    assert_eq!(run(0x1, 0xF, 0x4, 0x80, 0x80, quirks), (0x00, 1));
+    assert_eq!(run(0x1, 0xF, 0x5, 0x10, 0x20, quirks), (0xF0, 0));
+    assert_eq!(run(0x1, 0xF, 0x7, 0x10, 0x20, quirks), (0x10, 1));
+
+    let quirks = Quirks { shift_uses_vy: true, ..Quirks::default() };
+    assert_eq!(run(0x1, 0xF, 0x6, 0x00, 0x05, quirks), (0x02, 1));
+    assert_eq!(run(0x1, 0xF, 0xE, 0x00, 0x81, quirks), (0x02, 1));
+}
+
+#[test]
+fn same_register_operands_use_the_value_from_before() {
+    let quirks = Quirks::default();
+    // LD V2, b overwrites LD V2, a, so both operands are b
+    assert_eq!(run(0x2, 0x2, 0x4, 0x00, 0x90, quirks), (0x20, 1));
+    assert_eq!(run(0x2, 0x2, 0x5, 0x00, 0x90, quirks), (0x00, 1));
+    assert_eq!(run(0x2, 0x2, 0x7, 0x00, 0x90, quirks), (0x00, 1));
+    assert_eq!(run(0x2, 0x2, 0x6, 0x00, 0x81, quirks), (0x40, 1));
+    assert_eq!(run(0x2, 0x2, 0xE, 0x00, 0x81, quirks), (0x02, 1));
+}
+
+#[test]
+fn every_flag_instruction_matches_the_reference() {
+    let registers = [0x0, 0x1, 0xE, 0xF];
+    let values = [0x00, 0x01, 0x7F, 0x80, 0xFF];
+    for quirks in [Quirks::default(), Quirks { shift_uses_vy: true, vf_reset: true, ..Quirks::default() }] {
+        for &x in &registers {
+            for &y in &registers {
+                for n in [0x1, 0x2, 0x3, 0x4, 0x5, 0x6, 0x7, 0xE] {
+                    for &a in &values {
+                        for &b in &values {
+                            let rom = [0x60 | x, a, 0x60 | y, b, 0x80 | x, y << 4 | n, 0x00, 0x00];
+                            if let Err(divergence) = conformance::compare(&rom, quirks, 10) {
+                                panic!("{:?}: {}", quirks, divergence);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}